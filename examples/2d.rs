@@ -102,6 +102,7 @@ fn setup(
         axis: writer.lit(Vec3::Z).expr(),
         radius: writer.lit(0.05).expr(),
         dimension: ShapeDimension::Surface,
+        thickness: None,
     };
 
     let init_vel = SetVelocityCircleModifier {
@@ -121,11 +122,10 @@ fn setup(
             .init(init_vel)
             .init(init_age)
             .init(init_lifetime)
-            .render(SizeOverLifetimeModifier {
-                gradient: Gradient::constant(Vec2::splat(0.02)),
-                screen_space_size: false,
-            })
-            .render(ColorOverLifetimeModifier { gradient }),
+            .render(SizeOverLifetimeModifier::new(Gradient::constant(
+                Vec2::splat(0.02),
+            )))
+            .render(ColorOverLifetimeModifier::new(gradient)),
     );
 
     // Spawn an instance of the particle effect, and override its Z layer to
@@ -125,6 +125,7 @@ fn setup(
         center: writer.lit(Vec3::ZERO).expr(),
         radius: writer.lit(0.05).expr(),
         dimension: ShapeDimension::Surface,
+        thickness: None,
     };
 
     let init_vel = SetVelocitySphereModifier {
@@ -139,11 +140,10 @@ fn setup(
             .init(init_vel)
             .init(init_age)
             .init(init_lifetime)
-            .render(SizeOverLifetimeModifier {
-                gradient: Gradient::constant(Vec2::splat(0.02)),
-                screen_space_size: false,
-            })
-            .render(ColorOverLifetimeModifier { gradient }),
+            .render(SizeOverLifetimeModifier::new(Gradient::constant(
+                Vec2::splat(0.02),
+            )))
+            .render(ColorOverLifetimeModifier::new(gradient)),
     );
 
     ball.with_children(|node| {
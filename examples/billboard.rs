@@ -115,6 +115,7 @@ fn setup(
         axis: writer.lit(Vec3::Y).expr(),
         radius: writer.lit(1.).expr(),
         dimension: ShapeDimension::Volume,
+        thickness: None,
     };
 
     let init_vel = SetVelocityCircleModifier {
@@ -162,15 +163,15 @@ fn setup(
             .render(ParticleTextureModifier {
                 texture: texture_handle,
                 sample_mapping: ImageSampleMapping::ModulateOpacityFromR,
+                ..default()
             })
             .render(OrientModifier {
                 mode: OrientMode::FaceCameraPosition,
                 rotation: Some(rotation_attr),
             })
-            .render(SizeOverLifetimeModifier {
-                gradient: Gradient::constant([0.2; 2].into()),
-                screen_space_size: false,
-            }),
+            .render(SizeOverLifetimeModifier::new(Gradient::constant(
+                [0.2; 2].into(),
+            ))),
     );
 
     // The ground
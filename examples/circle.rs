@@ -106,6 +106,7 @@ fn setup(
         axis: writer.lit(Vec3::Y).expr(),
         radius: writer.lit(0.4).expr(),
         dimension: ShapeDimension::Surface,
+        thickness: None,
     };
 
     let init_vel = SetVelocityCircleModifier {
@@ -158,13 +159,13 @@ fn setup(
         .render(ParticleTextureModifier {
             texture: texture_handle.clone(),
             sample_mapping: ImageSampleMapping::ModulateOpacityFromR,
+            ..default()
         })
         .render(FlipbookModifier { sprite_grid_size })
-        .render(ColorOverLifetimeModifier { gradient })
-        .render(SizeOverLifetimeModifier {
-            gradient: Gradient::constant([0.5; 2].into()),
-            screen_space_size: false,
-        }),
+        .render(ColorOverLifetimeModifier::new(gradient))
+        .render(SizeOverLifetimeModifier::new(Gradient::constant(
+            [0.5; 2].into(),
+        ))),
     );
 
     // The ground
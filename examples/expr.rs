@@ -95,6 +95,7 @@ fn setup(mut commands: Commands, mut effects: ResMut<Assets<EffectAsset>>) {
         axis: writer.lit(Vec3::Y).expr(),
         radius: writer.lit(4.).expr(),
         dimension: ShapeDimension::Surface,
+        thickness: None,
     };
 
     let init_vel = SetVelocityTangentModifier {
@@ -111,13 +112,8 @@ fn setup(mut commands: Commands, mut effects: ResMut<Assets<EffectAsset>>) {
             .init(init_lifetime)
             .init(init_vel)
             .update(update_accel)
-            .render(ColorOverLifetimeModifier {
-                gradient: color_gradient,
-            })
-            .render(SizeOverLifetimeModifier {
-                gradient: size_gradient,
-                screen_space_size: false,
-            })
+            .render(ColorOverLifetimeModifier::new(color_gradient))
+            .render(SizeOverLifetimeModifier::new(size_gradient))
             .render(OrientModifier::new(OrientMode::AlongVelocity)),
     );
 
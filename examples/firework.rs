@@ -101,6 +101,7 @@ fn setup(mut commands: Commands, mut effects: ResMut<Assets<EffectAsset>>) {
         center: writer.lit(Vec3::ZERO).expr(),
         radius: writer.lit(2.).expr(),
         dimension: ShapeDimension::Volume,
+        thickness: None,
     };
 
     // Give a bit of variation by randomizing the initial speed
@@ -121,13 +122,8 @@ fn setup(mut commands: Commands, mut effects: ResMut<Assets<EffectAsset>>) {
     .init(init_lifetime)
     .update(update_drag)
     .update(update_accel)
-    .render(ColorOverLifetimeModifier {
-        gradient: color_gradient1,
-    })
-    .render(SizeOverLifetimeModifier {
-        gradient: size_gradient1,
-        screen_space_size: false,
-    });
+    .render(ColorOverLifetimeModifier::new(color_gradient1))
+    .render(SizeOverLifetimeModifier::new(size_gradient1));
 
     let effect1 = effects.add(effect);
 
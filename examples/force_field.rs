@@ -297,6 +297,7 @@ fn setup(
         center: writer.lit(Vec3::ZERO).expr(),
         radius: writer.lit(BALL_RADIUS).expr(),
         dimension: ShapeDimension::Surface,
+        thickness: None,
     };
 
     let init_vel = SetVelocitySphereModifier {
@@ -347,11 +348,10 @@ fn setup(
             .update(update_repulsor)
             .update(allow_zone)
             .update(deny_zone)
-            .render(SizeOverLifetimeModifier {
-                gradient: Gradient::constant(Vec2::splat(0.05)),
-                screen_space_size: false,
-            })
-            .render(ColorOverLifetimeModifier { gradient }),
+            .render(SizeOverLifetimeModifier::new(Gradient::constant(
+                Vec2::splat(0.05),
+            )))
+            .render(ColorOverLifetimeModifier::new(gradient)),
     );
 
     commands.spawn((
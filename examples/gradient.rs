@@ -94,6 +94,7 @@ fn setup(
         center: writer.lit(Vec3::ZERO).expr(),
         radius: writer.lit(1.).expr(),
         dimension: ShapeDimension::Volume,
+        thickness: None,
     };
 
     let init_vel = SetVelocitySphereModifier {
@@ -111,8 +112,9 @@ fn setup(
             .render(ParticleTextureModifier {
                 texture: texture_handle.clone(),
                 sample_mapping: ImageSampleMapping::ModulateOpacityFromR,
+                ..default()
             })
-            .render(ColorOverLifetimeModifier { gradient }),
+            .render(ColorOverLifetimeModifier::new(gradient)),
     );
 
     commands
@@ -81,9 +81,7 @@ where
     .with_simulation_space(SimulationSpace::Local)
     .init(init)
     .render(OrientModifier::new(OrientMode::FaceCameraPosition))
-    .render(SetColorModifier {
-        color: COLOR.into(),
-    })
+    .render(SetColorModifier::new(COLOR))
     .render(SetSizeModifier { size: SIZE.into() })
 }
 
@@ -166,6 +164,7 @@ fn setup(
                 axis: writer.lit(Vec3::Z).expr(),
                 radius: writer.lit(5.).expr(),
                 dimension: ShapeDimension::Volume,
+                thickness: None,
             }
         })),
         cube.clone(),
@@ -182,6 +181,7 @@ fn setup(
                 center: writer.lit(Vec3::ZERO).expr(),
                 radius: writer.lit(5.).expr(),
                 dimension: ShapeDimension::Volume,
+                thickness: None,
             }
         })),
         cube.clone(),
@@ -199,6 +199,7 @@ fn setup(
                 base_radius: writer.lit(1.).expr(),
                 top_radius: writer.lit(4.).expr(),
                 dimension: ShapeDimension::Volume,
+                orientation: None,
             }
         })),
         cube.clone(),
@@ -245,6 +245,7 @@ fn setup(
         center: writer.lit(Vec3::ZERO).expr(),
         radius: writer.lit(1.).expr(),
         dimension: ShapeDimension::Volume,
+        thickness: None,
     };
 
     let init_vel = SetVelocitySphereModifier {
@@ -259,7 +260,7 @@ fn setup(
             .init(init_vel)
             .init(init_age)
             .init(init_lifetime)
-            .render(ColorOverLifetimeModifier { gradient }),
+            .render(ColorOverLifetimeModifier::new(gradient)),
     );
 
     // Store the effect for later reference
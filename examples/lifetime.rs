@@ -110,6 +110,7 @@ fn setup(
         center: writer1.lit(Vec3::ZERO).expr(),
         radius: writer1.lit(5.).expr(),
         dimension: ShapeDimension::Volume,
+        thickness: None,
     };
     let init_vel1 = SetVelocitySphereModifier {
         center: writer1.lit(Vec3::ZERO).expr(),
@@ -126,9 +127,7 @@ fn setup(
         .init(init_vel1)
         .init(init_age1)
         .init(init_lifetime1)
-        .render(ColorOverLifetimeModifier {
-            gradient: gradient1,
-        }),
+        .render(ColorOverLifetimeModifier::new(gradient1)),
     );
 
     commands
@@ -165,6 +164,7 @@ fn setup(
         center: writer2.lit(Vec3::ZERO).expr(),
         radius: writer2.lit(5.).expr(),
         dimension: ShapeDimension::Volume,
+        thickness: None,
     };
     let init_vel2 = SetVelocitySphereModifier {
         center: writer2.lit(Vec3::ZERO).expr(),
@@ -181,9 +181,7 @@ fn setup(
         .init(init_vel2)
         .init(init_age2)
         .init(init_lifetime2)
-        .render(ColorOverLifetimeModifier {
-            gradient: gradient2,
-        }),
+        .render(ColorOverLifetimeModifier::new(gradient2)),
     );
 
     commands
@@ -220,6 +218,7 @@ fn setup(
         center: writer3.lit(Vec3::ZERO).expr(),
         radius: writer3.lit(5.).expr(),
         dimension: ShapeDimension::Volume,
+        thickness: None,
     };
     let init_vel3 = SetVelocitySphereModifier {
         center: writer3.lit(Vec3::ZERO).expr(),
@@ -236,9 +235,7 @@ fn setup(
         .init(init_vel3)
         .init(init_age3)
         .init(init_lifetime3)
-        .render(ColorOverLifetimeModifier {
-            gradient: gradient3,
-        }),
+        .render(ColorOverLifetimeModifier::new(gradient3)),
     );
 
     commands
@@ -76,6 +76,7 @@ fn make_effect(color: Color) -> EffectAsset {
         center: writer.lit(Vec3::ZERO).expr(),
         radius: writer.lit(2.).expr(),
         dimension: ShapeDimension::Surface,
+        thickness: None,
     };
 
     let init_vel = SetVelocitySphereModifier {
@@ -90,13 +91,8 @@ fn make_effect(color: Color) -> EffectAsset {
         .init(init_age)
         .init(init_lifetime)
         .update(update_accel)
-        .render(ColorOverLifetimeModifier {
-            gradient: color_gradient,
-        })
-        .render(SizeOverLifetimeModifier {
-            gradient: size_gradient.clone(),
-            screen_space_size: false,
-        })
+        .render(ColorOverLifetimeModifier::new(color_gradient))
+        .render(SizeOverLifetimeModifier::new(size_gradient.clone()))
         .render(OrientModifier::new(OrientMode::FaceCameraPosition))
 }
 
@@ -81,6 +81,7 @@ fn setup(mut commands: Commands, mut effects: ResMut<Assets<EffectAsset>>) {
         axis: writer.lit(Vec3::Z).expr(),
         radius: writer.lit(4.).expr(),
         dimension: ShapeDimension::Surface,
+        thickness: None,
     };
 
     let age = writer.lit(0.).expr();
@@ -106,13 +107,8 @@ fn setup(mut commands: Commands, mut effects: ResMut<Assets<EffectAsset>>) {
             .init(init_lifetime)
             .update(update_drag)
             .update(tangent_accel)
-            .render(ColorOverLifetimeModifier {
-                gradient: color_gradient1,
-            })
-            .render(SizeOverLifetimeModifier {
-                gradient: size_gradient1,
-                screen_space_size: false,
-            })
+            .render(ColorOverLifetimeModifier::new(color_gradient1))
+            .render(SizeOverLifetimeModifier::new(size_gradient1))
             .render(OrientModifier::new(OrientMode::AlongVelocity)),
     );
 
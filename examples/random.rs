@@ -98,6 +98,7 @@ fn setup(
         center: writer.lit(Vec3::ZERO).expr(),
         radius: writer.lit(5.).expr(),
         dimension: ShapeDimension::Volume,
+        thickness: None,
     };
 
     let init_vel = SetVelocitySphereModifier {
@@ -117,7 +118,7 @@ fn setup(
         .init(init_age)
         .init(init_lifetime)
         .update(update_accel)
-        .render(ColorOverLifetimeModifier { gradient }),
+        .render(ColorOverLifetimeModifier::new(gradient)),
     );
 
     commands
@@ -127,6 +127,7 @@ fn setup(
         top_radius: writer1.lit(10.).expr(),
         height: writer1.lit(20.).expr(),
         dimension: ShapeDimension::Volume,
+        orientation: None,
     };
 
     let init_vel1 = SetVelocitySphereModifier {
@@ -144,13 +145,8 @@ fn setup(
             .init(init_age1)
             .init(init_lifetime1)
             .update(update_accel1)
-            .render(ColorOverLifetimeModifier {
-                gradient: color_gradient1,
-            })
-            .render(SizeOverLifetimeModifier {
-                gradient: size_gradient1,
-                screen_space_size: false,
-            }),
+            .render(ColorOverLifetimeModifier::new(color_gradient1))
+            .render(SizeOverLifetimeModifier::new(size_gradient1)),
     );
 
     commands
@@ -192,6 +188,7 @@ fn setup(
         center: writer2.lit(Vec3::ZERO).expr(),
         radius: writer2.lit(5.).expr(),
         dimension: ShapeDimension::Volume,
+        thickness: None,
     };
     let init_vel2 = SetVelocitySphereModifier {
         center: writer2.lit(Vec3::ZERO).expr(),
@@ -208,9 +205,7 @@ fn setup(
         .init(init_vel2)
         .init(init_age2)
         .init(init_lifetime2)
-        .render(ColorOverLifetimeModifier {
-            gradient: gradient2,
-        }),
+        .render(ColorOverLifetimeModifier::new(gradient2)),
     );
 
     commands
@@ -261,6 +256,7 @@ fn setup(
         center: writer3.lit(Vec3::ZERO).expr(),
         radius: writer3.lit(5.).expr(),
         dimension: ShapeDimension::Volume,
+        thickness: None,
     };
 
     let init_vel3 = SetVelocitySphereModifier {
@@ -282,9 +278,7 @@ fn setup(
         .init(init_lifetime3)
         .init(init_size3)
         .update(update_accel3)
-        .render(ColorOverLifetimeModifier {
-            gradient: gradient3,
-        }),
+        .render(ColorOverLifetimeModifier::new(gradient3)),
     );
 
     commands
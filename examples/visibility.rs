@@ -116,6 +116,7 @@ fn setup(
         center: writer.lit(Vec3::ZERO).expr(),
         radius: writer.lit(5.).expr(),
         dimension: ShapeDimension::Volume,
+        thickness: None,
     };
 
     let mut asset = EffectAsset::new(
@@ -129,7 +130,7 @@ fn setup(
     .init(init_age)
     .init(init_lifetime)
     //.update(AccelModifier::constant(Vec3::new(0., 2., 0.)))
-    .render(ColorOverLifetimeModifier { gradient });
+    .render(ColorOverLifetimeModifier::new(gradient));
     let effect1 = effects.add(asset.clone());
 
     // Reference cube to visualize the emit origin
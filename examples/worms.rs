@@ -97,8 +97,6 @@ fn setup(
 
     // Update modifiers
 
-    let clone_modifier = CloneModifier::new(1.0 / 8.0, 1);
-
     // Make the particle wiggle, following a sine wave.
     let main_set_velocity_modifier = SetAttributeModifier::new(
         Attribute::VELOCITY,
@@ -114,9 +112,6 @@ fn setup(
     let trail_set_velocity_modifier =
         SetAttributeModifier::new(Attribute::VELOCITY, writer.lit(Vec3::ZERO).expr());
 
-    let trail_set_lifetime_modifier =
-        SetAttributeModifier::new(Attribute::LIFETIME, writer.lit(1.0).expr());
-
     // Render modifiers
 
     // Set the particle size.
@@ -131,31 +126,27 @@ fn setup(
     let particle_texture_modifier = ParticleTextureModifier {
         texture: circle,
         sample_mapping: ImageSampleMapping::Modulate,
+        ..default()
     };
 
     let module = writer.finish();
 
-    // Allocate room for 32,768 trail particles. Give each particle a 5-particle
-    // trail, and spawn a new trail particle every ⅛ of a second.
+    // Allocate room for 5,000 worms. Spawn a new trail particle every ⅛ of a
+    // second, which lives for 1 second, giving each worm an 8-particle trail.
     let effect = effects.add(
-        EffectAsset::new(
-            vec![5000, 5000 * 5],
-            Spawner::rate(CpuValue::Single(5.0)),
-            module,
-        )
-        .with_name("worms")
-        .init(set_initial_position_modifier)
-        .init(set_initial_angle_modifier)
-        .init(set_age_modifier)
-        .init(set_lifetime_modifier)
-        .init(set_color_modifier)
-        .update_groups(clone_modifier, ParticleGroupSet::single(0))
-        .update_groups(main_set_velocity_modifier, ParticleGroupSet::single(0))
-        .update_groups(trail_set_velocity_modifier, ParticleGroupSet::single(1))
-        .update_groups(trail_set_lifetime_modifier, ParticleGroupSet::single(1))
-        .render_groups(main_set_size_modifier, ParticleGroupSet::single(0))
-        .render_groups(trail_set_size_modifier, ParticleGroupSet::single(1))
-        .render(particle_texture_modifier),
+        EffectAsset::new(vec![5000], Spawner::rate(CpuValue::Single(5.0)), module)
+            .with_name("worms")
+            .with_trail(1.0, 1.0 / 8.0)
+            .init(set_initial_position_modifier)
+            .init(set_initial_angle_modifier)
+            .init(set_age_modifier)
+            .init(set_lifetime_modifier)
+            .init(set_color_modifier)
+            .update_groups(main_set_velocity_modifier, ParticleGroupSet::single(0))
+            .update_groups(trail_set_velocity_modifier, ParticleGroupSet::single(1))
+            .render_groups(main_set_size_modifier, ParticleGroupSet::single(0))
+            .render_groups(trail_set_size_modifier, ParticleGroupSet::single(1))
+            .render(particle_texture_modifier),
     );
 
     commands.spawn((
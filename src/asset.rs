@@ -1,19 +1,56 @@
 use bevy::{
-    asset::{io::Reader, Asset, AssetLoader, AsyncReadExt, LoadContext},
+    asset::{
+        io::Reader, Asset, AssetLoader, AsyncReadExt, Handle, LoadContext, UntypedAssetId,
+        VisitAssetDependencies,
+    },
+    log::warn,
+    math::{Rect, Vec2, Vec3, Vec4},
     reflect::Reflect,
-    utils::{default, thiserror::Error, BoxedFuture, HashSet},
+    render::{
+        primitives::Aabb,
+        render_resource::{BlendComponent, BlendFactor, BlendOperation, BlendState},
+        texture::Image,
+    },
+    utils::{default, thiserror::Error, BoxedFuture},
 };
 use serde::{Deserialize, Serialize};
 use std::ops::Deref;
 
 use crate::{
-    graph::Value,
-    modifier::{Modifier, RenderModifier},
-    ExprHandle, GroupedModifier, ModifierContext, Module, ParticleGroupSet, ParticleLayout,
-    Property, PropertyLayout, SimulationSpace, Spawner,
+    graph::{expr::offset_expr_handles, Value},
+    modifier::{
+        AccelModifier, CloneModifier, CollisionHeightfieldModifier, ConditionalModifier,
+        CustomCodeModifier, DissolveModifier, KillAabbModifier, KillSphereModifier,
+        LinearDragModifier, Modifier, ParticleTextureModifier, RenderModifier,
+        SetAttributeModifier, SetPositionBoxModifier, SetPositionCircleModifier,
+        SetPositionCone3dModifier, SetPositionPathModifier, SetPositionSphereModifier,
+        SetSizeModifier, SetVelocityCircleModifier, SetVelocitySphereModifier,
+        SetVelocityTangentModifier, SizeOverDistanceModifier, SizeOverLifetimeModifier,
+    },
+    Attribute, BoxedModifier, CpuValue, ExprError, ExprHandle, GroupedModifier, ModifierContext,
+    Module, ParticleGroupSet, ParticleLayout, Property, PropertyLayout, SimulationSpace, Spawner,
 };
 
 /// Type of motion integration applied to the particles of a system.
+///
+/// Motion integration is a simple Euler integration of the particle velocity
+/// into its position (`position += velocity * delta_time`), inserted in the
+/// update shader relative to the code generated by all update modifiers.
+/// The choice between [`PreUpdate`] and [`PostUpdate`] matters for modifiers
+/// which read or constrain the particle position, like collision or clamping
+/// modifiers:
+/// - With [`PreUpdate`], update modifiers see the integrated position of the
+///   current frame, and any position they write is the final position of the
+///   particle for this frame. This allows modifiers to override the result of
+///   the integration, for example to project the particle back onto a
+///   collider surface.
+/// - With [`PostUpdate`], update modifiers see the position of the previous
+///   frame, and any change they make to the velocity is integrated in the
+///   same frame. However any position they write is subsequently moved by the
+///   integration.
+///
+/// [`PreUpdate`]: MotionIntegration::PreUpdate
+/// [`PostUpdate`]: MotionIntegration::PostUpdate
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Reflect, Serialize, Deserialize)]
 pub enum MotionIntegration {
     /// No motion integration. The [`Attribute::POSITION`] of the particles
@@ -38,6 +75,75 @@ pub enum MotionIntegration {
     PostUpdate,
 }
 
+/// Order in which the particles of a group are drawn.
+///
+/// Within a group, particles are drawn in the order they're stored in the list
+/// of alive particles. This order affects the look of dense effects, in
+/// particular with additive or layered blending, where the last particles
+/// drawn appear on top of the others.
+///
+/// This is not a depth sort. The particles are ordered by the frame they were
+/// spawned on, not by their distance to the camera, so this is very cheap but
+/// doesn't make transparent particles blend correctly with each other when
+/// the camera moves around the effect. Particles spawned during the same frame
+/// have no particular order relative to each other, and the order is only
+/// approximate, because the GPU doesn't strictly preserve it when updating
+/// the particles in parallel.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Reflect, Serialize, Deserialize)]
+pub enum ParticleDrawOrder {
+    /// Draw the oldest particles first, so newer particles appear on top of
+    /// older ones. This is the default.
+    #[default]
+    OldestFirst,
+
+    /// Draw the newest particles first, so older particles appear on top of
+    /// newer ones.
+    ///
+    /// This has a small additional cost in the update pass, which rotates the
+    /// list of alive particles each frame to bring the newly spawned particles
+    /// to its front.
+    NewestFirst,
+}
+
+/// Primitive used to render each particle.
+///
+/// # Point mode
+///
+/// The [`Point`] primitive renders each particle as a single pixel, instead of
+/// a quad made of two triangles. This is a lightweight alternative for very
+/// dense effects made of tiny particles, like stars or dust, where per-particle
+/// quads are overkill. Points have no size nor orientation, and have no UV
+/// coordinates, so the following render modifiers have no effect, or only a
+/// degraded one, in point mode:
+/// - [`SetSizeModifier`] and [`SizeOverLifetimeModifier`], and any modifier
+///   writing the particle size, since points are always one pixel in size;
+/// - [`OrientModifier`], since points have no orientation;
+/// - [`ParticleTextureModifier`] and [`FlipbookModifier`], since points have
+///   no UV coordinates; the texture is sampled at the center of the image;
+/// - [`DissolveModifier`] and [`RefractionModifier`], for the same reason.
+///
+/// Color modifiers like [`SetColorModifier`] and [`ColorOverLifetimeModifier`]
+/// are fully supported.
+///
+/// [`Point`]: RenderPrimitive::Point
+/// [`SetSizeModifier`]: crate::modifier::SetSizeModifier
+/// [`SizeOverLifetimeModifier`]: crate::modifier::SizeOverLifetimeModifier
+/// [`OrientModifier`]: crate::modifier::OrientModifier
+/// [`ParticleTextureModifier`]: crate::modifier::ParticleTextureModifier
+/// [`FlipbookModifier`]: crate::modifier::FlipbookModifier
+/// [`DissolveModifier`]: crate::modifier::DissolveModifier
+/// [`RefractionModifier`]: crate::modifier::RefractionModifier
+/// [`SetColorModifier`]: crate::modifier::SetColorModifier
+/// [`ColorOverLifetimeModifier`]: crate::modifier::ColorOverLifetimeModifier
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Reflect, Serialize, Deserialize)]
+pub enum RenderPrimitive {
+    /// Render each particle as a quad billboard. This is the default.
+    #[default]
+    Quad,
+    /// Render each particle as a single pixel point.
+    Point,
+}
+
 /// Simulation condition for an effect.
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Reflect, Serialize, Deserialize)]
 pub enum SimulationCondition {
@@ -161,6 +267,175 @@ pub enum AlphaMode {
     ///
     /// [`AlphaMask3d`]: bevy::core_pipeline::core_3d::AlphaMask3d
     Mask(ExprHandle),
+
+    /// Render the effect with subtractive blending.
+    ///
+    /// The color of each particle fragment, modulated by its alpha value, is
+    /// subtracted from the destination render target. This darkens the scene
+    /// behind the particles, for example for dark smoke, while the
+    /// destination alpha is left unchanged.
+    ///
+    /// ```txt
+    /// dst_color = src_color - particle_color * particle_alpha;
+    /// dst_alpha = src_alpha;
+    /// ```
+    ///
+    /// For 3D views, effects with this mode are rendered during the
+    /// [`Transparent3d`] render phase.
+    ///
+    /// [`Transparent3d`]: bevy::core_pipeline::core_3d::Transparent3d
+    Subtract,
+
+    /// Render the effect with a minimum blend operation.
+    ///
+    /// Each color channel of the destination render target is replaced by the
+    /// corresponding channel of the particle fragment if the latter is lower.
+    /// The color of transparent fragments is lerped toward white by their
+    /// alpha value, so they don't affect the destination, while the
+    /// destination alpha is left unchanged.
+    ///
+    /// ```txt
+    /// dst_color = min(src_color, lerp(1, particle_color, particle_alpha));
+    /// dst_alpha = src_alpha;
+    /// ```
+    ///
+    /// For 3D views, effects with this mode are rendered during the
+    /// [`Transparent3d`] render phase.
+    ///
+    /// [`Transparent3d`]: bevy::core_pipeline::core_3d::Transparent3d
+    Min,
+
+    /// Render the effect with a maximum blend operation.
+    ///
+    /// Each color channel of the destination render target is replaced by the
+    /// corresponding channel of the particle fragment if the latter is higher.
+    /// The color of transparent fragments is modulated by their alpha value,
+    /// so they don't affect the destination, while the destination alpha is
+    /// left unchanged.
+    ///
+    /// ```txt
+    /// dst_color = max(src_color, particle_color * particle_alpha);
+    /// dst_alpha = src_alpha;
+    /// ```
+    ///
+    /// For 3D views, effects with this mode are rendered during the
+    /// [`Transparent3d`] render phase.
+    ///
+    /// [`Transparent3d`]: bevy::core_pipeline::core_3d::Transparent3d
+    Max,
+}
+
+impl AlphaMode {
+    /// Get the blend state of the render pipeline for this alpha mode, if
+    /// different from the default alpha blending.
+    pub(crate) fn blend_state(&self) -> Option<BlendState> {
+        // Leave the destination alpha unchanged
+        const KEEP_DST: BlendComponent = BlendComponent {
+            src_factor: BlendFactor::Zero,
+            dst_factor: BlendFactor::One,
+            operation: BlendOperation::Add,
+        };
+        // Min and max operations require both factors to be one
+        let min_max = |operation| BlendComponent {
+            src_factor: BlendFactor::One,
+            dst_factor: BlendFactor::One,
+            operation,
+        };
+        match self {
+            AlphaMode::Blend | AlphaMode::Mask(_) => None,
+            AlphaMode::Subtract => Some(BlendState {
+                color: BlendComponent {
+                    src_factor: BlendFactor::SrcAlpha,
+                    dst_factor: BlendFactor::One,
+                    operation: BlendOperation::ReverseSubtract,
+                },
+                alpha: KEEP_DST,
+            }),
+            AlphaMode::Min => Some(BlendState {
+                color: min_max(BlendOperation::Min),
+                alpha: KEEP_DST,
+            }),
+            AlphaMode::Max => Some(BlendState {
+                color: min_max(BlendOperation::Max),
+                alpha: KEEP_DST,
+            }),
+        }
+    }
+}
+
+/// Color space in which the colors authored on an effect are expressed.
+///
+/// The render shader of an effect always works with linear colors. This
+/// setting controls how the constant colors of the [`SetColorModifier`] and
+/// [`ColorOverLifetimeModifier`] of the effect are interpreted. Artists
+/// generally pick colors in sRGB space, for example from a color picker or an
+/// image, and using those values unconverted as linear colors makes the
+/// effect look washed out.
+///
+/// Colors written to particle attributes like [`Attribute::COLOR`] by
+/// expressions are not affected, and always interpreted as linear.
+///
+/// [`SetColorModifier`]: crate::modifier::SetColorModifier
+/// [`ColorOverLifetimeModifier`]: crate::modifier::ColorOverLifetimeModifier
+/// [`Attribute::COLOR`]: crate::Attribute::COLOR
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Reflect, Serialize, Deserialize)]
+pub enum ColorSpace {
+    /// Colors are linear RGB, and are used as is. This is the default.
+    #[default]
+    Linear,
+
+    /// Colors are non-linear sRGB, and are converted to linear RGB before
+    /// being emitted into the render shader. The alpha component is always
+    /// linear, and is never converted.
+    Srgb,
+}
+
+impl ColorSpace {
+    /// Convert a color expressed in this color space to linear RGB.
+    ///
+    /// The conversion of sRGB colors uses the same piecewise sRGB transfer
+    /// function as Bevy's [`Color`], so the result matches
+    /// `Color::rgba(r, g, b, a).as_linear_rgba_f32()`.
+    ///
+    /// [`Color`]: bevy::render::color::Color
+    pub fn to_linear(&self, color: Vec4) -> Vec4 {
+        match self {
+            ColorSpace::Linear => color,
+            ColorSpace::Srgb => {
+                let f = |c: f32| {
+                    if c <= 0. {
+                        c
+                    } else if c <= 0.04045 {
+                        c / 12.92
+                    } else {
+                        ((c + 0.055) / 1.055).powf(2.4)
+                    }
+                };
+                Vec4::new(f(color.x), f(color.y), f(color.z), color.w)
+            }
+        }
+    }
+}
+
+/// Potential authoring issue detected on an [`EffectAsset`].
+///
+/// Warnings don't prevent the effect from being used, but generally indicate
+/// that the effect doesn't behave as its author likely intended. See
+/// [`EffectAsset::validate()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Error)]
+pub enum EffectWarning {
+    /// The effect integrates the particle motion, but no modifier assigns the
+    /// [`Attribute::LIFETIME`] of the particles. Particles without a lifetime
+    /// never die, so they accumulate until the effect capacity is reached and
+    /// then stop spawning. Add an init modifier assigning a lifetime, like
+    /// [`SetAttributeModifier`] with [`Attribute::LIFETIME`].
+    #[error("the effect has motion integration but no modifier sets the particle lifetime")]
+    MissingLifetime,
+
+    /// A spawner of the effect emits into a group which doesn't exist, so
+    /// never spawns any particle. See [`Spawner::with_group()`].
+    #[error("a spawner emits into group #{0}, but the effect has no such group")]
+    InvalidSpawnerGroup(u32),
 }
 
 /// Asset describing a visual effect.
@@ -170,9 +445,18 @@ pub enum AlphaMode {
 ///
 /// [`ParticleEffect`]: crate::ParticleEffect
 /// [`ParticleEffectBundle`]: crate::ParticleEffectBundle
-#[derive(Asset, Default, Clone, Reflect, Serialize, Deserialize)]
+#[derive(Default, Clone, Reflect, Serialize, Deserialize)]
 #[reflect(from_reflect = false)]
 pub struct EffectAsset {
+    /// Version of the serialization format the asset was loaded from.
+    ///
+    /// This is always serialized as [`EffectAsset::FORMAT_VERSION`]. Assets
+    /// authored before the format was versioned don't have this field, and
+    /// deserialize as version `0`. The [`EffectAssetLoader`] migrates older
+    /// assets to the current format on load.
+    #[serde(default, serialize_with = "serialize_format_version")]
+    #[reflect(ignore)]
+    format_version: u32,
     /// Display name of the effect.
     ///
     /// This has no internal use, and is mostly for the user to identify an
@@ -189,6 +473,14 @@ pub struct EffectAsset {
     capacities: Vec<u32>,
     /// Spawner.
     pub spawner: Spawner,
+    /// Additional spawners, emitting particles in addition to [`spawner`].
+    ///
+    /// See [`with_spawner()`] for details.
+    ///
+    /// [`spawner`]: crate::EffectAsset::spawner
+    /// [`with_spawner()`]: crate::EffectAsset::with_spawner
+    #[serde(default)]
+    pub extra_spawners: Vec<Spawner>,
     /// For 2D rendering, the Z coordinate used as the sort key.
     ///
     /// This value is passed to the render pipeline and used when sorting
@@ -196,12 +488,81 @@ pub struct EffectAsset {
     /// with different Z values cannot be batched together, which may
     /// negatively affect performance.
     ///
+    /// This is the default value for all instances of this effect. Individual
+    /// instances can override it with [`ParticleEffect::z_layer_2d`].
+    ///
     /// Ignored for 3D rendering.
+    ///
+    /// [`ParticleEffect::z_layer_2d`]: crate::ParticleEffect::z_layer_2d
     pub z_layer_2d: f32,
     /// Particle simulation space.
     pub simulation_space: SimulationSpace,
     /// Condition under which the effect is simulated.
     pub simulation_condition: SimulationCondition,
+    /// Scale the emission by the scale of the emitter's transform.
+    ///
+    /// See [`with_emitter_scaling()`] for details.
+    ///
+    /// [`with_emitter_scaling()`]: crate::EffectAsset::with_emitter_scaling
+    #[serde(default)]
+    pub emitter_scaling: bool,
+    /// Duration in seconds the effect is pre-simulated for before it first
+    /// becomes visible.
+    ///
+    /// See [`with_warmup()`] for details.
+    ///
+    /// [`with_warmup()`]: crate::EffectAsset::with_warmup
+    #[serde(default)]
+    pub warmup: f32,
+    /// Pre-simulate the effect for one full particle lifetime before it first
+    /// becomes visible.
+    ///
+    /// See [`with_prewarm_full_lifetime()`] for details.
+    ///
+    /// [`with_prewarm_full_lifetime()`]: crate::EffectAsset::with_prewarm_full_lifetime
+    #[serde(default)]
+    pub prewarm_full_lifetime: bool,
+    /// Explicit upper bound of the particle lifetime, in seconds, if any.
+    ///
+    /// See [`with_max_lifetime()`] for details.
+    ///
+    /// [`with_max_lifetime()`]: crate::EffectAsset::with_max_lifetime
+    #[serde(default)]
+    pub max_lifetime: Option<f32>,
+    /// Interval in frames between two compactions of the particle buffer, or
+    /// `0` to never compact.
+    ///
+    /// See [`with_compaction_interval()`] for details.
+    ///
+    /// [`with_compaction_interval()`]: crate::EffectAsset::with_compaction_interval
+    #[serde(default)]
+    pub compaction_interval: u32,
+    /// Render order of each group. Groups without an entry use a render order
+    /// of `0`.
+    ///
+    /// See [`with_group_render_order()`] for details.
+    ///
+    /// [`with_group_render_order()`]: crate::EffectAsset::with_group_render_order
+    #[serde(default)]
+    group_render_orders: Vec<i32>,
+    /// Motion integration override of each group. Groups without an entry, or
+    /// with a `None` entry, use the asset-wide [`motion_integration`].
+    ///
+    /// See [`with_group_motion_integration()`] for details.
+    ///
+    /// [`motion_integration`]: crate::EffectAsset::motion_integration
+    /// [`with_group_motion_integration()`]: crate::EffectAsset::with_group_motion_integration
+    #[serde(default)]
+    group_motion_integrations: Vec<Option<MotionIntegration>>,
+    /// Draw order override of each group. Groups without an entry, or with a
+    /// `None` entry, use the asset-wide [`draw_order`].
+    ///
+    /// See [`with_group_draw_order()`] for details.
+    ///
+    /// [`draw_order`]: crate::EffectAsset::draw_order
+    /// [`with_group_draw_order()`]: crate::EffectAsset::with_group_draw_order
+    #[serde(default)]
+    group_draw_orders: Vec<Option<ParticleDrawOrder>>,
     /// Init modifier defining the effect.
     #[reflect(ignore)]
     // TODO - Can't manage to implement FromReflect for BoxedModifier in a nice way yet
@@ -226,13 +587,64 @@ pub struct EffectAsset {
     properties: Vec<Property>,
     /// Type of motion integration applied to the particles of a system.
     pub motion_integration: MotionIntegration,
+    /// Order in which the particles of each group are drawn.
+    ///
+    /// See [`ParticleDrawOrder`] for details.
+    #[serde(default)]
+    pub draw_order: ParticleDrawOrder,
     /// Expression module for this effect.
     module: Module,
     /// Alpha mode.
     pub alpha_mode: AlphaMode,
+    /// Color space of the colors authored on the effect's render modifiers.
+    ///
+    /// See [`ColorSpace`] for details.
+    #[serde(default)]
+    pub color_space: ColorSpace,
+    /// Maximum estimated screen coverage of the particles of each instance,
+    /// if any.
+    ///
+    /// See [`with_max_screen_coverage()`] for details.
+    ///
+    /// [`with_max_screen_coverage()`]: crate::EffectAsset::with_max_screen_coverage
+    #[serde(default)]
+    pub max_screen_coverage: Option<f32>,
+    /// Primitive used to render each particle.
+    ///
+    /// See [`RenderPrimitive`] for details.
+    #[serde(default)]
+    pub render_primitive: RenderPrimitive,
+    /// Explicit 2D bounds of the effect, if any.
+    ///
+    /// See [`with_bounds_2d()`] for details.
+    ///
+    /// [`with_bounds_2d()`]: crate::EffectAsset::with_bounds_2d
+    #[serde(
+        default,
+        serialize_with = "serialize_bounds_2d",
+        deserialize_with = "deserialize_bounds_2d"
+    )]
+    pub bounds_2d: Option<Rect>,
+    /// Custom blend state overriding the one derived from the
+    /// [`alpha_mode`], if any.
+    ///
+    /// See [`with_blend_state()`] for details. This is not serialized.
+    ///
+    /// [`alpha_mode`]: crate::EffectAsset::alpha_mode
+    /// [`with_blend_state()`]: crate::EffectAsset::with_blend_state
+    #[serde(skip)]
+    #[reflect(ignore)]
+    pub blend_state: Option<BlendState>,
 }
 
 impl EffectAsset {
+    /// Current version of the `.effect` serialization format.
+    ///
+    /// This is bumped each time the serialization format changes in a way
+    /// which requires a migration of older assets, beyond simply giving a
+    /// default value to new fields.
+    pub const FORMAT_VERSION: u32 = 1;
+
     /// Create a new effect asset.
     ///
     /// The effect assets requires 2 essential pieces:
@@ -245,9 +657,10 @@ impl EffectAsset {
     /// consumption of the effect, which will allocate some buffers to store
     /// that many particles for as long as the effect exists. The capacities of
     /// an effect are immutable. See also [`capacities()`] for more details.
-    /// - The [`Spawner`], which defines when particles are emitted. All
+    /// - The [`Spawner`], which defines when particles are emitted. By default
     /// spawners spawn particles into group 0. (To add particles to other
-    /// groups, use the [`crate::modifier::clone::CloneModifier`].)
+    /// groups, use [`Spawner::with_group()`] or the
+    /// [`crate::modifier::clone::CloneModifier`].)
     ///
     /// Additionally, if any modifier added to this effect uses some [`Expr`] to
     /// customize its behavior, then those [`Expr`] are stored into a [`Module`]
@@ -287,6 +700,7 @@ impl EffectAsset {
     /// [`Expr`]: crate::graph::expr::Expr
     pub fn new(capacities: Vec<u32>, spawner: Spawner, module: Module) -> Self {
         Self {
+            format_version: Self::FORMAT_VERSION,
             capacities,
             spawner,
             module,
@@ -294,6 +708,46 @@ impl EffectAsset {
         }
     }
 
+    /// Add a spawner emitting particles in addition to the main [`spawner`].
+    ///
+    /// Each spawner emits particles independently, with its own timing, and
+    /// the number of particles to spawn each frame is the sum of the number
+    /// emitted by all spawners. This allows combining emission patterns which
+    /// a single spawner can't express, like a steady trickle of particles
+    /// plus some periodic bursts. Each spawner emits into its own target group
+    /// (see [`Spawner::with_group()`]).
+    ///
+    /// The [`ParticleEffect::spawner`] override of an effect instance only
+    /// replaces the main spawner; additional spawners are always taken from
+    /// the asset.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::*;
+    /// // Spawn 10 particles per second, plus a burst of 50 particles every 2s.
+    /// let trickle = Spawner::rate(10.0.into());
+    /// let bursts = Spawner::burst(50.0.into(), 2.0.into());
+    /// let effect = EffectAsset::new(vec![256], trickle, Module::default()).with_spawner(bursts);
+    /// assert_eq!(effect.spawners().count(), 2);
+    /// ```
+    ///
+    /// [`spawner`]: crate::EffectAsset::spawner
+    /// [`ParticleEffect::spawner`]: crate::ParticleEffect::spawner
+    pub fn with_spawner(mut self, spawner: Spawner) -> Self {
+        self.extra_spawners.push(spawner);
+        self
+    }
+
+    /// Get all the spawners of the effect, starting with the main [`spawner`]
+    /// followed by the [`extra_spawners`].
+    ///
+    /// [`spawner`]: crate::EffectAsset::spawner
+    /// [`extra_spawners`]: crate::EffectAsset::extra_spawners
+    pub fn spawners(&self) -> impl Iterator<Item = &Spawner> {
+        std::iter::once(&self.spawner).chain(self.extra_spawners.iter())
+    }
+
     /// Get the capacities of the effect, in number of particles per group.
     ///
     /// For example, if this function returns `&[256, 512]`, then this effect
@@ -303,7 +757,7 @@ impl EffectAsset {
     /// Each value in the array represents the number of particles stored in GPU
     /// memory at all time for the group with the corresponding index, even if
     /// unused, so you should try to minimize this value. However, the
-    /// [`Spawner`] cannot emit more particles than the capacity of group 0.
+    /// [`Spawner`] cannot emit more particles than the capacity of its group.
     /// Whatever the spawner settings, if the number of particles reaches the
     /// capacity, no new particle can be emitted. Setting an appropriate
     /// capacity for an effect is therefore a compromise between more particles
@@ -325,6 +779,31 @@ impl EffectAsset {
         &self.module
     }
 
+    /// Get mutable access to the expression module storing all expressions in
+    /// use by modifiers of this effect.
+    ///
+    /// This allows tooling like an editor to inspect and modify the
+    /// expressions of an effect after it was built, for example to tweak the
+    /// value of a literal with [`Module::set_literal()`].
+    ///
+    /// Modifiers reference their expressions by [`ExprHandle`], so existing
+    /// expressions should be modified in place rather than removed. Any
+    /// expression reading an attribute must only reference attributes already
+    /// present in the [`particle_layout()`] of the effect, which is derived
+    /// from its modifiers, and any expression reading a property must
+    /// reference a property declared on the effect. Otherwise the effect fails
+    /// to compile.
+    ///
+    /// When the effect asset is mutated through [`Assets::get_mut()`], the
+    /// asset is marked as modified, and all effect instances using it are
+    /// automatically recompiled.
+    ///
+    /// [`particle_layout()`]: crate::EffectAsset::particle_layout
+    /// [`Assets::get_mut()`]: bevy::asset::Assets::get_mut
+    pub fn module_mut(&mut self) -> &mut Module {
+        &mut self.module
+    }
+
     /// Set the effect name.
     ///
     /// The effect name is used when serializing the effect.
@@ -339,167 +818,836 @@ impl EffectAsset {
         self
     }
 
-    /// Set the effect's simulation space.
-    pub fn with_simulation_space(mut self, simulation_space: SimulationSpace) -> Self {
-        self.simulation_space = simulation_space;
-        self
-    }
-
-    /// Set the alpha mode.
-    pub fn with_alpha_mode(mut self, alpha_mode: AlphaMode) -> Self {
-        self.alpha_mode = alpha_mode;
+    /// Set whether the emission scales with the emitter's transform.
+    ///
+    /// By default, scaling the [`GlobalTransform`] of an effect instance
+    /// doesn't change the number of particles spawned, so the particle
+    /// density drops as the instance grows. When enabled, the number of
+    /// particles spawned by each spawner is multiplied by the volume scale of
+    /// the emitter, that is the product of the absolute values of its scale
+    /// components. For 2D effects with a unit Z scale, this is the area
+    /// scale. The [`ParticleEffect::local_transform`] of the instance, if
+    /// any, is included.
+    ///
+    /// For effects simulated in [`SimulationSpace::Global`], the positions
+    /// assigned by the init modifiers, and therefore the emitter shapes, are
+    /// also scaled by the emitter scale before being offset by the emitter
+    /// position. Effects simulated in [`SimulationSpace::Local`] are already
+    /// rendered with the full emitter transform, so their shapes scale
+    /// regardless of this setting.
+    ///
+    /// Defaults to `false`.
+    ///
+    /// [`GlobalTransform`]: bevy::transform::components::GlobalTransform
+    /// [`ParticleEffect::local_transform`]: crate::ParticleEffect::local_transform
+    pub fn with_emitter_scaling(mut self, emitter_scaling: bool) -> Self {
+        self.emitter_scaling = emitter_scaling;
         self
     }
 
-    /// Add a new property to the asset.
+    /// Set the effect's warmup duration, in seconds.
     ///
-    /// See [`Property`] for more details on what effect properties are.
+    /// When an instance of the effect is first simulated, the effect is
+    /// pre-simulated for that many seconds before it becomes visible, so that
+    /// it appears already in its steady state, for example a fire already
+    /// burning or a smoke column already risen, instead of starting empty.
+    ///
+    /// To avoid stalling a single frame, the warmup is spread over several
+    /// frames. Each frame, the simulation advances by the frame's delta time
+    /// plus at most [`EffectSpawner::MAX_WARMUP_STEP`] seconds of the
+    /// remaining warmup. The effect is not rendered until the warmup
+    /// completes.
     ///
     /// # Panics
     ///
-    /// Panics if a property with the same name already exists.
-    pub fn with_property(mut self, name: impl Into<String>, default_value: Value) -> Self {
-        self.add_property(name, default_value);
+    /// Panics if `warmup` is negative.
+    ///
+    /// [`EffectSpawner::MAX_WARMUP_STEP`]: crate::EffectSpawner::MAX_WARMUP_STEP
+    pub fn with_warmup(mut self, warmup: f32) -> Self {
+        assert!(warmup >= 0., "Warmup duration cannot be negative.");
+        self.warmup = warmup;
         self
     }
 
-    /// Add a new property to the asset.
-    ///
-    /// See [`Property`] for more details on what effect properties are.
+    /// Set whether the effect is pre-simulated for one full particle lifetime
+    /// before it first becomes visible.
+    ///
+    /// This is a variant of [`with_warmup()`] which automatically targets the
+    /// maximum lifetime of the particles, as returned by
+    /// [`estimated_max_lifetime()`]. After that duration, a looping
+    /// effect reached its steady-state distribution of particles, so the
+    /// effect looks fully populated the first time it's rendered. This is
+    /// useful for ambient effects like fog or dust fields.
+    ///
+    /// If the lifetime cannot be estimated, for example because it's computed
+    /// from a property, a warning is emitted and only the [`warmup`] duration
+    /// is used; use [`with_max_lifetime()`] to provide it explicitly in that
+    /// case. If both a warmup duration and this option are set, the longest
+    /// of the two durations is used.
+    ///
+    /// [`with_warmup()`]: crate::EffectAsset::with_warmup
+    /// [`estimated_max_lifetime()`]: crate::EffectAsset::estimated_max_lifetime
+    /// [`warmup`]: crate::EffectAsset::warmup
+    /// [`with_max_lifetime()`]: crate::EffectAsset::with_max_lifetime
+    pub fn with_prewarm_full_lifetime(mut self, prewarm_full_lifetime: bool) -> Self {
+        self.prewarm_full_lifetime = prewarm_full_lifetime;
+        self
+    }
+
+    /// Set an explicit upper bound of the lifetime of the particles, in
+    /// seconds.
+    ///
+    /// Several features need to know how long the particles of the effect
+    /// persist:
+    /// - the full lifetime prewarm (see [`with_prewarm_full_lifetime()`]);
+    /// - the automatic bounds estimate (see [`estimate_aabb()`]);
+    /// - the finished detection (see [`CompiledParticleEffect::is_finished()`]);
+    /// - the screen coverage budget, which estimates the number of alive
+    ///   particles (see [`with_max_screen_coverage()`]).
+    ///
+    /// By default, the maximum lifetime is derived from the init modifier
+    /// setting the [`Attribute::LIFETIME`], when its value is statically known
+    /// (a literal, or a random value between literals). When the lifetime is
+    /// driven by a property or an arbitrary expression, it can't be derived,
+    /// and those features are disabled unless this hint is provided. If set,
+    /// the hint always takes precedence over the derived value.
+    ///
+    /// The hint is not enforced on GPU; providing a value smaller than the
+    /// actual lifetime of the particles makes the features above inaccurate,
+    /// for example reporting an effect as finished while some particles are
+    /// still alive.
     ///
     /// # Panics
     ///
-    /// Panics if a property with the same name already exists.
-    pub fn add_property(&mut self, name: impl Into<String>, default_value: Value) {
-        let name = name.into();
-        assert!(!self.properties.iter().any(|p| p.name() == name));
-        self.properties.push(Property::new(name, default_value));
+    /// Panics if `max_lifetime` is negative or not finite.
+    ///
+    /// [`with_prewarm_full_lifetime()`]: crate::EffectAsset::with_prewarm_full_lifetime
+    /// [`estimate_aabb()`]: crate::EffectAsset::estimate_aabb
+    /// [`CompiledParticleEffect::is_finished()`]: crate::CompiledParticleEffect::is_finished
+    /// [`with_max_screen_coverage()`]: crate::EffectAsset::with_max_screen_coverage
+    pub fn with_max_lifetime(mut self, max_lifetime: f32) -> Self {
+        assert!(
+            max_lifetime.is_finite() && max_lifetime >= 0.,
+            "Maximum lifetime must be a finite non-negative value."
+        );
+        self.max_lifetime = Some(max_lifetime);
+        self
     }
 
-    /// Get the list of existing properties.
-    pub fn properties(&self) -> &[Property] {
-        &self.properties
+    /// Set the interval in frames between two compactions of the particles of
+    /// the effect.
+    ///
+    /// As particles die and get recycled, the alive particles of a long-running
+    /// effect end up scattered across the entire capacity of their group in
+    /// the GPU particle buffer. Compaction moves the alive particles of each
+    /// group to the front of the group's slice of the buffer, so that the
+    /// update and render passes access a dense range of memory, which improves
+    /// cache efficiency. A value of `0` (the default) disables compaction.
+    ///
+    /// Compaction runs after the update pass of all the groups of the effect,
+    /// once every `compaction_interval` frames. It rewrites both the ping-pong
+    /// indirect indices of the alive particles used for rendering, and the
+    /// dead particle indices recycled by the init pass and the
+    /// [`CloneModifier`]. Because particles never change group, the
+    /// compaction of a group doesn't affect the other groups of the effect.
+    ///
+    /// Compaction has a cost proportional to the capacity of each group, and
+    /// is executed serially on the GPU. Only enable it for effects with a high
+    /// particle churn, with an interval large enough to amortize that cost.
+    ///
+    /// [`CloneModifier`]: crate::CloneModifier
+    pub fn with_compaction_interval(mut self, compaction_interval: u32) -> Self {
+        self.compaction_interval = compaction_interval;
+        self
     }
 
-    /// Add an initialization modifier to the effect.
+    /// Set the render order of a group.
     ///
-    /// Initialization modifiers only apply to particles that are freshly
-    /// spawned. Currently, spawners can only spawn into group 0. Consequently,
-    /// the initialization modifiers will only affect particles in group 0.
+    /// When an effect has multiple groups, for example a trail and the head
+    /// particles emitting it, the order in which the groups are drawn affects
+    /// blending. Groups with a lower render order are drawn first, so appear
+    /// behind groups with a higher render order. Groups with the same render
+    /// order are drawn in increasing group index order. By default all groups
+    /// have a render order of `0`, so are drawn by group index.
     ///
-    /// # Panics
+    /// The render order only applies between draws with the same sort key in
+    /// the render phase. In 3D, all effects currently share the same sort key,
+    /// so the render order also orders groups of different effects, like a
+    /// layer. In 2D, the Z layer of the effect takes precedence over the render
+    /// order.
     ///
-    /// Panics if the modifier doesn't support the init context (that is,
-    /// `modifier.context()` returns a flag which doesn't include
-    /// [`ModifierContext::Init`]).
-    #[inline]
-    pub fn init<M>(mut self, modifier: M) -> Self
-    where
-        M: Modifier + Send + Sync,
-    {
-        assert!(modifier.context().contains(ModifierContext::Init));
-        self.init_modifiers.push(GroupedModifier {
-            modifier: Box::new(modifier),
-            groups: ParticleGroupSet::single(0),
-        });
-        self
-    }
-
-    /// Add an update modifier to the effect.
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::*;
+    /// # let spawner = Spawner::rate(5_f32.into());
+    /// // Draw the trail (group 1) behind the head particles (group 0)
+    /// let effect = EffectAsset::new(vec![256, 4096], spawner, Module::default())
+    ///     .with_group_render_order(1, -1);
+    /// assert_eq!(effect.group_render_order(0), 0);
+    /// assert_eq!(effect.group_render_order(1), -1);
+    /// ```
     ///
     /// # Panics
     ///
-    /// Panics if the modifier doesn't support the update context (that is,
-    /// `modifier.context()` returns a flag which doesn't include
-    /// [`ModifierContext::Update`]).
-    #[inline]
-    pub fn update<M>(mut self, modifier: M) -> Self
-    where
-        M: Modifier + Send + Sync,
-    {
-        assert!(modifier.context().contains(ModifierContext::Update));
-        self.update_modifiers.push(GroupedModifier {
-            modifier: Box::new(modifier),
-            groups: ParticleGroupSet::all(),
-        });
+    /// Panics if `group_index` is not a valid group index of this effect.
+    pub fn with_group_render_order(mut self, group_index: u32, render_order: i32) -> Self {
+        let group_count = self.capacities.len();
+        assert!(
+            (group_index as usize) < group_count,
+            "Invalid group index {} for effect with {} group(s).",
+            group_index,
+            group_count
+        );
+        if self.group_render_orders.len() < group_count {
+            self.group_render_orders.resize(group_count, 0);
+        }
+        self.group_render_orders[group_index as usize] = render_order;
         self
     }
 
-    /// Add an update modifier to the effect targeting only a subset of groups.
+    /// Override the motion integration of a group.
     ///
-    /// [`with_property()`]: crate::EffectAsset::with_property
-    /// [`add_property()`]: crate::EffectAsset::add_property
-    #[inline]
-    pub fn update_groups<M>(mut self, modifier: M, groups: ParticleGroupSet) -> Self
-    where
-        M: Modifier + Send + Sync,
-    {
-        self.update_modifiers.push(GroupedModifier {
-            modifier: Box::new(modifier),
-            groups,
-        });
-        self
-    }
-
-    /// Add a [`BoxedModifier`] to the specific context.
+    /// By default all groups use the asset-wide [`motion_integration`]. This
+    /// allows overriding it for a single group of a multi-group effect, for
+    /// example to leave in place the particles of a trail, while the heads
+    /// emitting them keep moving.
     ///
-    /// # Panics
+    /// # Example
     ///
-    /// Panics if the context is [`ModifierContext::Render`]; use
-    /// [`add_render_modifier()`] instead.
+    /// ```
+    /// # use bevy_hanabi::*;
+    /// # let spawner = Spawner::rate(5_f32.into());
+    /// // Heads (group 0) move, trail particles (group 1) stay in place
+    /// let effect = EffectAsset::new(vec![256, 4096], spawner, Module::default())
+    ///     .with_motion_integration(MotionIntegration::PostUpdate)
+    ///     .with_group_motion_integration(1, MotionIntegration::None);
+    /// assert_eq!(effect.group_motion_integration(0), MotionIntegration::PostUpdate);
+    /// assert_eq!(effect.group_motion_integration(1), MotionIntegration::None);
+    /// ```
     ///
-    /// Panics if the input `context` contains more than one context (the
-    /// bitfield contains more than 1 bit set) or no context at all (zero bit
-    /// set).
+    /// # Panics
     ///
-    /// Panics if the modifier doesn't support the context specified (that is,
-    /// `modifier.context()` returns a flag which doesn't include `context`).
+    /// Panics if `group_index` is not a valid group index of this effect.
     ///
-    /// [`add_render_modifier()`]: crate::EffectAsset::add_render_modifier
-    pub fn add_modifier(self, context: ModifierContext, modifier: Box<dyn Modifier>) -> Self {
-        self.add_modifier_to_groups(context, modifier, ParticleGroupSet::all())
+    /// [`motion_integration`]: crate::EffectAsset::motion_integration
+    pub fn with_group_motion_integration(
+        mut self,
+        group_index: u32,
+        motion_integration: MotionIntegration,
+    ) -> Self {
+        let group_count = self.capacities.len();
+        assert!(
+            (group_index as usize) < group_count,
+            "Invalid group index {} for effect with {} group(s).",
+            group_index,
+            group_count
+        );
+        if self.group_motion_integrations.len() < group_count {
+            self.group_motion_integrations.resize(group_count, None);
+        }
+        self.group_motion_integrations[group_index as usize] = Some(motion_integration);
+        self
     }
 
-    /// Add a [`BoxedModifier`] to the specific context, in a specific set of
-    /// groups.
+    /// Override the draw order of the particles of a group.
     ///
-    /// # Panics
+    /// By default all groups use the asset-wide [`draw_order`]. This allows
+    /// overriding it for a single group of a multi-group effect.
     ///
-    /// Panics if the context is [`ModifierContext::Render`]; use
-    /// [`add_render_modifier()`] instead.
+    /// # Example
     ///
-    /// Panics if the input `context` contains more than one context (the
-    /// bitfield contains more than 1 bit set) or no context at all (zero bit
-    /// set).
+    /// ```
+    /// # use bevy_hanabi::*;
+    /// # let spawner = Spawner::rate(5_f32.into());
+    /// // Draw the newest trail particles (group 1) below the older ones
+    /// let effect = EffectAsset::new(vec![256, 4096], spawner, Module::default())
+    ///     .with_group_draw_order(1, ParticleDrawOrder::NewestFirst);
+    /// assert_eq!(effect.group_draw_order(0), ParticleDrawOrder::OldestFirst);
+    /// assert_eq!(effect.group_draw_order(1), ParticleDrawOrder::NewestFirst);
+    /// ```
     ///
-    /// Panics if the modifier doesn't support the context specified (that is,
-    /// `modifier.context()` returns a flag which doesn't include `context`).
+    /// # Panics
     ///
-    /// [`add_render_modifier()`]: crate::EffectAsset::add_render_modifier
-    pub fn add_modifier_to_groups(
+    /// Panics if `group_index` is not a valid group index of this effect.
+    ///
+    /// [`draw_order`]: crate::EffectAsset::draw_order
+    pub fn with_group_draw_order(
         mut self,
-        context: ModifierContext,
-        modifier: Box<dyn Modifier>,
-        groups: ParticleGroupSet,
+        group_index: u32,
+        draw_order: ParticleDrawOrder,
     ) -> Self {
-        assert!(context == ModifierContext::Init || context == ModifierContext::Update);
-        assert!(modifier.context().contains(context));
-        let grouped_modifier = GroupedModifier { modifier, groups };
-        if context == ModifierContext::Init {
-            self.init_modifiers.push(grouped_modifier);
-        } else {
-            self.update_modifiers.push(grouped_modifier);
+        let group_count = self.capacities.len();
+        assert!(
+            (group_index as usize) < group_count,
+            "Invalid group index {} for effect with {} group(s).",
+            group_index,
+            group_count
+        );
+        if self.group_draw_orders.len() < group_count {
+            self.group_draw_orders.resize(group_count, None);
         }
+        self.group_draw_orders[group_index as usize] = Some(draw_order);
         self
     }
 
-    /// Add a render modifier to the effect.
+    /// Add a trail to the particles of a single-group effect.
     ///
-    /// # Panics
+    /// This configures the common "head + trail" effect with two particle
+    /// groups. The particles emitted by the [`Spawner`] into group 0 form the
+    /// heads. Every `spawn_period` seconds, a [`CloneModifier`] duplicates
+    /// each head into group 1, where the copy stays for `trail_lifetime`
+    /// seconds to form the trail. The capacity of group 1 is set to hold the
+    /// maximum number of trail particles each head can leave behind, that is
+    /// `ceil(trail_lifetime / spawn_period)` times the capacity of group 0.
     ///
-    /// Panics if the modifier doesn't support the render context (that is,
-    /// `modifier.context()` returns a flag which doesn't include
-    /// [`ModifierContext::Render`]).
+    /// Trail particles are a copy of their head at the time they're cloned,
+    /// with their [`Attribute::AGE`] reset to zero. Modifiers added with
+    /// [`init()`], [`update()`] and [`render()`] apply to both groups. To
+    /// render the trail differently from the heads, add the group-specific
+    /// modifiers with [`update_groups()`] and [`render_groups()`]. To stop
+    /// moving the trail particles, disable their motion integration with
+    /// [`with_group_motion_integration()`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy::math::Vec2;
+    /// # use bevy_hanabi::*;
+    /// let writer = ExprWriter::new();
+    /// let spawner = Spawner::rate(5_f32.into());
+    ///
+    /// // 64 heads, each leaving a new trail particle every 0.125 second, alive
+    /// // for 0.5 second.
+    /// let effect = EffectAsset::new(vec![64], spawner, writer.finish())
+    ///     .with_trail(0.5, 0.125)
+    ///     // Trail particles don't move
+    ///     .with_group_motion_integration(1, MotionIntegration::None)
+    ///     // Trail particles are smaller than heads
+    ///     .render_groups(
+    ///         SetSizeModifier {
+    ///             size: Vec2::splat(0.3).into(),
+    ///         },
+    ///         ParticleGroupSet::single(1),
+    ///     );
+    /// assert_eq!(effect.capacities(), &[64, 256]);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the effect doesn't have exactly one group, or if
+    /// `spawn_period` is not strictly positive.
+    ///
+    /// [`init()`]: crate::EffectAsset::init
+    /// [`update()`]: crate::EffectAsset::update
+    /// [`render()`]: crate::EffectAsset::render
+    /// [`update_groups()`]: crate::EffectAsset::update_groups
+    /// [`render_groups()`]: crate::EffectAsset::render_groups
+    /// [`with_group_motion_integration()`]: crate::EffectAsset::with_group_motion_integration
+    pub fn with_trail(mut self, trail_lifetime: f32, spawn_period: f32) -> Self {
+        assert_eq!(
+            self.capacities.len(),
+            1,
+            "Cannot add a trail to an effect with {} group(s); expected a single group.",
+            self.capacities.len()
+        );
+        assert!(
+            spawn_period > 0.,
+            "Invalid trail spawn period {}; expected a strictly positive value.",
+            spawn_period
+        );
+        let trail_length = (trail_lifetime.max(0.) / spawn_period).ceil() as u32;
+        self.capacities
+            .push(self.capacities[0].saturating_mul(trail_length.max(1)));
+        let lifetime = self.module.lit(trail_lifetime);
+        self.update_groups(
+            CloneModifier::new(spawn_period, 1),
+            ParticleGroupSet::single(0),
+        )
+        .update_groups(
+            SetAttributeModifier::new(Attribute::LIFETIME, lifetime),
+            ParticleGroupSet::single(1),
+        )
+    }
+
+    /// Get the render order of a group.
+    ///
+    /// See [`with_group_render_order()`] for details.
+    ///
+    /// [`with_group_render_order()`]: crate::EffectAsset::with_group_render_order
+    pub fn group_render_order(&self, group_index: u32) -> i32 {
+        self.group_render_orders
+            .get(group_index as usize)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Get the motion integration of a group.
+    ///
+    /// This returns the override set with [`with_group_motion_integration()`]
+    /// if any, or the asset-wide [`motion_integration`] otherwise.
+    ///
+    /// [`with_group_motion_integration()`]: crate::EffectAsset::with_group_motion_integration
+    /// [`motion_integration`]: crate::EffectAsset::motion_integration
+    pub fn group_motion_integration(&self, group_index: u32) -> MotionIntegration {
+        self.group_motion_integrations
+            .get(group_index as usize)
+            .copied()
+            .flatten()
+            .unwrap_or(self.motion_integration)
+    }
+
+    /// Get the draw order of the particles of a group.
+    ///
+    /// This returns the override set with [`with_group_draw_order()`] if any,
+    /// or the asset-wide [`draw_order`] otherwise.
+    ///
+    /// [`with_group_draw_order()`]: crate::EffectAsset::with_group_draw_order
+    /// [`draw_order`]: crate::EffectAsset::draw_order
+    pub fn group_draw_order(&self, group_index: u32) -> ParticleDrawOrder {
+        self.group_draw_orders
+            .get(group_index as usize)
+            .copied()
+            .flatten()
+            .unwrap_or(self.draw_order)
+    }
+
+    /// Get the effective warmup duration of the effect, in seconds.
+    ///
+    /// This is the [`warmup`] duration, extended to the maximum particle
+    /// lifetime if [`prewarm_full_lifetime`] is set.
+    ///
+    /// [`warmup`]: crate::EffectAsset::warmup
+    /// [`prewarm_full_lifetime`]: crate::EffectAsset::prewarm_full_lifetime
+    pub fn warmup_duration(&self) -> f32 {
+        if !self.prewarm_full_lifetime {
+            return self.warmup;
+        }
+        match self.estimated_max_lifetime() {
+            Some(lifetime) => self.warmup.max(lifetime),
+            None => {
+                warn!(
+                    "Effect '{}' requested a full lifetime prewarm, but the particle lifetime cannot be estimated. Using only the warmup duration of {} seconds.",
+                    self.name, self.warmup
+                );
+                self.warmup
+            }
+        }
+    }
+
+    /// Set the effect's simulation space.
+    pub fn with_simulation_space(mut self, simulation_space: SimulationSpace) -> Self {
+        self.simulation_space = simulation_space;
+        self
+    }
+
+    /// Check the effect for common authoring mistakes.
+    ///
+    /// This returns the list of potential issues detected on the effect, which
+    /// is empty if none was found. See [`EffectWarning`] for the list of
+    /// checks. Those warnings are also logged once when the effect is
+    /// compiled.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::*;
+    /// // Particles move but never die
+    /// let effect = EffectAsset::new(vec![256], Spawner::default(), Module::default());
+    /// assert_eq!(effect.validate(), vec![EffectWarning::MissingLifetime]);
+    /// ```
+    pub fn validate(&self) -> Vec<EffectWarning> {
+        let mut warnings = vec![];
+
+        let group_count = self.capacities.len() as u32;
+        if (0..group_count).any(|group_index| {
+            self.group_motion_integration(group_index) != MotionIntegration::None
+        }) {
+            let has_lifetime = self
+                .init_modifiers()
+                .chain(self.update_modifiers())
+                .any(writes_lifetime);
+            if !has_lifetime {
+                warnings.push(EffectWarning::MissingLifetime);
+            }
+        }
+
+        for spawner in self.spawners() {
+            if spawner.group() >= group_count {
+                warnings.push(EffectWarning::InvalidSpawnerGroup(spawner.group()));
+            }
+        }
+
+        warnings
+    }
+
+    /// Set the effect's motion integration.
+    ///
+    /// Motion integration requires both [`Attribute::POSITION`] and
+    /// [`Attribute::VELOCITY`] to be present in the particle layout of the
+    /// effect, that is used by at least one modifier. Otherwise integration
+    /// is skipped, and a warning is emitted when the effect shaders are
+    /// generated.
+    ///
+    /// [`Attribute::POSITION`]: crate::Attribute::POSITION
+    /// [`Attribute::VELOCITY`]: crate::Attribute::VELOCITY
+    pub fn with_motion_integration(mut self, motion_integration: MotionIntegration) -> Self {
+        self.motion_integration = motion_integration;
+        self
+    }
+
+    /// Set the order in which the particles of all groups are drawn.
+    ///
+    /// See [`ParticleDrawOrder`] for details.
+    pub fn with_draw_order(mut self, draw_order: ParticleDrawOrder) -> Self {
+        self.draw_order = draw_order;
+        self
+    }
+
+    /// Set the default Z layer used when rendering in 2D mode.
+    ///
+    /// See [`EffectAsset::z_layer_2d`] for details.
+    pub fn with_z_layer_2d(mut self, z_layer_2d: f32) -> Self {
+        self.z_layer_2d = z_layer_2d;
+        self
+    }
+
+    /// Set explicit 2D bounds for the effect.
+    ///
+    /// The bounds are expressed in the local space of the emitter, in the XY
+    /// plane, and must contain all the particles of the effect. When set, the
+    /// bounds are inserted as an [`Aabb`] component on each [`ParticleEffect`]
+    /// instance rendered with the `2d` feature, which allows Bevy's frustum
+    /// culling to skip effects out of view. Since the automatic estimate (see
+    /// [`estimated_bounds_2d()`]) can't account for all particle motions, and
+    /// is for example too small for effects with a large procedural spread,
+    /// the explicit bounds allow fixing effects culled incorrectly.
+    ///
+    /// The bounds don't constrain the depth of the particles. The inserted
+    /// [`Aabb`] extends in depth as much as along its largest 2D dimension, so
+    /// the effect isn't culled by a 3D camera when both the `2d` and `3d`
+    /// features are enabled. The sorting of 2D effects relative to other 2D
+    /// items like sprites is still controlled by [`z_layer_2d`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bounds` is empty.
+    ///
+    /// [`ParticleEffect`]: crate::ParticleEffect
+    /// [`estimated_bounds_2d()`]: crate::EffectAsset::estimated_bounds_2d
+    /// [`z_layer_2d`]: crate::EffectAsset::z_layer_2d
+    pub fn with_bounds_2d(mut self, bounds: Rect) -> Self {
+        assert!(!bounds.is_empty(), "2D bounds cannot be empty.");
+        self.bounds_2d = Some(bounds);
+        self
+    }
+
+    /// Set the alpha mode.
+    pub fn with_alpha_mode(mut self, alpha_mode: AlphaMode) -> Self {
+        self.alpha_mode = alpha_mode;
+        self
+    }
+
+    /// Set a custom blend state, overriding the one derived from the
+    /// [`alpha_mode`].
+    ///
+    /// This is an advanced escape hatch for blending operations not covered by
+    /// [`AlphaMode`]. The blend state, with its color and alpha blend
+    /// components, is used as is for the color target of the render pipeline
+    /// of the effect. The [`alpha_mode`] still controls the render phase of
+    /// the effect, as well as the alpha masking in the fragment shader. Effects
+    /// with different blend states use different render pipelines, so are
+    /// never batched together.
+    ///
+    /// The blend state is not serialized, because its type is defined by the
+    /// underlying graphics API and doesn't support serialization in all
+    /// configurations. An effect saved to and loaded from a RON file loses
+    /// any custom blend state, and must be assigned it again after loading.
+    ///
+    /// ```
+    /// # use bevy::render::render_resource::{BlendComponent, BlendFactor, BlendOperation, BlendState};
+    /// # use bevy_hanabi::*;
+    /// # let asset = EffectAsset::new(vec![256], Spawner::rate(32.0.into()), Module::default());
+    /// // Multiplicative blending, darkening the scene behind the particles
+    /// let multiply = BlendComponent {
+    ///     src_factor: BlendFactor::Dst,
+    ///     dst_factor: BlendFactor::Zero,
+    ///     operation: BlendOperation::Add,
+    /// };
+    /// let asset = asset.with_blend_state(BlendState {
+    ///     color: multiply,
+    ///     alpha: BlendComponent::OVER,
+    /// });
+    /// ```
+    ///
+    /// [`alpha_mode`]: crate::EffectAsset::alpha_mode
+    pub fn with_blend_state(mut self, blend_state: BlendState) -> Self {
+        self.blend_state = Some(blend_state);
+        self
+    }
+
+    /// Set the primitive used to render each particle.
+    ///
+    /// See [`RenderPrimitive`] for details, including the render modifiers
+    /// not compatible with point rendering.
+    pub fn with_render_primitive(mut self, render_primitive: RenderPrimitive) -> Self {
+        self.render_primitive = render_primitive;
+        self
+    }
+
+    /// Set the color space of the colors authored on the render modifiers.
+    ///
+    /// See [`ColorSpace`] for details.
+    pub fn with_color_space(mut self, color_space: ColorSpace) -> Self {
+        self.color_space = color_space;
+        self
+    }
+
+    /// Set a fill-rate budget limiting the overdraw of each effect instance.
+    ///
+    /// The screen coverage of an instance is estimated each frame as the
+    /// number of alive particles multiplied by the area of a single particle
+    /// projected onto the screen at the distance of the emitter, relative to
+    /// the viewport area. A coverage of `1.0` means the particles together
+    /// cover the equivalent of the entire screen once. When the estimate
+    /// exceeds `max_screen_coverage`, the spawn rate of the instance is scaled
+    /// down proportionally (see [`EffectSpawner::spawn_scale()`]), reducing
+    /// the number of overlapping particles and therefore the overdraw. This is
+    /// mostly useful for large soft particles like smoke, which become very
+    /// expensive to render when the camera gets close to them.
+    ///
+    /// The estimate uses the particle size set by the init or render
+    /// modifiers when it's statically known, and falls back to the default
+    /// size of `1.0` otherwise. Effects whose size is in screen space are not
+    /// limited. The estimate is approximate; consider the value a safety
+    /// valve rather than an exact pixel budget.
+    ///
+    /// [`EffectSpawner::spawn_scale()`]: crate::EffectSpawner::spawn_scale
+    pub fn with_max_screen_coverage(mut self, max_screen_coverage: f32) -> Self {
+        self.max_screen_coverage = Some(max_screen_coverage);
+        self
+    }
+
+    /// Add a new property to the asset.
+    ///
+    /// See [`Property`] for more details on what effect properties are.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a property with the same name already exists.
+    pub fn with_property(mut self, name: impl Into<String>, default_value: Value) -> Self {
+        self.add_property(name, default_value);
+        self
+    }
+
+    /// Add a new property to the asset.
+    ///
+    /// See [`Property`] for more details on what effect properties are.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a property with the same name already exists.
+    pub fn add_property(&mut self, name: impl Into<String>, default_value: Value) {
+        let name = name.into();
+        assert!(!self.properties.iter().any(|p| p.name() == name));
+        self.properties.push(Property::new(name, default_value));
+    }
+
+    /// Get the list of existing properties.
+    pub fn properties(&self) -> &[Property] {
+        &self.properties
+    }
+
+    /// Add an initialization modifier to the effect.
+    ///
+    /// Initialization modifiers only apply to particles that are freshly
+    /// spawned by a [`Spawner`], into whichever group the spawner targets (see
+    /// [`Spawner::with_group()`]). Particles added to a group by a
+    /// [`CloneModifier`] are not affected by initialization modifiers.
+    ///
+    /// [`CloneModifier`]: crate::modifier::CloneModifier
+    ///
+    /// # Panics
+    ///
+    /// Panics if the modifier doesn't support the init context (that is,
+    /// `modifier.context()` returns a flag which doesn't include
+    /// [`ModifierContext::Init`]).
+    #[inline]
+    pub fn init<M>(mut self, modifier: M) -> Self
+    where
+        M: Modifier + Send + Sync,
+    {
+        assert!(modifier.context().contains(ModifierContext::Init));
+        self.init_modifiers.push(GroupedModifier {
+            modifier: Box::new(modifier),
+            groups: ParticleGroupSet::single(0),
+            order: 0,
+        });
+        self
+    }
+
+    /// Add an update modifier to the effect.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the modifier doesn't support the update context (that is,
+    /// `modifier.context()` returns a flag which doesn't include
+    /// [`ModifierContext::Update`]).
+    #[inline]
+    pub fn update<M>(mut self, modifier: M) -> Self
+    where
+        M: Modifier + Send + Sync,
+    {
+        assert!(modifier.context().contains(ModifierContext::Update));
+        self.update_modifiers.push(GroupedModifier {
+            modifier: Box::new(modifier),
+            groups: ParticleGroupSet::all(),
+            order: 0,
+        });
+        self
+    }
+
+    /// Add an update modifier to the effect targeting only a subset of groups.
+    ///
+    /// [`with_property()`]: crate::EffectAsset::with_property
+    /// [`add_property()`]: crate::EffectAsset::add_property
+    #[inline]
+    pub fn update_groups<M>(mut self, modifier: M, groups: ParticleGroupSet) -> Self
+    where
+        M: Modifier + Send + Sync,
+    {
+        self.update_modifiers.push(GroupedModifier {
+            modifier: Box::new(modifier),
+            groups,
+            order: 0,
+        });
+        self
+    }
+
+    /// Add a [`BoxedModifier`] to the specific context.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the context is [`ModifierContext::Render`]; use
+    /// [`add_render_modifier()`] instead.
+    ///
+    /// Panics if the input `context` contains more than one context (the
+    /// bitfield contains more than 1 bit set) or no context at all (zero bit
+    /// set).
+    ///
+    /// Panics if the modifier doesn't support the context specified (that is,
+    /// `modifier.context()` returns a flag which doesn't include `context`).
+    ///
+    /// [`add_render_modifier()`]: crate::EffectAsset::add_render_modifier
+    pub fn add_modifier(self, context: ModifierContext, modifier: Box<dyn Modifier>) -> Self {
+        self.add_modifier_to_groups(context, modifier, ParticleGroupSet::all())
+    }
+
+    /// Add a [`BoxedModifier`] to the specific context, in a specific set of
+    /// groups.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the context is [`ModifierContext::Render`]; use
+    /// [`add_render_modifier()`] instead.
+    ///
+    /// Panics if the input `context` contains more than one context (the
+    /// bitfield contains more than 1 bit set) or no context at all (zero bit
+    /// set).
+    ///
+    /// Panics if the modifier doesn't support the context specified (that is,
+    /// `modifier.context()` returns a flag which doesn't include `context`).
+    ///
+    /// [`add_render_modifier()`]: crate::EffectAsset::add_render_modifier
+    pub fn add_modifier_to_groups(
+        mut self,
+        context: ModifierContext,
+        modifier: Box<dyn Modifier>,
+        groups: ParticleGroupSet,
+    ) -> Self {
+        assert!(context == ModifierContext::Init || context == ModifierContext::Update);
+        assert!(modifier.context().contains(context));
+        let grouped_modifier = GroupedModifier {
+            modifier,
+            groups,
+            order: 0,
+        };
+        if context == ModifierContext::Init {
+            self.init_modifiers.push(grouped_modifier);
+        } else {
+            self.update_modifiers.push(grouped_modifier);
+        }
+        self
+    }
+
+    /// Add a modifier to the specific context, with an explicit application
+    /// order.
+    ///
+    /// Within a context, modifiers are applied in ascending `order`, and
+    /// modifiers with an equal `order` are applied in the order they were
+    /// added. All other methods adding a modifier use an order of zero. This
+    /// is useful when the result depends on the order modifiers are applied,
+    /// for example to ensure a kill modifier always applies last.
+    ///
+    /// The modifier affects all groups if `context` is
+    /// [`ModifierContext::Update`] or [`ModifierContext::Render`], and group 0
+    /// only if it's [`ModifierContext::Init`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the input `context` contains more than one context (the
+    /// bitfield contains more than 1 bit set) or no context at all (zero bit
+    /// set).
+    ///
+    /// Panics if the modifier doesn't support the context specified (that is,
+    /// `modifier.context()` returns a flag which doesn't include `context`).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::*;
+    /// # use bevy::math::Vec3;
+    /// let mut module = Module::default();
+    /// let center = module.lit(Vec3::ZERO);
+    /// let radius = module.lit(1.);
+    /// let drag = LinearDragModifier::constant(&mut module, 2.);
+    /// let kill = KillSphereModifier::new(center, radius);
+    /// let effect = EffectAsset::new(vec![256], Spawner::default(), module)
+    ///     // Always apply last, whatever the insertion order
+    ///     .add_modifier_ordered(ModifierContext::Update, Box::new(kill), 100)
+    ///     .update(drag);
+    /// ```
+    pub fn add_modifier_ordered(
+        mut self,
+        context: ModifierContext,
+        modifier: BoxedModifier,
+        order: i32,
+    ) -> Self {
+        assert!(context.bits().count_ones() == 1);
+        assert!(modifier.context().contains(context));
+        let (modifiers, groups) = if context == ModifierContext::Init {
+            (&mut self.init_modifiers, ParticleGroupSet::single(0))
+        } else if context == ModifierContext::Update {
+            (&mut self.update_modifiers, ParticleGroupSet::all())
+        } else {
+            assert!(modifier.as_render().is_some());
+            (&mut self.render_modifiers, ParticleGroupSet::all())
+        };
+        modifiers.push(GroupedModifier {
+            modifier,
+            groups,
+            order,
+        });
+        self
+    }
+
+    /// Add a render modifier to the effect.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the modifier doesn't support the render context (that is,
+    /// `modifier.context()` returns a flag which doesn't include
+    /// [`ModifierContext::Render`]).
     #[inline]
     pub fn render<M>(mut self, modifier: M) -> Self
     where
@@ -509,6 +1657,7 @@ impl EffectAsset {
         self.render_modifiers.push(GroupedModifier {
             modifier: Box::new(modifier),
             groups: ParticleGroupSet::all(),
+            order: 0,
         });
         self
     }
@@ -529,6 +1678,7 @@ impl EffectAsset {
         self.render_modifiers.push(GroupedModifier {
             modifier: Box::new(modifier),
             groups,
+            order: 0,
         });
         self
     }
@@ -545,6 +1695,7 @@ impl EffectAsset {
         self.render_modifiers.push(GroupedModifier {
             modifier: modifier.boxed_clone(),
             groups: ParticleGroupSet::all(),
+            order: 0,
         });
         self
     }
@@ -566,10 +1717,78 @@ impl EffectAsset {
         self.render_modifiers.push(GroupedModifier {
             modifier: modifier.boxed_clone(),
             groups,
+            order: 0,
         });
         self
     }
 
+    /// Compose a [`ModifierPreset`] into this effect.
+    ///
+    /// The expressions of the preset's module are appended to the module of
+    /// this effect, and the modifiers of the preset are added to this effect
+    /// after all the modifiers already present, retaining their context,
+    /// groups, and order.
+    ///
+    /// Modifiers present in the effect take precedence over the ones of the
+    /// preset: a modifier of the preset is skipped if the effect already has,
+    /// in the same context, a modifier of the same type using the same
+    /// particle attributes. For example a local [`LinearDragModifier`]
+    /// overrides the one of a preset, and a local [`SetAttributeModifier`]
+    /// overrides a preset one setting the same attribute, but not a preset
+    /// one setting a different attribute. Note that this only applies to
+    /// modifiers added before the preset.
+    ///
+    /// The properties of the preset are added to this effect too, unless the
+    /// effect already has a property with the same name, in which case the
+    /// property of the effect is retained.
+    ///
+    /// Returns an error if the combined module exceeds the maximum number of
+    /// expressions.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::*;
+    /// let mut module = Module::default();
+    /// let drag = LinearDragModifier::constant(&mut module, 2.);
+    /// let smoke = ModifierPreset::new(module).update(drag);
+    ///
+    /// let effect = EffectAsset::new(vec![256], Spawner::default(), Module::default())
+    ///     .with_preset(&smoke)
+    ///     .unwrap();
+    /// ```
+    ///
+    /// [`LinearDragModifier`]: crate::LinearDragModifier
+    /// [`SetAttributeModifier`]: crate::SetAttributeModifier
+    pub fn with_preset(mut self, preset: &ModifierPreset) -> Result<Self, ExprError> {
+        let offset = self.module.append(&preset.module)?;
+        for property in &preset.properties {
+            if !self.properties.iter().any(|p| p.name() == property.name()) {
+                self.properties.push(property.clone());
+            }
+        }
+        for (modifiers, preset_modifiers) in [
+            (&mut self.init_modifiers, &preset.init_modifiers),
+            (&mut self.update_modifiers, &preset.update_modifiers),
+            (&mut self.render_modifiers, &preset.render_modifiers),
+        ] {
+            let local_count = modifiers.len();
+            for preset_modifier in preset_modifiers {
+                let overridden = modifiers[..local_count].iter().any(|gm| {
+                    gm.modifier.as_any().type_id() == preset_modifier.modifier.as_any().type_id()
+                        && gm.modifier.attributes() == preset_modifier.modifier.attributes()
+                });
+                if overridden {
+                    continue;
+                }
+                let mut grouped_modifier = preset_modifier.clone();
+                offset_expr_handles(grouped_modifier.modifier.as_reflect_mut(), offset)?;
+                modifiers.push(grouped_modifier);
+            }
+        }
+        Ok(self)
+    }
+
     /// Get a list of all the modifiers of this effect.
     pub fn modifiers(&self) -> impl Iterator<Item = &dyn Modifier> {
         self.init_modifiers
@@ -591,10 +1810,11 @@ impl EffectAsset {
     ///
     /// This is a filtered list of all modifiers, retaining only modifiers
     /// executing in the [`ModifierContext::Init`] context.
+    /// Modifiers are returned in the order they're applied.
     ///
     /// [`ModifierContext::Init`]: crate::ModifierContext::Init
     pub fn init_modifiers(&self) -> impl Iterator<Item = &dyn Modifier> {
-        self.init_modifiers.iter().filter_map(|gm| {
+        sorted_by_order(&self.init_modifiers).filter_map(|gm| {
             if gm.modifier.context().contains(ModifierContext::Init) {
                 Some(gm.modifier.deref())
             } else {
@@ -607,10 +1827,11 @@ impl EffectAsset {
     ///
     /// This is a filtered list of all modifiers, retaining only modifiers
     /// executing in the [`ModifierContext::Update`] context.
+    /// Modifiers are returned in the order they're applied.
     ///
     /// [`ModifierContext::Update`]: crate::ModifierContext::Update
     pub fn update_modifiers(&self) -> impl Iterator<Item = &dyn Modifier> {
-        self.update_modifiers.iter().filter_map(|gm| {
+        sorted_by_order(&self.update_modifiers).filter_map(|gm| {
             if gm.modifier.context().contains(ModifierContext::Update) {
                 Some(gm.modifier.deref())
             } else {
@@ -630,7 +1851,7 @@ impl EffectAsset {
         &self,
         group_index: u32,
     ) -> impl Iterator<Item = &dyn Modifier> {
-        self.update_modifiers.iter().filter_map(move |gm| {
+        sorted_by_order(&self.update_modifiers).filter_map(move |gm| {
             if gm.groups.contains(group_index)
                 && gm.modifier.context().contains(ModifierContext::Update)
             {
@@ -641,72 +1862,583 @@ impl EffectAsset {
         })
     }
 
-    /// Get a list of all the render modifiers of this effect.
+    /// Get a list of all the render modifiers of this effect.
+    ///
+    /// This is a filtered list of all modifiers, retaining only modifiers
+    /// executing in the [`ModifierContext::Render`] context.
+    /// Modifiers are returned in the order they're applied.
+    ///
+    /// [`ModifierContext::Render`]: crate::ModifierContext::Render
+    pub fn render_modifiers(&self) -> impl Iterator<Item = &dyn RenderModifier> {
+        sorted_by_order(&self.render_modifiers).filter_map(|m| m.as_render())
+    }
+
+    /// Get a list of all the render modifiers of this effect that affect a
+    /// specific group.
+    ///
+    /// This is a filtered list of all modifiers, retaining only modifiers
+    /// executing in the [`ModifierContext::Render`] context and that affect the
+    /// given group.
+    ///
+    /// [`ModifierContext::Render`]: crate::ModifierContext::Render
+    pub fn render_modifiers_for_group(
+        &self,
+        group_index: u32,
+    ) -> impl Iterator<Item = &dyn RenderModifier> {
+        sorted_by_order(&self.render_modifiers).filter_map(move |m| {
+            if m.groups.contains(group_index) {
+                m.modifier.as_render()
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Get the handles of all the textures referenced by the modifiers of this
+    /// effect.
+    ///
+    /// For an effect loaded from an `.effect` file, those are the handles the
+    /// asset loader resolved from the texture paths serialized in the file.
+    /// They're the dependencies of the asset, which Bevy waits for before
+    /// reporting the effect as fully loaded, and tracks for hot-reloading.
+    pub fn textures(&self) -> impl Iterator<Item = &Handle<Image>> {
+        let render_textures = self.render_modifiers.iter().filter_map(|gm| {
+            let modifier = gm.modifier.as_any();
+            modifier
+                .downcast_ref::<ParticleTextureModifier>()
+                .map(|modifier| &modifier.texture)
+                .or_else(|| {
+                    modifier
+                        .downcast_ref::<DissolveModifier>()
+                        .map(|modifier| &modifier.texture)
+                })
+        });
+        let update_textures = self.update_modifiers.iter().filter_map(|gm| {
+            gm.modifier
+                .as_any()
+                .downcast_ref::<CollisionHeightfieldModifier>()
+                .map(|modifier| &modifier.heightmap)
+        });
+        render_textures
+            .chain(update_textures)
+            .filter(|texture| **texture != Handle::default())
+    }
+
+    /// Build the particle layout of the asset based on its modifiers.
+    ///
+    /// This method calculates the particle layout of the effect based on the
+    /// currently existing particles, and return it as a newly allocated
+    /// [`ParticleLayout`] object.
+    ///
+    /// The layout is deterministic: it only depends on the set of attributes
+    /// required by the modifiers, and not on the order of those modifiers, so
+    /// the same asset always produces the same byte layout.
+    pub fn particle_layout(&self) -> ParticleLayout {
+        // Build the list of unique attributes required for all modifiers, in a
+        // stable order
+        let mut attrs: Vec<Attribute> = self
+            .modifiers()
+            .flat_map(|modifier| modifier.attributes().iter().copied())
+            .collect();
+        attrs.sort_unstable_by_key(|attr| attr.name());
+        attrs.dedup();
+
+        // Build the layout
+        let mut layout = ParticleLayout::new();
+        for attr in attrs {
+            layout = layout.append(attr);
+        }
+        layout.build()
+    }
+
+    /// Estimate the maximum lifetime of the particles of this effect, in
+    /// seconds.
+    ///
+    /// This returns the [`max_lifetime`] hint if set. Otherwise the estimate
+    /// is derived from the init modifier setting the [`Attribute::LIFETIME`],
+    /// if its value expression is statically known (for example a literal, or
+    /// a random value between literals). Returns `None` if the particles have
+    /// no lifetime, or if the lifetime cannot be determined, including when an
+    /// update modifier overwrites it.
+    ///
+    /// [`max_lifetime`]: crate::EffectAsset::max_lifetime
+    pub fn estimated_max_lifetime(&self) -> Option<f32> {
+        if let Some(max_lifetime) = self.max_lifetime {
+            return Some(max_lifetime);
+        }
+        let set_lifetime = |m: &dyn Modifier| {
+            m.as_any()
+                .downcast_ref::<SetAttributeModifier>()
+                .filter(|m| m.attribute == Attribute::LIFETIME)
+                .map(|m| m.value)
+        };
+        if self.update_modifiers().any(|m| set_lifetime(m).is_some()) {
+            return None;
+        }
+        // The last init modifier writing the lifetime wins
+        let lifetime = self.init_modifiers().filter_map(set_lifetime).last()?;
+        self.module.scalar_upper_bound(lifetime)
+    }
+
+    /// Estimate the maximum size of the particles of this effect, in world
+    /// units.
+    ///
+    /// The estimate is derived from the init modifier setting the
+    /// [`Attribute::SIZE`], and from the [`SetSizeModifier`] and
+    /// [`SizeOverLifetimeModifier`] or [`SizeOverDistanceModifier`] render
+    /// modifiers, whichever comes last.
+    /// Returns the default size of `1.0` if none of those is present, and
+    /// `None` if the size cannot be determined or is expressed in screen
+    /// space.
+    pub(crate) fn estimated_max_size(&self) -> Option<f32> {
+        let mut size = 1.;
+        for modifier in self.init_modifiers() {
+            if let Some(m) = modifier
+                .as_any()
+                .downcast_ref::<SetAttributeModifier>()
+                .filter(|m| m.attribute == Attribute::SIZE)
+            {
+                let (lo, hi) = self.module.scalar_bounds(m.value)?;
+                size = lo.abs().max(hi.abs());
+            } else if modifier.attributes().contains(&Attribute::SIZE)
+                || modifier.attributes().contains(&Attribute::SIZE2)
+            {
+                return None;
+            }
+        }
+        for modifier in self.render_modifiers() {
+            let any = modifier.as_any();
+            if let Some(m) = any.downcast_ref::<SetSizeModifier>() {
+                size = match m.size {
+                    CpuValue::Single(v) => v.max_element(),
+                    CpuValue::Uniform((a, b)) => a.max(b).max_element(),
+                };
+            } else if let Some(m) = any.downcast_ref::<SizeOverLifetimeModifier>() {
+                if m.screen_space_size
+                    || !m.easing.is_bounded()
+                    || !m.gradient.easing().is_bounded()
+                {
+                    return None;
+                }
+                size = m
+                    .gradient
+                    .keys()
+                    .iter()
+                    .fold(0., |acc: f32, key| acc.max(key.value.max_element()));
+            } else if let Some(m) = any.downcast_ref::<SizeOverDistanceModifier>() {
+                if !m.gradient.easing().is_bounded() {
+                    return None;
+                }
+                size = m
+                    .gradient
+                    .keys()
+                    .iter()
+                    .fold(0., |acc: f32, key| acc.max(key.value.max_element()));
+            }
+        }
+        Some(size)
+    }
+
+    /// Estimate the bounds of the particles of this effect, in the local space
+    /// of the emitter.
+    ///
+    /// This is a CPU-side analytical approximation, not an exact bound. It
+    /// combines the bounds of the init shape the particles are spawned on, the
+    /// maximum initial speed of the particles, and the sum of the constant
+    /// accelerations applied to them, integrated over the maximum particle
+    /// lifetime. If the particle size is set by a [`SetAttributeModifier`]
+    /// writing [`Attribute::SIZE`], the bounds are also grown by half that
+    /// size. Finally a safety margin of 10% of the extents is added on each
+    /// side.
+    ///
+    /// The estimate is only available when all the quantities involved are
+    /// statically known from the module, that is are literals or random values
+    /// between literals, except for the lifetime which can also be provided
+    /// with [`with_max_lifetime()`]. Returns `None` if the particles have no
+    /// lifetime, if any of those expressions depend on a property or an
+    /// attribute, or if an init or update modifier writes the particle position
+    /// or velocity in a way this method doesn't know how to bound (for example
+    /// a force field, or a custom code modifier). Linear drag and kill
+    /// modifiers are assumed not to extend the bounds.
+    ///
+    /// The returned [`Aabb`] can be inserted as a component on the
+    /// [`ParticleEffect`] entity to let Bevy's frustum culling skip the effect
+    /// when it's not in view. Because the estimate is expressed in the local
+    /// space of the emitter, it's only valid for effects simulated in
+    /// [`SimulationSpace::Local`], or for emitters which don't move. Since the
+    /// estimate is approximate, it can always be overridden by authoring an
+    /// [`Aabb`] manually.
+    ///
+    /// [`ParticleEffect`]: crate::ParticleEffect
+    /// [`with_max_lifetime()`]: crate::EffectAsset::with_max_lifetime
+    pub fn estimate_aabb(&self) -> Option<Aabb> {
+        let lifetime = self.estimated_max_lifetime()?;
+        let module = &self.module;
+        let abs_max = |expr: ExprHandle| {
+            module
+                .scalar_bounds(expr)
+                .map(|(lo, hi)| lo.abs().max(hi.abs()))
+        };
+
+        // Initial position and speed; the last modifier writing them wins
+        let mut position = (Vec3::ZERO, Vec3::ZERO);
+        let mut speed = 0.;
+        let mut half_size = 0.;
+        for modifier in self.init_modifiers() {
+            let any = modifier.as_any();
+            let set_attr = |attr: Attribute| {
+                any.downcast_ref::<SetAttributeModifier>()
+                    .filter(|m| m.attribute == attr)
+                    .map(|m| m.value)
+            };
+            if let Some(m) = any.downcast_ref::<SetPositionSphereModifier>() {
+                let (c0, c1) = module.vec3_bounds(m.center)?;
+                let r = Vec3::splat(abs_max(m.radius)?);
+                position = (c0 - r, c1 + r);
+            } else if let Some(m) = any.downcast_ref::<SetPositionCircleModifier>() {
+                let (c0, c1) = module.vec3_bounds(m.center)?;
+                let r = Vec3::splat(abs_max(m.radius)?);
+                position = (c0 - r, c1 + r);
+            } else if let Some(m) = any.downcast_ref::<SetPositionBoxModifier>() {
+                let (c0, c1) = module.vec3_bounds(m.center)?;
+                let (h0, h1) = module.vec3_bounds(m.half_extents)?;
+                let mut h = h0.abs().max(h1.abs());
+                if m.orientation.is_some() {
+                    // Any rotation of the box fits in its bounding sphere
+                    h = Vec3::splat(h.length());
+                }
+                position = (c0 - h, c1 + h);
+            } else if let Some(m) = any.downcast_ref::<SetPositionCone3dModifier>() {
+                let (h0, h1) = module.scalar_bounds(m.height)?;
+                let r = abs_max(m.base_radius)?.max(abs_max(m.top_radius)?);
+                position = if m.orientation.is_some() {
+                    // Any rotation of the cone around its origin fits in this sphere
+                    let r = Vec3::splat(Vec2::new(h0.abs().max(h1.abs()), r).length());
+                    (-r, r)
+                } else {
+                    (Vec3::new(-r, h0.min(0.), -r), Vec3::new(r, h1.max(0.), r))
+                };
+            } else if let Some(m) = any.downcast_ref::<SetPositionPathModifier>() {
+                if m.points.is_empty() {
+                    return None;
+                }
+                position = m.points.iter().fold((Vec3::MAX, Vec3::MIN), |(lo, hi), p| {
+                    (lo.min(*p), hi.max(*p))
+                });
+            } else if let Some(m) = any.downcast_ref::<SetVelocitySphereModifier>() {
+                speed = abs_max(m.speed)?;
+            } else if let Some(m) = any.downcast_ref::<SetVelocityCircleModifier>() {
+                speed = abs_max(m.speed)?;
+            } else if let Some(m) = any.downcast_ref::<SetVelocityTangentModifier>() {
+                speed = abs_max(m.speed)?;
+            } else if let Some(value) = set_attr(Attribute::POSITION) {
+                position = module.vec3_bounds(value)?;
+            } else if let Some(value) = set_attr(Attribute::VELOCITY) {
+                let (v0, v1) = module.vec3_bounds(value)?;
+                speed = v0.abs().max(v1.abs()).length();
+            } else if let Some(value) = set_attr(Attribute::SIZE) {
+                half_size = abs_max(value)? / 2.;
+            } else if modifier
+                .attributes()
+                .iter()
+                .any(|attr| *attr == Attribute::POSITION || *attr == Attribute::VELOCITY)
+            {
+                return None;
+            }
+        }
+
+        // Constant accelerations
+        let mut accel = (Vec3::ZERO, Vec3::ZERO);
+        for modifier in self.update_modifiers() {
+            let any = modifier.as_any();
+            if let Some(m) = any.downcast_ref::<AccelModifier>() {
+                if m.respect_mass() {
+                    return None;
+                }
+                let (a0, a1) = module.vec3_bounds(m.accel())?;
+                accel = (accel.0 + a0, accel.1 + a1);
+            } else if any.is::<LinearDragModifier>()
+                || any.is::<KillSphereModifier>()
+                || any.is::<KillAabbModifier>()
+            {
+                // Those only slow down or remove particles
+            } else if modifier
+                .attributes()
+                .iter()
+                .any(|attr| *attr == Attribute::POSITION || *attr == Attribute::VELOCITY)
+            {
+                return None;
+            }
+        }
+
+        // Integrate the motion over the lifetime: p(t) = p0 + v0 * t + a * t^2 / 2
+        let t = lifetime.max(0.);
+        let reach = Vec3::splat(speed * t + half_size);
+        let min = position.0 - reach + accel.0.min(Vec3::ZERO) * (t * t / 2.);
+        let max = position.1 + reach + accel.1.max(Vec3::ZERO) * (t * t / 2.);
+        let margin = (max - min) * 0.1;
+        Some(Aabb::from_min_max(min - margin, max + margin))
+    }
+
+    /// Get the 2D bounds of the effect, in the local space of the emitter.
+    ///
+    /// This returns the explicit bounds set with [`with_bounds_2d()`] if any,
+    /// or otherwise the projection onto the XY plane of the estimate returned
+    /// by [`estimate_aabb()`].
+    ///
+    /// [`with_bounds_2d()`]: crate::EffectAsset::with_bounds_2d
+    /// [`estimate_aabb()`]: crate::EffectAsset::estimate_aabb
+    pub fn estimated_bounds_2d(&self) -> Option<Rect> {
+        if let Some(bounds) = self.bounds_2d {
+            return Some(bounds);
+        }
+        let aabb = self.estimate_aabb()?;
+        Some(Rect::from_corners(
+            Vec3::from(aabb.min()).truncate(),
+            Vec3::from(aabb.max()).truncate(),
+        ))
+    }
+
+    /// Build the property layout of the asset based on its properties.
+    ///
+    /// This method calculates the property layout of the effect based on the
+    /// currently existing properties, and return it as a newly allocated
+    /// [`PropertyLayout`] object.
+    pub fn property_layout(&self) -> PropertyLayout {
+        PropertyLayout::new(self.properties.iter())
+    }
+}
+
+/// Reusable set of modifiers which can be composed into multiple effects.
+///
+/// A preset bundles some modifiers together with the [`Module`] containing the
+/// expressions they use, so that a common behavior (for example a standard
+/// smoke drag, or a standard fire color ramp) can be defined once and added
+/// to any number of [`EffectAsset`] with [`EffectAsset::with_preset()`]. This
+/// centralizes the tuning of that behavior. Presets are serializable, so can
+/// be stored alongside effects.
+///
+/// See [`EffectAsset::with_preset()`] for the rules applied when a modifier of
+/// the preset conflicts with one of the effect.
+#[derive(Default, Clone, Serialize, Deserialize)]
+pub struct ModifierPreset {
+    /// Display name of the preset.
+    pub name: String,
+    /// Module containing all the expressions used by the modifiers.
+    module: Module,
+    /// Properties used by the modifiers.
+    #[serde(default)]
+    properties: Vec<Property>,
+    /// Init modifiers of the preset.
+    init_modifiers: Vec<GroupedModifier>,
+    /// Update modifiers of the preset.
+    update_modifiers: Vec<GroupedModifier>,
+    /// Render modifiers of the preset.
+    render_modifiers: Vec<GroupedModifier>,
+}
+
+impl ModifierPreset {
+    /// Create a new preset with the given module.
+    ///
+    /// The module must contain all the expressions used by the modifiers which
+    /// are later added to the preset.
+    pub fn new(module: Module) -> Self {
+        Self {
+            module,
+            ..default()
+        }
+    }
+
+    /// Set the preset name.
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    /// Get the module containing the expressions of this preset.
+    pub fn module(&self) -> &Module {
+        &self.module
+    }
+
+    /// Add a new property to the preset.
+    ///
+    /// The property is added to the effects the preset is composed into. See
+    /// [`EffectAsset::with_preset()`] for details.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a property with the same name already exists.
+    pub fn with_property(mut self, name: impl Into<String>, default_value: Value) -> Self {
+        let name = name.into();
+        assert!(!self.properties.iter().any(|p| p.name() == name));
+        self.properties.push(Property::new(name, default_value));
+        self
+    }
+
+    /// Get the list of existing properties.
+    pub fn properties(&self) -> &[Property] {
+        &self.properties
+    }
+
+    /// Add an init modifier to the preset.
     ///
-    /// This is a filtered list of all modifiers, retaining only modifiers
-    /// executing in the [`ModifierContext::Render`] context.
+    /// # Panics
     ///
-    /// [`ModifierContext::Render`]: crate::ModifierContext::Render
-    pub fn render_modifiers(&self) -> impl Iterator<Item = &dyn RenderModifier> {
-        self.render_modifiers.iter().filter_map(|m| m.as_render())
+    /// Panics if the modifier doesn't support the init context.
+    pub fn init<M>(mut self, modifier: M) -> Self
+    where
+        M: Modifier + Send + Sync,
+    {
+        assert!(modifier.context().contains(ModifierContext::Init));
+        self.init_modifiers.push(GroupedModifier {
+            modifier: Box::new(modifier),
+            groups: ParticleGroupSet::single(0),
+            order: 0,
+        });
+        self
     }
 
-    /// Get a list of all the render modifiers of this effect that affect a
-    /// specific group.
+    /// Add an update modifier to the preset.
     ///
-    /// This is a filtered list of all modifiers, retaining only modifiers
-    /// executing in the [`ModifierContext::Render`] context and that affect the
-    /// given group.
+    /// # Panics
     ///
-    /// [`ModifierContext::Render`]: crate::ModifierContext::Render
-    pub fn render_modifiers_for_group(
-        &self,
-        group_index: u32,
-    ) -> impl Iterator<Item = &dyn RenderModifier> {
-        self.render_modifiers.iter().filter_map(move |m| {
-            if m.groups.contains(group_index) {
-                m.modifier.as_render()
-            } else {
-                None
-            }
-        })
+    /// Panics if the modifier doesn't support the update context.
+    pub fn update<M>(mut self, modifier: M) -> Self
+    where
+        M: Modifier + Send + Sync,
+    {
+        assert!(modifier.context().contains(ModifierContext::Update));
+        self.update_modifiers.push(GroupedModifier {
+            modifier: Box::new(modifier),
+            groups: ParticleGroupSet::all(),
+            order: 0,
+        });
+        self
     }
 
-    /// Build the particle layout of the asset based on its modifiers.
+    /// Add a render modifier to the preset.
     ///
-    /// This method calculates the particle layout of the effect based on the
-    /// currently existing particles, and return it as a newly allocated
-    /// [`ParticleLayout`] object.
-    pub fn particle_layout(&self) -> ParticleLayout {
-        // Build the set of unique attributes required for all modifiers
-        let mut set = HashSet::new();
-        for modifier in self.modifiers() {
-            for &attr in modifier.attributes() {
-                set.insert(attr);
-            }
+    /// # Panics
+    ///
+    /// Panics if the modifier doesn't support the render context.
+    pub fn render<M>(mut self, modifier: M) -> Self
+    where
+        M: RenderModifier + Send + Sync,
+    {
+        assert!(modifier.context().contains(ModifierContext::Render));
+        self.render_modifiers.push(GroupedModifier {
+            modifier: Box::new(modifier),
+            groups: ParticleGroupSet::all(),
+            order: 0,
+        });
+        self
+    }
+}
+
+impl Asset for EffectAsset {}
+
+impl VisitAssetDependencies for EffectAsset {
+    fn visit_dependencies(&self, visit: &mut impl FnMut(UntypedAssetId)) {
+        for texture in self.textures() {
+            visit(texture.id().untyped());
         }
+    }
+}
 
-        // Build the layout
-        let mut layout = ParticleLayout::new();
-        for attr in set {
-            layout = layout.append(attr);
+/// Iterate over a list of modifiers in the order they're applied.
+///
+/// This is a stable sort by [`GroupedModifier::order`], so modifiers with an
+/// equal order retain their insertion order.
+fn sorted_by_order(modifiers: &[GroupedModifier]) -> impl Iterator<Item = &GroupedModifier> {
+    let mut sorted: Vec<_> = modifiers.iter().collect();
+    sorted.sort_by_key(|gm| gm.order);
+    sorted.into_iter()
+}
+
+/// Check whether a modifier assigns the [`Attribute::LIFETIME`] of the
+/// particles.
+///
+/// Many modifiers only read the lifetime, so this can't use the list of
+/// [`Modifier::attributes()`]. A [`CustomCodeModifier`] is assumed to write
+/// all the attributes it declares.
+fn writes_lifetime(modifier: &dyn Modifier) -> bool {
+    let any = modifier.as_any();
+    if let Some(m) = any.downcast_ref::<SetAttributeModifier>() {
+        m.attribute == Attribute::LIFETIME
+    } else if let Some(m) = any.downcast_ref::<ConditionalModifier>() {
+        writes_lifetime(m.inner.deref())
+    } else if let Some(m) = any.downcast_ref::<CustomCodeModifier>() {
+        m.attributes.contains(&Attribute::LIFETIME)
+    } else {
+        false
+    }
+}
+
+fn serialize_format_version<S: serde::Serializer>(_: &u32, s: S) -> Result<S::Ok, S::Error> {
+    s.serialize_u32(EffectAsset::FORMAT_VERSION)
+}
+
+fn serialize_bounds_2d<S: serde::Serializer>(
+    bounds: &Option<Rect>,
+    s: S,
+) -> Result<S::Ok, S::Error> {
+    bounds.map(|rect| (rect.min, rect.max)).serialize(s)
+}
+
+fn deserialize_bounds_2d<'de, D: serde::Deserializer<'de>>(d: D) -> Result<Option<Rect>, D::Error> {
+    Ok(Option::<(Vec2, Vec2)>::deserialize(d)?.map(|(min, max)| Rect { min, max }))
+}
+
+/// Minimal view of a serialized [`EffectAsset`], used to read its format
+/// version before deserializing the actual asset.
+#[derive(Deserialize)]
+#[serde(rename = "EffectAsset")]
+struct EffectAssetHeader {
+    #[serde(default)]
+    format_version: u32,
+}
+
+impl EffectAsset {
+    /// Deserialize an asset from its RON representation, migrating it from
+    /// older format versions if needed.
+    fn from_ron_versioned(bytes: &[u8]) -> Result<Self, EffectAssetLoaderError> {
+        let version = ron::de::from_bytes::<EffectAssetHeader>(bytes)?.format_version;
+        if version > Self::FORMAT_VERSION {
+            return Err(EffectAssetLoaderError::UnsupportedFormatVersion(version));
         }
-        layout.build()
+        let mut asset = ron::de::from_bytes::<EffectAsset>(bytes)?;
+        asset.migrate(version);
+        Ok(asset)
     }
 
-    /// Build the property layout of the asset based on its properties.
+    /// Migrate an asset deserialized from an older format version to the
+    /// current [`FORMAT_VERSION`].
     ///
-    /// This method calculates the property layout of the effect based on the
-    /// currently existing properties, and return it as a newly allocated
-    /// [`PropertyLayout`] object.
-    pub fn property_layout(&self) -> PropertyLayout {
-        PropertyLayout::new(self.properties.iter())
+    /// Each step upgrades the asset from one version to the next, so that
+    /// assets of any older version go through all intermediate migrations.
+    ///
+    /// [`FORMAT_VERSION`]: EffectAsset::FORMAT_VERSION
+    fn migrate(&mut self, from_version: u32) {
+        for version in from_version..Self::FORMAT_VERSION {
+            match version {
+                // Unversioned assets. All fields added since then have a serde default, so
+                // there's nothing to convert.
+                0 => {}
+                _ => unreachable!(),
+            }
+        }
+        self.format_version = Self::FORMAT_VERSION;
     }
 }
 
 /// Asset loader for [`EffectAsset`].
 ///
 /// Effet assets take the `.effect` extension.
+///
+/// The loader supports assets serialized with any format version up to
+/// [`EffectAsset::FORMAT_VERSION`], and migrates older assets to the current
+/// format. Assets with a newer format version fail to load with
+/// [`EffectAssetLoaderError::UnsupportedFormatVersion`].
 #[derive(Default)]
 pub struct EffectAssetLoader;
 
@@ -720,6 +2452,14 @@ pub enum EffectAssetLoaderError {
     /// Error during RON format parsing.
     #[error("A RON format error occurred during loading of a particle effect")]
     Ron(#[from] ron::error::SpannedError),
+
+    /// The asset was serialized with a newer format version than this version
+    /// of the crate supports.
+    #[error(
+        "Unsupported particle effect format version {0}, the latest supported version is {}",
+        EffectAsset::FORMAT_VERSION
+    )]
+    UnsupportedFormatVersion(u32),
 }
 
 impl AssetLoader for EffectAssetLoader {
@@ -733,12 +2473,45 @@ impl AssetLoader for EffectAssetLoader {
         &'a self,
         reader: &'a mut Reader,
         _settings: &'a Self::Settings,
-        _load_context: &'a mut LoadContext,
+        load_context: &'a mut LoadContext,
     ) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
         Box::pin(async move {
             let mut bytes = Vec::new();
             reader.read_to_end(&mut bytes).await?;
-            let custom_asset = ron::de::from_bytes::<EffectAsset>(&bytes)?;
+            let mut custom_asset = EffectAsset::from_ron_versioned(&bytes)?;
+
+            // Resolve the asset references serialized by path into actual handles
+            for grouped_modifier in &mut custom_asset.render_modifiers {
+                if let Some(modifier) = grouped_modifier
+                    .modifier
+                    .as_any_mut()
+                    .downcast_mut::<ParticleTextureModifier>()
+                {
+                    if let Some(path) = &modifier.texture_path {
+                        modifier.texture = load_context.load(path.clone());
+                    }
+                } else if let Some(modifier) = grouped_modifier
+                    .modifier
+                    .as_any_mut()
+                    .downcast_mut::<DissolveModifier>()
+                {
+                    if let Some(path) = &modifier.texture_path {
+                        modifier.texture = load_context.load(path.clone());
+                    }
+                }
+            }
+            for grouped_modifier in &mut custom_asset.update_modifiers {
+                if let Some(modifier) = grouped_modifier
+                    .modifier
+                    .as_any_mut()
+                    .downcast_mut::<CollisionHeightfieldModifier>()
+                {
+                    if let Some(path) = &modifier.heightmap_path {
+                        modifier.heightmap = load_context.load(path.clone());
+                    }
+                }
+            }
+
             Ok(custom_asset)
         })
     }
@@ -802,15 +2575,473 @@ mod tests {
         }
 
         {
-            let effect = EffectAsset::default().add_render_modifier(Box::new(SetColorModifier {
-                color: CpuValue::Single(Vec4::ONE),
-            }));
+            let effect = EffectAsset::default()
+                .add_render_modifier(Box::new(SetColorModifier::new(CpuValue::Single(Vec4::ONE))));
             assert_eq!(effect.modifiers().count(), 1);
             let m = effect.modifiers().next().unwrap();
             assert!(m.context().contains(ModifierContext::Render));
         }
     }
 
+    #[test]
+    fn modifier_order() {
+        let mut module = Module::default();
+        let drag = LinearDragModifier::constant(&mut module, 3.5);
+        let accel = AccelModifier::constant(&mut module, Vec3::Y);
+        let late_drag = LinearDragModifier::constant(&mut module, 1.5);
+
+        let generate_update_code = |effect: &EffectAsset| {
+            let mut module = effect.module().clone();
+            let property_layout = PropertyLayout::default();
+            let particle_layout = effect.particle_layout();
+            let mut context =
+                ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+            for m in effect.update_modifiers() {
+                m.apply(&mut module, &mut context).unwrap();
+            }
+            context.main_code
+        };
+
+        // Insertion order is used when all modifiers have the same order
+        let effect = EffectAsset::new(vec![32], Spawner::default(), module.clone())
+            .update(drag)
+            .update(accel);
+        let drag_then_accel = generate_update_code(&effect);
+
+        // Explicit order overrides insertion order
+        let effect = EffectAsset::new(vec![32], Spawner::default(), module.clone())
+            .add_modifier_ordered(ModifierContext::Update, Box::new(drag), 1)
+            .update(accel);
+        let accel_then_drag = generate_update_code(&effect);
+        assert_ne!(drag_then_accel, accel_then_drag);
+
+        // Lower orders are applied first
+        let effect = EffectAsset::new(vec![32], Spawner::default(), module.clone())
+            .add_modifier_ordered(ModifierContext::Update, Box::new(accel), -1)
+            .update(drag);
+        assert_eq!(accel_then_drag, generate_update_code(&effect));
+
+        // Sort is stable; modifiers with an equal explicit order keep their
+        // insertion order, even when sorted before another modifier
+        let effect = EffectAsset::new(vec![32], Spawner::default(), module.clone())
+            .update(drag)
+            .update(accel)
+            .update(late_drag);
+        let drag_accel_late = generate_update_code(&effect);
+        let effect = EffectAsset::new(vec![32], Spawner::default(), module.clone())
+            .update(accel)
+            .update(drag)
+            .update(late_drag);
+        let accel_drag_late = generate_update_code(&effect);
+        assert_ne!(drag_accel_late, accel_drag_late);
+        let effect = EffectAsset::new(vec![32], Spawner::default(), module.clone())
+            .add_modifier_ordered(ModifierContext::Update, Box::new(late_drag), 3)
+            .add_modifier_ordered(ModifierContext::Update, Box::new(drag), 2)
+            .add_modifier_ordered(ModifierContext::Update, Box::new(accel), 2);
+        assert_eq!(drag_accel_late, generate_update_code(&effect));
+        let effect = EffectAsset::new(vec![32], Spawner::default(), module)
+            .add_modifier_ordered(ModifierContext::Update, Box::new(late_drag), 3)
+            .add_modifier_ordered(ModifierContext::Update, Box::new(accel), 2)
+            .add_modifier_ordered(ModifierContext::Update, Box::new(drag), 2);
+        assert_eq!(accel_drag_late, generate_update_code(&effect));
+    }
+
+    #[test]
+    fn textures() {
+        let texture = Handle::<Image>::weak_from_u128(0x1234);
+        let heightmap = Handle::<Image>::weak_from_u128(0x5678);
+        let mut module = Module::default();
+        let origin = module.lit(Vec3::ZERO);
+        let extents = module.lit(Vec2::ONE);
+        let height_scale = module.lit(1.);
+        let collide = CollisionHeightfieldModifier::new(
+            &mut module,
+            heightmap.clone(),
+            origin,
+            extents,
+            height_scale,
+        );
+        let effect = EffectAsset::new(vec![32], Spawner::rate(1.0.into()), module)
+            .update(collide)
+            .render(ParticleTextureModifier::default())
+            .render(ParticleTextureModifier {
+                texture: texture.clone(),
+                ..default()
+            });
+
+        // Only modifiers with an actual texture are reported
+        let textures: Vec<_> = effect.textures().collect();
+        assert_eq!(textures, vec![&texture, &heightmap]);
+
+        let mut dependencies = vec![];
+        effect.visit_dependencies(&mut |id| dependencies.push(id));
+        assert_eq!(
+            dependencies,
+            vec![texture.id().untyped(), heightmap.id().untyped()]
+        );
+    }
+
+    #[test]
+    fn preset() {
+        let mut module = Module::default();
+        let lifetime = module.lit(2.);
+        let size = module.lit(5.);
+        let init_lifetime = SetAttributeModifier::new(Attribute::LIFETIME, lifetime);
+        let init_pos = SetAttributeModifier::new(Attribute::POSITION, size);
+        let drag = LinearDragModifier::new(size);
+        let accel = AccelModifier::constant(&mut module, Vec3::Y);
+        let wind = ConditionalModifier::via_property(&mut module, "wind", accel);
+        let preset = ModifierPreset::new(module)
+            .with_name("preset")
+            .with_property("wind", 1u32.into())
+            .with_property("scale", 2.0.into())
+            .init(init_lifetime)
+            .init(init_pos)
+            .update(drag)
+            .update(wind);
+
+        let mut module = Module::default();
+        let pos = module.lit(Vec3::ONE);
+        let init_pos = SetAttributeModifier::new(Attribute::POSITION, pos);
+        let drag = LinearDragModifier::constant(&mut module, 3.);
+        let effect = EffectAsset::new(vec![32], Spawner::default(), module)
+            .with_property("scale", 3.0.into())
+            .init(init_pos)
+            .update(drag)
+            .with_preset(&preset)
+            .unwrap();
+
+        // Local modifiers override the preset ones using the same attributes
+        assert_eq!(effect.init_modifiers().count(), 2);
+        assert_eq!(effect.update_modifiers().count(), 2);
+
+        // Properties of the preset are added, unless already present
+        assert_eq!(effect.properties().len(), 2);
+        assert_eq!(effect.properties()[0].default_value(), &Value::from(3.));
+        assert_eq!(effect.properties()[1].name(), "wind");
+
+        // The handles of the modifier nested in a ConditionalModifier are remapped too
+        let wind = effect
+            .update_modifiers()
+            .find_map(|m| m.as_any().downcast_ref::<ConditionalModifier>())
+            .unwrap();
+        let accel = wind.inner.as_any().downcast_ref::<AccelModifier>().unwrap();
+        assert_eq!(
+            effect.module().get(accel.accel()),
+            Some(&Expr::Literal(LiteralExpr::new(Vec3::Y)))
+        );
+
+        // Expressions of the preset were appended and remapped
+        let init_lifetime = effect
+            .init_modifiers()
+            .filter_map(|m| m.as_any().downcast_ref::<SetAttributeModifier>())
+            .find(|m| m.attribute == Attribute::LIFETIME)
+            .unwrap();
+        assert_eq!(
+            effect.module().get(init_lifetime.value),
+            Some(&Expr::Literal(LiteralExpr::new(2.)))
+        );
+    }
+
+    #[test]
+    fn validate() {
+        let mut module = Module::default();
+        let lifetime = module.lit(2.);
+
+        let effect = EffectAsset::new(vec![256], Spawner::default(), module);
+        assert_eq!(effect.validate(), vec![EffectWarning::MissingLifetime]);
+
+        // Without motion integration, particles not dying is likely intended
+        let effect = effect.with_motion_integration(MotionIntegration::None);
+        assert!(effect.validate().is_empty());
+
+        // Modifiers only reading the lifetime don't count
+        let effect = effect
+            .with_motion_integration(MotionIntegration::PostUpdate)
+            .update(CloneModifier::new(1.0, 0).with_emit_age_range(0.2, 0.8));
+        assert_eq!(effect.validate(), vec![EffectWarning::MissingLifetime]);
+
+        let effect = effect.init(SetAttributeModifier::new(Attribute::LIFETIME, lifetime));
+        assert!(effect.validate().is_empty());
+
+        let effect = effect.with_spawner(Spawner::rate(5.0.into()).with_group(1));
+        assert_eq!(
+            effect.validate(),
+            vec![EffectWarning::InvalidSpawnerGroup(1)]
+        );
+    }
+
+    #[test]
+    fn color_space() {
+        let color = Vec4::new(0.5, 0.02, 1.5, 0.25);
+        assert_eq!(ColorSpace::Linear.to_linear(color), color);
+
+        // Must match Bevy's own sRGB to linear conversion
+        let srgb = ColorSpace::Srgb.to_linear(color);
+        let bevy: Vec4 = bevy::render::color::Color::rgba(color.x, color.y, color.z, color.w)
+            .as_linear_rgba_f32()
+            .into();
+        assert!(srgb.abs_diff_eq(bevy, 1e-5));
+        assert_eq!(srgb.w, color.w);
+
+        let effect = EffectAsset::default();
+        assert_eq!(effect.color_space, ColorSpace::Linear);
+        let effect = effect.with_color_space(ColorSpace::Srgb);
+        assert_eq!(effect.color_space, ColorSpace::Srgb);
+    }
+
+    #[test]
+    fn estimate_aabb() {
+        let mut module = Module::default();
+        let init_pos = SetPositionSphereModifier {
+            center: module.lit(Vec3::ZERO),
+            radius: module.lit(1.),
+            dimension: ShapeDimension::Volume,
+            thickness: None,
+        };
+        let init_vel = SetVelocitySphereModifier {
+            center: module.lit(Vec3::ZERO),
+            speed: module.lit(2.),
+        };
+        let lifetime = module.lit(1.);
+        let init_lifetime = SetAttributeModifier::new(Attribute::LIFETIME, lifetime);
+        let accel = AccelModifier::constant(&mut module, Vec3::new(0., -10., 0.));
+        let effect = EffectAsset::new(vec![256], Spawner::rate(30.0.into()), module)
+            .init(init_pos)
+            .init(init_vel)
+            .init(init_lifetime)
+            .update(accel);
+
+        // x/z in [-1-2, 1+2], y in [-1-2-10/2, 1+2], plus 10% margin
+        let aabb = effect.estimate_aabb().unwrap();
+        let min: Vec3 = aabb.min().into();
+        let max: Vec3 = aabb.max().into();
+        assert!(min.abs_diff_eq(Vec3::new(-3.6, -9.1, -3.6), 1e-5));
+        assert!(max.abs_diff_eq(Vec3::new(3.6, 4.1, 3.6), 1e-5));
+
+        // Unknown lifetime
+        let mut module = Module::default();
+        let init_pos = SetAttributeModifier::new(Attribute::POSITION, module.lit(Vec3::ONE));
+        let effect = EffectAsset::new(vec![256], Spawner::rate(30.0.into()), module).init(init_pos);
+        assert!(effect.estimate_aabb().is_none());
+
+        // Acceleration depending on a property
+        let mut module = Module::default();
+        let init_lifetime = SetAttributeModifier::new(Attribute::LIFETIME, module.lit(1.));
+        let accel = AccelModifier::via_property(&mut module, "accel");
+        let effect = EffectAsset::new(vec![256], Spawner::rate(30.0.into()), module)
+            .with_property("accel", Vec3::ZERO.into())
+            .init(init_lifetime)
+            .update(accel);
+        assert!(effect.estimate_aabb().is_none());
+    }
+
+    #[test]
+    fn max_lifetime() {
+        // Lifetime driven by a property can't be estimated
+        let mut module = Module::default();
+        let lifetime = module.prop("lifetime");
+        let init_lifetime = SetAttributeModifier::new(Attribute::LIFETIME, lifetime);
+        let effect = EffectAsset::new(vec![256], Spawner::rate(30.0.into()), module)
+            .with_property("lifetime", 2.0.into())
+            .with_prewarm_full_lifetime(true)
+            .init(init_lifetime);
+        assert_eq!(effect.estimated_max_lifetime(), None);
+        assert_eq!(effect.warmup_duration(), 0.);
+
+        // The hint provides it
+        let effect = effect.with_max_lifetime(3.);
+        assert_eq!(effect.estimated_max_lifetime(), Some(3.));
+        assert_eq!(effect.warmup_duration(), 3.);
+
+        // The hint takes precedence over the derived value
+        let mut module = Module::default();
+        let init_lifetime = SetAttributeModifier::new(Attribute::LIFETIME, module.lit(1.));
+        let effect =
+            EffectAsset::new(vec![256], Spawner::rate(30.0.into()), module).init(init_lifetime);
+        assert_eq!(effect.estimated_max_lifetime(), Some(1.));
+        let effect = effect.with_max_lifetime(0.5);
+        assert_eq!(effect.estimated_max_lifetime(), Some(0.5));
+    }
+
+    #[test]
+    fn bounds_2d() {
+        let mut module = Module::default();
+        let init_pos = SetPositionCircleModifier {
+            center: module.lit(Vec3::ZERO),
+            axis: module.lit(Vec3::Z),
+            radius: module.lit(2.),
+            dimension: ShapeDimension::Volume,
+            thickness: None,
+        };
+        let init_lifetime = SetAttributeModifier::new(Attribute::LIFETIME, module.lit(1.));
+        let effect = EffectAsset::new(vec![256], Spawner::rate(30.0.into()), module)
+            .init(init_pos)
+            .init(init_lifetime);
+        assert!(effect.bounds_2d.is_none());
+
+        // Estimated from the 3D AABB, with a 10% margin
+        let bounds = effect.estimated_bounds_2d().unwrap();
+        assert!(bounds.min.abs_diff_eq(Vec2::splat(-2.4), 1e-5));
+        assert!(bounds.max.abs_diff_eq(Vec2::splat(2.4), 1e-5));
+
+        // The explicit bounds take precedence
+        let rect = Rect::new(-10., -5., 10., 20.);
+        let effect = effect.with_bounds_2d(rect);
+        assert_eq!(effect.bounds_2d, Some(rect));
+        assert_eq!(effect.estimated_bounds_2d(), Some(rect));
+
+        // The explicit bounds are serialized
+        let s = ron::ser::to_string(&effect).unwrap();
+        let effect: EffectAsset = ron::from_str(&s).unwrap();
+        assert_eq!(effect.bounds_2d, Some(rect));
+    }
+
+    #[test]
+    #[should_panic]
+    fn bounds_2d_empty() {
+        let _ = EffectAsset::default().with_bounds_2d(Rect::new(1., 1., 1., 5.));
+    }
+
+    #[test]
+    fn particle_layout_deterministic() {
+        let mut module = Module::default();
+        let init_pos = SetPositionSphereModifier {
+            center: module.lit(Vec3::ZERO),
+            radius: module.lit(1.),
+            dimension: ShapeDimension::Volume,
+            thickness: None,
+        };
+        let init_vel = SetVelocitySphereModifier {
+            center: module.lit(Vec3::ZERO),
+            speed: module.lit(1.),
+        };
+        let init_age = SetAttributeModifier::new(Attribute::AGE, module.lit(0.));
+        let init_lifetime = SetAttributeModifier::new(Attribute::LIFETIME, module.lit(1.));
+        let init_color = SetAttributeModifier::new(Attribute::COLOR, module.lit(0xFFFFFFFFu32));
+        let init_size = SetAttributeModifier::new(Attribute::SIZE, module.lit(0.1));
+        let effect = EffectAsset::new(vec![256], Spawner::rate(30.0.into()), module.clone())
+            .init(init_pos)
+            .init(init_vel)
+            .init(init_age)
+            .init(init_lifetime)
+            .init(init_color)
+            .init(init_size);
+
+        // Repeated builds produce the same layout
+        let layout = effect.particle_layout();
+        for _ in 0..16 {
+            assert_eq!(effect.particle_layout(), layout);
+        }
+
+        // The order of the modifiers doesn't matter
+        let effect_rev = EffectAsset::new(vec![256], Spawner::rate(30.0.into()), module)
+            .init(init_size)
+            .init(init_color)
+            .init(init_lifetime)
+            .init(init_age)
+            .init(init_vel)
+            .init(init_pos);
+        assert_eq!(effect_rev.particle_layout(), layout);
+        assert_eq!(
+            effect_rev.particle_layout().generate_code(),
+            layout.generate_code()
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn max_lifetime_negative() {
+        let _ = EffectAsset::default().with_max_lifetime(-1.);
+    }
+
+    #[test]
+    fn with_trail() {
+        let effect = EffectAsset::new(vec![64], Spawner::rate(5.0.into()), Module::default())
+            .with_trail(0.45, 0.1);
+        assert_eq!(effect.capacities(), &[64, 320]);
+
+        let mut update_modifiers = effect.update_modifiers.iter();
+        let m = update_modifiers.next().unwrap();
+        assert_eq!(
+            m.modifier.as_any().downcast_ref::<CloneModifier>(),
+            Some(&CloneModifier::new(0.1, 1))
+        );
+        assert_eq!(m.groups, ParticleGroupSet::single(0));
+        let m = update_modifiers.next().unwrap();
+        let m_lifetime = m
+            .modifier
+            .as_any()
+            .downcast_ref::<SetAttributeModifier>()
+            .unwrap();
+        assert_eq!(m_lifetime.attribute, Attribute::LIFETIME);
+        assert_eq!(
+            effect.module().scalar_bounds(m_lifetime.value),
+            Some((0.45, 0.45))
+        );
+        assert_eq!(m.groups, ParticleGroupSet::single(1));
+        assert!(update_modifiers.next().is_none());
+    }
+
+    #[test]
+    #[should_panic]
+    fn with_trail_multi_group() {
+        let _ = EffectAsset::new(vec![64, 64], Spawner::rate(5.0.into()), Module::default())
+            .with_trail(0.5, 0.1);
+    }
+
+    #[test]
+    fn estimated_max_size() {
+        let spawner = Spawner::rate(30.0.into());
+
+        // Default size
+        let effect = EffectAsset::new(vec![256], spawner, Module::default());
+        assert_eq!(effect.estimated_max_size(), Some(1.));
+
+        // Init size
+        let mut module = Module::default();
+        let size = module.lit(3.);
+        let effect = EffectAsset::new(vec![256], spawner, module)
+            .init(SetAttributeModifier::new(Attribute::SIZE, size));
+        assert_eq!(effect.estimated_max_size(), Some(3.));
+
+        // Render modifiers override the init size
+        let mut module = Module::default();
+        let size = module.lit(3.);
+        let mut gradient = Gradient::new();
+        gradient.add_key(0., Vec2::splat(0.5));
+        gradient.add_key(1., Vec2::new(2., 5.));
+        let effect = EffectAsset::new(vec![256], spawner, module)
+            .init(SetAttributeModifier::new(Attribute::SIZE, size))
+            .render(SizeOverLifetimeModifier::new(gradient.clone()));
+        assert_eq!(effect.estimated_max_size(), Some(5.));
+        let effect = effect
+            .render(SetSizeModifier {
+                size: CpuValue::Uniform((Vec2::ONE, Vec2::splat(2.))),
+            })
+            .with_max_screen_coverage(4.);
+        assert_eq!(effect.estimated_max_size(), Some(2.));
+        assert_eq!(effect.max_screen_coverage, Some(4.));
+
+        // Screen-space size
+        let effect = EffectAsset::new(vec![256], spawner, Module::default()).render(
+            SizeOverLifetimeModifier {
+                gradient: gradient.clone(),
+                screen_space_size: true,
+                ..Default::default()
+            },
+        );
+        assert!(effect.estimated_max_size().is_none());
+
+        // Overshooting easings extrapolate past the gradient keys
+        let effect = EffectAsset::new(vec![256], spawner, Module::default())
+            .render(SizeOverLifetimeModifier::new(gradient.clone()).with_easing(Easing::QuadIn));
+        assert_eq!(effect.estimated_max_size(), Some(5.));
+        let effect = EffectAsset::new(vec![256], spawner, Module::default())
+            .render(SizeOverLifetimeModifier::new(gradient).with_easing(Easing::ElasticOut));
+        assert!(effect.estimated_max_size().is_none());
+    }
+
     #[test]
     fn test_apply_modifiers() {
         let mut module = Module::default();
@@ -822,6 +3053,7 @@ mod tests {
             center: module.lit(Vec3::ZERO),
             radius: module.lit(1.),
             dimension: ShapeDimension::Volume,
+            thickness: None,
         };
         let init_vel_sphere = SetVelocitySphereModifier {
             center: module.lit(Vec3::ZERO),
@@ -880,15 +3112,24 @@ mod tests {
         let property_layout = PropertyLayout::default();
         let particle_layout = ParticleLayout::default();
         let mut render_context = RenderContext::new(&property_layout, &particle_layout);
-        ParticleTextureModifier::default().apply_render(&mut module, &mut render_context);
-        ColorOverLifetimeModifier::default().apply_render(&mut module, &mut render_context);
-        SizeOverLifetimeModifier::default().apply_render(&mut module, &mut render_context);
+        ParticleTextureModifier::default()
+            .apply_render(&mut module, &mut render_context)
+            .unwrap();
+        ColorOverLifetimeModifier::default()
+            .apply_render(&mut module, &mut render_context)
+            .unwrap();
+        SizeOverLifetimeModifier::default()
+            .apply_render(&mut module, &mut render_context)
+            .unwrap();
         OrientModifier::new(OrientMode::ParallelCameraDepthPlane)
-            .apply_render(&mut module, &mut render_context);
+            .apply_render(&mut module, &mut render_context)
+            .unwrap();
         OrientModifier::new(OrientMode::FaceCameraPosition)
-            .apply_render(&mut module, &mut render_context);
+            .apply_render(&mut module, &mut render_context)
+            .unwrap();
         OrientModifier::new(OrientMode::AlongVelocity)
-            .apply_render(&mut module, &mut render_context);
+            .apply_render(&mut module, &mut render_context)
+            .unwrap();
         // assert_eq!(effect.render_layout, render_layout);
     }
 
@@ -917,6 +3158,7 @@ mod tests {
         assert_eq!(
             s,
             r#"(
+    format_version: 1,
     name: "Effect",
     capacities: [
         4096,
@@ -927,10 +3169,23 @@ mod tests {
         period: Single(1.0),
         starts_active: true,
         starts_immediately: true,
+        fade_out: 0.0,
+        ramp_up: 0.0,
+        cooldown: 0.0,
+        group: 0,
     ),
+    extra_spawners: [],
     z_layer_2d: 0.0,
     simulation_space: Global,
     simulation_condition: WhenVisible,
+    emitter_scaling: false,
+    warmup: 0.0,
+    prewarm_full_lifetime: false,
+    max_lifetime: None,
+    compaction_interval: 0,
+    group_render_orders: [],
+    group_motion_integrations: [],
+    group_draw_orders: [],
     init_modifiers: [
         (
             modifier: {
@@ -940,6 +3195,7 @@ mod tests {
                 ),
             },
             groups: (1),
+            order: 0,
         ),
     ],
     update_modifiers: [],
@@ -951,6 +3207,7 @@ mod tests {
         ),
     ],
     motion_integration: PostUpdate,
+    draw_order: OldestFirst,
     module: [
         Literal(Vector(Vec3((1.2, -3.45, 87.54485)))),
         Literal(Vector(BVec2((false, true)))),
@@ -961,6 +3218,10 @@ mod tests {
         ),
     ],
     alpha_mode: Blend,
+    color_space: Linear,
+    max_screen_coverage: None,
+    render_primitive: Quad,
+    bounds_2d: None,
 )"#
         );
         let effect_serde: EffectAsset = ron::from_str(&s).unwrap();
@@ -973,8 +3234,22 @@ mod tests {
             effect.simulation_condition,
             effect_serde.simulation_condition
         );
+        assert_eq!(effect.emitter_scaling, effect_serde.emitter_scaling);
+        assert_eq!(effect.warmup, effect_serde.warmup);
+        assert_eq!(
+            effect.prewarm_full_lifetime,
+            effect_serde.prewarm_full_lifetime
+        );
+        assert_eq!(effect.compaction_interval, effect_serde.compaction_interval);
+        assert_eq!(effect.group_render_orders, effect_serde.group_render_orders);
+        assert_eq!(
+            effect.group_motion_integrations,
+            effect_serde.group_motion_integrations
+        );
         assert_eq!(effect.properties, effect_serde.properties);
         assert_eq!(effect.motion_integration, effect_serde.motion_integration);
+        assert_eq!(effect.group_draw_orders, effect_serde.group_draw_orders);
+        assert_eq!(effect.draw_order, effect_serde.draw_order);
         assert_eq!(effect.module, effect_serde.module);
         assert_eq!(effect.alpha_mode, effect_serde.alpha_mode);
         assert_eq!(
@@ -990,4 +3265,105 @@ mod tests {
             effect_serde.render_modifiers().count()
         );
     }
+
+    #[test]
+    fn alpha_mode_blend_ops() {
+        for (alpha_mode, ron_str) in [
+            (AlphaMode::Subtract, "Subtract"),
+            (AlphaMode::Min, "Min"),
+            (AlphaMode::Max, "Max"),
+        ] {
+            let effect =
+                EffectAsset::new(vec![32], Spawner::once(1.0.into(), true), Module::default())
+                    .with_alpha_mode(alpha_mode);
+            assert_eq!(effect.alpha_mode, alpha_mode);
+
+            let s = ron::ser::to_string(&alpha_mode).unwrap();
+            assert_eq!(s, ron_str);
+            let alpha_mode_serde: AlphaMode = ron::from_str(&s).unwrap();
+            assert_eq!(alpha_mode_serde, alpha_mode);
+        }
+
+        // Default alpha blending
+        assert!(AlphaMode::Blend.blend_state().is_none());
+        let mut module = Module::default();
+        let cutoff = module.lit(0.5);
+        assert!(AlphaMode::Mask(cutoff).blend_state().is_none());
+
+        // Destination alpha is always preserved
+        let keep_alpha = BlendComponent {
+            src_factor: BlendFactor::Zero,
+            dst_factor: BlendFactor::One,
+            operation: BlendOperation::Add,
+        };
+
+        let subtract = AlphaMode::Subtract.blend_state().unwrap();
+        assert_eq!(subtract.color.operation, BlendOperation::ReverseSubtract);
+        assert_eq!(subtract.color.src_factor, BlendFactor::SrcAlpha);
+        assert_eq!(subtract.color.dst_factor, BlendFactor::One);
+        assert_eq!(subtract.alpha, keep_alpha);
+
+        let min = AlphaMode::Min.blend_state().unwrap();
+        assert_eq!(min.color.operation, BlendOperation::Min);
+        assert_eq!(min.alpha, keep_alpha);
+
+        let max = AlphaMode::Max.blend_state().unwrap();
+        assert_eq!(max.color.operation, BlendOperation::Max);
+        assert_eq!(max.alpha, keep_alpha);
+
+        // All modes use distinct pipelines
+        assert_ne!(subtract, min);
+        assert_ne!(subtract, max);
+        assert_ne!(min, max);
+    }
+
+    #[test]
+    fn serde_format_version() {
+        // Legacy asset without a format version, and without any of the fields
+        // which have a default value.
+        let legacy = r#"(
+    name: "Legacy",
+    capacities: [256],
+    spawner: (
+        num_particles: Single(30.0),
+        spawn_time: Single(1.0),
+        period: Single(1.0),
+        starts_active: true,
+        starts_immediately: true,
+    ),
+    z_layer_2d: 0.0,
+    simulation_space: Global,
+    simulation_condition: WhenVisible,
+    init_modifiers: [],
+    update_modifiers: [],
+    render_modifiers: [],
+    properties: [],
+    motion_integration: PostUpdate,
+    module: [],
+    alpha_mode: Blend,
+)"#;
+        let effect = EffectAsset::from_ron_versioned(legacy.as_bytes()).unwrap();
+        assert_eq!(effect.format_version, EffectAsset::FORMAT_VERSION);
+        assert_eq!(effect.name, "Legacy");
+        assert_eq!(effect.capacities(), &[256]);
+        assert!(effect.extra_spawners.is_empty());
+        assert_eq!(effect.color_space, ColorSpace::default());
+
+        // Re-serializing writes the current version
+        let s = ron::ser::to_string(&effect).unwrap();
+        assert!(s.starts_with(&format!("(format_version:{},", EffectAsset::FORMAT_VERSION)));
+        let effect = EffectAsset::from_ron_versioned(s.as_bytes()).unwrap();
+        assert_eq!(effect.name, "Legacy");
+
+        // Assets from a future version are rejected
+        let future = format!(
+            "(format_version:{},{}",
+            EffectAsset::FORMAT_VERSION + 1,
+            &s[s.find("name:").unwrap()..]
+        );
+        assert!(matches!(
+            EffectAsset::from_ron_versioned(future.as_bytes()),
+            Err(EffectAssetLoaderError::UnsupportedFormatVersion(v)) if v == EffectAsset::FORMAT_VERSION + 1
+        ));
+    }
 }
@@ -90,6 +90,10 @@
 //! | [`Attribute::AXIS_Y`] | Y axis of the particle frame. |
 //! | [`Attribute::AXIS_Z`] | Z axis of the particle frame. |
 //! | [`Attribute::SPRITE_INDEX`] | Index of the current sprite for flipbook animation. |
+//! | [`Attribute::PREV_POSITION`] | The particle's position at the start of the current update. |
+//! | [`Attribute::MASS`] | The particle's mass, scaling down the effect of forces. |
+//! | [`Attribute::SEED`] | A random value fixed for the lifetime of the particle. |
+//! | [`Attribute::LAST_EMIT_POSITION`] | The particle's position when it last emitted a trail particle. |
 //!
 //! # Custom attributes
 //!
@@ -556,6 +560,22 @@ impl AttributeInner {
         Value::Scalar(ScalarValue::Int(0)),
     );
 
+    pub const PREV_POSITION: &'static AttributeInner = &AttributeInner::new(
+        Cow::Borrowed("prev_position"),
+        Value::Vector(VectorValue::new_vec3(Vec3::ZERO)),
+    );
+
+    pub const MASS: &'static AttributeInner =
+        &AttributeInner::new(Cow::Borrowed("mass"), Value::Scalar(ScalarValue::Float(1.)));
+
+    pub const SEED: &'static AttributeInner =
+        &AttributeInner::new(Cow::Borrowed("seed"), Value::Scalar(ScalarValue::Uint(0)));
+
+    pub const LAST_EMIT_POSITION: &'static AttributeInner = &AttributeInner::new(
+        Cow::Borrowed("last_emit_position"),
+        Value::Vector(VectorValue::new_vec3(Vec3::ZERO)),
+    );
+
     pub const F32_0: &'static AttributeInner = &AttributeInner::new(
         Cow::Borrowed("f32_0"),
         Value::Scalar(ScalarValue::Float(0.)),
@@ -1022,6 +1042,127 @@ impl Attribute {
     /// [`FlipbookModifier`]: crate::modifier::output::FlipbookModifier
     pub const SPRITE_INDEX: Attribute = Attribute(AttributeInner::SPRITE_INDEX);
 
+    /// The particle position at the start of the current simulation update.
+    ///
+    /// This attribute is automatically maintained by 🎆 Hanabi if present in
+    /// the particle layout. At spawn time it's initialized to the spawn
+    /// position of the particle, then at the start of each simulation update
+    /// it's assigned the current particle position, before motion integration
+    /// and any update modifier is applied. The difference between
+    /// [`Attribute::POSITION`] and this attribute is therefore the true
+    /// frame-to-frame displacement of the particle, independently of its
+    /// velocity. This is useful for effects where the velocity is noisy or
+    /// modified by collisions, for example to orient trails or compute motion
+    /// vectors.
+    ///
+    /// Like any other attribute, this attribute is added to the particle layout
+    /// only if at least one modifier of the effect uses it.
+    ///
+    /// # Name
+    ///
+    /// `prev_position`
+    ///
+    /// # Type
+    ///
+    /// [`VectorType::VEC3F`] representing the XYZ coordinates of the position.
+    pub const PREV_POSITION: Attribute = Attribute(AttributeInner::PREV_POSITION);
+
+    /// The particle mass.
+    ///
+    /// The mass scales down the effect of forces on the particle, such that
+    /// heavier particles respond less to them. This allows mixing heavy and
+    /// light particles in a single effect, for example to simulate debris of
+    /// various sizes. The mass is typically initialized per particle with a
+    /// [`SetAttributeModifier`] and a random value.
+    ///
+    /// The mass is only taken into account by the modifiers which are
+    /// explicitly configured to respect it, to preserve the behavior of
+    /// existing effects. The following modifiers can be configured to divide
+    /// the force they apply by the particle mass:
+    /// - [`AccelModifier`]
+    /// - [`RadialAccelModifier`]
+    /// - [`TangentAccelModifier`]
+    /// - [`LinearDragModifier`]
+    ///
+    /// The mass must be strictly positive.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::*;
+    /// # use bevy::math::Vec3;
+    /// let writer = ExprWriter::new();
+    ///
+    /// // Assign a random mass between 0.5 and 2.0 to each particle at spawn time
+    /// let mass = writer.lit(0.5).uniform(writer.lit(2.)).expr();
+    /// let init_mass = SetAttributeModifier::new(Attribute::MASS, mass);
+    ///
+    /// // Apply some gravity, divided by the particle mass
+    /// let gravity = writer.lit(Vec3::new(0., -9.8, 0.)).expr();
+    /// let update_accel = AccelModifier::new(gravity).with_respect_mass(true);
+    /// ```
+    ///
+    /// # Name
+    ///
+    /// `mass`
+    ///
+    /// # Type
+    ///
+    /// [`ScalarType::Float`]
+    ///
+    /// [`AccelModifier`]: crate::modifier::accel::AccelModifier
+    /// [`RadialAccelModifier`]: crate::modifier::accel::RadialAccelModifier
+    /// [`TangentAccelModifier`]: crate::modifier::accel::TangentAccelModifier
+    /// [`LinearDragModifier`]: crate::modifier::force::LinearDragModifier
+    /// [`SetAttributeModifier`]: crate::modifier::SetAttributeModifier
+    pub const MASS: Attribute = Attribute(AttributeInner::MASS);
+
+    /// A per-particle random seed.
+    ///
+    /// This attribute stores a random value assigned to the particle when it
+    /// spawns, and which stays constant for the entire lifetime of the
+    /// particle. It allows deriving some per-particle random variations which
+    /// are consistent from one frame to the next, unlike the random values
+    /// generated by [`ExprWriter::rand()`], which change each time they're
+    /// evaluated. This is used for example by the [`FlipbookAnimationModifier`]
+    /// to desynchronize the animation of the particles.
+    ///
+    /// If present in the particle layout, the seed is automatically
+    /// initialized with a random value when the particle spawns, before any
+    /// init modifier is applied.
+    ///
+    /// # Name
+    ///
+    /// `seed`
+    ///
+    /// # Type
+    ///
+    /// [`ScalarType::Uint`]
+    ///
+    /// [`ExprWriter::rand()`]: crate::graph::ExprWriter::rand
+    /// [`FlipbookAnimationModifier`]: crate::modifier::output::FlipbookAnimationModifier
+    pub const SEED: Attribute = Attribute(AttributeInner::SEED);
+
+    /// The particle position when it last emitted a trail particle.
+    ///
+    /// This attribute is used by the [`CloneModifier`] configured with a
+    /// minimum emission distance, to only emit a new particle once the source
+    /// particle moved far enough from the position where it emitted the
+    /// previous one. At spawn time it's automatically initialized to the spawn
+    /// position of the particle, once that position is in its final
+    /// simulation space.
+    ///
+    /// # Name
+    ///
+    /// `last_emit_position`
+    ///
+    /// # Type
+    ///
+    /// [`VectorType::VEC3F`] representing the XYZ coordinates of the position.
+    ///
+    /// [`CloneModifier`]: crate::modifier::clone::CloneModifier
+    pub const LAST_EMIT_POSITION: Attribute = Attribute(AttributeInner::LAST_EMIT_POSITION);
+
     /// A generic scalar float attribute.
     ///
     /// This attribute can be used for anything. It has no specific meaning. You
@@ -1096,7 +1237,7 @@ impl Attribute {
     declare_custom_attr_pub!(F32X4_3, "f32x4_3", 4, VEC4F);
 
     /// Collection of all the existing particle attributes.
-    const ALL: [Attribute; 29] = [
+    const ALL: [Attribute; 33] = [
         Attribute::POSITION,
         Attribute::VELOCITY,
         Attribute::AGE,
@@ -1110,6 +1251,10 @@ impl Attribute {
         Attribute::AXIS_Y,
         Attribute::AXIS_Z,
         Attribute::SPRITE_INDEX,
+        Attribute::PREV_POSITION,
+        Attribute::MASS,
+        Attribute::SEED,
+        Attribute::LAST_EMIT_POSITION,
         Attribute::F32_0,
         Attribute::F32_1,
         Attribute::F32_2,
@@ -1262,8 +1407,9 @@ impl ParticleLayoutBuilder {
         self.layout.sort_unstable_by_key(|la| la.attribute.name());
         self.layout.dedup_by_key(|la| la.attribute.name());
 
-        // Sort by size
-        self.layout.sort_unstable_by_key(|la| la.attribute.size());
+        // Sort by size. Use a stable sort to preserve the name ordering of attributes
+        // of the same size, so the layout doesn't depend on the append order.
+        self.layout.sort_by_key(|la| la.attribute.size());
 
         let mut layout = vec![];
         let mut offset = 0;
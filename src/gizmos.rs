@@ -0,0 +1,420 @@
+//! Debug visualization of the effect emitters and force fields with gizmos.
+//!
+//! This module is only available with the `debug_gizmos` feature. Add the
+//! [`EffectGizmoPlugin`] to the app, in addition to the [`HanabiPlugin`] and
+//! the Bevy [`GizmoPlugin`] (part of the `DefaultPlugins`), to draw for each
+//! effect instance:
+//! - the shape of the emitters, for the position modifiers of the init pass
+//!   (sphere, circle, box, cone);
+//! - the force fields and colliders of the update pass (conform-to-sphere
+//!   attractors, kill and clamp volumes, collision planes);
+//! - the [`Aabb`] of the effect, if any.
+//!
+//! The shape parameters are read from the expressions of the modifiers. Only
+//! expressions whose value can be determined on CPU are supported, like
+//! literals or random values between literals. Shapes whose parameters depend
+//! on properties or particle attributes are not drawn.
+//!
+//! [`HanabiPlugin`]: crate::HanabiPlugin
+//! [`GizmoPlugin`]: bevy::gizmos::GizmoPlugin
+
+use bevy::{prelude::*, render::primitives::Aabb, transform::TransformSystem};
+
+use crate::{
+    ClampToBoxModifier, ClampToSphereModifier, CollidePlaneModifier, ConformToSphereModifier,
+    EffectAsset, ExprHandle, KillAabbModifier, KillSphereModifier, Module, ParticleEffect,
+    SetPositionBoxModifier, SetPositionCircleModifier, SetPositionCone3dModifier,
+    SetPositionSphereModifier, SimulationSpace,
+};
+
+/// Plugin drawing debug gizmos for all particle effects.
+///
+/// See the [module documentation] for details.
+///
+/// [module documentation]: crate::gizmos
+#[derive(Debug, Default, Clone, Copy)]
+pub struct EffectGizmoPlugin;
+
+impl Plugin for EffectGizmoPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<EffectGizmoConfig>()
+            .register_type::<EffectGizmoConfig>()
+            .add_systems(
+                PostUpdate,
+                draw_effect_gizmos.after(TransformSystem::TransformPropagate),
+            );
+    }
+}
+
+/// Configuration of the debug gizmos drawn by the [`EffectGizmoPlugin`].
+#[derive(Debug, Clone, Copy, Resource, Reflect)]
+#[reflect(Resource)]
+pub struct EffectGizmoConfig {
+    /// Global toggle for all the effect gizmos.
+    pub enabled: bool,
+    /// Draw the emitter shapes of the init position modifiers.
+    pub draw_emitters: bool,
+    /// Draw the force fields and colliders of the update modifiers.
+    pub draw_forces: bool,
+    /// Draw the [`Aabb`] of the effect instances.
+    ///
+    /// This uses the [`Aabb`] component of the effect entity if any, or the
+    /// [`EffectAsset::estimate_aabb()`] otherwise.
+    pub draw_aabb: bool,
+    /// Color of the emitter shapes.
+    pub emitter_color: Color,
+    /// Color of the force fields and colliders.
+    pub force_color: Color,
+    /// Color of the [`Aabb`].
+    pub aabb_color: Color,
+    /// Size of the square drawn to represent infinite planes, like the ones of
+    /// the [`CollidePlaneModifier`].
+    pub plane_size: f32,
+}
+
+impl Default for EffectGizmoConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            draw_emitters: true,
+            draw_forces: true,
+            draw_aabb: true,
+            emitter_color: Color::CYAN,
+            force_color: Color::ORANGE,
+            aabb_color: Color::YELLOW,
+            plane_size: 2.,
+        }
+    }
+}
+
+/// Resolve a scalar expression to its largest absolute value, if known on CPU.
+fn scalar_extent(module: &Module, expr: ExprHandle) -> Option<f32> {
+    module
+        .scalar_bounds(expr)
+        .map(|(lo, hi)| lo.abs().max(hi.abs()))
+}
+
+/// Resolve a `vec3<f32>` expression to the center of its value range, if known
+/// on CPU.
+fn vec3_center(module: &Module, expr: ExprHandle) -> Option<Vec3> {
+    module.vec3_bounds(expr).map(|(lo, hi)| (lo + hi) / 2.)
+}
+
+/// Resolve a `vec3<f32>` expression to its largest absolute value per
+/// component, if known on CPU.
+fn vec3_extent(module: &Module, expr: ExprHandle) -> Option<Vec3> {
+    module
+        .vec3_bounds(expr)
+        .map(|(lo, hi)| lo.abs().max(hi.abs()))
+}
+
+/// Resolve an optional quaternion expression to a rotation, if known on CPU.
+///
+/// A `None` expression resolves to the identity rotation.
+fn quat_orientation(module: &Module, expr: Option<ExprHandle>) -> Option<Quat> {
+    match expr {
+        Some(expr) => module.quat_value(expr),
+        None => Some(Quat::IDENTITY),
+    }
+}
+
+/// Draw a sphere in the given space.
+fn draw_sphere(
+    gizmos: &mut Gizmos,
+    transform: &GlobalTransform,
+    center: Vec3,
+    radius: f32,
+    color: Color,
+) {
+    let (scale, rotation, _) = transform.to_scale_rotation_translation();
+    gizmos.sphere(
+        transform.transform_point(center),
+        rotation,
+        radius * scale.abs().max_element(),
+        color,
+    );
+}
+
+/// Draw a circle in the given space.
+fn draw_circle(
+    gizmos: &mut Gizmos,
+    transform: &GlobalTransform,
+    center: Vec3,
+    axis: Vec3,
+    radius: f32,
+    color: Color,
+) {
+    let (scale, rotation, _) = transform.to_scale_rotation_translation();
+    let Ok(normal) = Direction3d::new(rotation * axis) else {
+        return;
+    };
+    gizmos.circle(
+        transform.transform_point(center),
+        normal,
+        radius * scale.abs().max_element(),
+        color,
+    );
+}
+
+/// Draw an axis-aligned box in the given space.
+fn draw_box(
+    gizmos: &mut Gizmos,
+    transform: &GlobalTransform,
+    center: Vec3,
+    half_size: Vec3,
+    color: Color,
+) {
+    let local = Transform::from_translation(center).with_scale(half_size * 2.);
+    gizmos.cuboid(transform.mul_transform(local), color);
+}
+
+/// Draw a truncated cone along the local Y axis in the given space.
+fn draw_cone(
+    gizmos: &mut Gizmos,
+    transform: &GlobalTransform,
+    height: f32,
+    base_radius: f32,
+    top_radius: f32,
+    color: Color,
+) {
+    let top = Vec3::Y * height;
+    draw_circle(gizmos, transform, Vec3::ZERO, Vec3::Y, base_radius, color);
+    draw_circle(gizmos, transform, top, Vec3::Y, top_radius, color);
+    for dir in [Vec3::X, Vec3::Z, Vec3::NEG_X, Vec3::NEG_Z] {
+        gizmos.line(
+            transform.transform_point(dir * base_radius),
+            transform.transform_point(top + dir * top_radius),
+            color,
+        );
+    }
+}
+
+/// Draw a plane in the given space, as a square and its normal.
+fn draw_plane(
+    gizmos: &mut Gizmos,
+    transform: &GlobalTransform,
+    origin: Vec3,
+    normal: Vec3,
+    size: f32,
+    color: Color,
+) {
+    let Some(normal) = normal.try_normalize() else {
+        return;
+    };
+    let (_, rotation, _) = transform.to_scale_rotation_translation();
+    let origin = transform.transform_point(origin);
+    let normal = rotation * normal;
+    gizmos.rect(
+        origin,
+        Quat::from_rotation_arc(Vec3::Z, normal),
+        Vec2::splat(size),
+        color,
+    );
+    gizmos.arrow(origin, origin + normal * size / 2., color);
+}
+
+/// Draw the emitter shapes of the init modifiers of an effect.
+///
+/// The shapes are always relative to the emitter.
+fn draw_emitters(
+    gizmos: &mut Gizmos,
+    asset: &EffectAsset,
+    transform: &GlobalTransform,
+    color: Color,
+) {
+    let module = asset.module();
+    for modifier in asset.init_modifiers() {
+        let any = modifier.as_any();
+        if let Some(m) = any.downcast_ref::<SetPositionSphereModifier>() {
+            let (Some(center), Some(radius)) = (
+                vec3_center(module, m.center),
+                scalar_extent(module, m.radius),
+            ) else {
+                continue;
+            };
+            draw_sphere(gizmos, transform, center, radius, color);
+        } else if let Some(m) = any.downcast_ref::<SetPositionCircleModifier>() {
+            let (Some(center), Some(axis), Some(radius)) = (
+                vec3_center(module, m.center),
+                vec3_center(module, m.axis),
+                scalar_extent(module, m.radius),
+            ) else {
+                continue;
+            };
+            draw_circle(gizmos, transform, center, axis, radius, color);
+        } else if let Some(m) = any.downcast_ref::<SetPositionBoxModifier>() {
+            let (Some(center), Some(half_extents), Some(orientation)) = (
+                vec3_center(module, m.center),
+                vec3_extent(module, m.half_extents),
+                quat_orientation(module, m.orientation),
+            ) else {
+                continue;
+            };
+            let transform = transform
+                .mul_transform(Transform::from_translation(center).with_rotation(orientation));
+            draw_box(gizmos, &transform, Vec3::ZERO, half_extents, color);
+        } else if let Some(m) = any.downcast_ref::<SetPositionCone3dModifier>() {
+            let (Some(height), Some(base_radius), Some(top_radius), Some(orientation)) = (
+                scalar_extent(module, m.height),
+                scalar_extent(module, m.base_radius),
+                scalar_extent(module, m.top_radius),
+                quat_orientation(module, m.orientation),
+            ) else {
+                continue;
+            };
+            let transform = transform.mul_transform(Transform::from_rotation(orientation));
+            draw_cone(gizmos, &transform, height, base_radius, top_radius, color);
+        }
+    }
+}
+
+/// Draw the force fields and colliders of the update modifiers of an effect.
+///
+/// The modifiers are defined in simulation space, so `transform` is the
+/// emitter transform for effects simulated in local space, and the identity
+/// otherwise.
+fn draw_forces(
+    gizmos: &mut Gizmos,
+    asset: &EffectAsset,
+    transform: &GlobalTransform,
+    color: Color,
+    plane_size: f32,
+) {
+    let module = asset.module();
+    for modifier in asset.update_modifiers() {
+        let any = modifier.as_any();
+        if let Some(m) = any.downcast_ref::<ConformToSphereModifier>() {
+            let Some(origin) = vec3_center(module, m.origin) else {
+                continue;
+            };
+            if let Some(radius) = scalar_extent(module, m.radius) {
+                draw_sphere(gizmos, transform, origin, radius, color);
+            }
+            if let Some(influence_dist) = scalar_extent(module, m.influence_dist) {
+                draw_sphere(gizmos, transform, origin, influence_dist, color);
+            }
+        } else if let Some(m) = any.downcast_ref::<KillSphereModifier>() {
+            let (Some(center), Some(sqr_radius)) = (
+                vec3_center(module, m.center),
+                scalar_extent(module, m.sqr_radius),
+            ) else {
+                continue;
+            };
+            draw_sphere(gizmos, transform, center, sqr_radius.sqrt(), color);
+        } else if let Some(m) = any.downcast_ref::<ClampToSphereModifier>() {
+            let (Some(center), Some(radius)) = (
+                vec3_center(module, m.center),
+                scalar_extent(module, m.radius),
+            ) else {
+                continue;
+            };
+            draw_sphere(gizmos, transform, center, radius, color);
+        } else if let Some(m) = any.downcast_ref::<KillAabbModifier>() {
+            let (Some(center), Some(half_size)) = (
+                vec3_center(module, m.center),
+                vec3_extent(module, m.half_size),
+            ) else {
+                continue;
+            };
+            draw_box(gizmos, transform, center, half_size, color);
+        } else if let Some(m) = any.downcast_ref::<ClampToBoxModifier>() {
+            let (Some(center), Some(half_size)) = (
+                vec3_center(module, m.center),
+                vec3_extent(module, m.half_size),
+            ) else {
+                continue;
+            };
+            draw_box(gizmos, transform, center, half_size, color);
+        } else if let Some(m) = any.downcast_ref::<CollidePlaneModifier>() {
+            let (Some(origin), Some(normal)) =
+                (vec3_center(module, m.origin), vec3_center(module, m.normal))
+            else {
+                continue;
+            };
+            draw_plane(gizmos, transform, origin, normal, plane_size, color);
+        }
+    }
+}
+
+/// Draw the debug gizmos of all the visible particle effects.
+///
+/// This system runs during the [`PostUpdate`] schedule, after the transforms
+/// are propagated.
+pub fn draw_effect_gizmos(
+    mut gizmos: Gizmos,
+    config: Res<EffectGizmoConfig>,
+    assets: Res<Assets<EffectAsset>>,
+    query: Query<(
+        &ParticleEffect,
+        &GlobalTransform,
+        &InheritedVisibility,
+        Option<&Aabb>,
+    )>,
+) {
+    if !config.enabled {
+        return;
+    }
+
+    for (effect, transform, visibility, aabb) in query.iter() {
+        if !visibility.get() {
+            continue;
+        }
+        let Some(asset) = assets.get(&effect.handle) else {
+            continue;
+        };
+
+        if config.draw_emitters {
+            draw_emitters(&mut gizmos, asset, transform, config.emitter_color);
+        }
+
+        if config.draw_forces {
+            let sim_transform = match asset.simulation_space {
+                SimulationSpace::Global => GlobalTransform::IDENTITY,
+                SimulationSpace::Local => *transform,
+            };
+            draw_forces(
+                &mut gizmos,
+                asset,
+                &sim_transform,
+                config.force_color,
+                config.plane_size,
+            );
+        }
+
+        if config.draw_aabb {
+            if let Some(aabb) = aabb.copied().or_else(|| asset.estimate_aabb()) {
+                draw_box(
+                    &mut gizmos,
+                    transform,
+                    aabb.center.into(),
+                    aabb.half_extents.into(),
+                    config.aabb_color,
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolve_expr() {
+        let mut module = Module::default();
+        let lit = module.lit(2.);
+        let neg = module.lit(-3.);
+        let rand = module.uniform(lit, neg);
+        let prop = module.prop("radius");
+        assert_eq!(scalar_extent(&module, lit), Some(2.));
+        assert_eq!(scalar_extent(&module, rand), Some(3.));
+        assert_eq!(scalar_extent(&module, prop), None);
+
+        let a = module.lit(Vec3::new(1., -2., 0.));
+        let b = module.lit(Vec3::new(3., 2., -4.));
+        let rand = module.uniform(a, b);
+        assert_eq!(vec3_center(&module, a), Some(Vec3::new(1., -2., 0.)));
+        assert_eq!(vec3_center(&module, rand), Some(Vec3::new(2., 0., -2.)));
+        assert_eq!(vec3_extent(&module, rand), Some(Vec3::new(3., 2., 4.)));
+    }
+}
@@ -109,11 +109,318 @@ impl Hash for GradientKey<Vec4> {
     }
 }
 
+/// Behavior of a [`Gradient`] when sampled outside the \[0:1\] range.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Reflect, Serialize, Deserialize)]
+pub enum GradientWrap {
+    /// Clamp the sampling ratio to the range of the keys, returning the value
+    /// of the first or last key. This is the default.
+    #[default]
+    Clamp,
+
+    /// Repeat the gradient, wrapping the sampling ratio back into \[0:1\] with
+    /// its fractional part.
+    Repeat,
+
+    /// Repeat the gradient, alternating between its normal and its reversed
+    /// direction, so that the result is continuous.
+    Mirror,
+}
+
+impl GradientWrap {
+    /// Apply the wrap mode to a sampling ratio.
+    ///
+    /// This uses the same formula as the WGSL code emitted for the gradient,
+    /// so that sampling on the CPU matches sampling on the GPU.
+    pub fn apply(&self, ratio: f32) -> f32 {
+        // WGSL's fract(x) is defined as x - floor(x)
+        let fract = |x: f32| x - x.floor();
+        match self {
+            GradientWrap::Clamp => ratio,
+            GradientWrap::Repeat => fract(ratio),
+            GradientWrap::Mirror => 1. - (fract(ratio * 0.5) * 2. - 1.).abs(),
+        }
+    }
+
+    /// Get the WGSL expression applying the wrap mode to the given ratio
+    /// expression.
+    pub(crate) fn to_wgsl_code(self, ratio: &str) -> String {
+        match self {
+            GradientWrap::Clamp => ratio.to_string(),
+            GradientWrap::Repeat => format!("fract({ratio})"),
+            GradientWrap::Mirror => format!("1. - abs(fract({ratio} * 0.5) * 2. - 1.)"),
+        }
+    }
+}
+
+/// Body of the bounce curve shared by the bounce easings.
+///
+/// Reads the ratio from `x` and writes the eased value into `b`.
+const BOUNCE_OUT_CODE: &str = r#"let n1 = 7.5625;
+    let d1 = 2.75;
+    var b: f32;
+    if (x < 1. / d1) {
+        b = n1 * x * x;
+    } else if (x < 2. / d1) {
+        let y = x - 1.5 / d1;
+        b = n1 * y * y + 0.75;
+    } else if (x < 2.5 / d1) {
+        let y = x - 2.25 / d1;
+        b = n1 * y * y + 0.9375;
+    } else {
+        let y = x - 2.625 / d1;
+        b = n1 * y * y + 0.984375;
+    }"#;
+
+/// Easing function remapping a ratio in \[0:1\].
+///
+/// Easing functions are used to make an animation start or stop more or less
+/// abruptly. They can be used as the interpolation mode of a [`Gradient`], in
+/// an expression with [`ExprWriter::ease()`], or to remap the lifetime ratio
+/// of the over-lifetime modifiers like [`ColorOverLifetimeModifier`].
+///
+/// All easing functions map 0 to 0 and 1 to 1. The input ratio is clamped to
+/// \[0:1\] before being eased. The elastic easings overshoot, and produce
+/// values outside of \[0:1\] for some inputs; see [`is_bounded()`].
+///
+/// The CPU-side [`ease()`] uses the same formulas as the WGSL code generated
+/// for the GPU.
+///
+/// [`ExprWriter::ease()`]: crate::graph::ExprWriter::ease
+/// [`ColorOverLifetimeModifier`]: crate::ColorOverLifetimeModifier
+/// [`is_bounded()`]: crate::Easing::is_bounded
+/// [`ease()`]: crate::Easing::ease
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Reflect, Serialize, Deserialize)]
+pub enum Easing {
+    /// No easing; the ratio is returned as is. This is the default.
+    #[default]
+    Linear,
+    /// Quadratic ease-in, accelerating from zero velocity.
+    QuadIn,
+    /// Quadratic ease-out, decelerating to zero velocity.
+    QuadOut,
+    /// Quadratic ease-in for the first half, then ease-out.
+    QuadInOut,
+    /// Cubic ease-in, accelerating from zero velocity.
+    CubicIn,
+    /// Cubic ease-out, decelerating to zero velocity.
+    CubicOut,
+    /// Cubic ease-in for the first half, then ease-out.
+    CubicInOut,
+    /// Elastic ease-in, oscillating with a growing amplitude before snapping
+    /// to 1.
+    ElasticIn,
+    /// Elastic ease-out, overshooting 1 then oscillating around it with a
+    /// decaying amplitude.
+    ElasticOut,
+    /// Elastic ease-in for the first half, then ease-out.
+    ElasticInOut,
+    /// Bounce ease-in, bouncing off 0 with a growing amplitude.
+    BounceIn,
+    /// Bounce ease-out, bouncing against 1 with a decaying amplitude.
+    BounceOut,
+    /// Bounce ease-in for the first half, then ease-out.
+    BounceInOut,
+}
+
+impl Easing {
+    /// Apply the easing function to a ratio.
+    ///
+    /// The ratio is clamped to \[0:1\] before being eased.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::Easing;
+    /// assert_eq!(Easing::QuadIn.ease(0.5), 0.25);
+    /// assert_eq!(Easing::QuadOut.ease(0.5), 0.75);
+    /// assert_eq!(Easing::CubicIn.ease(0.5), 0.125);
+    /// ```
+    pub fn ease(&self, ratio: f32) -> f32 {
+        const C4: f32 = std::f32::consts::TAU / 3.;
+        const C5: f32 = std::f32::consts::TAU / 4.5;
+        let t = ratio.clamp(0., 1.);
+        match self {
+            Easing::Linear => t,
+            Easing::QuadIn => t * t,
+            Easing::QuadOut => {
+                let u = 1. - t;
+                u.mul_add(-u, 1.)
+            }
+            Easing::QuadInOut => {
+                if t < 0.5 {
+                    2. * t * t
+                } else {
+                    2_f32.mul_add(-t, 2.).powi(2).mul_add(-0.5, 1.)
+                }
+            }
+            Easing::CubicIn => t * t * t,
+            Easing::CubicOut => 1. - (1. - t).powi(3),
+            Easing::CubicInOut => {
+                if t < 0.5 {
+                    4. * t * t * t
+                } else {
+                    2_f32.mul_add(-t, 2.).powi(3).mul_add(-0.5, 1.)
+                }
+            }
+            Easing::ElasticIn => {
+                if t <= 0. || t >= 1. {
+                    t
+                } else {
+                    -(10_f32.mul_add(t, -10.).exp2()) * (10_f32.mul_add(t, -10.75) * C4).sin()
+                }
+            }
+            Easing::ElasticOut => {
+                if t <= 0. || t >= 1. {
+                    t
+                } else {
+                    (-10. * t)
+                        .exp2()
+                        .mul_add((10_f32.mul_add(t, -0.75) * C4).sin(), 1.)
+                }
+            }
+            Easing::ElasticInOut => {
+                if t <= 0. || t >= 1. {
+                    t
+                } else if t < 0.5 {
+                    -(20_f32.mul_add(t, -10.).exp2())
+                        * (20_f32.mul_add(t, -11.125) * C5).sin()
+                        * 0.5
+                } else {
+                    ((-20_f32).mul_add(t, 10.).exp2() * (20_f32.mul_add(t, -11.125) * C5).sin())
+                        .mul_add(0.5, 1.)
+                }
+            }
+            Easing::BounceIn => 1. - bounce_out(1. - t),
+            Easing::BounceOut => bounce_out(t),
+            Easing::BounceInOut => {
+                if t < 0.5 {
+                    (1. - bounce_out(2_f32.mul_add(-t, 1.))) * 0.5
+                } else {
+                    (1. + bounce_out(2_f32.mul_add(t, -1.))) * 0.5
+                }
+            }
+        }
+    }
+
+    /// Check if the easing function never produces values outside \[0:1\].
+    ///
+    /// This is `false` for the elastic easings, which overshoot. In that case,
+    /// a gradient using the easing extrapolates beyond the values of its keys.
+    pub fn is_bounded(&self) -> bool {
+        !matches!(
+            self,
+            Easing::ElasticIn | Easing::ElasticOut | Easing::ElasticInOut
+        )
+    }
+
+    /// Get the name of the WGSL helper function implementing the easing.
+    pub(crate) fn wgsl_fn_name(&self) -> &'static str {
+        match self {
+            Easing::Linear => "ease_linear",
+            Easing::QuadIn => "ease_quad_in",
+            Easing::QuadOut => "ease_quad_out",
+            Easing::QuadInOut => "ease_quad_in_out",
+            Easing::CubicIn => "ease_cubic_in",
+            Easing::CubicOut => "ease_cubic_out",
+            Easing::CubicInOut => "ease_cubic_in_out",
+            Easing::ElasticIn => "ease_elastic_in",
+            Easing::ElasticOut => "ease_elastic_out",
+            Easing::ElasticInOut => "ease_elastic_in_out",
+            Easing::BounceIn => "ease_bounce_in",
+            Easing::BounceOut => "ease_bounce_out",
+            Easing::BounceInOut => "ease_bounce_in_out",
+        }
+    }
+
+    /// Get the WGSL code of the helper function implementing the easing.
+    ///
+    /// The function is named [`wgsl_fn_name()`] and takes and returns a single
+    /// `f32`.
+    ///
+    /// [`wgsl_fn_name()`]: Easing::wgsl_fn_name
+    pub(crate) fn wgsl_fn_code(&self) -> String {
+        let body = match self {
+            Easing::Linear => "return t;".to_string(),
+            Easing::QuadIn => "return t * t;".to_string(),
+            Easing::QuadOut => "return 1. - (1. - t) * (1. - t);".to_string(),
+            Easing::QuadInOut => {
+                "let u = 2. - 2. * t;\n    return select(1. - u * u * 0.5, 2. * t * t, t < 0.5);"
+                    .to_string()
+            }
+            Easing::CubicIn => "return t * t * t;".to_string(),
+            Easing::CubicOut => "let u = 1. - t;\n    return 1. - u * u * u;".to_string(),
+            Easing::CubicInOut => {
+                "let u = 2. - 2. * t;\n    return select(1. - u * u * u * 0.5, 4. * t * t * t, t < 0.5);"
+                    .to_string()
+            }
+            Easing::ElasticIn => r#"if (t <= 0. || t >= 1.) { return t; }
+    return -exp2(10. * t - 10.) * sin((10. * t - 10.75) * 2.0943951);"#
+                .to_string(),
+            Easing::ElasticOut => r#"if (t <= 0. || t >= 1.) { return t; }
+    return exp2(-10. * t) * sin((10. * t - 0.75) * 2.0943951) + 1.;"#
+                .to_string(),
+            Easing::ElasticInOut => r#"if (t <= 0. || t >= 1.) { return t; }
+    let s = sin((20. * t - 11.125) * 1.3962634);
+    if (t < 0.5) { return -exp2(20. * t - 10.) * s * 0.5; }
+    return exp2(-20. * t + 10.) * s * 0.5 + 1.;"#
+                .to_string(),
+            Easing::BounceIn => {
+                format!("let x = 1. - t;\n    {BOUNCE_OUT_CODE}\n    return 1. - b;")
+            }
+            Easing::BounceOut => format!("let x = t;\n    {BOUNCE_OUT_CODE}\n    return b;"),
+            Easing::BounceInOut => format!(
+                r#"let x = select(2. * t - 1., 1. - 2. * t, t < 0.5);
+    {BOUNCE_OUT_CODE}
+    return select((1. + b) * 0.5, (1. - b) * 0.5, t < 0.5);"#
+            ),
+        };
+        format!(
+            "fn {}(ratio: f32) -> f32 {{\n    let t = saturate(ratio);\n    {}\n}}\n",
+            self.wgsl_fn_name(),
+            body
+        )
+    }
+}
+
+/// Bounce curve shared by the bounce easings.
+fn bounce_out(x: f32) -> f32 {
+    const N1: f32 = 7.5625;
+    const D1: f32 = 2.75;
+    if x < 1. / D1 {
+        N1 * x * x
+    } else if x < 2. / D1 {
+        let y = x - 1.5 / D1;
+        (N1 * y).mul_add(y, 0.75)
+    } else if x < 2.5 / D1 {
+        let y = x - 2.25 / D1;
+        (N1 * y).mul_add(y, 0.9375)
+    } else {
+        let y = x - 2.625 / D1;
+        (N1 * y).mul_add(y, 0.984375)
+    }
+}
+
 /// A gradient curve made of keypoints and associated values.
 ///
 /// The gradient can be sampled anywhere, and will return a linear interpolation
-/// of the values of its closest keys. Sampling before 0 or after 1 returns a
-/// constant value equal to the one of the closest bound.
+/// of the values of its closest keys. By default, sampling before 0 or after 1
+/// returns a constant value equal to the one of the closest bound; this can be
+/// changed with [`with_wrap()`]. The interpolation between two keys can be
+/// made non-linear with [`with_easing()`].
+///
+/// # Editing
+///
+/// Gradients can be edited after construction, for example by a tool UI, with
+/// [`add_key()`], [`remove_key()`], and [`keys_mut()`]. The CPU-side
+/// [`sample()`] uses the same interpolation as the WGSL code generated for the
+/// gradient, so can be used to preview it.
+///
+/// [`with_wrap()`]: crate::Gradient::with_wrap
+/// [`with_easing()`]: crate::Gradient::with_easing
+/// [`add_key()`]: crate::Gradient::add_key
+/// [`remove_key()`]: crate::Gradient::remove_key
+/// [`keys_mut()`]: crate::Gradient::keys_mut
+/// [`sample()`]: crate::Gradient::sample
 ///
 /// # Construction
 ///
@@ -130,6 +437,12 @@ impl Hash for GradientKey<Vec4> {
 #[derive(Debug, Default, Clone, PartialEq, Reflect, Serialize, Deserialize)]
 pub struct Gradient<T: Lerp + FromReflect> {
     keys: Vec<GradientKey<T>>,
+    /// Behavior when sampled outside the \[0:1\] range.
+    #[serde(default)]
+    wrap: GradientWrap,
+    /// Easing applied when interpolating between two consecutive keys.
+    #[serde(default)]
+    easing: Easing,
 }
 
 // SAFETY: This is consistent with the derive, but we can't derive due to trait
@@ -142,6 +455,8 @@ where
 {
     fn hash<H: Hasher>(&self, state: &mut H) {
         self.keys.hash(state);
+        self.wrap.hash(state);
+        self.easing.hash(state);
     }
 }
 
@@ -156,7 +471,11 @@ impl<T: Lerp + FromReflect> Gradient<T> {
     /// assert!(g.is_empty());
     /// ```
     pub const fn new() -> Self {
-        Self { keys: vec![] }
+        Self {
+            keys: vec![],
+            wrap: GradientWrap::Clamp,
+            easing: Easing::Linear,
+        }
     }
 
     /// Create a constant gradient.
@@ -175,6 +494,8 @@ impl<T: Lerp + FromReflect> Gradient<T> {
     pub fn constant(value: T) -> Self {
         Self {
             keys: vec![GradientKey::<T> { ratio: 0., value }],
+            wrap: GradientWrap::Clamp,
+            easing: Easing::Linear,
         }
     }
 
@@ -203,6 +524,8 @@ impl<T: Lerp + FromReflect> Gradient<T> {
                     value: end,
                 },
             ],
+            wrap: GradientWrap::Clamp,
+            easing: Easing::Linear,
         }
     }
 
@@ -243,8 +566,12 @@ impl<T: Lerp + FromReflect> Gradient<T> {
             .into_iter()
             .map(|(ratio, value)| GradientKey { ratio, value })
             .collect::<Vec<_>>();
-        keys.sort_by(|a, b| FloatOrd(a.ratio).cmp(&FloatOrd(b.ratio)));
-        Self { keys }
+        keys.sort_by_key(|key| FloatOrd(key.ratio));
+        Self {
+            keys,
+            wrap: GradientWrap::Clamp,
+            easing: Easing::Linear,
+        }
     }
 
     /// Returns `true` if the gradient contains no key points.
@@ -370,29 +697,131 @@ impl<T: Lerp + FromReflect> Gradient<T> {
         self.keys.insert(index, GradientKey { ratio, value });
     }
 
+    /// Remove the key point at the given index, and return it.
+    ///
+    /// Keys are sorted by ratio, so the index of a key is its position in the
+    /// slice returned by [`keys()`]. To move a key to another ratio, remove it
+    /// and add it again with [`add_key()`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::Gradient;
+    /// let mut g = Gradient::from_keys([(0., 1.), (0.5, 2.), (1., 3.)]);
+    /// let key = g.remove_key(1);
+    /// assert_eq!(key.ratio(), 0.5);
+    /// assert_eq!(g.sample(0.5), 2.);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// This method panics if `index` is out of bounds.
+    ///
+    /// [`keys()`]: crate::Gradient::keys
+    /// [`add_key()`]: crate::Gradient::add_key
+    pub fn remove_key(&mut self, index: usize) -> GradientKey<T> {
+        self.keys.remove(index)
+    }
+
+    /// Remove all key points.
+    pub fn clear(&mut self) {
+        self.keys.clear();
+    }
+
     /// Get the gradient keys.
+    ///
+    /// The keys are sorted by increasing ratio. Keys with the same ratio are
+    /// sorted in insertion order.
     pub fn keys(&self) -> &[GradientKey<T>] {
         &self.keys[..]
     }
 
     /// Get mutable access to the gradient keys.
+    ///
+    /// This allows modifying the value of existing keys. The ratio of a key
+    /// can't be modified in place, as this could break the ordering of the
+    /// keys; see [`remove_key()`] instead.
+    ///
+    /// [`remove_key()`]: crate::Gradient::remove_key
     pub fn keys_mut(&mut self) -> &mut [GradientKey<T>] {
         &mut self.keys[..]
     }
 
+    /// Set the behavior of the gradient when sampled outside the \[0:1\]
+    /// range.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::{Gradient, GradientWrap};
+    /// let g = Gradient::linear(0., 1.).with_wrap(GradientWrap::Repeat);
+    /// assert_eq!(g.sample(1.25), 0.25);
+    /// ```
+    pub fn with_wrap(mut self, wrap: GradientWrap) -> Self {
+        self.wrap = wrap;
+        self
+    }
+
+    /// Set the behavior of the gradient when sampled outside the \[0:1\]
+    /// range.
+    pub fn set_wrap(&mut self, wrap: GradientWrap) {
+        self.wrap = wrap;
+    }
+
+    /// Get the behavior of the gradient when sampled outside the \[0:1\]
+    /// range.
+    pub fn wrap(&self) -> GradientWrap {
+        self.wrap
+    }
+
+    /// Set the easing applied when interpolating between two consecutive keys.
+    ///
+    /// The easing remaps the interpolation ratio between each pair of keys,
+    /// while the value at each key is unchanged.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::{Easing, Gradient};
+    /// let g = Gradient::linear(0., 1.).with_easing(Easing::QuadIn);
+    /// assert_eq!(g.sample(0.5), 0.25);
+    /// ```
+    pub fn with_easing(mut self, easing: Easing) -> Self {
+        self.easing = easing;
+        self
+    }
+
+    /// Set the easing applied when interpolating between two consecutive keys.
+    pub fn set_easing(&mut self, easing: Easing) {
+        self.easing = easing;
+    }
+
+    /// Get the easing applied when interpolating between two consecutive keys.
+    pub fn easing(&self) -> Easing {
+        self.easing
+    }
+
     /// Sample the gradient at the given ratio.
     ///
-    /// If the ratio is exactly equal to those of one or more keys, sample the
-    /// first key in the collection. If the ratio falls between two keys,
-    /// return a linear interpolation of their values. If the ratio is
-    /// before the first key or after the last one, return the first and
-    /// last value, respectively.
+    /// The ratio is first transformed according to the [`wrap()`] mode of the
+    /// gradient. Then, if the ratio is exactly equal to those of one or more
+    /// keys, sample the first key in the collection. If the ratio falls
+    /// between two keys, return an interpolation of their values, eased with
+    /// the [`easing()`] of the gradient (linear by default). If the
+    /// ratio is before the first key or after the last one, return the first
+    /// and last value, respectively.
+    ///
+    /// This matches the WGSL code generated for the gradient.
     ///
     /// # Panics
     ///
     /// This method panics if the gradient is empty (has no key point).
+    ///
+    /// [`wrap()`]: crate::Gradient::wrap
+    /// [`easing()`]: crate::Gradient::easing
     pub fn sample(&self, ratio: f32) -> T {
         assert!(!self.keys.is_empty());
+        let ratio = self.wrap.apply(ratio);
         match self
             .keys
             .binary_search_by(|key| FloatOrd(key.ratio).cmp(&FloatOrd(ratio)))
@@ -411,7 +840,7 @@ impl<T: Lerp + FromReflect> Gradient<T> {
                         let key0 = &self.keys[upper_index - 1];
                         let key1 = &self.keys[upper_index];
                         let t = (ratio - key0.ratio) / (key1.ratio - key0.ratio);
-                        key0.value.lerp(key1.value, t)
+                        key0.value.lerp(key1.value, self.easing.ease(t))
                     } else {
                         // post: sampling point located after the last key
                         self.keys[upper_index - 1].value
@@ -438,6 +867,13 @@ impl<T: Lerp + FromReflect> Gradient<T> {
     pub fn sample_by(&self, start: f32, inc: f32, dst: &mut [T]) {
         let count = dst.len();
         assert!(!self.keys.is_empty());
+        if self.wrap != GradientWrap::Clamp {
+            // Wrapped ratios are not monotonic; sample each point individually
+            for (i, d) in dst.iter_mut().enumerate() {
+                *d = self.sample(inc.mul_add(i as f32, start));
+            }
+            return;
+        }
         let mut ratio = start;
         // pre: sampling points located before the first key
         let first_ratio = self.keys[0].ratio;
@@ -470,7 +906,7 @@ impl<T: Lerp + FromReflect> Gradient<T> {
                 let k0 = &self.keys[ikey - 1];
                 let k1 = &self.keys[ikey];
                 let t = (ratio - k0.ratio) / (k1.ratio - k0.ratio);
-                dst[i] = k0.value.lerp(k1.value, t);
+                dst[i] = k0.value.lerp(k1.value, self.easing.ease(t));
             }
             ratio += inc;
         }
@@ -545,6 +981,144 @@ mod tests {
         }
     }
 
+    #[test]
+    fn remove_key() {
+        let mut g = make_test_gradient();
+        assert_eq!(g.len(), 3);
+        let key = g.remove_key(1);
+        assert_eq!(key.ratio(), 0.8);
+        assert_eq!(key.value, BLUE);
+        assert_eq!(g.len(), 2);
+        assert_eq!(g.sample(0.8), GREEN);
+
+        // Move a key by removing and re-adding it
+        let key = g.remove_key(0);
+        g.add_key(1., key.value);
+        assert_eq!(g.keys()[0].value, GREEN);
+        assert_eq!(g.keys()[1].ratio(), 1.);
+        assert_eq!(g.sample(1.), RED);
+
+        g.clear();
+        assert!(g.is_empty());
+    }
+
+    #[test]
+    fn wrap() {
+        assert_eq!(GradientWrap::default(), GradientWrap::Clamp);
+        for r in [-1.5, -0.25, 0., 0.3, 1., 1.75, 42.5] {
+            assert_eq!(GradientWrap::Clamp.apply(r), r);
+            let repeat = GradientWrap::Repeat.apply(r);
+            assert!((0. ..1.).contains(&repeat));
+            assert_approx_eq!(repeat, r - r.floor());
+            let mirror = GradientWrap::Mirror.apply(r);
+            assert!((0. ..=1.).contains(&mirror));
+        }
+        assert_approx_eq!(GradientWrap::Mirror.apply(0.25), 0.25);
+        assert_approx_eq!(GradientWrap::Mirror.apply(1.25), 0.75);
+        assert_approx_eq!(GradientWrap::Mirror.apply(-0.25), 0.25);
+
+        let g = Gradient::linear(0., 1.);
+        assert_eq!(g.wrap(), GradientWrap::Clamp);
+        assert_eq!(g.sample(1.25), 1.);
+        assert_eq!(g.sample(-0.5), 0.);
+
+        let mut g = g.with_wrap(GradientWrap::Repeat);
+        assert_approx_eq!(g.sample(1.25), 0.25);
+        assert_approx_eq!(g.sample(-0.25), 0.75);
+
+        g.set_wrap(GradientWrap::Mirror);
+        assert_approx_eq!(g.sample(1.25), 0.75);
+        let mut dst = [0.; 4];
+        g.sample_by(0.5, 0.5, &mut dst);
+        for (i, v) in dst.iter().enumerate() {
+            assert_approx_eq!(*v, g.sample(0.5_f32.mul_add(i as f32, 0.5)));
+        }
+
+        // The wrap mode is part of the identity of the gradient
+        assert_ne!(
+            hash_gradient(&Gradient::linear(0., 1.)),
+            hash_gradient(&Gradient::linear(0., 1.).with_wrap(GradientWrap::Mirror))
+        );
+    }
+
+    #[test]
+    fn easing() {
+        let all = [
+            Easing::Linear,
+            Easing::QuadIn,
+            Easing::QuadOut,
+            Easing::QuadInOut,
+            Easing::CubicIn,
+            Easing::CubicOut,
+            Easing::CubicInOut,
+            Easing::ElasticIn,
+            Easing::ElasticOut,
+            Easing::ElasticInOut,
+            Easing::BounceIn,
+            Easing::BounceOut,
+            Easing::BounceInOut,
+        ];
+        assert_eq!(Easing::default(), Easing::Linear);
+        for easing in all {
+            // All easings map the bounds onto themselves, and clamp their input
+            assert_approx_eq!(easing.ease(0.), 0.);
+            assert_approx_eq!(easing.ease(1.), 1.);
+            assert_approx_eq!(easing.ease(-3.), 0.);
+            assert_approx_eq!(easing.ease(5.), 1.);
+            for i in 0..=100 {
+                let v = easing.ease(i as f32 / 100.);
+                assert!(v.is_finite());
+                if easing.is_bounded() {
+                    assert!((-1e-5..=1. + 1e-5).contains(&v));
+                }
+            }
+
+            // Symmetric easings pass through the midpoint
+            if matches!(
+                easing,
+                Easing::Linear
+                    | Easing::QuadInOut
+                    | Easing::CubicInOut
+                    | Easing::ElasticInOut
+                    | Easing::BounceInOut
+            ) {
+                assert_approx_eq!(easing.ease(0.5), 0.5);
+            }
+
+            let code = easing.wgsl_fn_code();
+            assert!(code.starts_with(&format!(
+                "fn {}(ratio: f32) -> f32 {{",
+                easing.wgsl_fn_name()
+            )));
+        }
+        assert_approx_eq!(Easing::QuadIn.ease(0.5), 0.25);
+        assert_approx_eq!(Easing::QuadOut.ease(0.5), 0.75);
+        assert_approx_eq!(Easing::CubicIn.ease(0.5), 0.125);
+        assert_approx_eq!(Easing::CubicOut.ease(0.5), 0.875);
+        assert_approx_eq!(Easing::BounceIn.ease(0.3), 1. - Easing::BounceOut.ease(0.7));
+        assert!(Easing::ElasticOut.ease(0.1) > 1.);
+        assert!(!Easing::ElasticOut.is_bounded());
+        assert!(Easing::BounceOut.is_bounded());
+
+        // Gradient easing applies between consecutive keys
+        let g = Gradient::from_keys([(0., 0.), (0.5, 1.), (1., 3.)]);
+        assert_eq!(g.easing(), Easing::Linear);
+        let mut g = g.with_easing(Easing::QuadIn);
+        assert_approx_eq!(g.sample(0.25), 0.25);
+        assert_approx_eq!(g.sample(0.5), 1.);
+        assert_approx_eq!(g.sample(0.75), 1.5);
+        let mut dst = [0.; 5];
+        g.sample_by(0., 0.25, &mut dst);
+        for (i, v) in dst.iter().enumerate() {
+            assert_approx_eq!(*v, g.sample(0.25 * i as f32));
+        }
+
+        // The easing is part of the identity of the gradient
+        let h = hash_gradient(&g);
+        g.set_easing(Easing::BounceOut);
+        assert_ne!(h, hash_gradient(&g));
+    }
+
     #[test]
     fn with_keys() {
         let g = Gradient::new().with_keys([(0.5, RED), (0.8, BLUE)].into_iter());
@@ -668,6 +1242,15 @@ mod tests {
         // println!("gradient: {:?}", s);
         let g_serde: Gradient<Vec4> = ron::from_str(&s).unwrap();
         assert_eq!(g, g_serde);
+
+        let g = g.with_wrap(GradientWrap::Repeat);
+        let s = ron::to_string(&g).unwrap();
+        let g_serde: Gradient<Vec4> = ron::from_str(&s).unwrap();
+        assert_eq!(g, g_serde);
+
+        // The wrap mode is optional, for backward compatibility
+        let g_serde: Gradient<f32> = ron::from_str("(keys:[(ratio:0.5,value:2.0)])").unwrap();
+        assert_eq!(g_serde, Gradient::new().with_key(0.5, 2.));
     }
 
     /// Hash the given gradient.
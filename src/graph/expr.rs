@@ -103,11 +103,16 @@
 
 use std::{cell::RefCell, num::NonZeroU32, rc::Rc};
 
-use bevy::{reflect::Reflect, utils::thiserror::Error};
+use bevy::{
+    math::{Vec2, Vec3, Vec4},
+    reflect::{Reflect, ReflectMut},
+    utils::{thiserror::Error, HashMap},
+};
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    Attribute, ModifierContext, ParticleLayout, PropertyLayout, ScalarType, ToWgslString, ValueType,
+    calc_func_id, Attribute, Easing, MatrixType, ModifierContext, ParticleLayout, PropertyLayout,
+    ScalarType, ScalarValue, ToWgslString, ValueType, VectorType,
 };
 
 use super::Value;
@@ -142,6 +147,96 @@ impl ExprHandle {
     }
 }
 
+/// Offset all the [`ExprHandle`] found inside a reflected value.
+///
+/// This recursively visits all the reflected fields of `value`, and adds
+/// `offset` to the index of any [`ExprHandle`] found. This is used to fix up
+/// the handles of modifiers and expressions when the module they reference is
+/// appended to another module. Fields which can't be accessed mutably through
+/// reflection are not visited. Fields excluded from reflection
+/// (`#[reflect(ignore)]`) are not visited either, except for the inner modifier
+/// of a [`ConditionalModifier`].
+///
+/// Returns an error if an offset handle overflows the maximum index of a
+/// module.
+///
+/// [`ConditionalModifier`]: crate::ConditionalModifier
+pub(crate) fn offset_expr_handles(value: &mut dyn Reflect, offset: u32) -> Result<(), ExprError> {
+    if let Some(handle) = value.as_any_mut().downcast_mut::<ExprHandle>() {
+        handle.index =
+            handle
+                .index
+                .checked_add(offset)
+                .ok_or(ExprError::InvalidExprHandleError(format!(
+                    "Cannot offset expression handle {:?} by {}, the index overflows.",
+                    handle, offset
+                )))?;
+        return Ok(());
+    }
+
+    // The inner modifier is excluded from reflection, so visit it explicitly.
+    if let Some(conditional) = value
+        .as_any_mut()
+        .downcast_mut::<crate::ConditionalModifier>()
+    {
+        offset_expr_handles(conditional.inner.as_reflect_mut(), offset)?;
+    }
+
+    match value.reflect_mut() {
+        ReflectMut::Struct(s) => {
+            for i in 0..s.field_len() {
+                if let Some(field) = s.field_at_mut(i) {
+                    offset_expr_handles(field, offset)?;
+                }
+            }
+        }
+        ReflectMut::TupleStruct(s) => {
+            for i in 0..s.field_len() {
+                if let Some(field) = s.field_mut(i) {
+                    offset_expr_handles(field, offset)?;
+                }
+            }
+        }
+        ReflectMut::Tuple(t) => {
+            for i in 0..t.field_len() {
+                if let Some(field) = t.field_mut(i) {
+                    offset_expr_handles(field, offset)?;
+                }
+            }
+        }
+        ReflectMut::List(l) => {
+            for i in 0..l.len() {
+                if let Some(item) = l.get_mut(i) {
+                    offset_expr_handles(item, offset)?;
+                }
+            }
+        }
+        ReflectMut::Array(a) => {
+            for i in 0..a.len() {
+                if let Some(item) = a.get_mut(i) {
+                    offset_expr_handles(item, offset)?;
+                }
+            }
+        }
+        ReflectMut::Map(m) => {
+            for i in 0..m.len() {
+                if let Some((_, value)) = m.get_at_mut(i) {
+                    offset_expr_handles(value, offset)?;
+                }
+            }
+        }
+        ReflectMut::Enum(e) => {
+            for i in 0..e.field_len() {
+                if let Some(field) = e.field_at_mut(i) {
+                    offset_expr_handles(field, offset)?;
+                }
+            }
+        }
+        ReflectMut::Value(_) => {}
+    }
+    Ok(())
+}
+
 /// Container for expressions.
 ///
 /// A module represents a storage for a set of expressions used in a single
@@ -154,17 +249,91 @@ impl ExprHandle {
 /// Modules are built incrementally. Expressions are written into the module
 /// through convenience helpers like [`lit()`] or [`attr()`]. Alternatively, an
 /// [`ExprWriter`] can be used to populate a new or existing module. Either way,
-/// once an expression is written into a module, it cannot be modified or
-/// deleted. Modules are not designed to be used as editing structures, but as
-/// storage and serialization ones.
+/// once an expression is written into a module, its handle remains valid.
+///
+/// Writing an expression identical to one already present in the module
+/// doesn't append a new expression, but instead returns the handle of the
+/// existing one. This deduplication keeps the module and the generated shader
+/// code small when the same literal or sub-expression is built multiple
+/// times, and is transparent since an expression always produces the same
+/// value. As a consequence, handles are shared: two separate calls to
+/// `lit(3.)` return the same handle, and modifying the expression behind it
+/// (for example with [`set_literal()`]) affects all its users. The only
+/// exception are expressions with side effects, like random values (see
+/// [`has_side_effect()`]), which are always appended, so that each one
+/// produces its own value.
+///
+/// Modules are not designed to be used as editing structures, but as storage
+/// and serialization ones. Limited editing is available through
+/// [`set_literal()`], [`optimize()`], and [`eliminate_dead_code()`].
 ///
 /// [`EffectAsset`]: crate::EffectAsset
 /// [`lit()`]: Module::lit
 /// [`attr()`]: Module::attr
-#[derive(Debug, Default, Clone, PartialEq, Hash, Reflect, Serialize, Deserialize)]
+/// [`set_literal()`]: Module::set_literal
+/// [`optimize()`]: Module::optimize
+/// [`eliminate_dead_code()`]: Module::eliminate_dead_code
+/// [`has_side_effect()`]: Module::has_side_effect
+#[derive(Default, Clone, Reflect, Serialize, Deserialize)]
 #[serde(transparent)]
 pub struct Module {
     expressions: Vec<Expr>,
+    /// Map from the expressions without side effect to their index, used to
+    /// deduplicate expressions on insertion.
+    #[reflect(ignore)]
+    #[serde(skip)]
+    dedup: HashMap<DedupKey, usize>,
+    /// Number of expressions at the start of `expressions` already indexed in
+    /// `dedup`.
+    #[reflect(ignore)]
+    #[serde(skip)]
+    indexed: usize,
+}
+
+impl std::fmt::Debug for Module {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Module")
+            .field("expressions", &self.expressions)
+            .finish()
+    }
+}
+
+impl PartialEq for Module {
+    fn eq(&self, other: &Self) -> bool {
+        self.expressions == other.expressions
+    }
+}
+
+impl std::hash::Hash for Module {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.expressions.hash(state);
+    }
+}
+
+/// Key for deduplicating expressions in a [`Module`].
+///
+/// This compares literals bit-wise, so that for example `-0.0` and `0.0` are
+/// not merged into a single expression, since they can produce different
+/// results (`1. / x`).
+#[derive(Debug, Clone)]
+struct DedupKey(Expr);
+
+impl PartialEq for DedupKey {
+    fn eq(&self, other: &Self) -> bool {
+        match (&self.0, &other.0) {
+            (Expr::Literal(a), Expr::Literal(b)) => a.value.bitwise_eq(&b.value),
+            (a, b) => a == b,
+        }
+    }
+}
+
+impl Eq for DedupKey {}
+
+impl std::hash::Hash for DedupKey {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        // Bit-wise equal values are also equal, so hash the same.
+        self.0.hash(state);
+    }
 }
 
 macro_rules! impl_module_unary {
@@ -200,17 +369,83 @@ macro_rules! impl_module_ternary {
 impl Module {
     /// Create a new module from an existing collection of expressions.
     pub fn from_raw(expr: Vec<Expr>) -> Self {
-        Self { expressions: expr }
+        Self {
+            expressions: expr,
+            ..Default::default()
+        }
     }
 
-    /// Append a new expression to the module.
+    /// Append a new expression to the module, or return the handle of an
+    /// identical expression already present if the expression doesn't have
+    /// any side effect.
     fn push(&mut self, expr: impl Into<Expr>) -> ExprHandle {
+        let expr = expr.into();
+        self.index_expressions();
+        let key = (!expr.has_side_effect(self)).then(|| DedupKey(expr.clone()));
+        if let Some(key) = &key {
+            if let Some(&index) = self.dedup.get(key) {
+                return ExprHandle::new(Index::new(index as u32 + 1).unwrap());
+            }
+        }
+
+        let index = self.expressions.len();
+        if let Some(key) = key {
+            self.dedup.insert(key, index);
+        }
+        self.expressions.push(expr);
+        self.indexed = self.expressions.len();
+
         #[allow(unsafe_code)]
-        let index: Index = unsafe { NonZeroU32::new_unchecked(self.expressions.len() as u32 + 1) };
-        self.expressions.push(expr.into());
+        let index: Index = unsafe { NonZeroU32::new_unchecked(index as u32 + 1) };
         ExprHandle::new(index)
     }
 
+    /// Index into the deduplication map all the expressions added since the
+    /// last call, for example by [`append()`] or deserialization.
+    ///
+    /// [`append()`]: Module::append
+    fn index_expressions(&mut self) {
+        let mut dedup = std::mem::take(&mut self.dedup);
+        for (index, expr) in self.expressions.iter().enumerate().skip(self.indexed) {
+            if !expr.has_side_effect(self) {
+                dedup.entry(DedupKey(expr.clone())).or_insert(index);
+            }
+        }
+        self.dedup = dedup;
+        self.indexed = self.expressions.len();
+    }
+
+    /// Invalidate the deduplication map after expressions were modified in
+    /// place. The map is rebuilt on next insertion.
+    fn invalidate_dedup(&mut self) {
+        self.dedup.clear();
+        self.indexed = 0;
+    }
+
+    /// Append all the expressions of another module to this module.
+    ///
+    /// The expressions are copied, and their operand handles are fixed up to
+    /// reference the copies. Any [`ExprHandle`] referencing an expression of
+    /// `other` can be converted to reference the copy in this module by adding
+    /// the returned offset to it, which [`offset_expr_handles()`] does.
+    ///
+    /// Returns an error if the combined module exceeds the maximum number of
+    /// expressions. In that case this module is left unchanged.
+    pub(crate) fn append(&mut self, other: &Module) -> Result<u32, ExprError> {
+        let offset = self.expressions.len() as u32;
+        let expressions = other
+            .expressions
+            .iter()
+            .cloned()
+            .map(|mut expr| {
+                offset_expr_handles(&mut expr, offset)?;
+                Ok(expr)
+            })
+            .collect::<Result<Vec<_>, ExprError>>()?;
+        self.expressions.extend(expressions);
+        Ok(offset)
+    }
+
     /// Build a literal expression and append it to the module.
     #[inline]
     pub fn lit<V>(&mut self, value: V) -> ExprHandle
@@ -272,7 +507,9 @@ impl Module {
     impl_module_unary!(length, Length);
     impl_module_unary!(log, Log);
     impl_module_unary!(log2, Log2);
+    impl_module_unary!(mat3_euler, Mat3Euler);
     impl_module_unary!(normalize, Normalize);
+    impl_module_unary!(not, Not);
     impl_module_unary!(pack4x8snorm, Pack4x8snorm);
     impl_module_unary!(pack4x8unorm, Pack4x8unorm);
     impl_module_unary!(saturate, Saturate);
@@ -287,6 +524,16 @@ impl Module {
     impl_module_unary!(y, Y);
     impl_module_unary!(z, Z);
 
+    /// Build a [`UnaryOperator::Ease`] unary expression and append it to the
+    /// module.
+    ///
+    /// This is a shortcut for [`unary(UnaryOperator::Ease(easing),
+    /// inner)`](crate::graph::expr::Module::unary).
+    #[inline]
+    pub fn ease(&mut self, easing: Easing, inner: ExprHandle) -> ExprHandle {
+        self.unary(UnaryOperator::Ease(easing), inner)
+    }
+
     /// Build a binary expression and append it to the module.
     ///
     /// The handles to the expressions representing the left and right operands
@@ -315,22 +562,28 @@ impl Module {
     }
 
     impl_module_binary!(add, Add);
+    impl_module_binary!(and, And);
     impl_module_binary!(cross, Cross);
     impl_module_binary!(distance, Distance);
     impl_module_binary!(div, Div);
     impl_module_binary!(dot, Dot);
+    impl_module_binary!(equal, Equal);
     impl_module_binary!(ge, GreaterThanOrEqual);
     impl_module_binary!(gt, GreaterThan);
     impl_module_binary!(le, LessThanOrEqual);
     impl_module_binary!(lt, LessThan);
+    impl_module_binary!(mat3_rotation, Mat3Rotation);
     impl_module_binary!(max, Max);
     impl_module_binary!(min, Min);
     impl_module_binary!(mul, Mul);
+    impl_module_binary!(not_equal, NotEqual);
+    impl_module_binary!(or, Or);
     impl_module_binary!(rem, Remainder);
     impl_module_binary!(step, Step);
     impl_module_binary!(sub, Sub);
     impl_module_binary!(uniform, UniformRand);
     impl_module_binary!(vec2, Vec2);
+    impl_module_binary!(vec4, Vec4);
 
     /// Build a ternary expression and append it to the module.
     ///
@@ -367,6 +620,9 @@ impl Module {
     }
 
     impl_module_ternary!(mix, Mix);
+    impl_module_ternary!(sdf_box, SdfBox);
+    impl_module_ternary!(sdf_plane, SdfPlane);
+    impl_module_ternary!(sdf_sphere, SdfSphere);
     impl_module_ternary!(smoothstep, SmoothStep);
 
     /// Build a cast expression and append it to the module.
@@ -408,9 +664,110 @@ impl Module {
     #[inline]
     pub fn get_mut(&mut self, expr: ExprHandle) -> Option<&mut Expr> {
         let index = expr.index();
+        self.invalidate_dedup();
         self.expressions.get_mut(index)
     }
 
+    /// Try to calculate a CPU-side upper bound of a scalar expression.
+    ///
+    /// This only handles a small subset of expressions whose value is
+    /// statically known, like literals and random values between literals.
+    /// Returns `None` if the upper bound cannot be determined, for example
+    /// because the expression depends on a property or an attribute.
+    pub(crate) fn scalar_upper_bound(&self, expr: ExprHandle) -> Option<f32> {
+        match self.get(expr)? {
+            Expr::Literal(lit) => match lit.value() {
+                Value::Scalar(s) => Some(s.as_f32()),
+                _ => None,
+            },
+            Expr::Binary { op, left, right } => {
+                let left = self.scalar_upper_bound(*left)?;
+                let right = self.scalar_upper_bound(*right)?;
+                match op {
+                    BinaryOperator::Add => Some(left + right),
+                    BinaryOperator::Max | BinaryOperator::UniformRand => Some(left.max(right)),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Try to calculate CPU-side lower and upper bounds of a scalar
+    /// expression.
+    ///
+    /// Like [`scalar_upper_bound()`], this only handles literals and a small
+    /// set of operators applied to expressions with known bounds.
+    ///
+    /// [`scalar_upper_bound()`]: Module::scalar_upper_bound
+    pub(crate) fn scalar_bounds(&self, expr: ExprHandle) -> Option<(f32, f32)> {
+        match self.get(expr)? {
+            Expr::Literal(lit) => match lit.value() {
+                Value::Scalar(s) => Some((s.as_f32(), s.as_f32())),
+                _ => None,
+            },
+            Expr::Binary { op, left, right } => {
+                let (lmin, lmax) = self.scalar_bounds(*left)?;
+                let (rmin, rmax) = self.scalar_bounds(*right)?;
+                match op {
+                    BinaryOperator::Add => Some((lmin + rmin, lmax + rmax)),
+                    BinaryOperator::Sub => Some((lmin - rmax, lmax - rmin)),
+                    BinaryOperator::Min => Some((lmin.min(rmin), lmax.min(rmax))),
+                    BinaryOperator::Max => Some((lmin.max(rmin), lmax.max(rmax))),
+                    BinaryOperator::UniformRand => Some((lmin.min(rmin), lmax.max(rmax))),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Try to calculate CPU-side component-wise lower and upper bounds of a
+    /// `vec3<f32>` expression.
+    ///
+    /// See [`scalar_bounds()`] for the supported expressions.
+    ///
+    /// [`scalar_bounds()`]: Module::scalar_bounds
+    pub(crate) fn vec3_bounds(&self, expr: ExprHandle) -> Option<(Vec3, Vec3)> {
+        match self.get(expr)? {
+            Expr::Literal(lit) => match lit.value() {
+                Value::Vector(v) if v.vector_type() == VectorType::VEC3F => {
+                    Some((v.as_vec3(), v.as_vec3()))
+                }
+                _ => None,
+            },
+            Expr::Binary { op, left, right } => {
+                let (lmin, lmax) = self.vec3_bounds(*left)?;
+                let (rmin, rmax) = self.vec3_bounds(*right)?;
+                match op {
+                    BinaryOperator::Add => Some((lmin + rmin, lmax + rmax)),
+                    BinaryOperator::Sub => Some((lmin - rmax, lmax - rmin)),
+                    BinaryOperator::Min => Some((lmin.min(rmin), lmax.min(rmax))),
+                    BinaryOperator::Max => Some((lmin.max(rmin), lmax.max(rmax))),
+                    BinaryOperator::UniformRand => Some((lmin.min(rmin), lmax.max(rmax))),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Evaluate a `vec4<f32>` expression on CPU as a unit quaternion, if it's
+    /// a literal.
+    #[cfg(feature = "debug_gizmos")]
+    pub(crate) fn quat_value(&self, expr: ExprHandle) -> Option<bevy::math::Quat> {
+        match self.get(expr)? {
+            Expr::Literal(lit) => match lit.value() {
+                Value::Vector(v) if v.vector_type() == VectorType::VEC4F => {
+                    let q = bevy::math::Quat::from_vec4(v.as_vec4());
+                    (q.length_squared() > 1e-12).then(|| q.normalize())
+                }
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
     /// Get an existing expression from its handle.
     #[inline]
     pub fn try_get(&self, expr: ExprHandle) -> Result<&Expr, ExprError> {
@@ -425,12 +782,78 @@ impl Module {
     #[inline]
     pub fn try_get_mut(&mut self, expr: ExprHandle) -> Result<&mut Expr, ExprError> {
         let index = expr.index();
+        self.invalidate_dedup();
         self.expressions
             .get_mut(index)
             .ok_or(ExprError::InvalidExprHandleError(format!(
                 "Cannot find expression with handle {:?} in the current module. Check that the Module used to build the expression was the same used in the EvalContext or the original EffectAsset.", expr)))
     }
 
+    /// Iterate over all the expressions of the module, with their handle.
+    ///
+    /// The expressions are iterated in the order they were added to the
+    /// module.
+    pub fn iter(&self) -> impl Iterator<Item = (ExprHandle, &Expr)> {
+        self.expressions
+            .iter()
+            .enumerate()
+            .map(|(index, expr)| (ExprHandle::new(Index::new(index as u32 + 1).unwrap()), expr))
+    }
+
+    /// Replace the value of an existing literal expression.
+    ///
+    /// This allows tweaking the value of a literal after the module was built,
+    /// for example from an editor, without rebuilding the expressions which
+    /// reference it. The new value must have the same [`ValueType`] as the
+    /// current one, so that the expressions referencing the literal remain
+    /// valid. On success, the previous value is returned.
+    ///
+    /// Because identical expressions are shared, the new value applies to all
+    /// the expressions referencing the literal, including ones which were
+    /// built with a separate call to [`lit()`] with the same value.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::*;
+    /// let mut module = Module::default();
+    /// let speed = module.lit(3.);
+    /// let old = module.set_literal(speed, 5.).unwrap();
+    /// assert_eq!(old, Value::from(3.));
+    /// assert_eq!(module.get(speed), Some(&Expr::Literal(LiteralExpr::new(5.))));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ExprError::InvalidExprHandleError`] if the handle is not
+    /// valid for this module, or [`ExprError::TypeError`] if the expression is
+    /// not a literal or the value type differs from the one of the literal.
+    ///
+    /// [`lit()`]: Module::lit
+    pub fn set_literal(
+        &mut self,
+        expr: ExprHandle,
+        value: impl Into<Value>,
+    ) -> Result<Value, ExprError> {
+        let value = value.into();
+        let Expr::Literal(literal) = self.try_get_mut(expr)? else {
+            return Err(ExprError::TypeError(format!(
+                "Expression {:?} is not a literal expression.",
+                expr
+            )));
+        };
+        if literal.value_type() != value.value_type() {
+            return Err(ExprError::TypeError(format!(
+                "Cannot replace literal of type {:?} with a value of type {:?}.",
+                literal.value_type(),
+                value.value_type()
+            )));
+        }
+        let old_value = literal.value;
+        literal.value = value;
+        Ok(old_value)
+    }
+
     /// Is the expression resulting in a compile-time constant which can be
     /// hard-coded into a shader's code?
     ///
@@ -456,6 +879,298 @@ impl Module {
         let expr = self.get(expr).unwrap();
         expr.has_side_effect(self)
     }
+
+    /// Resolve the value type of an expression.
+    ///
+    /// Unlike [`Expr::value_type()`], which only knows the type of leaf
+    /// expressions, this recursively resolves the type of the operands of
+    /// unary, binary, and ternary operations, and propagates it through the
+    /// operator to deduce the type of the result. This allows validating an
+    /// expression before it's used, for example to check that a color
+    /// expression produces a `vec4<f32>`, without waiting for the effect to be
+    /// compiled.
+    ///
+    /// Returns `None` if the handle is invalid, if the type cannot be known
+    /// without an evaluation context (for example a property, whose type is
+    /// defined by the effect asset and not the module), or if the operand
+    /// types are not valid for an operator.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::*;
+    /// # use bevy::math::Vec3;
+    /// let mut m = Module::default();
+    /// let v = m.lit(Vec3::ONE);
+    /// let s = m.lit(2.);
+    /// let mul = m.mul(v, s);
+    /// let len = m.length(mul);
+    /// assert_eq!(m.expr_type(mul), Some(ValueType::Vector(VectorType::VEC3F)));
+    /// assert_eq!(m.expr_type(len), Some(ValueType::Scalar(ScalarType::Float)));
+    /// ```
+    pub fn expr_type(&self, expr: ExprHandle) -> Option<ValueType> {
+        match self.get(expr)? {
+            Expr::Unary { op, expr } => op.result_type(self.expr_type(*expr)?),
+            Expr::Binary { op, left, right } => {
+                op.result_type(self.expr_type(*left)?, self.expr_type(*right)?)
+            }
+            Expr::Ternary {
+                op,
+                first,
+                second,
+                third,
+            } => op.result_type(
+                self.expr_type(*first)?,
+                self.expr_type(*second)?,
+                self.expr_type(*third)?,
+            ),
+            expr => expr.value_type(),
+        }
+    }
+
+    /// Fold constant sub-expressions of the module.
+    ///
+    /// This replaces in place any operation whose operands are all literals,
+    /// and whose result can be calculated on the CPU exactly like the GPU
+    /// would, with a literal holding the result. For example `2. * 3.` is
+    /// replaced by the literal `6.`. Folding is applied transitively, so a
+    /// whole constant sub-tree collapses into a single literal. This reduces
+    /// the size of the generated shader code, and the shader compilation time.
+    ///
+    /// The supported operations are the component-wise arithmetic operations
+    /// (add, subtract, multiply, divide, min, max) on floating-point scalars
+    /// and vectors of the same type, the `abs`, `ceil`, `floor`, and
+    /// `saturate` operations on floating-point values, and the extraction of a
+    /// vector component. Other operations, including the ones whose CPU
+    /// result may differ from the GPU one like transcendental functions, are
+    /// left untouched.
+    ///
+    /// Folding doesn't change the handle of any expression, so all handles
+    /// referenced by modifiers remain valid. Sub-expressions which are not
+    /// referenced anymore after folding are not emitted into shader code. To
+    /// also remove them from the module, see [`eliminate_dead_code()`].
+    ///
+    /// Returns the number of expressions folded.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::*;
+    /// let mut module = Module::default();
+    /// let two = module.lit(2.);
+    /// let three = module.lit(3.);
+    /// let six = module.mul(two, three);
+    /// assert_eq!(module.optimize(), 1);
+    /// assert_eq!(module.get(six), Some(&Expr::Literal(LiteralExpr::new(6.))));
+    /// ```
+    ///
+    /// [`eliminate_dead_code()`]: Module::eliminate_dead_code
+    pub fn optimize(&mut self) -> usize {
+        let literal = |module: &Module, expr: ExprHandle| -> Option<Value> {
+            match module.get(expr)? {
+                Expr::Literal(literal) => Some(literal.value()),
+                _ => None,
+            }
+        };
+
+        // Operands are always written into the module before the expressions using
+        // them, so a single forward pass folds entire constant sub-trees.
+        let mut count = 0;
+        for index in 0..self.expressions.len() {
+            let folded = match &self.expressions[index] {
+                Expr::Unary { op, expr } => {
+                    literal(self, *expr).and_then(|value| fold_unary(*op, &value))
+                }
+                Expr::Binary { op, left, right } => literal(self, *left)
+                    .zip(literal(self, *right))
+                    .and_then(|(left, right)| fold_binary(*op, &left, &right)),
+                _ => None,
+            };
+            if let Some(value) = folded {
+                self.expressions[index] = Expr::Literal(LiteralExpr { value });
+                count += 1;
+            }
+        }
+        if count > 0 {
+            self.invalidate_dedup();
+        }
+        count
+    }
+
+    /// Remove all the expressions not reachable from a set of root
+    /// expressions.
+    ///
+    /// The `roots` are generally the expressions referenced by the modifiers
+    /// of an effect. All expressions not used, directly or indirectly, by any
+    /// root are removed from the module. Because this changes the position of
+    /// the expressions in the module, this returns a map from the old handle
+    /// to the new handle of each expression kept. All handles referencing this
+    /// module, including the ones stored in modifiers, must be updated with
+    /// that map. The relative order of the expressions kept is preserved.
+    ///
+    /// Unreachable expressions are never emitted into shader code, so this
+    /// doesn't change the generated shaders; it only reduces the size of the
+    /// module itself, for example before serializing it.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::*;
+    /// let mut module = Module::default();
+    /// let unused = module.lit(1.);
+    /// let x = module.lit(2.);
+    /// let remap = module.eliminate_dead_code(&[x]);
+    /// assert!(!remap.contains_key(&unused));
+    /// let x = remap[&x];
+    /// assert_eq!(module.get(x), Some(&Expr::Literal(LiteralExpr::new(2.))));
+    /// ```
+    pub fn eliminate_dead_code(&mut self, roots: &[ExprHandle]) -> HashMap<ExprHandle, ExprHandle> {
+        // Mark all reachable expressions
+        let mut reachable = vec![false; self.expressions.len()];
+        let mut stack: Vec<ExprHandle> = roots.to_vec();
+        while let Some(expr) = stack.pop() {
+            let index = expr.index();
+            if index >= reachable.len() || reachable[index] {
+                continue;
+            }
+            reachable[index] = true;
+            match &self.expressions[index] {
+                Expr::Unary { expr, .. } => stack.push(*expr),
+                Expr::Binary { left, right, .. } => stack.extend([*left, *right]),
+                Expr::Ternary {
+                    first,
+                    second,
+                    third,
+                    ..
+                } => stack.extend([*first, *second, *third]),
+                Expr::Cast(cast) => stack.push(cast.inner),
+                _ => {}
+            }
+        }
+
+        // Build the remapping of the kept expressions
+        let mut remap = HashMap::default();
+        let mut next = 1;
+        for (index, &is_reachable) in reachable.iter().enumerate() {
+            if is_reachable {
+                let old = ExprHandle::new(Index::new(index as u32 + 1).unwrap());
+                let new = ExprHandle::new(Index::new(next).unwrap());
+                remap.insert(old, new);
+                next += 1;
+            }
+        }
+
+        // Compact the module and fix up the handles of the operands
+        self.invalidate_dedup();
+        let expressions = std::mem::take(&mut self.expressions);
+        self.expressions = expressions
+            .into_iter()
+            .zip(reachable)
+            .filter_map(|(expr, is_reachable)| is_reachable.then_some(expr))
+            .map(|mut expr| {
+                match &mut expr {
+                    Expr::Unary { expr, .. } => *expr = remap[expr],
+                    Expr::Binary { left, right, .. } => {
+                        *left = remap[left];
+                        *right = remap[right];
+                    }
+                    Expr::Ternary {
+                        first,
+                        second,
+                        third,
+                        ..
+                    } => {
+                        *first = remap[first];
+                        *second = remap[second];
+                        *third = remap[third];
+                    }
+                    Expr::Cast(cast) => cast.inner = remap[&cast.inner],
+                    _ => {}
+                }
+                expr
+            })
+            .collect();
+
+        remap
+    }
+}
+
+/// Apply a function to all the components of a floating-point scalar or vector
+/// value.
+fn map_float(value: &Value, f: impl Fn(f32) -> f32) -> Option<Value> {
+    match value {
+        Value::Scalar(ScalarValue::Float(x)) => Some(Value::Scalar(ScalarValue::Float(f(*x)))),
+        Value::Vector(v) if v.elem_type() == ScalarType::Float => {
+            let c = |i| f(v.get::<f32>(i));
+            Some(match v.vector_type().count() {
+                2 => Vec2::new(c(0), c(1)).into(),
+                3 => Vec3::new(c(0), c(1), c(2)).into(),
+                4 => Vec4::new(c(0), c(1), c(2), c(3)).into(),
+                _ => return None,
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Apply a function to all the pairs of components of two floating-point
+/// scalar or vector values of the same type.
+fn zip_float(left: &Value, right: &Value, f: impl Fn(f32, f32) -> f32) -> Option<Value> {
+    match (left, right) {
+        (Value::Scalar(ScalarValue::Float(l)), Value::Scalar(ScalarValue::Float(r))) => {
+            Some(Value::Scalar(ScalarValue::Float(f(*l, *r))))
+        }
+        (Value::Vector(l), Value::Vector(r))
+            if l.elem_type() == ScalarType::Float && l.vector_type() == r.vector_type() =>
+        {
+            let c = |i| f(l.get::<f32>(i), r.get::<f32>(i));
+            Some(match l.vector_type().count() {
+                2 => Vec2::new(c(0), c(1)).into(),
+                3 => Vec3::new(c(0), c(1), c(2)).into(),
+                4 => Vec4::new(c(0), c(1), c(2), c(3)).into(),
+                _ => return None,
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Calculate on the CPU the value of a unary operation on a literal, if
+/// supported.
+fn fold_unary(op: UnaryOperator, value: &Value) -> Option<Value> {
+    let component = |index: usize| -> Option<Value> {
+        match value {
+            Value::Vector(v) if index < v.vector_type().count() => {
+                Some(Value::Scalar(v.value(index)))
+            }
+            _ => None,
+        }
+    };
+    match op {
+        UnaryOperator::Abs => map_float(value, f32::abs),
+        UnaryOperator::Ceil => map_float(value, f32::ceil),
+        UnaryOperator::Floor => map_float(value, f32::floor),
+        UnaryOperator::Saturate => map_float(value, |x| x.clamp(0., 1.)),
+        UnaryOperator::X => component(0),
+        UnaryOperator::Y => component(1),
+        UnaryOperator::Z => component(2),
+        UnaryOperator::W => component(3),
+        _ => None,
+    }
+}
+
+/// Calculate on the CPU the value of a binary operation on two literals, if
+/// supported.
+fn fold_binary(op: BinaryOperator, left: &Value, right: &Value) -> Option<Value> {
+    match op {
+        BinaryOperator::Add => zip_float(left, right, |l, r| l + r),
+        BinaryOperator::Sub => zip_float(left, right, |l, r| l - r),
+        BinaryOperator::Mul => zip_float(left, right, |l, r| l * r),
+        BinaryOperator::Div => zip_float(left, right, |l, r| l / r),
+        BinaryOperator::Min => zip_float(left, right, f32::min),
+        BinaryOperator::Max => zip_float(left, right, f32::max),
+        _ => None,
+    }
 }
 
 /// Errors raised when manipulating expressions [`Expr`] and node graphs
@@ -572,6 +1287,10 @@ pub trait EvalContext {
     /// the returned code. The function can subsequently be called from the
     /// parent context by generating code to call `func_name`, with the correct
     /// arguments.
+    ///
+    /// If a function with the same `func_name` was already emitted by this
+    /// context, for example because the same modifier is applied twice, the
+    /// closure is not invoked and the existing function is reused.
     fn make_fn(
         &mut self,
         func_name: &str,
@@ -580,6 +1299,16 @@ pub trait EvalContext {
         f: &mut dyn FnMut(&mut Module, &mut dyn EvalContext) -> Result<String, ExprError>,
     ) -> Result<(), ExprError>;
 
+    /// Create a helper function.
+    ///
+    /// Emit the given function `code` at shader top level, unless a helper
+    /// function with the same `func_name` was already emitted into the shader
+    /// by this context or any of its parent contexts. This allows generating
+    /// helper functions shared by multiple expressions, without duplicate
+    /// definitions. The `func_name` should generally be derived from the
+    /// content of the function to make it unique.
+    fn make_helper_fn(&mut self, func_name: &str, code: &str);
+
     /// Check if the particle attribute struct is a pointer?
     ///
     /// In some context the attribute struct (named 'particle' in WGSL code) is
@@ -799,6 +1528,11 @@ impl Expr {
                 // Recursively evaluate child expressions throught the context to ensure caching
                 let expr = context.eval(module, *expr)?;
 
+                // Emit the helper function implementing the operator, if any
+                if let Some(code) = op.helper_fn_code() {
+                    context.make_helper_fn(&op.to_wgsl_string(), &code);
+                }
+
                 // if expr.value_type() != self.value_type() {
                 //     return Err(ExprError::TypeError(format!(
                 //         "Cannot apply normalize() function to non-vector expression: {}",
@@ -813,10 +1547,20 @@ impl Expr {
                 })
             }
             Expr::Binary { op, left, right } => {
+                // WGSL only has the short-circuiting logical operators for scalars;
+                // bool vectors use the component-wise bitwise form instead.
+                let is_vector_logic = matches!(*op, BinaryOperator::And | BinaryOperator::Or)
+                    && matches!(module.expr_type(*left), Some(ValueType::Vector(_)));
+
                 // Recursively evaluate child expressions throught the context to ensure caching
                 let left = context.eval(module, *left)?;
                 let right = context.eval(module, *right)?;
 
+                // Emit the helper function implementing the operator, if any
+                if let Some(code) = op.helper_fn_code() {
+                    context.make_helper_fn(&op.to_wgsl_string(), &code);
+                }
+
                 // if !self.input.value_type().is_vector() {
                 //     return Err(ExprError::TypeError(format!(
                 //         "Cannot apply normalize() function to non-vector expression: {}",
@@ -826,6 +1570,9 @@ impl Expr {
 
                 Ok(if op.is_functional() {
                     format!("{}({}, {})", op.to_wgsl_string(), left, right)
+                } else if is_vector_logic {
+                    let op = if *op == BinaryOperator::And { "&" } else { "|" };
+                    format!("({}) {} ({})", left, op, right)
                 } else {
                     format!("({}) {} ({})", left, op.to_wgsl_string(), right)
                 })
@@ -841,6 +1588,11 @@ impl Expr {
                 let second = context.eval(module, *second)?;
                 let third = context.eval(module, *third)?;
 
+                // Emit the helper function implementing the operator, if any
+                if let Some(code) = op.helper_fn_code() {
+                    context.make_helper_fn(&op.to_wgsl_string(), &code);
+                }
+
                 // if !self.input.value_type().is_vector() {
                 //     return Err(ExprError::TypeError(format!(
                 //         "Cannot apply normalize() function to non-vector expression: {}",
@@ -901,6 +1653,11 @@ impl LiteralExpr {
         self.value.value_type()
     }
 
+    /// Get the literal value of the expression.
+    pub fn value(&self) -> Value {
+        self.value
+    }
+
     /// Evaluate the expression in the given context.
     pub fn eval(&self, _context: &dyn EvalContext) -> Result<String, ExprError> {
         Ok(self.value.to_wgsl_string())
@@ -946,6 +1703,11 @@ impl AttributeExpr {
         false
     }
 
+    /// Get the attribute the expression reads.
+    pub fn attribute(&self) -> Attribute {
+        self.attr
+    }
+
     /// Get the value type of the expression.
     pub fn value_type(&self) -> ValueType {
         self.attr.value_type()
@@ -988,6 +1750,11 @@ impl PropertyExpr {
         }
     }
 
+    /// Get the name of the property the expression reads.
+    pub fn property_name(&self) -> &str {
+        &self.property_name
+    }
+
     /// Is the expression resulting in a compile-time constant which can be
     /// hard-coded into a shader's code?
     fn is_const(&self) -> bool {
@@ -1038,6 +1805,11 @@ impl CastExpr {
         }
     }
 
+    /// Get the operand expression to cast.
+    pub fn inner(&self) -> ExprHandle {
+        self.inner
+    }
+
     /// Get the value type of the expression.
     pub fn value_type(&self) -> ValueType {
         self.target
@@ -1121,6 +1893,36 @@ pub enum BuiltInOperator {
     ///
     /// [`AlphaMode::Mask`]: crate::AlphaMode::Mask
     AlphaCutoff,
+    /// Index of the current particle in the particle buffer of its effect.
+    ///
+    /// This value is only available in the update and render contexts. In the
+    /// render context, it's forwarded from the vertex shader to the fragment
+    /// shader only if the [`RenderContext::fragment_code`] uses it. The index
+    /// is stable for the lifetime of the particle, but is recycled once the
+    /// particle dies, and is not related to the spawning order.
+    ///
+    /// [`RenderContext::fragment_code`]: crate::RenderContext::fragment_code
+    ParticleIndex,
+    /// Index of the particle group the current particle belongs to.
+    ///
+    /// This value is only available in the update and render contexts, where
+    /// each group is processed by its own shader.
+    GroupIndex,
+    /// Normalized age of the current particle.
+    ///
+    /// This is the ratio of the [`Attribute::AGE`] of the particle over its
+    /// [`Attribute::LIFETIME`], clamped to \[0:1\]. It's `0` if the lifetime
+    /// is zero or negative, so the division is always safe. This is the value
+    /// that modifiers like the [`ColorOverLifetimeModifier`] use internally,
+    /// and allows driving any other parameter over the lifetime of the
+    /// particles with a custom expression.
+    ///
+    /// Both attributes must be present in the particle layout of the effect,
+    /// that is used by at least one modifier. Otherwise evaluating the
+    /// expression fails with an [`ExprError::GraphEvalError`].
+    ///
+    /// [`ColorOverLifetimeModifier`]: crate::modifier::ColorOverLifetimeModifier
+    AgeRatio,
 }
 
 impl BuiltInOperator {
@@ -1160,6 +1962,9 @@ impl BuiltInOperator {
                 ValueType::Matrix(_) => panic!("Invalid BuiltInOperator::Rand(ValueType::Matrix)."),
             },
             BuiltInOperator::AlphaCutoff => "alpha_cutoff",
+            BuiltInOperator::ParticleIndex => "particle_index",
+            BuiltInOperator::GroupIndex => "group_index",
+            BuiltInOperator::AgeRatio => "age_ratio",
         }
     }
 
@@ -1174,6 +1979,9 @@ impl BuiltInOperator {
             BuiltInOperator::RealDeltaTime => ValueType::Scalar(ScalarType::Float),
             BuiltInOperator::Rand(value_type) => *value_type,
             BuiltInOperator::AlphaCutoff => ValueType::Scalar(ScalarType::Float),
+            BuiltInOperator::ParticleIndex => ValueType::Scalar(ScalarType::Uint),
+            BuiltInOperator::GroupIndex => ValueType::Scalar(ScalarType::Uint),
+            BuiltInOperator::AgeRatio => ValueType::Scalar(ScalarType::Float),
         }
     }
 
@@ -1189,6 +1997,12 @@ impl ToWgslString for BuiltInOperator {
     fn to_wgsl_string(&self) -> String {
         match self {
             BuiltInOperator::Rand(_) => format!("{}()", self.name()),
+            BuiltInOperator::ParticleIndex | BuiltInOperator::GroupIndex => self.name().to_string(),
+            BuiltInOperator::AgeRatio => format!(
+                "select(0., clamp(particle.{0} / particle.{1}, 0., 1.), particle.{1} > 0.)",
+                Attribute::AGE.name(),
+                Attribute::LIFETIME.name()
+            ),
             _ => format!("sim_params.{}", self.name()),
         }
     }
@@ -1215,6 +2029,11 @@ impl BuiltInExpr {
         Self { operator }
     }
 
+    /// Get the built-in operator of the expression.
+    pub fn operator(&self) -> BuiltInOperator {
+        self.operator
+    }
+
     /// Is the expression resulting in a compile-time constant?
     ///
     /// Constant expressions can be hard-coded into a shader's code, making them
@@ -1242,6 +2061,34 @@ impl BuiltInExpr {
 
     /// Evaluate the expression in the given context.
     pub fn eval(&self, context: &mut dyn EvalContext) -> Result<String, ExprError> {
+        if matches!(
+            self.operator,
+            BuiltInOperator::ParticleIndex | BuiltInOperator::GroupIndex
+        ) {
+            let modifier_context = context.modifier_context();
+            let valid_context = ModifierContext::Update | ModifierContext::Render;
+            if !valid_context.contains(modifier_context) {
+                return Err(ExprError::InvalidModifierContext(
+                    modifier_context,
+                    valid_context,
+                ));
+            }
+        }
+
+        if self.operator == BuiltInOperator::AgeRatio {
+            for attr in [Attribute::AGE, Attribute::LIFETIME] {
+                if !context.particle_layout().contains(attr) {
+                    return Err(ExprError::GraphEvalError(format!(
+                        "BuiltInOperator::AgeRatio requires the {} attribute in the particle layout.",
+                        attr.name()
+                    )));
+                }
+            }
+            if context.is_attribute_pointer() {
+                return Ok(self.to_wgsl_string().replace("particle.", "(*particle)."));
+            }
+        }
+
         if self.has_side_effect() {
             let var_name = context.make_local_var();
             context.push_stmt(&format!("let {} = {};", var_name, self.to_wgsl_string()));
@@ -1292,6 +2139,13 @@ pub enum UnaryOperator {
     /// Cosine operator.
     Cos,
 
+    /// Easing operator.
+    ///
+    /// Apply the given easing function to a floating point scalar operand. The
+    /// operand is clamped to \[0:1\] before being eased. See [`Easing`] for
+    /// details.
+    Ease(Easing),
+
     /// Natural exponent operator.
     ///
     /// Return the natural exponentiation of the operand (`e^x`), component-wise
@@ -1350,6 +2204,12 @@ pub enum UnaryOperator {
     /// operands.
     Normalize,
 
+    /// Logical negation operator.
+    ///
+    /// Returns the logical negation of a boolean scalar or vector operand,
+    /// component-wise for vectors.
+    Not,
+
     /// Packing operator from `vec4<f32>` to `u32` (signed normalized).
     ///
     /// Convert the four components of a signed normalized floating point vector
@@ -1408,6 +2268,14 @@ pub enum UnaryOperator {
     /// `[0:1]`.
     Unpack4x8unorm,
 
+    /// Constructor for a 3x3 rotation matrix from Euler angles.
+    ///
+    /// Given a `Vec3` of angles in radians, returns the `mat3x3<f32>` rotation
+    /// matrix rotating first around the X axis by the angle `x`, then around
+    /// the Y axis by the angle `y`, and finally around the Z axis by the angle
+    /// `z`. All rotations are around the fixed axes of the original frame.
+    Mat3Euler,
+
     /// Get the fourth component of a vector.
     ///
     /// This is only valid for vectors of rank 4.
@@ -1441,16 +2309,101 @@ impl UnaryOperator {
             UnaryOperator::X | UnaryOperator::Y | UnaryOperator::Z | UnaryOperator::W
         )
     }
-}
 
-impl ToWgslString for UnaryOperator {
-    fn to_wgsl_string(&self) -> String {
-        match *self {
-            UnaryOperator::Abs => "abs".to_string(),
-            UnaryOperator::All => "all".to_string(),
+    /// Get the type of the result of the operator applied to an operand of the
+    /// given type.
+    ///
+    /// Returns `None` if the operand type is not valid for the operator.
+    pub fn result_type(&self, operand: ValueType) -> Option<ValueType> {
+        let float = ValueType::Scalar(ScalarType::Float);
+        let is_float = match operand {
+            ValueType::Scalar(s) => s == ScalarType::Float,
+            ValueType::Vector(v) => v.elem_type() == ScalarType::Float,
+            ValueType::Matrix(_) => false,
+        };
+        let count = match operand {
+            ValueType::Vector(v) => v.count(),
+            _ => 1,
+        };
+        let component = |min_count: usize, max_count: usize| match operand {
+            ValueType::Scalar(_) if min_count == 1 => Some(operand),
+            ValueType::Vector(v) if (min_count..=max_count).contains(&v.count()) => {
+                Some(ValueType::Scalar(v.elem_type()))
+            }
+            _ => None,
+        };
+        match *self {
+            UnaryOperator::Abs | UnaryOperator::Sign => {
+                (operand.is_numeric() && !operand.is_matrix()).then_some(operand)
+            }
+            UnaryOperator::Ceil
+            | UnaryOperator::Cos
+            | UnaryOperator::Exp
+            | UnaryOperator::Exp2
+            | UnaryOperator::Floor
+            | UnaryOperator::Fract
+            | UnaryOperator::InvSqrt
+            | UnaryOperator::Log
+            | UnaryOperator::Log2
+            | UnaryOperator::Saturate
+            | UnaryOperator::Sin
+            | UnaryOperator::Sqrt
+            | UnaryOperator::Tan => is_float.then_some(operand),
+            UnaryOperator::Normalize => (is_float && count > 1).then_some(operand),
+            UnaryOperator::Ease(_) => (operand == float).then_some(float),
+            UnaryOperator::Length => is_float.then_some(float),
+            UnaryOperator::Not => match operand {
+                ValueType::Scalar(ScalarType::Bool) => Some(operand),
+                ValueType::Vector(v) if v.elem_type() == ScalarType::Bool => Some(operand),
+                _ => None,
+            },
+            UnaryOperator::All | UnaryOperator::Any => match operand {
+                ValueType::Vector(v) if v.elem_type() == ScalarType::Bool => {
+                    Some(ValueType::Scalar(ScalarType::Bool))
+                }
+                _ => None,
+            },
+            UnaryOperator::Pack4x8snorm | UnaryOperator::Pack4x8unorm => (operand
+                == ValueType::Vector(VectorType::VEC4F))
+            .then_some(ValueType::Scalar(ScalarType::Uint)),
+            UnaryOperator::Unpack4x8snorm | UnaryOperator::Unpack4x8unorm => (operand
+                == ValueType::Scalar(ScalarType::Uint))
+            .then_some(ValueType::Vector(VectorType::VEC4F)),
+            UnaryOperator::Mat3Euler => (operand == ValueType::Vector(VectorType::VEC3F))
+                .then_some(ValueType::Matrix(MatrixType::MAT3X3F)),
+            UnaryOperator::X => component(1, 4),
+            UnaryOperator::Y => component(2, 4),
+            UnaryOperator::Z => component(3, 4),
+            UnaryOperator::W => component(4, 4),
+        }
+    }
+
+    /// Get the WGSL code of the helper function implementing the operator, if
+    /// any.
+    ///
+    /// See [`TernaryOperator::helper_fn_code()`] for details.
+    pub fn helper_fn_code(&self) -> Option<String> {
+        match *self {
+            UnaryOperator::Mat3Euler => Some(helper_fn_code(
+                "mat3_euler",
+                "(angles: vec3<f32>) -> mat3x3<f32>",
+                MAT3_EULER_BODY,
+            )),
+            UnaryOperator::Ease(easing) => Some(easing.wgsl_fn_code()),
+            _ => None,
+        }
+    }
+}
+
+impl ToWgslString for UnaryOperator {
+    fn to_wgsl_string(&self) -> String {
+        match *self {
+            UnaryOperator::Abs => "abs".to_string(),
+            UnaryOperator::All => "all".to_string(),
             UnaryOperator::Any => "any".to_string(),
             UnaryOperator::Ceil => "ceil".to_string(),
             UnaryOperator::Cos => "cos".to_string(),
+            UnaryOperator::Ease(easing) => easing.wgsl_fn_name().to_string(),
             UnaryOperator::Exp => "exp".to_string(),
             UnaryOperator::Exp2 => "exp2".to_string(),
             UnaryOperator::Floor => "floor".to_string(),
@@ -1460,6 +2413,7 @@ impl ToWgslString for UnaryOperator {
             UnaryOperator::Log => "log".to_string(),
             UnaryOperator::Log2 => "log2".to_string(),
             UnaryOperator::Normalize => "normalize".to_string(),
+            UnaryOperator::Not => "!".to_string(),
             UnaryOperator::Pack4x8snorm => "pack4x8snorm".to_string(),
             UnaryOperator::Pack4x8unorm => "pack4x8unorm".to_string(),
             UnaryOperator::Saturate => "saturate".to_string(),
@@ -1469,6 +2423,7 @@ impl ToWgslString for UnaryOperator {
             UnaryOperator::Tan => "tan".to_string(),
             UnaryOperator::Unpack4x8snorm => "unpack4x8snorm".to_string(),
             UnaryOperator::Unpack4x8unorm => "unpack4x8unorm".to_string(),
+            UnaryOperator::Mat3Euler => helper_fn_name("mat3_euler", MAT3_EULER_BODY),
             UnaryOperator::W => "w".to_string(),
             UnaryOperator::X => "x".to_string(),
             UnaryOperator::Y => "y".to_string(),
@@ -1489,6 +2444,15 @@ pub enum BinaryOperator {
     /// Returns the sum of its operands. Only valid for numeric operands.
     Add,
 
+    /// Logical "and" operator.
+    ///
+    /// Returns `true` if both operands are `true`. Only valid for bool scalar
+    /// or vectors of the same rank. Vectors are combined component-wise. Both
+    /// operands are always evaluated, even for scalars; in particular any
+    /// expression with side effects in the right operand, like a random value,
+    /// is evaluated even if the left operand is `false`.
+    And,
+
     /// Cross product operator.
     ///
     /// Returns the cross product of the left and right operands. Only valid for
@@ -1514,6 +2478,13 @@ pub enum BinaryOperator {
     /// vector type operands. Always produce a scalar floating-point result.
     Dot,
 
+    /// Equality operator.
+    ///
+    /// Returns `true` if the left operand is equal to the right operand. Only
+    /// valid for scalar or vector types. If the operands are vectors, they
+    /// must be of the same rank, and the result is a bool vector of that rank.
+    Equal,
+
     /// Greater-than operator.
     ///
     /// Returns `true` if the left operand is strictly greater than the right
@@ -1567,6 +2538,22 @@ pub enum BinaryOperator {
     /// Returns the product of its operands. Only valid for numeric operands.
     Mul,
 
+    /// Inequality operator.
+    ///
+    /// Returns `true` if the left operand is not equal to the right operand.
+    /// Only valid for scalar or vector types. If the operands are vectors, they
+    /// must be of the same rank, and the result is a bool vector of that rank.
+    NotEqual,
+
+    /// Logical "or" operator.
+    ///
+    /// Returns `true` if any of the operands is `true`. Only valid for bool
+    /// scalar or vectors of the same rank. Vectors are combined component-wise.
+    /// Both operands are always evaluated, even for scalars; in particular any
+    /// expression with side effects in the right operand, like a random value,
+    /// is evaluated even if the left operand is `true`.
+    Or,
+
     /// Remainder operator.
     ///
     /// Returns the remainder of the division of the first operand by the
@@ -1599,11 +2586,24 @@ pub enum BinaryOperator {
     /// scalar type.
     UniformRand,
 
+    /// Constructor for a 3x3 rotation matrix from an axis and an angle.
+    ///
+    /// Given a `Vec3` rotation axis, which doesn't need to be normalized, and
+    /// a floating point scalar angle in radians, returns the `mat3x3<f32>`
+    /// matrix of the rotation by that angle around that axis.
+    Mat3Rotation,
+
     /// Constructor for 2-element vectors.
     ///
     /// Given two scalar elements `x` and `y`, returns the vector consisting of
     /// those two elements `(x, y)`.
     Vec2,
+
+    /// Constructor for 4-element vectors.
+    ///
+    /// Given a 3-element vector `xyz` and a scalar element `w`, returns the
+    /// vector consisting of those four elements `(x, y, z, w)`.
+    Vec4,
 }
 
 impl BinaryOperator {
@@ -1616,22 +2616,130 @@ impl BinaryOperator {
     pub fn is_functional(&self) -> bool {
         match *self {
             BinaryOperator::Add
+            | BinaryOperator::And
             | BinaryOperator::Div
+            | BinaryOperator::Equal
             | BinaryOperator::GreaterThan
             | BinaryOperator::GreaterThanOrEqual
             | BinaryOperator::LessThan
             | BinaryOperator::LessThanOrEqual
             | BinaryOperator::Mul
+            | BinaryOperator::NotEqual
+            | BinaryOperator::Or
             | BinaryOperator::Remainder
             | BinaryOperator::Sub => false,
             BinaryOperator::Cross
             | BinaryOperator::Distance
             | BinaryOperator::Dot
+            | BinaryOperator::Mat3Rotation
             | BinaryOperator::Max
             | BinaryOperator::Min
             | BinaryOperator::Step
             | BinaryOperator::UniformRand
-            | BinaryOperator::Vec2 => true,
+            | BinaryOperator::Vec2
+            | BinaryOperator::Vec4 => true,
+        }
+    }
+
+    /// Get the type of the result of the operator applied to operands of the
+    /// given types.
+    ///
+    /// Returns `None` if the operand types are not valid for the operator.
+    pub fn result_type(&self, left: ValueType, right: ValueType) -> Option<ValueType> {
+        let float = ValueType::Scalar(ScalarType::Float);
+        let is_float = |ty: ValueType| match ty {
+            ValueType::Scalar(s) => s == ScalarType::Float,
+            ValueType::Vector(v) => v.elem_type() == ScalarType::Float,
+            ValueType::Matrix(_) => false,
+        };
+        let same = (left == right).then_some(left);
+        match *self {
+            BinaryOperator::Add
+            | BinaryOperator::Sub
+            | BinaryOperator::Div
+            | BinaryOperator::Remainder => arithmetic_result_type(left, right),
+            BinaryOperator::Mul => match (left, right) {
+                // Matrix products, using WGSL's column-major convention where
+                // `matCxR` has C columns and R rows.
+                (ValueType::Matrix(m), ValueType::Vector(v)) => (v.elem_type()
+                    == ScalarType::Float
+                    && v.count() == m.cols())
+                .then(|| ValueType::Vector(VectorType::new(ScalarType::Float, m.rows() as u8))),
+                (ValueType::Vector(v), ValueType::Matrix(m)) => (v.elem_type()
+                    == ScalarType::Float
+                    && v.count() == m.rows())
+                .then(|| ValueType::Vector(VectorType::new(ScalarType::Float, m.cols() as u8))),
+                (ValueType::Matrix(l), ValueType::Matrix(r)) => (l.cols() == r.rows())
+                    .then(|| ValueType::Matrix(MatrixType::new(r.cols() as u8, l.rows() as u8))),
+                (ValueType::Matrix(m), s) | (s, ValueType::Matrix(m)) => {
+                    (s == float).then_some(ValueType::Matrix(m))
+                }
+                _ => arithmetic_result_type(left, right),
+            },
+            BinaryOperator::Min | BinaryOperator::Max | BinaryOperator::UniformRand => {
+                same.filter(|ty| ty.is_numeric() && !ty.is_matrix())
+            }
+            BinaryOperator::Step => same.filter(|ty| is_float(*ty)),
+            BinaryOperator::GreaterThan
+            | BinaryOperator::GreaterThanOrEqual
+            | BinaryOperator::LessThan
+            | BinaryOperator::LessThanOrEqual => match same? {
+                ValueType::Scalar(s) if s.is_numeric() => Some(ValueType::Scalar(ScalarType::Bool)),
+                ValueType::Vector(v) if v.is_numeric() => Some(ValueType::Vector(VectorType::new(
+                    ScalarType::Bool,
+                    v.count() as u8,
+                ))),
+                _ => None,
+            },
+            BinaryOperator::Equal | BinaryOperator::NotEqual => match same? {
+                ValueType::Scalar(_) => Some(ValueType::Scalar(ScalarType::Bool)),
+                ValueType::Vector(v) => Some(ValueType::Vector(VectorType::new(
+                    ScalarType::Bool,
+                    v.count() as u8,
+                ))),
+                ValueType::Matrix(_) => None,
+            },
+            BinaryOperator::And | BinaryOperator::Or => match same? {
+                ValueType::Scalar(ScalarType::Bool) => same,
+                ValueType::Vector(v) if v.elem_type() == ScalarType::Bool => same,
+                _ => None,
+            },
+            BinaryOperator::Cross => same.filter(|ty| *ty == ValueType::Vector(VectorType::VEC3F)),
+            BinaryOperator::Dot => match same? {
+                ValueType::Vector(v) if v.is_numeric() => Some(ValueType::Scalar(v.elem_type())),
+                _ => None,
+            },
+            BinaryOperator::Distance => same.filter(|ty| is_float(*ty)).map(|_| float),
+            BinaryOperator::Mat3Rotation => (left == ValueType::Vector(VectorType::VEC3F)
+                && right == float)
+                .then_some(ValueType::Matrix(MatrixType::MAT3X3F)),
+            BinaryOperator::Vec2 => match same? {
+                ValueType::Scalar(s) => Some(ValueType::Vector(VectorType::new(s, 2))),
+                _ => None,
+            },
+            BinaryOperator::Vec4 => match (left, right) {
+                (ValueType::Vector(v), ValueType::Scalar(s))
+                    if v.count() == 3 && v.elem_type() == s =>
+                {
+                    Some(ValueType::Vector(VectorType::new(s, 4)))
+                }
+                _ => None,
+            },
+        }
+    }
+
+    /// Get the WGSL code of the helper function implementing the operator, if
+    /// any.
+    ///
+    /// See [`TernaryOperator::helper_fn_code()`] for details.
+    pub fn helper_fn_code(&self) -> Option<String> {
+        match *self {
+            BinaryOperator::Mat3Rotation => Some(helper_fn_code(
+                "mat3_rotation",
+                "(axis: vec3<f32>, angle: f32) -> mat3x3<f32>",
+                MAT3_ROTATION_BODY,
+            )),
+            _ => None,
         }
     }
 }
@@ -1640,22 +2748,28 @@ impl ToWgslString for BinaryOperator {
     fn to_wgsl_string(&self) -> String {
         match *self {
             BinaryOperator::Add => "+".to_string(),
+            BinaryOperator::And => "&&".to_string(),
             BinaryOperator::Cross => "cross".to_string(),
             BinaryOperator::Distance => "distance".to_string(),
             BinaryOperator::Div => "/".to_string(),
             BinaryOperator::Dot => "dot".to_string(),
+            BinaryOperator::Equal => "==".to_string(),
             BinaryOperator::GreaterThan => ">".to_string(),
             BinaryOperator::GreaterThanOrEqual => ">=".to_string(),
             BinaryOperator::LessThan => "<".to_string(),
             BinaryOperator::LessThanOrEqual => "<=".to_string(),
+            BinaryOperator::Mat3Rotation => helper_fn_name("mat3_rotation", MAT3_ROTATION_BODY),
             BinaryOperator::Max => "max".to_string(),
             BinaryOperator::Min => "min".to_string(),
             BinaryOperator::Mul => "*".to_string(),
+            BinaryOperator::NotEqual => "!=".to_string(),
+            BinaryOperator::Or => "||".to_string(),
             BinaryOperator::Remainder => "%".to_string(),
             BinaryOperator::Step => "step".to_string(),
             BinaryOperator::Sub => "-".to_string(),
             BinaryOperator::UniformRand => "rand_uniform".to_string(),
             BinaryOperator::Vec2 => "vec2".to_string(),
+            BinaryOperator::Vec4 => "vec4".to_string(),
         }
     }
 }
@@ -1697,6 +2811,182 @@ pub enum TernaryOperator {
     /// Given three scalar elements `x`, `y`, and `z`, returns the vector
     /// consisting of those three elements `(x, y, z)`.
     Vec3,
+
+    /// Signed distance to a sphere.
+    ///
+    /// Given a point `p`, a sphere center `c`, and a sphere radius `r`, returns
+    /// the signed distance from the point to the surface of the sphere. The
+    /// distance is negative inside the sphere, and positive outside.
+    ///
+    /// The point and center are `Vec3`, and the radius is a floating point
+    /// scalar. The result is a floating point scalar.
+    SdfSphere,
+
+    /// Signed distance to an axis-aligned box.
+    ///
+    /// Given a point `p`, a box center `c`, and the box half-extents `h`,
+    /// returns the signed distance from the point to the surface of the box.
+    /// The distance is negative inside the box, and positive outside.
+    ///
+    /// All operands are `Vec3`. The result is a floating point scalar.
+    SdfBox,
+
+    /// Signed distance to a plane.
+    ///
+    /// Given a point `p`, a point `o` on the plane, and the plane normal `n`,
+    /// returns the signed distance from the point to the plane. The distance is
+    /// positive on the side the normal points to, and negative on the other
+    /// side. The normal doesn't need to be normalized.
+    ///
+    /// All operands are `Vec3`. The result is a floating point scalar.
+    SdfPlane,
+}
+
+/// Get the type of the result of a component-wise arithmetic operation.
+///
+/// Both operands must be numeric scalars or vectors of the same element type.
+/// A scalar operand is implicitly splatted to match a vector one.
+fn arithmetic_result_type(left: ValueType, right: ValueType) -> Option<ValueType> {
+    match (left, right) {
+        (ValueType::Scalar(l), ValueType::Scalar(r)) if l == r && l.is_numeric() => Some(left),
+        (ValueType::Vector(l), ValueType::Vector(r)) if l == r && l.is_numeric() => Some(left),
+        (ValueType::Scalar(s), ValueType::Vector(v))
+        | (ValueType::Vector(v), ValueType::Scalar(s))
+            if s == v.elem_type() && s.is_numeric() =>
+        {
+            Some(ValueType::Vector(v))
+        }
+        _ => None,
+    }
+}
+
+/// Body of the helper function implementing [`UnaryOperator::Mat3Euler`].
+const MAT3_EULER_BODY: &str = r#"{
+    let s = sin(angles);
+    let c = cos(angles);
+    let rx = mat3x3<f32>(vec3<f32>(1., 0., 0.), vec3<f32>(0., c.x, s.x), vec3<f32>(0., -s.x, c.x));
+    let ry = mat3x3<f32>(vec3<f32>(c.y, 0., -s.y), vec3<f32>(0., 1., 0.), vec3<f32>(s.y, 0., c.y));
+    let rz = mat3x3<f32>(vec3<f32>(c.z, s.z, 0.), vec3<f32>(-s.z, c.z, 0.), vec3<f32>(0., 0., 1.));
+    return rz * ry * rx;
+}"#;
+
+/// Body of the helper function implementing [`BinaryOperator::Mat3Rotation`].
+const MAT3_ROTATION_BODY: &str = r#"{
+    let a = normalize(axis);
+    let s = sin(angle);
+    let c = cos(angle);
+    let t = 1. - c;
+    return mat3x3<f32>(
+        vec3<f32>(t * a.x * a.x + c, t * a.x * a.y + s * a.z, t * a.x * a.z - s * a.y),
+        vec3<f32>(t * a.x * a.y - s * a.z, t * a.y * a.y + c, t * a.y * a.z + s * a.x),
+        vec3<f32>(t * a.x * a.z + s * a.y, t * a.y * a.z - s * a.x, t * a.z * a.z + c),
+    );
+}"#;
+
+/// Body of the helper function implementing [`TernaryOperator::SdfSphere`].
+const SDF_SPHERE_BODY: &str = r#"{
+    return length(p - c) - r;
+}"#;
+
+/// Body of the helper function implementing [`TernaryOperator::SdfBox`].
+const SDF_BOX_BODY: &str = r#"{
+    let q = abs(p - c) - h;
+    return length(max(q, vec3<f32>(0.))) + min(max(q.x, max(q.y, q.z)), 0.);
+}"#;
+
+/// Body of the helper function implementing [`TernaryOperator::SdfPlane`].
+const SDF_PLANE_BODY: &str = r#"{
+    return dot(p - o, normalize(n));
+}"#;
+
+/// Get the name of a helper function implementing an operator.
+///
+/// Helper functions are named after their content to prevent collisions.
+fn helper_fn_name(prefix: &str, body: &str) -> String {
+    format!("{}_{:016X}", prefix, calc_func_id(&body))
+}
+
+/// Get the WGSL code of a helper function implementing an operator.
+fn helper_fn_code(prefix: &str, signature: &str, body: &str) -> String {
+    format!(
+        "fn {}{} {}\n",
+        helper_fn_name(prefix, body),
+        signature,
+        body
+    )
+}
+
+impl TernaryOperator {
+    /// Get the type of the result of the operator applied to operands of the
+    /// given types.
+    ///
+    /// Returns `None` if the operand types are not valid for the operator.
+    pub fn result_type(
+        &self,
+        first: ValueType,
+        second: ValueType,
+        third: ValueType,
+    ) -> Option<ValueType> {
+        let float = ValueType::Scalar(ScalarType::Float);
+        let vec3 = ValueType::Vector(VectorType::VEC3F);
+        let is_float = |ty: ValueType| match ty {
+            ValueType::Scalar(s) => s == ScalarType::Float,
+            ValueType::Vector(v) => v.elem_type() == ScalarType::Float,
+            ValueType::Matrix(_) => false,
+        };
+        match *self {
+            TernaryOperator::Mix => {
+                (first == second && is_float(first) && (third == first || third == float))
+                    .then_some(first)
+            }
+            TernaryOperator::SmoothStep => {
+                (first == second && second == third && is_float(third)).then_some(third)
+            }
+            TernaryOperator::Vec3 => match (first, second, third) {
+                (ValueType::Scalar(x), ValueType::Scalar(y), ValueType::Scalar(z))
+                    if x == y && y == z =>
+                {
+                    Some(ValueType::Vector(VectorType::new(x, 3)))
+                }
+                _ => None,
+            },
+            TernaryOperator::SdfSphere => {
+                (first == vec3 && second == vec3 && third == float).then_some(float)
+            }
+            TernaryOperator::SdfBox | TernaryOperator::SdfPlane => {
+                (first == vec3 && second == vec3 && third == vec3).then_some(float)
+            }
+        }
+    }
+
+    /// Get the WGSL code of the helper function implementing the operator, if
+    /// any.
+    ///
+    /// Operators without a WGSL built-in equivalent are implemented by a helper
+    /// function emitted at shader top level, named after the operator's
+    /// [`to_wgsl_string()`]. Other operators return `None`.
+    ///
+    /// [`to_wgsl_string()`]: crate::ToWgslString::to_wgsl_string
+    pub fn helper_fn_code(&self) -> Option<String> {
+        match *self {
+            TernaryOperator::SdfSphere => Some(helper_fn_code(
+                "sdf_sphere",
+                "(p: vec3<f32>, c: vec3<f32>, r: f32) -> f32",
+                SDF_SPHERE_BODY,
+            )),
+            TernaryOperator::SdfBox => Some(helper_fn_code(
+                "sdf_box",
+                "(p: vec3<f32>, c: vec3<f32>, h: vec3<f32>) -> f32",
+                SDF_BOX_BODY,
+            )),
+            TernaryOperator::SdfPlane => Some(helper_fn_code(
+                "sdf_plane",
+                "(p: vec3<f32>, o: vec3<f32>, n: vec3<f32>) -> f32",
+                SDF_PLANE_BODY,
+            )),
+            _ => None,
+        }
+    }
 }
 
 impl ToWgslString for TernaryOperator {
@@ -1705,6 +2995,9 @@ impl ToWgslString for TernaryOperator {
             TernaryOperator::Mix => "mix".to_string(),
             TernaryOperator::SmoothStep => "smoothstep".to_string(),
             TernaryOperator::Vec3 => "vec3".to_string(),
+            TernaryOperator::SdfSphere => helper_fn_name("sdf_sphere", SDF_SPHERE_BODY),
+            TernaryOperator::SdfBox => helper_fn_name("sdf_box", SDF_BOX_BODY),
+            TernaryOperator::SdfPlane => helper_fn_name("sdf_plane", SDF_PLANE_BODY),
         }
     }
 }
@@ -1882,6 +3175,223 @@ impl ExprWriter {
         ))))
     }
 
+    /// Create a new writer expression representing the signed distance from a
+    /// point to a sphere.
+    ///
+    /// The distance is negative inside the sphere, and positive outside. See
+    /// [`TernaryOperator::SdfSphere`] for details.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::*;
+    /// # use bevy::math::Vec3;
+    /// let mut w = ExprWriter::new();
+    /// let pos = w.attr(Attribute::POSITION);
+    /// let d = w.sdf_sphere(pos, w.lit(Vec3::ZERO), w.lit(2.));
+    /// ```
+    pub fn sdf_sphere(
+        &self,
+        point: WriterExpr,
+        center: WriterExpr,
+        radius: WriterExpr,
+    ) -> WriterExpr {
+        point.ternary_op(center, radius, TernaryOperator::SdfSphere)
+    }
+
+    /// Create a new writer expression representing the signed distance from a
+    /// point to an axis-aligned box.
+    ///
+    /// The distance is negative inside the box, and positive outside. See
+    /// [`TernaryOperator::SdfBox`] for details.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::*;
+    /// # use bevy::math::Vec3;
+    /// let mut w = ExprWriter::new();
+    /// let pos = w.attr(Attribute::POSITION);
+    /// let d = w.sdf_box(pos, w.lit(Vec3::ZERO), w.lit(Vec3::ONE));
+    /// ```
+    pub fn sdf_box(
+        &self,
+        point: WriterExpr,
+        center: WriterExpr,
+        half_extents: WriterExpr,
+    ) -> WriterExpr {
+        point.ternary_op(center, half_extents, TernaryOperator::SdfBox)
+    }
+
+    /// Create a new writer expression representing the signed distance from a
+    /// point to a plane.
+    ///
+    /// The distance is positive on the side the normal points to, and negative
+    /// on the other side. See [`TernaryOperator::SdfPlane`] for details.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::*;
+    /// # use bevy::math::Vec3;
+    /// let mut w = ExprWriter::new();
+    /// let pos = w.attr(Attribute::POSITION);
+    /// let d = w.sdf_plane(pos, w.lit(Vec3::ZERO), w.lit(Vec3::Y));
+    /// ```
+    pub fn sdf_plane(
+        &self,
+        point: WriterExpr,
+        origin: WriterExpr,
+        normal: WriterExpr,
+    ) -> WriterExpr {
+        point.ternary_op(origin, normal, TernaryOperator::SdfPlane)
+    }
+
+    /// Create a new writer expression representing a 3x3 rotation matrix
+    /// around an axis.
+    ///
+    /// The `axis` is a `Vec3` which doesn't need to be normalized, and the
+    /// `angle` is a floating point scalar in radians. See
+    /// [`BinaryOperator::Mat3Rotation`] for details.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the type of `axis` or `angle` is known when building the
+    /// expression and is invalid.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::*;
+    /// # use bevy::math::Vec3;
+    /// let mut w = ExprWriter::new();
+    /// // Rotation around the Y axis by an angle increasing with time
+    /// let rot = w.mat3_rotation(w.lit(Vec3::Y), w.time());
+    /// ```
+    pub fn mat3_rotation(&self, axis: WriterExpr, angle: WriterExpr) -> WriterExpr {
+        self.check_type(&axis, VectorType::VEC3F.into(), "rotation axis");
+        self.check_type(&angle, ScalarType::Float.into(), "rotation angle");
+        axis.binary_op(angle, BinaryOperator::Mat3Rotation)
+    }
+
+    /// Create a new writer expression representing a 3x3 rotation matrix from
+    /// Euler angles.
+    ///
+    /// The `angles` is a `Vec3` of angles in radians, applied around the X, Y,
+    /// and Z axes in that order. See [`UnaryOperator::Mat3Euler`] for details.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the type of `angles` is known when building the expression
+    /// and is invalid.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::*;
+    /// # use bevy::math::Vec3;
+    /// let mut w = ExprWriter::new();
+    /// let rot = w.mat3_euler(w.lit(Vec3::new(0.5, 0., 1.2)));
+    /// ```
+    pub fn mat3_euler(&self, angles: WriterExpr) -> WriterExpr {
+        self.check_type(&angles, VectorType::VEC3F.into(), "Euler angles");
+        angles.unary_op(UnaryOperator::Mat3Euler)
+    }
+
+    /// Create a new writer expression representing an easing function applied
+    /// to a ratio.
+    ///
+    /// The ratio `t` is a floating point scalar, clamped to \[0:1\] before
+    /// being eased. See [`Easing`] for the list of available functions.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the type of `t` is known when building the expression and is
+    /// not a floating point scalar.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::*;
+    /// let mut w = ExprWriter::new();
+    /// // Lifetime ratio of the particle, eased out with a bounce
+    /// let ratio = w.attr(Attribute::AGE) / w.attr(Attribute::LIFETIME);
+    /// let eased = w.ease(Easing::BounceOut, ratio);
+    /// ```
+    pub fn ease(&self, easing: Easing, t: WriterExpr) -> WriterExpr {
+        self.check_type(&t, ScalarType::Float.into(), "easing ratio");
+        t.unary_op(UnaryOperator::Ease(easing))
+    }
+
+    /// Create a new writer expression representing the transform of a vector
+    /// by a matrix.
+    ///
+    /// This is the matrix-vector product `mat * vec`. The number of columns of
+    /// the matrix must be equal to the number of components of the vector. The
+    /// result is a vector with as many components as the matrix has rows.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the types of `mat` and `vec` are known when building the
+    /// expression and are incompatible.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::*;
+    /// # use bevy::math::Vec3;
+    /// let mut w = ExprWriter::new();
+    /// // Rotate the particle velocity around the Y axis by an angle increasing
+    /// // with time.
+    /// let rot = w.mat3_rotation(w.lit(Vec3::Y), w.time());
+    /// let velocity = w.transform(rot, w.attr(Attribute::VELOCITY));
+    /// ```
+    pub fn transform(&self, mat: WriterExpr, vec: WriterExpr) -> WriterExpr {
+        let mat_type = self.value_type(&mat);
+        let vec_type = self.value_type(&vec);
+        match (mat_type, vec_type) {
+            (Some(ValueType::Matrix(mat_type)), Some(ValueType::Vector(vec_type))) => {
+                assert!(
+                    vec_type.elem_type() == ScalarType::Float && mat_type.cols() == vec_type.count(),
+                    "Cannot transform a vector of type {:?} by a matrix of type {:?}.",
+                    vec_type,
+                    mat_type
+                );
+            }
+            (Some(ValueType::Matrix(_)), None) | (None, Some(ValueType::Vector(_))) | (None, None) => {}
+            (mat_type, vec_type) => panic!(
+                "Cannot transform a value of type {:?} by a value of type {:?}; expected a vector and a matrix.",
+                vec_type, mat_type
+            ),
+        }
+        mat * vec
+    }
+
+    /// Get the value type of a writer expression, if known when building the
+    /// expression.
+    fn value_type(&self, expr: &WriterExpr) -> Option<ValueType> {
+        self.module
+            .borrow()
+            .get(expr.expr)
+            .and_then(|expr| expr.value_type())
+    }
+
+    /// Check the value type of a writer expression, if known when building the
+    /// expression.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the type is known and different from `expected`.
+    fn check_type(&self, expr: &WriterExpr, expected: ValueType, what: &str) {
+        if let Some(value_type) = self.value_type(expr) {
+            assert_eq!(
+                value_type, expected,
+                "Invalid type for {}: expected {:?}, found {:?}.",
+                what, expected, value_type
+            );
+        }
+    }
+
     /// Create a new writer expression representing the alpha cutoff value used
     /// for alpha masking.
     ///
@@ -1901,6 +3411,62 @@ impl ExprWriter {
         )))
     }
 
+    /// Create a new writer expression representing the index of the current
+    /// particle in the particle buffer.
+    ///
+    /// This expression is only valid in the update and render contexts. See
+    /// [`BuiltInOperator::ParticleIndex`] for details.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::*;
+    /// let mut w = ExprWriter::new();
+    /// let x = w.particle_index(); // x = particle_index;
+    /// ```
+    pub fn particle_index(&self) -> WriterExpr {
+        self.push(Expr::BuiltIn(BuiltInExpr::new(
+            BuiltInOperator::ParticleIndex,
+        )))
+    }
+
+    /// Create a new writer expression representing the index of the group the
+    /// current particle belongs to.
+    ///
+    /// This expression is only valid in the update and render contexts. See
+    /// [`BuiltInOperator::GroupIndex`] for details.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::*;
+    /// let mut w = ExprWriter::new();
+    /// let x = w.group_index(); // x = group_index;
+    /// ```
+    pub fn group_index(&self) -> WriterExpr {
+        self.push(Expr::BuiltIn(BuiltInExpr::new(BuiltInOperator::GroupIndex)))
+    }
+
+    /// Create a new writer expression representing the normalized age of the
+    /// current particle.
+    ///
+    /// This is the particle's age divided by its lifetime, clamped to
+    /// \[0:1\], and guarded against a zero lifetime. Both the
+    /// [`Attribute::AGE`] and [`Attribute::LIFETIME`] must be present in the
+    /// particle layout. See [`BuiltInOperator::AgeRatio`] for details.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::*;
+    /// let mut w = ExprWriter::new();
+    /// // Fade the particle size from 1 to 0 over its lifetime
+    /// let size = w.lit(1.) - w.age_ratio();
+    /// ```
+    pub fn age_ratio(&self) -> WriterExpr {
+        self.push(Expr::BuiltIn(BuiltInExpr::new(BuiltInOperator::AgeRatio)))
+    }
+
     /// Finish using the writer, and recover the [`Module`] where all [`Expr`]
     /// were written by the writer.
     ///
@@ -2307,6 +3873,35 @@ impl WriterExpr {
         self.unary_op(UnaryOperator::Normalize)
     }
 
+    /// Apply the logical operator "not" to the current bool scalar or vector
+    /// expression.
+    ///
+    /// This is a unary operator, which applies component-wise to vector
+    /// operand expressions.
+    ///
+    /// You can also use the [`std::ops::Not`] trait directly, via the `!`
+    /// symbol, as an alternative to calling this method directly.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::*;
+    /// # use bevy::math::BVec3;
+    /// # let mut w = ExprWriter::new();
+    /// // A literal expression `x = vec3<bool>(true, false, true);`.
+    /// let x = w.lit(BVec3::new(true, false, true));
+    ///
+    /// // Negate all components `y = !x;`.
+    /// let y = x.not(); // == vec3<bool>(false, true, false)
+    /// // -OR-
+    /// // let y = !x;
+    /// ```
+    #[allow(clippy::should_implement_trait)]
+    #[inline]
+    pub fn not(self) -> Self {
+        self.unary_op(UnaryOperator::Not)
+    }
+
     /// Apply the "pack4x8snorm" operator to the current 4-component float
     /// vector expression.
     ///
@@ -2642,6 +4237,33 @@ impl WriterExpr {
         self.binary_op(other, BinaryOperator::Add)
     }
 
+    /// Apply the logical operator "and" to this expression and another
+    /// expression.
+    ///
+    /// This is a binary operator, which applies to bool scalar or vector
+    /// operand expressions. For scalars, the right operand is only evaluated if
+    /// the left one is `true`. Vectors are combined component-wise.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::*;
+    /// # use bevy::math::BVec3;
+    /// # let mut w = ExprWriter::new();
+    /// // A literal expression `x = vec3<bool>(true, false, true);`.
+    /// let x = w.lit(BVec3::new(true, false, true));
+    ///
+    /// // Another literal expression `y = vec3<bool>(true, true, false);`.
+    /// let y = w.lit(BVec3::new(true, true, false));
+    ///
+    /// // The boolean result of the "and" operation `z = (x & y);`.
+    /// let z = x.and(y); // == vec3<bool>(true, false, false)
+    /// ```
+    #[inline]
+    pub fn and(self, other: Self) -> Self {
+        self.binary_op(other, BinaryOperator::And)
+    }
+
     /// Calculate the cross product of the current expression by another
     /// expression.
     ///
@@ -2690,8 +4312,34 @@ impl WriterExpr {
     /// let z = x.dot(y);
     /// ```
     #[inline]
-    pub fn dot(self, other: Self) -> Self {
-        self.binary_op(other, BinaryOperator::Dot)
+    pub fn dot(self, other: Self) -> Self {
+        self.binary_op(other, BinaryOperator::Dot)
+    }
+
+    /// Apply the logical operator "equal" to this expression and another
+    /// expression.
+    ///
+    /// This is a binary operator, which applies component-wise to vector
+    /// operand expressions.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::*;
+    /// # use bevy::math::Vec3;
+    /// # let mut w = ExprWriter::new();
+    /// // A literal expression `x = vec3<f32>(3., -2., 7.);`.
+    /// let x = w.lit(Vec3::new(3., -2., 7.));
+    ///
+    /// // Another literal expression `y = vec3<f32>(1., 5., 7.);`.
+    /// let y = w.lit(Vec3::new(1., 5., 7.));
+    ///
+    /// // The boolean result of the equality operation `z = (x == y);`.
+    /// let z = x.eq(y); // == vec3<bool>(false, false, true)
+    /// ```
+    #[inline]
+    pub fn eq(self, other: Self) -> Self {
+        self.binary_op(other, BinaryOperator::Equal)
     }
 
     /// Calculate the distance between the current expression and another
@@ -2935,6 +4583,59 @@ impl WriterExpr {
         self.binary_op(other, BinaryOperator::Mul)
     }
 
+    /// Apply the logical operator "not equal" to this expression and another
+    /// expression.
+    ///
+    /// This is a binary operator, which applies component-wise to vector
+    /// operand expressions.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::*;
+    /// # use bevy::math::Vec3;
+    /// # let mut w = ExprWriter::new();
+    /// // A literal expression `x = vec3<f32>(3., -2., 7.);`.
+    /// let x = w.lit(Vec3::new(3., -2., 7.));
+    ///
+    /// // Another literal expression `y = vec3<f32>(1., 5., 7.);`.
+    /// let y = w.lit(Vec3::new(1., 5., 7.));
+    ///
+    /// // The boolean result of the inequality operation `z = (x != y);`.
+    /// let z = x.neq(y); // == vec3<bool>(true, true, false)
+    /// ```
+    #[inline]
+    pub fn neq(self, other: Self) -> Self {
+        self.binary_op(other, BinaryOperator::NotEqual)
+    }
+
+    /// Apply the logical operator "or" to this expression and another
+    /// expression.
+    ///
+    /// This is a binary operator, which applies to bool scalar or vector
+    /// operand expressions. For scalars, the right operand is only evaluated if
+    /// the left one is `false`. Vectors are combined component-wise.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::*;
+    /// # use bevy::math::BVec3;
+    /// # let mut w = ExprWriter::new();
+    /// // A literal expression `x = vec3<bool>(true, false, false);`.
+    /// let x = w.lit(BVec3::new(true, false, false));
+    ///
+    /// // Another literal expression `y = vec3<bool>(true, true, false);`.
+    /// let y = w.lit(BVec3::new(true, true, false));
+    ///
+    /// // The boolean result of the "or" operation `z = (x | y);`.
+    /// let z = x.or(y); // == vec3<bool>(true, true, false)
+    /// ```
+    #[inline]
+    pub fn or(self, other: Self) -> Self {
+        self.binary_op(other, BinaryOperator::Or)
+    }
+
     /// Calculate the remainder of the division of the current expression by
     /// another expression.
     ///
@@ -3156,6 +4857,23 @@ impl WriterExpr {
         self.ternary_op(y, z, TernaryOperator::Vec3)
     }
 
+    /// Construct a `Vec4` from a `Vec3` and a scalar.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::*;
+    /// # use bevy::math::Vec3;
+    /// # let mut w = ExprWriter::new();
+    /// // Build an opaque color from an RGB value.
+    /// let rgb = w.lit(Vec3::new(1., 0.5, 0.));
+    /// let color = rgb.vec4(w.lit(1.));
+    /// ```
+    #[inline]
+    pub fn vec4(self, w: Self) -> Self {
+        self.binary_op(w, BinaryOperator::Vec4)
+    }
+
     /// Cast an expression to a different type.
     ///
     /// # Example
@@ -3246,9 +4964,21 @@ impl std::ops::Rem<WriterExpr> for WriterExpr {
     }
 }
 
+impl std::ops::Not for WriterExpr {
+    type Output = WriterExpr;
+
+    #[inline]
+    fn not(self) -> Self::Output {
+        self.not()
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::{prelude::Property, MatrixType, ScalarValue, ShaderWriter, VectorType};
+    use crate::{
+        prelude::Property, MatrixType, MatrixValue, RenderContext, ScalarValue, ShaderWriter,
+        VectorType,
+    };
 
     use super::*;
     use bevy::{prelude::*, utils::HashSet};
@@ -3275,6 +5005,300 @@ mod tests {
         assert_eq!(m.get_mut(x), Some(&mut expected));
         assert_eq!(m.try_get(x), Ok(&expected));
         assert_eq!(m.try_get_mut(x), Ok(&mut expected));
+
+        let y = m.attr(Attribute::AGE);
+        let handles: Vec<_> = m.iter().map(|(h, _)| h).collect();
+        assert_eq!(handles, vec![x, y]);
+    }
+
+    #[test]
+    fn set_literal() {
+        let mut m = Module::default();
+        let x = m.lit(5.);
+        let y = m.attr(Attribute::AGE);
+
+        assert_eq!(m.set_literal(x, 3.), Ok(Value::from(5.)));
+        assert_eq!(m.get(x), Some(&Expr::Literal(LiteralExpr::new(3.))));
+
+        // Type must match
+        assert!(matches!(
+            m.set_literal(x, Vec3::ONE),
+            Err(ExprError::TypeError(_))
+        ));
+        assert_eq!(m.get(x), Some(&Expr::Literal(LiteralExpr::new(3.))));
+
+        // Not a literal
+        assert!(matches!(m.set_literal(y, 3.), Err(ExprError::TypeError(_))));
+
+        // Invalid handle
+        let unknown = ExprHandle::new(NonZeroU32::new(10).unwrap());
+        assert!(matches!(
+            m.set_literal(unknown, 3.),
+            Err(ExprError::InvalidExprHandleError(_))
+        ));
+    }
+
+    #[test]
+    fn offset_handles() {
+        let mut handle = ExprHandle::new(NonZeroU32::new(3).unwrap());
+        assert!(offset_expr_handles(&mut handle, 5).is_ok());
+        assert_eq!(handle.index(), 7);
+
+        // Overflow
+        let mut handle = ExprHandle::new(NonZeroU32::new(u32::MAX - 1).unwrap());
+        assert!(matches!(
+            offset_expr_handles(&mut handle, 2),
+            Err(ExprError::InvalidExprHandleError(_))
+        ));
+    }
+
+    #[test]
+    fn expr_type() {
+        let mut m = Module::default();
+
+        let f = ValueType::Scalar(ScalarType::Float);
+        let u = ValueType::Scalar(ScalarType::Uint);
+        let b = ValueType::Scalar(ScalarType::Bool);
+        let v3 = ValueType::Vector(VectorType::VEC3F);
+        let v4 = ValueType::Vector(VectorType::VEC4F);
+
+        let x = m.lit(1.);
+        let i = m.lit(3_u32);
+        let pos = m.attr(Attribute::POSITION);
+        let color = m.attr(Attribute::HDR_COLOR);
+        let prop = m.prop("p");
+        assert_eq!(m.expr_type(x), Some(f));
+        assert_eq!(m.expr_type(i), Some(u));
+        assert_eq!(m.expr_type(pos), Some(v3));
+        assert_eq!(m.expr_type(color), Some(v4));
+        assert_eq!(m.expr_type(prop), None);
+
+        // Unary
+        let abs = m.abs(pos);
+        let len = m.length(pos);
+        let y = m.y(pos);
+        let w = m.w(pos);
+        let unpack = m.unpack4x8unorm(i);
+        let euler = m.mat3_euler(pos);
+        assert_eq!(m.expr_type(abs), Some(v3));
+        assert_eq!(m.expr_type(len), Some(f));
+        assert_eq!(m.expr_type(y), Some(f));
+        assert_eq!(m.expr_type(w), None);
+        assert_eq!(m.expr_type(unpack), Some(v4));
+        assert_eq!(
+            m.expr_type(euler),
+            Some(ValueType::Matrix(MatrixType::MAT3X3F))
+        );
+
+        // Binary, mixing scalars and vectors
+        let scaled = m.mul(pos, x);
+        let scaled2 = m.mul(x, pos);
+        let sum = m.add(pos, scaled);
+        let bad_sum = m.add(pos, color);
+        let bad_add = m.add(x, i);
+        let dot = m.dot(pos, sum);
+        let lt = m.lt(pos, sum);
+        let lt_scalar = m.lt(x, len);
+        let rgba = m.vec4(sum, dot);
+        let rot = m.mat3_rotation(pos, x);
+        let rotated = m.mul(rot, pos);
+        assert_eq!(m.expr_type(scaled), Some(v3));
+        assert_eq!(m.expr_type(scaled2), Some(v3));
+        assert_eq!(m.expr_type(sum), Some(v3));
+        assert_eq!(m.expr_type(bad_sum), None);
+        assert_eq!(m.expr_type(bad_add), None);
+        assert_eq!(m.expr_type(dot), Some(f));
+        assert_eq!(m.expr_type(lt), Some(ValueType::Vector(VectorType::VEC3B)));
+        assert_eq!(m.expr_type(lt_scalar), Some(b));
+        assert_eq!(m.expr_type(rgba), Some(v4));
+        assert_eq!(
+            m.expr_type(rot),
+            Some(ValueType::Matrix(MatrixType::MAT3X3F))
+        );
+        assert_eq!(m.expr_type(rotated), Some(v3));
+
+        // Ternary
+        let mix = m.mix(pos, sum, x);
+        let vec3 = m.ternary(TernaryOperator::Vec3, x, len, dot);
+        let sdf = m.sdf_sphere(pos, sum, x);
+        assert_eq!(m.expr_type(mix), Some(v3));
+        assert_eq!(m.expr_type(vec3), Some(v3));
+        assert_eq!(m.expr_type(sdf), Some(f));
+
+        // Cast
+        let cast = m.cast(len, ScalarType::Uint);
+        assert_eq!(m.expr_type(cast), Some(u));
+
+        // Unknown type propagates
+        let prop_sum = m.add(prop, x);
+        assert_eq!(m.expr_type(prop_sum), None);
+    }
+
+    #[test]
+    fn optimize() {
+        let mut m = Module::default();
+        let two = m.lit(2.);
+        let three = m.lit(3.);
+        let six = m.mul(two, three);
+        let half = m.lit(0.5);
+        let min = m.min(six, half);
+        let age = m.attr(Attribute::AGE);
+        let partial = m.add(age, min);
+        let v = m.lit(Vec3::new(1.5, -2.5, 3.));
+        let w = m.lit(Vec3::new(0.5, 0.5, 1.));
+        let vsub = m.sub(v, w);
+        let vfloor = m.floor(vsub);
+        let vy = m.y(vfloor);
+        let rand = m.builtin(BuiltInOperator::Rand(ValueType::Scalar(ScalarType::Float)));
+        let cos = m.cos(two);
+
+        // Evaluate the unfolded expressions first
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let mut ctx =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        let unfolded: Vec<String> = [six, min, partial, vfloor, vy, rand, cos]
+            .iter()
+            .map(|&expr| ctx.eval(&m, expr).unwrap())
+            .collect();
+        assert_eq!(unfolded[0], "(2.) * (3.)");
+        assert_eq!(unfolded[1], "min((2.) * (3.), 0.5)");
+
+        let mut folded_module = m.clone();
+        assert_eq!(folded_module.optimize(), 5);
+        assert_eq!(folded_module.optimize(), 0);
+
+        // Folded values match the ones the GPU would calculate
+        let value = |m: &Module, expr| match m.get(expr) {
+            Some(Expr::Literal(literal)) => Some(literal.value()),
+            _ => None,
+        };
+        assert_eq!(value(&folded_module, six), Some(Value::from(6.)));
+        assert_eq!(value(&folded_module, min), Some(Value::from(0.5)));
+        assert_eq!(
+            value(&folded_module, vfloor),
+            Some(Value::from(Vec3::new(1., -3., 2.)))
+        );
+        assert_eq!(value(&folded_module, vy), Some(Value::from(-3.)));
+
+        // Non-constant and unsupported expressions are left untouched
+        assert_eq!(folded_module.get(partial), m.get(partial));
+        assert_eq!(folded_module.get(rand), m.get(rand));
+        assert_eq!(folded_module.get(cos), m.get(cos));
+
+        let mut ctx =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        let folded: Vec<String> = [six, min, partial, vfloor, vy, rand, cos]
+            .iter()
+            .map(|&expr| ctx.eval(&folded_module, expr).unwrap())
+            .collect();
+        assert_eq!(folded[0], "6.");
+        assert_eq!(folded[1], "0.5");
+        assert_eq!(folded[2], "(particle.age) + (0.5)");
+        assert_eq!(folded[3], "vec3<f32>(1.,-3.,2.)");
+        assert_eq!(folded[4], "-3.");
+        assert_eq!(folded[6], unfolded[6]);
+    }
+
+    #[test]
+    fn eliminate_dead_code() {
+        let mut m = Module::default();
+        let unused0 = m.lit(1.);
+        let x = m.lit(2.);
+        let unused1 = m.lit(3.);
+        let age = m.attr(Attribute::AGE);
+        let sum = m.add(age, x);
+        let v = m.lit(Vec3::ONE);
+        let cast = m.cast(x, ScalarType::Int);
+        let _unused2 = m.sub(sum, unused1);
+
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let mut ctx =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        let before: Vec<String> = [sum, v, cast]
+            .iter()
+            .map(|&expr| ctx.eval(&m, expr).unwrap())
+            .collect();
+
+        let remap = m.eliminate_dead_code(&[sum, v, cast]);
+        assert_eq!(m.expressions.len(), 5);
+        assert_eq!(remap.len(), 5);
+        assert!(!remap.contains_key(&unused0));
+        assert!(!remap.contains_key(&unused1));
+
+        // Generated code is unchanged
+        let mut ctx =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        let after: Vec<String> = [remap[&sum], remap[&v], remap[&cast]]
+            .iter()
+            .map(|&expr| ctx.eval(&m, expr).unwrap())
+            .collect();
+        assert_eq!(before, after);
+    }
+
+    #[test]
+    fn dedup() {
+        let mut m = Module::default();
+        let x = m.lit(3.);
+        let age = m.attr(Attribute::AGE);
+        let sum = m.add(x, age);
+        assert_eq!(m.expressions.len(), 3);
+
+        // Building the same expression again yields the same nodes
+        let x2 = m.lit(3.);
+        let age2 = m.attr(Attribute::AGE);
+        let sum2 = m.add(x2, age2);
+        assert_eq!(x2, x);
+        assert_eq!(sum2, sum);
+        assert_eq!(m.expressions.len(), 3);
+
+        // Same value of a different type is a different expression
+        let xi = m.lit(3);
+        assert_ne!(xi, x);
+        assert_eq!(m.expressions.len(), 4);
+
+        // Operands order matters
+        let sum3 = m.add(age, x);
+        assert_ne!(sum3, sum);
+
+        // Expressions with side effect are never shared
+        let r0 = m.builtin(BuiltInOperator::Rand(ScalarType::Float.into()));
+        let r1 = m.builtin(BuiltInOperator::Rand(ScalarType::Float.into()));
+        assert_ne!(r0, r1);
+        let one = m.lit(1.);
+        let u0 = m.uniform(x, one);
+        let u1 = m.uniform(x, one);
+        assert_ne!(u0, u1);
+
+        // The writer shares the deduplication
+        let w = ExprWriter::new();
+        let a = w.lit(2.).expr();
+        let b = w.lit(2.).expr();
+        assert_eq!(a, b);
+        assert_eq!(w.finish().expressions.len(), 1);
+
+        // Float literals are compared bit-wise
+        let mut m = Module::default();
+        let zero = m.lit(0.);
+        let neg_zero = m.lit(-0.);
+        assert_ne!(zero, neg_zero);
+        assert_eq!(m.lit(Vec2::new(-0., 1.)), m.lit(Vec2::new(-0., 1.)));
+        assert_ne!(m.lit(Vec2::new(-0., 1.)), m.lit(Vec2::new(0., 1.)));
+
+        // Expressions added without deduplication are indexed on next insertion
+        let mut m = Module::from_raw(vec![Expr::Literal(LiteralExpr::new(5.))]);
+        let five = m.lit(5.);
+        assert_eq!(five.index(), 0);
+        let mut other = Module::default();
+        let six = other.lit(6.);
+        let offset = m.append(&other).unwrap();
+        assert_eq!(m.lit(6.).index(), six.index() + offset as usize);
+
+        // Edits in place are taken into account
+        m.set_literal(five, 7.).unwrap();
+        assert_ne!(m.lit(5.), five);
+        assert_eq!(m.lit(7.), five);
     }
 
     #[test]
@@ -3420,6 +5444,58 @@ mod tests {
             assert_eq!(expr, format!("sim_params.{}", op.name()));
         }
 
+        // BuiltInOperator::ParticleIndex and GroupIndex, only valid in some contexts
+        for op in [BuiltInOperator::ParticleIndex, BuiltInOperator::GroupIndex] {
+            let value = m.builtin(op);
+            assert_eq!(m.expr_type(value), Some(ScalarType::Uint.into()));
+
+            let property_layout = PropertyLayout::default();
+            let particle_layout = ParticleLayout::default();
+
+            let mut ctx =
+                ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+            assert_eq!(ctx.eval(&m, value).unwrap(), op.name());
+
+            let mut ctx = RenderContext::new(&property_layout, &particle_layout);
+            assert_eq!(ctx.eval(&m, value).unwrap(), op.name());
+
+            let mut ctx =
+                ShaderWriter::new(ModifierContext::Init, &property_layout, &particle_layout);
+            assert!(matches!(
+                ctx.eval(&m, value),
+                Err(ExprError::InvalidModifierContext(..))
+            ));
+        }
+
+        // BuiltInOperator::AgeRatio, which requires both the age and lifetime
+        {
+            let value = m.builtin(BuiltInOperator::AgeRatio);
+            assert_eq!(m.expr_type(value), Some(ScalarType::Float.into()));
+
+            let property_layout = PropertyLayout::default();
+            let particle_layout = ParticleLayout::new()
+                .append(Attribute::AGE)
+                .append(Attribute::LIFETIME)
+                .build();
+            let mut ctx = RenderContext::new(&property_layout, &particle_layout);
+            assert_eq!(
+                ctx.eval(&m, value).unwrap(),
+                "select(0., clamp(particle.age / particle.lifetime, 0., 1.), particle.lifetime > 0.)"
+            );
+            let mut ctx =
+                ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+            assert!(ctx.eval(&m, value).is_ok());
+
+            for attr in [Attribute::AGE, Attribute::LIFETIME] {
+                let particle_layout = ParticleLayout::new().append(attr).build();
+                let mut ctx = RenderContext::new(&property_layout, &particle_layout);
+                assert!(matches!(
+                    ctx.eval(&m, value),
+                    Err(ExprError::GraphEvalError(..))
+                ));
+            }
+        }
+
         // BuiltInOperator::Rand (which has side effect)
         for (scalar_type, prefix) in [
             (ScalarType::Bool, "b"),
@@ -3600,6 +5676,85 @@ mod tests {
         }
     }
 
+    #[test]
+    fn logical_expr() {
+        let mut m = Module::default();
+
+        let x = m.attr(Attribute::POSITION);
+        let y = m.lit(Vec3::ONE);
+        let a = m.lit(true);
+        let b = m.lit(false);
+        let bv = m.lit(BVec3::new(true, false, true));
+
+        let eq = m.equal(x, y);
+        let neq = m.not_equal(x, y);
+        let lt = m.lt(x, y);
+        let le = m.le(x, y);
+        let gt = m.gt(x, y);
+        let ge = m.ge(x, y);
+        let and = m.and(a, b);
+        let or = m.or(a, b);
+        let not = m.not(a);
+        let vand = m.and(bv, bv);
+        let vor = m.or(bv, bv);
+        let vnot = m.not(bv);
+
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let mut ctx =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+
+        let pos = format!("particle.{}", Attribute::POSITION.name());
+        let bv_str = "vec3<bool>(true,false,true)";
+        for (expr, wgsl) in [
+            (eq, format!("({}) == (vec3<f32>(1.,1.,1.))", pos)),
+            (neq, format!("({}) != (vec3<f32>(1.,1.,1.))", pos)),
+            (lt, format!("({}) < (vec3<f32>(1.,1.,1.))", pos)),
+            (le, format!("({}) <= (vec3<f32>(1.,1.,1.))", pos)),
+            (gt, format!("({}) > (vec3<f32>(1.,1.,1.))", pos)),
+            (ge, format!("({}) >= (vec3<f32>(1.,1.,1.))", pos)),
+            (and, "(true) && (false)".to_string()),
+            (or, "(true) || (false)".to_string()),
+            (not, "!(true)".to_string()),
+            (vand, format!("({}) & ({})", bv_str, bv_str)),
+            (vor, format!("({}) | ({})", bv_str, bv_str)),
+            (vnot, format!("!({})", bv_str)),
+        ] {
+            assert_eq!(ctx.eval(&m, expr).unwrap(), wgsl);
+        }
+
+        // Result types
+        let b = ValueType::Scalar(ScalarType::Bool);
+        let bv3 = ValueType::Vector(VectorType::VEC3B);
+        assert_eq!(m.expr_type(eq), Some(bv3));
+        assert_eq!(m.expr_type(neq), Some(bv3));
+        assert_eq!(m.expr_type(and), Some(b));
+        assert_eq!(m.expr_type(vor), Some(bv3));
+        assert_eq!(m.expr_type(not), Some(b));
+        assert_eq!(m.expr_type(vnot), Some(bv3));
+
+        // Invalid operand types
+        let f = m.lit(1.);
+        let bad_and = m.and(f, a);
+        let bad_or = m.or(bv, a);
+        let bad_not = m.not(x);
+        let bad_eq = m.equal(x, f);
+        assert_eq!(m.expr_type(bad_and), None);
+        assert_eq!(m.expr_type(bad_or), None);
+        assert_eq!(m.expr_type(bad_not), None);
+        assert_eq!(m.expr_type(bad_eq), None);
+
+        // Writer
+        let w = ExprWriter::new();
+        let c = w.lit(true);
+        let d = w.lit(false);
+        let e = (!c).or(d).expr();
+        let m = w.finish();
+        let mut ctx =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        assert_eq!(ctx.eval(&m, e).unwrap(), "(!(true)) || (false)");
+    }
+
     #[test]
     fn ternary_expr() {
         let mut m = Module::default();
@@ -3633,6 +5788,184 @@ mod tests {
         }
     }
 
+    #[test]
+    fn matrix_expr() {
+        let mut m = Module::default();
+
+        let axis = m.lit(Vec3::Y);
+        let angle = m.lit(0.5);
+        let w = m.lit(1.);
+
+        let rot = m.mat3_rotation(axis, angle);
+        let euler = m.mat3_euler(axis);
+        let v4 = m.vec4(axis, w);
+
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let mut ctx =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+
+        let rot_name = BinaryOperator::Mat3Rotation.to_wgsl_string();
+        let euler_name = UnaryOperator::Mat3Euler.to_wgsl_string();
+        assert_eq!(
+            ctx.eval(&m, rot).unwrap(),
+            format!("{}(vec3<f32>(0.,1.,0.), 0.5)", rot_name)
+        );
+        assert_eq!(
+            ctx.eval(&m, euler).unwrap(),
+            format!("{}(vec3<f32>(0.,1.,0.))", euler_name)
+        );
+        assert_eq!(
+            ctx.eval(&m, v4).unwrap(),
+            "vec4(vec3<f32>(0.,1.,0.), 1.)".to_string()
+        );
+        assert!(ctx.extra_code.contains(&format!(
+            "fn {}(axis: vec3<f32>, angle: f32) -> mat3x3<f32>",
+            rot_name
+        )));
+        assert!(ctx.extra_code.contains(&format!(
+            "fn {}(angles: vec3<f32>) -> mat3x3<f32>",
+            euler_name
+        )));
+    }
+
+    #[test]
+    fn writer_ease() {
+        let w = ExprWriter::new();
+        let ratio = w.attr(Attribute::AGE) / w.attr(Attribute::LIFETIME);
+        let eased = w.ease(Easing::BounceOut, ratio).expr();
+        let mut m = w.finish();
+        assert_eq!(
+            m.expr_type(eased),
+            Some(ValueType::Scalar(ScalarType::Float))
+        );
+        let pos = m.attr(Attribute::POSITION);
+        let bad = m.ease(Easing::QuadIn, pos);
+        assert_eq!(m.expr_type(bad), None);
+
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let mut ctx =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        assert_eq!(
+            ctx.eval(&m, eased).unwrap(),
+            "ease_bounce_out((particle.age) / (particle.lifetime))"
+        );
+        assert!(ctx
+            .extra_code
+            .contains("fn ease_bounce_out(ratio: f32) -> f32 {"));
+
+        // Round-trip through serialization
+        let op = UnaryOperator::Ease(Easing::ElasticInOut);
+        let s = ron::to_string(&op).unwrap();
+        let op_serde: UnaryOperator = ron::from_str(&s).unwrap();
+        assert_eq!(op, op_serde);
+    }
+
+    #[test]
+    #[should_panic]
+    fn writer_ease_invalid_type() {
+        let w = ExprWriter::new();
+        let _ = w.ease(Easing::QuadIn, w.attr(Attribute::POSITION));
+    }
+
+    #[test]
+    fn writer_transform() {
+        let w = ExprWriter::new();
+        let rot = w.mat3_rotation(w.lit(Vec3::Y), w.time());
+        let v = w.transform(rot, w.attr(Attribute::VELOCITY));
+        let mat = w.lit(MatrixValue::from(Mat3::IDENTITY));
+        let v2 = w.transform(mat, w.attr(Attribute::VELOCITY));
+        let m = w.finish();
+
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let mut ctx =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        let rot_name = BinaryOperator::Mat3Rotation.to_wgsl_string();
+        assert_eq!(
+            ctx.eval(&m, v.expr()).unwrap(),
+            format!(
+                "({}(vec3<f32>(0.,1.,0.), sim_params.time)) * (particle.velocity)",
+                rot_name
+            )
+        );
+        assert!(ctx.eval(&m, v2.expr()).is_ok());
+    }
+
+    #[test]
+    #[should_panic]
+    fn writer_transform_invalid_size() {
+        let w = ExprWriter::new();
+        let mat = w.lit(MatrixValue::from(Mat4::IDENTITY));
+        let _ = w.transform(mat, w.attr(Attribute::VELOCITY));
+    }
+
+    #[test]
+    #[should_panic]
+    fn writer_transform_invalid_type() {
+        let w = ExprWriter::new();
+        let _ = w.transform(w.lit(3.), w.attr(Attribute::VELOCITY));
+    }
+
+    #[test]
+    #[should_panic]
+    fn writer_mat3_rotation_invalid_axis() {
+        let w = ExprWriter::new();
+        let _ = w.mat3_rotation(w.lit(3.), w.lit(1.));
+    }
+
+    #[test]
+    fn sdf_expr() {
+        let mut m = Module::default();
+
+        let p = m.attr(Attribute::POSITION);
+        let c = m.lit(Vec3::ZERO);
+        let r = m.lit(2.);
+        let h = m.lit(Vec3::ONE);
+
+        let sphere = m.sdf_sphere(p, c, r);
+        let sphere2 = m.sdf_sphere(c, p, r);
+        let aabb = m.sdf_box(p, c, h);
+        let plane = m.sdf_plane(p, c, h);
+
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let mut ctx =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+
+        for (expr, op) in [
+            (sphere, TernaryOperator::SdfSphere),
+            (sphere2, TernaryOperator::SdfSphere),
+            (aabb, TernaryOperator::SdfBox),
+            (plane, TernaryOperator::SdfPlane),
+        ] {
+            let expr = ctx.eval(&m, expr).unwrap();
+            let func_name = op.to_wgsl_string();
+            assert!(expr.starts_with(&format!("{}(", func_name)));
+
+            // Helper function is defined exactly once
+            let def = format!("fn {}(", func_name);
+            assert_eq!(ctx.extra_code.matches(&def).count(), 1);
+        }
+        assert!(ctx.main_code.is_empty());
+
+        // Helpers already emitted by a parent context are not emitted again by nested
+        // function contexts
+        ctx.make_fn("f", "", &mut m, &mut |m: &mut Module,
+                                           ctx: &mut dyn EvalContext|
+         -> Result<String, ExprError> {
+            ctx.eval(m, sphere)
+        })
+        .unwrap();
+        let def = format!("fn {}(", TernaryOperator::SdfSphere.to_wgsl_string());
+        assert_eq!(ctx.extra_code.matches(&def).count(), 1);
+
+        // Operators implemented by WGSL built-ins have no helper
+        assert!(TernaryOperator::Mix.helper_fn_code().is_none());
+        assert!(TernaryOperator::SdfBox.helper_fn_code().is_some());
+    }
+
     #[test]
     fn cast_expr() {
         let mut m = Module::default();
@@ -1475,6 +1475,32 @@ impl Value {
         }
     }
 
+    /// Check whether two values are bit-wise identical.
+    ///
+    /// Unlike `==`, this distinguishes `-0.0` from `0.0`, as well as NaN
+    /// values with different bit patterns.
+    pub(crate) fn bitwise_eq(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::Scalar(ScalarValue::Float(a)), Value::Scalar(ScalarValue::Float(b))) => {
+                a.to_bits() == b.to_bits()
+            }
+            (Value::Scalar(a), Value::Scalar(b)) => a == b,
+            (Value::Vector(a), Value::Vector(b)) => {
+                let count = a.vector_type.count();
+                a.vector_type == b.vector_type && a.storage[..count] == b.storage[..count]
+            }
+            (Value::Matrix(a), Value::Matrix(b)) => {
+                let count = a.matrix_type.rows() * a.matrix_type.cols();
+                a.matrix_type == b.matrix_type
+                    && a.storage[..count]
+                        .iter()
+                        .zip(&b.storage[..count])
+                        .all(|(a, b)| a.to_bits() == b.to_bits())
+            }
+            _ => false,
+        }
+    }
+
     /// Get the type of the value.
     ///
     /// # Example
@@ -78,6 +78,7 @@
 //!       center: module.lit(Vec3::ZERO),
 //!       radius: module.lit(0.05),
 //!       dimension: ShapeDimension::Surface,
+//!       thickness: None,
 //!   };
 //!
 //!   // Also initialize a radial initial velocity to 6 units/sec
@@ -115,7 +116,7 @@
 //!   // Render the particles with a color gradient over their
 //!   // lifetime. This maps the gradient key 0 to the particle spawn
 //!   // time, and the gradient key 1 to the particle death (10s).
-//!   .render(ColorOverLifetimeModifier { gradient });
+//!   .render(ColorOverLifetimeModifier::new(gradient));
 //!
 //!   // Insert into the asset system
 //!   let effect_handle = effects.add(effect);
@@ -177,6 +178,7 @@
 use bevy::utils::FloatOrd;
 use bevy::{
     prelude::*,
+    render::render_resource::BlendState,
     utils::{thiserror::Error, HashSet},
 };
 use serde::{Deserialize, Serialize};
@@ -185,28 +187,49 @@ use std::fmt::Write as _; // import without risk of name clashing
 mod asset;
 pub mod attributes;
 mod bundle;
+#[cfg(feature = "debug_gizmos")]
+pub mod gizmos;
 mod gradient;
 pub mod graph;
+mod mesh;
 pub mod modifier;
 mod plugin;
+#[cfg(feature = "cpu_preview")]
+pub mod preview;
 pub mod properties;
 mod render;
 mod spawn;
+#[cfg(feature = "testing")]
+pub mod testing;
 mod time;
 
 #[cfg(test)]
 mod test_utils;
 
-pub use asset::{AlphaMode, EffectAsset, MotionIntegration, SimulationCondition};
+pub use asset::{
+    AlphaMode, ColorSpace, EffectAsset, EffectWarning, ModifierPreset, MotionIntegration,
+    ParticleDrawOrder, RenderPrimitive, SimulationCondition,
+};
 pub use attributes::*;
 pub use bundle::ParticleEffectBundle;
-pub use gradient::{Gradient, GradientKey};
+#[cfg(feature = "debug_gizmos")]
+pub use gizmos::{EffectGizmoConfig, EffectGizmoPlugin};
+pub use gradient::{Easing, Gradient, GradientKey, GradientWrap};
 pub use graph::*;
+pub use mesh::{MeshColorSource, MeshSampleError, MeshSurfaceSampler};
 pub use modifier::*;
-pub use plugin::HanabiPlugin;
+pub use plugin::{main_graph, simulate_graph, HanabiPlugin};
+#[cfg(feature = "cpu_preview")]
+pub use preview::{EffectPreview, PreviewParticle};
 pub use properties::*;
-pub use render::{EffectSystems, LayoutFlags, ShaderCache};
-pub use spawn::{tick_spawners, CpuValue, EffectSpawner, Random, Spawner};
+pub use render::{
+    EffectGpuBuffers, EffectSnapshot, EffectSnapshotRequest, EffectStats, EffectSystems,
+    EffectsMeta, LayoutFlags, ShaderCache,
+};
+pub use spawn::{
+    apply_screen_coverage_budget, tick_spawners, CpuValue, EffectSpawner, EffectSpawnerState,
+    ParticleInitState, ParticleInjector, Random, Spawner,
+};
 pub use time::{EffectSimulation, EffectSimulationTime};
 
 #[allow(missing_docs)]
@@ -630,7 +653,9 @@ pub struct ParticleEffect {
     /// This value is passed to the render pipeline and used when sorting
     /// transparent items to render, to order them. As a result, effects
     /// with different Z values cannot be batched together, which may
-    /// negatively affect performance.
+    /// negatively affect performance. This applies to instances of the same
+    /// [`EffectAsset`] too: overriding the Z layer of one instance with a value
+    /// different from the others splits that instance into its own batch.
     ///
     /// This is only available with the `2d` feature.
     #[cfg(feature = "2d")]
@@ -641,6 +666,24 @@ pub struct ParticleEffect {
     /// Otherwise the spawner from the effect asset will be copied here when the
     /// component is first processed.
     pub spawner: Option<Spawner>,
+    /// Local transform of the effect relative to the [`GlobalTransform`] of
+    /// the entity this component is attached to.
+    ///
+    /// This offsets and/or rotates the effect relative to its entity, without
+    /// the need for a child entity. The effect origin is the composition of
+    /// the entity's [`GlobalTransform`] followed by this local transform, so
+    /// the local transform is expressed in the entity's local space, and is
+    /// affected by the entity's own rotation and scale.
+    ///
+    /// The composed transform replaces the entity's transform everywhere the
+    /// effect uses its emitter transform. With [`SimulationSpace::Global`],
+    /// particles are spawned relative to the composed transform, and
+    /// afterward are not affected by it. With [`SimulationSpace::Local`],
+    /// particles are simulated in the space of the composed transform, so
+    /// they follow both the entity and any change to this local transform.
+    ///
+    /// Defaults to [`Transform::IDENTITY`].
+    pub local_transform: Transform,
 }
 
 impl ParticleEffect {
@@ -651,6 +694,7 @@ impl ParticleEffect {
             #[cfg(feature = "2d")]
             z_layer_2d: None,
             spawner: None,
+            local_transform: Transform::IDENTITY,
         }
     }
 
@@ -689,6 +733,99 @@ impl ParticleEffect {
         self.spawner = Some(spawner);
         self
     }
+
+    /// Set the local transform of the effect relative to its entity.
+    ///
+    /// See [`local_transform`] for details.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy::prelude::*;
+    /// # use bevy_hanabi::*;
+    /// # let asset = Handle::<EffectAsset>::default();
+    /// // Emit from the tip of a weapon barrel, 0.8 units in front of the weapon origin
+    /// let effect = ParticleEffect::new(asset)
+    ///     .with_local_transform(Transform::from_xyz(0., 0., -0.8));
+    /// ```
+    ///
+    /// [`local_transform`]: crate::ParticleEffect::local_transform
+    pub fn with_local_transform(mut self, local_transform: Transform) -> Self {
+        self.local_transform = local_transform;
+        self
+    }
+}
+
+/// Runtime state of a [`ParticleEffect`] instance.
+///
+/// This optional component, located on the same [`Entity`] as the
+/// [`ParticleEffect`], allows fully disabling an effect instance without
+/// despawning it. A disabled instance is neither simulated nor rendered, and
+/// its spawner is not ticked, but its GPU buffers stay allocated, so it can be
+/// re-enabled instantly with its particles in the same state as when it was
+/// disabled. Instances without this component are always enabled.
+///
+/// Unlike [`ParticleEffect`], changing this component never invalidates the
+/// compiled effect, so toggling it is cheap.
+///
+/// # Disabling versus hiding or despawning
+///
+/// This differs from hiding the entity with [`Visibility::Hidden`]: with
+/// [`SimulationCondition::WhenVisible`] a hidden effect only pauses its
+/// simulation, and with [`SimulationCondition::Always`] a hidden effect keeps
+/// being simulated. A disabled effect is never simulated nor rendered,
+/// irrespective of its visibility and simulation condition.
+///
+/// This also differs from despawning the effect, which frees its GPU buffers.
+/// A disabled instance keeps consuming the GPU memory of its particle
+/// capacity, in exchange for avoiding the cost of reallocating the buffers
+/// and recompiling the effect when it's needed again. This makes disabling a
+/// good fit for pooling effects which are frequently reused, like impacts or
+/// muzzle flashes, while effects which are not expected to be needed again
+/// soon should be despawned instead.
+///
+/// # Example
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_hanabi::*;
+/// fn toggle(mut query: Query<&mut EffectInstance>) {
+///     for mut instance in &mut query {
+///         let enabled = instance.is_enabled();
+///         instance.set_enabled(!enabled);
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Component, Reflect)]
+#[reflect(Component)]
+pub struct EffectInstance {
+    /// Is the effect instance enabled?
+    enabled: bool,
+}
+
+impl Default for EffectInstance {
+    fn default() -> Self {
+        Self { enabled: true }
+    }
+}
+
+impl EffectInstance {
+    /// Create a new instance state, enabled or not.
+    pub fn new(enabled: bool) -> Self {
+        Self { enabled }
+    }
+
+    /// Enable or disable the effect instance.
+    ///
+    /// See [`EffectInstance`] for details.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    /// Check whether the effect instance is enabled.
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
 }
 
 /// Effect shader.
@@ -714,6 +851,9 @@ struct EffectShaderSource {
     pub render: Vec<String>,
     pub layout_flags: LayoutFlags,
     pub particle_texture: Option<Handle<Image>>,
+    pub dissolve_texture: Option<Handle<Image>>,
+    pub refraction_texture: Option<Handle<Image>>,
+    pub heightfield_texture: Option<Handle<Image>>,
 }
 
 /// Error resulting from the generating of the WGSL shader code of an
@@ -839,6 +979,13 @@ impl EffectShaderSource {
         } else {
             "@group(1) @binding(3) var<storage, read> properties : Properties;".to_string()
         };
+        // The render pass uses binding 3 for the spawner in local space simulation,
+        // so properties take the next binding.
+        let render_properties_binding_code = if property_layout.is_empty() {
+            "// (no properties)".to_string()
+        } else {
+            "@group(1) @binding(4) var<storage, read> properties : Properties;".to_string()
+        };
 
         // Start from the base module containing the expressions actually serialized in
         // the asset. We will add the ones created on-the-fly by applying the
@@ -849,19 +996,85 @@ impl EffectShaderSource {
         let (init_code, init_extra, init_sim_space_transform_code) = {
             let mut init_context =
                 ShaderWriter::new(ModifierContext::Init, &property_layout, &particle_layout);
+            // Assign the per-particle seed before any modifier can read it.
+            if present_attributes.contains(&Attribute::SEED) {
+                init_context.main_code += &format!(
+                    "seed = pcg_hash(seed);\nparticle.{0} = seed;\n",
+                    Attribute::SEED.name()
+                );
+            }
             for m in asset.init_modifiers() {
                 if let Err(err) = m.apply(&mut module, &mut init_context) {
                     error!("Failed to compile effect, error in init context: {:?}", err);
                     return Err(ShaderGenerateError::Expr(err));
                 }
             }
-            let sim_space_transform_code = match asset.simulation_space.eval(&init_context) {
+            // Overwrite the state of the particles injected from CPU, if any. Those
+            // use the first spawn slots of the frame. See ParticleInjector.
+            let mut inject_code = String::new();
+            if present_attributes.contains(&Attribute::POSITION) {
+                inject_code += &format!(
+                    "    particle.{} = state.position;\n",
+                    Attribute::POSITION.name()
+                );
+            }
+            if present_attributes.contains(&Attribute::VELOCITY) {
+                inject_code += &format!(
+                    "    if ((state.flags & 1u) != 0u) {{ particle.{} = state.velocity; }}\n",
+                    Attribute::VELOCITY.name()
+                );
+            }
+            if present_attributes.contains(&Attribute::HDR_COLOR) {
+                inject_code += &format!(
+                    "    if ((state.flags & 2u) != 0u) {{ particle.{} = state.color; }}\n",
+                    Attribute::HDR_COLOR.name()
+                );
+            } else if present_attributes.contains(&Attribute::COLOR) {
+                inject_code += &format!(
+                    "    if ((state.flags & 2u) != 0u) {{ particle.{} = pack4x8unorm(state.color); }}\n",
+                    Attribute::COLOR.name()
+                );
+            }
+            if !inject_code.is_empty() {
+                init_context.main_code += &format!(
+                    "if (global_invocation_id.x < spawner.inject_count) {{\n    let state = injected_states[spawner.inject_offset + global_invocation_id.x];\n{}}}\n",
+                    inject_code
+                );
+            }
+            let mut sim_space_transform_code = match asset.simulation_space.eval(&init_context) {
                 Ok(s) => s,
                 Err(err) => {
                     error!("Failed to compile effect's simulation space: {:?}", err);
                     return Err(ShaderGenerateError::Expr(err));
                 }
             };
+            // Scale the emitter shapes with the emitter, before offsetting them by the
+            // emitter position. Local-space effects are rendered with the full emitter
+            // transform, so are already scaled.
+            if asset.emitter_scaling && asset.simulation_space == SimulationSpace::Global {
+                sim_space_transform_code = format!(
+                    "particle.{0} *= vec3<f32>(length(transform[0].xyz), length(transform[1].xyz), length(transform[2].xyz));\n{1}",
+                    Attribute::POSITION.name(),
+                    sim_space_transform_code
+                );
+            }
+            // Initialize the previous position once the position is in its final space.
+            if present_attributes.contains(&Attribute::PREV_POSITION) {
+                sim_space_transform_code += &format!(
+                    "\nparticle.{0} = particle.{1};\n",
+                    Attribute::PREV_POSITION.name(),
+                    Attribute::POSITION.name()
+                );
+            }
+            // Same for the last emit position, so the first trail particle is only
+            // emitted once the particle moved away from its spawn position.
+            if present_attributes.contains(&Attribute::LAST_EMIT_POSITION) {
+                sim_space_transform_code += &format!(
+                    "\nparticle.{0} = particle.{1};\n",
+                    Attribute::LAST_EMIT_POSITION.name(),
+                    Attribute::POSITION.name()
+                );
+            }
             (
                 init_context.main_code,
                 init_context.extra_code,
@@ -887,16 +1100,25 @@ impl EffectShaderSource {
         if asset.simulation_space == SimulationSpace::Local {
             layout_flags |= LayoutFlags::LOCAL_SPACE_SIMULATION;
         }
-        if let AlphaMode::Mask(_) = &asset.alpha_mode {
-            layout_flags |= LayoutFlags::USE_ALPHA_MASK;
+        match &asset.alpha_mode {
+            AlphaMode::Mask(_) => layout_flags |= LayoutFlags::USE_ALPHA_MASK,
+            AlphaMode::Min => layout_flags |= LayoutFlags::BLEND_MIN,
+            AlphaMode::Max => layout_flags |= LayoutFlags::BLEND_MAX,
+            _ => {}
+        }
+        if asset.render_primitive == RenderPrimitive::Point {
+            layout_flags |= LayoutFlags::POINT_PRIMITIVE;
         }
 
         let mut effect_particle_texture = None;
+        let mut effect_dissolve_texture = None;
+        let mut effect_refraction_texture = None;
+        let mut effect_heightfield_texture = None;
 
         let (mut update_shader_sources, mut render_shader_sources) = (vec![], vec![]);
         for group_index in 0..(asset.capacities().len() as u32) {
             // Generate the shader code for the update shader
-            let (mut update_code, update_extra) = {
+            let (mut update_code, update_extra, delta_time_code) = {
                 let mut update_context =
                     ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
                 for m in asset.update_modifiers_for_group(group_index) {
@@ -908,13 +1130,22 @@ impl EffectShaderSource {
                         return Err(ShaderGenerateError::Expr(err));
                     }
                 }
-                (update_context.main_code, update_context.extra_code)
+                // FIXME: What about multiple textures?
+                if let Some(heightfield_texture) = update_context.heightfield_texture {
+                    effect_heightfield_texture = Some(heightfield_texture);
+                }
+                (
+                    update_context.main_code,
+                    update_context.extra_code,
+                    update_context.delta_time_code,
+                )
             };
 
             // Insert Euler motion integration if needed.
             let has_position = present_attributes.contains(&Attribute::POSITION);
             let has_velocity = present_attributes.contains(&Attribute::VELOCITY);
-            if asset.motion_integration != MotionIntegration::None {
+            let motion_integration = asset.group_motion_integration(group_index);
+            if motion_integration != MotionIntegration::None {
                 if has_position && has_velocity {
                     // Note the prepended "\n" to prevent appending to a comment line.
                     let code = format!(
@@ -922,24 +1153,38 @@ impl EffectShaderSource {
                         Attribute::POSITION.name(),
                         Attribute::VELOCITY.name()
                     );
-                    if asset.motion_integration == MotionIntegration::PreUpdate {
+                    if motion_integration == MotionIntegration::PreUpdate {
                         update_code.insert_str(0, &code);
                     } else {
                         update_code += &code;
                     }
                 } else {
                     warn!(
-                        "Asset {} specifies motion integration but is missing {}.",
+                        "Asset {} specifies motion integration {:?} but is missing {}. Motion integration will be skipped.",
                         asset.name,
-                        if has_position {
-                            "Attribute::VELOCITY"
-                        } else {
-                            "Attribute::POSITION"
+                        motion_integration,
+                        match (has_position, has_velocity) {
+                            (false, false) => "Attribute::POSITION and Attribute::VELOCITY",
+                            (false, true) => "Attribute::POSITION",
+                            _ => "Attribute::VELOCITY",
                         }
                     )
                 }
             }
 
+            // Save the current position before anything modifies it, including
+            // motion integration.
+            if present_attributes.contains(&Attribute::PREV_POSITION) {
+                update_code.insert_str(
+                    0,
+                    &format!(
+                        "\nparticle.{0} = particle.{1};\n",
+                        Attribute::PREV_POSITION.name(),
+                        Attribute::POSITION.name()
+                    ),
+                );
+            }
+
             // Generate the shader code for the render shader
             let (
                 vertex_code,
@@ -949,10 +1194,22 @@ impl EffectShaderSource {
                 flipbook_scale_code,
                 flipbook_row_count_code,
                 image_sample_mapping_code,
+                image_sample_uv_code,
+                uv_code,
+                dissolve_code,
+                refraction_code,
+                normal_code,
             ) = {
                 let mut render_context = RenderContext::new(&property_layout, &particle_layout);
+                render_context.color_space = asset.color_space;
                 for m in asset.render_modifiers_for_group(group_index) {
-                    m.apply_render(&mut module, &mut render_context);
+                    if let Err(err) = m.apply_render(&mut module, &mut render_context) {
+                        error!(
+                            "Failed to compile effect, error in render context: {:?}",
+                            err
+                        );
+                        return Err(ShaderGenerateError::Expr(err));
+                    }
                 }
 
                 let alpha_cutoff_code = if let AlphaMode::Mask(cutoff) = &asset.alpha_mode {
@@ -990,6 +1247,23 @@ impl EffectShaderSource {
                 if let Some(particle_texture) = render_context.particle_texture {
                     effect_particle_texture = Some(particle_texture);
                 }
+                if let Some(dissolve_texture) = render_context.dissolve_texture {
+                    effect_dissolve_texture = Some(dissolve_texture);
+                }
+                if let Some(refraction_texture) = render_context.refraction_texture {
+                    effect_refraction_texture = Some(refraction_texture);
+                }
+                if !render_context.normal_code.is_empty() {
+                    layout_flags |= LayoutFlags::PARTICLE_NORMAL;
+                }
+                // The particle index is only defined in the vertex shader; forward it to
+                // the fragment shader only if used there.
+                if render_context
+                    .fragment_code
+                    .contains(&BuiltInOperator::ParticleIndex.to_wgsl_string())
+                {
+                    layout_flags |= LayoutFlags::PARTICLE_INDEX;
+                }
 
                 (
                     render_context.vertex_code,
@@ -999,6 +1273,15 @@ impl EffectShaderSource {
                     flipbook_scale_code,
                     flipbook_row_count_code,
                     render_context.image_sample_mapping_code,
+                    if render_context.image_sample_uv_code.is_empty() {
+                        "in.uv".to_string()
+                    } else {
+                        render_context.image_sample_uv_code
+                    },
+                    render_context.uv_code,
+                    render_context.dissolve_code,
+                    render_context.refraction_code,
+                    render_context.normal_code,
                 )
             };
 
@@ -1017,14 +1300,18 @@ impl EffectShaderSource {
                 // simulate dead particles).
                 "var is_alive = true;".to_string()
             };
-            let age_code = if has_age {
-                format!(
-                    "particle.{0} = particle.{0} + sim_params.delta_time;",
-                    Attribute::AGE.name()
-                )
-            } else {
-                "".to_string()
-            } + "\n    "
+            // Scale the per-particle delta time first, so that it applies to aging,
+            // motion integration, and all update modifiers alike.
+            let age_code = delta_time_code
+                + &if has_age {
+                    format!(
+                        "particle.{0} = particle.{0} + sim_params.delta_time;",
+                        Attribute::AGE.name()
+                    )
+                } else {
+                    "".to_string()
+                }
+                + "\n    "
                 + &alive_init_code;
 
             // Configure reaping code
@@ -1040,12 +1327,26 @@ impl EffectShaderSource {
 
             let group_index_code = format!("{}", group_index);
 
+            // Configure the order in which the update pass reads the alive particles,
+            // which is the order they're written back for rendering.
+            let draw_order_code = match asset.group_draw_order(group_index) {
+                ParticleDrawOrder::OldestFirst => "let read_index = thread_index;".to_string(),
+                // Rotate the list so the particles spawned this frame, appended by the
+                // init pass, come first.
+                ParticleDrawOrder::NewestFirst => {
+                    "let read_index = (thread_index + render_group_indirect[group_index].spawn_offset)
+        % render_group_indirect[group_index].max_update;"
+                        .to_string()
+                }
+            };
+
             // Configure the update shader template, and make sure a corresponding shader
             // asset exists
             let update_shader_source = PARTICLES_UPDATE_SHADER_TEMPLATE
                 .replace("{{ATTRIBUTES}}", &attributes_code)
                 .replace("{{AGE_CODE}}", &age_code)
                 .replace("{{REAP_CODE}}", &reap_code)
+                .replace("{{DRAW_ORDER_CODE}}", &draw_order_code)
                 .replace("{{UPDATE_CODE}}", &update_code)
                 .replace("{{UPDATE_EXTRA}}", &update_extra)
                 .replace("{{PROPERTIES}}", &properties_code)
@@ -1060,14 +1361,23 @@ impl EffectShaderSource {
                 .replace("{{INPUTS}}", &inputs_code)
                 .replace("{{VERTEX_MODIFIERS}}", &vertex_code)
                 .replace("{{FRAGMENT_MODIFIERS}}", &fragment_code)
+                // Before the texture UV, which the normal code may reference
+                .replace("{{NORMAL_CODE}}", &normal_code)
                 .replace("{{RENDER_EXTRA}}", &render_extra)
                 .replace("{{ALPHA_CUTOFF}}", &alpha_cutoff_code)
+                .replace("{{UV_CODE}}", &uv_code)
                 .replace("{{FLIPBOOK_SCALE}}", &flipbook_scale_code)
                 .replace("{{FLIPBOOK_ROW_COUNT}}", &flipbook_row_count_code)
                 .replace(
                     "{{PARTICLE_TEXTURE_SAMPLE_MAPPING}}",
                     &image_sample_mapping_code,
-                );
+                )
+                .replace("{{PARTICLE_TEXTURE_UV}}", &image_sample_uv_code)
+                .replace("{{DISSOLVE_CODE}}", &dissolve_code)
+                .replace("{{REFRACTION_CODE}}", &refraction_code)
+                .replace("{{PROPERTIES}}", &properties_code)
+                .replace("{{PROPERTIES_BINDING}}", &render_properties_binding_code)
+                .replace("{{GROUP_INDEX}}", &group_index_code);
             trace!("Configured render shader:\n{}", render_shader_source);
 
             update_shader_sources.push(update_shader_source);
@@ -1080,6 +1390,9 @@ impl EffectShaderSource {
             render: render_shader_sources,
             layout_flags,
             particle_texture: effect_particle_texture,
+            dissolve_texture: effect_dissolve_texture,
+            refraction_texture: effect_refraction_texture,
+            heightfield_texture: effect_heightfield_texture,
         })
     }
 }
@@ -1109,11 +1422,33 @@ pub struct CompiledParticleEffect {
     effect_shader: Option<EffectShader>,
     /// Main particle texture.
     particle_texture: Option<Handle<Image>>,
+    /// Erosion texture used to dissolve particles.
+    dissolve_texture: Option<Handle<Image>>,
+    /// Scene color texture refracted behind particles.
+    refraction_texture: Option<Handle<Image>>,
+    /// Heightmap texture sampled by the update pass for terrain collisions.
+    heightfield_texture: Option<Handle<Image>>,
+    /// Blend state of the render pipeline, if different from the default alpha
+    /// blending.
+    blend_state: Option<BlendState>,
     /// 2D layer for the effect instance.
     #[cfg(feature = "2d")]
     z_layer_2d: FloatOrd,
     /// Layout flags.
     layout_flags: LayoutFlags,
+    /// Capacities the effect was compiled with. The GPU buffers of the effect
+    /// instance are allocated once based on those, so cannot change afterward.
+    capacities: Vec<u32>,
+    /// Particle layout the effect was compiled with. The GPU buffers of the
+    /// effect instance are allocated once based on this, so cannot change
+    /// afterward.
+    particle_layout: ParticleLayout,
+    /// Property layout the effect was compiled with. The GPU buffer storing
+    /// the properties of the effect instance is allocated once based on this,
+    /// so cannot change afterward.
+    property_layout: PropertyLayout,
+    /// Estimated maximum particle lifetime, if known.
+    max_lifetime: Option<f32>,
 }
 
 impl Default for CompiledParticleEffect {
@@ -1123,9 +1458,17 @@ impl Default for CompiledParticleEffect {
             simulation_condition: SimulationCondition::default(),
             effect_shader: None,
             particle_texture: None,
+            dissolve_texture: None,
+            refraction_texture: None,
+            heightfield_texture: None,
+            blend_state: None,
             #[cfg(feature = "2d")]
             z_layer_2d: FloatOrd(0.0),
             layout_flags: LayoutFlags::NONE,
+            capacities: vec![],
+            particle_layout: ParticleLayout::default(),
+            property_layout: PropertyLayout::default(),
+            max_lifetime: None,
         }
     }
 }
@@ -1164,12 +1507,14 @@ impl CompiledParticleEffect {
             // smarter here, only invalidate what changed, but for now just wipe everything
             // and rebuild from scratch all three shaders together.
             self.effect_shader = None;
+        }
 
-            // Update the 2D layer
-            #[cfg(feature = "2d")]
-            {
-                self.z_layer_2d = z_layer_2d;
-            }
+        // Update the blend state and 2D layer. Those don't affect the shaders, so
+        // are always refreshed even if nothing else needs to be rebuilt.
+        self.blend_state = asset.blend_state.or_else(|| asset.alpha_mode.blend_state());
+        #[cfg(feature = "2d")]
+        {
+            self.z_layer_2d = z_layer_2d;
         }
 
         // If the shaders are already compiled, there's nothing more to do
@@ -1188,18 +1533,46 @@ impl CompiledParticleEffect {
             }
         };
 
-        self.layout_flags = shader_source.layout_flags;
+        for warning in asset.validate() {
+            warn!("Effect asset '{}': {}", asset.name, warning);
+        }
 
-        let init_shader = shader_cache.get_or_insert(&asset.name, &shader_source.init, shaders);
+        self.layout_flags = shader_source.layout_flags;
+        self.capacities = asset.capacities().to_vec();
+        self.particle_layout = asset.particle_layout();
+        self.property_layout = asset.property_layout();
+        self.max_lifetime = asset.estimated_max_lifetime();
+
+        // Name the shaders after the effect and the pass they implement, so GPU
+        // captures and shader errors can be traced back to the effect.
+        let init_shader = shader_cache.get_or_insert(
+            &format!("{}_init", asset.name),
+            &shader_source.init,
+            shaders,
+        );
         let update_shaders: Vec<_> = shader_source
             .update
             .iter()
-            .map(|update_source| shader_cache.get_or_insert(&asset.name, update_source, shaders))
+            .enumerate()
+            .map(|(group_index, update_source)| {
+                shader_cache.get_or_insert(
+                    &format!("{}_update{}", asset.name, group_index),
+                    update_source,
+                    shaders,
+                )
+            })
             .collect();
         let render_shaders: Vec<_> = shader_source
             .render
             .iter()
-            .map(|render_source| shader_cache.get_or_insert(&asset.name, render_source, shaders))
+            .enumerate()
+            .map(|(group_index, render_source)| {
+                shader_cache.get_or_insert(
+                    &format!("{}_render{}", asset.name, group_index),
+                    render_source,
+                    shaders,
+                )
+            })
             .collect();
 
         trace!(
@@ -1225,12 +1598,198 @@ impl CompiledParticleEffect {
         });
 
         self.particle_texture = shader_source.particle_texture;
+        self.dissolve_texture = shader_source.dissolve_texture;
+        self.refraction_texture = shader_source.refraction_texture;
+        self.heightfield_texture = shader_source.heightfield_texture;
+    }
+
+    /// Check if the GPU resources allocated for this compiled effect are
+    /// compatible with the given asset.
+    ///
+    /// The GPU buffers of an effect instance are allocated when the instance
+    /// is first compiled, and are not reallocated afterward. Therefore a
+    /// change in capacities, particle layout, or property layout cannot be
+    /// applied in place; the effect instance needs to be despawned and spawned
+    /// again.
+    ///
+    /// An effect not compiled yet is always compatible.
+    pub(crate) fn is_compatible_with(&self, asset: &EffectAsset) -> bool {
+        self.effect_shader.is_none()
+            || (self.capacities == asset.capacities()
+                && self.particle_layout == asset.particle_layout()
+                && self.property_layout == asset.property_layout())
+    }
+
+    /// Check whether the effect instance finished playing.
+    ///
+    /// An effect is finished when its spawner is done spawning particles (see
+    /// [`EffectSpawner::is_done()`]) and all the particles it spawned died.
+    ///
+    /// There are two ways to determine that no particle remains alive:
+    /// - an exact one, reading back from the GPU the number of alive particles;
+    /// - a conservative one, based on the spawner state and the maximum
+    ///   particle lifetime, without any GPU readback.
+    ///
+    /// Since GPU readback of the particle count is not available yet, this
+    /// method currently uses the conservative mode only. The effect is
+    /// considered finished once the maximum particle lifetime elapsed since
+    /// the spawner last spawned any particle. This can report an effect as
+    /// finished slightly later than it actually is, but never earlier, unless
+    /// particles are killed by modifiers before their lifetime elapses, which
+    /// only makes the result more conservative. The maximum lifetime is given
+    /// by [`EffectAsset::estimated_max_lifetime()`]; if that lifetime is not
+    /// known, or if the particles have no lifetime, this always returns
+    /// `false`.
+    pub fn is_finished(&self, spawner: &EffectSpawner) -> bool {
+        if !spawner.is_done() {
+            return false;
+        }
+        self.max_lifetime
+            .map(|max_lifetime| spawner.idle_time() >= max_lifetime)
+            .unwrap_or(false)
+    }
+
+    /// Get the handle of the init compute shader of the effect, if compiled.
+    ///
+    /// The handles of the shaders of an effect allow correlating a GPU
+    /// profiler capture with that effect. The [`Shader`] asset path contains
+    /// the name of the effect and of the pass, like `hanabi/my_effect_init_*`.
+    /// Note that effects with identical shader code share the same shader and
+    /// pipelines, so the name in the path is that of the first effect compiled
+    /// with that code.
+    ///
+    /// Returns `None` if the effect is not compiled yet.
+    pub fn init_shader(&self) -> Option<&Handle<Shader>> {
+        self.effect_shader.as_ref().map(|shader| &shader.init)
+    }
+
+    /// Get the handles of the update compute shaders of the effect, one per
+    /// group.
+    ///
+    /// See [`init_shader()`] for details. Returns an empty slice if the effect
+    /// is not compiled yet.
+    ///
+    /// [`init_shader()`]: crate::CompiledParticleEffect::init_shader
+    pub fn update_shaders(&self) -> &[Handle<Shader>] {
+        self.effect_shader
+            .as_ref()
+            .map(|shader| &shader.update[..])
+            .unwrap_or(&[])
+    }
+
+    /// Get the handles of the render shaders of the effect, one per group.
+    ///
+    /// See [`init_shader()`] for details. Returns an empty slice if the effect
+    /// is not compiled yet.
+    ///
+    /// [`init_shader()`]: crate::CompiledParticleEffect::init_shader
+    pub fn render_shaders(&self) -> &[Handle<Shader>] {
+        self.effect_shader
+            .as_ref()
+            .map(|shader| &shader.render[..])
+            .unwrap_or(&[])
     }
 
     /// Get the effect shader if configured, or `None` otherwise.
     pub(crate) fn get_configured_shader(&self) -> Option<EffectShader> {
         self.effect_shader.clone()
     }
+
+    /// Layout flags used to render this effect instance.
+    ///
+    /// This includes the layout flags of the compiled effect, and the flags
+    /// derived from the textures it uses.
+    pub(crate) fn render_layout_flags(&self) -> LayoutFlags {
+        let mut layout_flags = self.layout_flags;
+        if self.particle_texture.is_some() {
+            layout_flags |= LayoutFlags::PARTICLE_TEXTURE;
+        }
+        if self.dissolve_texture.is_some() {
+            layout_flags |= LayoutFlags::DISSOLVE_TEXTURE;
+        }
+        if self.refraction_texture.is_some() {
+            layout_flags |= LayoutFlags::REFRACTION_TEXTURE;
+        }
+        layout_flags
+    }
+
+    /// Get the batch key of this effect instance, if compiled.
+    ///
+    /// Effect instances with equal batch keys share the same render pipelines
+    /// and GPU resource bindings, and are candidates for being batched
+    /// together. Comparing the batch keys of several instances is a way to
+    /// diagnose why they don't share pipelines. See [`EffectBatchKey`] for
+    /// details.
+    ///
+    /// Returns `None` if the effect is not compiled yet.
+    pub fn batch_key(&self) -> Option<EffectBatchKey> {
+        let effect_shader = self.effect_shader.as_ref()?;
+        Some(EffectBatchKey {
+            render_shaders: effect_shader.render.iter().map(|h| h.id()).collect(),
+            particle_layout: self.particle_layout.clone(),
+            layout_flags: self.render_layout_flags(),
+            textures: [
+                &self.particle_texture,
+                &self.dissolve_texture,
+                &self.refraction_texture,
+            ]
+            .iter()
+            .filter_map(|texture| texture.as_ref().map(|handle| handle.id()))
+            .collect(),
+            blend_state: self.blend_state,
+            #[cfg(feature = "2d")]
+            z_layer_2d: self.z_layer_2d,
+        })
+    }
+}
+
+/// Key describing what determines the rendering of an effect instance.
+///
+/// The batch key gathers all the values which are baked into the render
+/// pipelines of an effect instance, or into the GPU resources bound when
+/// rendering it. Effect instances with equal batch keys share the same render
+/// pipelines, while any difference forces a separate pipeline or draw call.
+/// The batch key of a compiled effect instance can be obtained with
+/// [`CompiledParticleEffect::batch_key()`].
+///
+/// # Authoring batch-friendly effects
+///
+/// To maximize pipeline sharing between effects:
+/// - Prefer instancing a same [`EffectAsset`] over creating many slightly
+///   different assets. All instances of an asset share the same shaders. Two
+///   different assets produce the same shaders only if their modifiers
+///   generate the exact same code.
+/// - Prefer varying values through [properties] rather than through modifier
+///   values. Properties are uploaded to the GPU and don't change the shader
+///   code, while a literal value in a modifier is baked into the shader.
+/// - Keep the same [`AlphaMode`] and [`SimulationSpace`] for effects which
+///   should share pipelines, as both are part of the [`layout_flags`].
+/// - Use the same textures. Each texture is bound separately.
+/// - For 2D rendering, avoid overriding the Z layer per instance with
+///   [`ParticleEffect::z_layer_2d`].
+///
+/// [properties]: crate::properties
+/// [`layout_flags`]: EffectBatchKey::layout_flags
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct EffectBatchKey {
+    /// Render shaders of the effect, one per particle group.
+    pub render_shaders: Vec<AssetId<Shader>>,
+    /// Particle layout of the effect.
+    pub particle_layout: ParticleLayout,
+    /// Layout flags, which include the [`AlphaMode`] and [`SimulationSpace`] of
+    /// the effect, as well as the textures it uses.
+    pub layout_flags: LayoutFlags,
+    /// Textures bound when rendering the effect.
+    pub textures: Vec<AssetId<Image>>,
+    /// Blend state of the effect, if different from the default alpha
+    /// blending. This is either derived from the [`AlphaMode`], or set with
+    /// [`EffectAsset::with_blend_state()`].
+    pub blend_state: Option<BlendState>,
+    /// Z coordinate of the layer at which the particles are rendered.
+    ///
+    /// This is only available with the `2d` feature.
+    #[cfg(feature = "2d")]
+    pub z_layer_2d: FloatOrd,
 }
 
 const PARTICLES_INIT_SHADER_TEMPLATE: &str = include_str!("render/vfx_init.wgsl");
@@ -1261,20 +1820,41 @@ impl ShaderCode for Gradient<Vec2> {
                 )
             })
             .fold("// Gradient\n".into(), |s, key| s + &key + "\n");
+        let wrapped;
+        let input = if self.wrap() == GradientWrap::Clamp || self.keys().len() == 1 {
+            input
+        } else {
+            let _ = writeln!(
+                s,
+                "let wrapped_{input} = {};",
+                self.wrap().to_wgsl_code(input)
+            );
+            wrapped = format!("wrapped_{input}");
+            wrapped.as_str()
+        };
         if self.keys().len() == 1 {
             s + "return v0;\n"
         } else {
             s += &format!("if ({input} <= t0) {{ return v0; }}\n");
+            let easing = self.easing();
             let mut s = self
                 .keys()
                 .iter()
                 .skip(1)
                 .enumerate()
                 .map(|(index, _key)| {
+                    let ratio = format!("({input} - t{0}) / (t{1} - t{0})", index, index + 1);
+                    // Linear easing is the identity, so is omitted
+                    let ratio = if easing == Easing::Linear {
+                        ratio
+                    } else {
+                        format!("{}({})", easing.wgsl_fn_name(), ratio)
+                    };
                     format!(
-                        "else if ({input} <= t{1}) {{ return mix(v{0}, v{1}, ({input} - t{0}) / (t{1} - t{0})); }}\n",
+                        "else if ({input} <= t{1}) {{ return mix(v{0}, v{1}, {2}); }}\n",
                         index,
-                        index + 1
+                        index + 1,
+                        ratio
                     )
                 })
                 .fold(s, |s, key| s + &key);
@@ -1302,20 +1882,41 @@ impl ShaderCode for Gradient<Vec4> {
                 )
             })
             .fold("// Gradient\n".into(), |s, key| s + &key + "\n");
+        let wrapped;
+        let input = if self.wrap() == GradientWrap::Clamp || self.keys().len() == 1 {
+            input
+        } else {
+            let _ = writeln!(
+                s,
+                "let wrapped_{input} = {};",
+                self.wrap().to_wgsl_code(input)
+            );
+            wrapped = format!("wrapped_{input}");
+            wrapped.as_str()
+        };
         if self.keys().len() == 1 {
             s + "return c0;\n"
         } else {
             s += &format!("if ({input} <= t0) {{ return c0; }}\n");
+            let easing = self.easing();
             let mut s = self
                 .keys()
                 .iter()
                 .skip(1)
                 .enumerate()
                 .map(|(index, _key)| {
+                    let ratio = format!("({input} - t{0}) / (t{1} - t{0})", index, index + 1);
+                    // Linear easing is the identity, so is omitted
+                    let ratio = if easing == Easing::Linear {
+                        ratio
+                    } else {
+                        format!("{}({})", easing.wgsl_fn_name(), ratio)
+                    };
                     format!(
-                        "else if ({input} <= t{1}) {{ return mix(c{0}, c{1}, ({input} - t{0}) / (t{1} - t{0})); }}\n",
+                        "else if ({input} <= t{1}) {{ return mix(c{0}, c{1}, {2}); }}\n",
                         index,
-                        index + 1
+                        index + 1,
+                        ratio
                     )
                 })
                 .fold(s, |s, key| s + &key);
@@ -1338,14 +1939,32 @@ impl ShaderCode for Gradient<Vec4> {
 /// having mixed state where only some effects are compiled, and effects
 /// becoming visible later need to be special casing. If you want to avoid
 /// compiling an effect, don't spawn it.
+///
+/// Instances are also recompiled when their [`EffectAsset`] is modified, for
+/// example when the `.effect` file it was loaded from changes on disk while
+/// asset hot-reloading is enabled. However the GPU buffers of an instance are
+/// not reallocated, so modifications changing the capacities, the particle
+/// layout, or the property layout of the asset are rejected with a warning,
+/// and the instance keeps using the previously compiled version of the effect
+/// until it's spawned again.
 fn compile_effects(
     effects: Res<Assets<EffectAsset>>,
+    mut asset_events: EventReader<AssetEvent<EffectAsset>>,
     mut shaders: ResMut<Assets<Shader>>,
     mut shader_cache: ResMut<ShaderCache>,
     mut q_effects: Query<(Entity, Ref<ParticleEffect>, &mut CompiledParticleEffect)>,
 ) {
     trace!("compile_effects");
 
+    // Collect all assets modified since last update, to recompile their instances
+    let modified_assets: HashSet<AssetId<EffectAsset>> = asset_events
+        .read()
+        .filter_map(|event| match event {
+            AssetEvent::Modified { id } => Some(*id),
+            _ => None,
+        })
+        .collect();
+
     // Loop over all existing effects to update them, including invisible ones
     for (asset, entity, effect, mut compiled_effect) in
         q_effects
@@ -1359,15 +1978,29 @@ fn compile_effects(
                 Some((asset, entity, effect, compiled_effect))
             })
     {
-        // If the ParticleEffect didn't change, and the compiled one is for the correct
-        // asset, then there's nothing to do.
-        let need_rebuild = effect.is_changed();
+        // If the ParticleEffect didn't change, the asset wasn't modified, and the
+        // compiled one is for the correct asset, then there's nothing to do.
+        let asset_modified = modified_assets.contains(&effect.handle.id());
+        let need_rebuild = effect.is_changed() || asset_modified;
         if !need_rebuild && (compiled_effect.asset == effect.handle) {
             continue;
         }
 
-        if need_rebuild {
+        if asset_modified
+            && (compiled_effect.asset == effect.handle)
+            && !compiled_effect.is_compatible_with(asset)
+        {
+            warn!("Cannot recompile effect on entity {:?} after its EffectAsset '{}' was modified, because the capacities, the particle layout, or the property layout changed. Despawn and respawn the effect to apply those changes.", entity, asset.name);
+            continue;
+        }
+
+        if effect.is_changed() {
             debug!("Invalidating the compiled cache for effect on entity {:?} due to changes in the ParticleEffect component. If you see this message too much, then performance might be affected. Find why the change detection of the ParticleEffect is triggered.", entity);
+        } else if asset_modified {
+            debug!(
+                "Invalidating the compiled cache for effect on entity {:?} due to changes in its EffectAsset '{}'.",
+                entity, asset.name
+            );
         }
 
         #[cfg(feature = "2d")]
@@ -1421,6 +2054,67 @@ fn update_properties_from_asset(
     }
 }
 
+/// Update the [`Aabb`] of 2D effects from the explicit bounds of their asset.
+///
+/// For each [`ParticleEffect`] whose asset has explicit 2D bounds set with
+/// [`EffectAsset::with_bounds_2d()`], insert or update an [`Aabb`] component
+/// on the effect entity, so Bevy's frustum culling can skip the effect when
+/// it's out of view. Effects whose asset doesn't have explicit bounds are left
+/// untouched, to preserve any [`Aabb`] authored manually.
+///
+/// This system executes inside the [`EffectSystems::CompileEffects`] set of
+/// the [`PostUpdate`] schedule.
+///
+/// [`Aabb`]: bevy::render::primitives::Aabb
+#[cfg(feature = "2d")]
+fn update_bounds_2d(
+    mut commands: Commands,
+    assets: Res<Assets<EffectAsset>>,
+    mut q_effects: Query<(
+        Entity,
+        &ParticleEffect,
+        Option<&mut bevy::render::primitives::Aabb>,
+    )>,
+) {
+    trace!("update_bounds_2d");
+
+    for (entity, effect, aabb) in q_effects.iter_mut() {
+        let Some(bounds) = assets.get(&effect.handle).and_then(|asset| asset.bounds_2d) else {
+            continue;
+        };
+        let new_aabb = bounds_2d_to_aabb(bounds);
+        match aabb {
+            Some(mut aabb) => {
+                // Only write on change, to keep change detection meaningful
+                if *aabb != new_aabb {
+                    *aabb = new_aabb;
+                }
+            }
+            None => {
+                commands.entity(entity).insert(new_aabb);
+            }
+        }
+    }
+}
+
+/// Convert the explicit 2D bounds of an effect into an [`Aabb`].
+///
+/// The 2D bounds don't constrain the depth of the particles. To avoid a
+/// zero-depth box, which culls any particle not exactly on the Z=0 plane of
+/// the emitter, for example when the effect is viewed by a 3D camera with both
+/// the `2d` and `3d` features enabled, the box extends in depth as much as
+/// along its largest 2D dimension.
+///
+/// [`Aabb`]: bevy::render::primitives::Aabb
+#[cfg(feature = "2d")]
+fn bounds_2d_to_aabb(bounds: Rect) -> bevy::render::primitives::Aabb {
+    let half_depth = bounds.half_size().max_element();
+    bevy::render::primitives::Aabb {
+        center: bounds.center().extend(0.).into(),
+        half_extents: bounds.half_size().extend(half_depth).into(),
+    }
+}
+
 /// Event sent by [`gather_removed_effects()`] with the list of effects removed
 /// during this frame.
 ///
@@ -1453,12 +2147,13 @@ mod tests {
     use std::{iter, ops::DerefMut};
 
     use bevy::{
+        app::MainScheduleOrder,
         asset::{
             io::{
                 memory::{Dir, MemoryAssetReader},
                 AssetSourceBuilder, AssetSourceBuilders, AssetSourceId,
             },
-            AssetServerMode,
+            AssetEvents, AssetServerMode,
         },
         render::{
             deterministic::DeterministicRenderingConfig,
@@ -1628,6 +2323,35 @@ let c1 = vec4<f32>(1.,0.,0.,1.);
 if (key <= t0) { return c0; }
 else if (key <= t1) { return mix(c0, c1, (key - t0) / (t1 - t0)); }
 else { return c1; }
+"#,
+            grad.to_shader_code("key")
+        );
+        let grad = grad.with_wrap(GradientWrap::Repeat);
+        assert_eq!(
+            r#"// Gradient
+let t0 = 0.;
+let c0 = vec4<f32>(0.,0.,0.,0.);
+let t1 = 1.;
+let c1 = vec4<f32>(1.,0.,0.,1.);
+let wrapped_key = fract(key);
+if (wrapped_key <= t0) { return c0; }
+else if (wrapped_key <= t1) { return mix(c0, c1, (wrapped_key - t0) / (t1 - t0)); }
+else { return c1; }
+"#,
+            grad.to_shader_code("key")
+        );
+        let grad = grad
+            .with_wrap(GradientWrap::Clamp)
+            .with_easing(Easing::CubicOut);
+        assert_eq!(
+            r#"// Gradient
+let t0 = 0.;
+let c0 = vec4<f32>(0.,0.,0.,0.);
+let t1 = 1.;
+let c1 = vec4<f32>(1.,0.,0.,1.);
+if (key <= t0) { return c0; }
+else if (key <= t1) { return mix(c0, c1, ease_cubic_out((key - t0) / (t1 - t0))); }
+else { return c1; }
 "#,
             grad.to_shader_code("key")
         );
@@ -1697,6 +2421,12 @@ else { return c1; }
             AssetServer::new(sources, AssetServerMode::Unprocessed, watch_for_changes);
 
         app.insert_resource(asset_server);
+        // Run the schedule sending the asset events, like the AssetPlugin does, so
+        // that compile_effects() can react to modified assets.
+        app.init_schedule(AssetEvents);
+        app.world
+            .resource_mut::<MainScheduleOrder>()
+            .insert_after(PostUpdate, AssetEvents);
         // app.add_plugins(DefaultPlugins);
         app.init_asset::<Mesh>();
         app.init_asset::<Shader>();
@@ -1726,6 +2456,226 @@ else { return c1; }
         }
     }
 
+    #[test]
+    fn test_effect_shader_source_motion_integration() {
+        let integration_code = "particle.position += particle.velocity * sim_params.delta_time;";
+        let modifier_code = "particle.position = ";
+
+        for (motion_integration, integration_first) in [
+            (MotionIntegration::PreUpdate, true),
+            (MotionIntegration::PostUpdate, false),
+        ] {
+            let mut module = Module::default();
+            let zero = module.lit(Vec3::ZERO);
+            let up = module.lit(Vec3::Y);
+            let asset = EffectAsset::new(vec![256], Spawner::rate(32.0.into()), module)
+                .with_motion_integration(motion_integration)
+                .init(SetAttributeModifier::new(Attribute::POSITION, zero))
+                .init(SetAttributeModifier::new(Attribute::VELOCITY, up))
+                .update(SetAttributeModifier::new(Attribute::POSITION, up));
+            let shader_source = EffectShaderSource::generate(&asset).unwrap();
+            let update_code = &shader_source.update[0];
+            let integration_pos = update_code.find(integration_code).unwrap();
+            let modifier_pos = update_code.find(modifier_code).unwrap();
+            assert_eq!(integration_pos < modifier_pos, integration_first);
+        }
+
+        // No integration
+        let mut module = Module::default();
+        let zero = module.lit(Vec3::ZERO);
+        let asset = EffectAsset::new(vec![256], Spawner::rate(32.0.into()), module)
+            .with_motion_integration(MotionIntegration::None)
+            .init(SetAttributeModifier::new(Attribute::POSITION, zero))
+            .init(SetAttributeModifier::new(Attribute::VELOCITY, zero));
+        let shader_source = EffectShaderSource::generate(&asset).unwrap();
+        assert!(!shader_source.update[0].contains(integration_code));
+
+        // Per-group override
+        let mut module = Module::default();
+        let zero = module.lit(Vec3::ZERO);
+        let asset = EffectAsset::new(vec![256, 256], Spawner::rate(32.0.into()), module)
+            .with_motion_integration(MotionIntegration::PostUpdate)
+            .with_group_motion_integration(1, MotionIntegration::None)
+            .init(SetAttributeModifier::new(Attribute::POSITION, zero))
+            .init(SetAttributeModifier::new(Attribute::VELOCITY, zero));
+        let shader_source = EffectShaderSource::generate(&asset).unwrap();
+        assert!(shader_source.update[0].contains(integration_code));
+        assert!(!shader_source.update[1].contains(integration_code));
+    }
+
+    #[test]
+    fn test_effect_shader_source_draw_order() {
+        let rotate_code = "render_group_indirect[group_index].spawn_offset";
+
+        let mut module = Module::default();
+        let zero = module.lit(Vec3::ZERO);
+        let asset = EffectAsset::new(vec![256, 256], Spawner::rate(32.0.into()), module)
+            .init(SetAttributeModifier::new(Attribute::POSITION, zero));
+        assert_eq!(asset.draw_order, ParticleDrawOrder::OldestFirst);
+        let shader_source = EffectShaderSource::generate(&asset).unwrap();
+        for update_code in &shader_source.update {
+            assert!(update_code.contains("let read_index = thread_index;"));
+            assert!(!update_code.contains(rotate_code));
+        }
+
+        // Asset-wide draw order
+        let asset = asset.with_draw_order(ParticleDrawOrder::NewestFirst);
+        let shader_source = EffectShaderSource::generate(&asset).unwrap();
+        for update_code in &shader_source.update {
+            assert!(update_code.contains(rotate_code));
+        }
+
+        // Per-group override
+        let asset = asset.with_group_draw_order(1, ParticleDrawOrder::OldestFirst);
+        assert_eq!(asset.group_draw_order(0), ParticleDrawOrder::NewestFirst);
+        assert_eq!(asset.group_draw_order(1), ParticleDrawOrder::OldestFirst);
+        let shader_source = EffectShaderSource::generate(&asset).unwrap();
+        assert!(shader_source.update[0].contains(rotate_code));
+        assert!(!shader_source.update[1].contains(rotate_code));
+    }
+
+    #[test]
+    fn test_effect_shader_source_emitter_scaling() {
+        let scale_code = "particle.position *= vec3<f32>(length(transform[0].xyz)";
+
+        let mut module = Module::default();
+        let zero = module.lit(Vec3::ZERO);
+        let asset = EffectAsset::new(vec![256], Spawner::rate(32.0.into()), module)
+            .init(SetAttributeModifier::new(Attribute::POSITION, zero));
+        assert!(!asset.emitter_scaling);
+        let shader_source = EffectShaderSource::generate(&asset).unwrap();
+        assert!(!shader_source.init.contains(scale_code));
+
+        let asset = asset.with_emitter_scaling(true);
+        let shader_source = EffectShaderSource::generate(&asset).unwrap();
+        assert!(shader_source.init.contains(scale_code));
+
+        // Local-space effects are scaled by the render transform
+        let asset = asset.with_simulation_space(SimulationSpace::Local);
+        let shader_source = EffectShaderSource::generate(&asset).unwrap();
+        assert!(!shader_source.init.contains(scale_code));
+    }
+
+    #[test]
+    fn test_effect_shader_source_render_primitive() {
+        let mut module = Module::default();
+        let zero = module.lit(Vec3::ZERO);
+        let asset = EffectAsset::new(vec![256], Spawner::rate(32.0.into()), module)
+            .init(SetAttributeModifier::new(Attribute::POSITION, zero));
+        assert_eq!(asset.render_primitive, RenderPrimitive::Quad);
+        let shader_source = EffectShaderSource::generate(&asset).unwrap();
+        assert!(!shader_source
+            .layout_flags
+            .contains(LayoutFlags::POINT_PRIMITIVE));
+
+        let asset = asset.with_render_primitive(RenderPrimitive::Point);
+        let shader_source = EffectShaderSource::generate(&asset).unwrap();
+        assert!(shader_source
+            .layout_flags
+            .contains(LayoutFlags::POINT_PRIMITIVE));
+    }
+
+    /// Render modifier writing some fragment code, to test the fragment stage.
+    #[derive(Debug, Clone, Reflect, Serialize, Deserialize)]
+    struct FragmentCodeModifier(String);
+
+    modifier::impl_mod_render!(FragmentCodeModifier, &[]);
+
+    #[typetag::serde]
+    impl RenderModifier for FragmentCodeModifier {
+        fn apply_render(
+            &self,
+            _module: &mut Module,
+            context: &mut RenderContext,
+        ) -> Result<(), ExprError> {
+            context.fragment_code += &self.0;
+            Ok(())
+        }
+
+        fn boxed_render_clone(&self) -> Box<dyn RenderModifier> {
+            Box::new(self.clone())
+        }
+
+        fn as_modifier(&self) -> &dyn Modifier {
+            self
+        }
+    }
+
+    #[test]
+    fn test_effect_shader_source_particle_index() {
+        let mut module = Module::default();
+        let zero = module.lit(Vec3::ZERO);
+        let asset = EffectAsset::new(vec![256], Spawner::rate(32.0.into()), module)
+            .init(SetAttributeModifier::new(Attribute::POSITION, zero))
+            .render(FragmentCodeModifier("let alpha = 1.;".to_string()));
+        let shader_source = EffectShaderSource::generate(&asset).unwrap();
+        assert!(!shader_source
+            .layout_flags
+            .contains(LayoutFlags::PARTICLE_INDEX));
+
+        // Reading the particle index in the fragment shader forwards it there
+        let asset = asset.render(FragmentCodeModifier(
+            "let parity = particle_index % 2u;".to_string(),
+        ));
+        let shader_source = EffectShaderSource::generate(&asset).unwrap();
+        assert!(shader_source
+            .layout_flags
+            .contains(LayoutFlags::PARTICLE_INDEX));
+    }
+
+    #[test]
+    fn test_effect_shader_source_normal() {
+        let mut module = Module::default();
+        let zero = module.lit(Vec3::ZERO);
+        let asset = EffectAsset::new(vec![256], Spawner::rate(32.0.into()), module)
+            .init(SetAttributeModifier::new(Attribute::POSITION, zero));
+        let shader_source = EffectShaderSource::generate(&asset).unwrap();
+        assert!(!shader_source
+            .layout_flags
+            .contains(LayoutFlags::PARTICLE_NORMAL));
+
+        let asset = asset
+            .render(ParticleTextureModifier::default())
+            .render(NormalModifier::new(NormalMode::NormalMap));
+        let shader_source = EffectShaderSource::generate(&asset).unwrap();
+        assert!(shader_source
+            .layout_flags
+            .contains(LayoutFlags::PARTICLE_NORMAL));
+        let render_code = &shader_source.render[0];
+        assert!(render_code.contains("let normal = normalize("));
+        assert!(render_code.contains("textureSample(particle_texture, particle_sampler, in.uv)"));
+        assert!(!render_code.contains("{{"));
+    }
+
+    #[test]
+    fn test_effect_shader_source_prev_position() {
+        let prev_position_code = "particle.prev_position = particle.position;";
+        let integration_code = "particle.position += particle.velocity * sim_params.delta_time;";
+
+        let mut module = Module::default();
+        let zero = module.lit(Vec3::ZERO);
+        let asset = EffectAsset::new(vec![256], Spawner::rate(32.0.into()), module)
+            .with_motion_integration(MotionIntegration::PreUpdate)
+            .init(SetAttributeModifier::new(Attribute::POSITION, zero))
+            .init(SetAttributeModifier::new(Attribute::VELOCITY, zero))
+            .init(SetAttributeModifier::new(Attribute::PREV_POSITION, zero));
+        let shader_source = EffectShaderSource::generate(&asset).unwrap();
+
+        // Initialized after the position
+        let init_code = &shader_source.init;
+        assert!(
+            init_code.find(prev_position_code).unwrap()
+                > init_code.find("particle.position = ").unwrap()
+        );
+
+        // Updated before motion integration
+        let update_code = &shader_source.update[0];
+        assert!(
+            update_code.find(prev_position_code).unwrap()
+                < update_code.find(integration_code).unwrap()
+        );
+    }
+
     #[test]
     fn test_effect_shader_source() {
         // Empty particle layout
@@ -1778,6 +2728,7 @@ else { return c1; }
             let mut shader_defs = std::collections::HashMap::<String, ShaderDefValue>::new();
             shader_defs.insert("LOCAL_SPACE_SIMULATION".into(), ShaderDefValue::Bool(true));
             shader_defs.insert("PARTICLE_TEXTURE".into(), ShaderDefValue::Bool(true));
+            shader_defs.insert("PARTICLE_UV".into(), ShaderDefValue::Bool(true));
             shader_defs.insert("RENDER_NEEDS_SPAWNER".into(), ShaderDefValue::Bool(true));
             shader_defs.insert(
                 "PARTICLE_SCREEN_SPACE_SIZE".into(),
@@ -1907,6 +2858,21 @@ else { return c1; }
             assert_eq!(compiled_particle_effect.asset, handle);
             assert!(compiled_particle_effect.asset.is_weak());
             assert!(compiled_particle_effect.effect_shader.is_some());
+
+            // Shader handles are exposed for profiling
+            let effect_shader = compiled_particle_effect.effect_shader.as_ref().unwrap();
+            assert_eq!(
+                compiled_particle_effect.init_shader(),
+                Some(&effect_shader.init)
+            );
+            assert_eq!(
+                compiled_particle_effect.update_shaders(),
+                &effect_shader.update[..]
+            );
+            assert_eq!(
+                compiled_particle_effect.render_shaders(),
+                &effect_shader.render[..]
+            );
         }
 
         // Mark as changed without actually changing anything
@@ -1945,6 +2911,248 @@ else { return c1; }
         }
     }
 
+    #[test]
+    fn test_compile_effect_asset_modified() {
+        let make_asset = |capacity: u32, init_pos: Vec3| {
+            let mut module = Module::default();
+            let init_pos = module.lit(init_pos);
+            EffectAsset::new(vec![capacity], Spawner::once(32.0.into(), true), module)
+                .with_simulation_condition(SimulationCondition::Always)
+                .init(SetAttributeModifier::new(Attribute::POSITION, init_pos))
+        };
+
+        let mut app = make_test_app();
+
+        let handle = {
+            let world = &mut app.world;
+
+            let handle = world
+                .resource_mut::<Assets<EffectAsset>>()
+                .add(make_asset(64, Vec3::ZERO));
+            world.spawn((
+                ParticleEffect::new(handle.clone()),
+                CompiledParticleEffect::default(),
+            ));
+            world.spawn(Camera3dBundle::default());
+
+            handle
+        };
+
+        let get_shader = |app: &mut App| {
+            let world = &mut app.world;
+            let compiled_particle_effect = world
+                .query::<&CompiledParticleEffect>()
+                .iter(world)
+                .next()
+                .unwrap();
+            compiled_particle_effect.effect_shader.clone().unwrap()
+        };
+
+        // Tick once
+        app.update();
+        let shader = get_shader(&mut app);
+
+        // Modify the asset in a compatible way; this recompiles the effect. Bevy sends
+        // the AssetEvent::Modified event in its AssetEvents schedule, after PostUpdate,
+        // so the effect is only recompiled on the next tick.
+        *app.world
+            .resource_mut::<Assets<EffectAsset>>()
+            .get_mut(&handle)
+            .unwrap() = make_asset(64, Vec3::ONE);
+        app.update();
+        app.update();
+        let new_shader = get_shader(&mut app);
+        assert_ne!(shader.init, new_shader.init);
+
+        // Modify the capacity; this is rejected, and the effect is not recompiled
+        *app.world
+            .resource_mut::<Assets<EffectAsset>>()
+            .get_mut(&handle)
+            .unwrap() = make_asset(128, Vec3::NEG_ONE);
+        app.update();
+        app.update();
+        let rejected_shader = get_shader(&mut app);
+        assert_eq!(new_shader.init, rejected_shader.init);
+
+        // Add a property; this is rejected too, as the property buffer of the effect
+        // instance was sized for the old property layout
+        *app.world
+            .resource_mut::<Assets<EffectAsset>>()
+            .get_mut(&handle)
+            .unwrap() = make_asset(64, Vec3::NEG_ONE).with_property("my_prop", 3.0.into());
+        app.update();
+        app.update();
+        let rejected_shader = get_shader(&mut app);
+        assert_eq!(new_shader.init, rejected_shader.init);
+    }
+
+    #[test]
+    fn test_compiled_effect_batch_key() {
+        let make_asset = |simulation_space: SimulationSpace| {
+            let mut module = Module::default();
+            let init_pos = module.lit(Vec3::ZERO);
+            EffectAsset::new(vec![64], Spawner::once(32.0.into(), true), module)
+                .with_simulation_condition(SimulationCondition::Always)
+                .with_simulation_space(simulation_space)
+                .init(SetAttributeModifier::new(Attribute::POSITION, init_pos))
+        };
+
+        assert!(CompiledParticleEffect::default().batch_key().is_none());
+
+        let mut app = make_test_app();
+        {
+            let world = &mut app.world;
+            let (global, local, blended) = {
+                let mut assets = world.resource_mut::<Assets<EffectAsset>>();
+                (
+                    assets.add(make_asset(SimulationSpace::Global)),
+                    assets.add(make_asset(SimulationSpace::Local)),
+                    assets.add(
+                        make_asset(SimulationSpace::Global)
+                            .with_blend_state(BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+                    ),
+                )
+            };
+            for handle in [global.clone(), global, local, blended] {
+                world.spawn((
+                    ParticleEffect::new(handle),
+                    CompiledParticleEffect::default(),
+                ));
+            }
+            world.spawn(Camera3dBundle::default());
+        }
+
+        app.update();
+
+        let world = &mut app.world;
+        let mut keys: Vec<_> = world
+            .query::<(Entity, &CompiledParticleEffect)>()
+            .iter(world)
+            .map(|(entity, compiled_effect)| (entity, compiled_effect.batch_key().unwrap()))
+            .collect();
+        keys.sort_by_key(|(entity, _)| *entity);
+        let keys: Vec<_> = keys.into_iter().map(|(_, key)| key).collect();
+        assert_eq!(keys.len(), 4);
+
+        // Instances of the same asset share the same batch key
+        assert_eq!(keys[0], keys[1]);
+
+        // A different simulation space prevents batching
+        assert_ne!(keys[0], keys[2]);
+        assert!(!keys[0]
+            .layout_flags
+            .contains(LayoutFlags::LOCAL_SPACE_SIMULATION));
+        assert!(keys[2]
+            .layout_flags
+            .contains(LayoutFlags::LOCAL_SPACE_SIMULATION));
+
+        // A custom blend state prevents batching
+        assert_ne!(keys[0], keys[3]);
+        assert_eq!(keys[0].blend_state, None);
+        assert_eq!(
+            keys[3].blend_state,
+            Some(BlendState::PREMULTIPLIED_ALPHA_BLENDING)
+        );
+    }
+
+    #[test]
+    fn test_compiled_effect_is_finished() {
+        let mut module = Module::default();
+        let min_lifetime = module.lit(0.5);
+        let max_lifetime = module.lit(1.5);
+        let lifetime = module.uniform(min_lifetime, max_lifetime);
+        let asset = EffectAsset::new(vec![64], Spawner::once(32.0.into(), true), module)
+            .init(SetAttributeModifier::new(Attribute::LIFETIME, lifetime));
+        assert_eq!(asset.estimated_max_lifetime(), Some(1.5));
+
+        let compiled_effect = CompiledParticleEffect {
+            max_lifetime: asset.estimated_max_lifetime(),
+            ..default()
+        };
+        let mut spawner = EffectSpawner::new(&asset, &ParticleEffect::default());
+        assert!(!spawner.is_done());
+        assert!(!compiled_effect.is_finished(&spawner));
+
+        // Spawn the burst; the spawner is done, but particles are still alive
+        let rng = &mut new_rng();
+        assert_eq!(spawner.tick(0.1, rng), 32);
+        assert!(spawner.is_done());
+        assert!(!compiled_effect.is_finished(&spawner));
+        spawner.tick(1.0, rng);
+        assert!(!compiled_effect.is_finished(&spawner));
+
+        // After the max lifetime elapsed, all particles died
+        spawner.tick(0.6, rng);
+        assert!(compiled_effect.is_finished(&spawner));
+
+        // Resetting the spawner restarts the effect
+        spawner.reset();
+        assert!(!spawner.is_done());
+        assert!(!compiled_effect.is_finished(&spawner));
+
+        // Unknown lifetime is never finished
+        let compiled_effect = CompiledParticleEffect::default();
+        spawner.tick(0.1, rng);
+        spawner.tick(10., rng);
+        assert!(spawner.is_done());
+        assert!(!compiled_effect.is_finished(&spawner));
+    }
+
+    #[cfg(feature = "2d")]
+    #[test]
+    fn test_bounds_2d_to_aabb() {
+        let aabb = bounds_2d_to_aabb(Rect::new(-1., 2., 3., 4.));
+        assert_eq!(Vec3::from(aabb.center), Vec3::new(1., 3., 0.));
+        assert_eq!(Vec3::from(aabb.half_extents), Vec3::new(2., 1., 2.));
+    }
+
+    #[cfg(feature = "2d")]
+    #[test]
+    fn test_compile_effect_z_layer_2d() {
+        let spawner = Spawner::once(32.0.into(), true);
+
+        let mut app = make_test_app();
+
+        {
+            let world = &mut app.world;
+
+            // Add effect asset with a default Z layer
+            let mut assets = world.resource_mut::<Assets<EffectAsset>>();
+            let mut module = Module::default();
+            let init_pos = module.lit(Vec3::ZERO);
+            let asset = EffectAsset::new(vec![64], spawner, module)
+                .with_z_layer_2d(0.5)
+                .with_simulation_condition(SimulationCondition::Always)
+                .init(SetAttributeModifier::new(Attribute::POSITION, init_pos));
+            let handle = assets.add(asset);
+
+            // Spawn two instances, one of which overrides the Z layer
+            world.spawn((
+                ParticleEffect::new(handle.clone()),
+                CompiledParticleEffect::default(),
+            ));
+            world.spawn((
+                ParticleEffect::new(handle).with_z_layer_2d(Some(-3.)),
+                CompiledParticleEffect::default(),
+            ));
+
+            world.spawn(Camera3dBundle::default());
+        }
+
+        // Tick once
+        app.update();
+
+        // Check
+        let world = &mut app.world;
+        let mut z_layers = world
+            .query::<&CompiledParticleEffect>()
+            .iter(world)
+            .map(|compiled| compiled.z_layer_2d.0)
+            .collect::<Vec<_>>();
+        z_layers.sort_by(f32::total_cmp);
+        assert_eq!(z_layers, vec![-3., 0.5]);
+    }
+
     #[test]
     fn test_compile_effect_visibility() {
         let spawner = Spawner::once(32.0.into(), true);
@@ -0,0 +1,580 @@
+//! Sampling of mesh surfaces, to emit particles from a mesh.
+//!
+//! The [`MeshSurfaceSampler`] generates particle initial states uniformly
+//! distributed over the surface of a [`Mesh`], to be spawned with a
+//! [`ParticleInjector`]. This allows for example dissolving an object into
+//! particles, with each particle retaining the color of the object where it
+//! was emitted, either from the mesh vertex colors or from a texture sampled
+//! at the mesh UVs.
+//!
+//! The mesh data is copied from the CPU-side [`Mesh`] and [`Image`] assets
+//! when the sampler is created, so those assets must retain their main world
+//! data (see [`RenderAssetUsages::MAIN_WORLD`]). The sampled positions,
+//! velocities, and colors are uploaded to the GPU alongside the other
+//! injected particles.
+//!
+//! [`ParticleInjector`]: crate::ParticleInjector
+//! [`RenderAssetUsages::MAIN_WORLD`]: bevy::render::render_asset::RenderAssetUsages::MAIN_WORLD
+
+use bevy::{
+    prelude::*,
+    render::{
+        mesh::{Indices, VertexAttributeValues},
+        render_resource::{PrimitiveTopology, TextureFormat},
+    },
+    utils::thiserror::Error,
+};
+use rand::Rng;
+
+use crate::ParticleInitState;
+
+/// Error resulting from the creation of a [`MeshSurfaceSampler`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub enum MeshSampleError {
+    /// The mesh is not a triangle list, so has no surface to sample.
+    #[error("the mesh has topology {0:?}, but only PrimitiveTopology::TriangleList is supported")]
+    UnsupportedTopology(PrimitiveTopology),
+
+    /// The mesh doesn't have a [`Mesh::ATTRIBUTE_POSITION`] attribute, or
+    /// that attribute is not in [`VertexFormat::Float32x3`] format.
+    ///
+    /// [`VertexFormat::Float32x3`]: bevy::render::render_resource::VertexFormat::Float32x3
+    #[error("the mesh has no Float32x3 position attribute")]
+    MissingPositions,
+
+    /// The mesh doesn't have a [`Mesh::ATTRIBUTE_COLOR`] attribute, or that
+    /// attribute is not in [`VertexFormat::Float32x4`] format.
+    ///
+    /// [`VertexFormat::Float32x4`]: bevy::render::render_resource::VertexFormat::Float32x4
+    #[error("the mesh has no Float32x4 vertex color attribute")]
+    MissingVertexColors,
+
+    /// The mesh doesn't have a [`Mesh::ATTRIBUTE_UV_0`] attribute, or that
+    /// attribute is not in [`VertexFormat::Float32x2`] format.
+    ///
+    /// [`VertexFormat::Float32x2`]: bevy::render::render_resource::VertexFormat::Float32x2
+    #[error("the mesh has no Float32x2 UV attribute")]
+    MissingUvs,
+
+    /// The number of values of a vertex attribute of the mesh, given as first
+    /// field, doesn't match the number of vertex positions, given as second
+    /// field.
+    #[error("the mesh has {0} values for a vertex attribute, but {1} vertex positions")]
+    AttributeCountMismatch(usize, usize),
+
+    /// The texture format can't be sampled on the CPU. Only the
+    /// [`TextureFormat::Rgba8Unorm`], [`TextureFormat::Rgba8UnormSrgb`], and
+    /// [`TextureFormat::Rgba32Float`] formats are supported.
+    #[error("the texture format {0:?} is not supported")]
+    UnsupportedTextureFormat(TextureFormat),
+
+    /// The texture doesn't contain any CPU data, or its data doesn't match
+    /// its size.
+    #[error("the texture has no CPU data")]
+    MissingTextureData,
+
+    /// The mesh has no triangle, or all its triangles are degenerate.
+    #[error("the mesh has no triangle with a non-zero area")]
+    EmptySurface,
+}
+
+/// Source of the color of the particles emitted by a [`MeshSurfaceSampler`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MeshColorSource {
+    /// Don't assign any color; the particles retain the color assigned by the
+    /// init modifiers of the effect.
+    #[default]
+    None,
+    /// Interpolate the [`Mesh::ATTRIBUTE_COLOR`] vertex colors of the sampled
+    /// triangle at the emission point.
+    VertexColor,
+    /// Sample a texture at the UV coordinates of the emission point,
+    /// interpolated from the [`Mesh::ATTRIBUTE_UV_0`] of the sampled
+    /// triangle.
+    Texture,
+}
+
+/// CPU copy of a texture, in linear color space.
+#[derive(Debug, Clone, PartialEq)]
+struct TextureData {
+    /// Size of the texture, in texels.
+    size: UVec2,
+    /// Texels, row by row.
+    texels: Vec<Vec4>,
+}
+
+impl TextureData {
+    fn from_image(image: &Image) -> Result<Self, MeshSampleError> {
+        let format = image.texture_descriptor.format;
+        let size = UVec2::new(
+            image.texture_descriptor.size.width,
+            image.texture_descriptor.size.height,
+        );
+        let texel_size = match format {
+            TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb => 4,
+            TextureFormat::Rgba32Float => 16,
+            _ => return Err(MeshSampleError::UnsupportedTextureFormat(format)),
+        };
+        let count = (size.x * size.y) as usize;
+        if count == 0 || image.data.len() < count * texel_size {
+            return Err(MeshSampleError::MissingTextureData);
+        }
+        let texels = image.data[..count * texel_size]
+            .chunks_exact(texel_size)
+            .map(|t| match format {
+                TextureFormat::Rgba8Unorm => {
+                    Vec4::new(t[0] as f32, t[1] as f32, t[2] as f32, t[3] as f32) / 255.
+                }
+                TextureFormat::Rgba8UnormSrgb => {
+                    Vec4::from_array(Color::rgba_u8(t[0], t[1], t[2], t[3]).as_linear_rgba_f32())
+                }
+                _ => Vec4::from_array(std::array::from_fn(|i| {
+                    f32::from_le_bytes(t[i * 4..i * 4 + 4].try_into().unwrap())
+                })),
+            })
+            .collect();
+        Ok(Self { size, texels })
+    }
+
+    /// Sample the nearest texel, repeating the texture outside of the
+    /// `[0:1]` UV range.
+    fn sample(&self, uv: Vec2) -> Vec4 {
+        let uv = uv - uv.floor();
+        let texel = (uv * self.size.as_vec2())
+            .as_uvec2()
+            .min(self.size - UVec2::ONE);
+        self.texels[(texel.y * self.size.x + texel.x) as usize]
+    }
+}
+
+/// Sampler generating particles uniformly distributed over the surface of a
+/// mesh.
+///
+/// The sampler picks a random triangle of the mesh, weighted by its area,
+/// then a random point inside that triangle. The position of the generated
+/// [`ParticleInitState`] is that point, in the local space of the mesh. The
+/// particle can optionally be colored from the mesh itself, as selected by
+/// [`MeshColorSource`]:
+/// - with [`with_vertex_colors()`], the vertex colors of the triangle are
+///   interpolated at the emission point;
+/// - with [`with_texture()`], a texture is sampled at the UV coordinates of
+///   the emission point, using nearest filtering and repeat addressing.
+///
+/// The colors are in linear space, like [`Mesh::ATTRIBUTE_COLOR`]. Textures
+/// in an sRGB format are converted to linear space.
+///
+/// The generated states are meant to be spawned with a [`ParticleInjector`]:
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_hanabi::*;
+/// fn dissolve(
+///     meshes: Res<Assets<Mesh>>,
+///     mut rng: ResMut<Random>,
+///     mut query: Query<(&Handle<Mesh>, &mut ParticleInjector)>,
+/// ) {
+///     for (mesh, mut injector) in &mut query {
+///         let Some(mesh) = meshes.get(mesh) else {
+///             continue;
+///         };
+///         let Ok(sampler) =
+///             MeshSurfaceSampler::new(mesh).and_then(|sampler| sampler.with_vertex_colors())
+///         else {
+///             continue;
+///         };
+///         injector.inject(sampler.sample_many(256, &mut rng.0));
+///     }
+/// }
+/// ```
+///
+/// [`with_vertex_colors()`]: MeshSurfaceSampler::with_vertex_colors
+/// [`with_texture()`]: MeshSurfaceSampler::with_texture
+/// [`ParticleInjector`]: crate::ParticleInjector
+#[derive(Debug, Clone, PartialEq)]
+pub struct MeshSurfaceSampler {
+    /// Vertex positions.
+    positions: Vec<Vec3>,
+    /// Vertex colors, if the mesh has any.
+    colors: Option<Vec<Vec4>>,
+    /// Vertex UVs, if the mesh has any.
+    uvs: Option<Vec<Vec2>>,
+    /// Vertex indices of the non-degenerate triangles.
+    triangles: Vec<[usize; 3]>,
+    /// Cumulative area of the triangles, used for area-weighted sampling.
+    cumulative_areas: Vec<f32>,
+    /// Texture sampled for [`MeshColorSource::Texture`].
+    texture: Option<TextureData>,
+    /// Source of the particle colors.
+    color_source: MeshColorSource,
+}
+
+impl MeshSurfaceSampler {
+    /// Create a new sampler for the surface of the given mesh.
+    ///
+    /// The mesh must be a [`PrimitiveTopology::TriangleList`] with a
+    /// [`Mesh::ATTRIBUTE_POSITION`] attribute. Indexed and non-indexed meshes
+    /// are supported. The vertex colors and UVs are copied too if present,
+    /// for use with [`with_vertex_colors()`] and [`with_texture()`].
+    ///
+    /// [`with_vertex_colors()`]: MeshSurfaceSampler::with_vertex_colors
+    /// [`with_texture()`]: MeshSurfaceSampler::with_texture
+    pub fn new(mesh: &Mesh) -> Result<Self, MeshSampleError> {
+        let topology = mesh.primitive_topology();
+        if topology != PrimitiveTopology::TriangleList {
+            return Err(MeshSampleError::UnsupportedTopology(topology));
+        }
+
+        let positions: Vec<Vec3> = match mesh.attribute(Mesh::ATTRIBUTE_POSITION) {
+            Some(VertexAttributeValues::Float32x3(values)) => {
+                values.iter().map(|&p| Vec3::from_array(p)).collect()
+            }
+            _ => return Err(MeshSampleError::MissingPositions),
+        };
+        let colors = match mesh.attribute(Mesh::ATTRIBUTE_COLOR) {
+            Some(VertexAttributeValues::Float32x4(values)) => {
+                Some(values.iter().map(|&c| Vec4::from_array(c)).collect())
+            }
+            _ => None,
+        };
+        let uvs = match mesh.attribute(Mesh::ATTRIBUTE_UV_0) {
+            Some(VertexAttributeValues::Float32x2(values)) => {
+                Some(values.iter().map(|&uv| Vec2::from_array(uv)).collect())
+            }
+            _ => None,
+        };
+
+        let indices: Vec<usize> = match mesh.indices() {
+            Some(Indices::U16(indices)) => indices.iter().map(|&i| i as usize).collect(),
+            Some(Indices::U32(indices)) => indices.iter().map(|&i| i as usize).collect(),
+            None => (0..positions.len()).collect(),
+        };
+
+        let mut triangles = vec![];
+        let mut cumulative_areas = vec![];
+        let mut total_area = 0.;
+        for tri in indices.chunks_exact(3) {
+            // Skip any triangle referencing an invalid vertex
+            if tri.iter().any(|&i| i >= positions.len()) {
+                continue;
+            }
+            let (a, b, c) = (positions[tri[0]], positions[tri[1]], positions[tri[2]]);
+            let area = (b - a).cross(c - a).length() * 0.5;
+            if area <= 0. || !area.is_finite() {
+                continue;
+            }
+            total_area += area;
+            triangles.push([tri[0], tri[1], tri[2]]);
+            cumulative_areas.push(total_area);
+        }
+        if triangles.is_empty() {
+            return Err(MeshSampleError::EmptySurface);
+        }
+
+        Ok(Self {
+            positions,
+            colors,
+            uvs,
+            triangles,
+            cumulative_areas,
+            texture: None,
+            color_source: MeshColorSource::None,
+        })
+    }
+
+    /// Color the particles with the vertex colors of the mesh.
+    ///
+    /// This fails if the mesh doesn't have a [`Mesh::ATTRIBUTE_COLOR`]
+    /// attribute, or if that attribute doesn't have one value per vertex.
+    pub fn with_vertex_colors(mut self) -> Result<Self, MeshSampleError> {
+        let Some(colors) = &self.colors else {
+            return Err(MeshSampleError::MissingVertexColors);
+        };
+        self.check_attribute_count(colors.len())?;
+        self.color_source = MeshColorSource::VertexColor;
+        self.texture = None;
+        Ok(self)
+    }
+
+    /// Color the particles by sampling a texture at the mesh UVs.
+    ///
+    /// This fails if the mesh doesn't have a [`Mesh::ATTRIBUTE_UV_0`]
+    /// attribute, if that attribute doesn't have one value per vertex, or if
+    /// the texture can't be read on the CPU.
+    pub fn with_texture(mut self, image: &Image) -> Result<Self, MeshSampleError> {
+        let Some(uvs) = &self.uvs else {
+            return Err(MeshSampleError::MissingUvs);
+        };
+        self.check_attribute_count(uvs.len())?;
+        self.texture = Some(TextureData::from_image(image)?);
+        self.color_source = MeshColorSource::Texture;
+        Ok(self)
+    }
+
+    /// Check that a vertex attribute has as many values as there are vertex
+    /// positions.
+    fn check_attribute_count(&self, count: usize) -> Result<(), MeshSampleError> {
+        if count != self.positions.len() {
+            return Err(MeshSampleError::AttributeCountMismatch(
+                count,
+                self.positions.len(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Get the source of the particle colors.
+    pub fn color_source(&self) -> MeshColorSource {
+        self.color_source
+    }
+
+    /// Get the total area of the mesh surface.
+    pub fn area(&self) -> f32 {
+        *self.cumulative_areas.last().unwrap()
+    }
+
+    /// Generate the initial state of a single particle.
+    pub fn sample(&self, rng: &mut impl Rng) -> ParticleInitState {
+        // Pick a triangle, weighted by area
+        let target = rng.gen::<f32>() * self.area();
+        let index = self
+            .cumulative_areas
+            .partition_point(|&area| area <= target)
+            .min(self.triangles.len() - 1);
+        let tri = self.triangles[index];
+
+        // Pick a point uniformly inside the triangle, folding the points
+        // outside of it back inside.
+        let (mut u, mut v) = (rng.gen::<f32>(), rng.gen::<f32>());
+        if u + v > 1. {
+            u = 1. - u;
+            v = 1. - v;
+        }
+        let weights = Vec3::new(1. - u - v, u, v);
+
+        let position = interpolate(&self.positions, tri, weights);
+        let color = match self.color_source {
+            MeshColorSource::None => None,
+            MeshColorSource::VertexColor => self
+                .colors
+                .as_ref()
+                .map(|colors| interpolate(colors, tri, weights)),
+            MeshColorSource::Texture => {
+                let uv = interpolate(self.uvs.as_ref().unwrap(), tri, weights);
+                self.texture.as_ref().map(|texture| texture.sample(uv))
+            }
+        };
+
+        ParticleInitState {
+            position,
+            velocity: None,
+            color,
+        }
+    }
+
+    /// Generate the initial states of `count` particles.
+    pub fn sample_many<'a, R: Rng>(
+        &'a self,
+        count: usize,
+        rng: &'a mut R,
+    ) -> impl Iterator<Item = ParticleInitState> + 'a {
+        (0..count).map(move |_| self.sample(rng))
+    }
+}
+
+/// Interpolate some per-vertex values of a triangle with barycentric weights.
+fn interpolate<T>(values: &[T], tri: [usize; 3], weights: Vec3) -> T
+where
+    T: Copy + std::ops::Mul<f32, Output = T> + std::ops::Add<Output = T>,
+{
+    values[tri[0]] * weights.x + values[tri[1]] * weights.y + values[tri[2]] * weights.z
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::render::{
+        render_asset::RenderAssetUsages,
+        render_resource::{Extent3d, TextureDimension},
+    };
+    use rand::SeedableRng;
+    use rand_pcg::Pcg32;
+
+    use super::*;
+
+    /// Unit quad in the XY plane, red on the left and green on the right.
+    fn make_quad() -> Mesh {
+        Mesh::new(
+            PrimitiveTopology::TriangleList,
+            RenderAssetUsages::default(),
+        )
+        .with_inserted_attribute(
+            Mesh::ATTRIBUTE_POSITION,
+            vec![[0., 0., 0.], [1., 0., 0.], [1., 1., 0.], [0., 1., 0.]],
+        )
+        .with_inserted_attribute(
+            Mesh::ATTRIBUTE_COLOR,
+            vec![
+                [1., 0., 0., 1.],
+                [0., 1., 0., 1.],
+                [0., 1., 0., 1.],
+                [1., 0., 0., 1.],
+            ],
+        )
+        .with_inserted_attribute(
+            Mesh::ATTRIBUTE_UV_0,
+            vec![[0., 0.], [1., 0.], [1., 1.], [0., 1.]],
+        )
+        .with_inserted_indices(Indices::U16(vec![0, 1, 2, 0, 2, 3]))
+    }
+
+    #[test]
+    fn sample_positions() {
+        let sampler = MeshSurfaceSampler::new(&make_quad()).unwrap();
+        assert_eq!(sampler.color_source(), MeshColorSource::None);
+        assert!((sampler.area() - 1.).abs() < 1e-5);
+
+        let mut rng = Pcg32::seed_from_u64(42);
+        let mut sum = Vec3::ZERO;
+        for state in sampler.sample_many(1000, &mut rng) {
+            assert!(state.position.cmpge(Vec3::ZERO).all());
+            assert!(state.position.cmple(Vec3::new(1., 1., 0.)).all());
+            assert!(state.velocity.is_none());
+            assert!(state.color.is_none());
+            sum += state.position;
+        }
+        // Uniform distribution centered on the quad
+        let mean = sum / 1000.;
+        assert!(mean.abs_diff_eq(Vec3::new(0.5, 0.5, 0.), 0.05));
+    }
+
+    #[test]
+    fn sample_vertex_colors() {
+        let sampler = MeshSurfaceSampler::new(&make_quad())
+            .unwrap()
+            .with_vertex_colors()
+            .unwrap();
+        assert_eq!(sampler.color_source(), MeshColorSource::VertexColor);
+
+        let mut rng = Pcg32::seed_from_u64(42);
+        for state in sampler.sample_many(100, &mut rng) {
+            let color = state.color.unwrap();
+            // Red fades to green along X
+            let expected = Vec4::new(1. - state.position.x, state.position.x, 0., 1.);
+            assert!(color.abs_diff_eq(expected, 1e-4));
+        }
+    }
+
+    #[test]
+    fn sample_texture() {
+        // 2x1 texture, blue on the left and white on the right
+        let image = Image::new(
+            Extent3d {
+                width: 2,
+                height: 1,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            vec![0, 0, 255, 255, 255, 255, 255, 255],
+            TextureFormat::Rgba8Unorm,
+            RenderAssetUsages::default(),
+        );
+        let sampler = MeshSurfaceSampler::new(&make_quad())
+            .unwrap()
+            .with_texture(&image)
+            .unwrap();
+        assert_eq!(sampler.color_source(), MeshColorSource::Texture);
+
+        let mut rng = Pcg32::seed_from_u64(42);
+        for state in sampler.sample_many(100, &mut rng) {
+            let expected = if state.position.x < 0.5 {
+                Vec4::new(0., 0., 1., 1.)
+            } else {
+                Vec4::ONE
+            };
+            assert_eq!(state.color.unwrap(), expected);
+        }
+
+        let image = Image::new(
+            Extent3d {
+                width: 1,
+                height: 1,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            vec![0; 2],
+            TextureFormat::Rg8Unorm,
+            RenderAssetUsages::default(),
+        );
+        assert_eq!(
+            MeshSurfaceSampler::new(&make_quad())
+                .unwrap()
+                .with_texture(&image),
+            Err(MeshSampleError::UnsupportedTextureFormat(
+                TextureFormat::Rg8Unorm
+            ))
+        );
+    }
+
+    #[test]
+    fn invalid_mesh() {
+        let mesh = Mesh::new(PrimitiveTopology::LineList, RenderAssetUsages::default());
+        assert_eq!(
+            MeshSurfaceSampler::new(&mesh),
+            Err(MeshSampleError::UnsupportedTopology(
+                PrimitiveTopology::LineList
+            ))
+        );
+
+        let mesh = Mesh::new(
+            PrimitiveTopology::TriangleList,
+            RenderAssetUsages::default(),
+        );
+        assert_eq!(
+            MeshSurfaceSampler::new(&mesh),
+            Err(MeshSampleError::MissingPositions)
+        );
+
+        // Degenerate triangle
+        let mesh = Mesh::new(
+            PrimitiveTopology::TriangleList,
+            RenderAssetUsages::default(),
+        )
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, vec![[0., 0., 0.]; 3]);
+        assert_eq!(
+            MeshSurfaceSampler::new(&mesh),
+            Err(MeshSampleError::EmptySurface)
+        );
+
+        // No color nor UV
+        let mesh = Mesh::new(
+            PrimitiveTopology::TriangleList,
+            RenderAssetUsages::default(),
+        )
+        .with_inserted_attribute(
+            Mesh::ATTRIBUTE_POSITION,
+            vec![[0., 0., 0.], [1., 0., 0.], [0., 1., 0.]],
+        );
+        let sampler = MeshSurfaceSampler::new(&mesh).unwrap();
+        assert_eq!(
+            sampler.clone().with_vertex_colors(),
+            Err(MeshSampleError::MissingVertexColors)
+        );
+        assert_eq!(
+            sampler.with_texture(&Image::default()),
+            Err(MeshSampleError::MissingUvs)
+        );
+
+        // Fewer colors and UVs than positions
+        let mesh = mesh
+            .with_inserted_attribute(Mesh::ATTRIBUTE_COLOR, vec![[1., 1., 1., 1.]; 2])
+            .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, vec![[0., 0.]; 2]);
+        let sampler = MeshSurfaceSampler::new(&mesh).unwrap();
+        assert_eq!(
+            sampler.clone().with_vertex_colors(),
+            Err(MeshSampleError::AttributeCountMismatch(2, 3))
+        );
+        assert_eq!(
+            sampler.with_texture(&Image::default()),
+            Err(MeshSampleError::AttributeCountMismatch(2, 3))
+        );
+    }
+}
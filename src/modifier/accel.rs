@@ -27,36 +27,69 @@ use crate::{
 /// particle.velocity += acceleration * simulation.delta_time;
 /// ```
 ///
+/// If [`with_respect_mass()`] is enabled, the acceleration is instead
+/// interpreted as a force, and divided by the particle mass:
+///
+/// ```txt
+/// particle.velocity += acceleration * simulation.delta_time / particle.mass;
+/// ```
+///
+/// Particles with a zero mass are not accelerated.
+///
 /// # Attributes
 ///
 /// This modifier requires the following particle attributes:
 /// - [`Attribute::VELOCITY`]
+/// - [`Attribute::MASS`] (only if [`with_respect_mass()`] is enabled)
+///
+/// [`with_respect_mass()`]: crate::modifier::accel::AccelModifier::with_respect_mass
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Reflect, Serialize, Deserialize)]
 pub struct AccelModifier {
     /// The acceleration to apply to all particles in the effect each frame.
     ///
     /// Expression type: `Vec3`
     accel: ExprHandle,
+    /// Divide the acceleration by the particle mass.
+    #[serde(default)]
+    respect_mass: bool,
 }
 
 impl AccelModifier {
     /// Create a new modifier from an acceleration expression.
     pub fn new(accel: ExprHandle) -> Self {
-        Self { accel }
+        Self {
+            accel,
+            respect_mass: false,
+        }
     }
 
     /// Create a new modifier with an acceleration derived from a property.
     pub fn via_property(module: &mut Module, property_name: impl Into<String>) -> Self {
-        Self {
-            accel: module.prop(property_name),
-        }
+        Self::new(module.prop(property_name))
     }
 
     /// Create a new modifier with a constant acceleration.
     pub fn constant(module: &mut Module, acceleration: Vec3) -> Self {
-        Self {
-            accel: module.lit(acceleration),
-        }
+        Self::new(module.lit(acceleration))
+    }
+
+    /// Set whether the acceleration is divided by the particle mass.
+    ///
+    /// When enabled, the modifier requires the [`Attribute::MASS`] attribute,
+    /// and heavier particles are accelerated less. Defaults to `false`.
+    pub fn with_respect_mass(mut self, respect_mass: bool) -> Self {
+        self.respect_mass = respect_mass;
+        self
+    }
+
+    /// Get the expression of the acceleration applied to the particles.
+    pub fn accel(&self) -> ExprHandle {
+        self.accel
+    }
+
+    /// Is the acceleration divided by the particle mass?
+    pub fn respect_mass(&self) -> bool {
+        self.respect_mass
     }
 }
 
@@ -67,7 +100,11 @@ impl Modifier for AccelModifier {
     }
 
     fn attributes(&self) -> &[Attribute] {
-        &[Attribute::VELOCITY]
+        if self.respect_mass {
+            &[Attribute::VELOCITY, Attribute::MASS]
+        } else {
+            &[Attribute::VELOCITY]
+        }
     }
 
     fn boxed_clone(&self) -> BoxedModifier {
@@ -79,7 +116,13 @@ impl Modifier for AccelModifier {
         let attr = context.eval(module, attr)?;
         let expr = context.eval(module, self.accel)?;
         let dt = BuiltInExpr::new(crate::graph::BuiltInOperator::DeltaTime).eval(context)?;
-        context.main_code += &format!("{} += ({}) * {};", attr, expr, dt);
+        if self.respect_mass {
+            let mass = module.attr(Attribute::MASS);
+            let mass = context.eval(module, mass)?;
+            context.main_code += &format!("{} += ({}) * safe_div({}, {});", attr, expr, dt, mass);
+        } else {
+            context.main_code += &format!("{} += ({}) * {};", attr, expr, dt);
+        }
         Ok(())
     }
 }
@@ -99,11 +142,17 @@ impl Modifier for AccelModifier {
 /// point. The radial direction is calculated as the direction from the modifier
 /// origin to the particle position.
 ///
+/// If [`with_respect_mass()`] is enabled, the acceleration is divided by the
+/// particle mass, such that heavier particles are accelerated less.
+///
 /// # Attributes
 ///
 /// This modifier requires the following particle attributes:
 /// - [`Attribute::POSITION`]
 /// - [`Attribute::VELOCITY`]
+/// - [`Attribute::MASS`] (only if [`with_respect_mass()`] is enabled)
+///
+/// [`with_respect_mass()`]: crate::modifier::accel::RadialAccelModifier::with_respect_mass
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Reflect, Serialize, Deserialize)]
 pub struct RadialAccelModifier {
     /// The center point the radial direction is calculated from.
@@ -114,13 +163,20 @@ pub struct RadialAccelModifier {
     ///
     /// Expression type: `f32`
     accel: ExprHandle,
+    /// Divide the acceleration by the particle mass.
+    #[serde(default)]
+    respect_mass: bool,
 }
 
 impl RadialAccelModifier {
     /// Create a new modifier from an origin expression and an acceleration
     /// expression.
     pub fn new(origin: ExprHandle, accel: ExprHandle) -> Self {
-        Self { origin, accel }
+        Self {
+            origin,
+            accel,
+            respect_mass: false,
+        }
     }
 
     /// Create a new modifier with an acceleration derived from a property.
@@ -131,18 +187,36 @@ impl RadialAccelModifier {
         origin: Vec3,
         property_name: impl Into<String>,
     ) -> Self {
-        Self {
-            origin: module.lit(origin),
-            accel: module.prop(property_name),
-        }
+        Self::new(module.lit(origin), module.prop(property_name))
     }
 
     /// Create a new modifier with a constant radial origin and acceleration.
     pub fn constant(module: &mut Module, origin: Vec3, acceleration: f32) -> Self {
-        Self {
-            origin: module.lit(origin),
-            accel: module.lit(acceleration),
-        }
+        Self::new(module.lit(origin), module.lit(acceleration))
+    }
+
+    /// Set whether the acceleration is divided by the particle mass.
+    ///
+    /// When enabled, the modifier requires the [`Attribute::MASS`] attribute,
+    /// and heavier particles are accelerated less. Defaults to `false`.
+    pub fn with_respect_mass(mut self, respect_mass: bool) -> Self {
+        self.respect_mass = respect_mass;
+        self
+    }
+
+    /// Get the expression of the origin of the radial direction.
+    pub fn origin(&self) -> ExprHandle {
+        self.origin
+    }
+
+    /// Get the expression of the acceleration applied to the particles.
+    pub fn accel(&self) -> ExprHandle {
+        self.accel
+    }
+
+    /// Is the acceleration divided by the particle mass?
+    pub fn respect_mass(&self) -> bool {
+        self.respect_mass
     }
 }
 
@@ -153,7 +227,11 @@ impl Modifier for RadialAccelModifier {
     }
 
     fn attributes(&self) -> &[Attribute] {
-        &[Attribute::POSITION, Attribute::VELOCITY]
+        if self.respect_mass {
+            &[Attribute::POSITION, Attribute::VELOCITY, Attribute::MASS]
+        } else {
+            &[Attribute::POSITION, Attribute::VELOCITY]
+        }
     }
 
     fn boxed_clone(&self) -> BoxedModifier {
@@ -163,6 +241,7 @@ impl Modifier for RadialAccelModifier {
     fn apply(&self, module: &mut Module, context: &mut ShaderWriter) -> Result<(), ExprError> {
         let func_id = calc_func_id(self);
         let func_name = format!("radial_accel_{0:016X}", func_id);
+        let inv_mass = inv_mass_factor(self.respect_mass);
 
         context.make_fn(
             &func_name,
@@ -173,13 +252,14 @@ impl Modifier for RadialAccelModifier {
                 let accel = ctx.eval(m, self.accel)?;
 
                 Ok(format!(
-                    r##"let radial = normalize((*particle).{} - {});
-            (*particle).{} += radial * (({}) * sim_params.delta_time);
+                    r##"let radial = safe_normalize((*particle).{} - {});
+            (*particle).{} += radial * (({}) * sim_params.delta_time{});
         "##,
                     Attribute::POSITION.name(),
                     origin,
                     Attribute::VELOCITY.name(),
                     accel,
+                    inv_mass,
                 ))
             },
         )?;
@@ -206,11 +286,17 @@ impl Modifier for RadialAccelModifier {
 /// from the modifier origin to the particle position. The effect is undefined
 /// if those two directions align.
 ///
+/// If [`with_respect_mass()`] is enabled, the acceleration is divided by the
+/// particle mass, such that heavier particles are accelerated less.
+///
 /// # Attributes
 ///
 /// This modifier requires the following particle attributes:
 /// - [`Attribute::POSITION`]
 /// - [`Attribute::VELOCITY`]
+/// - [`Attribute::MASS`] (only if [`with_respect_mass()`] is enabled)
+///
+/// [`with_respect_mass()`]: crate::modifier::accel::TangentAccelModifier::with_respect_mass
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Reflect, Serialize, Deserialize)]
 pub struct TangentAccelModifier {
     /// The center point the tangent direction is calculated from.
@@ -225,6 +311,9 @@ pub struct TangentAccelModifier {
     ///
     /// Expression type: `f32`
     accel: ExprHandle,
+    /// Divide the acceleration by the particle mass.
+    #[serde(default)]
+    respect_mass: bool,
 }
 
 impl TangentAccelModifier {
@@ -235,6 +324,7 @@ impl TangentAccelModifier {
             origin,
             axis,
             accel,
+            respect_mass: false,
         }
     }
 
@@ -247,20 +337,49 @@ impl TangentAccelModifier {
         axis: Vec3,
         property_name: impl Into<String>,
     ) -> Self {
-        Self {
-            origin: module.lit(origin),
-            axis: module.lit(axis),
-            accel: module.prop(property_name),
-        }
+        Self::new(
+            module.lit(origin),
+            module.lit(axis),
+            module.prop(property_name),
+        )
     }
 
     /// Create a new modifier with constant values.
     pub fn constant(module: &mut Module, origin: Vec3, axis: Vec3, acceleration: f32) -> Self {
-        Self {
-            origin: module.lit(origin),
-            axis: module.lit(axis),
-            accel: module.lit(acceleration),
-        }
+        Self::new(
+            module.lit(origin),
+            module.lit(axis),
+            module.lit(acceleration),
+        )
+    }
+
+    /// Set whether the acceleration is divided by the particle mass.
+    ///
+    /// When enabled, the modifier requires the [`Attribute::MASS`] attribute,
+    /// and heavier particles are accelerated less. Defaults to `false`.
+    pub fn with_respect_mass(mut self, respect_mass: bool) -> Self {
+        self.respect_mass = respect_mass;
+        self
+    }
+
+    /// Get the expression of the origin of the radial direction.
+    pub fn origin(&self) -> ExprHandle {
+        self.origin
+    }
+
+    /// Get the expression of the rotation axis.
+    pub fn axis(&self) -> ExprHandle {
+        self.axis
+    }
+
+    /// Get the expression of the acceleration applied to the particles.
+    pub fn accel(&self) -> ExprHandle {
+        self.accel
+    }
+
+    /// Is the acceleration divided by the particle mass?
+    pub fn respect_mass(&self) -> bool {
+        self.respect_mass
     }
 }
 
@@ -271,7 +390,11 @@ impl Modifier for TangentAccelModifier {
     }
 
     fn attributes(&self) -> &[Attribute] {
-        &[Attribute::POSITION, Attribute::VELOCITY]
+        if self.respect_mass {
+            &[Attribute::POSITION, Attribute::VELOCITY, Attribute::MASS]
+        } else {
+            &[Attribute::POSITION, Attribute::VELOCITY]
+        }
     }
 
     fn boxed_clone(&self) -> BoxedModifier {
@@ -281,6 +404,7 @@ impl Modifier for TangentAccelModifier {
     fn apply(&self, module: &mut Module, context: &mut ShaderWriter) -> Result<(), ExprError> {
         let func_id = calc_func_id(self);
         let func_name = format!("tangent_accel_{0:016X}", func_id);
+        let inv_mass = inv_mass_factor(self.respect_mass);
 
         let origin = context.eval(module, self.origin)?;
         let axis = context.eval(module, self.axis)?;
@@ -288,9 +412,9 @@ impl Modifier for TangentAccelModifier {
 
         context.extra_code += &format!(
             r##"fn {}(particle: ptr<function, Particle>) {{
-    let radial = normalize((*particle).{} - {});
-    let tangent = normalize(cross({}, radial));
-    (*particle).{} += tangent * (({}) * sim_params.delta_time);
+    let radial = safe_normalize((*particle).{} - {});
+    let tangent = safe_normalize(cross({}, radial));
+    (*particle).{} += tangent * (({}) * sim_params.delta_time{});
 }}
 "##,
             func_name,
@@ -299,6 +423,7 @@ impl Modifier for TangentAccelModifier {
             axis,
             Attribute::VELOCITY.name(),
             accel,
+            inv_mass,
         );
 
         context.main_code += &format!("{}(&particle);\n", func_name);
@@ -307,6 +432,18 @@ impl Modifier for TangentAccelModifier {
     }
 }
 
+/// Code dividing an acceleration by the mass of the particle referenced by the
+/// `particle: ptr<function, Particle>` function argument, if `respect_mass` is
+/// `true`. A zero mass cancels the acceleration instead of producing an
+/// infinite or NaN velocity.
+fn inv_mass_factor(respect_mass: bool) -> String {
+    if respect_mass {
+        format!(" * safe_div(1., (*particle).{})", Attribute::MASS.name())
+    } else {
+        String::new()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::{ParticleLayout, Property, PropertyLayout, ToWgslString};
@@ -371,4 +508,48 @@ mod tests {
         // TODO: less weak check...
         assert!(context.extra_code.contains(&accel.to_wgsl_string()));
     }
+
+    #[test]
+    fn mod_accel_mass() {
+        let mut module = Module::default();
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+
+        let modifier = AccelModifier::constant(&mut module, Vec3::Y);
+        assert!(!modifier.attributes().contains(&Attribute::MASS));
+        let modifier = modifier.with_respect_mass(true);
+        assert!(modifier.attributes().contains(&Attribute::MASS));
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        assert!(modifier.apply(&mut module, &mut context).is_ok());
+        assert!(context
+            .main_code
+            .contains("safe_div(sim_params.delta_time, particle.mass)"));
+
+        let modifier = RadialAccelModifier::constant(&mut module, Vec3::ZERO, 3.);
+        assert!(!modifier.attributes().contains(&Attribute::MASS));
+        let modifier = modifier.with_respect_mass(true);
+        assert!(modifier.attributes().contains(&Attribute::MASS));
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        assert!(modifier.apply(&mut module, &mut context).is_ok());
+        assert!(context
+            .extra_code
+            .contains("* safe_div(1., (*particle).mass)"));
+
+        let modifier = TangentAccelModifier::constant(&mut module, Vec3::ZERO, Vec3::Y, 3.);
+        assert!(!modifier.attributes().contains(&Attribute::MASS));
+        let modifier = modifier.with_respect_mass(true);
+        assert!(modifier.attributes().contains(&Attribute::MASS));
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        assert!(modifier.apply(&mut module, &mut context).is_ok());
+        assert!(context
+            .extra_code
+            .contains("* safe_div(1., (*particle).mass)"));
+
+        // Previously serialized modifiers default to ignoring the mass
+        let modifier: AccelModifier = ron::from_str("(accel:1)").unwrap();
+        assert!(!modifier.attributes().contains(&Attribute::MASS));
+    }
 }
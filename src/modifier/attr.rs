@@ -112,3 +112,191 @@ impl Modifier for SetAttributeModifier {
         Ok(())
     }
 }
+
+/// Color space in which [`SetColorJitterModifier`] randomizes a color.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Reflect, Serialize, Deserialize)]
+pub enum ColorJitterMode {
+    /// Jitter each of the red, green, blue, and alpha channels independently.
+    ///
+    /// This is the cheapest mode, but jittering the RGB channels tends to
+    /// change the brightness and desaturate the color as much as it changes
+    /// its hue.
+    #[default]
+    Rgb,
+    /// Jitter the hue, saturation, value, and alpha of the color.
+    ///
+    /// The color is converted to HSV, jittered, and converted back to RGB.
+    /// The hue jitter is expressed as a fraction of a full turn of the color
+    /// wheel, and wraps around, so a hue variance of `0.05` varies the hue by
+    /// up to 18 degrees in either direction.
+    Hsv,
+}
+
+/// A modifier to initialize the color of particles with a randomly jittered
+/// base color.
+///
+/// This modifier assigns to the [`Attribute::COLOR`] of each newly spawned
+/// particle a base color, randomly offset on each channel by a value
+/// uniformly distributed in `[-variance:variance]`. This gives an emission a
+/// natural color variety, like embers ranging from orange to yellow, without
+/// building the random offsets and color conversions from raw expressions.
+///
+/// The channels jittered depend on the [`ColorJitterMode`]:
+/// - [`ColorJitterMode::Rgb`] jitters the `(r, g, b, a)` channels;
+/// - [`ColorJitterMode::Hsv`] jitters the `(h, s, v, a)` channels of the
+///   color converted to HSV.
+///
+/// The resulting channels are clamped to `[0:1]`, except the hue which wraps
+/// around.
+///
+/// # Example
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_hanabi::*;
+/// let writer = ExprWriter::new();
+/// // Orange embers, varying up to yellow or red
+/// let color = writer.lit(Vec4::new(1., 0.5, 0., 1.)).expr();
+/// let variance = writer.lit(Vec4::new(0.04, 0.1, 0.2, 0.)).expr();
+/// let modifier = SetColorJitterModifier::new(color, variance, ColorJitterMode::Hsv);
+/// ```
+///
+/// # Attributes
+///
+/// This modifier requires the following particle attributes:
+/// - [`Attribute::COLOR`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Reflect, Serialize, Deserialize)]
+pub struct SetColorJitterModifier {
+    /// The base color, in linear RGBA.
+    ///
+    /// Expression type: `Vec4`
+    pub color: ExprHandle,
+    /// The maximum offset applied to each channel, in either direction.
+    ///
+    /// The channels are `(r, g, b, a)` or `(h, s, v, a)` depending on the
+    /// [`mode`].
+    ///
+    /// Expression type: `Vec4`
+    ///
+    /// [`mode`]: crate::SetColorJitterModifier::mode
+    pub variance: ExprHandle,
+    /// The color space in which the color is jittered.
+    pub mode: ColorJitterMode,
+}
+
+impl SetColorJitterModifier {
+    /// Create a new modifier jittering a base color.
+    pub fn new(color: ExprHandle, variance: ExprHandle, mode: ColorJitterMode) -> Self {
+        Self {
+            color,
+            variance,
+            mode,
+        }
+    }
+}
+
+/// Emit the WGSL helper functions converting a color between the RGB and HSV
+/// color spaces, and return their names.
+fn make_hsv_fns(context: &mut dyn EvalContext) -> (&'static str, &'static str) {
+    context.make_helper_fn(
+        "rgb_to_hsv",
+        r##"fn rgb_to_hsv(c: vec3<f32>) -> vec3<f32> {
+    let k = vec4<f32>(0.0, -1.0 / 3.0, 2.0 / 3.0, -1.0);
+    let p = mix(vec4<f32>(c.bg, k.wz), vec4<f32>(c.gb, k.xy), step(c.b, c.g));
+    let q = mix(vec4<f32>(p.xyw, c.r), vec4<f32>(c.r, p.yzx), step(p.x, c.r));
+    let d = q.x - min(q.w, q.y);
+    let e = 1.0e-10;
+    return vec3<f32>(abs(q.z + (q.w - q.y) / (6.0 * d + e)), d / (q.x + e), q.x);
+}
+"##,
+    );
+    context.make_helper_fn(
+        "hsv_to_rgb",
+        r##"fn hsv_to_rgb(c: vec3<f32>) -> vec3<f32> {
+    let k = vec4<f32>(1.0, 2.0 / 3.0, 1.0 / 3.0, 3.0);
+    let p = abs(fract(c.xxx + k.xyz) * 6.0 - k.www);
+    return c.z * mix(k.xxx, clamp(p - k.xxx, vec3<f32>(0.0), vec3<f32>(1.0)), c.y);
+}
+"##,
+    );
+    ("rgb_to_hsv", "hsv_to_rgb")
+}
+
+#[typetag::serde]
+impl Modifier for SetColorJitterModifier {
+    fn context(&self) -> ModifierContext {
+        ModifierContext::Init
+    }
+
+    fn attributes(&self) -> &[Attribute] {
+        &[Attribute::COLOR]
+    }
+
+    fn boxed_clone(&self) -> BoxedModifier {
+        Box::new(*self)
+    }
+
+    fn apply(&self, module: &mut Module, context: &mut ShaderWriter) -> Result<(), ExprError> {
+        let attr = module.attr(Attribute::COLOR);
+        let attr = context.eval(module, attr)?;
+        let color = context.eval(module, self.color)?;
+        let variance = context.eval(module, self.variance)?;
+
+        let (hsv, jitter) = match self.mode {
+            ColorJitterMode::Rgb => (String::new(), "saturate(c + j)".to_string()),
+            ColorJitterMode::Hsv => {
+                let (rgb_to_hsv, hsv_to_rgb) = make_hsv_fns(context);
+                (
+                    format!("    let hsv = {rgb_to_hsv}(saturate(c.rgb));\n"),
+                    format!("vec4<f32>({hsv_to_rgb}(vec3<f32>(fract(hsv.x + j.x), saturate(hsv.yz + j.yz))), saturate(c.a + j.w))"),
+                )
+            }
+        };
+        let code = format!(
+            r##"{{
+    let c = {color};
+    let j = (frand4() * 2.0 - 1.0) * ({variance});
+{hsv}    {attr} = pack4x8unorm({jitter});
+}}
+"##
+        );
+        context.main_code += &code;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ParticleLayout, PropertyLayout};
+
+    #[test]
+    fn mod_color_jitter() {
+        let mut module = Module::default();
+        let color = module.lit(Vec4::new(1., 0.5, 0., 1.));
+        let variance = module.lit(Vec4::splat(0.1));
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+
+        let modifier = SetColorJitterModifier::new(color, variance, ColorJitterMode::Rgb);
+        let mut context =
+            ShaderWriter::new(ModifierContext::Init, &property_layout, &particle_layout);
+        assert!(modifier.apply(&mut module, &mut context).is_ok());
+        assert!(context.main_code.contains("frand4()"));
+        assert!(context.main_code.contains("pack4x8unorm(saturate(c + j))"));
+        assert!(!context.extra_code.contains("fn rgb_to_hsv"));
+
+        let modifier = SetColorJitterModifier::new(color, variance, ColorJitterMode::Hsv);
+        let mut context =
+            ShaderWriter::new(ModifierContext::Init, &property_layout, &particle_layout);
+        assert!(modifier.apply(&mut module, &mut context).is_ok());
+        assert!(context.main_code.contains("let hsv = rgb_to_hsv("));
+        assert!(context.main_code.contains("hsv_to_rgb("));
+        assert!(context.extra_code.contains("fn rgb_to_hsv("));
+        assert!(context.extra_code.contains("fn hsv_to_rgb("));
+
+        // Helper functions are emitted once
+        assert!(modifier.apply(&mut module, &mut context).is_ok());
+        assert_eq!(context.extra_code.matches("fn rgb_to_hsv(").count(), 1);
+    }
+}
@@ -1,4 +1,4 @@
-//! Modifiers to duplicate particles.
+//! Modifiers to duplicate particles, or move them between groups.
 
 use std::hash::{Hash, Hasher};
 
@@ -6,10 +6,40 @@ use bevy::{prelude::*, utils::FloatOrd};
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    calc_func_id, Attribute, BoxedModifier, EvalContext, ExprError, Modifier, ModifierContext,
-    Module, ShaderWriter,
+    calc_func_id, Attribute, BoxedModifier, EvalContext, ExprError, ExprHandle, Modifier,
+    ModifierContext, Module, ShaderWriter, ToWgslString,
 };
 
+/// Policy applied when a particle is added to a group which has no room left.
+///
+/// Each group has a fixed capacity. When a [`CloneModifier`] attempts to add a
+/// particle to a full group, this policy determines whether the new particle is
+/// discarded, or replaces an existing particle of that group.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Reflect, Serialize, Deserialize)]
+pub enum RecyclePolicy {
+    /// Silently drop the new particle.
+    ///
+    /// This is the cheapest option, but visually leaves gaps, for example in
+    /// trails, when the group is under pressure.
+    #[default]
+    Drop,
+    /// Overwrite the oldest alive particle of the group.
+    ///
+    /// The oldest particles are approximated as the ones at the front of the
+    /// list of particles alive at the beginning of the frame. This keeps
+    /// trails continuous under pressure, at the expense of shortening them.
+    ///
+    /// Groups are updated in order, so particles can only be overwritten in
+    /// a group of higher index than the one of the source particle. For other
+    /// groups, this behaves like [`RecyclePolicy::Drop`].
+    OverwriteOldest,
+    /// Overwrite a random alive particle of the group.
+    ///
+    /// Like for [`RecyclePolicy::OverwriteOldest`], this only applies to a
+    /// group of higher index than the one of the source particle.
+    OverwriteRandom,
+}
+
 /// Duplicates a particle and places it in a group.
 ///
 /// Spawners always spawn particles into group 0, so this is the primary way to
@@ -18,6 +48,28 @@ use crate::{
 ///
 /// All attributes are copied to the new particle, with the exception of
 /// [`Attribute::AGE`], which is reset to zero.
+///
+/// If the destination group is full, the [`RecyclePolicy`] determines what
+/// happens to the new particle. By default it's dropped.
+///
+/// By default particles are duplicated during their entire lifetime. Use
+/// [`with_emit_age_range()`] to restrict duplication to a fraction of the
+/// lifetime of the source particle, for example to produce a trail which
+/// stops before the source particle fades out.
+///
+/// By default the color of the new particle is copied verbatim from the source
+/// particle. Use [`with_color_tint()`] to modulate it instead, for example to
+/// darken or fade out trail particles compared to the head particles emitting
+/// them.
+///
+/// By default particles are duplicated whether they move or not, so trails
+/// pile up behind stationary particles. Use [`with_min_distance()`] to only
+/// duplicate a particle once it moved far enough from the position where it
+/// was last duplicated, producing distance-based trails.
+///
+/// [`with_emit_age_range()`]: CloneModifier::with_emit_age_range
+/// [`with_color_tint()`]: CloneModifier::with_color_tint
+/// [`with_min_distance()`]: CloneModifier::with_min_distance
 #[derive(Debug, Clone, Copy, PartialEq, Reflect, Serialize, Deserialize)]
 pub struct CloneModifier {
     /// How many seconds must elapse before the particle will be duplicated.
@@ -26,6 +78,46 @@ pub struct CloneModifier {
     pub spawn_period: f32,
     /// The group that the new particle will be spawned into.
     pub destination_group: u32,
+    /// The policy applied when the destination group is full.
+    #[serde(default)]
+    pub recycle_policy: RecyclePolicy,
+    /// Optional range of age fraction of the source particle, in `[0:1]`,
+    /// during which the particle is duplicated.
+    ///
+    /// The age fraction is the ratio of [`Attribute::AGE`] over
+    /// [`Attribute::LIFETIME`], so both attributes are required when this is
+    /// set. The range includes its start but not its end. If `None`, the
+    /// particle is duplicated during its entire lifetime.
+    #[serde(default)]
+    pub emit_age_range: Option<(f32, f32)>,
+    /// Optional tint multiplied component-wise with the color of the source
+    /// particle to produce the color of the new particle.
+    ///
+    /// The expression is evaluated for the source particle, so can depend on
+    /// its attributes. It applies to [`Attribute::COLOR`] and
+    /// [`Attribute::HDR_COLOR`], whichever are present in the particle layout.
+    /// If the layout contains neither, the tint is ignored. If `None`, the
+    /// color is copied as is.
+    ///
+    /// Expression type: `Vec4`
+    #[serde(default)]
+    pub color_tint: Option<ExprHandle>,
+    /// Optional minimum distance the source particle must have moved since it
+    /// was last duplicated before being duplicated again.
+    ///
+    /// The distance is measured between [`Attribute::POSITION`] and
+    /// [`Attribute::LAST_EMIT_POSITION`], so both attributes are required when
+    /// this is set. The latter is initialized to the spawn position of the
+    /// particle, and updated to its current position each time it's
+    /// duplicated. If a [`spawn_period`] is also set, the particle is only
+    /// duplicated when the period elapsed and it moved far enough. If `None`,
+    /// the particle is duplicated irrespective of its motion.
+    ///
+    /// Expression type: `f32`
+    ///
+    /// [`spawn_period`]: CloneModifier::spawn_period
+    #[serde(default)]
+    pub min_distance: Option<ExprHandle>,
 }
 
 #[typetag::serde]
@@ -35,7 +127,17 @@ impl Modifier for CloneModifier {
     }
 
     fn attributes(&self) -> &[Attribute] {
-        &[]
+        match (self.emit_age_range.is_some(), self.min_distance.is_some()) {
+            (false, false) => &[],
+            (true, false) => &[Attribute::AGE, Attribute::LIFETIME],
+            (false, true) => &[Attribute::POSITION, Attribute::LAST_EMIT_POSITION],
+            (true, true) => &[
+                Attribute::AGE,
+                Attribute::LIFETIME,
+                Attribute::POSITION,
+                Attribute::LAST_EMIT_POSITION,
+            ],
+        }
     }
 
     fn boxed_clone(&self) -> BoxedModifier {
@@ -43,6 +145,15 @@ impl Modifier for CloneModifier {
     }
 
     fn apply(&self, module: &mut Module, context: &mut ShaderWriter) -> Result<(), ExprError> {
+        if let Some((start, end)) = self.emit_age_range {
+            if !Self::is_valid_age_range(start, end) {
+                return Err(ExprError::GraphEvalError(format!(
+                    "Invalid CloneModifier emit age range [{}:{}[.",
+                    start, end
+                )));
+            }
+        }
+
         let func_id = calc_func_id(self);
         let func_name = format!("duplicate_{0:016X}", func_id);
         let multiple_count_name = format!("multiple_count_{0:016X}", func_id);
@@ -51,61 +162,201 @@ impl Modifier for CloneModifier {
             &func_name,
             "particle: ptr<function, Particle>",
             module,
-            &mut |_m: &mut Module, context: &mut dyn EvalContext| -> Result<String, ExprError> {
-                let age_reset_code = if context.particle_layout().contains(Attribute::AGE) {
+            &mut |m: &mut Module, context: &mut dyn EvalContext| -> Result<String, ExprError> {
+                // Code fixing up the attributes of the new particle after it's copied
+                let mut copy_fixup_code = if context.particle_layout().contains(Attribute::AGE) {
                     format!("particle_buffer.particles[index].{} = 0.0;", Attribute::AGE.name())
                 } else {
                     "".to_owned()
                 };
 
+                // Tint the color of the new particle, if any
+                if let Some(color_tint) = self.color_tint {
+                    let has_color = context.particle_layout().contains(Attribute::COLOR);
+                    let has_hdr_color = context.particle_layout().contains(Attribute::HDR_COLOR);
+                    if has_color || has_hdr_color {
+                        let tint = context.eval(m, color_tint)?;
+                        let tint_var = context.make_local_var();
+                        context.push_stmt(&format!("let {} = vec4<f32>({});", tint_var, tint));
+                        if has_color {
+                            copy_fixup_code += &format!(
+                                "\nparticle_buffer.particles[index].{0} = pack4x8unorm(unpack4x8unorm(particle_buffer.particles[index].{0}) * {1});",
+                                Attribute::COLOR.name(),
+                                tint_var
+                            );
+                        }
+                        if has_hdr_color {
+                            copy_fixup_code += &format!(
+                                "\nparticle_buffer.particles[index].{0} *= {1};",
+                                Attribute::HDR_COLOR.name(),
+                                tint_var
+                            );
+                        }
+                    }
+                }
+
+                // Groups are updated in order, and each update pass moves the alive
+                // particles of its group from pong to ping. Only overwrite particles of
+                // a group which didn't run its update pass yet this frame; those of
+                // other groups may already be dead or moved, so overwriting them would
+                // orphan particles or count them twice.
+                let overwrite_guard = format!(
+                    "if ({}u <= group_index) {{ return; }}",
+                    self.destination_group
+                );
+                let full_code = match self.recycle_policy {
+                    RecyclePolicy::Drop => "// Drop the new particle.".to_owned(),
+                    RecyclePolicy::OverwriteOldest => format!(
+                        r##"// Overwrite the oldest particle alive. Particles are appended to the
+                        // list of alive particles as they spawn, so the front of that list
+                        // holds the oldest ones.
+                        {guard}
+                        let recycle_index = atomicAdd(&render_group_indirect[{dest}u].recycle_count, 1u);
+                        if (recycle_index < render_group_indirect[{dest}u].max_update) {{
+                            let pong = 1u - render_effect_indirect.ping;
+                            let index = indirect_buffer.indices[3u * (base_index + recycle_index) + pong];
+                            particle_buffer.particles[index] = *particle;
+                            {copy_fixup_code}
+                        }}"##,
+                        dest = self.destination_group,
+                        guard = overwrite_guard,
+                    ),
+                    RecyclePolicy::OverwriteRandom => format!(
+                        r##"// Overwrite a random particle alive.
+                        {guard}
+                        let max_update = render_group_indirect[{dest}u].max_update;
+                        if (max_update > 0u) {{
+                            let recycle_index = min(u32(frand() * f32(max_update)), max_update - 1u);
+                            let pong = 1u - render_effect_indirect.ping;
+                            let index = indirect_buffer.indices[3u * (base_index + recycle_index) + pong];
+                            particle_buffer.particles[index] = *particle;
+                            {copy_fixup_code}
+                        }}"##,
+                        dest = self.destination_group,
+                        guard = overwrite_guard,
+                    ),
+                };
+
                 Ok(format!(
                     r##"
                     let base_index = particle_groups[{dest}u].indirect_index;
 
+                    // Reserve a dead particle in the destination group, if there's room
+                    // left. Never decrement the dead count below zero, so that other
+                    // threads don't observe a wrapped-around count.
+                    var dead_count = atomicLoad(&render_group_indirect[{dest}u].dead_count);
+                    loop {{
+                        if (dead_count == 0u) {{
+                            {full_code}
+                            return;
+                        }}
+                        let res = atomicCompareExchangeWeak(&render_group_indirect[{dest}u].dead_count, dead_count, dead_count - 1u);
+                        if (res.exchanged) {{
+                            break;
+                        }}
+                        dead_count = res.old_value;
+                    }}
+
                     // Recycle a dead particle.
-                    let dead_index = atomicSub(&render_group_indirect[{dest}u].dead_count, 1u) - 1u;
-                    let index = indirect_buffer.indices[3u * (base_index + dead_index) + 2u];
+                    let index = indirect_buffer.indices[3u * (base_index + dead_count - 1u) + 2u];
 
                     // Copy particle in.
                     particle_buffer.particles[index] = *particle;
-                    {age_reset_code}
+                    {copy_fixup_code}
 
                     // Mark as alive.
                     atomicAdd(&render_group_indirect[{dest}u].alive_count, 1u);
 
-                    // Add instance.
+                    // Add instance. Like particles spawned by the init pass, this one is
+                    // the newest of its group.
                     let ping = render_effect_indirect.ping;
                     let indirect_index = atomicAdd(&render_group_indirect[{dest}u].instance_count, 1u);
                     indirect_buffer.indices[3u * (base_index + indirect_index) + ping] = index;
+                    atomicMin(&render_group_indirect[{dest}u].first_spawn_index, indirect_index);
                 "##,
                     dest = self.destination_group,
                 ))
             },
         )?;
 
+        if let Some((start, end)) = self.emit_age_range {
+            context.main_code += &format!(
+                "if ((particle.{age} >= {start} * particle.{lifetime}) && (particle.{age} < {end} * particle.{lifetime})) {{\n",
+                age = Attribute::AGE.name(),
+                lifetime = Attribute::LIFETIME.name(),
+                start = start.to_wgsl_string(),
+                end = end.to_wgsl_string(),
+            );
+        }
+
+        // Only duplicate the particle once it moved far enough, and record the
+        // position it's duplicated at. This is written before the copy, so the new
+        // particle starts with an up-to-date value too.
+        let distance_check = if let Some(min_distance) = self.min_distance {
+            let min_distance = context.eval(module, min_distance)?;
+            Some((
+                format!(
+                    "distance(particle.{0}, particle.{1}) >= ({2})",
+                    Attribute::POSITION.name(),
+                    Attribute::LAST_EMIT_POSITION.name(),
+                    min_distance
+                ),
+                format!(
+                    "particle.{0} = particle.{1};\n",
+                    Attribute::LAST_EMIT_POSITION.name(),
+                    Attribute::POSITION.name()
+                ),
+            ))
+        } else {
+            None
+        };
+
         if self.spawn_period <= 0.0 {
-            context.main_code += &format!("{func}(&particle);", func = func_name);
+            if let Some((condition, update)) = &distance_check {
+                context.main_code += &format!(
+                    "if ({cond}) {{\n{update}{func}(&particle);\n}}\n",
+                    cond = condition,
+                    update = update,
+                    func = func_name
+                );
+            } else {
+                context.main_code += &format!("{func}(&particle);", func = func_name);
+            }
         } else {
             // Calculate the number of multiples of `spawn_period` that fall
             // between the last tick and this one, and spawn one particle for
             // each such multiple.
             //
             // https://stackoverflow.com/a/31871205
+            let (condition, update) = if let Some((condition, update)) = &distance_check {
+                (format!(" && ({})", condition), update.as_str())
+            } else {
+                (String::new(), "")
+            };
             context.main_code += &format!(
                 r##"
                 let {multiple_count} = max(0, i32(floor({b} / {m})) - i32(ceil(({b} - {delta}) / {m})) + 1);
-                for (var i = 0; i < {multiple_count}; i += 1) {{
-                    {func}(&particle);
+                if ({multiple_count} > 0{cond}) {{
+                    {update}
+                    for (var i = 0; i < {multiple_count}; i += 1) {{
+                        {func}(&particle);
+                    }}
                 }}
             "##,
                 func = func_name,
                 multiple_count = multiple_count_name,
                 b = "sim_params.time",
                 delta = "sim_params.delta_time",
-                m = self.spawn_period
+                m = self.spawn_period,
+                cond = condition,
+                update = update,
             );
         }
 
+        if self.emit_age_range.is_some() {
+            context.main_code += "\n}\n";
+        }
+
         Ok(())
     }
 }
@@ -117,8 +368,70 @@ impl CloneModifier {
         CloneModifier {
             spawn_period,
             destination_group,
+            recycle_policy: RecyclePolicy::default(),
+            emit_age_range: None,
+            color_tint: None,
+            min_distance: None,
         }
     }
+
+    /// Only duplicate particles once they moved at least `min_distance` since
+    /// they were last duplicated.
+    ///
+    /// This produces distance-based trails, which don't pile up when the source
+    /// particle is stationary, and are evenly spaced independently of its
+    /// speed. See [`min_distance`] for details.
+    ///
+    /// [`min_distance`]: CloneModifier::min_distance
+    pub fn with_min_distance(mut self, min_distance: ExprHandle) -> Self {
+        self.min_distance = Some(min_distance);
+        self
+    }
+
+    /// Tint the color of the new particles.
+    ///
+    /// The `color_tint` expression is multiplied component-wise with the color
+    /// of the source particle. For example a tint of `vec4(0.5, 0.5, 0.5, 1.0)`
+    /// darkens the trail particles, while a tint of `vec4(1.0, 1.0, 1.0, 0.5)`
+    /// makes them half transparent. See [`color_tint`] for details.
+    ///
+    /// [`color_tint`]: CloneModifier::color_tint
+    pub fn with_color_tint(mut self, color_tint: ExprHandle) -> Self {
+        self.color_tint = Some(color_tint);
+        self
+    }
+
+    /// Restrict duplication to the given range of age fraction of the source
+    /// particle.
+    ///
+    /// The particle is only duplicated while its age fraction, which is the
+    /// ratio of its age over its lifetime, is in `[start:end[`. For example, a
+    /// range of `(0.0, 0.5)` only duplicates particles during the first half of
+    /// their lifetime.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `start` is negative, or if `end` is less than `start`.
+    pub fn with_emit_age_range(mut self, start: f32, end: f32) -> Self {
+        assert!(
+            Self::is_valid_age_range(start, end),
+            "Invalid emit age range [{}:{}[.",
+            start,
+            end
+        );
+        self.emit_age_range = Some((start, end));
+        self
+    }
+
+    fn is_valid_age_range(start: f32, end: f32) -> bool {
+        start.is_finite() && end.is_finite() && start >= 0.0 && start <= end
+    }
+
+    /// Set the policy applied when the destination group is full.
+    pub fn with_recycle_policy(mut self, recycle_policy: RecyclePolicy) -> Self {
+        self.recycle_policy = recycle_policy;
+        self
+    }
 }
 
 impl Eq for CloneModifier {}
@@ -130,5 +443,385 @@ impl Hash for CloneModifier {
     {
         FloatOrd(self.spawn_period).hash(state);
         self.destination_group.hash(state);
+        self.recycle_policy.hash(state);
+        self.emit_age_range
+            .map(|(start, end)| (FloatOrd(start), FloatOrd(end)))
+            .hash(state);
+        self.color_tint.hash(state);
+        self.min_distance.hash(state);
+    }
+}
+
+/// Moves a particle from its current group into another group when a
+/// condition holds.
+///
+/// Unlike [`CloneModifier`], which leaves the source particle untouched, this
+/// modifier kills the source particle after copying it into the destination
+/// group. This enables state-machine-like particle lifecycles, where a particle
+/// changes behavior and rendering over time, for example a rising ember which
+/// becomes smoke once it cooled down.
+///
+/// All attributes are copied to the new particle, with the exception of
+/// [`Attribute::AGE`], which is reset to zero, like for [`CloneModifier`].
+///
+/// The `condition` is evaluated each frame for each alive particle of the
+/// group(s) the modifier applies to. If the destination group has no room
+/// left for a new particle, the particle stays in its current group, and the
+/// transfer is attempted again on next frame if the condition still holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Reflect, Serialize, Deserialize)]
+pub struct TransferModifier {
+    /// The condition under which the particle is transferred.
+    ///
+    /// Expression type: `bool`
+    pub condition: ExprHandle,
+    /// The group that the particle will be moved into.
+    pub destination_group: u32,
+}
+
+impl TransferModifier {
+    /// Creates a new [`TransferModifier`] that will move particles meeting
+    /// `condition` into the `destination_group`.
+    pub fn new(condition: ExprHandle, destination_group: u32) -> TransferModifier {
+        TransferModifier {
+            condition,
+            destination_group,
+        }
+    }
+}
+
+#[typetag::serde]
+impl Modifier for TransferModifier {
+    fn context(&self) -> ModifierContext {
+        ModifierContext::Update
+    }
+
+    fn attributes(&self) -> &[Attribute] {
+        &[]
+    }
+
+    fn boxed_clone(&self) -> BoxedModifier {
+        Box::new(*self)
+    }
+
+    fn apply(&self, module: &mut Module, context: &mut ShaderWriter) -> Result<(), ExprError> {
+        let func_id = calc_func_id(self);
+        let func_name = format!("transfer_{0:016X}", func_id);
+
+        context.make_fn(
+            &func_name,
+            "particle: ptr<function, Particle>, is_alive: ptr<function, bool>",
+            module,
+            &mut |_m: &mut Module, context: &mut dyn EvalContext| -> Result<String, ExprError> {
+                let age_reset_code = if context.particle_layout().contains(Attribute::AGE) {
+                    format!("particle_buffer.particles[index].{} = 0.0;", Attribute::AGE.name())
+                } else {
+                    "".to_owned()
+                };
+
+                // The init pass of next frame caps the number of particles spawned into
+                // group #0 with max_spawn, so keep it in sync with the dead count.
+                let max_spawn_code = if self.destination_group == 0 {
+                    "atomicSub(&render_effect_indirect.max_spawn, 1u);"
+                } else {
+                    ""
+                };
+
+                Ok(format!(
+                    r##"
+                    // Reserve a dead particle in the destination group, if there's room
+                    // left. Never decrement the dead count below zero, so that other
+                    // threads don't observe a wrapped-around count.
+                    var dead_count = atomicLoad(&render_group_indirect[{dest}u].dead_count);
+                    loop {{
+                        if (dead_count == 0u) {{
+                            return;
+                        }}
+                        let res = atomicCompareExchangeWeak(&render_group_indirect[{dest}u].dead_count, dead_count, dead_count - 1u);
+                        if (res.exchanged) {{
+                            break;
+                        }}
+                        dead_count = res.old_value;
+                    }}
+                    {max_spawn_code}
+
+                    let base_index = particle_groups[{dest}u].effect_particle_offset + particle_groups[{dest}u].indirect_index;
+
+                    // Recycle a dead particle.
+                    let index = indirect_buffer.indices[3u * (base_index + dead_count - 1u) + 2u];
+
+                    // Copy particle in.
+                    particle_buffer.particles[index] = *particle;
+                    {age_reset_code}
+
+                    // Mark as alive.
+                    atomicAdd(&render_group_indirect[{dest}u].alive_count, 1u);
+
+                    // Add instance. Like particles spawned by the init pass, this one is
+                    // the newest of its group.
+                    let ping = render_effect_indirect.ping;
+                    let indirect_index = atomicAdd(&render_group_indirect[{dest}u].instance_count, 1u);
+                    indirect_buffer.indices[3u * (base_index + indirect_index) + ping] = index;
+                    atomicMin(&render_group_indirect[{dest}u].first_spawn_index, indirect_index);
+
+                    // Kill the source particle, which frees its slot in the source group.
+                    *is_alive = false;
+                "##,
+                    dest = self.destination_group,
+                ))
+            },
+        )?;
+
+        let condition = context.eval(module, self.condition)?;
+        context.main_code += &format!(
+            r##"if (is_alive && ({cond})) {{
+    {func}(&particle, &is_alive);
+}}
+"##,
+            cond = condition,
+            func = func_name,
+        );
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ExprWriter, ParticleLayout, PropertyLayout};
+
+    #[test]
+    fn mod_clone_recycle_policy() {
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::new().append(Attribute::AGE).build();
+
+        for policy in [
+            RecyclePolicy::Drop,
+            RecyclePolicy::OverwriteOldest,
+            RecyclePolicy::OverwriteRandom,
+        ] {
+            let modifier = CloneModifier::new(0.0, 2).with_recycle_policy(policy);
+            assert_eq!(modifier.recycle_policy, policy);
+
+            let mut module = Module::default();
+            let mut context =
+                ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+            assert!(modifier.apply(&mut module, &mut context).is_ok());
+
+            // All policies check for room left in the destination group
+            let code = &context.extra_code;
+            assert!(code.contains("if (dead_count == 0u) {"));
+            assert!(code.contains(
+                "atomicCompareExchangeWeak(&render_group_indirect[2u].dead_count, dead_count, dead_count - 1u)"
+            ));
+            assert!(!code.contains("atomicSub"));
+            assert!(code.contains(
+                "atomicMin(&render_group_indirect[2u].first_spawn_index, indirect_index);"
+            ));
+
+            let overwrites = code.contains("1u - render_effect_indirect.ping");
+            match policy {
+                RecyclePolicy::Drop => {
+                    assert!(!overwrites);
+                    assert!(!code.contains("recycle_index"));
+                }
+                RecyclePolicy::OverwriteOldest => {
+                    assert!(overwrites);
+                    assert!(code.contains("if (2u <= group_index) { return; }"));
+                    assert!(
+                        code.contains("atomicAdd(&render_group_indirect[2u].recycle_count, 1u)")
+                    );
+                    assert!(!code.contains("frand()"));
+                }
+                RecyclePolicy::OverwriteRandom => {
+                    assert!(overwrites);
+                    assert!(code.contains("if (2u <= group_index) { return; }"));
+                    assert!(code.contains("frand()"));
+                    assert!(!code.contains("recycle_count"));
+                }
+            }
+        }
+
+        // Different policies generate different functions
+        let a = CloneModifier::new(0.0, 1);
+        let b = a.with_recycle_policy(RecyclePolicy::OverwriteOldest);
+        assert_ne!(calc_func_id(&a), calc_func_id(&b));
+    }
+
+    #[test]
+    fn mod_clone_emit_age_range() {
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::new()
+            .append(Attribute::AGE)
+            .append(Attribute::LIFETIME)
+            .build();
+
+        // Default is to duplicate during the entire lifetime
+        let modifier = CloneModifier::new(0.0, 1);
+        assert!(modifier.emit_age_range.is_none());
+        assert!(modifier.attributes().is_empty());
+        let mut module = Module::default();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        assert!(modifier.apply(&mut module, &mut context).is_ok());
+        assert!(!context.main_code.contains("particle.lifetime"));
+
+        let modifier = CloneModifier::new(0.0, 1).with_emit_age_range(0.0, 0.5);
+        assert_eq!(modifier.emit_age_range, Some((0.0, 0.5)));
+        assert!(modifier.attributes().contains(&Attribute::AGE));
+        assert!(modifier.attributes().contains(&Attribute::LIFETIME));
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        assert!(modifier.apply(&mut module, &mut context).is_ok());
+        assert!(context.main_code.contains(
+            "if ((particle.age >= 0. * particle.lifetime) && (particle.age < 0.5 * particle.lifetime)) {"
+        ));
+
+        // Invalid range, for example after deserializing
+        let mut modifier = CloneModifier::new(0.0, 1);
+        modifier.emit_age_range = Some((0.5, 0.2));
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        assert!(matches!(
+            modifier.apply(&mut module, &mut context),
+            Err(ExprError::GraphEvalError(_))
+        ));
+    }
+
+    #[test]
+    fn mod_clone_color_tint() {
+        let writer = ExprWriter::new();
+        let tint = writer.lit(Vec4::new(0.5, 0.5, 0.5, 1.)).expr();
+        let mut module = writer.finish();
+        let modifier = CloneModifier::new(0.0, 1).with_color_tint(tint);
+        assert_eq!(modifier.color_tint, Some(tint));
+        assert_ne!(
+            calc_func_id(&modifier),
+            calc_func_id(&CloneModifier::new(0.0, 1))
+        );
+        let property_layout = PropertyLayout::default();
+
+        // LDR color
+        let particle_layout = ParticleLayout::new().append(Attribute::COLOR).build();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        assert!(modifier.apply(&mut module, &mut context).is_ok());
+        let code = &context.extra_code;
+        assert!(code.contains("= vec4<f32>(vec4<f32>(0.5,0.5,0.5,1.));"));
+        assert!(code.contains(
+            "pack4x8unorm(unpack4x8unorm(particle_buffer.particles[index].color) * var0)"
+        ));
+        assert!(!code.contains("hdr_color"));
+
+        // HDR color
+        let particle_layout = ParticleLayout::new().append(Attribute::HDR_COLOR).build();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        assert!(modifier.apply(&mut module, &mut context).is_ok());
+        let code = &context.extra_code;
+        assert!(code.contains("particle_buffer.particles[index].hdr_color *= var0;"));
+        assert!(!code.contains("pack4x8unorm"));
+
+        // No color; the tint is ignored
+        let particle_layout = ParticleLayout::new().append(Attribute::AGE).build();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        assert!(modifier.apply(&mut module, &mut context).is_ok());
+        assert!(!context.extra_code.contains("vec4<f32>(0.5,0.5,0.5,1.)"));
+    }
+
+    #[test]
+    fn mod_clone_min_distance() {
+        let writer = ExprWriter::new();
+        let min_distance = writer.lit(0.25).expr();
+        let mut module = writer.finish();
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::new()
+            .append(Attribute::POSITION)
+            .append(Attribute::LAST_EMIT_POSITION)
+            .build();
+        let check_code = "distance(particle.position, particle.last_emit_position) >= (0.25)";
+        let update_code = "particle.last_emit_position = particle.position;";
+
+        // Default is to duplicate irrespective of the particle motion
+        let modifier = CloneModifier::new(0.0, 1);
+        assert!(modifier.min_distance.is_none());
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        assert!(modifier.apply(&mut module, &mut context).is_ok());
+        assert!(!context.main_code.contains("last_emit_position"));
+
+        let modifier = CloneModifier::new(0.0, 1).with_min_distance(min_distance);
+        assert_eq!(modifier.min_distance, Some(min_distance));
+        assert!(modifier.attributes().contains(&Attribute::POSITION));
+        assert!(modifier
+            .attributes()
+            .contains(&Attribute::LAST_EMIT_POSITION));
+        assert_ne!(
+            calc_func_id(&modifier),
+            calc_func_id(&CloneModifier::new(0.0, 1))
+        );
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        assert!(modifier.apply(&mut module, &mut context).is_ok());
+        let code = &context.main_code;
+        assert!(code.contains(&format!("if ({}) {{", check_code)));
+        assert!(code.find(update_code).unwrap() < code.find("(&particle);").unwrap());
+
+        // Combined with a spawn period and an age range
+        let modifier = CloneModifier::new(0.1, 1)
+            .with_emit_age_range(0.0, 0.5)
+            .with_min_distance(min_distance);
+        assert_eq!(modifier.attributes().len(), 4);
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        assert!(modifier.apply(&mut module, &mut context).is_ok());
+        assert!(context
+            .main_code
+            .contains(&format!(" > 0 && ({})) {{", check_code)));
+        assert!(context.main_code.contains(update_code));
+    }
+
+    #[test]
+    #[should_panic]
+    fn mod_clone_emit_age_range_invalid() {
+        let _ = CloneModifier::new(0.0, 1).with_emit_age_range(-1.0, 0.5);
+    }
+
+    #[test]
+    fn mod_transfer() {
+        let writer = ExprWriter::new();
+        let condition = writer.attr(Attribute::AGE).gt(writer.lit(0.5)).expr();
+        let modifier = TransferModifier::new(condition, 1);
+        assert_eq!(modifier.context(), ModifierContext::Update);
+
+        let mut module = writer.finish();
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::new().append(Attribute::AGE).build();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        assert!(modifier.apply(&mut module, &mut context).is_ok());
+
+        let func_name = format!("transfer_{0:016X}", calc_func_id(&modifier));
+        assert!(context.extra_code.contains(&format!("fn {}(", func_name)));
+        assert!(context.extra_code.contains("*is_alive = false;"));
+        assert!(context.extra_code.contains("render_group_indirect[1u]"));
+        assert!(context
+            .extra_code
+            .contains("atomicCompareExchangeWeak(&render_group_indirect[1u].dead_count"));
+        assert!(!context.extra_code.contains("atomicSub"));
+        assert!(context
+            .extra_code
+            .contains("atomicMin(&render_group_indirect[1u].first_spawn_index, indirect_index);"));
+        assert!(context
+            .extra_code
+            .contains("particle_buffer.particles[index].age = 0.0;"));
+        assert!(!context.extra_code.contains("max_spawn"));
+        assert!(context
+            .main_code
+            .contains("if (is_alive && ((particle.age) > (0.5)))"));
+        assert!(context
+            .main_code
+            .contains(&format!("{}(&particle, &is_alive);", func_name)));
     }
 }
@@ -0,0 +1,763 @@
+//! Modifiers to make particles collide with simple shapes.
+//!
+//! These modifiers detect particles crossing the surface of a shape, and
+//! either bounce them back, kill them on impact, or clamp them back inside
+//! the shape. The [`CollisionHeightfieldModifier`] extends this to terrains
+//! described by a heightmap texture.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    calc_func_id,
+    graph::{EvalContext, ExprError},
+    Attribute, BoxedModifier, ExprHandle, Modifier, ModifierContext, Module, ShaderWriter,
+};
+
+/// A modifier making particles collide with an infinite plane.
+///
+/// The plane is defined by a point on the plane and its normal. Particles are
+/// allowed to move on the side of the plane the normal points to. When a
+/// particle moving toward the plane ends up behind it, it's considered
+/// colliding with the plane. On collision, the particle is either moved back
+/// onto the plane and bounced off it, or killed if [`kill_on_impact`] is
+/// `true`.
+///
+/// The bounce reflects the velocity component along the plane normal, scaled
+/// by the [`restitution`] coefficient. A restitution of `1.0` produces a
+/// perfectly elastic bounce, while a restitution of `0.0` makes the particle
+/// slide along the plane. To avoid all particles bouncing uniformly, the
+/// restitution can be randomized on each bounce with [`restitution_jitter`].
+///
+/// ```txt
+/// restitution = max(restitution + (frand() * 2. - 1.) * restitution_jitter, 0.);
+/// particle.velocity -= (1. + restitution) * dot(particle.velocity, normal) * normal;
+/// ```
+///
+/// # Attributes
+///
+/// This modifier requires the following particle attributes:
+/// - [`Attribute::POSITION`]
+/// - [`Attribute::VELOCITY`]
+///
+/// [`kill_on_impact`]: crate::modifier::collision::CollidePlaneModifier::kill_on_impact
+/// [`restitution`]: crate::modifier::collision::CollidePlaneModifier::restitution
+/// [`restitution_jitter`]: crate::modifier::collision::CollidePlaneModifier::restitution_jitter
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Reflect, Serialize, Deserialize)]
+pub struct CollidePlaneModifier {
+    /// A point on the plane.
+    ///
+    /// Expression type: `Vec3`
+    pub origin: ExprHandle,
+    /// The plane normal, pointing toward the side particles are allowed to
+    /// move in. The normal doesn't need to be normalized.
+    ///
+    /// Expression type: `Vec3`
+    pub normal: ExprHandle,
+    /// The restitution coefficient of the bounce, generally in `[0:1]`.
+    ///
+    /// Expression type: `f32`
+    pub restitution: ExprHandle,
+    /// Optional amplitude of the random variation of the restitution
+    /// coefficient. On each bounce, a random value in `[-jitter:jitter]` is
+    /// added to the restitution coefficient. The resulting coefficient is
+    /// clamped to be non-negative.
+    ///
+    /// Expression type: `f32`
+    pub restitution_jitter: Option<ExprHandle>,
+    /// If `true`, kill the particle on first impact instead of bouncing it.
+    pub kill_on_impact: bool,
+}
+
+impl CollidePlaneModifier {
+    /// Create a new modifier bouncing particles off the plane passing through
+    /// `origin` with the given `normal`.
+    ///
+    /// The created instance has no restitution jitter, and doesn't kill
+    /// particles on impact.
+    pub fn new(origin: ExprHandle, normal: ExprHandle, restitution: ExprHandle) -> Self {
+        Self {
+            origin,
+            normal,
+            restitution,
+            restitution_jitter: None,
+            kill_on_impact: false,
+        }
+    }
+
+    /// Set the amplitude of the random variation of the restitution
+    /// coefficient.
+    pub fn with_restitution_jitter(mut self, restitution_jitter: ExprHandle) -> Self {
+        self.restitution_jitter = Some(restitution_jitter);
+        self
+    }
+
+    /// Set whether particles are killed on first impact.
+    pub fn with_kill_on_impact(mut self, kill_on_impact: bool) -> Self {
+        self.kill_on_impact = kill_on_impact;
+        self
+    }
+}
+
+#[typetag::serde]
+impl Modifier for CollidePlaneModifier {
+    fn context(&self) -> ModifierContext {
+        ModifierContext::Update
+    }
+
+    fn attributes(&self) -> &[Attribute] {
+        &[Attribute::POSITION, Attribute::VELOCITY]
+    }
+
+    fn boxed_clone(&self) -> BoxedModifier {
+        Box::new(*self)
+    }
+
+    fn apply(&self, module: &mut Module, context: &mut ShaderWriter) -> Result<(), ExprError> {
+        let func_id = calc_func_id(self);
+        let func_name = format!("collide_plane_{0:016X}", func_id);
+
+        context.make_fn(
+            &func_name,
+            "particle: ptr<function, Particle>, is_alive: ptr<function, bool>",
+            module,
+            &mut |m: &mut Module, ctx: &mut dyn EvalContext| -> Result<String, ExprError> {
+                let origin = ctx.eval(m, self.origin)?;
+                let normal = ctx.eval(m, self.normal)?;
+
+                let impact_code = if self.kill_on_impact {
+                    "*is_alive = false;".to_string()
+                } else {
+                    let restitution = ctx.eval(m, self.restitution)?;
+                    let restitution = if let Some(jitter) = self.restitution_jitter {
+                        let jitter = ctx.eval(m, jitter)?;
+                        format!(
+                            "max(({}) + (frand() * 2. - 1.) * ({}), 0.)",
+                            restitution, jitter
+                        )
+                    } else {
+                        format!("max({}, 0.)", restitution)
+                    };
+                    format!(
+                        r##"let restitution = {restitution};
+    (*particle).{pos} -= dist * normal;
+    (*particle).{vel} -= (1. + restitution) * vn * normal;"##,
+                        pos = Attribute::POSITION.name(),
+                        vel = Attribute::VELOCITY.name(),
+                    )
+                };
+
+                Ok(format!(
+                    r##"let normal = normalize({normal});
+    let dist = dot((*particle).{pos} - ({origin}), normal);
+    let vn = dot((*particle).{vel}, normal);
+    if (dist >= 0. || vn >= 0.) {{
+        return;
+    }}
+    {impact_code}
+"##,
+                    pos = Attribute::POSITION.name(),
+                    vel = Attribute::VELOCITY.name(),
+                ))
+            },
+        )?;
+
+        context.main_code += &format!("{}(&particle, &is_alive);\n", func_name);
+
+        Ok(())
+    }
+}
+
+/// A modifier containing particles inside an axis-aligned box.
+///
+/// Particles exiting the box are moved back onto its surface, at the closest
+/// point to their current position. This is a hard constraint, unlike the
+/// [`ConformToSphereModifier`] which attracts particles with a force, and
+/// unlike the [`KillAabbModifier`] which kills the particles exiting the box.
+/// This is useful for particles which must stay inside a room or a tank.
+///
+/// If [`stop_on_contact`] is `true`, the component of the particle velocity
+/// pointing outside the box is also zeroed on contact, so the particles slide
+/// along the walls instead of continuously pushing against them.
+///
+/// # Attributes
+///
+/// This modifier requires the following particle attributes:
+/// - [`Attribute::POSITION`]
+/// - [`Attribute::VELOCITY`] (only if [`stop_on_contact`] is enabled)
+///
+/// [`ConformToSphereModifier`]: crate::modifier::force::ConformToSphereModifier
+/// [`KillAabbModifier`]: crate::modifier::kill::KillAabbModifier
+/// [`stop_on_contact`]: crate::modifier::collision::ClampToBoxModifier::stop_on_contact
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Reflect, Serialize, Deserialize)]
+pub struct ClampToBoxModifier {
+    /// Center of the box.
+    ///
+    /// Expression type: `Vec3`
+    pub center: ExprHandle,
+    /// Half-size of the box.
+    ///
+    /// Expression type: `Vec3`
+    pub half_size: ExprHandle,
+    /// If `true`, zero the outward component of the particle velocity on
+    /// contact with the box surface.
+    pub stop_on_contact: bool,
+}
+
+impl ClampToBoxModifier {
+    /// Create a new modifier containing particles inside the box with the
+    /// given center and half size.
+    ///
+    /// The created instance doesn't stop particles on contact.
+    pub fn new(center: ExprHandle, half_size: ExprHandle) -> Self {
+        Self {
+            center,
+            half_size,
+            stop_on_contact: false,
+        }
+    }
+
+    /// Set whether the outward velocity of particles is zeroed on contact.
+    pub fn with_stop_on_contact(mut self, stop_on_contact: bool) -> Self {
+        self.stop_on_contact = stop_on_contact;
+        self
+    }
+}
+
+#[typetag::serde]
+impl Modifier for ClampToBoxModifier {
+    fn context(&self) -> ModifierContext {
+        ModifierContext::Update
+    }
+
+    fn attributes(&self) -> &[Attribute] {
+        if self.stop_on_contact {
+            &[Attribute::POSITION, Attribute::VELOCITY]
+        } else {
+            &[Attribute::POSITION]
+        }
+    }
+
+    fn boxed_clone(&self) -> BoxedModifier {
+        Box::new(*self)
+    }
+
+    fn apply(&self, module: &mut Module, context: &mut ShaderWriter) -> Result<(), ExprError> {
+        let func_id = calc_func_id(self);
+        let func_name = format!("clamp_to_box_{0:016X}", func_id);
+
+        context.make_fn(
+            &func_name,
+            "particle: ptr<function, Particle>",
+            module,
+            &mut |m: &mut Module, ctx: &mut dyn EvalContext| -> Result<String, ExprError> {
+                let center = ctx.eval(m, self.center)?;
+                let half_size = ctx.eval(m, self.half_size)?;
+
+                // Zero the velocity components moving away from the clamped position
+                let stop_code = if self.stop_on_contact {
+                    format!(
+                        r##"let vel = (*particle).{vel};
+    (*particle).{vel} = select(vel, vec3<f32>(0.), (clamped - pos) * vel < vec3<f32>(0.));
+    "##,
+                        vel = Attribute::VELOCITY.name(),
+                    )
+                } else {
+                    String::new()
+                };
+
+                Ok(format!(
+                    r##"let center = {center};
+    let half_size = abs({half_size});
+    let pos = (*particle).{pos};
+    let clamped = clamp(pos, center - half_size, center + half_size);
+    {stop_code}(*particle).{pos} = clamped;
+"##,
+                    pos = Attribute::POSITION.name(),
+                ))
+            },
+        )?;
+
+        context.main_code += &format!("{}(&particle);\n", func_name);
+
+        Ok(())
+    }
+}
+
+/// A modifier containing particles inside a sphere.
+///
+/// Particles exiting the sphere are moved back onto its surface, at the
+/// closest point to their current position. This is a hard constraint, unlike
+/// the [`ConformToSphereModifier`] which attracts particles with a force, and
+/// unlike the [`KillSphereModifier`] which kills the particles exiting the
+/// sphere.
+///
+/// If [`stop_on_contact`] is `true`, the component of the particle velocity
+/// pointing outside the sphere is also zeroed on contact, so the particles
+/// slide along the surface instead of continuously pushing against it.
+///
+/// # Attributes
+///
+/// This modifier requires the following particle attributes:
+/// - [`Attribute::POSITION`]
+/// - [`Attribute::VELOCITY`] (only if [`stop_on_contact`] is enabled)
+///
+/// [`ConformToSphereModifier`]: crate::modifier::force::ConformToSphereModifier
+/// [`KillSphereModifier`]: crate::modifier::kill::KillSphereModifier
+/// [`stop_on_contact`]: crate::modifier::collision::ClampToSphereModifier::stop_on_contact
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Reflect, Serialize, Deserialize)]
+pub struct ClampToSphereModifier {
+    /// Center of the sphere.
+    ///
+    /// Expression type: `Vec3`
+    pub center: ExprHandle,
+    /// Radius of the sphere.
+    ///
+    /// Expression type: `f32`
+    pub radius: ExprHandle,
+    /// If `true`, zero the outward component of the particle velocity on
+    /// contact with the sphere surface.
+    pub stop_on_contact: bool,
+}
+
+impl ClampToSphereModifier {
+    /// Create a new modifier containing particles inside the sphere with the
+    /// given center and radius.
+    ///
+    /// The created instance doesn't stop particles on contact.
+    pub fn new(center: ExprHandle, radius: ExprHandle) -> Self {
+        Self {
+            center,
+            radius,
+            stop_on_contact: false,
+        }
+    }
+
+    /// Set whether the outward velocity of particles is zeroed on contact.
+    pub fn with_stop_on_contact(mut self, stop_on_contact: bool) -> Self {
+        self.stop_on_contact = stop_on_contact;
+        self
+    }
+}
+
+#[typetag::serde]
+impl Modifier for ClampToSphereModifier {
+    fn context(&self) -> ModifierContext {
+        ModifierContext::Update
+    }
+
+    fn attributes(&self) -> &[Attribute] {
+        if self.stop_on_contact {
+            &[Attribute::POSITION, Attribute::VELOCITY]
+        } else {
+            &[Attribute::POSITION]
+        }
+    }
+
+    fn boxed_clone(&self) -> BoxedModifier {
+        Box::new(*self)
+    }
+
+    fn apply(&self, module: &mut Module, context: &mut ShaderWriter) -> Result<(), ExprError> {
+        let func_id = calc_func_id(self);
+        let func_name = format!("clamp_to_sphere_{0:016X}", func_id);
+
+        context.make_fn(
+            &func_name,
+            "particle: ptr<function, Particle>",
+            module,
+            &mut |m: &mut Module, ctx: &mut dyn EvalContext| -> Result<String, ExprError> {
+                let center = ctx.eval(m, self.center)?;
+                let radius = ctx.eval(m, self.radius)?;
+
+                // Remove the outward velocity along the surface normal
+                let stop_code = if self.stop_on_contact {
+                    format!(
+                        r##"
+    let vn = dot((*particle).{vel}, normal);
+    if (vn > 0.) {{
+        (*particle).{vel} -= vn * normal;
+    }}"##,
+                        vel = Attribute::VELOCITY.name(),
+                    )
+                } else {
+                    String::new()
+                };
+
+                Ok(format!(
+                    r##"let center = {center};
+    let radius = abs({radius});
+    let delta = (*particle).{pos} - center;
+    let dist = length(delta);
+    if (dist <= radius) {{
+        return;
+    }}
+    let normal = delta / dist;
+    (*particle).{pos} = center + normal * radius;{stop_code}
+"##,
+                    pos = Attribute::POSITION.name(),
+                ))
+            },
+        )?;
+
+        context.main_code += &format!("{}(&particle);\n", func_name);
+
+        Ok(())
+    }
+}
+
+/// A modifier making particles collide with a terrain described by a
+/// heightmap texture.
+///
+/// The heightmap covers an axis-aligned rectangle of the XZ plane, starting at
+/// [`origin`] and extending over [`extents`]. The terrain height at a given
+/// point is the height of [`origin`] plus the red channel of the heightmap
+/// sampled at that point, multiplied by [`height_scale`]. All values are
+/// expressed in simulation space, which is the world space unless the effect
+/// is simulated in local space.
+///
+/// Particles below the terrain surface are pushed back up onto it. On impact,
+/// the velocity is split into its normal and tangential components relative
+/// to the terrain surface, whose normal is approximated from the neighboring
+/// texels of the heightmap. The normal component is reflected and scaled by
+/// the [`restitution`] coefficient, while the tangential component is scaled
+/// by `1 - friction`. Particles outside the rectangle covered by the heightmap
+/// are not affected.
+///
+/// This is a middle ground between the [`CollidePlaneModifier`] and a full
+/// signed distance field, well suited for outdoor scenes with particles like
+/// rain, leaves, or debris falling on terrain.
+///
+/// The heightmap is sampled with the sampler of its [`Image`], in the compute
+/// update pass, so its texture format must be filterable (for example
+/// [`R8Unorm`] or [`R16Float`], but not [`R32Float`]). An effect currently
+/// supports a single heightmap texture.
+///
+/// # Attributes
+///
+/// This modifier requires the following particle attributes:
+/// - [`Attribute::POSITION`]
+/// - [`Attribute::VELOCITY`]
+///
+/// [`origin`]: crate::modifier::collision::CollisionHeightfieldModifier::origin
+/// [`extents`]: crate::modifier::collision::CollisionHeightfieldModifier::extents
+/// [`height_scale`]: crate::modifier::collision::CollisionHeightfieldModifier::height_scale
+/// [`restitution`]: crate::modifier::collision::CollisionHeightfieldModifier::restitution
+/// [`R8Unorm`]: bevy::render::render_resource::TextureFormat::R8Unorm
+/// [`R16Float`]: bevy::render::render_resource::TextureFormat::R16Float
+/// [`R32Float`]: bevy::render::render_resource::TextureFormat::R32Float
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Reflect, Serialize, Deserialize)]
+pub struct CollisionHeightfieldModifier {
+    /// The heightmap texture. Only the red channel is used.
+    #[serde(skip)]
+    pub heightmap: Handle<Image>,
+    /// The asset path of the heightmap texture, if any.
+    ///
+    /// This is the serialized representation of [`heightmap`], which is
+    /// resolved into a handle when loading the effect asset.
+    ///
+    /// [`heightmap`]: CollisionHeightfieldModifier::heightmap
+    #[serde(default)]
+    pub heightmap_path: Option<String>,
+    /// Corner of the terrain with the lowest X and Z coordinates, at the
+    /// height corresponding to a zero heightmap value.
+    ///
+    /// Expression type: `Vec3`
+    pub origin: ExprHandle,
+    /// Size of the terrain along the X and Z axes.
+    ///
+    /// Expression type: `Vec2`
+    pub extents: ExprHandle,
+    /// Scale applied to the heightmap values to obtain a height.
+    ///
+    /// Expression type: `f32`
+    pub height_scale: ExprHandle,
+    /// The restitution coefficient of the bounce, generally in `[0:1]`.
+    ///
+    /// Expression type: `f32`
+    pub restitution: ExprHandle,
+    /// The friction coefficient reducing the tangential velocity on impact, in
+    /// `[0:1]`.
+    ///
+    /// Expression type: `f32`
+    pub friction: ExprHandle,
+}
+
+impl CollisionHeightfieldModifier {
+    /// Create a new modifier colliding particles with the terrain described by
+    /// the given heightmap texture.
+    ///
+    /// The created instance has a zero restitution and no friction, so
+    /// particles slide along the terrain on impact.
+    ///
+    /// If the heightmap handle was obtained from the [`AssetServer`], its asset
+    /// path is saved into [`heightmap_path`] so that the heightmap is
+    /// serialized with the effect.
+    ///
+    /// [`heightmap_path`]: CollisionHeightfieldModifier::heightmap_path
+    pub fn new(
+        module: &mut Module,
+        heightmap: Handle<Image>,
+        origin: ExprHandle,
+        extents: ExprHandle,
+        height_scale: ExprHandle,
+    ) -> Self {
+        Self {
+            heightmap_path: heightmap.path().map(ToString::to_string),
+            heightmap,
+            origin,
+            extents,
+            height_scale,
+            restitution: module.lit(0.),
+            friction: module.lit(0.),
+        }
+    }
+
+    /// Set the restitution coefficient of the bounce.
+    pub fn with_restitution(mut self, restitution: ExprHandle) -> Self {
+        self.restitution = restitution;
+        self
+    }
+
+    /// Set the friction coefficient.
+    pub fn with_friction(mut self, friction: ExprHandle) -> Self {
+        self.friction = friction;
+        self
+    }
+}
+
+#[typetag::serde]
+impl Modifier for CollisionHeightfieldModifier {
+    fn context(&self) -> ModifierContext {
+        ModifierContext::Update
+    }
+
+    fn attributes(&self) -> &[Attribute] {
+        &[Attribute::POSITION, Attribute::VELOCITY]
+    }
+
+    fn boxed_clone(&self) -> BoxedModifier {
+        Box::new(self.clone())
+    }
+
+    fn apply(&self, module: &mut Module, context: &mut ShaderWriter) -> Result<(), ExprError> {
+        let func_id = calc_func_id(self);
+        let func_name = format!("collide_heightfield_{0:016X}", func_id);
+
+        context.make_fn(
+            &func_name,
+            "particle: ptr<function, Particle>",
+            module,
+            &mut |m: &mut Module, ctx: &mut dyn EvalContext| -> Result<String, ExprError> {
+                let origin = ctx.eval(m, self.origin)?;
+                let extents = ctx.eval(m, self.extents)?;
+                let height_scale = ctx.eval(m, self.height_scale)?;
+                let restitution = ctx.eval(m, self.restitution)?;
+                let friction = ctx.eval(m, self.friction)?;
+
+                Ok(format!(
+                    r##"let origin = {origin};
+    let extents = {extents};
+    let height_scale = {height_scale};
+    let uv = ((*particle).{pos}.xz - origin.xz) / extents;
+    if (any(uv < vec2<f32>(0.)) || any(uv > vec2<f32>(1.))) {{
+        return;
+    }}
+    let height = origin.y + textureSampleLevel(heightfield_texture, heightfield_sampler, uv, 0.).r * height_scale;
+    if ((*particle).{pos}.y >= height) {{
+        return;
+    }}
+    (*particle).{pos}.y = height;
+
+    // Approximate the surface normal from the neighboring texels
+    let texel = 1. / vec2<f32>(textureDimensions(heightfield_texture, 0));
+    let h_left = textureSampleLevel(heightfield_texture, heightfield_sampler, uv - vec2<f32>(texel.x, 0.), 0.).r;
+    let h_right = textureSampleLevel(heightfield_texture, heightfield_sampler, uv + vec2<f32>(texel.x, 0.), 0.).r;
+    let h_down = textureSampleLevel(heightfield_texture, heightfield_sampler, uv - vec2<f32>(0., texel.y), 0.).r;
+    let h_up = textureSampleLevel(heightfield_texture, heightfield_sampler, uv + vec2<f32>(0., texel.y), 0.).r;
+    let slope = vec2<f32>(h_right - h_left, h_up - h_down) * height_scale / (2. * texel * extents);
+    let normal = normalize(vec3<f32>(-slope.x, 1., -slope.y));
+
+    let vn = dot((*particle).{vel}, normal);
+    if (vn < 0.) {{
+        let vt = (*particle).{vel} - vn * normal;
+        (*particle).{vel} = vt * (1. - clamp({friction}, 0., 1.)) - max({restitution}, 0.) * vn * normal;
+    }}
+"##,
+                    pos = Attribute::POSITION.name(),
+                    vel = Attribute::VELOCITY.name(),
+                ))
+            },
+        )?;
+
+        context.heightfield_texture = Some(self.heightmap.clone());
+        context.main_code += &format!("{}(&particle);\n", func_name);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ExprWriter, ParticleLayout, PropertyLayout};
+
+    #[test]
+    fn mod_collide_plane() {
+        let writer = ExprWriter::new();
+        let origin = writer.lit(Vec3::ZERO).expr();
+        let normal = writer.lit(Vec3::Y).expr();
+        let restitution = writer.lit(0.6).expr();
+        let jitter = writer.lit(0.2).expr();
+        let bounce = CollidePlaneModifier::new(origin, normal, restitution);
+        let bounce_jitter = bounce.with_restitution_jitter(jitter);
+        let kill = bounce.with_kill_on_impact(true);
+        let mut module = writer.finish();
+
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        assert!(bounce.apply(&mut module, &mut context).is_ok());
+        let func_name = format!("collide_plane_{0:016X}", calc_func_id(&bounce));
+        assert!(context.extra_code.contains(&format!("fn {}(", func_name)));
+        assert!(context
+            .extra_code
+            .contains("let restitution = max(0.6, 0.);"));
+        assert!(!context.extra_code.contains("frand()"));
+        assert!(!context.extra_code.contains("*is_alive = false;"));
+        assert!(context
+            .main_code
+            .contains(&format!("{}(&particle, &is_alive);", func_name)));
+
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        assert!(bounce_jitter.apply(&mut module, &mut context).is_ok());
+        assert!(context
+            .extra_code
+            .contains("let restitution = max((0.6) + (frand() * 2. - 1.) * (0.2), 0.);"));
+
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        assert!(kill.apply(&mut module, &mut context).is_ok());
+        assert!(context.extra_code.contains("*is_alive = false;"));
+        assert!(!context.extra_code.contains("restitution"));
+    }
+
+    #[test]
+    fn mod_clamp_to_box() {
+        let writer = ExprWriter::new();
+        let center = writer.lit(Vec3::ZERO).expr();
+        let half_size = writer.lit(Vec3::ONE).expr();
+        let clamp = ClampToBoxModifier::new(center, half_size);
+        let stop = clamp.with_stop_on_contact(true);
+        let mut module = writer.finish();
+
+        assert_eq!(clamp.attributes(), &[Attribute::POSITION]);
+        assert!(stop.attributes().contains(&Attribute::VELOCITY));
+
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        assert!(clamp.apply(&mut module, &mut context).is_ok());
+        let func_name = format!("clamp_to_box_{0:016X}", calc_func_id(&clamp));
+        assert!(context.extra_code.contains(&format!("fn {}(", func_name)));
+        assert!(context
+            .extra_code
+            .contains("let clamped = clamp(pos, center - half_size, center + half_size);"));
+        assert!(!context.extra_code.contains("velocity"));
+        assert!(context
+            .main_code
+            .contains(&format!("{}(&particle);", func_name)));
+
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        assert!(stop.apply(&mut module, &mut context).is_ok());
+        assert!(context.extra_code.contains(
+            "(*particle).velocity = select(vel, vec3<f32>(0.), (clamped - pos) * vel < vec3<f32>(0.));"
+        ));
+    }
+
+    #[test]
+    fn mod_clamp_to_sphere() {
+        let writer = ExprWriter::new();
+        let center = writer.lit(Vec3::ZERO).expr();
+        let radius = writer.lit(2.).expr();
+        let clamp = ClampToSphereModifier::new(center, radius);
+        let stop = clamp.with_stop_on_contact(true);
+        let mut module = writer.finish();
+
+        assert_eq!(clamp.attributes(), &[Attribute::POSITION]);
+        assert!(stop.attributes().contains(&Attribute::VELOCITY));
+
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        assert!(clamp.apply(&mut module, &mut context).is_ok());
+        let func_name = format!("clamp_to_sphere_{0:016X}", calc_func_id(&clamp));
+        assert!(context.extra_code.contains(&format!("fn {}(", func_name)));
+        assert!(context
+            .extra_code
+            .contains("(*particle).position = center + normal * radius;"));
+        assert!(!context.extra_code.contains("velocity"));
+
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        assert!(stop.apply(&mut module, &mut context).is_ok());
+        assert!(context
+            .extra_code
+            .contains("(*particle).velocity -= vn * normal;"));
+    }
+
+    #[test]
+    fn mod_collide_heightfield() {
+        let mut module = Module::default();
+        let origin = module.lit(Vec3::new(-10., 0., -10.));
+        let extents = module.lit(Vec2::splat(20.));
+        let height_scale = module.lit(5.);
+        let heightmap = Handle::<Image>::weak_from_u128(0x1234);
+        let collide = CollisionHeightfieldModifier::new(
+            &mut module,
+            heightmap.clone(),
+            origin,
+            extents,
+            height_scale,
+        );
+        let restitution = module.lit(0.3);
+        let friction = module.lit(0.1);
+        assert_eq!(collide.heightmap, heightmap);
+        // A handle not loaded from a path has nothing to serialize
+        assert!(collide.heightmap_path.is_none());
+        let collide = collide
+            .with_restitution(restitution)
+            .with_friction(friction);
+        assert_eq!(collide.context(), ModifierContext::Update);
+        assert!(collide.attributes().contains(&Attribute::POSITION));
+        assert!(collide.attributes().contains(&Attribute::VELOCITY));
+
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        assert!(context.heightfield_texture.is_none());
+        assert!(collide.apply(&mut module, &mut context).is_ok());
+        assert_eq!(context.heightfield_texture, Some(heightmap));
+
+        let func_name = format!("collide_heightfield_{0:016X}", calc_func_id(&collide));
+        assert!(context.extra_code.contains(&format!("fn {}(", func_name)));
+        assert!(context
+            .extra_code
+            .contains("textureSampleLevel(heightfield_texture"));
+        assert!(context
+            .extra_code
+            .contains("(*particle).position.y = height;"));
+        assert!(context
+            .main_code
+            .contains(&format!("{}(&particle);", func_name)));
+    }
+}
@@ -0,0 +1,309 @@
+//! Modifier to conditionally apply another modifier.
+//!
+//! The [`ConditionalModifier`] wraps another modifier, and applies it only to
+//! the particles for which a boolean expression evaluates to `true`. This
+//! enables behaviors like applying some drag only to particles above a speed
+//! threshold, or cloning only particles tagged by some attribute.
+//!
+//! With [`ConditionalModifier::via_property()`], the condition is instead a
+//! property of the effect, which allows toggling a modifier on and off at
+//! runtime without recompiling the effect's shaders.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    graph::{EvalContext, ExprError},
+    Attribute, BoxedModifier, ExprHandle, Modifier, ModifierContext, Module, ScalarType,
+    ShaderWriter, ValueType,
+};
+
+/// A modifier applying another modifier only to particles meeting a
+/// condition.
+///
+/// The `condition` expression is evaluated per particle, and the code of the
+/// `inner` modifier is executed only if it evaluates to `true`. The condition
+/// must be a scalar boolean expression, for example the result of a
+/// comparison like `particle.age > 1.0`.
+///
+/// Any function the inner modifier defines is emitted at shader top level as
+/// usual; only the main code of the inner modifier is conditionally executed.
+///
+/// This modifier only supports the [`ModifierContext::Init`] and
+/// [`ModifierContext::Update`] contexts. Its context is the one of the inner
+/// modifier, restricted to those contexts.
+///
+/// # Example
+///
+/// ```
+/// # use bevy_hanabi::*;
+/// let writer = ExprWriter::new();
+///
+/// // Apply some drag only to particles older than 1 second
+/// let age = writer.attr(Attribute::AGE);
+/// let condition = age.gt(writer.lit(1.)).expr();
+/// let drag = LinearDragModifier::new(writer.lit(3.).expr());
+/// let modifier = ConditionalModifier::new(condition, drag);
+/// ```
+///
+/// # Runtime toggle
+///
+/// A modifier can be enabled and disabled at runtime by conditioning it on a
+/// property with [`via_property()`], then assigning the property with
+/// [`EffectProperties::set_enabled()`]. For example, to react to the wind only
+/// during a storm:
+///
+/// ```
+/// # use bevy_hanabi::*;
+/// let mut module = Module::default();
+/// let wind = AccelModifier::constant(&mut module, bevy::math::Vec3::X * 5.);
+/// let modifier = ConditionalModifier::via_property(&mut module, "storm", wind);
+/// let asset = EffectAsset::new(vec![1024], Spawner::rate(32.0.into()), module)
+///     // Disabled by default
+///     .with_property("storm", 0u32.into())
+///     .update(modifier);
+///
+/// // Later, at runtime, on the effect instance
+/// let mut properties = EffectProperties::default();
+/// properties.set_enabled("storm", true);
+/// ```
+///
+/// The inner modifier's code is always compiled into the shader. While
+/// disabled, its only cost is reading the property and evaluating the branch.
+/// Because the property has the same value for all particles of the effect,
+/// all GPU threads take the same branch, so there's no divergence penalty.
+/// However, the registers needed by the inner modifier are still allocated,
+/// which may reduce the GPU occupancy of the shader compared to removing the
+/// modifier entirely.
+///
+/// # Attributes
+///
+/// This modifier requires the attributes of the inner modifier. Any attribute
+/// read by the condition expression must also be present in the particle
+/// layout of the effect.
+///
+/// [`via_property()`]: ConditionalModifier::via_property
+/// [`EffectProperties::set_enabled()`]: crate::EffectProperties::set_enabled
+#[derive(Clone, Reflect, Serialize, Deserialize)]
+#[reflect(from_reflect = false)]
+pub struct ConditionalModifier {
+    /// The condition under which the inner modifier is applied.
+    ///
+    /// Expression type: `bool`
+    pub condition: ExprHandle,
+    /// The modifier applied to the particles meeting the condition.
+    #[reflect(ignore)]
+    pub inner: BoxedModifier,
+}
+
+impl ConditionalModifier {
+    /// Create a new modifier applying `inner` only to particles for which
+    /// `condition` evaluates to `true`.
+    pub fn new(condition: ExprHandle, inner: impl Modifier) -> Self {
+        Self {
+            condition,
+            inner: Box::new(inner),
+        }
+    }
+
+    /// Create a new modifier applying `inner` only while a property is
+    /// enabled.
+    ///
+    /// The property must be declared on the [`EffectAsset`] with a `u32`
+    /// value, where any non-zero value enables the inner modifier. WGSL
+    /// doesn't allow storing a `bool` in the GPU buffer holding the
+    /// properties. Use [`EffectProperties::set_enabled()`] to toggle the
+    /// property at runtime.
+    ///
+    /// [`EffectAsset`]: crate::EffectAsset
+    /// [`EffectProperties::set_enabled()`]: crate::EffectProperties::set_enabled
+    pub fn via_property(
+        module: &mut Module,
+        property_name: impl Into<String>,
+        inner: impl Modifier,
+    ) -> Self {
+        let property = module.prop(property_name);
+        let zero = module.lit(0u32);
+        let condition = module.not_equal(property, zero);
+        Self::new(condition, inner)
+    }
+}
+
+#[typetag::serde]
+impl Modifier for ConditionalModifier {
+    fn context(&self) -> ModifierContext {
+        self.inner.context() & (ModifierContext::Init | ModifierContext::Update)
+    }
+
+    fn attributes(&self) -> &[Attribute] {
+        self.inner.attributes()
+    }
+
+    fn boxed_clone(&self) -> BoxedModifier {
+        Box::new(self.clone())
+    }
+
+    fn apply(&self, module: &mut Module, context: &mut ShaderWriter) -> Result<(), ExprError> {
+        let modifier_context = context.modifier_context();
+        if !self.context().contains(modifier_context) {
+            return Err(ExprError::InvalidModifierContext(
+                modifier_context,
+                self.context(),
+            ));
+        }
+
+        // The type of operator expressions is only known once evaluated. Assign the
+        // condition to a typed variable below so that the shader compiler catches any
+        // non-boolean condition in that case.
+        if let Some(value_type) = module.try_get(self.condition)?.value_type() {
+            if value_type != ValueType::Scalar(ScalarType::Bool) {
+                return Err(ExprError::TypeError(format!(
+                    "Condition of ConditionalModifier must be a scalar bool, found {:?}.",
+                    value_type
+                )));
+            }
+        }
+
+        context.emit_conditional_block(self.condition, module, |module, nested| {
+            self.inner.apply(module, nested)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ExprWriter, ParticleLayout, Property, PropertyLayout, RadialAccelModifier,
+        TimeDilationModifier, Value,
+    };
+
+    #[test]
+    fn apply() {
+        let writer = ExprWriter::new();
+        let condition = writer.attr(Attribute::AGE).gt(writer.lit(1.)).expr();
+        let accel = RadialAccelModifier::new(writer.lit(Vec3::ZERO).expr(), writer.lit(2.).expr());
+        let modifier = ConditionalModifier::new(condition, accel);
+        assert_eq!(modifier.context(), ModifierContext::Update);
+        assert_eq!(modifier.attributes(), accel.attributes());
+
+        let mut module = writer.finish();
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+
+        // Wrong context
+        let mut context =
+            ShaderWriter::new(ModifierContext::Init, &property_layout, &particle_layout);
+        assert!(matches!(
+            modifier.apply(&mut module, &mut context),
+            Err(ExprError::InvalidModifierContext(..))
+        ));
+
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        assert!(modifier.apply(&mut module, &mut context).is_ok());
+
+        // The inner function is defined at top level, and only called conditionally
+        assert!(context.extra_code.contains("fn radial_accel_"));
+        let if_index = context.main_code.find("if (").unwrap();
+        let call_index = context.main_code.find("radial_accel_").unwrap();
+        assert!(call_index > if_index);
+        assert!(context
+            .main_code
+            .contains(": bool = (particle.age) > (1.);"));
+    }
+
+    #[test]
+    fn via_property() {
+        let mut module = Module::default();
+        let accel = RadialAccelModifier::constant(&mut module, Vec3::ZERO, 2.);
+        let modifier = ConditionalModifier::via_property(&mut module, "storm", accel);
+
+        let property_layout = PropertyLayout::new(&[Property::new("storm", Value::from(0u32))]);
+        let particle_layout = ParticleLayout::default();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        assert!(modifier.apply(&mut module, &mut context).is_ok());
+        assert!(context
+            .main_code
+            .contains(": bool = (properties.storm) != (0u);"));
+
+        // Undeclared property
+        let property_layout = PropertyLayout::default();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        assert!(matches!(
+            modifier.apply(&mut module, &mut context),
+            Err(ExprError::PropertyError(..))
+        ));
+    }
+
+    #[test]
+    fn time_dilation() {
+        let mut module = Module::default();
+        let center = module.lit(Vec3::ZERO);
+        let radius = module.lit(1.);
+        let scale = module.lit(0.5);
+        let dilation = TimeDilationModifier::new(center, radius, scale);
+        let modifier = ConditionalModifier::via_property(&mut module, "slow", dilation);
+
+        let property_layout = PropertyLayout::new(&[Property::new("slow", Value::from(0u32))]);
+        let particle_layout = ParticleLayout::new().append(Attribute::POSITION).build();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        assert!(modifier.apply(&mut module, &mut context).is_ok());
+
+        // The delta time is only scaled if the condition holds
+        let code = &context.delta_time_code;
+        assert!(code.contains(": bool = (properties.slow) != (0u);"));
+        let if_index = code.find("if (").unwrap();
+        let call_index = code.find("time_dilation_").unwrap();
+        let end_index = code.rfind('}').unwrap();
+        assert!(call_index > if_index);
+        assert!(end_index > call_index);
+        assert!(!context.main_code.contains("time_dilation_"));
+    }
+
+    #[test]
+    fn invalid_condition() {
+        let writer = ExprWriter::new();
+        let condition = writer.lit(1.).expr();
+        let accel = RadialAccelModifier::new(writer.lit(Vec3::ZERO).expr(), writer.lit(2.).expr());
+        let modifier = ConditionalModifier::new(condition, accel);
+
+        let mut module = writer.finish();
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        assert!(matches!(
+            modifier.apply(&mut module, &mut context),
+            Err(ExprError::TypeError(..))
+        ));
+    }
+
+    #[test]
+    fn serde() {
+        let writer = ExprWriter::new();
+        let condition = writer.lit(true).expr();
+        let accel = RadialAccelModifier::new(writer.lit(Vec3::ZERO).expr(), writer.lit(2.).expr());
+        let modifier = ConditionalModifier::new(condition, accel);
+        let s = ron::to_string(&modifier).unwrap();
+        let modifier_serde: ConditionalModifier = ron::from_str(&s).unwrap();
+        assert_eq!(modifier.condition, modifier_serde.condition);
+        assert_eq!(
+            modifier
+                .inner
+                .as_any()
+                .downcast_ref::<RadialAccelModifier>(),
+            Some(&accel)
+        );
+        assert_eq!(
+            modifier_serde
+                .inner
+                .as_any()
+                .downcast_ref::<RadialAccelModifier>(),
+            Some(&accel)
+        );
+    }
+}
@@ -0,0 +1,318 @@
+//! Modifier to inject custom user code into the effect shaders.
+//!
+//! This is an escape hatch for behaviors which cannot be expressed with the
+//! expression graph and the built-in modifiers. The code is injected as is,
+//! without much validation, so is inherently more fragile than other
+//! modifiers.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    calc_func_id, Attribute, BoxedModifier, EvalContext, ExprError, Modifier, ModifierContext,
+    Module, RenderContext, RenderModifier, ShaderWriter,
+};
+
+/// A modifier injecting raw custom WGSL code into the effect shaders.
+///
+/// The code of [`wgsl`] is injected as is into the main function of the
+/// shader(s) of each context the modifier is used in, inside its own block
+/// scope so that any local variable it declares doesn't collide with the code
+/// generated by other modifiers. The code of [`extra_wgsl`] is injected at
+/// shader top level, and can declare helper functions called from [`wgsl`].
+///
+/// Any occurrence of the `{{ID}}` placeholder in either code is replaced with
+/// a unique identifier derived from the modifier content. Use it to mangle
+/// the names of the top-level functions of [`extra_wgsl`], to avoid collisions
+/// if several instances of this modifier are used in the same effect.
+///
+/// The variables available to the code depend on the context:
+/// - In the [`ModifierContext::Init`] and [`ModifierContext::Update`]
+///   contexts, the particle is accessed through the `particle` variable, for
+///   example `particle.position`.
+/// - In the [`ModifierContext::Render`] context, the code is injected into the
+///   vertex shader. In addition to the `particle` variable, the code can modify
+///   the render inputs `size`, `color`, `axis_x`, `axis_y`, and `axis_z`.
+///
+/// # Warning
+///
+/// This is an advanced feature. The code is not validated beyond the checks
+/// below, and the variables and functions available to it are an
+/// implementation detail of the shader templates, which may change between
+/// versions without notice. Invalid code produces shader compilation errors at
+/// runtime.
+///
+/// # Attributes
+///
+/// This modifier requires the attributes listed in [`attributes`]. All the
+/// attributes read or written by the custom code must be declared there, so
+/// that they're allocated in the particle layout. Any access to an attribute
+/// in the form `particle.<name>` for an attribute not declared produces an
+/// [`ExprError::GraphEvalError`] when the modifier is applied.
+///
+/// [`wgsl`]: crate::modifier::custom::CustomCodeModifier::wgsl
+/// [`extra_wgsl`]: crate::modifier::custom::CustomCodeModifier::extra_wgsl
+/// [`attributes`]: crate::modifier::custom::CustomCodeModifier::attributes
+#[derive(Debug, Clone, PartialEq, Reflect, Serialize, Deserialize)]
+pub struct CustomCodeModifier {
+    /// The context(s) the modifier can be used in.
+    #[reflect(ignore)]
+    pub context: ModifierContext,
+    /// The WGSL code injected into the main function of the shader(s).
+    pub wgsl: String,
+    /// Optional WGSL code injected at shader top level.
+    #[serde(default)]
+    pub extra_wgsl: String,
+    /// The particle attributes read or written by the code.
+    #[reflect(ignore)]
+    pub attributes: Vec<Attribute>,
+}
+
+impl CustomCodeModifier {
+    /// Placeholder replaced with a unique identifier in the custom code.
+    pub const ID_PLACEHOLDER: &'static str = "{{ID}}";
+
+    /// Create a new modifier injecting the given code into the given
+    /// context(s), and requiring the given attributes.
+    pub fn new(
+        context: ModifierContext,
+        wgsl: impl Into<String>,
+        attributes: impl Into<Vec<Attribute>>,
+    ) -> Self {
+        Self {
+            context,
+            wgsl: wgsl.into(),
+            extra_wgsl: String::new(),
+            attributes: attributes.into(),
+        }
+    }
+
+    /// Set the WGSL code injected at shader top level.
+    pub fn with_extra_wgsl(mut self, extra_wgsl: impl Into<String>) -> Self {
+        self.extra_wgsl = extra_wgsl.into();
+        self
+    }
+
+    /// Validate the attributes accessed by the code, and generate the main and
+    /// extra code with the `{{ID}}` placeholder replaced.
+    fn generate(&self) -> Result<(String, String), ExprError> {
+        for code in [&self.wgsl, &self.extra_wgsl] {
+            for attr in Attribute::all() {
+                if !self.attributes.contains(attr) && accesses_attribute(code, attr.name()) {
+                    return Err(ExprError::GraphEvalError(format!(
+                        "Custom code accesses attribute '{}' which is not declared in the attributes of the CustomCodeModifier.",
+                        attr.name()
+                    )));
+                }
+            }
+        }
+
+        let id = format!(
+            "{0:016X}",
+            calc_func_id(&(&self.wgsl, &self.extra_wgsl, &self.attributes))
+        );
+        let main_code = format!("{{\n{}\n}}\n", self.wgsl.replace(Self::ID_PLACEHOLDER, &id));
+        let extra_code = if self.extra_wgsl.is_empty() {
+            String::new()
+        } else {
+            format!("{}\n", self.extra_wgsl.replace(Self::ID_PLACEHOLDER, &id))
+        };
+        Ok((main_code, extra_code))
+    }
+}
+
+/// Check if some code accesses the field `name` of the `particle` variable.
+fn accesses_attribute(code: &str, name: &str) -> bool {
+    let is_ident_char = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    ["particle.", "(*particle)."].iter().any(|prefix| {
+        let pattern = format!("{}{}", prefix, name);
+        code.match_indices(&pattern).any(|(index, _)| {
+            let before = code[..index].chars().next_back();
+            let after = code[index + pattern.len()..].chars().next();
+            !before.map_or(false, is_ident_char) && !after.map_or(false, is_ident_char)
+        })
+    })
+}
+
+#[typetag::serde]
+impl Modifier for CustomCodeModifier {
+    fn context(&self) -> ModifierContext {
+        self.context
+    }
+
+    fn as_render(&self) -> Option<&dyn RenderModifier> {
+        if self.context.contains(ModifierContext::Render) {
+            Some(self)
+        } else {
+            None
+        }
+    }
+
+    fn as_render_mut(&mut self) -> Option<&mut dyn RenderModifier> {
+        if self.context.contains(ModifierContext::Render) {
+            Some(self)
+        } else {
+            None
+        }
+    }
+
+    fn attributes(&self) -> &[Attribute] {
+        &self.attributes
+    }
+
+    fn boxed_clone(&self) -> BoxedModifier {
+        Box::new(self.clone())
+    }
+
+    fn apply(&self, _module: &mut Module, context: &mut ShaderWriter) -> Result<(), ExprError> {
+        let modifier_context = context.modifier_context();
+        if !self.context.contains(modifier_context) {
+            return Err(ExprError::InvalidModifierContext(
+                modifier_context,
+                self.context,
+            ));
+        }
+        let (main_code, extra_code) = self.generate()?;
+        context.main_code += &main_code;
+        context.extra_code += &extra_code;
+        Ok(())
+    }
+}
+
+#[typetag::serde]
+impl RenderModifier for CustomCodeModifier {
+    fn apply_render(
+        &self,
+        _module: &mut Module,
+        context: &mut RenderContext,
+    ) -> Result<(), ExprError> {
+        let (main_code, extra_code) = self.generate()?;
+        context.vertex_code += &main_code;
+        context.render_extra += &extra_code;
+        Ok(())
+    }
+
+    fn boxed_render_clone(&self) -> Box<dyn RenderModifier> {
+        Box::new(self.clone())
+    }
+
+    fn as_modifier(&self) -> &dyn Modifier {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ParticleLayout, PropertyLayout};
+
+    #[test]
+    fn accesses() {
+        assert!(accesses_attribute("particle.age += 1.;", "age"));
+        assert!(accesses_attribute("(*particle).age += 1.;", "age"));
+        assert!(!accesses_attribute("particle.age2 += 1.;", "age"));
+        assert!(!accesses_attribute("my_particle.age += 1.;", "age"));
+        assert!(!accesses_attribute("particle.lifetime += 1.;", "age"));
+    }
+
+    #[test]
+    fn apply() {
+        let modifier = CustomCodeModifier::new(
+            ModifierContext::Update,
+            "particle.age += my_fn_{{ID}}();",
+            [Attribute::AGE],
+        )
+        .with_extra_wgsl("fn my_fn_{{ID}}() -> f32 { return 1.0; }");
+        assert_eq!(modifier.attributes(), &[Attribute::AGE]);
+        assert!(modifier.as_render().is_none());
+
+        let mut module = Module::default();
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+
+        // Wrong context
+        let mut context =
+            ShaderWriter::new(ModifierContext::Init, &property_layout, &particle_layout);
+        assert!(matches!(
+            modifier.apply(&mut module, &mut context),
+            Err(ExprError::InvalidModifierContext(..))
+        ));
+
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        assert!(modifier.apply(&mut module, &mut context).is_ok());
+        assert_eq!(context.modifier_context(), ModifierContext::Update);
+        assert!(!context.main_code.contains("{{ID}}"));
+        assert!(!context.extra_code.contains("{{ID}}"));
+        let id = format!(
+            "{0:016X}",
+            calc_func_id(&(&modifier.wgsl, &modifier.extra_wgsl, &modifier.attributes))
+        );
+        assert!(context
+            .main_code
+            .contains(&format!("particle.age += my_fn_{}();", id)));
+        assert!(context
+            .extra_code
+            .contains(&format!("fn my_fn_{}() -> f32", id)));
+    }
+
+    #[test]
+    fn undeclared_attribute() {
+        let modifier = CustomCodeModifier::new(
+            ModifierContext::Init,
+            "particle.lifetime = 3.0;",
+            [Attribute::AGE],
+        );
+        let mut module = Module::default();
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Init, &property_layout, &particle_layout);
+        assert!(matches!(
+            modifier.apply(&mut module, &mut context),
+            Err(ExprError::GraphEvalError(..))
+        ));
+    }
+
+    #[test]
+    fn render() {
+        let modifier = CustomCodeModifier::new(
+            ModifierContext::Render,
+            "color.a *= 0.5;",
+            Vec::<Attribute>::new(),
+        );
+        assert!(modifier.as_render().is_some());
+
+        let mut module = Module::default();
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let mut context = RenderContext::new(&property_layout, &particle_layout);
+        modifier.apply_render(&mut module, &mut context).unwrap();
+        assert_eq!(context.vertex_code, "{\ncolor.a *= 0.5;\n}\n");
+
+        // Undeclared attribute
+        let modifier = CustomCodeModifier::new(
+            ModifierContext::Render,
+            "color.a *= particle.age;",
+            Vec::<Attribute>::new(),
+        );
+        let mut context = RenderContext::new(&property_layout, &particle_layout);
+        assert!(matches!(
+            modifier.apply_render(&mut module, &mut context),
+            Err(ExprError::GraphEvalError(_))
+        ));
+        assert!(context.vertex_code.is_empty());
+    }
+
+    #[test]
+    fn serde() {
+        let modifier = CustomCodeModifier::new(
+            ModifierContext::Init | ModifierContext::Update,
+            "particle.age = 0.0;",
+            [Attribute::AGE],
+        );
+        let s = ron::to_string(&modifier).unwrap();
+        let modifier_serde: CustomCodeModifier = ron::from_str(&s).unwrap();
+        assert_eq!(modifier, modifier_serde);
+    }
+}
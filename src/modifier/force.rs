@@ -201,7 +201,7 @@ impl Modifier for ConformToSphereModifier {
     // Distance and direction to origin (sphere center)
     let rel_pos = c - {attr_pos};
     let origin_dist = length(rel_pos);
-    let origin_dir = normalize(rel_pos);
+    let origin_dir = safe_normalize(rel_pos);
     // Signed distance to sphere surface, negative if inside sphere
     let surface_dist = origin_dist - r;
     // Influence distance
@@ -242,10 +242,16 @@ impl Modifier for ConformToSphereModifier {
 /// A modifier to apply a linear drag force to all particles each frame. The
 /// force slows down the particles without changing their direction.
 ///
+/// If [`respect_mass`] is enabled, the drag force is divided by the particle
+/// mass, such that heavier particles are slowed down less.
+///
 /// # Attributes
 ///
 /// This modifier requires the following particle attributes:
 /// - [`Attribute::VELOCITY`]
+/// - [`Attribute::MASS`] (only if [`respect_mass`] is enabled)
+///
+/// [`respect_mass`]: crate::modifier::force::LinearDragModifier::respect_mass
 #[derive(Debug, Clone, Copy, Reflect, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct LinearDragModifier {
     /// Drag coefficient. Higher values increase the drag force, and
@@ -253,19 +259,34 @@ pub struct LinearDragModifier {
     ///
     /// Expression type: `f32`
     pub drag: ExprHandle,
+    /// Divide the drag force by the particle mass. Defaults to `false`.
+    ///
+    /// Particles with a zero mass are not slowed down.
+    #[serde(default)]
+    pub respect_mass: bool,
 }
 
 impl LinearDragModifier {
     /// Create a new modifier from a drag expression.
     pub fn new(drag: ExprHandle) -> Self {
-        Self { drag }
+        Self {
+            drag,
+            respect_mass: false,
+        }
     }
 
     /// Instantiate a [`LinearDragModifier`] with a constant drag value.
     pub fn constant(module: &mut Module, drag: f32) -> Self {
-        Self {
-            drag: module.lit(drag),
-        }
+        Self::new(module.lit(drag))
+    }
+
+    /// Set whether the drag force is divided by the particle mass.
+    ///
+    /// When enabled, the modifier requires the [`Attribute::MASS`] attribute,
+    /// and heavier particles are slowed down less. Defaults to `false`.
+    pub fn with_respect_mass(mut self, respect_mass: bool) -> Self {
+        self.respect_mass = respect_mass;
+        self
     }
 }
 
@@ -276,7 +297,11 @@ impl Modifier for LinearDragModifier {
     }
 
     fn attributes(&self) -> &[Attribute] {
-        &[Attribute::VELOCITY]
+        if self.respect_mass {
+            &[Attribute::VELOCITY, Attribute::MASS]
+        } else {
+            &[Attribute::VELOCITY]
+        }
     }
 
     fn boxed_clone(&self) -> BoxedModifier {
@@ -288,20 +313,349 @@ impl Modifier for LinearDragModifier {
         let attr = m.attr(Attribute::VELOCITY);
         let dt = m.builtin(BuiltInOperator::DeltaTime);
         let drag_dt = m.mul(self.drag, dt);
-        let one = m.lit(1.);
-        let one_minus_drag_dt = m.sub(one, drag_dt);
-        let zero = m.lit(0.);
-        let expr = m.max(zero, one_minus_drag_dt);
         let attr = context.eval(m, attr)?;
-        let expr = context.eval(m, expr)?;
-        context.main_code += &format!("{} *= {};", attr, expr);
+        let mut drag_dt = context.eval(m, drag_dt)?;
+        if self.respect_mass {
+            // Guard against a zero mass, which would produce an infinite or NaN
+            // drag factor and propagate into the velocity.
+            let mass = m.attr(Attribute::MASS);
+            let mass = context.eval(m, mass)?;
+            drag_dt = format!("safe_div({}, {})", drag_dt, mass);
+        }
+        context.main_code += &format!("{} *= max(0., 1. - {});", attr, drag_dt);
+        Ok(())
+    }
+}
+
+/// A modifier to clamp the speed of all particles each frame.
+///
+/// This clamps the magnitude of the [`Attribute::VELOCITY`] of each particle
+/// to the `[min_speed:max_speed]` range, without changing its direction. It's
+/// typically added after any force modifier, to prevent runaway accelerations
+/// from stacked forces, and prevent slow particles from freezing in place.
+///
+/// A particle at rest has no direction, so cannot be accelerated to
+/// `min_speed`; its velocity is left unchanged (zero).
+///
+/// # Example
+///
+/// ```
+/// # use bevy_hanabi::*;
+/// let writer = ExprWriter::new();
+/// let update_clamp =
+///     ClampVelocityModifier::new(writer.lit(0.5).expr(), writer.lit(10.).expr());
+/// ```
+///
+/// # Attributes
+///
+/// This modifier requires the following particle attributes:
+/// - [`Attribute::VELOCITY`]
+#[derive(Debug, Clone, Copy, Reflect, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ClampVelocityModifier {
+    /// Minimum speed of the particles, in units per second.
+    ///
+    /// Expression type: `f32`
+    pub min_speed: ExprHandle,
+    /// Maximum speed of the particles, in units per second.
+    ///
+    /// Expression type: `f32`
+    pub max_speed: ExprHandle,
+}
+
+impl ClampVelocityModifier {
+    /// Create a new modifier from the minimum and maximum speed expressions.
+    pub fn new(min_speed: ExprHandle, max_speed: ExprHandle) -> Self {
+        Self {
+            min_speed,
+            max_speed,
+        }
+    }
+
+    /// Instantiate a [`ClampVelocityModifier`] with constant minimum and maximum
+    /// speeds.
+    pub fn constant(module: &mut Module, min_speed: f32, max_speed: f32) -> Self {
+        Self::new(module.lit(min_speed), module.lit(max_speed))
+    }
+
+    /// Instantiate a [`ClampVelocityModifier`] only limiting the maximum speed
+    /// of the particles.
+    pub fn max_speed(module: &mut Module, max_speed: ExprHandle) -> Self {
+        Self::new(module.lit(0.), max_speed)
+    }
+}
+
+#[typetag::serde]
+impl Modifier for ClampVelocityModifier {
+    fn context(&self) -> ModifierContext {
+        ModifierContext::Update
+    }
+
+    fn attributes(&self) -> &[Attribute] {
+        &[Attribute::VELOCITY]
+    }
+
+    fn boxed_clone(&self) -> BoxedModifier {
+        Box::new(*self)
+    }
+
+    fn apply(&self, module: &mut Module, context: &mut ShaderWriter) -> Result<(), ExprError> {
+        let attr = module.attr(Attribute::VELOCITY);
+        let attr = context.eval(module, attr)?;
+        let min_speed = context.eval(module, self.min_speed)?;
+        let max_speed = context.eval(module, self.max_speed)?;
+        // Skip particles at rest to avoid a division by zero. Scope the local
+        // variable to allow multiple instances of this modifier.
+        context.main_code += &format!(
+            r##"{{
+    let speed = length({attr});
+    if (speed > 0.0) {{
+        {attr} *= clamp(speed, {min_speed}, {max_speed}) / speed;
+    }}
+}}
+"##
+        );
+        Ok(())
+    }
+}
+
+/// A modifier pulling particles toward a target position with a spring-damper
+/// force.
+///
+/// Each frame, the particle is accelerated toward the [`target`] position
+/// proportionally to its distance to that target, scaled by the
+/// [`stiffness`], while its velocity is reduced by the [`damping`]:
+///
+/// ```txt
+/// accel = stiffness * (target - particle.position) - damping * particle.velocity;
+/// ```
+///
+/// Unlike a static attractor, the target is generally bound to a property
+/// updated each frame from the CPU with [`EffectProperties::set()`], so that
+/// particles chase a moving object with a smooth lag. This is useful to make
+/// particles trail a cursor, or for homing effects.
+///
+/// The damping ratio determines how the particles converge. A damping of
+/// `2 * sqrt(stiffness)`, called critical damping, makes particles converge as
+/// fast as possible without overshooting the target; lower values make them
+/// oscillate around the target, while higher values make them converge more
+/// slowly. [`critically_damped()`] creates a modifier with such damping. The
+/// force is integrated implicitly, so the simulation stays stable even with
+/// a high stiffness or a large time step.
+///
+/// # Example
+///
+/// ```
+/// # use bevy_hanabi::*;
+/// let mut module = Module::default();
+/// let target = module.prop("target");
+/// let update_follow = SpringFollowModifier::critically_damped(&mut module, target, 20.);
+/// ```
+///
+/// # Attributes
+///
+/// This modifier requires the following particle attributes:
+/// - [`Attribute::POSITION`]
+/// - [`Attribute::VELOCITY`]
+///
+/// [`target`]: crate::modifier::force::SpringFollowModifier::target
+/// [`stiffness`]: crate::modifier::force::SpringFollowModifier::stiffness
+/// [`damping`]: crate::modifier::force::SpringFollowModifier::damping
+/// [`critically_damped()`]: crate::modifier::force::SpringFollowModifier::critically_damped
+/// [`EffectProperties::set()`]: crate::EffectProperties::set
+#[derive(Debug, Clone, Copy, Reflect, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct SpringFollowModifier {
+    /// The position the particles are pulled toward, in simulation space.
+    ///
+    /// Expression type: `Vec3`
+    pub target: ExprHandle,
+    /// Stiffness of the spring, in inverse squared seconds. Higher values pull
+    /// the particles more strongly toward the target.
+    ///
+    /// Expression type: `f32`
+    pub stiffness: ExprHandle,
+    /// Damping coefficient, in inverse seconds.
+    ///
+    /// Expression type: `f32`
+    pub damping: ExprHandle,
+}
+
+impl SpringFollowModifier {
+    /// Create a new modifier from the target, stiffness, and damping
+    /// expressions.
+    pub fn new(target: ExprHandle, stiffness: ExprHandle, damping: ExprHandle) -> Self {
+        Self {
+            target,
+            stiffness,
+            damping,
+        }
+    }
+
+    /// Create a new modifier with a constant stiffness and the corresponding
+    /// critical damping `2 * sqrt(stiffness)`.
+    pub fn critically_damped(module: &mut Module, target: ExprHandle, stiffness: f32) -> Self {
+        let damping = 2. * stiffness.max(0.).sqrt();
+        Self::new(target, module.lit(stiffness), module.lit(damping))
+    }
+}
+
+#[typetag::serde]
+impl Modifier for SpringFollowModifier {
+    fn context(&self) -> ModifierContext {
+        ModifierContext::Update
+    }
+
+    fn attributes(&self) -> &[Attribute] {
+        &[Attribute::POSITION, Attribute::VELOCITY]
+    }
+
+    fn boxed_clone(&self) -> BoxedModifier {
+        Box::new(*self)
+    }
+
+    fn apply(&self, module: &mut Module, context: &mut ShaderWriter) -> Result<(), ExprError> {
+        let target = context.eval(module, self.target)?;
+        let stiffness = context.eval(module, self.stiffness)?;
+        let damping = context.eval(module, self.damping)?;
+        // Implicit Euler integration of the spring-damper, solved for the new
+        // velocity, which is unconditionally stable. Scope the local variables
+        // to allow multiple instances of this modifier.
+        context.main_code += &format!(
+            r##"{{
+    let dt = sim_params.delta_time;
+    let stiffness = max({stiffness}, 0.);
+    let damping = max({damping}, 0.);
+    let offset = ({target}) - particle.{pos};
+    particle.{vel} = (particle.{vel} + dt * stiffness * offset) / (1. + dt * damping + dt * dt * stiffness);
+}}
+"##,
+            pos = Attribute::POSITION.name(),
+            vel = Attribute::VELOCITY.name(),
+        );
+        Ok(())
+    }
+}
+
+/// A modifier scaling the flow of time for the particles inside a spherical
+/// region.
+///
+/// This multiplies the simulation delta time of each particle by a factor
+/// depending on its distance to the [`center`] of the region. Particles inside
+/// the sphere of the given [`radius`] are simulated with their delta time
+/// scaled by [`scale`], while particles outside are unaffected. A [`scale`]
+/// less than `1.0` slows down the particles, for example to create a stasis
+/// field or a bullet-time zone, while a value greater than `1.0` speeds them
+/// up. The optional [`falloff`] distance smoothly blends the scale toward
+/// `1.0` outside the sphere, to avoid an abrupt change at its boundary.
+///
+/// The scaled delta time applies to everything the update pass simulates for
+/// the particle: its aging, the motion integration, and all other update
+/// modifiers, whatever their order relative to this modifier. The particle
+/// position used to determine the scale is the one at the beginning of the
+/// frame. Multiple instances of this modifier compound their scale.
+///
+/// # Attributes
+///
+/// This modifier requires the following particle attributes:
+/// - [`Attribute::POSITION`]
+///
+/// [`center`]: crate::modifier::force::TimeDilationModifier::center
+/// [`radius`]: crate::modifier::force::TimeDilationModifier::radius
+/// [`scale`]: crate::modifier::force::TimeDilationModifier::scale
+/// [`falloff`]: crate::modifier::force::TimeDilationModifier::falloff
+#[derive(Debug, Clone, Copy, Reflect, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct TimeDilationModifier {
+    /// The center of the region, in simulation space.
+    ///
+    /// Expression type: `Vec3`
+    pub center: ExprHandle,
+    /// The radius of the region.
+    ///
+    /// Expression type: `f32`
+    pub radius: ExprHandle,
+    /// The scale applied to the delta time of particles inside the region.
+    ///
+    /// Expression type: `f32`
+    pub scale: ExprHandle,
+    /// Optional distance outside the region over which the scale smoothly
+    /// returns to `1.0`. If `None`, the scale changes abruptly at the boundary
+    /// of the region.
+    ///
+    /// Expression type: `f32`
+    #[serde(default)]
+    pub falloff: Option<ExprHandle>,
+}
+
+impl TimeDilationModifier {
+    /// Create a new modifier from its center, radius, and scale expressions.
+    pub fn new(center: ExprHandle, radius: ExprHandle, scale: ExprHandle) -> Self {
+        Self {
+            center,
+            radius,
+            scale,
+            falloff: None,
+        }
+    }
+
+    /// Set the distance over which the scale smoothly returns to `1.0` outside
+    /// the region.
+    pub fn with_falloff(mut self, falloff: ExprHandle) -> Self {
+        self.falloff = Some(falloff);
+        self
+    }
+}
+
+#[typetag::serde]
+impl Modifier for TimeDilationModifier {
+    fn context(&self) -> ModifierContext {
+        ModifierContext::Update
+    }
+
+    fn attributes(&self) -> &[Attribute] {
+        &[Attribute::POSITION]
+    }
+
+    fn boxed_clone(&self) -> BoxedModifier {
+        Box::new(*self)
+    }
+
+    fn apply(&self, module: &mut Module, context: &mut ShaderWriter) -> Result<(), ExprError> {
+        let func_id = calc_func_id(self);
+        let func_name = format!("time_dilation_{0:016X}", func_id);
+
+        context.make_fn(
+            &func_name,
+            "particle: ptr<function, Particle>",
+            module,
+            &mut |m: &mut Module, ctx: &mut dyn EvalContext| -> Result<String, ExprError> {
+                let pos = m.attr(Attribute::POSITION);
+                let pos = ctx.eval(m, pos)?;
+                let center = ctx.eval(m, self.center)?;
+                let radius = ctx.eval(m, self.radius)?;
+                let scale = ctx.eval(m, self.scale)?;
+                let blend = if let Some(falloff) = self.falloff {
+                    let falloff = ctx.eval(m, falloff)?;
+                    format!("smoothstep(radius, radius + max({falloff}, 0.0001), dist)")
+                } else {
+                    "select(1.0, 0.0, dist < radius)".to_string()
+                };
+                Ok(format!(
+                    r##"    let dist = distance({pos}, {center});
+    let radius = {radius};
+    sim_params.delta_time *= mix({scale}, 1.0, {blend});
+"##
+                ))
+            },
+        )?;
+
+        // Emitted before any other update code, see ShaderWriter::delta_time_code
+        context.delta_time_code += &format!("{}(&particle);\n    ", func_name);
         Ok(())
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{ParticleLayout, PropertyLayout};
+    use crate::{ParticleLayout, Property, PropertyLayout};
 
     use super::*;
 
@@ -317,5 +671,114 @@ mod tests {
         assert!(modifier.apply(&mut module, &mut context).is_ok());
 
         assert!(context.main_code.contains("3.5")); // TODO - less weak check
+        assert!(!context.main_code.contains(Attribute::MASS.name()));
+
+        let modifier = modifier.with_respect_mass(true);
+        assert!(modifier.attributes().contains(&Attribute::MASS));
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        assert!(modifier.apply(&mut module, &mut context).is_ok());
+        assert!(context.main_code.contains("safe_div("));
+        assert!(context.main_code.contains("particle.mass"));
+    }
+
+    #[test]
+    fn mod_clamp_velocity() {
+        let mut module = Module::default();
+        let modifier = ClampVelocityModifier::constant(&mut module, 0.5, 10.);
+        assert_eq!(modifier.context(), ModifierContext::Update);
+        assert_eq!(modifier.attributes(), &[Attribute::VELOCITY]);
+
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        assert!(modifier.apply(&mut module, &mut context).is_ok());
+
+        assert!(context
+            .main_code
+            .contains("let speed = length(particle.velocity);"));
+        assert!(context.main_code.contains("if (speed > 0.0)"));
+        assert!(context
+            .main_code
+            .contains("particle.velocity *= clamp(speed, 0.5, 10.) / speed;"));
+
+        // Max speed only
+        let max_speed = module.lit(4.);
+        let modifier = ClampVelocityModifier::max_speed(&mut module, max_speed);
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        assert!(modifier.apply(&mut module, &mut context).is_ok());
+        assert!(context.main_code.contains("clamp(speed, 0., 4.)"));
+    }
+
+    #[test]
+    fn mod_spring_follow() {
+        let mut module = Module::default();
+        let target = module.prop("target");
+        let modifier = SpringFollowModifier::critically_damped(&mut module, target, 16.);
+        assert_eq!(modifier.context(), ModifierContext::Update);
+        assert!(modifier.attributes().contains(&Attribute::POSITION));
+        assert!(modifier.attributes().contains(&Attribute::VELOCITY));
+        assert_eq!(modifier.target, target);
+
+        let property_layout = PropertyLayout::new(&[Property::new("target", Vec3::ZERO)]);
+        let particle_layout = ParticleLayout::default();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        assert!(modifier.apply(&mut module, &mut context).is_ok());
+
+        assert!(context.main_code.contains("let stiffness = max(16., 0.);"));
+        assert!(context.main_code.contains("let damping = max(8., 0.);"));
+        assert!(context
+            .main_code
+            .contains("let offset = (properties.target) - particle.position;"));
+        assert!(context.main_code.contains(
+            "particle.velocity = (particle.velocity + dt * stiffness * offset) / (1. + dt * damping + dt * dt * stiffness);"
+        ));
+    }
+
+    #[test]
+    fn mod_time_dilation() {
+        let mut module = Module::default();
+        let center = module.lit(Vec3::ZERO);
+        let radius = module.lit(2.);
+        let scale = module.lit(0.25);
+        let modifier = TimeDilationModifier::new(center, radius, scale);
+        assert_eq!(modifier.context(), ModifierContext::Update);
+        assert_eq!(modifier.attributes(), &[Attribute::POSITION]);
+
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::new().append(Attribute::POSITION).build();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        assert!(modifier.apply(&mut module, &mut context).is_ok());
+
+        // The delta time is scaled ahead of any other update code
+        let func_name = format!("time_dilation_{0:016X}", calc_func_id(&modifier));
+        assert!(context.main_code.is_empty());
+        assert!(context
+            .delta_time_code
+            .contains(&format!("{}(&particle);", func_name)));
+        assert!(context.extra_code.contains(&format!(
+            "fn {}(particle: ptr<function, Particle>)",
+            func_name
+        )));
+        assert!(context
+            .extra_code
+            .contains("let dist = distance((*particle).position, vec3<f32>(0.,0.,0.));"));
+        assert!(context
+            .extra_code
+            .contains("sim_params.delta_time *= mix(0.25, 1.0, select(1.0, 0.0, dist < radius));"));
+
+        // Smooth falloff
+        let falloff = module.lit(0.5);
+        let modifier = modifier.with_falloff(falloff);
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        assert!(modifier.apply(&mut module, &mut context).is_ok());
+        assert!(context
+            .extra_code
+            .contains("smoothstep(radius, radius + max(0.5, 0.0001), dist)"));
     }
 }
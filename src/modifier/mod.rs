@@ -29,7 +29,7 @@ use bevy::{
     math::{UVec2, Vec2, Vec4},
     reflect::Reflect,
     render::texture::Image,
-    utils::HashMap,
+    utils::{HashMap, HashSet},
 };
 use bitflags::bitflags;
 use serde::{Deserialize, Serialize};
@@ -41,6 +41,9 @@ use std::{
 pub mod accel;
 pub mod attr;
 pub mod clone;
+pub mod collision;
+pub mod conditional;
+pub mod custom;
 pub mod force;
 pub mod kill;
 pub mod output;
@@ -50,6 +53,9 @@ pub mod velocity;
 pub use accel::*;
 pub use attr::*;
 pub use clone::*;
+pub use collision::*;
+pub use conditional::*;
+pub use custom::*;
 pub use force::*;
 pub use kill::*;
 pub use output::*;
@@ -57,7 +63,8 @@ pub use position::*;
 pub use velocity::*;
 
 use crate::{
-    Attribute, EvalContext, ExprError, ExprHandle, Gradient, Module, ParticleLayout, PropertyLayout,
+    Attribute, ColorSpace, Easing, EvalContext, ExprError, ExprHandle, Gradient, Module,
+    ParticleLayout, PropertyLayout,
 };
 
 /// The dimension of a shape to consider.
@@ -80,9 +87,28 @@ pub(crate) fn calc_func_id<T: Hash>(value: &T) -> u64 {
     hasher.finish()
 }
 
+/// Emit the WGSL helper function rotating a `vec3<f32>` by a quaternion, and
+/// return its name.
+///
+/// The quaternion is stored as a `vec4<f32>` with the (x, y, z, w) layout of
+/// [`Quat`], and must be normalized.
+///
+/// [`Quat`]: bevy::math::Quat
+pub(crate) fn make_quat_rotate_fn(context: &mut dyn EvalContext) -> &'static str {
+    context.make_helper_fn(
+        "quat_rotate",
+        r##"fn quat_rotate(q: vec4<f32>, v: vec3<f32>) -> vec3<f32> {
+    let t = 2.0 * cross(q.xyz, v);
+    return v + q.w * t + cross(q.xyz, t);
+}
+"##,
+    );
+    "quat_rotate"
+}
+
 bitflags! {
     /// Context a modifier applies to.
-    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
     pub struct ModifierContext : u8 {
         /// Particle initializing on spawning.
         ///
@@ -102,6 +128,12 @@ bitflags! {
     }
 }
 
+impl Default for ModifierContext {
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
 impl std::fmt::Display for ModifierContext {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let mut s = if self.contains(ModifierContext::Init) {
@@ -216,6 +248,14 @@ pub struct GroupedModifier {
     pub modifier: BoxedModifier,
     /// The set of groups that this modifier affects.
     pub groups: ParticleGroupSet,
+    /// Sort key controlling the order in which modifiers of a same context are
+    /// applied.
+    ///
+    /// Modifiers are applied in ascending order of this key. Modifiers with an
+    /// equal key are applied in the order they were added to the effect.
+    /// Defaults to zero.
+    #[serde(default)]
+    pub order: i32,
 }
 
 impl GroupedModifier {
@@ -246,10 +286,19 @@ pub struct ShaderWriter<'a> {
     /// This contains optional WGSL code emitted at shader top level. This
     /// generally contains functions called from `main_code`.
     pub extra_code: String,
+    /// Code scaling the per-particle simulation delta time.
+    ///
+    /// This is only used in the [`ModifierContext::Update`] context. The code
+    /// is emitted before any other update code, including the particle aging
+    /// and the motion integration, and can modify `sim_params.delta_time` to
+    /// make time flow at a different rate for each particle.
+    pub delta_time_code: String,
     /// Layout of properties for the current effect.
     pub property_layout: &'a PropertyLayout,
     /// Layout of attributes of a particle for the current effect.
     pub particle_layout: &'a ParticleLayout,
+    /// Heightfield texture sampled by the update shader, if any.
+    pub heightfield_texture: Option<Handle<Image>>,
     /// Modifier context the writer is being used from.
     modifier_context: ModifierContext,
     /// Counter for unique variable names.
@@ -258,6 +307,8 @@ pub struct ShaderWriter<'a> {
     expr_cache: HashMap<ExprHandle, String>,
     /// Is the attribute struct a pointer?
     is_attribute_pointer: bool,
+    /// Names of the helper functions already emitted.
+    helper_fns: HashSet<String>,
 }
 
 impl<'a> ShaderWriter<'a> {
@@ -270,12 +321,15 @@ impl<'a> ShaderWriter<'a> {
         Self {
             main_code: String::new(),
             extra_code: String::new(),
+            delta_time_code: String::new(),
             property_layout,
             particle_layout,
+            heightfield_texture: None,
             modifier_context,
             var_counter: 0,
             expr_cache: Default::default(),
             is_attribute_pointer: false,
+            helper_fns: Default::default(),
         }
     }
 
@@ -284,6 +338,77 @@ impl<'a> ShaderWriter<'a> {
         self.is_attribute_pointer = true;
         self
     }
+
+    /// Create a nested writer for a block of code.
+    ///
+    /// Expressions evaluated by the nested writer are cached separately from
+    /// the ones of this writer, so that any local variable declared inside the
+    /// block is never referenced from outside it.
+    fn nested(&self) -> ShaderWriter<'a> {
+        ShaderWriter {
+            main_code: String::new(),
+            extra_code: String::new(),
+            delta_time_code: String::new(),
+            property_layout: self.property_layout,
+            particle_layout: self.particle_layout,
+            heightfield_texture: self.heightfield_texture.clone(),
+            modifier_context: self.modifier_context,
+            // Continue numbering to prevent shadowing any variable of the outer scope
+            var_counter: self.var_counter,
+            expr_cache: Default::default(),
+            is_attribute_pointer: self.is_attribute_pointer,
+            helper_fns: self.helper_fns.clone(),
+        }
+    }
+
+    /// Merge back the state of a nested writer created by [`nested()`].
+    ///
+    /// The extra code of the nested writer, like function definitions, is
+    /// emitted at shader top level as usual.
+    ///
+    /// [`nested()`]: ShaderWriter::nested
+    fn merge_nested(&mut self, nested: &ShaderWriter<'a>) {
+        self.var_counter = nested.var_counter;
+        self.helper_fns.clone_from(&nested.helper_fns);
+        self.heightfield_texture
+            .clone_from(&nested.heightfield_texture);
+        self.extra_code += &nested.extra_code;
+    }
+
+    /// Emit a block of code executed only if a condition holds.
+    ///
+    /// The main code generated by `f` is wrapped into an `if` block testing the
+    /// scalar boolean `condition`. Any delta time code generated by `f` is
+    /// wrapped the same way, evaluating the condition a second time, since the
+    /// delta time code is emitted before the main code where the condition is
+    /// first evaluated.
+    pub(crate) fn emit_conditional_block(
+        &mut self,
+        condition: ExprHandle,
+        module: &mut Module,
+        f: impl FnOnce(&mut Module, &mut ShaderWriter<'a>) -> Result<(), ExprError>,
+    ) -> Result<(), ExprError> {
+        let cond = self.eval(module, condition)?;
+        let var = self.make_local_var();
+        self.main_code += &format!("let {}: bool = {};\n", var, cond);
+
+        let mut nested = self.nested();
+        f(module, &mut nested)?;
+        self.merge_nested(&nested);
+        self.main_code += &format!("if ({}) {{\n{}}}\n", var, nested.main_code);
+
+        if !nested.delta_time_code.is_empty() {
+            let mut pre = self.nested();
+            let cond = pre.eval(module, condition)?;
+            let var = pre.make_local_var();
+            self.merge_nested(&pre);
+            self.delta_time_code += &format!(
+                "{{\n{}let {}: bool = {};\nif ({}) {{\n{}}}\n}}\n    ",
+                pre.main_code, var, cond, var, nested.delta_time_code
+            );
+        }
+        Ok(())
+    }
 }
 
 impl<'a> EvalContext for ShaderWriter<'a> {
@@ -330,6 +455,11 @@ impl<'a> EvalContext for ShaderWriter<'a> {
         module: &mut Module,
         f: &mut dyn FnMut(&mut Module, &mut dyn EvalContext) -> Result<String, ExprError>,
     ) -> Result<(), ExprError> {
+        // Reuse any function already emitted, for example by an identical modifier
+        if self.helper_fns.contains(func_name) {
+            return Ok(());
+        }
+
         // Generate a temporary context for the function content itself
         // FIXME - Dynamic with_attribute_pointer()!
         let mut ctx = ShaderWriter::new(
@@ -338,12 +468,15 @@ impl<'a> EvalContext for ShaderWriter<'a> {
             self.particle_layout,
         )
         .with_attribute_pointer();
+        ctx.helper_fns = self.helper_fns.clone();
 
         // Evaluate the function content
         let body = f(module, &mut ctx)?;
 
         // Append any extra
         self.extra_code += &ctx.extra_code;
+        self.helper_fns = ctx.helper_fns;
+        self.helper_fns.insert(func_name.to_string());
 
         // Append the function itself
         self.extra_code += &format!(
@@ -355,6 +488,12 @@ impl<'a> EvalContext for ShaderWriter<'a> {
         Ok(())
     }
 
+    fn make_helper_fn(&mut self, func_name: &str, code: &str) {
+        if self.helper_fns.insert(func_name.to_string()) {
+            self.extra_code += code;
+        }
+    }
+
     fn is_attribute_pointer(&self) -> bool {
         self.is_attribute_pointer
     }
@@ -379,18 +518,42 @@ pub struct RenderContext<'a> {
     /// WGSL code describing how to modulate the base color of the particle with
     /// the image texture sample, if any.
     pub image_sample_mapping_code: String,
+    /// WGSL expression of the UV coordinates the image texture is sampled at,
+    /// if not the default per-quad UV coordinates.
+    pub image_sample_uv_code: String,
+    /// WGSL code for the vertex shader modifying the per-quad `uv` coordinates
+    /// of the particle, before any flipbook sprite is selected.
+    pub uv_code: String,
+    /// Erosion texture used to dissolve particles, if any.
+    pub dissolve_texture: Option<Handle<Image>>,
+    /// WGSL code for the fragment shader dissolving the particle with the
+    /// erosion texture, if any.
+    pub dissolve_code: String,
+    /// Scene color texture refracted behind the particles, if any.
+    pub refraction_texture: Option<Handle<Image>>,
+    /// WGSL code for the fragment shader refracting the scene color texture,
+    /// if any.
+    pub refraction_code: String,
+    /// WGSL code for the fragment shader defining the world-space `normal` of
+    /// the particle, if any.
+    pub normal_code: String,
     /// Flipbook sprite sheet grid size, if any.
     pub sprite_grid_size: Option<UVec2>,
     /// Color gradients.
     pub gradients: HashMap<u64, Gradient<Vec4>>,
     /// Size gradients.
     pub size_gradients: HashMap<u64, Gradient<Vec2>>,
+    /// Color space of the constant colors of the render modifiers, which are
+    /// converted to linear RGB when emitted into the shader code.
+    pub color_space: ColorSpace,
     /// Counter for unique variable names.
     var_counter: u32,
     /// Cache of evaluated expressions.
     expr_cache: HashMap<ExprHandle, String>,
     /// Is the attriubute struct a pointer?
     is_attribute_pointer: bool,
+    /// Names of the helper functions already emitted.
+    helper_fns: HashSet<String>,
 }
 
 impl<'a> RenderContext<'a> {
@@ -404,12 +567,21 @@ impl<'a> RenderContext<'a> {
             render_extra: String::new(),
             particle_texture: None,
             image_sample_mapping_code: String::new(),
+            image_sample_uv_code: String::new(),
+            uv_code: String::new(),
+            dissolve_texture: None,
+            dissolve_code: String::new(),
+            refraction_texture: None,
+            refraction_code: String::new(),
+            normal_code: String::new(),
             sprite_grid_size: None,
             gradients: HashMap::new(),
             size_gradients: HashMap::new(),
+            color_space: ColorSpace::Linear,
             var_counter: 0,
             expr_cache: Default::default(),
             is_attribute_pointer: false,
+            helper_fns: Default::default(),
         }
     }
 
@@ -425,6 +597,9 @@ impl<'a> RenderContext<'a> {
     /// Returns the unique name of the gradient, to be used as function name in
     /// the shader code.
     fn add_color_gradient(&mut self, gradient: Gradient<Vec4>) -> String {
+        if gradient.easing() != Easing::Linear {
+            self.add_easing(gradient.easing());
+        }
         let func_id = calc_func_id(&gradient);
         self.gradients.insert(func_id, gradient);
         let func_name = format!("color_gradient_{0:016X}", func_id);
@@ -438,12 +613,26 @@ impl<'a> RenderContext<'a> {
     /// Returns the unique name of the gradient, to be used as function name in
     /// the shader code.
     fn add_size_gradient(&mut self, gradient: Gradient<Vec2>) -> String {
+        if gradient.easing() != Easing::Linear {
+            self.add_easing(gradient.easing());
+        }
         let func_id = calc_func_id(&gradient);
         self.size_gradients.insert(func_id, gradient);
         let func_name = format!("size_gradient_{0:016X}", func_id);
         func_name
     }
 
+    /// Add the helper function implementing an easing.
+    ///
+    /// # Returns
+    ///
+    /// Returns the name of the helper function, to be used in the shader code.
+    fn add_easing(&mut self, easing: Easing) -> &'static str {
+        let func_name = easing.wgsl_fn_name();
+        self.make_helper_fn(func_name, &easing.wgsl_fn_code());
+        func_name
+    }
+
     /// Mark the attribute struct as being available through a pointer.
     pub fn with_attribute_pointer(mut self) -> Self {
         self.is_attribute_pointer = true;
@@ -496,16 +685,24 @@ impl<'a> EvalContext for RenderContext<'a> {
         module: &mut Module,
         f: &mut dyn FnMut(&mut Module, &mut dyn EvalContext) -> Result<String, ExprError>,
     ) -> Result<(), ExprError> {
+        // Reuse any function already emitted, for example by an identical modifier
+        if self.helper_fns.contains(func_name) {
+            return Ok(());
+        }
+
         // Generate a temporary context for the function content itself
         // FIXME - Dynamic with_attribute_pointer()!
         let mut ctx =
             RenderContext::new(self.property_layout, self.particle_layout).with_attribute_pointer();
+        ctx.helper_fns = self.helper_fns.clone();
 
         // Evaluate the function content
         let body = f(module, &mut ctx)?;
 
         // Append any extra
         self.render_extra += &ctx.render_extra;
+        self.helper_fns = ctx.helper_fns;
+        self.helper_fns.insert(func_name.to_string());
 
         // Append the function itself
         self.render_extra += &format!(
@@ -519,6 +716,12 @@ impl<'a> EvalContext for RenderContext<'a> {
         Ok(())
     }
 
+    fn make_helper_fn(&mut self, func_name: &str, code: &str) {
+        if self.helper_fns.insert(func_name.to_string()) {
+            self.render_extra += code;
+        }
+    }
+
     fn is_attribute_pointer(&self) -> bool {
         self.is_attribute_pointer
     }
@@ -528,7 +731,14 @@ impl<'a> EvalContext for RenderContext<'a> {
 #[typetag::serde]
 pub trait RenderModifier: Modifier {
     /// Apply the rendering code.
-    fn apply_render(&self, module: &mut Module, context: &mut RenderContext);
+    ///
+    /// Returns an error if the code cannot be generated, for example because
+    /// an expression of the modifier fails to evaluate.
+    fn apply_render(
+        &self,
+        module: &mut Module,
+        context: &mut RenderContext,
+    ) -> Result<(), ExprError>;
 
     /// Clone into boxed self.
     fn boxed_render_clone(&self) -> Box<dyn RenderModifier>;
@@ -593,6 +803,18 @@ mod tests {
 
     use super::*;
 
+    /// Copy of the safe math helpers of `vfx_common.wgsl`, which generated code
+    /// can call.
+    const SAFE_MATH_CODE: &str = r#"fn safe_div(a: f32, b: f32) -> f32 {
+    return select(a / b, 0., b == 0.);
+}
+
+fn safe_normalize(v: vec3<f32>) -> vec3<f32> {
+    let len_sq = dot(v, v);
+    return select(vec3<f32>(0.), v * inverseSqrt(len_sq), len_sq > 1e-12);
+}
+"#;
+
     fn make_test_modifier() -> SetPositionSphereModifier {
         // We use a dummy module here because we don't care about the values and won't
         // evaluate the modifier.
@@ -601,6 +823,7 @@ mod tests {
             center: m.lit(Vec3::ZERO),
             radius: m.lit(1.),
             dimension: ShapeDimension::Surface,
+            thickness: None,
         }
     }
 
@@ -665,30 +888,147 @@ mod tests {
         assert_eq!(*rm_reflect, m);
     }
 
+    #[test]
+    fn path() {
+        let path = SetPositionPathModifier::new([Vec3::ZERO, Vec3::X * 3., Vec3::new(3., 1., 0.)]);
+        let (points, lengths) = path.polyline();
+        assert_eq!(points.len(), 3);
+        assert_eq!(lengths, vec![0., 0.75, 1.]);
+
+        // Closing the path adds a segment back to the start
+        let (points, lengths) = path.clone().with_closed(true).polyline();
+        assert_eq!(points.len(), 4);
+        assert_eq!(points[3], Vec3::ZERO);
+        assert_eq!(lengths[0], 0.);
+        assert_eq!(lengths[3], 1.);
+
+        // The spline passes through the control points
+        let control_points = [Vec3::ZERO, Vec3::X, Vec3::new(1., 1., 0.)];
+        let spline = SetPositionPathModifier::from_catmull_rom(&control_points, 4, false);
+        assert_eq!(spline.points.len(), 9);
+        assert!(!spline.closed);
+        assert_eq!(spline.points[0], control_points[0]);
+        assert_eq!(spline.points[4], control_points[1]);
+        assert_eq!(spline.points[8], control_points[2]);
+        let spline = SetPositionPathModifier::from_catmull_rom(&control_points, 4, true);
+        assert_eq!(spline.points.len(), 12);
+        assert!(spline.closed);
+
+        // Sequential sampling reads the spawn index
+        let mut module = Module::default();
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Init, &property_layout, &particle_layout);
+        let sequential = path.clone().with_sampling(PathSampling::Sequential);
+        assert!(sequential.apply(&mut module, &mut context).is_ok());
+        assert!(context.main_code.contains("global_invocation_id.x"));
+
+        // Not enough points
+        let mut context =
+            ShaderWriter::new(ModifierContext::Init, &property_layout, &particle_layout);
+        let invalid = SetPositionPathModifier::new([Vec3::ZERO]);
+        assert!(matches!(
+            invalid.apply(&mut module, &mut context),
+            Err(ExprError::GraphEvalError(_))
+        ));
+    }
+
+    #[test]
+    fn camera_facing_disc() {
+        let mut module = Module::default();
+        let disc = SetPositionCameraFacingDiscModifier {
+            center: module.lit(Vec3::ZERO),
+            radius: module.lit(0.5),
+            speed: module.lit(3.),
+            dimension: ShapeDimension::Volume,
+        };
+        assert_eq!(disc.context(), ModifierContext::Init);
+        assert_eq!(
+            disc.attributes(),
+            &[Attribute::POSITION, Attribute::VELOCITY]
+        );
+
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Init, &property_layout, &particle_layout);
+        assert!(disc.apply(&mut module, &mut context).is_ok());
+        assert!(context
+            .main_code
+            .starts_with("set_position_camera_facing_disc_"));
+        assert!(context.extra_code.contains("sim_params.camera_position"));
+        assert!(context.extra_code.contains("spawner.inverse_transform"));
+        assert!(context.extra_code.contains("sqrt(frand())"));
+    }
+
     #[test]
     fn validate_init() {
         let mut module = Module::default();
         let center = module.lit(Vec3::ZERO);
         let axis = module.lit(Vec3::Y);
         let radius = module.lit(1.);
+        let orientation = module.lit(Vec4::from(Quat::from_rotation_x(1.)));
         let modifiers: &[&dyn Modifier] = &[
             &SetPositionCircleModifier {
                 center,
                 axis,
                 radius,
                 dimension: ShapeDimension::Volume,
+                thickness: None,
             },
             &SetPositionSphereModifier {
                 center,
                 radius,
                 dimension: ShapeDimension::Volume,
+                thickness: None,
+            },
+            &SetPositionCircleModifier {
+                center,
+                axis,
+                radius,
+                dimension: ShapeDimension::Surface,
+                thickness: Some(radius),
+            },
+            &SetPositionSphereModifier {
+                center,
+                radius,
+                dimension: ShapeDimension::Surface,
+                thickness: Some(radius),
+            },
+            &SetPositionBoxModifier {
+                center,
+                half_extents: axis,
+                dimension: ShapeDimension::Volume,
+                orientation: None,
+            },
+            &SetPositionBoxModifier {
+                center,
+                half_extents: axis,
+                dimension: ShapeDimension::Surface,
+                orientation: None,
+            },
+            &SetPositionCone3dModifier {
+                base_radius: radius,
+                top_radius: radius,
+                height: radius,
+                dimension: ShapeDimension::Volume,
+                orientation: None,
+            },
+            &SetPositionBoxModifier {
+                center,
+                half_extents: axis,
+                dimension: ShapeDimension::Surface,
+                orientation: Some(orientation),
             },
             &SetPositionCone3dModifier {
                 base_radius: radius,
                 top_radius: radius,
                 height: radius,
                 dimension: ShapeDimension::Volume,
+                orientation: Some(orientation),
             },
+            &SetPositionPathModifier::new([Vec3::ZERO, Vec3::X, Vec3::ONE]).with_closed(true),
             &SetVelocityCircleModifier {
                 center,
                 axis,
@@ -703,6 +1043,35 @@ mod tests {
                 axis,
                 speed: radius,
             },
+            &SetVelocityNormalModifier::new(EmitterShape::Sphere { center }, radius),
+            &SetVelocityNormalModifier::new(
+                EmitterShape::Box {
+                    center,
+                    half_extents: axis,
+                    orientation: Some(orientation),
+                },
+                radius,
+            )
+            .with_normal_jitter(radius),
+            &SetVelocityNormalModifier::new(
+                EmitterShape::Cone {
+                    height: radius,
+                    base_radius: radius,
+                    top_radius: radius,
+                    orientation: None,
+                },
+                radius,
+            ),
+            &SetVelocityNormalModifier::new(
+                EmitterShape::Cone {
+                    height: radius,
+                    base_radius: radius,
+                    top_radius: radius,
+                    orientation: Some(orientation),
+                },
+                radius,
+            ),
+            &SetRandomSpriteIndexModifier::new(4),
         ];
         for &modifier in modifiers.iter() {
             assert!(modifier.context().contains(ModifierContext::Init));
@@ -728,6 +1097,8 @@ mod tests {
 
 const tau: f32 = 6.283185307179586476925286766559;
 
+{SAFE_MATH_CODE}
+
 struct Particle {{
     {attributes_code}
 }};
@@ -778,17 +1149,20 @@ fn main() {{
                 axis,
                 radius,
                 dimension: ShapeDimension::Volume,
+                thickness: None,
             },
             &SetPositionSphereModifier {
                 center,
                 radius,
                 dimension: ShapeDimension::Volume,
+                thickness: None,
             },
             &SetPositionCone3dModifier {
                 base_radius: radius,
                 top_radius: radius,
                 height: radius,
                 dimension: ShapeDimension::Volume,
+                orientation: None,
             },
             &SetVelocityCircleModifier {
                 center,
@@ -804,6 +1178,30 @@ fn main() {{
                 axis,
                 speed: radius,
             },
+            &ConditionalModifier::new(
+                writer.lit(true).expr(),
+                RadialAccelModifier::new(origin, one),
+            ),
+            &CollidePlaneModifier::new(origin, y_axis, one).with_restitution_jitter(one),
+            &CollidePlaneModifier::new(origin, y_axis, one).with_kill_on_impact(true),
+            &ClampVelocityModifier::new(writer.lit(0.5).expr(), one),
+            &ClampToBoxModifier::new(origin, writer.lit(Vec3::ONE).expr()),
+            &ClampToBoxModifier::new(origin, writer.lit(Vec3::ONE).expr())
+                .with_stop_on_contact(true),
+            &ClampToSphereModifier::new(origin, one),
+            &ClampToSphereModifier::new(origin, one).with_stop_on_contact(true),
+            &FlipbookAnimationModifier::new(16, 24.)
+                .with_speed_variance(0.3)
+                .with_random_phase(true),
+            &CollisionHeightfieldModifier {
+                heightmap: default(),
+                heightmap_path: None,
+                origin,
+                extents: writer.lit(Vec2::ONE).expr(),
+                height_scale: one,
+                restitution: one,
+                friction: one,
+            },
         ];
         let mut module = writer.finish();
         for &modifier in modifiers.iter() {
@@ -828,6 +1226,14 @@ fn main() {{
     return 0.0;
 }}
 
+fn pcg_hash(input: u32) -> u32 {{
+    return input;
+}}
+
+fn to_float01(u: u32) -> f32 {{
+    return 0.0;
+}}
+
 const tau: f32 = 6.283185307179586476925286766559;
 
 struct Particle {{
@@ -845,6 +1251,7 @@ struct SimParams {{
     virtual_time: f32,
     real_delta_time: f32,
     real_time: f32,
+    camera_position: vec3<f32>,
 }};
 
 struct Spawner {{
@@ -855,8 +1262,10 @@ struct Spawner {{
     effect_index: u32,
 }};
 
+{SAFE_MATH_CODE}
+
 fn proj(u: vec3<f32>, v: vec3<f32>) -> vec3<f32> {{
-    return dot(v, u) / dot(u,u) * u;
+    return safe_div(dot(v, u), dot(u, u)) * u;
 }}
 
 {update_extra}
@@ -864,6 +1273,8 @@ fn proj(u: vec3<f32>, v: vec3<f32>) -> vec3<f32> {{
 @group(0) @binding(0) var<uniform> sim_params : SimParams;
 @group(1) @binding(0) var<storage, read_write> particle_buffer : ParticleBuffer;
 @group(2) @binding(0) var<storage, read_write> spawner : Spawner; // NOTE - same group as init
+@group(3) @binding(2) var heightfield_texture: texture_2d<f32>;
+@group(3) @binding(3) var heightfield_sampler: sampler;
 
 @compute @workgroup_size(64)
 fn main() {{
@@ -897,15 +1308,22 @@ fn main() {{
             &OrientModifier::new(OrientMode::ParallelCameraDepthPlane),
             &OrientModifier::new(OrientMode::FaceCameraPosition),
             &OrientModifier::new(OrientMode::AlongVelocity),
+            &MotionBlurModifier::new(0.5),
+            &DissolveModifier::default()
+                .with_edge_width(0.1)
+                .with_edge_color(Vec4::new(4., 2., 0., 1.)),
+            &RefractionModifier::default(),
         ];
         for &modifier in modifiers.iter() {
             let mut module = Module::default();
             let property_layout = PropertyLayout::default();
             let particle_layout = ParticleLayout::default();
             let mut context = RenderContext::new(&property_layout, &particle_layout);
-            modifier.apply_render(&mut module, &mut context);
+            modifier.apply_render(&mut module, &mut context).unwrap();
             let vertex_code = context.vertex_code;
             let fragment_code = context.fragment_code;
+            let dissolve_code = context.dissolve_code;
+            let refraction_code = context.refraction_code;
             let render_extra = context.render_extra;
 
             let mut particle_layout = ParticleLayout::new();
@@ -933,6 +1351,8 @@ fn frand() -> f32 {{ return 0.0; }}
 fn get_camera_position_effect_space() -> vec3<f32> {{ return vec3<f32>(); }}
 fn get_camera_rotation_effect_space() -> mat3x3<f32> {{ return mat3x3<f32>(); }}
 
+{SAFE_MATH_CODE}
+
 const tau: f32 = 6.283185307179586476925286766559;
 
 struct Particle {{
@@ -942,9 +1362,15 @@ struct Particle {{
 struct VertexOutput {{
     @builtin(position) position: vec4<f32>,
     @location(0) color: vec4<f32>,
+    @location(1) uv: vec2<f32>,
+    @location(2) dissolve: f32,
 }};
 
 @group(0) @binding(0) var<uniform> view: View;
+@group(2) @binding(2) var dissolve_texture: texture_2d<f32>;
+@group(2) @binding(3) var dissolve_sampler: sampler;
+@group(2) @binding(4) var refraction_texture: texture_2d<f32>;
+@group(2) @binding(5) var refraction_sampler: sampler;
 
 {render_extra}
 
@@ -958,8 +1384,8 @@ fn main() {{
     var axis_y = vec3<f32>(0.0, 1.0, 0.0);
     var axis_z = vec3<f32>(0.0, 0.0, 1.0);
     var color = vec4<f32>(1.0, 1.0, 1.0, 1.0);
-{vertex_code}
     var out: VertexOutput;
+{vertex_code}
     return out;
 }}
 
@@ -967,7 +1393,12 @@ fn main() {{
 @fragment
 fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {{
 {fragment_code}
-    return vec4<f32>(1.0);
+    var color = in.color;
+    {dissolve_code}
+    let refraction_normal = in.uv * 2. - 1.;
+    let refraction_screen_uv = in.position.xy;
+    {refraction_code}
+    return color;
 }}"##
             );
 
@@ -1006,4 +1437,21 @@ fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {{
             assert_eq!(s2, s);
         }
     }
+
+    #[test]
+    fn make_fn_reused() {
+        let mut module = Module::default();
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+
+        // Identical modifiers share the same expressions, so the same function
+        let accel0 = RadialAccelModifier::constant(&mut module, Vec3::ZERO, 2.);
+        let accel1 = RadialAccelModifier::constant(&mut module, Vec3::ZERO, 2.);
+        assert_eq!(accel0, accel1);
+        accel0.apply(&mut module, &mut context).unwrap();
+        accel1.apply(&mut module, &mut context).unwrap();
+        assert_eq!(context.extra_code.matches("fn ").count(), 1);
+    }
 }
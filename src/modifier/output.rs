@@ -5,9 +5,9 @@ use serde::{Deserialize, Serialize};
 use std::hash::Hash;
 
 use crate::{
-    impl_mod_render, Attribute, BoxedModifier, CpuValue, EvalContext, ExprError, ExprHandle,
-    Gradient, Modifier, ModifierContext, Module, RenderContext, RenderModifier, ShaderCode,
-    ShaderWriter, ToWgslString,
+    impl_mod_render, Attribute, BoxedModifier, BuiltInOperator, CpuValue, Easing, EvalContext,
+    ExprError, ExprHandle, Gradient, Modifier, ModifierContext, Module, RenderContext,
+    RenderModifier, ShaderCode, ShaderWriter, ToWgslString,
 };
 
 /// Mapping of the sample read from a texture image to the base particle color.
@@ -58,29 +58,157 @@ impl ToWgslString for ImageSampleMapping {
 
 /// A modifier modulating each particle's color by sampling a texture.
 ///
+/// By default the texture is mapped onto each particle quad. If
+/// [`screen_space_uv`] is `true`, the texture is instead sampled at the screen
+/// position of each rendered pixel, with UV coordinates `(0,0)` at the top
+/// left corner of the viewport and `(1,1)` at its bottom right corner. The
+/// texture then stays fixed on screen while particles move through it, which
+/// is useful for shared overlays like heat-haze distortion or fog noise.
+///
+/// To avoid the obvious repetition of a single sprite over many particles,
+/// the texture can be randomly mirrored per particle with [`random_flip_x`]
+/// and [`random_flip_y`]. The flip is derived from the [`Attribute::SEED`] of
+/// the particle, so is constant over its lifetime. The per-quad UV coordinates
+/// are flipped before any [`FlipbookModifier`] selects a sprite, so each
+/// sprite is mirrored in place inside the sprite sheet. This has no effect
+/// with [`screen_space_uv`].
+///
+/// # Serialization
+///
+/// Asset handles cannot be serialized. Instead, the asset path of the texture
+/// is serialized via [`texture_path`]. When an effect is loaded from an
+/// `.effect` file, the [`EffectAsset`] loader loads the image at that path and
+/// assigns the resulting handle to [`texture`]. When creating the modifier in
+/// code, use [`new()`] to fill [`texture_path`] from the asset path of the
+/// texture handle; assigning [`texture`] directly leaves it unset, and the
+/// texture is then lost when serializing the effect.
+///
 /// # Attributes
 ///
-/// This modifier does not require any specific particle attribute.
+/// This modifier requires the [`Attribute::SEED`] particle attribute if
+/// either [`random_flip_x`] or [`random_flip_y`] is `true`. Otherwise it
+/// doesn't require any specific particle attribute.
+///
+/// [`texture_path`]: ParticleTextureModifier::texture_path
+/// [`texture`]: ParticleTextureModifier::texture
+/// [`new()`]: ParticleTextureModifier::new
+/// [`screen_space_uv`]: ParticleTextureModifier::screen_space_uv
+/// [`random_flip_x`]: ParticleTextureModifier::random_flip_x
+/// [`random_flip_y`]: ParticleTextureModifier::random_flip_y
+/// [`EffectAsset`]: crate::EffectAsset
 #[derive(Default, Debug, Clone, PartialEq, Reflect, Serialize, Deserialize)]
 pub struct ParticleTextureModifier {
     /// The texture image to modulate the particle color with.
     #[serde(skip)]
-    // TODO - Clarify if Modifier needs to be serializable, or we need another on-disk
-    // representation... NOTE - Need to keep a strong handle here, nothing else will keep that
+    // NOTE - Need to keep a strong handle here, nothing else will keep that
     // texture loaded currently.
     pub texture: Handle<Image>,
 
+    /// The asset path of the texture image, if any.
+    ///
+    /// This is the serialized representation of [`texture`], which is resolved
+    /// into a handle when loading the effect asset. This is ignored when
+    /// rendering; only [`texture`] is used. This is not updated automatically
+    /// when assigning [`texture`].
+    ///
+    /// [`texture`]: ParticleTextureModifier::texture
+    #[serde(default)]
+    pub texture_path: Option<String>,
+
     /// The mapping of the texture image samples to the base particle color.
     pub sample_mapping: ImageSampleMapping,
+
+    /// Sample the texture in screen space instead of per particle quad.
+    #[serde(default)]
+    pub screen_space_uv: bool,
+
+    /// Randomly mirror the texture horizontally on half of the particles.
+    #[serde(default)]
+    pub random_flip_x: bool,
+
+    /// Randomly mirror the texture vertically on half of the particles.
+    #[serde(default)]
+    pub random_flip_y: bool,
+}
+
+impl ParticleTextureModifier {
+    /// Create a new modifier sampling the given texture.
+    ///
+    /// If the texture handle was obtained from the [`AssetServer`], its asset
+    /// path is saved into [`texture_path`] so that the texture is serialized
+    /// with the effect.
+    ///
+    /// [`texture_path`]: ParticleTextureModifier::texture_path
+    pub fn new(texture: Handle<Image>) -> Self {
+        Self {
+            texture_path: texture.path().map(ToString::to_string),
+            texture,
+            ..default()
+        }
+    }
 }
 
-impl_mod_render!(ParticleTextureModifier, &[]); // TODO - should require some UV maybe?
+#[typetag::serde]
+impl Modifier for ParticleTextureModifier {
+    fn context(&self) -> ModifierContext {
+        ModifierContext::Render
+    }
+
+    fn as_render(&self) -> Option<&dyn RenderModifier> {
+        Some(self)
+    }
+
+    fn as_render_mut(&mut self) -> Option<&mut dyn RenderModifier> {
+        Some(self)
+    }
+
+    fn attributes(&self) -> &[Attribute] {
+        if self.random_flip_x || self.random_flip_y {
+            &[Attribute::SEED]
+        } else {
+            &[]
+        }
+    }
+
+    fn boxed_clone(&self) -> BoxedModifier {
+        Box::new(self.clone())
+    }
+
+    fn apply(&self, _module: &mut Module, context: &mut ShaderWriter) -> Result<(), ExprError> {
+        Err(ExprError::InvalidModifierContext(
+            context.modifier_context(),
+            ModifierContext::Render,
+        ))
+    }
+}
 
 #[typetag::serde]
 impl RenderModifier for ParticleTextureModifier {
-    fn apply_render(&self, _module: &mut Module, context: &mut RenderContext) {
+    fn apply_render(
+        &self,
+        _module: &mut Module,
+        context: &mut RenderContext,
+    ) -> Result<(), ExprError> {
         context.set_particle_texture(self.texture.clone());
         context.image_sample_mapping_code = self.sample_mapping.to_wgsl_string();
+        if self.screen_space_uv {
+            context.image_sample_uv_code =
+                "(in.position.xy - view.viewport.xy) / view.viewport.zw".to_string();
+        } else if self.random_flip_x || self.random_flip_y {
+            // Hash the seed again, to decorrelate the flip from other random
+            // values derived from the seed, like the flipbook animation speed.
+            context.uv_code += &format!(
+                "let uv_flip = pcg_hash(pcg_hash(particle.{}));\n",
+                Attribute::SEED.name()
+            );
+            if self.random_flip_x {
+                context.uv_code += "if ((uv_flip & 1u) != 0u) { uv.x = 1. - uv.x; }\n";
+            }
+            if self.random_flip_y {
+                context.uv_code += "if ((uv_flip & 2u) != 0u) { uv.y = 1. - uv.y; }\n";
+            }
+        }
+        Ok(())
     }
 
     fn boxed_render_clone(&self) -> Box<dyn RenderModifier> {
@@ -98,6 +226,17 @@ impl RenderModifier for ParticleTextureModifier {
 /// determined by the user with [`CpuValue::Single`], or left randomized with
 /// [`CpuValue::Uniform`], but will be the same color for all particles.
 ///
+/// The color is interpreted in the [`ColorSpace`] of the effect, and converted
+/// to linear RGB if needed.
+///
+/// The color can optionally be modulated by a [`tint`] expression evaluated in
+/// the render shader. This is typically used with a property, to control the
+/// color of an effect at runtime without rebuilding it. The tint is used as
+/// is, without any color space conversion.
+///
+/// [`ColorSpace`]: crate::ColorSpace
+/// [`tint`]: crate::modifier::output::SetColorModifier::tint
+///
 /// # Attributes
 ///
 /// This modifier does not require any specific particle attribute.
@@ -105,14 +244,53 @@ impl RenderModifier for ParticleTextureModifier {
 pub struct SetColorModifier {
     /// The particle color.
     pub color: CpuValue<Vec4>,
+    /// Optional tint expression, as a `vec4<f32>` multiplied with the particle
+    /// color.
+    #[serde(default)]
+    pub tint: Option<ExprHandle>,
+}
+
+impl SetColorModifier {
+    /// Create a new modifier with the given color.
+    pub fn new(color: impl Into<CpuValue<Vec4>>) -> Self {
+        Self {
+            color: color.into(),
+            tint: None,
+        }
+    }
+
+    /// Set the tint expression multiplied with the particle color.
+    pub fn with_tint(mut self, tint: ExprHandle) -> Self {
+        self.tint = Some(tint);
+        self
+    }
 }
 
 impl_mod_render!(SetColorModifier, &[]);
 
 #[typetag::serde]
 impl RenderModifier for SetColorModifier {
-    fn apply_render(&self, _module: &mut Module, context: &mut RenderContext) {
-        context.vertex_code += &format!("color = {0};\n", self.color.to_wgsl_string());
+    fn apply_render(
+        &self,
+        module: &mut Module,
+        context: &mut RenderContext,
+    ) -> Result<(), ExprError> {
+        let to_linear = |c| context.color_space.to_linear(c);
+        let color = match self.color {
+            CpuValue::Single(c) => CpuValue::Single(to_linear(c)),
+            CpuValue::Uniform((a, b)) => CpuValue::Uniform((to_linear(a), to_linear(b))),
+        };
+        if let Some(tint) = self.tint {
+            let tint = context.eval(module, tint)?;
+            context.vertex_code += &format!(
+                "color = {0} * vec4<f32>({1});\n",
+                color.to_wgsl_string(),
+                tint
+            );
+        } else {
+            context.vertex_code += &format!("color = {0};\n", color.to_wgsl_string());
+        }
+        Ok(())
     }
 
     fn boxed_render_clone(&self) -> Box<dyn RenderModifier> {
@@ -127,6 +305,11 @@ impl RenderModifier for SetColorModifier {
 /// A modifier modulating each particle's color over its lifetime with a
 /// gradient curve.
 ///
+/// The gradient keys are interpreted in the [`ColorSpace`] of the effect, and
+/// converted to linear RGB if needed before being interpolated.
+///
+/// [`ColorSpace`]: crate::ColorSpace
+///
 /// # Attributes
 ///
 /// This modifier requires the following particle attributes:
@@ -136,6 +319,27 @@ impl RenderModifier for SetColorModifier {
 pub struct ColorOverLifetimeModifier {
     /// The color gradient defining the particle color based on its lifetime.
     pub gradient: Gradient<Vec4>,
+    /// Easing applied to the lifetime ratio of the particle before sampling
+    /// the gradient.
+    #[serde(default)]
+    pub easing: Easing,
+}
+
+impl ColorOverLifetimeModifier {
+    /// Create a new modifier from a color gradient.
+    pub fn new(gradient: Gradient<Vec4>) -> Self {
+        Self {
+            gradient,
+            easing: Easing::Linear,
+        }
+    }
+
+    /// Set the easing applied to the lifetime ratio of the particle before
+    /// sampling the gradient.
+    pub fn with_easing(mut self, easing: Easing) -> Self {
+        self.easing = easing;
+        self
+    }
 }
 
 impl_mod_render!(
@@ -145,8 +349,17 @@ impl_mod_render!(
 
 #[typetag::serde]
 impl RenderModifier for ColorOverLifetimeModifier {
-    fn apply_render(&self, _module: &mut Module, context: &mut RenderContext) {
-        let func_name = context.add_color_gradient(self.gradient.clone());
+    fn apply_render(
+        &self,
+        _module: &mut Module,
+        context: &mut RenderContext,
+    ) -> Result<(), ExprError> {
+        // Convert the keys, so that the gradient is interpolated in linear space
+        let mut gradient = self.gradient.clone();
+        for key in gradient.keys_mut() {
+            key.value = context.color_space.to_linear(key.value);
+        }
+        let func_name = context.add_color_gradient(gradient.clone());
         context.render_extra += &format!(
             r#"fn {0}(key: f32) -> vec4<f32> {{
     {1}
@@ -154,15 +367,12 @@ impl RenderModifier for ColorOverLifetimeModifier {
 
 "#,
             func_name,
-            self.gradient.to_shader_code("key")
+            gradient.to_shader_code("key")
         );
 
-        context.vertex_code += &format!(
-            "color = {0}(particle.{1} / particle.{2});\n",
-            func_name,
-            Attribute::AGE.name(),
-            Attribute::LIFETIME.name()
-        );
+        let ratio = lifetime_ratio(self.easing, context);
+        context.vertex_code += &format!("color = {0}({1});\n", func_name, ratio);
+        Ok(())
     }
 
     fn boxed_render_clone(&self) -> Box<dyn RenderModifier> {
@@ -174,6 +384,21 @@ impl RenderModifier for ColorOverLifetimeModifier {
     }
 }
 
+/// Get the WGSL expression of the lifetime ratio of the particle, eased with
+/// the given easing.
+///
+/// The ratio is the one of [`BuiltInOperator::AgeRatio`], clamped to \[0:1\]
+/// so that easings don't overshoot, and zero for a particle without lifetime.
+fn lifetime_ratio(easing: Easing, context: &mut RenderContext) -> String {
+    let ratio = BuiltInOperator::AgeRatio.to_wgsl_string();
+    if easing == Easing::Linear {
+        ratio
+    } else {
+        let func_name = context.add_easing(easing);
+        format!("{}({})", func_name, ratio)
+    }
+}
+
 /// A modifier to set the size of all particles.
 ///
 /// This modifier assigns a _single_ size to all particles. That size can be
@@ -196,8 +421,13 @@ impl_mod_render!(SetSizeModifier, &[]);
 
 #[typetag::serde]
 impl RenderModifier for SetSizeModifier {
-    fn apply_render(&self, _module: &mut Module, context: &mut RenderContext) {
+    fn apply_render(
+        &self,
+        _module: &mut Module,
+        context: &mut RenderContext,
+    ) -> Result<(), ExprError> {
         context.vertex_code += &format!("size = {0};\n", self.size.to_wgsl_string());
+        Ok(())
     }
 
     fn boxed_render_clone(&self) -> Box<dyn RenderModifier> {
@@ -225,6 +455,28 @@ pub struct SizeOverLifetimeModifier {
     /// is in screen-space logical pixels, and not affected by the camera
     /// projection. If `false`, the particle size is in world units.
     pub screen_space_size: bool,
+    /// Easing applied to the lifetime ratio of the particle before sampling
+    /// the gradient.
+    #[serde(default)]
+    pub easing: Easing,
+}
+
+impl SizeOverLifetimeModifier {
+    /// Create a new modifier from a size gradient, in world units.
+    pub fn new(gradient: Gradient<Vec2>) -> Self {
+        Self {
+            gradient,
+            screen_space_size: false,
+            easing: Easing::Linear,
+        }
+    }
+
+    /// Set the easing applied to the lifetime ratio of the particle before
+    /// sampling the gradient.
+    pub fn with_easing(mut self, easing: Easing) -> Self {
+        self.easing = easing;
+        self
+    }
 }
 
 impl_mod_render!(
@@ -234,7 +486,11 @@ impl_mod_render!(
 
 #[typetag::serde]
 impl RenderModifier for SizeOverLifetimeModifier {
-    fn apply_render(&self, _module: &mut Module, context: &mut RenderContext) {
+    fn apply_render(
+        &self,
+        _module: &mut Module,
+        context: &mut RenderContext,
+    ) -> Result<(), ExprError> {
         let func_name = context.add_size_gradient(self.gradient.clone());
         context.render_extra += &format!(
             r#"fn {0}(key: f32) -> vec2<f32> {{
@@ -246,12 +502,251 @@ impl RenderModifier for SizeOverLifetimeModifier {
             self.gradient.to_shader_code("key")
         );
 
-        context.vertex_code += &format!(
-            "size = {0}(particle.{1} / particle.{2});\n",
+        let ratio = lifetime_ratio(self.easing, context);
+        context.vertex_code += &format!("size = {0}({1});\n", func_name, ratio);
+        Ok(())
+    }
+
+    fn boxed_render_clone(&self) -> Box<dyn RenderModifier> {
+        Box::new(self.clone())
+    }
+
+    fn as_modifier(&self) -> &dyn Modifier {
+        self
+    }
+}
+
+/// Mapping of the particle position to a gradient sampling key, for the
+/// over-distance modifiers.
+///
+/// The key is derived from the distance of the particle to an [`origin`],
+/// either measured along an [`axis`], or radially in all directions if no
+/// axis is specified. That distance is then normalized such that
+/// [`min_distance`] maps to `0.` and [`max_distance`] maps to `1.`. All those
+/// values are expressions, so can be driven for example by properties:
+///
+/// ```wgsl
+/// // With an axis
+/// let d = dot(particle.position - origin, safe_normalize(axis));
+/// // Without an axis
+/// let d = distance(particle.position, origin);
+/// let key = safe_div(d - min_distance, max_distance - min_distance);
+/// ```
+///
+/// An empty distance range produces a key of `0.`. The key is not clamped,
+/// so the [`GradientWrap`] mode of the gradient sampled with it applies to
+/// particles outside of the normalization range.
+///
+/// The particle position is expressed in the simulation space of the effect.
+/// For a beam attached to an emitter, use [`SimulationSpace::Local`] so that
+/// the origin and axis are relative to the emitter and follow it.
+///
+/// [`origin`]: DistanceMapping::origin
+/// [`axis`]: DistanceMapping::axis
+/// [`min_distance`]: DistanceMapping::min_distance
+/// [`max_distance`]: DistanceMapping::max_distance
+/// [`GradientWrap`]: crate::GradientWrap
+/// [`SimulationSpace::Local`]: crate::SimulationSpace::Local
+#[derive(Debug, Clone, Copy, PartialEq, Reflect, Serialize, Deserialize)]
+pub struct DistanceMapping {
+    /// The origin the distance is measured from, as a `vec3<f32>` expression.
+    pub origin: ExprHandle,
+    /// Optional axis the distance is measured along, as a `vec3<f32>`
+    /// expression. The axis doesn't need to be normalized. If `None`, the
+    /// distance is measured radially from the origin.
+    #[serde(default)]
+    pub axis: Option<ExprHandle>,
+    /// The distance mapped to the start of the gradient (key `0.`), as an
+    /// `f32` expression.
+    pub min_distance: ExprHandle,
+    /// The distance mapped to the end of the gradient (key `1.`), as an `f32`
+    /// expression.
+    pub max_distance: ExprHandle,
+}
+
+impl DistanceMapping {
+    /// Create a mapping measuring the distance from an origin, in all
+    /// directions.
+    pub fn from_origin(
+        origin: ExprHandle,
+        min_distance: ExprHandle,
+        max_distance: ExprHandle,
+    ) -> Self {
+        Self {
+            origin,
+            axis: None,
+            min_distance,
+            max_distance,
+        }
+    }
+
+    /// Create a mapping measuring the distance from an origin along an axis.
+    pub fn along_axis(
+        origin: ExprHandle,
+        axis: ExprHandle,
+        min_distance: ExprHandle,
+        max_distance: ExprHandle,
+    ) -> Self {
+        Self {
+            origin,
+            axis: Some(axis),
+            min_distance,
+            max_distance,
+        }
+    }
+
+    /// Evaluate the WGSL expression of the normalized gradient sampling key.
+    fn eval(&self, module: &Module, context: &mut RenderContext) -> Result<String, ExprError> {
+        let origin = context.eval(module, self.origin)?;
+        let distance = if let Some(axis) = self.axis {
+            let axis = context.eval(module, axis)?;
+            format!(
+                "dot(particle.{0} - {1}, safe_normalize({2}))",
+                Attribute::POSITION.name(),
+                origin,
+                axis
+            )
+        } else {
+            format!(
+                "distance(particle.{0}, {1})",
+                Attribute::POSITION.name(),
+                origin
+            )
+        };
+        let min_distance = context.eval(module, self.min_distance)?;
+        let max_distance = context.eval(module, self.max_distance)?;
+        // Guard against an empty range, which would produce an infinite key
+        Ok(format!(
+            "safe_div({0} - {1}, {2} - {1})",
+            distance, min_distance, max_distance
+        ))
+    }
+}
+
+/// A modifier modulating each particle's color with a gradient curve based on
+/// its distance to a point, instead of its lifetime.
+///
+/// This is typically used for beam or laser effects made of a line of
+/// particles, which fade toward the tip of the beam. The gradient is sampled
+/// with the key derived from the particle position by the [`mapping`]. See
+/// [`DistanceMapping`] for details.
+///
+/// The gradient keys are interpreted in the [`ColorSpace`] of the effect, and
+/// converted to linear RGB if needed before being interpolated.
+///
+/// [`mapping`]: crate::modifier::output::ColorOverDistanceModifier::mapping
+/// [`ColorSpace`]: crate::ColorSpace
+///
+/// # Attributes
+///
+/// This modifier requires the following particle attributes:
+/// - [`Attribute::POSITION`]
+#[derive(Debug, Clone, PartialEq, Reflect, Serialize, Deserialize)]
+pub struct ColorOverDistanceModifier {
+    /// The color gradient defining the particle color based on its distance.
+    pub gradient: Gradient<Vec4>,
+    /// The mapping from the particle position to the gradient key.
+    pub mapping: DistanceMapping,
+}
+
+impl ColorOverDistanceModifier {
+    /// Create a new modifier from a gradient and a distance mapping.
+    pub fn new(gradient: Gradient<Vec4>, mapping: DistanceMapping) -> Self {
+        Self { gradient, mapping }
+    }
+}
+
+impl_mod_render!(ColorOverDistanceModifier, &[Attribute::POSITION]);
+
+#[typetag::serde]
+impl RenderModifier for ColorOverDistanceModifier {
+    fn apply_render(
+        &self,
+        module: &mut Module,
+        context: &mut RenderContext,
+    ) -> Result<(), ExprError> {
+        // Convert the keys, so that the gradient is interpolated in linear space
+        let mut gradient = self.gradient.clone();
+        for key in gradient.keys_mut() {
+            key.value = context.color_space.to_linear(key.value);
+        }
+        let func_name = context.add_color_gradient(gradient.clone());
+        context.render_extra += &format!(
+            r#"fn {0}(key: f32) -> vec4<f32> {{
+    {1}
+}}
+
+"#,
             func_name,
-            Attribute::AGE.name(),
-            Attribute::LIFETIME.name()
+            gradient.to_shader_code("key")
+        );
+
+        let key = self.mapping.eval(module, context)?;
+        context.vertex_code += &format!("color = {0}({1});\n", func_name, key);
+        Ok(())
+    }
+
+    fn boxed_render_clone(&self) -> Box<dyn RenderModifier> {
+        Box::new(self.clone())
+    }
+
+    fn as_modifier(&self) -> &dyn Modifier {
+        self
+    }
+}
+
+/// A modifier modulating each particle's size with a gradient curve based on
+/// its distance to a point, instead of its lifetime.
+///
+/// This is typically used for beam or laser effects made of a line of
+/// particles, which thin out toward the tip of the beam. The gradient is
+/// sampled with the key derived from the particle position by the
+/// [`mapping`]. See [`DistanceMapping`] for details.
+///
+/// [`mapping`]: crate::modifier::output::SizeOverDistanceModifier::mapping
+///
+/// # Attributes
+///
+/// This modifier requires the following particle attributes:
+/// - [`Attribute::POSITION`]
+#[derive(Debug, Clone, PartialEq, Reflect, Serialize, Deserialize)]
+pub struct SizeOverDistanceModifier {
+    /// The size gradient defining the particle size based on its distance.
+    pub gradient: Gradient<Vec2>,
+    /// The mapping from the particle position to the gradient key.
+    pub mapping: DistanceMapping,
+}
+
+impl SizeOverDistanceModifier {
+    /// Create a new modifier from a gradient and a distance mapping.
+    pub fn new(gradient: Gradient<Vec2>, mapping: DistanceMapping) -> Self {
+        Self { gradient, mapping }
+    }
+}
+
+impl_mod_render!(SizeOverDistanceModifier, &[Attribute::POSITION]);
+
+#[typetag::serde]
+impl RenderModifier for SizeOverDistanceModifier {
+    fn apply_render(
+        &self,
+        module: &mut Module,
+        context: &mut RenderContext,
+    ) -> Result<(), ExprError> {
+        let func_name = context.add_size_gradient(self.gradient.clone());
+        context.render_extra += &format!(
+            r#"fn {0}(key: f32) -> vec2<f32> {{
+    {1}
+}}
+
+"#,
+            func_name,
+            self.gradient.to_shader_code("key")
         );
+
+        let key = self.mapping.eval(module, context)?;
+        context.vertex_code += &format!("size = {0}({1});\n", func_name, key);
+        Ok(())
     }
 
     fn boxed_render_clone(&self) -> Box<dyn RenderModifier> {
@@ -395,11 +890,15 @@ impl Modifier for OrientModifier {
 
 #[typetag::serde]
 impl RenderModifier for OrientModifier {
-    fn apply_render(&self, module: &mut Module, context: &mut RenderContext) {
+    fn apply_render(
+        &self,
+        module: &mut Module,
+        context: &mut RenderContext,
+    ) -> Result<(), ExprError> {
         match self.mode {
             OrientMode::ParallelCameraDepthPlane => {
                 if let Some(rotation) = self.rotation {
-                    let rotation = context.eval(module, rotation).unwrap();
+                    let rotation = context.eval(module, rotation)?;
                     context.vertex_code += &format!(
                         r#"let cam_rot = get_camera_rotation_effect_space();
 let particle_rot_in_cam_space = {};
@@ -421,7 +920,7 @@ axis_z = cam_rot[2].xyz;
             }
             OrientMode::FaceCameraPosition => {
                 if let Some(rotation) = self.rotation {
-                    let rotation = context.eval(module, rotation).unwrap();
+                    let rotation = context.eval(module, rotation)?;
                     context.vertex_code += &format!(
                         r#"axis_z = normalize(get_camera_position_effect_space() - position);
 let particle_rot_in_cam_space = {};
@@ -442,13 +941,17 @@ axis_y = cross(axis_z, axis_x);
                 }
             }
             OrientMode::AlongVelocity => {
+                // Particles at rest have no direction to align with; fall back to the
+                // camera right axis instead of normalizing a null vector.
                 context.vertex_code += r#"let dir = normalize(position - get_camera_position_effect_space());
-axis_x = normalize(particle.velocity);
+let vel_dir = safe_normalize(particle.velocity);
+axis_x = select(get_camera_rotation_effect_space()[0], vel_dir, dot(vel_dir, vel_dir) > 0.);
 axis_y = cross(dir, axis_x);
 axis_z = cross(axis_x, axis_y);
 "#;
             }
         }
+        Ok(())
     }
 
     fn boxed_render_clone(&self) -> Box<dyn RenderModifier> {
@@ -476,12 +979,15 @@ axis_z = cross(axis_x, axis_y);
 /// doesn't animate anything; instead, the animation comes from a varying value
 /// of [`Attribute::SPRITE_INDEX`].
 ///
-/// There's no built-in modifier to update the [`Attribute::SPRITE_INDEX`];
-/// instead you should use a [`SetAttributeModifier`] with a suitable
-/// expression. A common example is to base the sprite index on the particle
-/// age, accessed from [`Attribute::AGE`]. Note that in that case the
-/// [`Attribute::AGE`] being a floating point value must be cast to an integer
-/// to be assigned to [`Attribute::SPRITE_INDEX`].
+/// To give each particle a random, fixed sprite, use a
+/// [`SetRandomSpriteIndexModifier`]. To play the sprites in sequence at a
+/// fixed frame rate, optionally randomized per particle, use a
+/// [`FlipbookAnimationModifier`]. For other animations, use a
+/// [`SetAttributeModifier`] with a suitable expression. A common example is to
+/// base the sprite index on the particle age, accessed from
+/// [`Attribute::AGE`]. Note that in that case the [`Attribute::AGE`] being a
+/// floating point value must be cast to an integer to be assigned to
+/// [`Attribute::SPRITE_INDEX`].
 ///
 /// # Example
 ///
@@ -510,6 +1016,7 @@ axis_z = cross(axis_x, axis_y);
 ///     .render(ParticleTextureModifier {
 ///         texture,
 ///         sample_mapping: ImageSampleMapping::ModulateOpacityFromR,
+///         ..default()
 ///     })
 ///     .render(FlipbookModifier {
 ///         sprite_grid_size: UVec2::new(2, 2), // 4 frames
@@ -552,8 +1059,13 @@ impl_mod_render!(FlipbookModifier, &[Attribute::SPRITE_INDEX]);
 
 #[typetag::serde]
 impl RenderModifier for FlipbookModifier {
-    fn apply_render(&self, _module: &mut Module, context: &mut RenderContext) {
+    fn apply_render(
+        &self,
+        _module: &mut Module,
+        context: &mut RenderContext,
+    ) -> Result<(), ExprError> {
         context.sprite_grid_size = Some(self.sprite_grid_size);
+        Ok(())
     }
 
     fn boxed_render_clone(&self) -> Box<dyn RenderModifier> {
@@ -565,69 +1077,748 @@ impl RenderModifier for FlipbookModifier {
     }
 }
 
-/// A modifier to interpret the size of all particles in screen-space pixels.
+/// A modifier to pick a random sprite of a sprite sheet for each particle.
 ///
-/// This modifier assigns a pixel size to particles in screen space, ignoring
-/// the distance to the camera and perspective. It effectively scales the
-/// existing [`Attribute::SIZE`] of each particle to negate the perspective
-/// correction usually applied to rendered objects based on their distance to
-/// the camera.
+/// This modifier initializes the [`Attribute::SPRITE_INDEX`] of each newly
+/// spawned particle to a random value uniformly distributed in `[0:N-1]`, where
+/// `N` is the number of sprites in the sprite sheet. Combined with a
+/// [`FlipbookModifier`] slicing the texture into those sprites, this allows a
+/// single effect to render a variety of sprites, like different debris shapes
+/// or smoke puffs, without animating them.
 ///
-/// Note that this modifier should generally be placed last in the stack, or at
-/// least after any modifier which might modify the particle position or its
-/// size. Otherwise the scaling will be incorrect.
+/// # Example
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_hanabi::*;
+/// # let texture = Handle::<Image>::default();
+/// let writer = ExprWriter::new();
+/// let grid = UVec2::new(4, 2); // 8 different sprites
+///
+/// let asset = EffectAsset::new(vec![32768], Spawner::rate(32.0.into()), writer.finish())
+///     .init(SetRandomSpriteIndexModifier::from_grid(grid))
+///     .render(ParticleTextureModifier {
+///         texture,
+///         ..default()
+///     })
+///     .render(FlipbookModifier {
+///         sprite_grid_size: grid,
+///     });
+/// ```
 ///
 /// # Attributes
 ///
 /// This modifier requires the following particle attributes:
-/// - [`Attribute::POSITION`]
-///
-/// If the [`Attribute::SIZE`] or [`Attribute::SIZE2`] are present, they're used
-/// to initialize the particle's size. Otherwise the default size is used. So
-/// this modifier doesn't require any size attribute.
+/// - [`Attribute::SPRITE_INDEX`]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Reflect, Serialize, Deserialize)]
-pub struct ScreenSpaceSizeModifier;
+pub struct SetRandomSpriteIndexModifier {
+    /// Number of sprites to pick from.
+    ///
+    /// A value of zero is treated as one, always selecting the first sprite.
+    pub sprite_count: u32,
+}
 
-impl_mod_render!(
-    ScreenSpaceSizeModifier,
-    &[Attribute::POSITION, Attribute::SIZE]
-);
+impl SetRandomSpriteIndexModifier {
+    /// Create a new modifier picking among the given number of sprites.
+    pub fn new(sprite_count: u32) -> Self {
+        Self { sprite_count }
+    }
+
+    /// Create a new modifier picking among all the sprites of a sprite sheet
+    /// grid, as used by [`FlipbookModifier::sprite_grid_size`].
+    pub fn from_grid(sprite_grid_size: UVec2) -> Self {
+        Self::new(sprite_grid_size.x * sprite_grid_size.y)
+    }
+}
 
 #[typetag::serde]
-impl RenderModifier for ScreenSpaceSizeModifier {
-    fn apply_render(&self, _module: &mut Module, context: &mut RenderContext) {
-        // Get perspective divide factor from clip space position. This is the "average"
-        // factor for the entire particle, taken at its position (mesh origin),
-        // and applied uniformly for all vertices. Scale size by w_cs to negate
-        // the perspective divide which will happen later after the vertex shader.
-        // The 2.0 factor is because clip space is in [-1:1] so we need to divide by the
-        // half screen size only.
-        // Note: here "size" is the built-in render size, which is always defined and
-        // called "size", and which may or may not be the Attribute::SIZE/2
-        // attribute(s).
-        context.vertex_code += &format!(
-            "let w_cs = transform_position_simulation_to_clip(particle.{0}).w;\n
-            let screen_size_pixels = view.viewport.zw;\n
-            let projection_scale = vec2<f32>(view.projection[0][0], view.projection[1][1]);\n
-            size = (size * w_cs * 2.0) / min(screen_size_pixels.x * projection_scale.x, screen_size_pixels.y * projection_scale.y);\n",
-            Attribute::POSITION.name());
+impl Modifier for SetRandomSpriteIndexModifier {
+    fn context(&self) -> ModifierContext {
+        ModifierContext::Init
     }
 
-    fn boxed_render_clone(&self) -> Box<dyn RenderModifier> {
+    fn attributes(&self) -> &[Attribute] {
+        &[Attribute::SPRITE_INDEX]
+    }
+
+    fn boxed_clone(&self) -> BoxedModifier {
         Box::new(*self)
     }
 
-    fn as_modifier(&self) -> &dyn Modifier {
-        self
+    fn apply(&self, module: &mut Module, context: &mut ShaderWriter) -> Result<(), ExprError> {
+        let attr = module.attr(Attribute::SPRITE_INDEX);
+        let attr = context.eval(module, attr)?;
+        let count = self.sprite_count.max(1);
+        // Clamp in case frand() returns exactly 1.0
+        context.main_code += &format!(
+            "{} = min(i32(frand() * {}), {});\n",
+            attr,
+            (count as f32).to_wgsl_string(),
+            (count - 1) as i32
+        );
+        Ok(())
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use crate::*;
-
-    use super::*;
-
+/// A modifier to animate the sprite of each particle through a sprite sheet.
+///
+/// This modifier updates the [`Attribute::SPRITE_INDEX`] of each particle
+/// based on its [`Attribute::AGE`], cycling through `N` sprites at a given
+/// frame rate, and looping back to the first sprite after the last one.
+/// Combined with a [`FlipbookModifier`] slicing the texture into those
+/// sprites, this animates each particle through the sprite sheet.
+///
+/// By default all particles play the animation in lockstep, which makes large
+/// groups of particles, like a field of smoke puffs, look obviously looped.
+/// To break that synchronization, the playback speed of each particle can be
+/// randomized with [`speed_variance`], and its starting sprite randomized
+/// with [`random_phase`]. Both are derived from the [`Attribute::SEED`] of the
+/// particle, so the variation is constant over the lifetime of the particle.
+///
+/// # Example
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_hanabi::*;
+/// # let texture = Handle::<Image>::default();
+/// let writer = ExprWriter::new();
+/// let grid = UVec2::new(4, 4); // 16 frames
+///
+/// let asset = EffectAsset::new(vec![32768], Spawner::rate(32.0.into()), writer.finish())
+///     .update(
+///         FlipbookAnimationModifier::from_grid(grid, 24.)
+///             .with_speed_variance(0.3)
+///             .with_random_phase(true),
+///     )
+///     .render(ParticleTextureModifier {
+///         texture,
+///         ..default()
+///     })
+///     .render(FlipbookModifier {
+///         sprite_grid_size: grid,
+///     });
+/// ```
+///
+/// # Attributes
+///
+/// This modifier requires the following particle attributes:
+/// - [`Attribute::AGE`]
+/// - [`Attribute::SEED`]
+/// - [`Attribute::SPRITE_INDEX`]
+///
+/// [`speed_variance`]: FlipbookAnimationModifier::speed_variance
+/// [`random_phase`]: FlipbookAnimationModifier::random_phase
+#[derive(Debug, Clone, Copy, PartialEq, Reflect, Serialize, Deserialize)]
+pub struct FlipbookAnimationModifier {
+    /// Number of sprites in the animation.
+    ///
+    /// A value of zero is treated as one, always selecting the first sprite.
+    pub sprite_count: u32,
+    /// Playback speed of the animation, in sprites per second.
+    pub frame_rate: f32,
+    /// Relative random variation of the playback speed of each particle, in
+    /// \[0:1\].
+    ///
+    /// Each particle plays the animation at a speed uniformly distributed in
+    /// `frame_rate * [1 - speed_variance : 1 + speed_variance]`. A value of
+    /// zero plays all particles at the same speed. Values outside the
+    /// \[0:1\] range are clamped.
+    pub speed_variance: f32,
+    /// Start each particle on a random sprite of the animation, instead of the
+    /// first one.
+    pub random_phase: bool,
+}
+
+impl FlipbookAnimationModifier {
+    /// Create a new modifier playing the given number of sprites at the given
+    /// frame rate, in sprites per second.
+    pub fn new(sprite_count: u32, frame_rate: f32) -> Self {
+        Self {
+            sprite_count,
+            frame_rate,
+            speed_variance: 0.,
+            random_phase: false,
+        }
+    }
+
+    /// Create a new modifier playing all the sprites of a sprite sheet grid, as
+    /// used by [`FlipbookModifier::sprite_grid_size`].
+    pub fn from_grid(sprite_grid_size: UVec2, frame_rate: f32) -> Self {
+        Self::new(sprite_grid_size.x * sprite_grid_size.y, frame_rate)
+    }
+
+    /// Set the relative random variation of the playback speed of each
+    /// particle.
+    ///
+    /// See [`speed_variance`] for details.
+    ///
+    /// [`speed_variance`]: FlipbookAnimationModifier::speed_variance
+    pub fn with_speed_variance(mut self, speed_variance: f32) -> Self {
+        self.speed_variance = speed_variance;
+        self
+    }
+
+    /// Set whether each particle starts on a random sprite of the animation.
+    pub fn with_random_phase(mut self, random_phase: bool) -> Self {
+        self.random_phase = random_phase;
+        self
+    }
+}
+
+#[typetag::serde]
+impl Modifier for FlipbookAnimationModifier {
+    fn context(&self) -> ModifierContext {
+        ModifierContext::Update
+    }
+
+    fn attributes(&self) -> &[Attribute] {
+        &[Attribute::AGE, Attribute::SEED, Attribute::SPRITE_INDEX]
+    }
+
+    fn boxed_clone(&self) -> BoxedModifier {
+        Box::new(*self)
+    }
+
+    fn apply(&self, module: &mut Module, context: &mut ShaderWriter) -> Result<(), ExprError> {
+        let age = module.attr(Attribute::AGE);
+        let age = context.eval(module, age)?;
+        let seed = module.attr(Attribute::SEED);
+        let seed = context.eval(module, seed)?;
+        let sprite_index = module.attr(Attribute::SPRITE_INDEX);
+        let sprite_index = context.eval(module, sprite_index)?;
+        let count = self.sprite_count.max(1) as f32;
+
+        // Derive the random speed and phase from two independent values hashed from
+        // the seed, so they're constant over the lifetime of the particle.
+        let mut frame = format!("{} * {}", age, self.frame_rate.to_wgsl_string());
+        let speed_variance = self.speed_variance.clamp(0., 1.);
+        if speed_variance > 0. {
+            frame = format!(
+                "{} * (1. + {} * (2. * to_float01({}) - 1.))",
+                frame,
+                speed_variance.to_wgsl_string(),
+                seed
+            );
+        }
+        if self.random_phase {
+            frame = format!(
+                "{} + to_float01(pcg_hash({})) * {}",
+                frame,
+                seed,
+                count.to_wgsl_string()
+            );
+        }
+        context.main_code += &format!(
+            "{} = i32(floor(({}) % {}));\n",
+            sprite_index,
+            frame,
+            count.to_wgsl_string()
+        );
+        Ok(())
+    }
+}
+
+/// A modifier to interpret the size of all particles in screen-space pixels.
+///
+/// This modifier assigns a pixel size to particles in screen space, ignoring
+/// the distance to the camera and perspective. It effectively scales the
+/// existing [`Attribute::SIZE`] of each particle to negate the perspective
+/// correction usually applied to rendered objects based on their distance to
+/// the camera.
+///
+/// Note that this modifier should generally be placed last in the stack, or at
+/// least after any modifier which might modify the particle position or its
+/// size. Otherwise the scaling will be incorrect.
+///
+/// # Attributes
+///
+/// This modifier requires the following particle attributes:
+/// - [`Attribute::POSITION`]
+///
+/// If the [`Attribute::SIZE`] or [`Attribute::SIZE2`] are present, they're used
+/// to initialize the particle's size. Otherwise the default size is used. So
+/// this modifier doesn't require any size attribute.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Reflect, Serialize, Deserialize)]
+pub struct ScreenSpaceSizeModifier;
+
+impl_mod_render!(
+    ScreenSpaceSizeModifier,
+    &[Attribute::POSITION, Attribute::SIZE]
+);
+
+#[typetag::serde]
+impl RenderModifier for ScreenSpaceSizeModifier {
+    fn apply_render(
+        &self,
+        _module: &mut Module,
+        context: &mut RenderContext,
+    ) -> Result<(), ExprError> {
+        // Get perspective divide factor from clip space position. This is the "average"
+        // factor for the entire particle, taken at its position (mesh origin),
+        // and applied uniformly for all vertices. Scale size by w_cs to negate
+        // the perspective divide which will happen later after the vertex shader.
+        // The 2.0 factor is because clip space is in [-1:1] so we need to divide by the
+        // half screen size only.
+        // Note: here "size" is the built-in render size, which is always defined and
+        // called "size", and which may or may not be the Attribute::SIZE/2
+        // attribute(s).
+        context.vertex_code += &format!(
+            "let w_cs = transform_position_simulation_to_clip(particle.{0}).w;\n
+            let screen_size_pixels = view.viewport.zw;\n
+            let projection_scale = vec2<f32>(view.projection[0][0], view.projection[1][1]);\n
+            size = (size * w_cs * 2.0) / min(screen_size_pixels.x * projection_scale.x, screen_size_pixels.y * projection_scale.y);\n",
+            Attribute::POSITION.name());
+        Ok(())
+    }
+
+    fn boxed_render_clone(&self) -> Box<dyn RenderModifier> {
+        Box::new(*self)
+    }
+
+    fn as_modifier(&self) -> &dyn Modifier {
+        self
+    }
+}
+
+/// A modifier smearing particles along their velocity to approximate motion
+/// blur.
+///
+/// The particle quad is elongated along the projection of its velocity onto
+/// the camera plane, by the distance the particle travels during the
+/// [`strength`] exposure time. The particle alpha is scaled down by the same
+/// ratio the quad is stretched, so that the total opacity is spread along the
+/// motion path instead of just being stretched. This produces a smoother smear
+/// than [`OrientMode::AlongVelocity`] combined with a velocity-based size, and
+/// is mostly useful for fast-moving particles like tracers and bullets.
+///
+/// The smear extends behind the particle, so that the particle position stays
+/// at the head of the motion path.
+///
+/// This modifier overwrites the local frame of the particle, so should be
+/// placed after any [`OrientModifier`]. It should generally be placed after
+/// any modifier changing the particle size or color too.
+///
+/// # Attributes
+///
+/// This modifier requires the following particle attributes:
+/// - [`Attribute::POSITION`]
+/// - [`Attribute::VELOCITY`]
+///
+/// [`strength`]: crate::modifier::output::MotionBlurModifier::strength
+#[derive(Debug, Clone, Copy, PartialEq, Reflect, Serialize, Deserialize)]
+pub struct MotionBlurModifier {
+    /// Blur strength, as the exposure time in seconds.
+    ///
+    /// The smear length is the distance traveled by the particle at its
+    /// current velocity during that time. A value of zero disables the blur.
+    pub strength: f32,
+}
+
+impl MotionBlurModifier {
+    /// Create a new instance of this modifier with the given blur strength.
+    pub fn new(strength: f32) -> Self {
+        Self { strength }
+    }
+}
+
+impl Default for MotionBlurModifier {
+    fn default() -> Self {
+        // Roughly one frame at 60 FPS
+        Self { strength: 1. / 60. }
+    }
+}
+
+impl_mod_render!(
+    MotionBlurModifier,
+    &[Attribute::POSITION, Attribute::VELOCITY]
+);
+
+#[typetag::serde]
+impl RenderModifier for MotionBlurModifier {
+    fn apply_render(
+        &self,
+        _module: &mut Module,
+        context: &mut RenderContext,
+    ) -> Result<(), ExprError> {
+        // Project the motion vector onto the plane perpendicular to the view
+        // direction, so the smear follows the screen-space motion. Then stretch
+        // the quad along it, and spread the alpha over the larger area.
+        context.vertex_code += &format!(
+            r#"let mb_motion = particle.{1} * {2};
+let mb_view_dir = normalize(particle.{0} - get_camera_position_effect_space());
+let mb_motion_plane = mb_motion - dot(mb_motion, mb_view_dir) * mb_view_dir;
+let mb_length = length(mb_motion_plane);
+if (mb_length > 1e-5) {{
+    axis_x = mb_motion_plane / mb_length;
+    axis_y = cross(mb_view_dir, axis_x);
+    axis_z = cross(axis_x, axis_y);
+    let mb_base_size = max(size.x, 1e-5);
+    size.x = mb_base_size + mb_length;
+    color.a = color.a * mb_base_size / size.x;
+    particle.{0} = particle.{0} - mb_motion_plane * 0.5;
+}}
+"#,
+            Attribute::POSITION.name(),
+            Attribute::VELOCITY.name(),
+            self.strength.to_wgsl_string()
+        );
+        Ok(())
+    }
+
+    fn boxed_render_clone(&self) -> Box<dyn RenderModifier> {
+        Box::new(*self)
+    }
+
+    fn as_modifier(&self) -> &dyn Modifier {
+        self
+    }
+}
+
+/// A modifier dissolving particles over their lifetime by eroding them with a
+/// texture.
+///
+/// The red channel of the erosion texture is compared against the age ratio
+/// `particle.age / particle.lifetime` of the particle. Fragments where the
+/// erosion value falls below the threshold are discarded, such that the
+/// particle progressively dissolves, starting with the texels of lowest
+/// erosion value and ending with the highest ones. This produces a burning-edge
+/// dissipation, generally more convincing than a simple fade for smoke or
+/// magic effects.
+///
+/// ```wgsl
+/// threshold = age_ratio * (1. + edge_width) - edge_width;
+/// if (erosion < threshold) { discard; }
+/// ```
+///
+/// Optionally, an [`edge_color`] is blended into the particle color along the
+/// dissolve boundary, over a band of width [`edge_width`] in erosion units.
+/// The alpha component of the edge color controls the blending strength. The
+/// edge color can be an HDR color to produce a glowing edge.
+///
+/// The erosion is applied after the particle color is resolved, including any
+/// [`ParticleTextureModifier`].
+///
+/// # Serialization
+///
+/// Like for [`ParticleTextureModifier`], the asset path of the erosion texture
+/// is serialized via [`texture_path`], and resolved into a handle when loading
+/// the effect asset.
+///
+/// # Attributes
+///
+/// This modifier requires the following particle attributes:
+/// - [`Attribute::AGE`]
+/// - [`Attribute::LIFETIME`]
+///
+/// [`edge_color`]: crate::modifier::output::DissolveModifier::edge_color
+/// [`edge_width`]: crate::modifier::output::DissolveModifier::edge_width
+/// [`texture_path`]: crate::modifier::output::DissolveModifier::texture_path
+#[derive(Default, Debug, Clone, PartialEq, Reflect, Serialize, Deserialize)]
+pub struct DissolveModifier {
+    /// The erosion texture. Only the red channel is used.
+    #[serde(skip)]
+    pub texture: Handle<Image>,
+
+    /// The asset path of the erosion texture, if any.
+    ///
+    /// This is the serialized representation of [`texture`], which is resolved
+    /// into a handle when loading the effect asset.
+    ///
+    /// [`texture`]: DissolveModifier::texture
+    #[serde(default)]
+    pub texture_path: Option<String>,
+
+    /// Width of the dissolve boundary, in erosion units.
+    pub edge_width: f32,
+
+    /// Optional color blended into the particle color along the dissolve
+    /// boundary.
+    #[serde(default)]
+    pub edge_color: Option<Vec4>,
+}
+
+impl DissolveModifier {
+    /// Create a new modifier eroding particles with the given texture.
+    ///
+    /// Like for [`ParticleTextureModifier::new()`], the asset path of the
+    /// texture handle, if any, is saved into [`texture_path`].
+    ///
+    /// [`texture_path`]: DissolveModifier::texture_path
+    pub fn new(texture: Handle<Image>) -> Self {
+        Self {
+            texture_path: texture.path().map(ToString::to_string),
+            texture,
+            ..default()
+        }
+    }
+
+    /// Set the width of the dissolve boundary.
+    pub fn with_edge_width(mut self, edge_width: f32) -> Self {
+        self.edge_width = edge_width;
+        self
+    }
+
+    /// Set the color blended into the particle color along the dissolve
+    /// boundary.
+    pub fn with_edge_color(mut self, edge_color: Vec4) -> Self {
+        self.edge_color = Some(edge_color);
+        self
+    }
+}
+
+impl_mod_render!(DissolveModifier, &[Attribute::AGE, Attribute::LIFETIME]);
+
+#[typetag::serde]
+impl RenderModifier for DissolveModifier {
+    fn apply_render(
+        &self,
+        _module: &mut Module,
+        context: &mut RenderContext,
+    ) -> Result<(), ExprError> {
+        context.dissolve_texture = Some(self.texture.clone());
+
+        context.vertex_code += &format!(
+            "out.dissolve = particle.{0} / particle.{1};\n",
+            Attribute::AGE.name(),
+            Attribute::LIFETIME.name()
+        );
+
+        let edge_width = self.edge_width.max(0.);
+        let mut code = format!(
+            r#"let dissolve_threshold = in.dissolve * (1. + {0}) - {0};
+    let dissolve_erosion = textureSample(dissolve_texture, dissolve_sampler, in.uv).r;
+    if (dissolve_erosion < dissolve_threshold) {{
+        discard;
+    }}
+"#,
+            edge_width.to_wgsl_string()
+        );
+        if let Some(edge_color) = self.edge_color {
+            if edge_width > 0. {
+                code += &format!(
+                    r#"    let dissolve_edge = 1. - smoothstep(0., {0}, dissolve_erosion - dissolve_threshold);
+    let dissolve_edge_color = {1};
+    color = vec4<f32>(mix(color.rgb, dissolve_edge_color.rgb, dissolve_edge * dissolve_edge_color.a), color.a);
+"#,
+                    edge_width.to_wgsl_string(),
+                    edge_color.to_wgsl_string()
+                );
+            }
+        }
+        context.dissolve_code = code;
+        Ok(())
+    }
+
+    fn boxed_render_clone(&self) -> Box<dyn RenderModifier> {
+        Box::new(self.clone())
+    }
+
+    fn as_modifier(&self) -> &dyn Modifier {
+        self
+    }
+}
+
+/// A modifier refracting the scene behind particles, for distortion effects
+/// like heat haze or glass.
+///
+/// The modifier samples a scene color texture in screen space, at an offset
+/// derived from a normal map, and uses the result as the particle color,
+/// modulated by the particle's base color. The offset direction is read from
+/// the red and green channels of the texture of the [`ParticleTextureModifier`]
+/// if any, remapped from `[0:1]` to `[-1:1]`, and scaled by the distortion
+/// [`strength`]. Without a particle texture, the offset points radially away
+/// from the center of the particle quad, producing a lens-like distortion.
+/// If the effect also has a [`NormalModifier`], the offset is instead the
+/// particle normal projected onto the view plane.
+///
+/// ```wgsl
+/// uv = screen_uv + (normal.rg * 2. - 1.) * strength;
+/// color = vec4<f32>(textureSample(scene_texture, uv).rgb * base_color.rgb, color.a);
+/// ```
+///
+/// # Render order
+///
+/// A render pass cannot read the render target it's drawing into. Therefore
+/// [`scene_texture`] must be a separate image containing the color of the
+/// scene behind the particles, which must be fully rendered before the
+/// particles are. This is typically achieved by rendering the opaque scene
+/// with a first camera into an image render target (see
+/// [`RenderTarget::Image`]), and rendering the particles with a second camera
+/// of higher [`Camera::order`]. The image should have the same size as the
+/// viewport the particles are rendered into.
+///
+/// # Attributes
+///
+/// This modifier does not require any specific particle attribute.
+///
+/// [`strength`]: crate::modifier::output::RefractionModifier::strength
+/// [`scene_texture`]: crate::modifier::output::RefractionModifier::scene_texture
+/// [`RenderTarget::Image`]: bevy::render::camera::RenderTarget::Image
+/// [`Camera::order`]: bevy::render::camera::Camera::order
+#[derive(Default, Debug, Clone, PartialEq, Reflect, Serialize, Deserialize)]
+pub struct RefractionModifier {
+    /// The scene color texture to refract. This is generally a render target,
+    /// so is not serialized.
+    #[serde(skip)]
+    pub scene_texture: Handle<Image>,
+
+    /// Distortion strength, as the maximum offset in fraction of the viewport
+    /// size.
+    pub strength: f32,
+}
+
+impl RefractionModifier {
+    /// Create a new modifier refracting the given scene color texture.
+    pub fn new(scene_texture: Handle<Image>, strength: f32) -> Self {
+        Self {
+            scene_texture,
+            strength,
+        }
+    }
+}
+
+impl_mod_render!(RefractionModifier, &[]);
+
+#[typetag::serde]
+impl RenderModifier for RefractionModifier {
+    fn apply_render(
+        &self,
+        _module: &mut Module,
+        context: &mut RenderContext,
+    ) -> Result<(), ExprError> {
+        context.refraction_texture = Some(self.scene_texture.clone());
+        context.refraction_code = format!(
+            r#"let refraction_uv = refraction_screen_uv + refraction_normal * {0};
+    let refraction_color = textureSample(refraction_texture, refraction_sampler, refraction_uv);
+    color = vec4<f32>(refraction_color.rgb * in.color.rgb, color.a);
+"#,
+            self.strength.to_wgsl_string()
+        );
+        Ok(())
+    }
+
+    fn boxed_render_clone(&self) -> Box<dyn RenderModifier> {
+        Box::new(self.clone())
+    }
+
+    fn as_modifier(&self) -> &dyn Modifier {
+        self
+    }
+}
+
+/// Method used by [`NormalModifier`] to compute the normal of a particle.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Reflect, Serialize, Deserialize)]
+pub enum NormalMode {
+    /// The normal is the local Z axis of the particle, constant over the
+    /// particle quad.
+    ///
+    /// This is the default variant.
+    #[default]
+    Flat,
+
+    /// The normal is the one of a unit sphere inscribed in the particle quad,
+    /// facing the local Z axis of the particle.
+    ///
+    /// This gives a round look to camera-facing billboards, for example smoke
+    /// puffs lit from the side. Outside the inscribed circle, the normal lies
+    /// in the plane of the quad.
+    Spherical,
+
+    /// The normal is read from the texture of the [`ParticleTextureModifier`],
+    /// interpreted as a tangent-space normal map.
+    ///
+    /// The RGB channels are remapped from `[0:1]` to `[-1:1]` and transformed
+    /// by the local frame of the particle, with the local X and Y axes as
+    /// tangent and bitangent. Note that the texture still modulates the
+    /// particle color according to its [`ImageSampleMapping`]. Without a
+    /// particle texture, this behaves like [`NormalMode::Flat`].
+    NormalMap,
+}
+
+/// A modifier computing the world-space normal of each particle fragment.
+///
+/// The vertex shader outputs the world-space normal of the particle, which is
+/// the local Z axis of the particle (see [`OrientModifier`]), along with its
+/// local X and Y axes as tangent frame. The fragment shader then calculates
+/// the normal of each fragment from those according to the [`NormalMode`],
+/// and stores it into a `normal` variable of type `vec3<f32>`, in world space
+/// and of unit length. The variable is defined before any other fragment code
+/// runs, so that it's available to the fragment code of other render
+/// modifiers, for example for lighting or decal compositing. The
+/// [`RefractionModifier`] uses it as distortion direction.
+///
+/// # Forward and deferred rendering
+///
+/// Particles are always rendered in the forward transparent (or alpha-masked)
+/// phases of the camera, whose render pass only has a color target. Therefore
+/// the normal is only available to the fragment shader of the effect itself,
+/// and is not written into any normal target. In particular particles don't
+/// contribute to the normal prepass, nor to the G-buffer of a deferred
+/// renderer, even when the camera uses one; they're drawn after the deferred
+/// lighting pass, like other transparent objects.
+///
+/// # Attributes
+///
+/// This modifier does not require any specific particle attribute.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Reflect, Serialize, Deserialize)]
+pub struct NormalModifier {
+    /// Method used to compute the normal.
+    pub mode: NormalMode,
+}
+
+impl NormalModifier {
+    /// Create a new instance of this modifier with the given normal mode.
+    pub fn new(mode: NormalMode) -> Self {
+        Self { mode }
+    }
+}
+
+impl_mod_render!(NormalModifier, &[]);
+
+#[typetag::serde]
+impl RenderModifier for NormalModifier {
+    fn apply_render(
+        &self,
+        _module: &mut Module,
+        context: &mut RenderContext,
+    ) -> Result<(), ExprError> {
+        context.normal_code = match self.mode {
+            NormalMode::Flat => "let normal = normalize(in.normal);\n".to_string(),
+            NormalMode::Spherical => r#"let normal_xy = in.normal_quad_position;
+    let normal_z = sqrt(max(1. - dot(normal_xy, normal_xy), 0.));
+    let normal = normalize(normal_xy.x * in.tangent + normal_xy.y * in.bitangent + normal_z * in.normal);
+"#
+            .to_string(),
+            // Note: the texture UV placeholder is substituted after this code is inserted
+            // into the shader template.
+            NormalMode::NormalMap => r#"
+#ifdef PARTICLE_TEXTURE
+    let normal_ts = textureSample(particle_texture, particle_sampler, {{PARTICLE_TEXTURE_UV}}).rgb * 2. - 1.;
+#else
+    let normal_ts = vec3<f32>(0., 0., 1.);
+#endif
+    let normal = normalize(normal_ts.x * in.tangent + normal_ts.y * in.bitangent + normal_ts.z * in.normal);
+"#
+            .to_string(),
+        };
+        Ok(())
+    }
+
+    fn boxed_render_clone(&self) -> Box<dyn RenderModifier> {
+        Box::new(*self)
+    }
+
+    fn as_modifier(&self) -> &dyn Modifier {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    use super::*;
+
     #[test]
     fn mod_particle_texture() {
         let texture = Handle::<Image>::default();
@@ -640,10 +1831,188 @@ mod tests {
         let property_layout = PropertyLayout::default();
         let particle_layout = ParticleLayout::default();
         let mut context = RenderContext::new(&property_layout, &particle_layout);
-        modifier.apply_render(&mut module, &mut context);
+        modifier.apply_render(&mut module, &mut context).unwrap();
 
         assert!(context.particle_texture.is_some());
         assert_eq!(context.particle_texture.unwrap(), texture);
+        assert!(context.image_sample_uv_code.is_empty());
+
+        let modifier = ParticleTextureModifier {
+            screen_space_uv: true,
+            ..modifier
+        };
+        let mut context = RenderContext::new(&property_layout, &particle_layout);
+        modifier.apply_render(&mut module, &mut context).unwrap();
+        assert!(context.image_sample_uv_code.contains("in.position.xy"));
+        assert!(context.uv_code.is_empty());
+    }
+
+    #[test]
+    fn mod_particle_texture_random_flip() {
+        let modifier = ParticleTextureModifier::default();
+        assert!(modifier.attributes().is_empty());
+
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let mut module = Module::default();
+        let mut context = RenderContext::new(&property_layout, &particle_layout);
+        modifier.apply_render(&mut module, &mut context).unwrap();
+        assert!(context.uv_code.is_empty());
+
+        let modifier = ParticleTextureModifier {
+            random_flip_x: true,
+            ..default()
+        };
+        assert_eq!(modifier.attributes(), &[Attribute::SEED]);
+        let mut context = RenderContext::new(&property_layout, &particle_layout);
+        modifier.apply_render(&mut module, &mut context).unwrap();
+        assert!(context.uv_code.contains("particle.seed"));
+        assert!(context.uv_code.contains("uv.x = 1. - uv.x;"));
+        assert!(!context.uv_code.contains("uv.y"));
+
+        let modifier = ParticleTextureModifier {
+            random_flip_x: true,
+            random_flip_y: true,
+            ..default()
+        };
+        let mut context = RenderContext::new(&property_layout, &particle_layout);
+        modifier.apply_render(&mut module, &mut context).unwrap();
+        assert!(context.uv_code.contains("uv.x = 1. - uv.x;"));
+        assert!(context.uv_code.contains("uv.y = 1. - uv.y;"));
+
+        // Screen-space UVs are never flipped
+        let modifier = ParticleTextureModifier {
+            random_flip_y: true,
+            screen_space_uv: true,
+            ..default()
+        };
+        let mut context = RenderContext::new(&property_layout, &particle_layout);
+        modifier.apply_render(&mut module, &mut context).unwrap();
+        assert!(context.uv_code.is_empty());
+    }
+
+    #[test]
+    fn mod_particle_texture_serde() {
+        let modifier = ParticleTextureModifier {
+            texture_path: Some("textures/cloud.png".to_string()),
+            ..default()
+        };
+
+        let s = ron::to_string(&modifier).unwrap();
+        assert_eq!(
+            s,
+            "(texture_path:Some(\"textures/cloud.png\"),sample_mapping:Modulate,screen_space_uv:false,random_flip_x:false,random_flip_y:false)"
+        );
+        let modifier_serde: ParticleTextureModifier = ron::from_str(&s).unwrap();
+        assert_eq!(modifier, modifier_serde);
+
+        // Effects saved before the random flip was added still load
+        let modifier_serde: ParticleTextureModifier =
+            ron::from_str("(sample_mapping:Modulate,screen_space_uv:false)").unwrap();
+        assert!(!modifier_serde.random_flip_x);
+        assert!(!modifier_serde.random_flip_y);
+    }
+
+    #[test]
+    fn mod_particle_texture_new() {
+        let texture = Handle::<Image>::weak_from_u128(0x1234);
+        let modifier = ParticleTextureModifier::new(texture.clone());
+        assert_eq!(modifier.texture, texture);
+        // A handle not loaded from a path has nothing to serialize
+        assert!(modifier.texture_path.is_none());
+        assert_eq!(modifier.sample_mapping, ImageSampleMapping::Modulate);
+    }
+
+    #[test]
+    fn mod_dissolve() {
+        let texture = Handle::<Image>::default();
+        let modifier = DissolveModifier::new(texture.clone())
+            .with_edge_width(0.1)
+            .with_edge_color(Vec4::new(4., 2., 0., 1.));
+        assert_eq!(
+            modifier.attributes(),
+            &[Attribute::AGE, Attribute::LIFETIME]
+        );
+
+        let mut module = Module::default();
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let mut context = RenderContext::new(&property_layout, &particle_layout);
+        modifier.apply_render(&mut module, &mut context).unwrap();
+
+        assert_eq!(context.dissolve_texture, Some(texture));
+        assert!(context
+            .vertex_code
+            .contains("out.dissolve = particle.age / particle.lifetime;"));
+        assert!(context.dissolve_code.contains("discard;"));
+        assert!(context.dissolve_code.contains("smoothstep(0., 0.1,"));
+
+        // No edge glow without edge width
+        let modifier = modifier.with_edge_width(0.);
+        let mut context = RenderContext::new(&property_layout, &particle_layout);
+        modifier.apply_render(&mut module, &mut context).unwrap();
+        assert!(context.dissolve_code.contains("discard;"));
+        assert!(!context.dissolve_code.contains("smoothstep"));
+    }
+
+    #[test]
+    fn mod_dissolve_serde() {
+        let modifier = DissolveModifier {
+            texture_path: Some("textures/erosion.png".to_string()),
+            edge_width: 0.1,
+            ..default()
+        };
+
+        let s = ron::to_string(&modifier).unwrap();
+        let modifier_serde: DissolveModifier = ron::from_str(&s).unwrap();
+        assert_eq!(modifier, modifier_serde);
+    }
+
+    #[test]
+    fn mod_refraction() {
+        let texture = Handle::<Image>::default();
+        let modifier = RefractionModifier::new(texture.clone(), 0.05);
+
+        let mut module = Module::default();
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let mut context = RenderContext::new(&property_layout, &particle_layout);
+        modifier.apply_render(&mut module, &mut context).unwrap();
+
+        assert_eq!(context.refraction_texture, Some(texture));
+        assert!(context
+            .refraction_code
+            .contains("refraction_screen_uv + refraction_normal * 0.05"));
+
+        let s = ron::to_string(&modifier).unwrap();
+        assert_eq!(s, "(strength:0.05)");
+        let modifier_serde: RefractionModifier = ron::from_str(&s).unwrap();
+        assert_eq!(modifier_serde.strength, modifier.strength);
+    }
+
+    #[test]
+    fn mod_normal() {
+        let mut module = Module::default();
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+
+        for (mode, code) in [
+            (NormalMode::Flat, "normalize(in.normal)"),
+            (NormalMode::Spherical, "normal_xy.y * in.bitangent"),
+            (NormalMode::NormalMap, "textureSample(particle_texture"),
+        ] {
+            let modifier = NormalModifier::new(mode);
+            let mut context = RenderContext::new(&property_layout, &particle_layout);
+            modifier.apply_render(&mut module, &mut context).unwrap();
+            assert!(context.normal_code.contains("let normal = "));
+            assert!(context.normal_code.contains(code));
+        }
+
+        let modifier = NormalModifier::new(NormalMode::Spherical);
+        let s = ron::to_string(&modifier).unwrap();
+        assert_eq!(s, "(mode:Spherical)");
+        let modifier_serde: NormalModifier = ron::from_str(&s).unwrap();
+        assert_eq!(modifier, modifier_serde);
     }
 
     #[test]
@@ -656,12 +2025,61 @@ mod tests {
         let property_layout = PropertyLayout::default();
         let particle_layout = ParticleLayout::default();
         let mut context = RenderContext::new(&property_layout, &particle_layout);
-        modifier.apply_render(&mut module, &mut context);
+        modifier.apply_render(&mut module, &mut context).unwrap();
 
         assert!(context.sprite_grid_size.is_some());
         assert_eq!(context.sprite_grid_size.unwrap(), UVec2::new(3, 4));
     }
 
+    #[test]
+    fn mod_random_sprite_index() {
+        let modifier = SetRandomSpriteIndexModifier::from_grid(UVec2::new(3, 4));
+        assert_eq!(modifier.sprite_count, 12);
+
+        let mut module = Module::default();
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Init, &property_layout, &particle_layout);
+        assert!(modifier.apply(&mut module, &mut context).is_ok());
+        assert!(context
+            .main_code
+            .contains("particle.sprite_index = min(i32(frand() * 12.), 11);"));
+
+        let s = ron::to_string(&modifier).unwrap();
+        assert_eq!(s, "(sprite_count:12)");
+    }
+
+    #[test]
+    fn mod_flipbook_animation() {
+        let modifier = FlipbookAnimationModifier::from_grid(UVec2::new(4, 2), 10.);
+        assert_eq!(modifier.sprite_count, 8);
+        assert_eq!(modifier.context(), ModifierContext::Update);
+        assert!(modifier.attributes().contains(&Attribute::SEED));
+
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+
+        // Lockstep
+        let mut module = Module::default();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        assert!(modifier.apply(&mut module, &mut context).is_ok());
+        assert!(context
+            .main_code
+            .contains("particle.sprite_index = i32(floor((particle.age * 10.) % 8.));"));
+
+        // Randomized speed and phase
+        let modifier = modifier.with_speed_variance(0.5).with_random_phase(true);
+        let mut module = Module::default();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        assert!(modifier.apply(&mut module, &mut context).is_ok());
+        assert!(context.main_code.contains(
+            "particle.sprite_index = i32(floor((particle.age * 10. * (1. + 0.5 * (2. * to_float01(particle.seed) - 1.)) + to_float01(pcg_hash(particle.seed)) * 8.) % 8.));"
+        ));
+    }
+
     #[test]
     fn mod_color_over_lifetime() {
         let red: Vec4 = Vec4::new(1., 0., 0., 1.);
@@ -669,19 +2087,33 @@ mod tests {
         let mut gradient = Gradient::new();
         gradient.add_key(0.5, red);
         gradient.add_key(0.8, blue);
-        let modifier = ColorOverLifetimeModifier {
-            gradient: gradient.clone(),
-        };
+        let modifier = ColorOverLifetimeModifier::new(gradient.clone());
 
         let mut module = Module::default();
         let property_layout = PropertyLayout::default();
         let particle_layout = ParticleLayout::default();
         let mut context = RenderContext::new(&property_layout, &particle_layout);
-        modifier.apply_render(&mut module, &mut context);
+        modifier.apply_render(&mut module, &mut context).unwrap();
 
         assert!(context
             .render_extra
             .contains(&gradient.to_shader_code("key")));
+        // The lifetime ratio is clamped and guarded against a zero lifetime
+        assert!(context.vertex_code.contains(
+            "(select(0., clamp(particle.age / particle.lifetime, 0., 1.), particle.lifetime > 0.));"
+        ));
+
+        // sRGB keys are converted to linear
+        let orange = Vec4::new(1., 0.5, 0., 1.);
+        let modifier = ColorOverLifetimeModifier::new(Gradient::constant(orange));
+        let mut context = RenderContext::new(&property_layout, &particle_layout);
+        context.color_space = ColorSpace::Srgb;
+        modifier.apply_render(&mut module, &mut context).unwrap();
+        let linear = Gradient::constant(ColorSpace::Srgb.to_linear(orange));
+        assert!(context.render_extra.contains(&linear.to_shader_code("key")));
+        assert!(!context
+            .render_extra
+            .contains(&modifier.gradient.to_shader_code("key")));
     }
 
     #[test]
@@ -691,20 +2123,97 @@ mod tests {
         let mut gradient = Gradient::new();
         gradient.add_key(0.5, x);
         gradient.add_key(0.8, y);
-        let modifier = SizeOverLifetimeModifier {
-            gradient: gradient.clone(),
-            screen_space_size: false,
-        };
+        let modifier = SizeOverLifetimeModifier::new(gradient.clone());
+
+        let mut module = Module::default();
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let mut context = RenderContext::new(&property_layout, &particle_layout);
+        modifier.apply_render(&mut module, &mut context).unwrap();
+
+        assert!(context
+            .render_extra
+            .contains(&gradient.to_shader_code("key")));
+        assert!(context.vertex_code.contains(
+            "(select(0., clamp(particle.age / particle.lifetime, 0., 1.), particle.lifetime > 0.));"
+        ));
+        assert!(!context.render_extra.contains("fn ease_"));
+
+        // Easing the lifetime ratio and the gradient interpolation
+        let gradient = gradient.with_easing(Easing::QuadOut);
+        let modifier =
+            SizeOverLifetimeModifier::new(gradient.clone()).with_easing(Easing::BounceIn);
+        let mut context = RenderContext::new(&property_layout, &particle_layout);
+        modifier.apply_render(&mut module, &mut context).unwrap();
+        assert!(context
+            .render_extra
+            .contains(&gradient.to_shader_code("key")));
+        assert!(context
+            .render_extra
+            .contains("fn ease_quad_out(ratio: f32) -> f32 {"));
+        assert!(context
+            .render_extra
+            .contains("fn ease_bounce_in(ratio: f32) -> f32 {"));
+        assert!(context.vertex_code.contains(
+            "(ease_bounce_in(select(0., clamp(particle.age / particle.lifetime, 0., 1.), particle.lifetime > 0.)));"
+        ));
+    }
+
+    #[test]
+    fn mod_color_over_distance() {
+        let mut module = Module::default();
+        let origin = module.lit(Vec3::ZERO);
+        let axis = module.lit(Vec3::Z);
+        let min_distance = module.lit(0.);
+        let max_distance = module.prop("beam_length");
+        let gradient = Gradient::linear(Vec4::ONE, Vec4::ZERO);
+        let modifier = ColorOverDistanceModifier::new(
+            gradient.clone(),
+            DistanceMapping::along_axis(origin, axis, min_distance, max_distance),
+        );
+        assert_eq!(modifier.attributes(), &[Attribute::POSITION]);
+
+        let property_layout = PropertyLayout::new(&[Property::new("beam_length", 4.)]);
+        let particle_layout = ParticleLayout::default();
+        let mut context = RenderContext::new(&property_layout, &particle_layout);
+        modifier.apply_render(&mut module, &mut context).unwrap();
+
+        assert!(context
+            .render_extra
+            .contains(&gradient.to_shader_code("key")));
+        assert!(context.vertex_code.contains("dot(particle.position - "));
+        assert!(context
+            .vertex_code
+            .contains(" - 0., properties.beam_length - 0.)"));
+
+        // An undeclared property is an error, not a panic
+        let property_layout = PropertyLayout::default();
+        let mut context = RenderContext::new(&property_layout, &particle_layout);
+        assert!(modifier.apply_render(&mut module, &mut context).is_err());
+    }
 
+    #[test]
+    fn mod_size_over_distance() {
         let mut module = Module::default();
+        let origin = module.lit(Vec3::ZERO);
+        let min_distance = module.lit(1.);
+        let max_distance = module.lit(3.);
+        let gradient = Gradient::linear(Vec2::ONE, Vec2::ZERO);
+        let modifier = SizeOverDistanceModifier::new(
+            gradient.clone(),
+            DistanceMapping::from_origin(origin, min_distance, max_distance),
+        );
+
         let property_layout = PropertyLayout::default();
         let particle_layout = ParticleLayout::default();
         let mut context = RenderContext::new(&property_layout, &particle_layout);
-        modifier.apply_render(&mut module, &mut context);
+        modifier.apply_render(&mut module, &mut context).unwrap();
 
         assert!(context
             .render_extra
             .contains(&gradient.to_shader_code("key")));
+        assert!(context.vertex_code.contains("distance(particle.position, "));
+        assert!(context.vertex_code.contains(" - 1., 3. - 1.)"));
     }
 
     #[test]
@@ -719,12 +2228,33 @@ mod tests {
         let property_layout = PropertyLayout::default();
         let particle_layout = ParticleLayout::default();
         let mut context = RenderContext::new(&property_layout, &particle_layout);
-        modifier.apply_render(&mut module, &mut context);
+        modifier.apply_render(&mut module, &mut context).unwrap();
 
         assert_eq!(modifier.color, CpuValue::from(Vec4::ZERO));
         assert_eq!(context.vertex_code, "color = vec4<f32>(0.,0.,0.,0.);\n");
     }
 
+    #[test]
+    fn mod_set_color_property() {
+        let mut module = Module::default();
+        let tint = module.prop("tint");
+        let modifier = SetColorModifier::new(Vec4::ONE).with_tint(tint);
+
+        let property_layout = PropertyLayout::new(&[Property::new("tint", Vec4::ONE)]);
+        let particle_layout = ParticleLayout::default();
+        let mut context = RenderContext::new(&property_layout, &particle_layout);
+        modifier.apply_render(&mut module, &mut context).unwrap();
+        assert_eq!(
+            context.vertex_code,
+            "color = vec4<f32>(1.,1.,1.,1.) * vec4<f32>(properties.tint);\n"
+        );
+
+        // Referencing an undefined property fails
+        let property_layout = PropertyLayout::default();
+        let mut context = RenderContext::new(&property_layout, &particle_layout);
+        assert!(context.eval(&module, tint).is_err());
+    }
+
     #[test]
     fn mod_set_size() {
         let mut modifier = SetSizeModifier::default();
@@ -737,7 +2267,7 @@ mod tests {
         let property_layout = PropertyLayout::default();
         let particle_layout = ParticleLayout::default();
         let mut context = RenderContext::new(&property_layout, &particle_layout);
-        modifier.apply_render(&mut module, &mut context);
+        modifier.apply_render(&mut module, &mut context).unwrap();
 
         assert_eq!(modifier.size, CpuValue::from(Vec2::ZERO));
         assert_eq!(context.vertex_code, "size = vec2<f32>(0.,0.);\n");
@@ -759,7 +2289,7 @@ mod tests {
         let property_layout = PropertyLayout::default();
         let particle_layout = ParticleLayout::default();
         let mut context = RenderContext::new(&property_layout, &particle_layout);
-        modifier.apply_render(&mut module, &mut context);
+        modifier.apply_render(&mut module, &mut context).unwrap();
 
         // TODO - less weak test...
         assert!(context
@@ -778,7 +2308,7 @@ mod tests {
         let property_layout = PropertyLayout::default();
         let particle_layout = ParticleLayout::default();
         let mut context = RenderContext::new(&property_layout, &particle_layout);
-        modifier.apply_render(&mut module, &mut context);
+        modifier.apply_render(&mut module, &mut context).unwrap();
 
         // TODO - less weak test...
         assert!(context
@@ -798,7 +2328,7 @@ mod tests {
         let property_layout = PropertyLayout::default();
         let particle_layout = ParticleLayout::default();
         let mut context = RenderContext::new(&property_layout, &particle_layout);
-        modifier.apply_render(&mut module, &mut context);
+        modifier.apply_render(&mut module, &mut context).unwrap();
 
         // TODO - less weak test...
         assert!(context
@@ -809,4 +2339,24 @@ mod tests {
             .contains("cos(particle_rot_in_cam_space)"));
         assert!(context.vertex_code.contains("let axis_x0 ="));
     }
+
+    #[test]
+    fn mod_motion_blur() {
+        let modifier = MotionBlurModifier::new(0.05);
+        assert_eq!(
+            modifier.attributes(),
+            &[Attribute::POSITION, Attribute::VELOCITY]
+        );
+
+        let mut module = Module::default();
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let mut context = RenderContext::new(&property_layout, &particle_layout);
+        modifier.apply_render(&mut module, &mut context).unwrap();
+
+        assert!(context.vertex_code.contains("particle.velocity * 0.05"));
+        assert!(context.vertex_code.contains("axis_x ="));
+        assert!(context.vertex_code.contains("size.x ="));
+        assert!(context.vertex_code.contains("color.a ="));
+    }
 }
@@ -4,12 +4,15 @@
 //! useful to initialize the position at spawn time, but can occasionally be
 //! used during simulation update to enforce a particular position.
 
-use bevy::prelude::*;
+use std::hash::{Hash, Hasher};
+
+use bevy::{prelude::*, utils::FloatOrd};
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    calc_func_id, graph::ExprError, modifier::ShapeDimension, Attribute, BoxedModifier,
-    EvalContext, ExprHandle, Modifier, ModifierContext, Module, ShaderWriter,
+    calc_func_id, graph::ExprError, make_quat_rotate_fn, modifier::ShapeDimension, Attribute,
+    BoxedModifier, EvalContext, ExprHandle, Modifier, ModifierContext, Module, ShaderWriter,
+    ToWgslString,
 };
 
 /// A modifier to set the position of particles on or inside a circle/disc,
@@ -46,6 +49,18 @@ pub struct SetPositionCircleModifier {
     /// be the perimeter circle, the set of points at a distance from the center
     /// exactly equal to the radius (`dist == r`).
     pub dimension: ShapeDimension,
+    /// Optional thickness of the perimeter circle.
+    ///
+    /// When emitting on the [`ShapeDimension::Surface`], particles are offset
+    /// radially by a random amount uniformly distributed in
+    /// `[-thickness/2:thickness/2]`, so they spawn within a ring of the given
+    /// thickness instead of exactly on the perimeter. This is ignored for
+    /// [`ShapeDimension::Volume`]. A value of `None` or zero keeps the
+    /// emission on the perimeter only.
+    ///
+    /// Expression type: `f32`
+    #[serde(default)]
+    pub thickness: Option<ExprHandle>,
 }
 
 impl SetPositionCircleModifier {
@@ -67,8 +82,16 @@ impl SetPositionCircleModifier {
 
                 let radius = match self.dimension {
                     ShapeDimension::Surface => {
-                        // Constant radius
-                        format!("let r = {};", ctx.eval(m, self.radius)?)
+                        // Constant radius, optionally jittered by the thickness
+                        if let Some(thickness) = self.thickness {
+                            format!(
+                                "let r = ({}) + (frand() - 0.5) * ({});",
+                                ctx.eval(m, self.radius)?,
+                                ctx.eval(m, thickness)?
+                            )
+                        } else {
+                            format!("let r = {};", ctx.eval(m, self.radius)?)
+                        }
                     }
                     ShapeDimension::Volume => {
                         // Radius uniformly distributed in [0:1], then square-rooted
@@ -147,6 +170,18 @@ pub struct SetPositionSphereModifier {
     pub radius: ExprHandle,
     /// The shape dimension to set the position to.
     pub dimension: ShapeDimension,
+    /// Optional thickness of the sphere shell.
+    ///
+    /// When emitting on the [`ShapeDimension::Surface`], particles are offset
+    /// along the sphere normal by a random amount uniformly distributed in
+    /// `[-thickness/2:thickness/2]`, so they spawn within a shell of the given
+    /// thickness instead of exactly on the surface. This is ignored for
+    /// [`ShapeDimension::Volume`]. A value of `None` or zero keeps the
+    /// emission on the surface only.
+    ///
+    /// Expression type: `f32`
+    #[serde(default)]
+    pub thickness: Option<ExprHandle>,
 }
 
 impl SetPositionSphereModifier {
@@ -167,8 +202,16 @@ impl SetPositionSphereModifier {
 
                 let radius = match self.dimension {
                     ShapeDimension::Surface => {
-                        // Constant radius
-                        format!("let r = {};", ctx.eval(m, self.radius)?)
+                        // Constant radius, optionally jittered by the thickness
+                        if let Some(thickness) = self.thickness {
+                            format!(
+                                "let r = ({}) + (frand() - 0.5) * ({});",
+                                ctx.eval(m, self.radius)?,
+                                ctx.eval(m, thickness)?
+                            )
+                        } else {
+                            format!("let r = {};", ctx.eval(m, self.radius)?)
+                        }
                     }
                     ShapeDimension::Volume => {
                         // Radius uniformly distributed in [0:1], then scaled by ^(1/3) in 3D
@@ -232,11 +275,154 @@ impl Modifier for SetPositionSphereModifier {
     }
 }
 
+/// A modifier to set the position of particles on or inside an axis-aligned
+/// box, randomly.
+///
+/// With [`ShapeDimension::Volume`], particles are uniformly distributed inside
+/// the box. With [`ShapeDimension::Surface`], particles are uniformly
+/// distributed on the six faces of the box, each face being selected with a
+/// probability proportional to its area.
+///
+/// The box is aligned with the axes of the emitter, unless an [`orientation`]
+/// is provided, in which case the box is rotated around its center.
+///
+/// # Attributes
+///
+/// This modifier requires the following particle attributes:
+/// - [`Attribute::POSITION`]
+///
+/// [`orientation`]: crate::modifier::position::SetPositionBoxModifier::orientation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Reflect, Serialize, Deserialize)]
+pub struct SetPositionBoxModifier {
+    /// The box center, relative to the emitter position.
+    ///
+    /// Expression type: `Vec3`
+    pub center: ExprHandle,
+    /// The box half extents, that is half the box size along each axis.
+    ///
+    /// Expression type: `Vec3`
+    pub half_extents: ExprHandle,
+    /// The shape dimension to set the position to.
+    pub dimension: ShapeDimension,
+    /// Optional orientation of the box, as a normalized quaternion with the
+    /// (x, y, z, w) layout of [`Quat`]. A value of `None` keeps the box
+    /// aligned with the emitter axes.
+    ///
+    /// Expression type: `Vec4`
+    #[serde(default)]
+    pub orientation: Option<ExprHandle>,
+}
+
+impl SetPositionBoxModifier {
+    /// Set the orientation of the box.
+    ///
+    /// See [`orientation`] for details.
+    ///
+    /// [`orientation`]: crate::modifier::position::SetPositionBoxModifier::orientation
+    pub fn with_orientation(mut self, orientation: ExprHandle) -> Self {
+        self.orientation = Some(orientation);
+        self
+    }
+
+    fn eval(
+        &self,
+        module: &mut Module,
+        context: &mut dyn EvalContext,
+    ) -> Result<String, ExprError> {
+        let func_id = calc_func_id(self);
+        let func_name = format!("set_position_box_{0:016X}", func_id);
+
+        context.make_fn(
+            &func_name,
+            "particle: ptr<function, Particle>",
+            module,
+            &mut |m: &mut Module, ctx: &mut dyn EvalContext| -> Result<String, ExprError> {
+                let center = ctx.eval(m, self.center)?;
+                let half_extents = ctx.eval(m, self.half_extents)?;
+
+                let surface = match self.dimension {
+                    ShapeDimension::Surface => {
+                        r##"    // Pick a face pair with a probability proportional to its area,
+    // then snap the point onto one of the two faces of that pair.
+    let areas = vec3<f32>(h.y * h.z, h.x * h.z, h.x * h.y);
+    let u = frand() * (areas.x + areas.y + areas.z);
+    let side = select(-1.0, 1.0, frand() < 0.5);
+    if (u < areas.x) {
+        p.x = side;
+    } else if (u < areas.x + areas.y) {
+        p.y = side;
+    } else {
+        p.z = side;
+    }
+"##
+                    }
+                    ShapeDimension::Volume => "",
+                };
+
+                let offset = if let Some(orientation) = self.orientation {
+                    let quat_rotate = make_quat_rotate_fn(ctx);
+                    format!(
+                        "{}(normalize({}), p * h)",
+                        quat_rotate,
+                        ctx.eval(m, orientation)?
+                    )
+                } else {
+                    "p * h".to_string()
+                };
+
+                Ok(format!(
+                    r##"    // Box center
+    let c = {};
+    // Box half extents
+    let h = {};
+    // Random point in [-1:1]^3
+    var p = vec3<f32>(frand(), frand(), frand()) * 2.0 - 1.0;
+{}    (*particle).{} = c + {};
+"##,
+                    center,
+                    half_extents,
+                    surface,
+                    Attribute::POSITION.name(),
+                    offset,
+                ))
+            },
+        )?;
+
+        let code = format!("{}(&particle);\n", func_name);
+
+        Ok(code)
+    }
+}
+
+#[typetag::serde]
+impl Modifier for SetPositionBoxModifier {
+    fn context(&self) -> ModifierContext {
+        ModifierContext::Init | ModifierContext::Update
+    }
+
+    fn attributes(&self) -> &[Attribute] {
+        &[Attribute::POSITION]
+    }
+
+    fn boxed_clone(&self) -> BoxedModifier {
+        Box::new(*self)
+    }
+
+    fn apply(&self, module: &mut Module, context: &mut ShaderWriter) -> Result<(), ExprError> {
+        let code = self.eval(module, context)?;
+        context.main_code += &code;
+        Ok(())
+    }
+}
+
 /// A modifier to set the position of particles on a truncated 3D cone.
 ///
 /// The 3D cone is oriented along the Y axis, with its origin at the center of
 /// the base circle of the cone. The center of the top circle truncating the
-/// cone is located at a positive Y.
+/// cone is located at a positive Y. An [`orientation`] can be provided to
+/// rotate the cone around its origin, for example to point it along the Z
+/// axis in Z-up projects, without adding a child entity just to rotate the
+/// emitter.
 ///
 /// Particles are moved somewhere inside the volume or on the surface of a
 /// truncated 3D cone defined by its base radius, its top radius, and the height
@@ -246,6 +432,8 @@ impl Modifier for SetPositionSphereModifier {
 ///
 /// This modifier requires the following particle attributes:
 /// - [`Attribute::POSITION`]
+///
+/// [`orientation`]: crate::modifier::position::SetPositionCone3dModifier::orientation
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Reflect, Serialize, Deserialize)]
 pub struct SetPositionCone3dModifier {
     /// The cone height along its axis, between the base and top radii.
@@ -263,9 +451,26 @@ pub struct SetPositionCone3dModifier {
     pub top_radius: ExprHandle,
     /// The shape dimension to set the position to.
     pub dimension: ShapeDimension,
+    /// Optional orientation of the cone, as a normalized quaternion with the
+    /// (x, y, z, w) layout of [`Quat`]. The rotation is applied around the
+    /// origin of the cone. A value of `None` keeps the cone axis along +Y.
+    ///
+    /// Expression type: `Vec4`
+    #[serde(default)]
+    pub orientation: Option<ExprHandle>,
 }
 
 impl SetPositionCone3dModifier {
+    /// Set the orientation of the cone.
+    ///
+    /// See [`orientation`] for details.
+    ///
+    /// [`orientation`]: crate::modifier::position::SetPositionCone3dModifier::orientation
+    pub fn with_orientation(mut self, orientation: ExprHandle) -> Self {
+        self.orientation = Some(orientation);
+        self
+    }
+
     fn eval(
         &self,
         module: &mut Module,
@@ -282,6 +487,16 @@ impl SetPositionCone3dModifier {
                 let height = ctx.eval(m, self.height)?;
                 let top_radius = ctx.eval(m, self.top_radius)?;
                 let base_radius = ctx.eval(m, self.base_radius)?;
+                let local_position = if let Some(orientation) = self.orientation {
+                    let quat_rotate = make_quat_rotate_fn(ctx);
+                    format!(
+                        "{}(normalize({}), vec3<f32>(x, y, z))",
+                        quat_rotate,
+                        ctx.eval(m, orientation)?
+                    )
+                } else {
+                    "vec3<f32>(x, y, z)".to_string()
+                };
 
                 Ok(format!(
                     r##"    // Truncated cone height
@@ -308,7 +523,7 @@ impl SetPositionCone3dModifier {
     let x = r * cost;
     let y = h;
     let z = r * sint;
-    let p = vec3<f32>(x, y, z);
+    let p = {4};
     let p2 = transform * vec4<f32>(p, 0.0);
     (*particle).{3} = p2.xyz;
 "##,
@@ -316,6 +531,7 @@ impl SetPositionCone3dModifier {
                     top_radius,
                     base_radius,
                     Attribute::POSITION.name(),
+                    local_position,
                 ))
             },
         )?;
@@ -346,3 +562,397 @@ impl Modifier for SetPositionCone3dModifier {
         Ok(())
     }
 }
+
+/// Method used to pick the location along a path where particles spawn.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Reflect, Serialize, Deserialize)]
+pub enum PathSampling {
+    /// Spawn each particle at a random location along the path, uniformly
+    /// distributed by arc length.
+    #[default]
+    Random,
+    /// Spawn the particles of a same spawn batch at locations evenly spaced by
+    /// arc length along the path, in spawn order. The first particle of the
+    /// batch spawns near the start of the path, and the last one near its end.
+    ///
+    /// This is mostly useful with bursts, to trace the entire path each time
+    /// the spawner emits.
+    Sequential,
+}
+
+/// A modifier to set the position of particles along a path.
+///
+/// The path is a polyline defined by a list of points, relative to the emitter
+/// position. Particles are positioned along that polyline according to the
+/// [`PathSampling`] method, by arc length, so that the distribution of
+/// particles is independent of the spacing of the points. If the path is
+/// closed, the last point is connected back to the first one.
+///
+/// To spawn particles along a smooth curve, use [`from_catmull_rom()`] to
+/// tessellate a Catmull-Rom spline passing through some control points into a
+/// polyline.
+///
+/// The points are baked into the generated shader code, so changing them
+/// requires recompiling the effect. Prefer a small number of points, as the
+/// cost of sampling the path is linear in the number of points.
+///
+/// # Example
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_hanabi::*;
+/// // Spawn particles along a smooth square loop
+/// let init_pos = SetPositionPathModifier::from_catmull_rom(
+///     &[
+///         Vec3::new(-1., 0., -1.),
+///         Vec3::new(1., 0., -1.),
+///         Vec3::new(1., 0., 1.),
+///         Vec3::new(-1., 0., 1.),
+///     ],
+///     8,
+///     true,
+/// );
+/// ```
+///
+/// # Attributes
+///
+/// This modifier requires the following particle attributes:
+/// - [`Attribute::POSITION`]
+///
+/// [`from_catmull_rom()`]: crate::modifier::position::SetPositionPathModifier::from_catmull_rom
+#[derive(Debug, Clone, PartialEq, Reflect, Serialize, Deserialize)]
+pub struct SetPositionPathModifier {
+    /// The points of the polyline, relative to the emitter position. There
+    /// must be at least 2 points.
+    pub points: Vec<Vec3>,
+    /// Whether the path is closed, connecting the last point back to the first
+    /// one.
+    #[serde(default)]
+    pub closed: bool,
+    /// The method used to pick the location of each particle along the path.
+    #[serde(default)]
+    pub sampling: PathSampling,
+}
+
+impl SetPositionPathModifier {
+    /// Create a new modifier positioning particles randomly along an open
+    /// polyline.
+    pub fn new(points: impl Into<Vec<Vec3>>) -> Self {
+        Self {
+            points: points.into(),
+            closed: false,
+            sampling: PathSampling::Random,
+        }
+    }
+
+    /// Create a new modifier positioning particles randomly along a uniform
+    /// Catmull-Rom spline passing through the given control points.
+    ///
+    /// The spline is tessellated on the CPU into a polyline, with
+    /// `subdivisions` segments between each pair of consecutive control
+    /// points. If `closed` is `true`, the spline loops back from the last
+    /// control point to the first one.
+    pub fn from_catmull_rom(control_points: &[Vec3], subdivisions: u32, closed: bool) -> Self {
+        let count = control_points.len();
+        if count < 2 {
+            return Self::new(control_points).with_closed(closed);
+        }
+        let subdivisions = subdivisions.max(1);
+
+        // Fetch a control point, wrapping around for closed splines, or clamping to
+        // the end points otherwise.
+        let point = |index: isize| -> Vec3 {
+            let index = if closed {
+                index.rem_euclid(count as isize)
+            } else {
+                index.clamp(0, count as isize - 1)
+            };
+            control_points[index as usize]
+        };
+
+        let span_count = if closed { count } else { count - 1 };
+        let mut points = Vec::with_capacity(span_count * subdivisions as usize + 1);
+        for span in 0..span_count as isize {
+            let (p0, p1, p2, p3) = (
+                point(span - 1),
+                point(span),
+                point(span + 1),
+                point(span + 2),
+            );
+            for step in 0..subdivisions {
+                let t = step as f32 / subdivisions as f32;
+                let t2 = t * t;
+                let t3 = t2 * t;
+                points.push(
+                    0.5 * (2. * p1
+                        + (p2 - p0) * t
+                        + (2. * p0 - 5. * p1 + 4. * p2 - p3) * t2
+                        + (3. * p1 - p0 - 3. * p2 + p3) * t3),
+                );
+            }
+        }
+        if !closed {
+            points.push(control_points[count - 1]);
+        }
+
+        Self::new(points).with_closed(closed)
+    }
+
+    /// Set whether the path is closed.
+    pub fn with_closed(mut self, closed: bool) -> Self {
+        self.closed = closed;
+        self
+    }
+
+    /// Set the method used to pick the location of each particle along the
+    /// path.
+    pub fn with_sampling(mut self, sampling: PathSampling) -> Self {
+        self.sampling = sampling;
+        self
+    }
+
+    /// Get the points of the polyline actually sampled, including the closing
+    /// point for a closed path, and their cumulated arc length normalized to
+    /// `[0:1]`.
+    pub(crate) fn polyline(&self) -> (Vec<Vec3>, Vec<f32>) {
+        let mut points = self.points.clone();
+        if self.closed {
+            points.push(points[0]);
+        }
+
+        let mut lengths = Vec::with_capacity(points.len());
+        let mut total = 0.;
+        lengths.push(0.);
+        for pair in points.windows(2) {
+            total += pair[0].distance(pair[1]);
+            lengths.push(total);
+        }
+        let count = lengths.len();
+        if total > 0. {
+            lengths.iter_mut().for_each(|l| *l /= total);
+        } else {
+            // Degenerate path; fall back to a uniform parametrization.
+            lengths
+                .iter_mut()
+                .enumerate()
+                .for_each(|(i, l)| *l = i as f32 / (count - 1) as f32);
+        }
+
+        (points, lengths)
+    }
+}
+
+impl Eq for SetPositionPathModifier {}
+
+impl Hash for SetPositionPathModifier {
+    fn hash<H>(&self, state: &mut H)
+    where
+        H: Hasher,
+    {
+        self.points.len().hash(state);
+        for p in &self.points {
+            FloatOrd(p.x).hash(state);
+            FloatOrd(p.y).hash(state);
+            FloatOrd(p.z).hash(state);
+        }
+        self.closed.hash(state);
+        self.sampling.hash(state);
+    }
+}
+
+#[typetag::serde]
+impl Modifier for SetPositionPathModifier {
+    fn context(&self) -> ModifierContext {
+        ModifierContext::Init
+    }
+
+    fn attributes(&self) -> &[Attribute] {
+        &[Attribute::POSITION]
+    }
+
+    fn boxed_clone(&self) -> BoxedModifier {
+        Box::new(self.clone())
+    }
+
+    fn apply(&self, module: &mut Module, context: &mut ShaderWriter) -> Result<(), ExprError> {
+        if self.points.len() < 2 {
+            return Err(ExprError::GraphEvalError(format!(
+                "SetPositionPathModifier requires at least 2 points, found {}.",
+                self.points.len()
+            )));
+        }
+
+        let func_id = calc_func_id(self);
+        let func_name = format!("set_position_path_{0:016X}", func_id);
+
+        let (points, lengths) = self.polyline();
+        let count = points.len();
+        let points = points
+            .iter()
+            .map(|p| p.to_wgsl_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let lengths = lengths
+            .iter()
+            .map(|l| l.to_wgsl_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        context.make_fn(
+            &func_name,
+            "particle: ptr<function, Particle>, t: f32",
+            module,
+            &mut |_m: &mut Module, _ctx: &mut dyn EvalContext| -> Result<String, ExprError> {
+                Ok(format!(
+                    r##"    var points = array<vec3<f32>, {count}>({points});
+    // Cumulated arc length of each point, normalized to [0:1]
+    var lengths = array<f32, {count}>({lengths});
+    let s = clamp(t, 0., 1.);
+    // Find the segment containing s
+    var i = 1u;
+    for (var k = 1u; k < {count}u; k += 1u) {{
+        i = k;
+        if (s <= lengths[k]) {{
+            break;
+        }}
+    }}
+    let len = max(lengths[i] - lengths[i - 1u], 1e-9);
+    let f = clamp((s - lengths[i - 1u]) / len, 0., 1.);
+    (*particle).{pos} = mix(points[i - 1u], points[i], f);
+"##,
+                    pos = Attribute::POSITION.name(),
+                ))
+            },
+        )?;
+
+        let t = match self.sampling {
+            PathSampling::Random => "frand()",
+            PathSampling::Sequential => {
+                "(f32(global_invocation_id.x) + 0.5) / f32(max(spawn_count, 1u))"
+            }
+        };
+        context.main_code += &format!("{}(&particle, {});\n", func_name, t);
+
+        Ok(())
+    }
+}
+
+/// A modifier to set the position and velocity of particles on a disc facing
+/// the camera, randomly.
+///
+/// At spawn time, the disc is oriented so that its normal points toward the
+/// main camera, that is the active camera with the lowest [`Camera::order`].
+/// Particles are positioned on or inside the disc, and move outward within the
+/// plane of the disc. This is useful for impact flashes, muzzle flashes, or
+/// bullet impacts, which should read the same from any viewing angle.
+///
+/// The camera position is sampled once when the particle spawns. The disc is
+/// not reoriented afterward, so particles keep their initial plane and velocity
+/// even if the camera moves. Combine with [`OrientModifier`] in
+/// [`OrientMode::FaceCameraPosition`] if the particle quads themselves should
+/// keep facing the camera.
+///
+/// # Attributes
+///
+/// This modifier requires the following particle attributes:
+/// - [`Attribute::POSITION`]
+/// - [`Attribute::VELOCITY`]
+///
+/// [`Camera::order`]: bevy::render::camera::Camera::order
+/// [`OrientModifier`]: crate::modifier::OrientModifier
+/// [`OrientMode::FaceCameraPosition`]: crate::modifier::OrientMode::FaceCameraPosition
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Reflect, Serialize, Deserialize)]
+pub struct SetPositionCameraFacingDiscModifier {
+    /// The disc center, relative to the emitter position.
+    ///
+    /// Expression type: `Vec3`
+    pub center: ExprHandle,
+    /// The disc radius.
+    ///
+    /// Expression type: `f32`
+    pub radius: ExprHandle,
+    /// The speed of the particles, moving outward from the disc center within
+    /// the plane of the disc.
+    ///
+    /// Expression type: `f32`
+    pub speed: ExprHandle,
+    /// The shape dimension to set the position to.
+    ///
+    /// As for [`SetPositionCircleModifier`], [`ShapeDimension::Volume`] spawns
+    /// particles anywhere on the disc, while [`ShapeDimension::Surface`] spawns
+    /// them on the perimeter circle only.
+    pub dimension: ShapeDimension,
+}
+
+#[typetag::serde]
+impl Modifier for SetPositionCameraFacingDiscModifier {
+    fn context(&self) -> ModifierContext {
+        ModifierContext::Init
+    }
+
+    fn attributes(&self) -> &[Attribute] {
+        &[Attribute::POSITION, Attribute::VELOCITY]
+    }
+
+    fn boxed_clone(&self) -> BoxedModifier {
+        Box::new(*self)
+    }
+
+    fn apply(&self, module: &mut Module, context: &mut ShaderWriter) -> Result<(), ExprError> {
+        let func_id = calc_func_id(self);
+        let func_name = format!("set_position_camera_facing_disc_{0:016X}", func_id);
+
+        context.make_fn(
+            &func_name,
+            "particle: ptr<function, Particle>",
+            module,
+            &mut |m: &mut Module, ctx: &mut dyn EvalContext| -> Result<String, ExprError> {
+                let center = ctx.eval(m, self.center)?;
+                let speed = ctx.eval(m, self.speed)?;
+                let radius = match self.dimension {
+                    ShapeDimension::Surface => format!("let r = {};", ctx.eval(m, self.radius)?),
+                    ShapeDimension::Volume => {
+                        format!("let r = sqrt(frand()) * ({});", ctx.eval(m, self.radius)?)
+                    }
+                };
+
+                Ok(format!(
+                    r##"    // Disc center
+    let c = {center};
+    // Camera position in emitter space. The init code always works in emitter space,
+    // even for global space simulation.
+    let cam = vec4<f32>(sim_params.camera_position, 1.0);
+    let cam_pos = vec3<f32>(
+        dot(spawner.inverse_transform[0], cam),
+        dot(spawner.inverse_transform[1], cam),
+        dot(spawner.inverse_transform[2], cam),
+    );
+    // Disc normal toward the camera, falling back to +Z if the camera is on the center
+    let to_cam = cam_pos - c;
+    var n = vec3<f32>(0.0, 0.0, 1.0);
+    if (dot(to_cam, to_cam) > 1e-12) {{
+        n = normalize(to_cam);
+    }}
+    // Disc basis
+    let sign = step(0.0, n.z) * 2.0 - 1.0;
+    let a = -1.0 / (sign + n.z);
+    let b = n.x * n.y * a;
+    let tangent = vec3<f32>(1.0 + sign * n.x * n.x * a, sign * b, -sign * n.x);
+    let bitangent = vec3<f32>(b, sign + n.y * n.y * a, -n.y);
+    // Disc radius
+    {radius}
+    // Spawn random point on/in disc, moving outward in the disc plane
+    let theta = frand() * tau;
+    let dir = tangent * cos(theta) + bitangent * sin(theta);
+    (*particle).{pos} = c + r * dir;
+    (*particle).{vel} = dir * ({speed});
+"##,
+                    pos = Attribute::POSITION.name(),
+                    vel = Attribute::VELOCITY.name(),
+                ))
+            },
+        )?;
+
+        context.main_code += &format!("{}(&particle);\n", func_name);
+        Ok(())
+    }
+}
@@ -13,12 +13,30 @@ use bevy::prelude::*;
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    calc_func_id, graph::ExprError, Attribute, BoxedModifier, EvalContext, ExprHandle, Modifier,
-    ModifierContext, Module, ShaderWriter,
+    calc_func_id,
+    graph::ExprError,
+    make_quat_rotate_fn,
+    modifier::position::{
+        SetPositionBoxModifier, SetPositionCone3dModifier, SetPositionSphereModifier,
+    },
+    Attribute, BoxedModifier, EvalContext, ExprHandle, Modifier, ModifierContext, Module,
+    ShaderWriter,
 };
 
 /// A modifier to set the velocity of particles radially on a circle.
 ///
+/// The velocity is directed radially outward from the circle `center`, within
+/// the plane of the circle defined by its `axis`. Use a negative `speed` to
+/// direct the velocity inward toward the center instead. Particles located
+/// exactly at the center (or anywhere on the circle axis) are assigned a
+/// random radial direction in the circle plane.
+///
+/// This pairs with [`SetPositionCircleModifier`] for planar emission like 2D
+/// shockwaves. Unlike [`SetVelocitySphereModifier`], the velocity has no
+/// component along the circle axis.
+///
+/// [`SetPositionCircleModifier`]: crate::modifier::position::SetPositionCircleModifier
+///
 /// # Attributes
 ///
 /// This modifier requires the following particle attributes:
@@ -35,7 +53,8 @@ pub struct SetVelocityCircleModifier {
     ///
     /// Expression type: `Vec3`
     pub axis: ExprHandle,
-    /// The initial speed distribution of a particle when it spawns.
+    /// The initial speed distribution of a particle when it spawns. A
+    /// negative speed directs the velocity inward.
     ///
     /// Expression type: `f32`
     pub speed: ExprHandle,
@@ -61,7 +80,22 @@ impl SetVelocityCircleModifier {
 
                 Ok(format!(
                     r##"    let delta = (*particle).{0} - ({1});
-    let radial = normalize(delta - dot(delta, {2}) * ({2}));
+    let n = {2};
+    let delta_in_plane = delta - dot(delta, n) * n;
+    var radial: vec3<f32>;
+    if (dot(delta_in_plane, delta_in_plane) > 1e-12) {{
+        radial = normalize(delta_in_plane);
+    }} else {{
+        // Particle at the circle center (or on its axis); pick a random radial
+        // direction in the circle plane instead of normalizing a null vector.
+        let sign = step(0.0, n.z) * 2.0 - 1.0;
+        let a = -1.0 / (sign + n.z);
+        let b = n.x * n.y * a;
+        let tangent = vec3<f32>(1.0 + sign * n.x * n.x * a, sign * b, -sign * n.x);
+        let bitangent = vec3<f32>(b, sign + n.y * n.y * a, -n.y);
+        let theta = frand() * tau;
+        radial = tangent * cos(theta) + bitangent * sin(theta);
+    }}
     let radial_vec4 = transform * vec4<f32>(radial.xyz, 0.0);
     (*particle).{3} = radial_vec4.xyz * ({4});
 "##,
@@ -130,7 +164,7 @@ impl SetVelocitySphereModifier {
         let center = context.eval(module, self.center)?;
         let speed = context.eval(module, self.speed)?;
         let code = format!(
-            "particle.{} = normalize(particle.{} - ({})) * ({});\n",
+            "particle.{} = safe_normalize(particle.{} - ({})) * ({});\n",
             Attribute::VELOCITY.name(),
             Attribute::POSITION.name(),
             center,
@@ -206,7 +240,7 @@ impl SetVelocityTangentModifier {
 
                 Ok(format!(
                     r##"    let radial = (*particle).{0} - ({1});
-    let tangent = normalize(cross({2}, radial));
+    let tangent = safe_normalize(cross({2}, radial));
     let tangent_vec4 = transform * vec4<f32>(tangent.xyz, 0.0);
     (*particle).{3} = tangent_vec4.xyz * ({4});
 "##,
@@ -245,3 +279,575 @@ impl Modifier for SetVelocityTangentModifier {
         Ok(())
     }
 }
+
+/// A modifier to set the velocity of particles within a cone of directions.
+///
+/// This is the canonical "spray" emitter, for shotgun blasts, sparks, or
+/// water jets. The velocity of each particle is directed along a random
+/// direction inside a cone around the central [`direction`], with a half
+/// angle of [`spread`] radians, and has a magnitude of [`speed`]. The
+/// directions are distributed uniformly over the solid angle of the cone, so
+/// they don't cluster around its axis. A spread of `0` emits all particles
+/// along the central direction, while a spread of `PI` emits them in all
+/// directions.
+///
+/// Optionally, the position of the particles can be set to the [`apex`] of
+/// the cone, which makes this modifier a complete emitter on its own.
+///
+/// # Example
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_hanabi::*;
+/// let writer = ExprWriter::new();
+/// // Sparks spraying upward within 15 degrees of the vertical
+/// let modifier = SetVelocityConeModifier::new(
+///     writer.lit(Vec3::Y).expr(),
+///     writer.lit(15_f32.to_radians()).expr(),
+///     writer.lit(4.).uniform(writer.lit(6.)).expr(),
+/// )
+/// .with_apex(writer.lit(Vec3::ZERO).expr());
+/// ```
+///
+/// # Attributes
+///
+/// This modifier requires the following particle attributes:
+/// - [`Attribute::VELOCITY`]
+/// - [`Attribute::POSITION`], only if [`apex`] is set
+///
+/// [`direction`]: crate::SetVelocityConeModifier::direction
+/// [`spread`]: crate::SetVelocityConeModifier::spread
+/// [`speed`]: crate::SetVelocityConeModifier::speed
+/// [`apex`]: crate::SetVelocityConeModifier::apex
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Reflect, Serialize, Deserialize)]
+pub struct SetVelocityConeModifier {
+    /// The central direction of the cone. This doesn't need to be normalized.
+    ///
+    /// Expression type: `Vec3`
+    pub direction: ExprHandle,
+    /// The half angle of the cone, in radians, in `[0:PI]`.
+    ///
+    /// Expression type: `f32`
+    pub spread: ExprHandle,
+    /// The initial speed distribution of a particle when it spawns.
+    ///
+    /// Expression type: `f32`
+    pub speed: ExprHandle,
+    /// The apex of the cone, relative to the emitter position, if any.
+    ///
+    /// If set, the position of the particles is also set to this apex.
+    ///
+    /// Expression type: `Vec3`
+    #[serde(default)]
+    pub apex: Option<ExprHandle>,
+}
+
+impl SetVelocityConeModifier {
+    /// Create a new modifier emitting particles within a cone of directions.
+    pub fn new(direction: ExprHandle, spread: ExprHandle, speed: ExprHandle) -> Self {
+        Self {
+            direction,
+            spread,
+            speed,
+            apex: None,
+        }
+    }
+
+    /// Set the position of the particles to the apex of the cone.
+    pub fn with_apex(mut self, apex: ExprHandle) -> Self {
+        self.apex = Some(apex);
+        self
+    }
+
+    fn eval(
+        &self,
+        module: &mut Module,
+        context: &mut dyn EvalContext,
+    ) -> Result<String, ExprError> {
+        let func_id = calc_func_id(self);
+        let func_name = format!("set_velocity_cone_{0:016X}", func_id);
+
+        context.make_fn(
+            &func_name,
+            "transform: mat4x4<f32>, particle: ptr<function, Particle>",
+            module,
+            &mut |m: &mut Module, ctx: &mut dyn EvalContext| -> Result<String, ExprError> {
+                let direction = ctx.eval(m, self.direction)?;
+                let spread = ctx.eval(m, self.spread)?;
+                let speed = ctx.eval(m, self.speed)?;
+                let apex = if let Some(apex) = self.apex {
+                    format!(
+                        "    (*particle).{} = {};\n",
+                        Attribute::POSITION.name(),
+                        ctx.eval(m, apex)?
+                    )
+                } else {
+                    String::new()
+                };
+
+                Ok(format!(
+                    r##"{0}    let n = safe_normalize({1});
+    // Cone basis
+    let sign = step(0.0, n.z) * 2.0 - 1.0;
+    let a = -1.0 / (sign + n.z);
+    let b = n.x * n.y * a;
+    let tangent = vec3<f32>(1.0 + sign * n.x * n.x * a, sign * b, -sign * n.x);
+    let bitangent = vec3<f32>(b, sign + n.y * n.y * a, -n.y);
+    // Uniform distribution over the spherical cap of the cone
+    let cos_theta = mix(1.0, cos(clamp({2}, 0.0, 0.5 * tau)), frand());
+    let sin_theta = sqrt(max(0.0, 1.0 - cos_theta * cos_theta));
+    let phi = frand() * tau;
+    let dir = n * cos_theta + (tangent * cos(phi) + bitangent * sin(phi)) * sin_theta;
+    let dir_vec4 = transform * vec4<f32>(dir, 0.0);
+    (*particle).{3} = dir_vec4.xyz * ({4});
+"##,
+                    apex,
+                    direction,
+                    spread,
+                    Attribute::VELOCITY.name(),
+                    speed,
+                ))
+            },
+        )?;
+
+        let code = format!("{}(transform, &particle);\n", func_name);
+
+        Ok(code)
+    }
+}
+
+#[typetag::serde]
+impl Modifier for SetVelocityConeModifier {
+    fn context(&self) -> ModifierContext {
+        ModifierContext::Init
+    }
+
+    fn attributes(&self) -> &[Attribute] {
+        if self.apex.is_some() {
+            &[Attribute::POSITION, Attribute::VELOCITY]
+        } else {
+            &[Attribute::VELOCITY]
+        }
+    }
+
+    fn boxed_clone(&self) -> BoxedModifier {
+        Box::new(*self)
+    }
+
+    fn apply(&self, module: &mut Module, context: &mut ShaderWriter) -> Result<(), ExprError> {
+        let code = self.eval(module, context)?;
+        context.main_code += &code;
+        Ok(())
+    }
+}
+
+/// Shape of an emitter, used by [`SetVelocityNormalModifier`] to derive the
+/// outward surface normal at the particle position.
+///
+/// Each variant mirrors the parameters of the position modifier emitting on
+/// that shape. Use the `From` conversions from those modifiers to keep both in
+/// sync.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Reflect, Serialize, Deserialize)]
+pub enum EmitterShape {
+    /// A sphere, as emitted by [`SetPositionSphereModifier`].
+    ///
+    /// The normal is the radial direction from the sphere center.
+    ///
+    /// [`SetPositionSphereModifier`]: crate::modifier::position::SetPositionSphereModifier
+    Sphere {
+        /// The sphere center, relative to the emitter position.
+        ///
+        /// Expression type: `Vec3`
+        center: ExprHandle,
+    },
+    /// An axis-aligned box, as emitted by [`SetPositionBoxModifier`].
+    ///
+    /// The normal is the normal of the box face closest to the particle,
+    /// relative to the box size.
+    ///
+    /// [`SetPositionBoxModifier`]: crate::modifier::position::SetPositionBoxModifier
+    Box {
+        /// The box center, relative to the emitter position.
+        ///
+        /// Expression type: `Vec3`
+        center: ExprHandle,
+        /// The box half extents.
+        ///
+        /// Expression type: `Vec3`
+        half_extents: ExprHandle,
+        /// Optional orientation of the box, as a normalized quaternion.
+        ///
+        /// Expression type: `Vec4`
+        orientation: Option<ExprHandle>,
+    },
+    /// A truncated cone, as emitted by [`SetPositionCone3dModifier`].
+    ///
+    /// The normal is the normal of the lateral surface of the cone, at the
+    /// angle around the cone axis where the particle is located. The base and
+    /// top caps of the cone are ignored.
+    ///
+    /// [`SetPositionCone3dModifier`]: crate::modifier::position::SetPositionCone3dModifier
+    Cone {
+        /// The cone height along its axis.
+        ///
+        /// Expression type: `f32`
+        height: ExprHandle,
+        /// The cone radius at its base.
+        ///
+        /// Expression type: `f32`
+        base_radius: ExprHandle,
+        /// The cone radius at its truncated top.
+        ///
+        /// Expression type: `f32`
+        top_radius: ExprHandle,
+        /// Optional orientation of the cone, as a normalized quaternion.
+        ///
+        /// Expression type: `Vec4`
+        orientation: Option<ExprHandle>,
+    },
+}
+
+impl From<&SetPositionSphereModifier> for EmitterShape {
+    fn from(modifier: &SetPositionSphereModifier) -> Self {
+        Self::Sphere {
+            center: modifier.center,
+        }
+    }
+}
+
+impl From<&SetPositionBoxModifier> for EmitterShape {
+    fn from(modifier: &SetPositionBoxModifier) -> Self {
+        Self::Box {
+            center: modifier.center,
+            half_extents: modifier.half_extents,
+            orientation: modifier.orientation,
+        }
+    }
+}
+
+impl From<&SetPositionCone3dModifier> for EmitterShape {
+    fn from(modifier: &SetPositionCone3dModifier) -> Self {
+        Self::Cone {
+            height: modifier.height,
+            base_radius: modifier.base_radius,
+            top_radius: modifier.top_radius,
+            orientation: modifier.orientation,
+        }
+    }
+}
+
+/// A modifier to set the velocity of particles along the outward surface
+/// normal of the emitter shape.
+///
+/// Particles shoot perpendicularly to the surface they spawned on, which is
+/// the natural pairing with surface emission for explosions and bursts. The
+/// normal is derived from the current particle position and the [`shape`],
+/// optionally perturbed by a random [`normal_jitter`], and scaled by the
+/// [`speed`].
+///
+/// # Composition with the position modifier
+///
+/// This modifier doesn't set the particle position. Instead it reads the
+/// position assigned by a position modifier, so it must be applied _after_
+/// that modifier, and its [`shape`] must use the same parameters. Build the
+/// shape from the position modifier itself to ensure this:
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_hanabi::*;
+/// let writer = ExprWriter::new();
+/// let init_pos = SetPositionSphereModifier {
+///     center: writer.lit(Vec3::ZERO).expr(),
+///     radius: writer.lit(2.).expr(),
+///     dimension: ShapeDimension::Surface,
+///     thickness: None,
+/// };
+/// let init_vel = SetVelocityNormalModifier::new((&init_pos).into(), writer.lit(5.).expr())
+///     .with_normal_jitter(writer.lit(0.2).expr());
+/// ```
+///
+/// When emitting in the volume of the shape, the normal is the one of the
+/// closest surface, so particles still move outward. Particles located exactly
+/// on the axis of a sphere or cone are assigned a random direction.
+///
+/// # Attributes
+///
+/// This modifier requires the following particle attributes:
+/// - [`Attribute::POSITION`]
+/// - [`Attribute::VELOCITY`]
+///
+/// [`shape`]: crate::SetVelocityNormalModifier::shape
+/// [`normal_jitter`]: crate::SetVelocityNormalModifier::normal_jitter
+/// [`speed`]: crate::SetVelocityNormalModifier::speed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Reflect, Serialize, Deserialize)]
+pub struct SetVelocityNormalModifier {
+    /// The shape of the emitter the particles spawned on.
+    pub shape: EmitterShape,
+    /// The initial speed distribution of a particle when it spawns. A
+    /// negative speed directs the velocity inward.
+    ///
+    /// Expression type: `f32`
+    pub speed: ExprHandle,
+    /// Optional amount of random perturbation of the normal direction.
+    ///
+    /// The velocity direction is the normal offset by a random unit vector
+    /// scaled by this amount, then normalized. A value of `None` or zero
+    /// emits exactly along the normal.
+    ///
+    /// Expression type: `f32`
+    #[serde(default)]
+    pub normal_jitter: Option<ExprHandle>,
+}
+
+impl SetVelocityNormalModifier {
+    /// Create a new modifier setting the velocity along the outward normal of
+    /// the given emitter shape.
+    pub fn new(shape: EmitterShape, speed: ExprHandle) -> Self {
+        Self {
+            shape,
+            speed,
+            normal_jitter: None,
+        }
+    }
+
+    /// Set the amount of random perturbation of the normal direction.
+    pub fn with_normal_jitter(mut self, normal_jitter: ExprHandle) -> Self {
+        self.normal_jitter = Some(normal_jitter);
+        self
+    }
+
+    fn eval(
+        &self,
+        module: &mut Module,
+        context: &mut dyn EvalContext,
+    ) -> Result<String, ExprError> {
+        let func_id = calc_func_id(self);
+        let func_name = format!("set_velocity_normal_{0:016X}", func_id);
+
+        context.make_fn(
+            &func_name,
+            "transform: mat4x4<f32>, particle: ptr<function, Particle>",
+            module,
+            &mut |m: &mut Module, ctx: &mut dyn EvalContext| -> Result<String, ExprError> {
+                let normal = match self.shape {
+                    EmitterShape::Sphere { center } => format!(
+                        r##"    let delta = (*particle).{0} - ({1});
+    var n: vec3<f32>;
+    if (dot(delta, delta) > 1e-12) {{
+        n = normalize(delta);
+    }} else {{
+        // Particle at the sphere center; pick a random direction instead of
+        // normalizing a null vector.
+        let theta = frand() * tau;
+        let z = frand() * 2. - 1.;
+        let sinphi = sqrt(max(0., 1. - z * z));
+        n = vec3<f32>(sinphi * cos(theta), sinphi * sin(theta), z);
+    }}
+"##,
+                        Attribute::POSITION.name(),
+                        ctx.eval(m, center)?,
+                    ),
+                    EmitterShape::Box {
+                        center,
+                        half_extents,
+                        orientation,
+                    } => {
+                        let center = ctx.eval(m, center)?;
+                        let half_extents = ctx.eval(m, half_extents)?;
+                        let (to_local, to_emitter) = if let Some(orientation) = orientation {
+                            let quat_rotate = make_quat_rotate_fn(ctx);
+                            (
+                                format!(
+                                    "let q = normalize({0});\n    let delta = {1}(vec4<f32>(-q.xyz, q.w), (*particle).{2} - ({3}));",
+                                    ctx.eval(m, orientation)?,
+                                    quat_rotate,
+                                    Attribute::POSITION.name(),
+                                    center
+                                ),
+                                format!("{}(q, n_local)", quat_rotate),
+                            )
+                        } else {
+                            (
+                                format!(
+                                    "let delta = (*particle).{0} - ({1});",
+                                    Attribute::POSITION.name(),
+                                    center
+                                ),
+                                "n_local".to_string(),
+                            )
+                        };
+                        format!(
+                            r##"    {0}
+    // Position relative to the box size, in [-1:1]^3 inside the box
+    let p = delta / max(abs({1}), vec3<f32>(1e-6));
+    let ap = abs(p);
+    // Normal of the face closest to the particle
+    var n_local: vec3<f32>;
+    if (ap.x >= ap.y && ap.x >= ap.z) {{
+        n_local = vec3<f32>(select(-1.0, 1.0, p.x >= 0.0), 0.0, 0.0);
+    }} else if (ap.y >= ap.z) {{
+        n_local = vec3<f32>(0.0, select(-1.0, 1.0, p.y >= 0.0), 0.0);
+    }} else {{
+        n_local = vec3<f32>(0.0, 0.0, select(-1.0, 1.0, p.z >= 0.0));
+    }}
+    let n = {2};
+"##,
+                            to_local, half_extents, to_emitter,
+                        )
+                    }
+                    EmitterShape::Cone {
+                        height,
+                        base_radius,
+                        top_radius,
+                        orientation,
+                    } => {
+                        let height = ctx.eval(m, height)?;
+                        let base_radius = ctx.eval(m, base_radius)?;
+                        let top_radius = ctx.eval(m, top_radius)?;
+                        let (to_local, to_emitter) = if let Some(orientation) = orientation {
+                            let quat_rotate = make_quat_rotate_fn(ctx);
+                            (
+                                format!(
+                                    "let q = normalize({0});\n    let p_local = {1}(vec4<f32>(-q.xyz, q.w), p_emitter);",
+                                    ctx.eval(m, orientation)?,
+                                    quat_rotate
+                                ),
+                                format!("{}(q, n_local)", quat_rotate),
+                            )
+                        } else {
+                            (
+                                "let p_local = p_emitter;".to_string(),
+                                "n_local".to_string(),
+                            )
+                        };
+                        format!(
+                            r##"    // The cone position was transformed by the emitter transform. Use the
+    // cofactor matrix of that transform to map the position back into the
+    // emitter frame, and the normal out of it.
+    let m = mat3x3<f32>(transform[0].xyz, transform[1].xyz, transform[2].xyz);
+    let cof = mat3x3<f32>(cross(m[1], m[2]), cross(m[2], m[0]), cross(m[0], m[1]));
+    let det = dot(m[0], cof[0]);
+    let p_emitter = (transpose(cof) * (*particle).{0}) / select(det, 1.0, abs(det) < 1e-12);
+    {1}
+    // Truncated cone height and radii
+    let h = {2};
+    let rb = {3};
+    let rt = {4};
+    // Radial direction around the cone axis
+    var radial = p_local.xz;
+    if (dot(radial, radial) > 1e-12) {{
+        radial = normalize(radial);
+    }} else {{
+        let theta = frand() * tau;
+        radial = vec2<f32>(cos(theta), sin(theta));
+    }}
+    // Normal of the lateral surface, tilted by the cone slope
+    let n_local = normalize(vec3<f32>(radial.x * h, rb - rt, radial.y * h));
+    let n_emitter = {5};
+    let n = normalize(cof * n_emitter) * select(1.0, -1.0, det < 0.0);
+"##,
+                            Attribute::POSITION.name(),
+                            to_local,
+                            height,
+                            base_radius,
+                            top_radius,
+                            to_emitter,
+                        )
+                    }
+                };
+
+                let jitter = if let Some(normal_jitter) = self.normal_jitter {
+                    format!(
+                        r##"    // Perturb the normal with a random unit vector
+    let jitter_theta = frand() * tau;
+    let jitter_z = frand() * 2. - 1.;
+    let jitter_sinphi = sqrt(max(0., 1. - jitter_z * jitter_z));
+    let jitter_dir = vec3<f32>(jitter_sinphi * cos(jitter_theta), jitter_sinphi * sin(jitter_theta), jitter_z);
+    let dir = safe_normalize(n + jitter_dir * ({0}));
+"##,
+                        ctx.eval(m, normal_jitter)?
+                    )
+                } else {
+                    "    let dir = n;\n".to_string()
+                };
+
+                let speed = ctx.eval(m, self.speed)?;
+
+                Ok(format!(
+                    "{0}{1}    (*particle).{2} = dir * ({3});\n",
+                    normal,
+                    jitter,
+                    Attribute::VELOCITY.name(),
+                    speed,
+                ))
+            },
+        )?;
+
+        let code = format!("{}(transform, &particle);\n", func_name);
+
+        Ok(code)
+    }
+}
+
+#[typetag::serde]
+impl Modifier for SetVelocityNormalModifier {
+    fn context(&self) -> ModifierContext {
+        ModifierContext::Init | ModifierContext::Update
+    }
+
+    fn attributes(&self) -> &[Attribute] {
+        &[Attribute::POSITION, Attribute::VELOCITY]
+    }
+
+    fn boxed_clone(&self) -> BoxedModifier {
+        Box::new(*self)
+    }
+
+    fn apply(&self, module: &mut Module, context: &mut ShaderWriter) -> Result<(), ExprError> {
+        let code = self.eval(module, context)?;
+        context.main_code += &code;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ParticleLayout, PropertyLayout};
+
+    use super::*;
+
+    #[test]
+    fn mod_velocity_cone() {
+        let mut module = Module::default();
+        let direction = module.lit(Vec3::Y);
+        let spread = module.lit(0.3);
+        let speed = module.lit(5.);
+        let modifier = SetVelocityConeModifier::new(direction, spread, speed);
+        assert_eq!(modifier.context(), ModifierContext::Init);
+        assert_eq!(modifier.attributes(), &[Attribute::VELOCITY]);
+
+        let property_layout = PropertyLayout::default();
+        let particle_layout = ParticleLayout::default();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Init, &property_layout, &particle_layout);
+        assert!(modifier.apply(&mut module, &mut context).is_ok());
+
+        assert!(context.main_code.contains("set_velocity_cone_"));
+        assert!(context.main_code.contains("(transform, &particle);"));
+        assert!(context.extra_code.contains("fn set_velocity_cone_"));
+        assert!(context.extra_code.contains("(*particle).velocity = "));
+        assert!(!context.extra_code.contains("(*particle).position = "));
+
+        // With an apex, the particle position is set too
+        let apex = module.lit(Vec3::X);
+        let modifier = modifier.with_apex(apex);
+        assert_eq!(
+            modifier.attributes(),
+            &[Attribute::POSITION, Attribute::VELOCITY]
+        );
+        let mut context =
+            ShaderWriter::new(ModifierContext::Init, &property_layout, &particle_layout);
+        assert!(modifier.apply(&mut module, &mut context).is_ok());
+        assert!(context.extra_code.contains("(*particle).position = "));
+    }
+}
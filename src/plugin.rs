@@ -15,24 +15,30 @@ use bevy::{
     time::{virtual_time_system, TimeSystem},
 };
 
+#[cfg(feature = "2d")]
+use crate::update_bounds_2d;
 use crate::{
     asset::{EffectAsset, EffectAssetLoader},
     compile_effects, gather_removed_effects,
     properties::EffectProperties,
     render::{
         extract_effect_events, extract_effects, prepare_effects, prepare_resources, queue_effects,
-        DispatchIndirectPipeline, DrawEffects, EffectAssetEvents, EffectBindGroups, EffectSystems,
-        EffectsMeta, ExtractedEffects, GpuSpawnerParams, ParticlesInitPipeline,
+        readback_effect_stats, update_effect_stats, DispatchIndirectPipeline, DrawEffects,
+        EffectAssetEvents, EffectBindGroups, EffectStats, EffectStatsChannel, EffectStatsReadback,
+        EffectSystems, EffectsMeta, ExtractedEffects, GpuSpawnerParams, ParticlesInitPipeline,
         ParticlesRenderPipeline, ParticlesUpdatePipeline, ShaderCache, SimParams,
         VfxSimulateDriverNode, VfxSimulateNode,
     },
-    spawn::{self, Random},
+    spawn::{self, apply_screen_coverage_budget, Random},
     tick_spawners,
     time::effect_simulation_time_system,
-    update_properties_from_asset, EffectSimulation, ParticleEffect, RemovedEffectsEvent, Spawner,
+    update_properties_from_asset, EffectInstance, EffectSimulation, ParticleEffect,
+    RemovedEffectsEvent, Spawner,
 };
 
+/// Labels for the Hanabi nodes of the main render graph.
 pub mod main_graph {
+    /// Render graph node labels.
     pub mod node {
         use bevy::render::render_graph::RenderLabel;
 
@@ -42,6 +48,7 @@ pub mod main_graph {
     }
 }
 
+/// Labels for the Hanabi simulation sub-graph and its nodes.
 pub mod simulate_graph {
     use bevy::render::render_graph::RenderSubGraph;
 
@@ -49,6 +56,7 @@ pub mod simulate_graph {
     #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, RenderSubGraph)]
     pub struct HanabiSimulateGraph;
 
+    /// Render graph node labels.
     pub mod node {
         use bevy::render::render_graph::RenderLabel;
 
@@ -101,6 +109,7 @@ impl Plugin for HanabiPlugin {
             .init_resource::<ShaderCache>()
             .init_asset_loader::<EffectAssetLoader>()
             .init_resource::<Time<EffectSimulation>>()
+            .init_resource::<EffectStatsChannel>()
             .configure_sets(
                 PostUpdate,
                 (
@@ -110,6 +119,7 @@ impl Plugin for HanabiPlugin {
                         .after(VisibilitySystems::VisibilityPropagate),
                     EffectSystems::CompileEffects,
                     EffectSystems::GatherRemovedEffects,
+                    EffectSystems::UpdateEffectStats.after(EffectSystems::TickSpawners),
                 ),
             )
             .configure_sets(
@@ -125,16 +135,26 @@ impl Plugin for HanabiPlugin {
             .add_systems(
                 PostUpdate,
                 (
+                    apply_screen_coverage_budget
+                        .in_set(EffectSystems::TickSpawners)
+                        .before(tick_spawners),
                     tick_spawners.in_set(EffectSystems::TickSpawners),
                     compile_effects.in_set(EffectSystems::CompileEffects),
+                    #[cfg(feature = "2d")]
+                    update_bounds_2d
+                        .in_set(EffectSystems::CompileEffects)
+                        .before(VisibilitySystems::CheckVisibility),
                     update_properties_from_asset.in_set(EffectSystems::UpdatePropertiesFromAsset),
                     gather_removed_effects.in_set(EffectSystems::GatherRemovedEffects),
+                    update_effect_stats.in_set(EffectSystems::UpdateEffectStats),
                 ),
             );
 
         // Register types with reflection
         app.register_type::<EffectAsset>()
             .register_type::<ParticleEffect>()
+            .register_type::<EffectInstance>()
+            .register_type::<EffectStats>()
             .register_type::<EffectProperties>()
             .register_type::<Spawner>()
             .register_type::<Time<EffectSimulation>>();
@@ -174,11 +194,13 @@ impl Plugin for HanabiPlugin {
         }
 
         let effects_meta = EffectsMeta::new(render_device);
+        let effect_stats_channel = app.world.resource::<EffectStatsChannel>().clone();
 
         // Register the custom render pipeline
         let render_app = app.sub_app_mut(RenderApp);
         render_app
             .insert_resource(effects_meta)
+            .insert_resource(Random(spawn::new_rng()))
             .init_resource::<EffectBindGroups>()
             .init_resource::<DispatchIndirectPipeline>()
             .init_resource::<ParticlesInitPipeline>()
@@ -190,6 +212,7 @@ impl Plugin for HanabiPlugin {
             .init_resource::<ExtractedEffects>()
             .init_resource::<EffectAssetEvents>()
             .init_resource::<SimParams>()
+            .insert_resource(EffectStatsReadback::new(effect_stats_channel))
             .configure_sets(
                 Render,
                 (
@@ -211,6 +234,7 @@ impl Plugin for HanabiPlugin {
                     prepare_resources
                         .in_set(EffectSystems::PrepareEffectGpuResources)
                         .after(prepare_view_uniforms),
+                    readback_effect_stats.in_set(RenderSet::Cleanup),
                 ),
             );
 
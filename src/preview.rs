@@ -0,0 +1,1148 @@
+//! CPU reference simulation of effects, for editor previews and tests.
+//!
+//! This module is only available with the `cpu_preview` feature. An
+//! [`EffectPreview`] simulates a small number of particles of an
+//! [`EffectAsset`] on CPU, without any GPU or render world. This is intended
+//! for editor previews, thumbnails, and headless tests on machines without a
+//! graphic adapter.
+//!
+//! The preview interprets the same [`Module`] expressions and modifiers as
+//! the shaders generated for the GPU, but is only an approximation of the GPU
+//! simulation:
+//! - only the first particle group is simulated;
+//! - the emitter sits at the origin with an identity transform, so all
+//!   positions are in the local space of the effect;
+//! - only the common built-in modifiers listed below are supported, and any
+//!   other modifier is ignored;
+//! - modifiers using an expression which can't be evaluated on CPU, like
+//!   matrix operations, are ignored;
+//! - colors are returned as authored, without any color space conversion.
+//!
+//! The supported modifiers are:
+//! - init and update: [`SetAttributeModifier`], [`SetPositionSphereModifier`],
+//!   [`SetPositionCircleModifier`], [`SetPositionBoxModifier`],
+//!   [`SetPositionCone3dModifier`], [`SetVelocitySphereModifier`],
+//!   [`SetVelocityCircleModifier`], [`SetVelocityTangentModifier`],
+//!   [`SetVelocityConeModifier`];
+//! - update only: [`AccelModifier`], [`RadialAccelModifier`],
+//!   [`TangentAccelModifier`], [`LinearDragModifier`], [`KillAabbModifier`],
+//!   [`KillSphereModifier`];
+//! - render: [`SetColorModifier`], [`ColorOverLifetimeModifier`],
+//!   [`SetSizeModifier`], [`SizeOverLifetimeModifier`].
+//!
+//! # Example
+//!
+//! ```
+//! # use bevy_hanabi::*;
+//! # fn preview(asset: &EffectAsset) {
+//! let mut preview = EffectPreview::new(asset).with_seed(42);
+//! // Simulate one second at 60 FPS
+//! preview.simulate(1.0, 1.0 / 60.0);
+//! for particle in preview.particles() {
+//!     println!("{:?} {:?}", particle.position, particle.color);
+//! }
+//! # }
+//! ```
+
+use std::f32::consts::TAU;
+
+use bevy::math::{DVec3, DVec4, Quat, Vec2, Vec3, Vec4};
+use rand::{Rng, SeedableRng};
+use rand_pcg::Pcg32;
+
+use crate::{
+    graph::expr::TernaryOperator, AccelModifier, Attribute, BinaryOperator, BuiltInOperator,
+    ColorOverLifetimeModifier, CpuValue, EffectAsset, EffectSpawner, Expr, ExprHandle,
+    KillAabbModifier, KillSphereModifier, LinearDragModifier, Modifier, Module, MotionIntegration,
+    ParticleEffect, ParticleLayout, RadialAccelModifier, ScalarType, ScalarValue,
+    SetAttributeModifier, SetColorModifier, SetPositionBoxModifier, SetPositionCircleModifier,
+    SetPositionCone3dModifier, SetPositionSphereModifier, SetSizeModifier,
+    SetVelocityCircleModifier, SetVelocityConeModifier, SetVelocitySphereModifier,
+    SetVelocityTangentModifier, ShapeDimension, SizeOverLifetimeModifier, TangentAccelModifier,
+    UnaryOperator, Value, ValueType,
+};
+
+/// State of a single particle of an [`EffectPreview`], as it would be
+/// rendered.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PreviewParticle {
+    /// Position of the particle, in the local space of the effect.
+    pub position: Vec3,
+    /// Velocity of the particle, in the local space of the effect.
+    pub velocity: Vec3,
+    /// Age of the particle, in seconds.
+    pub age: f32,
+    /// Lifetime of the particle, in seconds.
+    pub lifetime: f32,
+    /// Rendered color of the particle, after the render modifiers applied.
+    pub color: Vec4,
+    /// Rendered size of the particle, after the render modifiers applied.
+    pub size: Vec2,
+}
+
+/// CPU simulation of an effect, for previews.
+///
+/// See the [module documentation](crate::preview) for details and
+/// limitations.
+pub struct EffectPreview {
+    /// Copy of the asset being previewed.
+    asset: EffectAsset,
+    /// Particle layout of the asset.
+    layout: ParticleLayout,
+    /// Spawner emitting the particles.
+    spawner: EffectSpawner,
+    /// Random number generator for the spawner and the expressions.
+    rng: Pcg32,
+    /// Current value of the properties.
+    properties: Vec<(String, Num)>,
+    /// Alive particles.
+    particles: Vec<Particle>,
+    /// Rendered state of the alive particles, as of the last tick.
+    rendered: Vec<PreviewParticle>,
+    /// Maximum number of particles simulated.
+    max_particles: usize,
+    /// Simulation time, in seconds.
+    time: f64,
+}
+
+impl EffectPreview {
+    /// Default maximum number of particles simulated by a preview.
+    pub const DEFAULT_MAX_PARTICLES: usize = 1024;
+
+    /// Create a new preview of an asset.
+    ///
+    /// The asset is cloned, so later changes to it are not reflected in the
+    /// preview. The properties start with their default value.
+    pub fn new(asset: &EffectAsset) -> Self {
+        Self {
+            asset: asset.clone(),
+            layout: asset.particle_layout(),
+            spawner: EffectSpawner::new(asset, &ParticleEffect::default()),
+            rng: Pcg32::seed_from_u64(0),
+            properties: asset
+                .properties()
+                .iter()
+                .filter_map(|p| Some((p.name().to_string(), Num::from_value(p.default_value())?)))
+                .collect(),
+            particles: vec![],
+            rendered: vec![],
+            max_particles: Self::DEFAULT_MAX_PARTICLES,
+            time: 0.,
+        }
+    }
+
+    /// Set the seed of the random number generator.
+    ///
+    /// Two previews of the same asset with the same seed produce the same
+    /// particles.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = Pcg32::seed_from_u64(seed);
+        self
+    }
+
+    /// Set the maximum number of particles simulated.
+    ///
+    /// The number of particles is also capped by the capacity of the first
+    /// group of the asset. Defaults to [`DEFAULT_MAX_PARTICLES`].
+    ///
+    /// [`DEFAULT_MAX_PARTICLES`]: EffectPreview::DEFAULT_MAX_PARTICLES
+    pub fn with_max_particles(mut self, max_particles: usize) -> Self {
+        self.max_particles = max_particles;
+        self
+    }
+
+    /// Set the value of a property.
+    ///
+    /// Values of a type not representable on CPU, like matrices, are ignored.
+    pub fn set_property(&mut self, name: &str, value: impl Into<Value>) {
+        let Some(value) = Num::from_value(&value.into()) else {
+            return;
+        };
+        if let Some(prop) = self.properties.iter_mut().find(|(n, _)| n == name) {
+            prop.1 = value;
+        } else {
+            self.properties.push((name.to_string(), value));
+        }
+    }
+
+    /// Get the simulation time, in seconds.
+    pub fn time(&self) -> f32 {
+        self.time as f32
+    }
+
+    /// Get the rendered state of the alive particles, as of the last tick.
+    pub fn particles(&self) -> &[PreviewParticle] {
+        &self.rendered
+    }
+
+    /// Reset the preview, killing all particles and restarting the spawner.
+    pub fn reset(&mut self) {
+        self.spawner.reset();
+        self.particles.clear();
+        self.rendered.clear();
+        self.time = 0.;
+    }
+
+    /// Simulate a total duration, in fixed steps of `dt` seconds.
+    pub fn simulate(&mut self, duration: f32, dt: f32) {
+        assert!(dt > 0.);
+        let mut remaining = duration;
+        while remaining > 0. {
+            let step = dt.min(remaining);
+            self.tick(step);
+            remaining -= step;
+        }
+    }
+
+    /// Advance the simulation by `dt` seconds.
+    ///
+    /// This spawns new particles, runs the init and update modifiers, then
+    /// evaluates the render modifiers to update the [`particles()`].
+    ///
+    /// [`particles()`]: EffectPreview::particles
+    pub fn tick(&mut self, dt: f32) {
+        let Self {
+            asset,
+            layout,
+            spawner,
+            rng,
+            properties,
+            particles,
+            rendered,
+            max_particles,
+            time,
+        } = self;
+
+        let spawn_count = spawner.tick(dt, rng) as usize;
+        // Include any warmup time consumed this tick
+        let dt = spawner.delta_time() as f64;
+        *time += dt;
+
+        let mut eval = EvalState {
+            module: asset.module(),
+            properties: &properties[..],
+            time: *time,
+            delta_time: dt,
+            particle_index: 0,
+            rng,
+        };
+
+        // Spawn new particles
+        let capacity = asset
+            .capacities()
+            .first()
+            .map_or(0, |&c| c as usize)
+            .min(*max_particles);
+        let spawn_count = spawn_count.min(capacity.saturating_sub(particles.len()));
+        let init_modifiers: Vec<_> = asset.init_modifiers().collect();
+        for _ in 0..spawn_count {
+            eval.particle_index = particles.len() as u32;
+            let mut particle = Particle::default();
+            let mut is_alive = true;
+            for modifier in &init_modifiers {
+                eval.apply(*modifier, &mut particle, &mut is_alive);
+            }
+            particles.push(particle);
+        }
+
+        // Update all particles
+        let has_age = layout.contains(Attribute::AGE);
+        let has_lifetime = layout.contains(Attribute::LIFETIME);
+        let has_motion = layout.contains(Attribute::POSITION)
+            && layout.contains(Attribute::VELOCITY)
+            && asset.group_motion_integration(0) != MotionIntegration::None;
+        let pre_update = asset.group_motion_integration(0) == MotionIntegration::PreUpdate;
+        let update_modifiers: Vec<_> = asset.update_modifiers_for_group(0).collect();
+        let mut index = 0;
+        particles.retain_mut(|particle| {
+            eval.particle_index = index;
+            index += 1;
+            if has_age {
+                let age = particle.float(Attribute::AGE) + dt as f32;
+                particle.set(Attribute::AGE, Num::scalar(age as f64));
+            }
+            let is_expired = |p: &Particle| {
+                has_age && has_lifetime && p.float(Attribute::AGE) >= p.float(Attribute::LIFETIME)
+            };
+            let mut is_alive = !is_expired(particle);
+            if has_motion && pre_update {
+                particle.integrate(dt as f32);
+            }
+            for modifier in &update_modifiers {
+                eval.apply(*modifier, particle, &mut is_alive);
+            }
+            if has_motion && !pre_update {
+                particle.integrate(dt as f32);
+            }
+            is_alive && !is_expired(particle)
+        });
+
+        // Render
+        let size = layout
+            .attributes()
+            .iter()
+            .find_map(|a| {
+                if a.attribute == Attribute::SIZE {
+                    Some(Size::Uniform)
+                } else if a.attribute == Attribute::SIZE2 {
+                    Some(Size::NonUniform)
+                } else {
+                    None
+                }
+            })
+            .unwrap_or(Size::Default);
+        let color = layout
+            .attributes()
+            .iter()
+            .find_map(|a| {
+                if a.attribute == Attribute::HDR_COLOR {
+                    Some(Color::Hdr)
+                } else if a.attribute == Attribute::COLOR {
+                    Some(Color::Packed)
+                } else {
+                    None
+                }
+            })
+            .unwrap_or(Color::Default);
+        let render_modifiers: Vec<_> = asset.render_modifiers_for_group(0).collect();
+        rendered.clear();
+        for (index, particle) in particles.iter().enumerate() {
+            eval.particle_index = index as u32;
+            let mut out = PreviewParticle {
+                position: particle.vec3(Attribute::POSITION),
+                velocity: particle.vec3(Attribute::VELOCITY),
+                age: particle.float(Attribute::AGE),
+                lifetime: particle.float(Attribute::LIFETIME),
+                color: match color {
+                    Color::Hdr => particle.get(Attribute::HDR_COLOR).vec4(),
+                    Color::Packed => unpack4x8unorm(particle.get(Attribute::COLOR).v.x).vec4(),
+                    Color::Default => default_value(Attribute::HDR_COLOR).vec4(),
+                },
+                size: match size {
+                    Size::Uniform => Vec2::splat(particle.float(Attribute::SIZE)),
+                    Size::NonUniform => particle.get(Attribute::SIZE2).vec2(),
+                    Size::Default => default_value(Attribute::SIZE2).vec2(),
+                },
+            };
+            // Same as BuiltInOperator::AgeRatio on GPU
+            let ratio = if out.lifetime > 0. {
+                (out.age / out.lifetime).clamp(0., 1.)
+            } else {
+                0.
+            };
+            for modifier in &render_modifiers {
+                let any = modifier.as_any();
+                if let Some(m) = any.downcast_ref::<SetColorModifier>() {
+                    let color = match m.color {
+                        CpuValue::Single(c) => c,
+                        CpuValue::Uniform((a, b)) => a.lerp(b, eval.frand()),
+                    };
+                    let tint = m
+                        .tint
+                        .and_then(|tint| eval.eval(particle, tint))
+                        .map_or(Vec4::ONE, |tint| tint.vec4());
+                    out.color = color * tint;
+                } else if let Some(m) = any.downcast_ref::<ColorOverLifetimeModifier>() {
+                    if !m.gradient.is_empty() {
+                        out.color = m.gradient.sample(m.easing.ease(ratio));
+                    }
+                } else if let Some(m) = any.downcast_ref::<SetSizeModifier>() {
+                    out.size = match m.size {
+                        CpuValue::Single(s) => s,
+                        CpuValue::Uniform((a, b)) => a.lerp(b, eval.frand()),
+                    };
+                } else if let Some(m) = any.downcast_ref::<SizeOverLifetimeModifier>() {
+                    if !m.gradient.is_empty() {
+                        out.size = m.gradient.sample(m.easing.ease(ratio));
+                    }
+                }
+            }
+            rendered.push(out);
+        }
+    }
+}
+
+/// Source of the rendered size of the particles.
+#[derive(Clone, Copy)]
+enum Size {
+    Uniform,
+    NonUniform,
+    Default,
+}
+
+/// Source of the rendered color of the particles.
+#[derive(Clone, Copy)]
+enum Color {
+    Hdr,
+    Packed,
+    Default,
+}
+
+/// Value of an expression evaluated on CPU.
+///
+/// All scalar types are stored as `f64`, which represents any `f32`, `i32`,
+/// or `u32` value exactly. Booleans are stored as `0.0` or `1.0`. Unused
+/// components are always zero.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Num {
+    v: DVec4,
+    count: usize,
+}
+
+impl Num {
+    fn new(mut v: DVec4, count: usize) -> Self {
+        for i in count..4 {
+            v[i] = 0.;
+        }
+        Self { v, count }
+    }
+
+    fn scalar(x: f64) -> Self {
+        Self::new(DVec4::new(x, 0., 0., 0.), 1)
+    }
+
+    fn boolean(b: bool) -> Self {
+        Self::scalar(if b { 1. } else { 0. })
+    }
+
+    fn from_vec3(v: Vec3) -> Self {
+        Self::new(v.as_dvec3().extend(0.), 3)
+    }
+
+    fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::Scalar(s) => Some(Self::scalar(scalar_to_f64(s))),
+            Value::Vector(v) => {
+                let count = v.vector_type().count();
+                let mut out = DVec4::ZERO;
+                for i in 0..count {
+                    out[i] = scalar_to_f64(&v.value(i));
+                }
+                Some(Self::new(out, count))
+            }
+            Value::Matrix(_) => None,
+        }
+    }
+
+    fn zero(value_type: ValueType) -> Self {
+        match value_type {
+            ValueType::Vector(v) => Self::new(DVec4::ZERO, v.count()),
+            _ => Self::scalar(0.),
+        }
+    }
+
+    fn float(&self) -> f32 {
+        self.v.x as f32
+    }
+
+    fn vec2(&self) -> Vec2 {
+        self.v.truncate().truncate().as_vec2()
+    }
+
+    fn vec3(&self) -> Vec3 {
+        self.v.truncate().as_vec3()
+    }
+
+    fn vec4(&self) -> Vec4 {
+        self.v.as_vec4()
+    }
+
+    /// Get the components, splatting a scalar to `count` components.
+    fn splat(&self, count: usize) -> DVec4 {
+        if self.count == 1 && count > 1 {
+            DVec4::splat(self.v.x)
+        } else {
+            self.v
+        }
+    }
+
+    fn map(self, f: impl Fn(f64) -> f64) -> Self {
+        let v = self.v;
+        Self::new(DVec4::new(f(v.x), f(v.y), f(v.z), f(v.w)), self.count)
+    }
+
+    /// Apply a component-wise binary operation, splatting any scalar operand.
+    fn zip(self, other: Self, f: impl Fn(f64, f64) -> f64) -> Self {
+        let count = self.count.max(other.count);
+        let (a, b) = (self.splat(count), other.splat(count));
+        Self::new(
+            DVec4::new(f(a.x, b.x), f(a.y, b.y), f(a.z, b.z), f(a.w, b.w)),
+            count,
+        )
+    }
+
+    /// Apply a component-wise ternary operation, splatting any scalar operand.
+    fn zip3(self, second: Self, third: Self, f: impl Fn(f64, f64, f64) -> f64) -> Self {
+        let count = self.count.max(second.count).max(third.count);
+        let (a, b, c) = (self.splat(count), second.splat(count), third.splat(count));
+        Self::new(
+            DVec4::new(
+                f(a.x, b.x, c.x),
+                f(a.y, b.y, c.y),
+                f(a.z, b.z, c.z),
+                f(a.w, b.w, c.w),
+            ),
+            count,
+        )
+    }
+
+    /// Reduce the components with a boolean operation.
+    fn reduce(self, all: bool) -> Self {
+        let mut it = (0..self.count).map(|i| self.v[i] != 0.);
+        Self::boolean(if all { it.all(|b| b) } else { it.any(|b| b) })
+    }
+}
+
+fn scalar_to_f64(s: &ScalarValue) -> f64 {
+    match s.scalar_type() {
+        ScalarType::Bool => {
+            if s.as_bool() {
+                1.
+            } else {
+                0.
+            }
+        }
+        ScalarType::Float => s.as_f32() as f64,
+        ScalarType::Int => s.as_i32() as f64,
+        ScalarType::Uint => s.as_u32() as f64,
+    }
+}
+
+fn default_value(attr: Attribute) -> Num {
+    Num::from_value(&attr.default_value()).unwrap_or_else(|| Num::zero(attr.value_type()))
+}
+
+fn unpack4x8unorm(x: f64) -> Num {
+    let u = x as u32;
+    let byte = |i: u32| ((u >> (i * 8)) & 0xFF) as f64 / 255.;
+    Num::new(DVec4::new(byte(0), byte(1), byte(2), byte(3)), 4)
+}
+
+fn unpack4x8snorm(x: f64) -> Num {
+    let u = x as u32;
+    let byte = |i: u32| ((((u >> (i * 8)) & 0xFF) as u8 as i8) as f64 / 127.).max(-1.);
+    Num::new(DVec4::new(byte(0), byte(1), byte(2), byte(3)), 4)
+}
+
+fn pack4x8unorm(v: DVec4) -> Num {
+    let byte = |x: f64| (x.clamp(0., 1.) * 255.).round() as u32;
+    Num::scalar((byte(v.x) | (byte(v.y) << 8) | (byte(v.z) << 16) | (byte(v.w) << 24)) as f64)
+}
+
+fn pack4x8snorm(v: DVec4) -> Num {
+    let byte = |x: f64| ((x.clamp(-1., 1.) * 127.).round() as i8) as u8 as u32;
+    Num::scalar((byte(v.x) | (byte(v.y) << 8) | (byte(v.z) << 16) | (byte(v.w) << 24)) as f64)
+}
+
+/// Build an orthonormal basis of the plane orthogonal to the unit vector `n`.
+///
+/// This is the same construction as the shaders use, from "Building an
+/// Orthonormal Basis, Revisited" (Duff et al., 2017).
+fn plane_basis(n: Vec3) -> (Vec3, Vec3) {
+    let sign = if n.z >= 0. { 1. } else { -1. };
+    let a = -1. / (sign + n.z);
+    let b = n.x * n.y * a;
+    let tangent = Vec3::new((sign * n.x * n.x).mul_add(a, 1.), sign * b, -sign * n.x);
+    let bitangent = Vec3::new(b, (n.y * n.y).mul_add(a, sign), -n.y);
+    (tangent, bitangent)
+}
+
+/// Particle simulated on CPU.
+#[derive(Debug, Default, Clone)]
+struct Particle {
+    /// Values of the attributes written so far. Other attributes are zero, like
+    /// on GPU.
+    attributes: Vec<(Attribute, Num)>,
+}
+
+impl Particle {
+    fn get(&self, attr: Attribute) -> Num {
+        self.attributes
+            .iter()
+            .find(|(a, _)| *a == attr)
+            .map_or_else(|| Num::zero(attr.value_type()), |(_, v)| *v)
+    }
+
+    fn set(&mut self, attr: Attribute, value: Num) {
+        if let Some(entry) = self.attributes.iter_mut().find(|(a, _)| *a == attr) {
+            entry.1 = value;
+        } else {
+            self.attributes.push((attr, value));
+        }
+    }
+
+    fn float(&self, attr: Attribute) -> f32 {
+        self.get(attr).float()
+    }
+
+    fn vec3(&self, attr: Attribute) -> Vec3 {
+        self.get(attr).vec3()
+    }
+
+    /// Inverse of the mass of the particle, or `1.0` if `respect_mass` is
+    /// `false`. Like on the GPU, a zero mass yields `0.0`.
+    fn inv_mass(&self, respect_mass: bool) -> f32 {
+        if respect_mass {
+            let mass = self.float(Attribute::MASS);
+            if mass == 0. {
+                0.
+            } else {
+                1. / mass
+            }
+        } else {
+            1.
+        }
+    }
+
+    fn add_velocity(&mut self, delta: Vec3) {
+        let velocity = self.vec3(Attribute::VELOCITY) + delta;
+        self.set(Attribute::VELOCITY, Num::from_vec3(velocity));
+    }
+
+    fn integrate(&mut self, dt: f32) {
+        let position = self.vec3(Attribute::POSITION) + self.vec3(Attribute::VELOCITY) * dt;
+        self.set(Attribute::POSITION, Num::from_vec3(position));
+    }
+}
+
+/// State shared by all evaluations of a tick.
+struct EvalState<'a> {
+    module: &'a Module,
+    properties: &'a [(String, Num)],
+    time: f64,
+    delta_time: f64,
+    particle_index: u32,
+    rng: &'a mut Pcg32,
+}
+
+impl EvalState<'_> {
+    fn frand(&mut self) -> f32 {
+        self.rng.gen()
+    }
+
+    fn float(&mut self, particle: &Particle, expr: ExprHandle) -> Option<f32> {
+        self.eval(particle, expr).map(|n| n.float())
+    }
+
+    fn vec3(&mut self, particle: &Particle, expr: ExprHandle) -> Option<Vec3> {
+        self.eval(particle, expr).map(|n| n.vec3())
+    }
+
+    /// Evaluate an expression for a particle.
+    ///
+    /// Returns `None` if the expression can't be evaluated on CPU.
+    fn eval(&mut self, particle: &Particle, expr: ExprHandle) -> Option<Num> {
+        Some(match self.module.get(expr)? {
+            Expr::Literal(lit) => Num::from_value(&lit.value())?,
+            Expr::Property(prop) => {
+                self.properties
+                    .iter()
+                    .find(|(name, _)| name == prop.property_name())?
+                    .1
+            }
+            Expr::Attribute(attr) => particle.get(attr.attribute()),
+            Expr::BuiltIn(builtin) => match builtin.operator() {
+                BuiltInOperator::AgeRatio => {
+                    let lifetime = particle.float(Attribute::LIFETIME);
+                    let ratio = if lifetime > 0. {
+                        (particle.float(Attribute::AGE) / lifetime).clamp(0., 1.)
+                    } else {
+                        0.
+                    };
+                    Num::scalar(ratio as f64)
+                }
+                op => self.builtin(op)?,
+            },
+            Expr::Unary { op, expr } => {
+                let x = self.eval(particle, *expr)?;
+                unary(*op, x)?
+            }
+            Expr::Binary { op, left, right } => {
+                let l = self.eval(particle, *left)?;
+                let r = self.eval(particle, *right)?;
+                if *op == BinaryOperator::UniformRand {
+                    let t = self.rand(ScalarType::Float, l.count.max(r.count));
+                    l.zip3(r, t, |a, b, t| (b - a).mul_add(t, a))
+                } else {
+                    binary(*op, l, r)?
+                }
+            }
+            Expr::Ternary {
+                op,
+                first,
+                second,
+                third,
+            } => {
+                let a = self.eval(particle, *first)?;
+                let b = self.eval(particle, *second)?;
+                let c = self.eval(particle, *third)?;
+                ternary(*op, a, b, c)
+            }
+            Expr::Cast(cast) => {
+                let x = self.eval(particle, cast.inner())?;
+                convert(x, cast.value_type())?
+            }
+        })
+    }
+
+    fn builtin(&mut self, op: BuiltInOperator) -> Option<Num> {
+        Some(match op {
+            BuiltInOperator::Time | BuiltInOperator::VirtualTime | BuiltInOperator::RealTime => {
+                Num::scalar(self.time)
+            }
+            BuiltInOperator::DeltaTime
+            | BuiltInOperator::VirtualDeltaTime
+            | BuiltInOperator::RealDeltaTime => Num::scalar(self.delta_time),
+            BuiltInOperator::Rand(value_type) => match value_type {
+                ValueType::Scalar(s) => self.rand(s, 1),
+                ValueType::Vector(v) => self.rand(v.elem_type(), v.count()),
+                ValueType::Matrix(_) => return None,
+            },
+            BuiltInOperator::ParticleIndex => Num::scalar(self.particle_index as f64),
+            BuiltInOperator::GroupIndex => Num::scalar(0.),
+            // The age ratio depends on the particle; see eval()
+            BuiltInOperator::AlphaCutoff | BuiltInOperator::AgeRatio => return None,
+        })
+    }
+
+    fn rand(&mut self, elem_type: ScalarType, count: usize) -> Num {
+        let mut v = DVec4::ZERO;
+        for i in 0..count {
+            v[i] = match elem_type {
+                ScalarType::Bool => (self.frand() < 0.5) as u32 as f64,
+                ScalarType::Float => self.frand() as f64,
+                ScalarType::Int => self.rng.gen::<i32>() as f64,
+                ScalarType::Uint => self.rng.gen::<u32>() as f64,
+            };
+        }
+        Num::new(v, count)
+    }
+
+    /// Apply a modifier to a particle.
+    ///
+    /// Modifiers which are not supported, or use expressions which can't be
+    /// evaluated on CPU, are ignored.
+    fn apply(&mut self, modifier: &dyn Modifier, particle: &mut Particle, is_alive: &mut bool) {
+        let _ = self.try_apply(modifier, particle, is_alive);
+    }
+
+    fn try_apply(
+        &mut self,
+        modifier: &dyn Modifier,
+        particle: &mut Particle,
+        is_alive: &mut bool,
+    ) -> Option<()> {
+        let dt = self.delta_time as f32;
+        let any = modifier.as_any();
+        if let Some(m) = any.downcast_ref::<SetAttributeModifier>() {
+            let value = self.eval(particle, m.value)?;
+            particle.set(m.attribute, value);
+        } else if let Some(m) = any.downcast_ref::<SetPositionSphereModifier>() {
+            let c = self.vec3(particle, m.center)?;
+            let radius = self.float(particle, m.radius)?;
+            let r = match (m.dimension, m.thickness) {
+                (ShapeDimension::Surface, Some(thickness)) => {
+                    (self.frand() - 0.5).mul_add(self.float(particle, thickness)?, radius)
+                }
+                (ShapeDimension::Surface, None) => radius,
+                (ShapeDimension::Volume, _) => self.frand().cbrt() * radius,
+            };
+            let theta = self.frand() * TAU;
+            let z = self.frand().mul_add(2., -1.);
+            let sinphi = (1. - z * z).max(0.).sqrt();
+            let dir = Vec3::new(sinphi * theta.cos(), sinphi * theta.sin(), z);
+            particle.set(Attribute::POSITION, Num::from_vec3(c + r * dir));
+        } else if let Some(m) = any.downcast_ref::<SetPositionCircleModifier>() {
+            let c = self.vec3(particle, m.center)?;
+            let n = self.vec3(particle, m.axis)?;
+            let radius = self.float(particle, m.radius)?;
+            let r = match (m.dimension, m.thickness) {
+                (ShapeDimension::Surface, Some(thickness)) => {
+                    (self.frand() - 0.5).mul_add(self.float(particle, thickness)?, radius)
+                }
+                (ShapeDimension::Surface, None) => radius,
+                (ShapeDimension::Volume, _) => self.frand().sqrt() * radius,
+            };
+            let (tangent, bitangent) = plane_basis(n);
+            let theta = self.frand() * TAU;
+            let dir = tangent * theta.cos() + bitangent * theta.sin();
+            particle.set(Attribute::POSITION, Num::from_vec3(c + r * dir));
+        } else if let Some(m) = any.downcast_ref::<SetPositionBoxModifier>() {
+            let c = self.vec3(particle, m.center)?;
+            let h = self.vec3(particle, m.half_extents)?;
+            let orientation = match m.orientation {
+                Some(q) => Quat::from_vec4(self.eval(particle, q)?.vec4()).normalize(),
+                None => Quat::IDENTITY,
+            };
+            let mut p = Vec3::new(self.frand(), self.frand(), self.frand()) * 2. - 1.;
+            if m.dimension == ShapeDimension::Surface {
+                // Pick a face pair with a probability proportional to its area
+                let areas = Vec3::new(h.y * h.z, h.x * h.z, h.x * h.y);
+                let u = self.frand() * (areas.x + areas.y + areas.z);
+                let side = if self.frand() < 0.5 { -1. } else { 1. };
+                if u < areas.x {
+                    p.x = side;
+                } else if u < areas.x + areas.y {
+                    p.y = side;
+                } else {
+                    p.z = side;
+                }
+            }
+            particle.set(
+                Attribute::POSITION,
+                Num::from_vec3(c + orientation * (p * h)),
+            );
+        } else if let Some(m) = any.downcast_ref::<SetPositionCone3dModifier>() {
+            let h0 = self.float(particle, m.height)?;
+            let rt = self.float(particle, m.top_radius)?;
+            let rb = self.float(particle, m.base_radius)?;
+            let orientation = match m.orientation {
+                Some(q) => Quat::from_vec4(self.eval(particle, q)?.vec4()).normalize(),
+                None => Quat::IDENTITY,
+            };
+            let alpha_h = self.frand().cbrt();
+            let h = h0 * alpha_h;
+            let r0 = (rt - rb).mul_add(alpha_h, rb);
+            let r = r0 * self.frand().sqrt();
+            let theta = self.frand() * TAU;
+            let p = Vec3::new(r * theta.cos(), h, r * theta.sin());
+            particle.set(Attribute::POSITION, Num::from_vec3(orientation * p));
+        } else if let Some(m) = any.downcast_ref::<SetVelocitySphereModifier>() {
+            let c = self.vec3(particle, m.center)?;
+            let speed = self.float(particle, m.speed)?;
+            let dir = (particle.vec3(Attribute::POSITION) - c).normalize_or_zero();
+            particle.set(Attribute::VELOCITY, Num::from_vec3(dir * speed));
+        } else if let Some(m) = any.downcast_ref::<SetVelocityCircleModifier>() {
+            let c = self.vec3(particle, m.center)?;
+            let n = self.vec3(particle, m.axis)?;
+            let speed = self.float(particle, m.speed)?;
+            let delta = particle.vec3(Attribute::POSITION) - c;
+            let delta_in_plane = delta - delta.dot(n) * n;
+            let radial = if delta_in_plane.length_squared() > 1e-12 {
+                delta_in_plane.normalize()
+            } else {
+                // Particle on the circle axis; pick a random radial direction
+                let (tangent, bitangent) = plane_basis(n);
+                let theta = self.frand() * TAU;
+                tangent * theta.cos() + bitangent * theta.sin()
+            };
+            particle.set(Attribute::VELOCITY, Num::from_vec3(radial * speed));
+        } else if let Some(m) = any.downcast_ref::<SetVelocityTangentModifier>() {
+            let origin = self.vec3(particle, m.origin)?;
+            let axis = self.vec3(particle, m.axis)?;
+            let speed = self.float(particle, m.speed)?;
+            let radial = particle.vec3(Attribute::POSITION) - origin;
+            let tangent = axis.cross(radial).normalize_or_zero();
+            particle.set(Attribute::VELOCITY, Num::from_vec3(tangent * speed));
+        } else if let Some(m) = any.downcast_ref::<SetVelocityConeModifier>() {
+            if let Some(apex) = m.apex {
+                let apex = self.vec3(particle, apex)?;
+                particle.set(Attribute::POSITION, Num::from_vec3(apex));
+            }
+            let n = self.vec3(particle, m.direction)?.normalize_or_zero();
+            let spread = self.float(particle, m.spread)?.clamp(0., TAU / 2.);
+            let speed = self.float(particle, m.speed)?;
+            let (tangent, bitangent) = plane_basis(n);
+            let cos_theta = (spread.cos() - 1.).mul_add(self.frand(), 1.);
+            let sin_theta = (1. - cos_theta * cos_theta).max(0.).sqrt();
+            let phi = self.frand() * TAU;
+            let dir = n * cos_theta + (tangent * phi.cos() + bitangent * phi.sin()) * sin_theta;
+            particle.set(Attribute::VELOCITY, Num::from_vec3(dir * speed));
+        } else if let Some(m) = any.downcast_ref::<AccelModifier>() {
+            let accel = self.vec3(particle, m.accel())?;
+            particle.add_velocity(accel * dt * particle.inv_mass(m.respect_mass()));
+        } else if let Some(m) = any.downcast_ref::<RadialAccelModifier>() {
+            let origin = self.vec3(particle, m.origin())?;
+            let accel = self.float(particle, m.accel())?;
+            let radial = (particle.vec3(Attribute::POSITION) - origin).normalize_or_zero();
+            particle.add_velocity(radial * accel * dt * particle.inv_mass(m.respect_mass()));
+        } else if let Some(m) = any.downcast_ref::<TangentAccelModifier>() {
+            let origin = self.vec3(particle, m.origin())?;
+            let axis = self.vec3(particle, m.axis())?;
+            let accel = self.float(particle, m.accel())?;
+            let radial = (particle.vec3(Attribute::POSITION) - origin).normalize_or_zero();
+            let tangent = axis.cross(radial).normalize_or_zero();
+            particle.add_velocity(tangent * accel * dt * particle.inv_mass(m.respect_mass()));
+        } else if let Some(m) = any.downcast_ref::<LinearDragModifier>() {
+            let drag = self.float(particle, m.drag)?;
+            let factor = (-drag * dt)
+                .mul_add(particle.inv_mass(m.respect_mass), 1.)
+                .max(0.);
+            let velocity = particle.vec3(Attribute::VELOCITY) * factor;
+            particle.set(Attribute::VELOCITY, Num::from_vec3(velocity));
+        } else if let Some(m) = any.downcast_ref::<KillAabbModifier>() {
+            let c = self.vec3(particle, m.center)?;
+            let h = self.vec3(particle, m.half_size)?;
+            let dist = (particle.vec3(Attribute::POSITION) - c).abs();
+            let kill = if m.kill_inside {
+                dist.cmplt(h).all()
+            } else {
+                dist.cmpgt(h).any()
+            };
+            if kill {
+                *is_alive = false;
+            }
+        } else if let Some(m) = any.downcast_ref::<KillSphereModifier>() {
+            let c = self.vec3(particle, m.center)?;
+            let sqr_radius = self.float(particle, m.sqr_radius)?;
+            let sqr_dist = (particle.vec3(Attribute::POSITION) - c).length_squared();
+            let kill = if m.kill_inside {
+                sqr_dist < sqr_radius
+            } else {
+                sqr_dist > sqr_radius
+            };
+            if kill {
+                *is_alive = false;
+            }
+        }
+        Some(())
+    }
+}
+
+fn unary(op: UnaryOperator, x: Num) -> Option<Num> {
+    Some(match op {
+        UnaryOperator::Abs => x.map(f64::abs),
+        UnaryOperator::All => x.reduce(true),
+        UnaryOperator::Any => x.reduce(false),
+        UnaryOperator::Ceil => x.map(f64::ceil),
+        UnaryOperator::Cos => x.map(f64::cos),
+        UnaryOperator::Ease(easing) => x.map(|x| easing.ease(x as f32) as f64),
+        UnaryOperator::Exp => x.map(f64::exp),
+        UnaryOperator::Exp2 => x.map(f64::exp2),
+        UnaryOperator::Floor => x.map(f64::floor),
+        UnaryOperator::Fract => x.map(|x| x - x.floor()),
+        UnaryOperator::InvSqrt => x.map(|x| 1. / x.sqrt()),
+        UnaryOperator::Length => Num::scalar(x.v.length()),
+        UnaryOperator::Log => x.map(f64::ln),
+        UnaryOperator::Log2 => x.map(f64::log2),
+        UnaryOperator::Normalize => Num::new(x.v.normalize_or_zero(), x.count),
+        UnaryOperator::Not => x.map(|x| if x == 0. { 1. } else { 0. }),
+        UnaryOperator::Pack4x8snorm => pack4x8snorm(x.v),
+        UnaryOperator::Pack4x8unorm => pack4x8unorm(x.v),
+        UnaryOperator::Saturate => x.map(|x| x.clamp(0., 1.)),
+        UnaryOperator::Sign => x.map(|x| if x == 0. { 0. } else { x.signum() }),
+        UnaryOperator::Sin => x.map(f64::sin),
+        UnaryOperator::Sqrt => x.map(f64::sqrt),
+        UnaryOperator::Tan => x.map(f64::tan),
+        UnaryOperator::Unpack4x8snorm => unpack4x8snorm(x.v.x),
+        UnaryOperator::Unpack4x8unorm => unpack4x8unorm(x.v.x),
+        UnaryOperator::Mat3Euler => return None,
+        UnaryOperator::W => Num::scalar(x.v.w),
+        UnaryOperator::X => Num::scalar(x.v.x),
+        UnaryOperator::Y => Num::scalar(x.v.y),
+        UnaryOperator::Z => Num::scalar(x.v.z),
+    })
+}
+
+fn binary(op: BinaryOperator, l: Num, r: Num) -> Option<Num> {
+    let cmp = |f: fn(f64, f64) -> bool| l.zip(r, |a, b| f(a, b) as u32 as f64);
+    Some(match op {
+        BinaryOperator::Add => l.zip(r, |a, b| a + b),
+        BinaryOperator::And => cmp(|a, b| a != 0. && b != 0.),
+        BinaryOperator::Cross => Num::new(l.v.truncate().cross(r.v.truncate()).extend(0.), 3),
+        BinaryOperator::Distance => Num::scalar(l.v.distance(r.v)),
+        BinaryOperator::Div => l.zip(r, |a, b| a / b),
+        BinaryOperator::Dot => Num::scalar(l.v.dot(r.v)),
+        BinaryOperator::Equal => cmp(|a, b| a == b),
+        BinaryOperator::GreaterThan => cmp(|a, b| a > b),
+        BinaryOperator::GreaterThanOrEqual => cmp(|a, b| a >= b),
+        BinaryOperator::LessThan => cmp(|a, b| a < b),
+        BinaryOperator::LessThanOrEqual => cmp(|a, b| a <= b),
+        BinaryOperator::Max => l.zip(r, f64::max),
+        BinaryOperator::Min => l.zip(r, f64::min),
+        BinaryOperator::Mul => l.zip(r, |a, b| a * b),
+        BinaryOperator::NotEqual => cmp(|a, b| a != b),
+        BinaryOperator::Or => cmp(|a, b| a != 0. || b != 0.),
+        BinaryOperator::Remainder => l.zip(r, |a, b| a % b),
+        BinaryOperator::Step => cmp(|edge, x| edge <= x),
+        BinaryOperator::Sub => l.zip(r, |a, b| a - b),
+        // Handled by the caller, which owns the random number generator
+        BinaryOperator::UniformRand => return None,
+        BinaryOperator::Mat3Rotation => return None,
+        BinaryOperator::Vec2 => Num::new(DVec4::new(l.v.x, r.v.x, 0., 0.), 2),
+        BinaryOperator::Vec4 => Num::new(l.v.truncate().extend(r.v.x), 4),
+    })
+}
+
+fn ternary(op: TernaryOperator, a: Num, b: Num, c: Num) -> Num {
+    let p = a.v.truncate();
+    match op {
+        TernaryOperator::Mix => a.zip3(b, c, |x, y, t| (y - x).mul_add(t, x)),
+        TernaryOperator::SmoothStep => a.zip3(b, c, |low, high, x| {
+            let t = ((x - low) / (high - low)).clamp(0., 1.);
+            t * t * 2f64.mul_add(-t, 3.)
+        }),
+        TernaryOperator::Vec3 => Num::new(DVec4::new(a.v.x, b.v.x, c.v.x, 0.), 3),
+        TernaryOperator::SdfSphere => Num::scalar(p.distance(b.v.truncate()) - c.v.x),
+        TernaryOperator::SdfBox => {
+            let q = (p - b.v.truncate()).abs() - c.v.truncate();
+            Num::scalar(q.max(DVec3::ZERO).length() + q.max_element().min(0.))
+        }
+        TernaryOperator::SdfPlane => {
+            Num::scalar((p - b.v.truncate()).dot(c.v.truncate().normalize_or_zero()))
+        }
+    }
+}
+
+/// Convert a value to another type, like a cast expression.
+fn convert(x: Num, target: ValueType) -> Option<Num> {
+    let (elem_type, count) = match target {
+        ValueType::Scalar(s) => (s, 1),
+        ValueType::Vector(v) => (v.elem_type(), v.count()),
+        ValueType::Matrix(_) => return None,
+    };
+    let x = Num::new(x.splat(count), count);
+    Some(match elem_type {
+        ScalarType::Bool => x.map(|x| (x != 0.) as u32 as f64),
+        ScalarType::Float => x.map(|x| x as f32 as f64),
+        ScalarType::Int => x.map(|x| x as i32 as f64),
+        ScalarType::Uint => x.map(|x| x as u32 as f64),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Gradient, Spawner};
+
+    #[test]
+    fn eval_expr() {
+        let mut module = Module::default();
+        let x = module.lit(Vec3::new(1., -2., 3.));
+        let y = module.lit(2.);
+        let mul = module.mul(x, y);
+        let abs = module.abs(mul);
+        let len = module.length(x);
+        let color = module.lit(0xFF00FF80u32);
+        let unpacked = module.unpack4x8unorm(color);
+        let packed = module.pack4x8unorm(unpacked);
+        let prop = module.prop("my_prop");
+        let sum = module.add(prop, y);
+
+        let mut rng = Pcg32::seed_from_u64(0);
+        let properties = vec![("my_prop".to_string(), Num::scalar(5.))];
+        let mut eval = EvalState {
+            module: &module,
+            properties: &properties,
+            time: 0.,
+            delta_time: 0.,
+            particle_index: 0,
+            rng: &mut rng,
+        };
+        let particle = Particle::default();
+        assert_eq!(eval.vec3(&particle, abs).unwrap(), Vec3::new(2., 4., 6.));
+        assert!((eval.float(&particle, len).unwrap() - 14f32.sqrt()).abs() < 1e-5);
+        let c = eval.eval(&particle, unpacked).unwrap().vec4();
+        assert!((c - Vec4::new(128. / 255., 1., 0., 1.)).abs().max_element() < 1e-5);
+        assert_eq!(
+            eval.eval(&particle, packed).unwrap().v.x,
+            0xFF00FF80u32 as f64
+        );
+        assert_eq!(eval.float(&particle, sum).unwrap(), 7.);
+    }
+
+    #[test]
+    fn simulate() {
+        let mut module = Module::default();
+        let center = module.lit(Vec3::ZERO);
+        let radius = module.lit(2.);
+        let lifetime = module.lit(1.);
+        let accel = module.lit(Vec3::new(0., -10., 0.));
+        let asset = EffectAsset::new(vec![256], Spawner::once(32.0.into(), true), module)
+            .init(SetPositionSphereModifier {
+                center,
+                radius,
+                dimension: ShapeDimension::Surface,
+                thickness: None,
+            })
+            .init(SetAttributeModifier::new(Attribute::LIFETIME, lifetime))
+            .update(AccelModifier::new(accel))
+            .render(ColorOverLifetimeModifier::new(Gradient::linear(
+                Vec4::ONE,
+                Vec4::ZERO,
+            )));
+
+        let mut preview = EffectPreview::new(&asset).with_seed(42);
+        preview.tick(0.1);
+        assert_eq!(preview.particles().len(), 32);
+        for p in preview.particles() {
+            assert!((p.age - 0.1).abs() < 1e-5);
+            assert_eq!(p.lifetime, 1.);
+            // Spawned on the sphere surface, then moved down by the acceleration
+            assert!((p.velocity - Vec3::new(0., -1., 0.)).length() < 1e-5);
+            let spawn_pos = p.position - p.velocity * 0.1;
+            assert!((spawn_pos.length() - 2.).abs() < 1e-4);
+            assert!((p.color - Vec4::splat(0.9)).abs().max_element() < 1e-5);
+        }
+
+        // Same seed, same particles
+        let mut other = EffectPreview::new(&asset).with_seed(42);
+        other.tick(0.1);
+        assert_eq!(preview.particles(), other.particles());
+
+        // All particles die once their lifetime elapsed
+        preview.simulate(1., 0.1);
+        assert!(preview.particles().is_empty());
+
+        // Capped number of particles
+        let mut preview = EffectPreview::new(&asset).with_max_particles(4);
+        preview.tick(0.1);
+        assert_eq!(preview.particles().len(), 4);
+    }
+
+    #[test]
+    fn velocity_cone() {
+        let mut module = Module::default();
+        let direction = module.lit(Vec3::new(0., 2., 0.));
+        let spread = module.lit(0.3);
+        let speed = module.lit(5.);
+        let apex = module.lit(Vec3::X);
+        let lifetime = module.lit(1.);
+        let asset = EffectAsset::new(vec![256], Spawner::once(64.0.into(), true), module)
+            .init(SetVelocityConeModifier::new(direction, spread, speed).with_apex(apex))
+            .init(SetAttributeModifier::new(Attribute::LIFETIME, lifetime));
+
+        let mut preview = EffectPreview::new(&asset).with_seed(42);
+        preview.tick(0.);
+        assert_eq!(preview.particles().len(), 64);
+        for p in preview.particles() {
+            assert!((p.position - Vec3::X).length() < 1e-5);
+            assert!((p.velocity.length() - 5.).abs() < 1e-4);
+            assert!(p.velocity.angle_between(Vec3::Y) <= 0.3 + 1e-4);
+        }
+    }
+
+    #[test]
+    fn degenerate_inputs_stay_finite() {
+        // Particles spawned exactly at the center of all the radial modifiers,
+        // with a zero mass, so every direction and inverse mass is degenerate.
+        let mut module = Module::default();
+        let center = module.lit(Vec3::ZERO);
+        let zero = module.lit(0.);
+        let one = module.lit(1.);
+        let axis = module.lit(Vec3::Y);
+        let asset = EffectAsset::new(vec![256], Spawner::once(16.0.into(), true), module)
+            .init(SetPositionSphereModifier {
+                center,
+                radius: zero,
+                dimension: ShapeDimension::Volume,
+                thickness: None,
+            })
+            .init(SetVelocitySphereModifier { center, speed: one })
+            .init(SetAttributeModifier::new(Attribute::MASS, zero))
+            .init(SetAttributeModifier::new(Attribute::LIFETIME, one))
+            .update(AccelModifier::new(axis).with_respect_mass(true))
+            .update(RadialAccelModifier::new(center, one).with_respect_mass(true))
+            .update(TangentAccelModifier::new(center, axis, one))
+            .update(LinearDragModifier::new(one).with_respect_mass(true));
+
+        let mut preview = EffectPreview::new(&asset).with_seed(42);
+        preview.simulate(0.5, 0.1);
+        assert_eq!(preview.particles().len(), 16);
+        for p in preview.particles() {
+            assert!(p.position.is_finite());
+            assert!(p.velocity.is_finite());
+            assert_eq!(p.velocity, Vec3::ZERO);
+        }
+    }
+}
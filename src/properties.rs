@@ -297,6 +297,16 @@ impl EffectProperties {
         }
     }
 
+    /// Enable or disable a modifier toggled by a property.
+    ///
+    /// This sets the `u32` property `name` to `1` if `enabled` is `true`, or
+    /// `0` otherwise. See [`ConditionalModifier::via_property()`].
+    ///
+    /// [`ConditionalModifier::via_property()`]: crate::ConditionalModifier::via_property
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) {
+        self.set(name, Value::from(enabled as u32));
+    }
+
     /// Set the value of a property, only if it changed.
     ///
     /// This is similar to [`set()`], with the notable difference that this
@@ -1166,4 +1176,13 @@ mod tests {
         assert_eq!(ep.properties[1].def, asset_properties[1]);
         assert_eq!(last_changed, this_run); // changed (added missing property)
     }
+
+    #[test]
+    fn effect_properties_set_enabled() {
+        let mut ep = EffectProperties::default();
+        ep.set_enabled("toggle", true);
+        assert_eq!(ep.get_stored("toggle"), Some(1u32.into()));
+        ep.set_enabled("toggle", false);
+        assert_eq!(ep.get_stored("toggle"), Some(0u32.into()));
+    }
 }
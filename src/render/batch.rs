@@ -2,7 +2,7 @@ use std::ops::{Index, Range};
 
 use bevy::{
     prelude::*,
-    render::render_resource::{Buffer, CachedComputePipelineId},
+    render::render_resource::{BlendState, Buffer, CachedComputePipelineId},
 };
 
 #[cfg(feature = "2d")]
@@ -12,7 +12,7 @@ use crate::{EffectAsset, EffectShader, ParticleLayout, PropertyLayout};
 
 use super::{
     effect_cache::{DispatchBufferIndices, EffectSlices},
-    EffectCacheId, GpuCompressedTransform, LayoutFlags,
+    EffectCacheId, GpuCompressedTransform, GpuInjectedState, LayoutFlags,
 };
 
 /// Data needed to render all batches pertaining to a specific effect.
@@ -26,8 +26,12 @@ pub(crate) struct EffectBatches {
     pub buffer_index: u32,
     /// Index of the first Spawner of the effects in the batch.
     pub spawner_base: u32,
-    /// Number of particles to spawn/init this frame.
+    /// Number of particles to spawn/init this frame into group #0.
     pub spawn_count: u32,
+    /// Particles to spawn/init this frame into groups other than group #0.
+    pub group_spawns: Vec<GroupSpawn>,
+    /// Whether the effect is warming up, and therefore shouldn't be rendered.
+    pub is_warming_up: bool,
     /// The effect cache ID.
     pub effect_cache_id: EffectCacheId,
     /// The indices within the various indirect dispatch buffers.
@@ -36,6 +40,8 @@ pub(crate) struct EffectBatches {
     pub first_particle_group_buffer_index: u32,
     /// Particle layout.
     pub particle_layout: ParticleLayout,
+    /// Property layout.
+    pub property_layout: PropertyLayout,
     /// Flags describing the render layout.
     pub layout_flags: LayoutFlags,
     /// Entities holding the source [`ParticleEffect`] instances which were
@@ -47,6 +53,14 @@ pub(crate) struct EffectBatches {
     ///
     /// TODO: We should support more than one of these.
     pub image_handle: Handle<Image>,
+    /// Erosion texture to dissolve the particles.
+    pub dissolve_image_handle: Handle<Image>,
+    /// Scene color texture refracted behind the particles.
+    pub refraction_image_handle: Handle<Image>,
+    /// Heightmap texture sampled during the update pass, if any.
+    pub heightfield_image_handle: Option<Handle<Image>>,
+    /// Custom blend state of the render pipeline, if any.
+    pub blend_state: Option<BlendState>,
     /// Configured shaders used for the particle rendering of this batch.
     /// Note that we don't need to keep the init/update shaders alive because
     /// their pipeline specialization is doing it via the specialization key.
@@ -55,6 +69,9 @@ pub(crate) struct EffectBatches {
     pub init_pipeline_id: CachedComputePipelineId,
     /// Update compute pipeline specialized for this batch.
     pub update_pipeline_ids: Vec<CachedComputePipelineId>,
+    /// Compaction compute pipelines specialized for this batch, one per group.
+    /// Empty if the effect is not compacted this frame.
+    pub compact_pipeline_ids: Vec<CachedComputePipelineId>,
 }
 
 impl Index<u32> for EffectBatches {
@@ -77,12 +94,36 @@ pub(crate) struct EffectDrawBatch {
     pub group_index: u32,
     /// Entity holding the [`EffectBatches`] this batch is part of.
     pub batches_entity: Entity,
+    /// Render order of the group. Groups with a lower render order are drawn
+    /// first.
+    pub render_order: i32,
     /// For 2D rendering, the Z coordinate used as the sort key. Ignored for 3D
     /// rendering.
     #[cfg(feature = "2d")]
     pub z_sort_key_2d: FloatOrd,
 }
 
+impl EffectDrawBatch {
+    /// Key used to order the draw batches of a same render phase sort key.
+    ///
+    /// Draw batches are ordered by render order, then by effect, then by group
+    /// index, which makes the draw order deterministic.
+    pub fn draw_order_key(&self) -> (i32, Entity, u32) {
+        (self.render_order, self.batches_entity, self.group_index)
+    }
+}
+
+/// Particles spawned this frame by the init pass into a single group.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct GroupSpawn {
+    /// Index of the group to spawn the particles into.
+    pub group_index: u32,
+    /// Index of the spawner parameters of the init pass in the spawner buffer.
+    pub spawner_index: u32,
+    /// Number of particles to spawn.
+    pub spawn_count: u32,
+}
+
 /// Batch data specific to a single particle group.
 #[derive(Debug)]
 pub(crate) struct EffectBatch {
@@ -105,7 +146,10 @@ impl EffectBatches {
             buffer_index: input.effect_slices.buffer_index,
             spawner_base,
             spawn_count: input.spawn_count,
+            group_spawns: vec![],
+            is_warming_up: input.is_warming_up,
             particle_layout: input.effect_slices.particle_layout,
+            property_layout: input.property_layout,
             effect_cache_id,
             dispatch_buffer_indices,
             first_particle_group_buffer_index,
@@ -120,9 +164,14 @@ impl EffectBatches {
             handle: input.handle,
             layout_flags: input.layout_flags,
             image_handle: input.image_handle,
+            dissolve_image_handle: input.dissolve_image_handle,
+            refraction_image_handle: input.refraction_image_handle,
+            heightfield_image_handle: input.heightfield_image_handle,
+            blend_state: input.blend_state,
             render_shaders: input.effect_shader.render,
             init_pipeline_id,
             update_pipeline_ids,
+            compact_pipeline_ids: vec![],
             entities: vec![input.entity.index()],
         }
     }
@@ -146,8 +195,35 @@ pub(crate) struct BatchesInput {
     pub layout_flags: LayoutFlags,
     /// Texture to modulate the particle color.
     pub image_handle: Handle<Image>,
-    /// Number of particles to spawn for this effect.
+    /// Erosion texture to dissolve the particles.
+    pub dissolve_image_handle: Handle<Image>,
+    /// Scene color texture refracted behind the particles.
+    pub refraction_image_handle: Handle<Image>,
+    /// Heightmap texture sampled during the update pass, if any.
+    pub heightfield_image_handle: Option<Handle<Image>>,
+    /// Custom blend state of the render pipeline, if any.
+    pub blend_state: Option<BlendState>,
+    /// Number of particles to spawn for this effect into group #0.
     pub spawn_count: u32,
+    /// Number of particles to spawn for this effect into each group, indexed
+    /// by group. The first element is the same as [`spawn_count`], without
+    /// any injected particle.
+    ///
+    /// [`spawn_count`]: crate::render::batch::BatchesInput::spawn_count
+    pub group_spawn_counts: Vec<u32>,
+    /// Particles injected from CPU this frame, spawned in addition to
+    /// [`spawn_count`].
+    ///
+    /// [`spawn_count`]: crate::render::batch::BatchesInput::spawn_count
+    pub injected_states: Vec<GpuInjectedState>,
+    /// Simulation delta time for this effect.
+    pub delta_time: f32,
+    /// Whether the effect is warming up.
+    pub is_warming_up: bool,
+    /// Whether the effect is compacted this frame.
+    pub compact: bool,
+    /// Render order of each group.
+    pub group_render_orders: Vec<i32>,
     /// Emitter transform.
     pub transform: GpuCompressedTransform,
     /// Emitter inverse transform.
@@ -161,3 +237,36 @@ pub(crate) struct BatchesInput {
     #[cfg(feature = "2d")]
     pub z_sort_key_2d: FloatOrd,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn draw_order() {
+        let effect0 = Entity::from_raw(3);
+        let effect1 = Entity::from_raw(5);
+        let make_batch = |batches_entity, group_index, render_order| EffectDrawBatch {
+            group_index,
+            batches_entity,
+            render_order,
+            #[cfg(feature = "2d")]
+            z_sort_key_2d: FloatOrd(0.),
+        };
+        let mut batches = [
+            make_batch(effect1, 0, 0),
+            make_batch(effect0, 1, 0),
+            make_batch(effect0, 0, 0),
+            make_batch(effect1, 1, -1),
+        ];
+        batches.sort_by_key(EffectDrawBatch::draw_order_key);
+        let order: Vec<_> = batches
+            .iter()
+            .map(|b| (b.batches_entity, b.group_index))
+            .collect();
+        assert_eq!(
+            order,
+            vec![(effect1, 1), (effect0, 0), (effect0, 1), (effect1, 0)]
+        );
+    }
+}
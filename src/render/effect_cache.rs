@@ -190,7 +190,8 @@ impl EffectBuffer {
         let particle_buffer = render_device.create_buffer(&BufferDescriptor {
             label,
             size: particle_capacity_bytes,
-            usage: BufferUsages::COPY_DST | BufferUsages::STORAGE,
+            // COPY_SRC for snapshots
+            usage: BufferUsages::COPY_SRC | BufferUsages::COPY_DST | BufferUsages::STORAGE,
             mapped_at_creation: false,
         });
 
@@ -204,7 +205,7 @@ impl EffectBuffer {
         let indirect_buffer = render_device.create_buffer(&BufferDescriptor {
             label: Some(&indirect_label),
             size: capacity_bytes * 3, // ping-pong + deadlist
-            usage: BufferUsages::COPY_DST | BufferUsages::STORAGE,
+            usage: BufferUsages::COPY_SRC | BufferUsages::COPY_DST | BufferUsages::STORAGE,
             mapped_at_creation: true,
         });
         // Set content
@@ -392,6 +393,18 @@ impl EffectBuffer {
                 count: None,
             });
         }
+        if !property_layout.is_empty() {
+            entries.push(BindGroupLayoutEntry {
+                binding: 4,
+                visibility: ShaderStages::VERTEX | ShaderStages::FRAGMENT,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Storage { read_only: true },
+                    has_dynamic_offset: false,
+                    min_binding_size: Some(property_layout.min_binding_size()),
+                },
+                count: None,
+            });
+        }
         trace!(
             "Creating render layout with {} entries (flags: {:?})",
             entries.len(),
@@ -418,6 +431,14 @@ impl EffectBuffer {
         }
     }
 
+    pub fn particle_buffer(&self) -> &Buffer {
+        &self.particle_buffer
+    }
+
+    pub fn indirect_buffer(&self) -> &Buffer {
+        &self.indirect_buffer
+    }
+
     pub fn properties_buffer(&self) -> Option<&Buffer> {
         self.properties_buffer.as_ref()
     }
@@ -836,6 +857,12 @@ impl EffectCache {
         self.effects[&id].slices.dispatch_buffer_indices
     }
 
+    /// Get the effect buffer storing the particles of an effect, if any.
+    pub fn get_buffer(&self, id: EffectCacheId) -> Option<&EffectBuffer> {
+        let cached_effect_indices = self.effects.get(&id)?;
+        self.buffers[cached_effect_indices.buffer_index as usize].as_ref()
+    }
+
     pub fn get_property_buffer(&self, id: EffectCacheId) -> Option<&Buffer> {
         if let Some(cached_effect_indices) = self.effects.get(&id) {
             if let Some(buffer) = &self.buffers[cached_effect_indices.buffer_index as usize] {
@@ -1,7 +1,7 @@
 #[cfg(feature = "2d")]
 use bevy::utils::FloatOrd;
 use bevy::{
-    core::{Pod, Zeroable},
+    core::{FrameCount, Pod, Zeroable},
     ecs::{
         prelude::*,
         system::{lifetimeless::*, SystemParam, SystemState},
@@ -25,7 +25,7 @@ use bevy::{
 };
 use bitflags::bitflags;
 use naga_oil::compose::{Composer, NagaModuleDescriptor};
-use rand::random;
+use rand::Rng;
 use std::{
     borrow::Cow,
     num::{NonZeroU32, NonZeroU64},
@@ -41,12 +41,13 @@ use crate::{
     asset::EffectAsset,
     next_multiple_of,
     render::{
-        batch::{BatchesInput, EffectDrawBatch},
+        batch::{BatchesInput, EffectDrawBatch, GroupSpawn},
         effect_cache::DispatchBufferIndices,
     },
-    spawn::EffectSpawner,
-    CompiledParticleEffect, EffectProperties, EffectShader, EffectSimulation, HanabiPlugin,
-    ParticleLayout, PropertyLayout, RemovedEffectsEvent, SimulationCondition,
+    spawn::{EffectSpawner, ParticleInitState, ParticleInjector, Random},
+    CompiledParticleEffect, EffectInstance, EffectProperties, EffectShader, EffectSimulation,
+    HanabiPlugin, ParticleEffect, ParticleLayout, PropertyLayout, RemovedEffectsEvent,
+    SimulationCondition,
 };
 
 mod aligned_buffer_vec;
@@ -54,12 +55,19 @@ mod batch;
 mod buffer_table;
 mod effect_cache;
 mod shader_cache;
+mod snapshot;
+mod stats;
 
 use aligned_buffer_vec::AlignedBufferVec;
 use buffer_table::{BufferTable, BufferTableId};
 pub(crate) use effect_cache::{EffectCache, EffectCacheId};
 
 pub use shader_cache::ShaderCache;
+pub use snapshot::{EffectSnapshot, EffectSnapshotRequest};
+pub use stats::EffectStats;
+pub(crate) use stats::{
+    readback_effect_stats, update_effect_stats, EffectStatsChannel, EffectStatsReadback,
+};
 
 use self::batch::EffectBatches;
 
@@ -106,6 +114,14 @@ pub enum EffectSystems {
     /// [`ParticleEffect`]: crate::ParticleEffect
     GatherRemovedEffects,
 
+    /// Update the [`EffectStats`] component of the effect instances having one.
+    ///
+    /// This system runs during the [`PostUpdate`] schedule, after
+    /// [`EffectSystems::TickSpawners`]. Systems reacting to changes to
+    /// [`EffectStats`] should run after this set to observe the changes
+    /// during the same frame.
+    UpdateEffectStats,
+
     /// Prepare effect assets for the extracted effects.
     PrepareEffectAssets,
 
@@ -136,6 +152,10 @@ pub(crate) struct SimParams {
     real_time: f64,
     /// Real delta time, in seconds, since last effect system update.
     real_delta_time: f32,
+
+    /// World position of the main camera, that is the active camera with the
+    /// lowest order, or zero if there's no active camera.
+    camera_position: Vec3,
 }
 
 /// GPU representation of [`SimParams`], as well as additional per-frame
@@ -178,6 +198,8 @@ struct GpuSimParams {
     ///
     /// This is only used by the `vfx_indirect` compute shader.
     dispatch_stride: u32,
+    /// World position of the main camera.
+    camera_position: Vec3,
 }
 
 impl Default for GpuSimParams {
@@ -193,6 +215,7 @@ impl Default for GpuSimParams {
             render_effect_stride: 0, // invalid
             render_group_stride: 0,  // invalid
             dispatch_stride: 0,      // invalid
+            camera_position: Vec3::ZERO,
         }
     }
 }
@@ -206,6 +229,7 @@ impl From<SimParams> for GpuSimParams {
             virtual_time: src.virtual_time as f32,
             real_delta_time: src.real_delta_time,
             real_time: src.real_time as f32,
+            camera_position: src.camera_position,
             ..default()
         }
     }
@@ -275,6 +299,20 @@ pub(crate) struct GpuSpawnerParams {
     count: i32,
     /// Index of the effect in the indirect dispatch and render buffers.
     effect_index: u32,
+    /// Simulation delta time for this effect this frame, which overrides the
+    /// global [`SimParams::delta_time`] in the init and update passes. This
+    /// differs from the global delta time while the effect is warming up.
+    delta_time: f32,
+    /// Offset of the first particle state injected this frame into the
+    /// injected state buffer shared by all effects.
+    inject_offset: u32,
+    /// Number of particles injected this frame. Those are included in
+    /// [`spawn`], and use the first spawn slots.
+    ///
+    /// [`spawn`]: crate::render::GpuSpawnerParams::spawn
+    inject_count: u32,
+    /// Index of the group the init pass spawns particles into.
+    spawn_group: u32,
 }
 
 impl GpuSpawnerParams {
@@ -304,10 +342,60 @@ impl GpuSpawnerParams {
     }
 }
 
+/// GPU representation of a particle state injected from CPU.
+///
+/// See [`ParticleInjector`] for details.
+///
+/// [`ParticleInjector`]: crate::ParticleInjector
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, Pod, Zeroable)]
+pub(crate) struct GpuInjectedState {
+    /// Initial particle position.
+    position: [f32; 3],
+    /// Bit 0 is set if `velocity` is valid, and bit 1 if `color` is valid.
+    flags: u32,
+    /// Initial particle velocity, if bit 0 of `flags` is set.
+    velocity: [f32; 3],
+    /// Padding.
+    __pad0: u32,
+    /// Initial particle color, if bit 1 of `flags` is set.
+    color: [f32; 4],
+}
+
+impl From<&ParticleInitState> for GpuInjectedState {
+    fn from(state: &ParticleInitState) -> Self {
+        let mut flags = 0;
+        if state.velocity.is_some() {
+            flags |= 1;
+        }
+        if state.color.is_some() {
+            flags |= 2;
+        }
+        Self {
+            position: state.position.to_array(),
+            flags,
+            velocity: state.velocity.unwrap_or_default().to_array(),
+            __pad0: 0,
+            color: state.color.unwrap_or_default().to_array(),
+        }
+    }
+}
+
+/// Indirect dispatch arguments of the update pass of a particle group.
+///
+/// The `vfx_indirect.wgsl` pass writes the workgroup count `x` from the
+/// number of particles alive in the group, so that the update pass only
+/// dispatches threads for alive particles instead of the full group capacity.
+/// Because the update pass itself modifies `alive_count` atomically as
+/// particles die, the indirect pass also snapshots the count into
+/// `max_update`, which the update threads use as their upper bound. This keeps
+/// the dispatch size and the thread bound consistent within the frame.
 // FIXME - min_storage_buffer_offset_alignment
 #[repr(C)]
 #[derive(Debug, Clone, Copy, Pod, Zeroable, ShaderType)]
 pub struct GpuDispatchIndirect {
+    /// Number of workgroups of the update pass, derived from the alive
+    /// particle count.
     pub x: u32,
     pub y: u32,
     pub z: u32,
@@ -345,7 +433,11 @@ pub struct GpuRenderGroupIndirect {
     pub alive_count: u32,
     pub max_update: u32,
     pub dead_count: u32,
-    pub __pad1: u32,
+    pub recycle_count: u32,
+    /// Index of the first particle spawned this frame in the alive list.
+    pub first_spawn_index: u32,
+    /// Number of particles alive before the ones spawned this frame.
+    pub spawn_offset: u32,
     // FIXME - min_storage_buffer_offset_alignment
 }
 
@@ -579,16 +671,30 @@ impl FromWorld for ParticlesInitPipeline {
         );
         let spawner_buffer_layout = render_device.create_bind_group_layout(
             "hanabi:buffer_layout:init_spawner",
-            &[BindGroupLayoutEntry {
-                binding: 0,
-                visibility: ShaderStages::COMPUTE,
-                ty: BindingType::Buffer {
-                    ty: BufferBindingType::Storage { read_only: false },
-                    has_dynamic_offset: true,
-                    min_binding_size: Some(GpuSpawnerParams::min_size()),
+            &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Storage { read_only: false },
+                        has_dynamic_offset: true,
+                        min_binding_size: Some(GpuSpawnerParams::min_size()),
+                    },
+                    count: None,
                 },
-                count: None,
-            }],
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: BufferSize::new(
+                            std::mem::size_of::<GpuInjectedState>() as u64
+                        ),
+                    },
+                    count: None,
+                },
+            ],
         );
 
         trace!(
@@ -732,6 +838,13 @@ pub(crate) struct ParticlesUpdatePipeline {
     sim_params_layout: BindGroupLayout,
     spawner_buffer_layout: BindGroupLayout,
     render_indirect_layout: BindGroupLayout,
+    /// Same as `render_indirect_layout`, with the heightmap texture sampled by
+    /// the [`CollisionHeightfieldModifier`] appended. The heightmap is folded
+    /// into this bind group to stay within the 4 bind groups guaranteed by
+    /// WebGPU.
+    ///
+    /// [`CollisionHeightfieldModifier`]: crate::CollisionHeightfieldModifier
+    render_indirect_heightfield_layout: BindGroupLayout,
 }
 
 impl FromWorld for ParticlesUpdatePipeline {
@@ -767,32 +880,14 @@ impl FromWorld for ParticlesUpdatePipeline {
         );
         let spawner_buffer_layout = render_device.create_bind_group_layout(
             "hanabi:update_spawner_buffer_layout",
-            &[BindGroupLayoutEntry {
-                binding: 0,
-                visibility: ShaderStages::COMPUTE,
-                ty: BindingType::Buffer {
-                    ty: BufferBindingType::Storage { read_only: false },
-                    has_dynamic_offset: true,
-                    min_binding_size: Some(GpuSpawnerParams::min_size()),
-                },
-                count: None,
-            }],
-        );
-
-        trace!(
-            "GpuRenderGroupIndirect: min_size={}",
-            GpuRenderGroupIndirect::min_size()
-        );
-        let render_indirect_layout = render_device.create_bind_group_layout(
-            "hanabi:update_render_indirect_layout",
             &[
                 BindGroupLayoutEntry {
                     binding: 0,
                     visibility: ShaderStages::COMPUTE,
                     ty: BindingType::Buffer {
                         ty: BufferBindingType::Storage { read_only: false },
-                        has_dynamic_offset: false,
-                        min_binding_size: Some(GpuRenderEffectMetadata::min_size()),
+                        has_dynamic_offset: true,
+                        min_binding_size: Some(GpuSpawnerParams::min_size()),
                     },
                     count: None,
                 },
@@ -800,20 +895,77 @@ impl FromWorld for ParticlesUpdatePipeline {
                     binding: 1,
                     visibility: ShaderStages::COMPUTE,
                     ty: BindingType::Buffer {
-                        ty: BufferBindingType::Storage { read_only: false },
+                        ty: BufferBindingType::Storage { read_only: true },
                         has_dynamic_offset: false,
-                        min_binding_size: Some(GpuRenderGroupIndirect::min_size()),
+                        min_binding_size: BufferSize::new(
+                            std::mem::size_of::<GpuInjectedState>() as u64
+                        ),
                     },
                     count: None,
                 },
             ],
         );
 
+        trace!(
+            "GpuRenderGroupIndirect: min_size={}",
+            GpuRenderGroupIndirect::min_size()
+        );
+        let mut render_indirect_entries = vec![
+            BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStages::COMPUTE,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Storage { read_only: false },
+                    has_dynamic_offset: false,
+                    min_binding_size: Some(GpuRenderEffectMetadata::min_size()),
+                },
+                count: None,
+            },
+            BindGroupLayoutEntry {
+                binding: 1,
+                visibility: ShaderStages::COMPUTE,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Storage { read_only: false },
+                    has_dynamic_offset: false,
+                    min_binding_size: Some(GpuRenderGroupIndirect::min_size()),
+                },
+                count: None,
+            },
+        ];
+        let render_indirect_layout = render_device.create_bind_group_layout(
+            "hanabi:update_render_indirect_layout",
+            &render_indirect_entries,
+        );
+
+        render_indirect_entries.extend([
+            BindGroupLayoutEntry {
+                binding: 2,
+                visibility: ShaderStages::COMPUTE,
+                ty: BindingType::Texture {
+                    multisampled: false,
+                    sample_type: TextureSampleType::Float { filterable: true },
+                    view_dimension: TextureViewDimension::D2,
+                },
+                count: None,
+            },
+            BindGroupLayoutEntry {
+                binding: 3,
+                visibility: ShaderStages::COMPUTE,
+                ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                count: None,
+            },
+        ]);
+        let render_indirect_heightfield_layout = render_device.create_bind_group_layout(
+            "hanabi:update_render_indirect_heightfield_layout",
+            &render_indirect_entries,
+        );
+
         Self {
             render_device: render_device.clone(),
             sim_params_layout,
             spawner_buffer_layout,
             render_indirect_layout,
+            render_indirect_heightfield_layout,
         }
     }
 }
@@ -826,6 +978,12 @@ pub(crate) struct ParticleUpdatePipelineKey {
     particle_layout: ParticleLayout,
     /// Property layout.
     property_layout: PropertyLayout,
+    /// Specialize the compaction pass instead of the update pass. Both share
+    /// the same shader and bind group layouts, but use a different entry point.
+    compact: bool,
+    /// Key: HEIGHTFIELD_TEXTURE
+    /// Bind a heightmap texture for terrain collisions.
+    has_heightfield_texture: bool,
 }
 
 impl SpecializedComputePipeline for ParticlesUpdatePipeline {
@@ -896,27 +1054,101 @@ impl SpecializedComputePipeline for ParticlesUpdatePipeline {
         let update_particles_buffer_layout =
             self.render_device.create_bind_group_layout(label, &entries);
 
+        let (label, entry_point) = if key.compact {
+            ("hanabi:pipeline_compact_compute", "compact")
+        } else {
+            ("hanabi:pipeline_update_compute", "main")
+        };
+
+        let mut shader_defs = vec!["REM_MAX_SPAWN_ATOMIC".into()];
+
+        // Key: HEIGHTFIELD_TEXTURE
+        // The heightmap texture is bound after the render indirect buffers, in the
+        // same bind group.
+        let render_indirect_layout = if key.has_heightfield_texture {
+            shader_defs.push("HEIGHTFIELD_TEXTURE".into());
+            self.render_indirect_heightfield_layout.clone()
+        } else {
+            self.render_indirect_layout.clone()
+        };
+
         ComputePipelineDescriptor {
-            label: Some("hanabi:pipeline_update_compute".into()),
+            label: Some(label.into()),
             layout: vec![
                 self.sim_params_layout.clone(),
                 update_particles_buffer_layout,
                 self.spawner_buffer_layout.clone(),
-                self.render_indirect_layout.clone(),
+                render_indirect_layout,
             ],
             shader: key.shader,
-            shader_defs: vec!["REM_MAX_SPAWN_ATOMIC".into()],
-            entry_point: "main".into(),
+            shader_defs,
+            entry_point: entry_point.into(),
             push_constant_ranges: Vec::new(),
         }
     }
 }
 
+/// Textures sampled by the render shader, in the order of their bindings in
+/// the material bind group.
+///
+/// All textures are packed into a single bind group (group 2), to stay within
+/// the 4 bind groups guaranteed by WebGPU. Each texture takes 2 consecutive
+/// bindings, for its view and its sampler, which don't depend on whether the
+/// other textures are present.
+const MATERIAL_TEXTURES: [LayoutFlags; 3] = [
+    LayoutFlags::PARTICLE_TEXTURE,
+    LayoutFlags::DISSOLVE_TEXTURE,
+    LayoutFlags::REFRACTION_TEXTURE,
+];
+
+/// Key of a material bind group, made of the textures it binds in the order of
+/// [`MATERIAL_TEXTURES`]. A texture not used by the effect is `None`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct MaterialKey([Option<AssetId<Image>>; 3]);
+
+impl MaterialKey {
+    /// Get the material of a batch of effects.
+    fn from_batches(batches: &EffectBatches) -> Self {
+        let handles = [
+            &batches.image_handle,
+            &batches.dissolve_image_handle,
+            &batches.refraction_image_handle,
+        ];
+        Self(std::array::from_fn(|index| {
+            batches
+                .layout_flags
+                .contains(MATERIAL_TEXTURES[index])
+                .then(|| handles[index].id())
+        }))
+    }
+
+    /// Get the layout flags of the textures bound by this material.
+    fn layout_flags(&self) -> LayoutFlags {
+        material_layout_flags(self.0.map(|id| id.is_some()))
+    }
+
+    /// Check if the material doesn't bind any texture.
+    fn is_empty(&self) -> bool {
+        self.0.iter().all(Option::is_none)
+    }
+}
+
+/// Get the layout flags of the [`MATERIAL_TEXTURES`] present in a material.
+fn material_layout_flags(present: [bool; 3]) -> LayoutFlags {
+    MATERIAL_TEXTURES
+        .iter()
+        .zip(present)
+        .filter(|(_, present)| *present)
+        .fold(LayoutFlags::NONE, |acc, (flag, _)| acc | *flag)
+}
+
 #[derive(Resource)]
 pub(crate) struct ParticlesRenderPipeline {
     render_device: RenderDevice,
     view_layout: BindGroupLayout,
-    material_layout: BindGroupLayout,
+    /// Layouts of the material bind group, for each combination of
+    /// [`MATERIAL_TEXTURES`] flags.
+    material_layouts: HashMap<LayoutFlags, BindGroupLayout>,
 }
 
 impl FromWorld for ParticlesRenderPipeline {
@@ -950,32 +1182,45 @@ impl FromWorld for ParticlesRenderPipeline {
             ],
         );
 
-        let material_layout = render_device.create_bind_group_layout(
-            "hanabi:material_layout_render",
-            &[
-                BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: ShaderStages::FRAGMENT,
-                    ty: BindingType::Texture {
-                        multisampled: false,
-                        sample_type: TextureSampleType::Float { filterable: true },
-                        view_dimension: TextureViewDimension::D2,
-                    },
-                    count: None,
-                },
-                BindGroupLayoutEntry {
-                    binding: 1,
-                    visibility: ShaderStages::FRAGMENT,
-                    ty: BindingType::Sampler(SamplerBindingType::Filtering),
-                    count: None,
-                },
-            ],
-        );
+        let material_layouts = (1u32..(1 << MATERIAL_TEXTURES.len()))
+            .map(|mask| {
+                let present = std::array::from_fn(|index| mask & (1 << index) != 0);
+                let entries: Vec<_> = present
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, present)| **present)
+                    .flat_map(|(index, _)| {
+                        let binding = index as u32 * 2;
+                        [
+                            BindGroupLayoutEntry {
+                                binding,
+                                visibility: ShaderStages::FRAGMENT,
+                                ty: BindingType::Texture {
+                                    multisampled: false,
+                                    sample_type: TextureSampleType::Float { filterable: true },
+                                    view_dimension: TextureViewDimension::D2,
+                                },
+                                count: None,
+                            },
+                            BindGroupLayoutEntry {
+                                binding: binding + 1,
+                                visibility: ShaderStages::FRAGMENT,
+                                ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                                count: None,
+                            },
+                        ]
+                    })
+                    .collect();
+                let layout = render_device
+                    .create_bind_group_layout("hanabi:material_layout_render", &entries);
+                (material_layout_flags(present), layout)
+            })
+            .collect();
 
         Self {
             render_device: render_device.clone(),
             view_layout,
-            material_layout,
+            material_layouts,
         }
     }
 }
@@ -993,11 +1238,22 @@ pub(crate) struct ParticleRenderPipelineKey {
     shader: Handle<Shader>,
     /// Particle layout.
     particle_layout: ParticleLayout,
+    /// Property layout. If not empty, the property buffer is bound to the
+    /// render shader so that render modifiers can read properties.
+    property_layout: PropertyLayout,
     /// Key: PARTICLE_TEXTURE
     /// Define a texture sampled to modulate the particle color.
     /// This key requires the presence of UV coordinates on the particle
     /// vertices.
     has_image: bool,
+    /// Key: DISSOLVE_TEXTURE
+    /// Define an erosion texture sampled to dissolve the particles. This key
+    /// requires the presence of UV coordinates on the particle vertices.
+    has_dissolve_texture: bool,
+    /// Key: REFRACTION_TEXTURE
+    /// Define a scene color texture sampled to refract the scene behind the
+    /// particles.
+    has_refraction_texture: bool,
     /// Key: LOCAL_SPACE_SIMULATION
     /// The effect is simulated in local space, and during rendering all
     /// particles are transformed by the effect's [`GlobalTransform`].
@@ -1009,6 +1265,25 @@ pub(crate) struct ParticleRenderPipelineKey {
     /// The effect is rendered with flipbook texture animation based on the
     /// sprite index of each particle.
     flipbook: bool,
+    /// Key: POINT_PRIMITIVE
+    /// The effect is rendered with a single point per particle instead of a
+    /// quad.
+    point_primitive: bool,
+    /// Key: PARTICLE_NORMAL
+    /// The effect computes a world space normal in the fragment shader.
+    particle_normal: bool,
+    /// Key: PARTICLE_INDEX
+    /// The fragment shader reads the index of the particle.
+    particle_index: bool,
+    /// Key: BLEND_MIN
+    /// The effect is rendered with a minimum blend operation.
+    blend_min: bool,
+    /// Key: BLEND_MAX
+    /// The effect is rendered with a maximum blend operation.
+    blend_max: bool,
+    /// Custom blend state of the color target, if any. Otherwise alpha
+    /// blending is used.
+    blend_state: Option<BlendState>,
     /// For dual-mode configurations only, the actual mode of the current render
     /// pipeline. Otherwise the mode is implicitly determined by the active
     /// feature.
@@ -1025,10 +1300,19 @@ impl Default for ParticleRenderPipelineKey {
         Self {
             shader: Handle::default(),
             particle_layout: ParticleLayout::empty(),
+            property_layout: PropertyLayout::empty(),
             has_image: false,
+            has_dissolve_texture: false,
+            has_refraction_texture: false,
             local_space_simulation: false,
             use_alpha_mask: false,
             flipbook: false,
+            point_primitive: false,
+            particle_normal: false,
+            particle_index: false,
+            blend_min: false,
+            blend_max: false,
+            blend_state: None,
             #[cfg(all(feature = "2d", feature = "3d"))]
             pipeline_mode: PipelineMode::Camera3d,
             msaa_samples: Msaa::default().samples(),
@@ -1125,6 +1409,18 @@ impl SpecializedRenderPipeline for ParticlesRenderPipeline {
                 count: None,
             });
         }
+        if !key.property_layout.is_empty() {
+            entries.push(BindGroupLayoutEntry {
+                binding: 4,
+                visibility: ShaderStages::VERTEX | ShaderStages::FRAGMENT,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Storage { read_only: true },
+                    has_dynamic_offset: false,
+                    min_binding_size: Some(key.property_layout.min_binding_size()),
+                },
+                count: None,
+            });
+        }
 
         trace!(
             "GpuParticle: layout.min_binding_size={}",
@@ -1141,10 +1437,20 @@ impl SpecializedRenderPipeline for ParticlesRenderPipeline {
         let mut layout = vec![self.view_layout.clone(), particles_buffer_layout];
         let mut shader_defs = vec![];
 
+        // All textures are bound in a single material bind group, if any
+        let material_flags = material_layout_flags([
+            key.has_image,
+            key.has_dissolve_texture,
+            key.has_refraction_texture,
+        ]);
+        if let Some(material_layout) = self.material_layouts.get(&material_flags) {
+            layout.push(material_layout.clone());
+        }
+
         // Key: PARTICLE_TEXTURE
         if key.has_image {
-            layout.push(self.material_layout.clone());
             shader_defs.push("PARTICLE_TEXTURE".into());
+            shader_defs.push("PARTICLE_UV".into());
             // //  @location(1) vertex_uv: vec2<f32>
             // vertex_buffer_layout.attributes.push(VertexAttribute {
             //     format: VertexFormat::Float32x2,
@@ -1154,6 +1460,22 @@ impl SpecializedRenderPipeline for ParticlesRenderPipeline {
             // vertex_buffer_layout.array_stride += 8;
         }
 
+        // Key: DISSOLVE_TEXTURE
+        if key.has_dissolve_texture {
+            shader_defs.push("DISSOLVE_TEXTURE".into());
+            if !key.has_image {
+                shader_defs.push("PARTICLE_UV".into());
+            }
+        }
+
+        // Key: REFRACTION_TEXTURE
+        if key.has_refraction_texture {
+            shader_defs.push("REFRACTION_TEXTURE".into());
+            if !key.has_image && !key.has_dissolve_texture {
+                shader_defs.push("PARTICLE_UV".into());
+            }
+        }
+
         // Key: LOCAL_SPACE_SIMULATION
         if key.local_space_simulation {
             shader_defs.push("LOCAL_SPACE_SIMULATION".into());
@@ -1170,6 +1492,32 @@ impl SpecializedRenderPipeline for ParticlesRenderPipeline {
             shader_defs.push("FLIPBOOK".into());
         }
 
+        // Key: POINT_PRIMITIVE
+        let topology = if key.point_primitive {
+            shader_defs.push("POINT_PRIMITIVE".into());
+            PrimitiveTopology::PointList
+        } else {
+            PrimitiveTopology::TriangleList
+        };
+
+        // Key: PARTICLE_NORMAL
+        if key.particle_normal {
+            shader_defs.push("PARTICLE_NORMAL".into());
+        }
+
+        // Key: PARTICLE_INDEX
+        if key.particle_index {
+            shader_defs.push("PARTICLE_INDEX".into());
+        }
+
+        // Key: BLEND_MIN / BLEND_MAX
+        if key.blend_min {
+            shader_defs.push("BLEND_MIN".into());
+        }
+        if key.blend_max {
+            shader_defs.push("BLEND_MAX".into());
+        }
+
         #[cfg(all(feature = "2d", feature = "3d"))]
         let depth_stencil = match key.pipeline_mode {
             // Bevy's Transparent2d render phase doesn't support a depth-stencil buffer.
@@ -1216,7 +1564,7 @@ impl SpecializedRenderPipeline for ParticlesRenderPipeline {
                 entry_point: "fragment".into(),
                 targets: vec![Some(ColorTargetState {
                     format,
-                    blend: Some(BlendState::ALPHA_BLENDING),
+                    blend: Some(key.blend_state.unwrap_or(BlendState::ALPHA_BLENDING)),
                     write_mask: ColorWrites::ALL,
                 })],
             }),
@@ -1227,7 +1575,7 @@ impl SpecializedRenderPipeline for ParticlesRenderPipeline {
                 unclipped_depth: false,
                 polygon_mode: PolygonMode::Fill,
                 conservative: false,
-                topology: PrimitiveTopology::TriangleList,
+                topology,
                 strip_index_format: None,
             },
             depth_stencil,
@@ -1265,12 +1613,39 @@ pub(crate) struct ExtractedEffect {
     ///
     /// [`property_layout`]: crate::render::ExtractedEffect::property_layout
     pub property_data: Option<Vec<u8>>,
-    /// Number of particles to spawn this frame for the effect.
+    /// Number of particles to spawn this frame for the effect into group #0.
     /// Obtained from calling [`EffectSpawner::tick()`] on the source effect
     /// instance.
     ///
     /// [`EffectSpawner::tick()`]: crate::EffectSpawner::tick
     pub spawn_count: u32,
+    /// Number of particles to spawn this frame for the effect into each
+    /// group, indexed by group. Obtained from
+    /// [`EffectSpawner::spawn_count_for_group()`].
+    ///
+    /// [`EffectSpawner::spawn_count_for_group()`]: crate::EffectSpawner::spawn_count_for_group
+    pub group_spawn_counts: Vec<u32>,
+    /// Particles injected this frame, from the [`ParticleInjector`] of the
+    /// source effect instance, if any. Those are not included in
+    /// [`spawn_count`].
+    ///
+    /// [`ParticleInjector`]: crate::ParticleInjector
+    /// [`spawn_count`]: crate::render::ExtractedEffect::spawn_count
+    pub injected_states: Vec<GpuInjectedState>,
+    /// Simulation delta time this frame for the effect, including any warmup
+    /// step. Obtained from [`EffectSpawner::delta_time()`].
+    ///
+    /// [`EffectSpawner::delta_time()`]: crate::EffectSpawner::delta_time
+    pub delta_time: f32,
+    /// Whether the effect is still warming up, in which case it's simulated
+    /// but not rendered.
+    pub is_warming_up: bool,
+    /// Whether the particles of the effect are compacted this frame. See
+    /// [`EffectAsset::with_compaction_interval()`].
+    pub compact: bool,
+    /// Render order of each group. See
+    /// [`EffectAsset::with_group_render_order()`].
+    pub group_render_orders: Vec<i32>,
     /// Global transform of the effect origin, extracted from the
     /// [`GlobalTransform`].
     pub transform: Mat4,
@@ -1281,6 +1656,15 @@ pub(crate) struct ExtractedEffect {
     pub layout_flags: LayoutFlags,
     /// Texture to modulate the particle color.
     pub image_handle: Handle<Image>,
+    /// Erosion texture to dissolve the particles.
+    pub dissolve_image_handle: Handle<Image>,
+    /// Scene color texture refracted behind the particles.
+    pub refraction_image_handle: Handle<Image>,
+    /// Heightmap texture sampled during the update pass, if any.
+    pub heightfield_image_handle: Option<Handle<Image>>,
+    /// Custom blend state of the render pipeline, if any. See
+    /// [`EffectAsset::with_blend_state()`].
+    pub blend_state: Option<BlendState>,
     /// Effect shader.
     pub effect_shader: EffectShader,
     /// For 2D rendering, the Z coordinate used as the sort key. Ignored for 3D
@@ -1353,17 +1737,22 @@ pub(crate) fn extract_effect_events(
 /// Extract rendering data for all [`ParticleEffect`] components in the world
 /// which are visible ([`ComputedVisibility::is_visible`] is `true`), and wrap
 /// the data into a new [`ExtractedEffect`] instance added to the
-/// [`ExtractedEffects`] resource.
+/// [`ExtractedEffects`] resource. Effect instances disabled via their
+/// [`EffectInstance`] component are skipped, so are neither simulated nor
+/// rendered, but retain their GPU buffers.
 ///
 /// This system runs in parallel of [`extract_effect_events`].
 ///
 /// [`ParticleEffect`]: crate::ParticleEffect
+/// [`EffectInstance`]: crate::EffectInstance
 pub(crate) fn extract_effects(
     real_time: Extract<Res<Time<Real>>>,
     virtual_time: Extract<Res<Time<Virtual>>>,
     time: Extract<Res<Time<EffectSimulation>>>,
     effects: Extract<Res<Assets<EffectAsset>>>,
     _images: Extract<Res<Assets<Image>>>,
+    frame_count: Extract<Option<Res<FrameCount>>>,
+    cameras: Extract<Query<(&Camera, &GlobalTransform)>>,
     mut query: Extract<
         ParamSet<(
             // All existing ParticleEffect components
@@ -1373,8 +1762,11 @@ pub(crate) fn extract_effects(
                 Option<&ViewVisibility>,
                 &EffectSpawner,
                 &CompiledParticleEffect,
+                Option<&ParticleEffect>,
                 Option<Ref<EffectProperties>>,
                 &GlobalTransform,
+                Option<&ParticleInjector>,
+                Option<&EffectInstance>,
             )>,
             // Newly added ParticleEffect components
             Query<
@@ -1396,6 +1788,12 @@ pub(crate) fn extract_effects(
     sim_params.virtual_delta_time = virtual_time.delta_seconds();
     sim_params.real_time = real_time.elapsed_seconds_f64();
     sim_params.real_delta_time = real_time.delta_seconds();
+    sim_params.camera_position = cameras
+        .iter()
+        .filter(|(camera, _)| camera.is_active)
+        .min_by_key(|(camera, _)| camera.order)
+        .map(|(_, transform)| transform.translation())
+        .unwrap_or(Vec3::ZERO);
 
     // Collect removed effects for later GPU data purge
     extracted_effects.removed_effect_entities =
@@ -1419,19 +1817,22 @@ pub(crate) fn extract_effects(
         .map(|(entity, effect)| {
             let handle = effect.asset.clone_weak();
             let asset = effects.get(&effect.asset).unwrap();
-            let particle_layout = asset.particle_layout();
+            // Allocate the GPU resources based on the layouts and capacities the
+            // effect shaders were compiled with, which may differ from the ones of
+            // the asset if it was modified since.
+            let particle_layout = effect.particle_layout.clone();
             assert!(
                 particle_layout.size() > 0,
                 "Invalid empty particle layout for effect '{}' on entity {:?}. Did you forget to add some modifier to the asset?",
                 asset.name,
                 entity
             );
-            let property_layout = asset.property_layout();
+            let property_layout = effect.property_layout.clone();
 
-            trace!("Found new effect: entity {:?} | capacities {:?} | particle_layout {:?} | property_layout {:?} | layout_flags {:?}", entity, asset.capacities(), particle_layout, property_layout, effect.layout_flags);
+            trace!("Found new effect: entity {:?} | capacities {:?} | particle_layout {:?} | property_layout {:?} | layout_flags {:?}", entity, effect.capacities, particle_layout, property_layout, effect.layout_flags);
             AddedEffect {
                 entity,
-                capacities: asset.capacities().to_vec(),
+                capacities: effect.capacities.clone(),
                 particle_layout,
                 property_layout,
                 layout_flags: effect.layout_flags,
@@ -1448,10 +1849,19 @@ pub(crate) fn extract_effects(
         maybe_view_visibility,
         spawner,
         effect,
+        maybe_instance,
         maybe_properties,
         transform,
+        maybe_injector,
+        maybe_effect_instance,
     ) in query.p0().iter_mut()
     {
+        // Skip disabled instances. Their GPU buffers stay allocated, so they can be
+        // re-enabled later without any reallocation.
+        if !maybe_effect_instance.map_or(true, |instance| instance.is_enabled()) {
+            continue;
+        }
+
         // Check if shaders are configured
         let Some(effect_shader) = effect.get_configured_shader() else {
             continue;
@@ -1468,7 +1878,21 @@ pub(crate) fn extract_effects(
         }
 
         // Retrieve other values from the compiled effect
-        let spawn_count = spawner.spawn_count();
+        let group_spawn_counts: Vec<u32> = (0..effect.capacities.len() as u32)
+            .map(|group_index| spawner.spawn_count_for_group(group_index))
+            .collect();
+        let spawn_count = group_spawn_counts.first().copied().unwrap_or(0);
+        let injected_states = maybe_injector
+            .map(|injector| {
+                injector
+                    .injected()
+                    .iter()
+                    .map(GpuInjectedState::from)
+                    .collect()
+            })
+            .unwrap_or_default();
+        let delta_time = spawner.delta_time();
+        let is_warming_up = spawner.is_warming_up();
 
         // Check if asset is available, otherwise silently ignore
         let Some(asset) = effects.get(&effect.asset) else {
@@ -1487,8 +1911,24 @@ pub(crate) fn extract_effects(
             .as_ref()
             .map(|handle| handle.clone_weak())
             .unwrap_or_default();
+        let dissolve_image_handle = effect
+            .dissolve_texture
+            .as_ref()
+            .map(|handle| handle.clone_weak())
+            .unwrap_or_default();
+        let refraction_image_handle = effect
+            .refraction_texture
+            .as_ref()
+            .map(|handle| handle.clone_weak())
+            .unwrap_or_default();
+        let heightfield_image_handle = effect
+            .heightfield_texture
+            .as_ref()
+            .map(|handle| handle.clone_weak());
 
-        let property_layout = asset.property_layout();
+        // Use the property layout the effect was compiled with, which is the one the
+        // properties buffer was sized for, even if the asset was modified since.
+        let property_layout = effect.property_layout.clone();
 
         let property_data = if let Some(properties) = maybe_properties {
             if properties.is_changed() {
@@ -1501,10 +1941,22 @@ pub(crate) fn extract_effects(
             None
         };
 
-        let mut layout_flags = effect.layout_flags;
-        if effect.particle_texture.is_some() {
-            layout_flags |= LayoutFlags::PARTICLE_TEXTURE;
+        let layout_flags = effect.render_layout_flags();
+
+        // Compose the emitter transform with the local transform of the instance
+        let transform = if let Some(instance) = maybe_instance {
+            transform.mul_transform(instance.local_transform)
+        } else {
+            *transform
         }
+        .compute_matrix();
+
+        // Compact periodically, if enabled
+        let compact = asset.compaction_interval > 0
+            && frame_count
+                .as_ref()
+                .map(|frame_count| frame_count.0 % asset.compaction_interval == 0)
+                .unwrap_or(false);
 
         trace!(
             "Extracted instance of effect '{}' on entity {:?}: image_handle={:?} has_image={} layout_flags={:?}",
@@ -1519,15 +1971,27 @@ pub(crate) fn extract_effects(
             entity,
             ExtractedEffect {
                 handle: effect.asset.clone_weak(),
-                particle_layout: asset.particle_layout().clone(),
+                particle_layout: effect.particle_layout.clone(),
                 property_layout,
                 property_data,
                 spawn_count,
-                transform: transform.compute_matrix(),
+                group_spawn_counts,
+                injected_states,
+                delta_time,
+                is_warming_up,
+                compact,
+                group_render_orders: (0..effect.capacities.len() as u32)
+                    .map(|group_index| asset.group_render_order(group_index))
+                    .collect(),
+                transform,
                 // TODO - more efficient/correct way than inverse()?
-                inverse_transform: transform.compute_matrix().inverse(),
+                inverse_transform: transform.inverse(),
                 layout_flags,
                 image_handle,
+                dissolve_image_handle,
+                refraction_image_handle,
+                heightfield_image_handle,
+                blend_state: effect.blend_state,
                 effect_shader,
                 #[cfg(feature = "2d")]
                 z_sort_key_2d,
@@ -1641,6 +2105,11 @@ impl GpuLimits {
 /// render for the current frame, for all views in the frame, and consumed by
 /// [`queue_effects()`] to actually enqueue the drawning commands to draw those
 /// effects.
+///
+/// Use [`effect_buffers()`] to access the GPU buffers of an effect instance
+/// from a custom render world system or render graph node.
+///
+/// [`effect_buffers()`]: crate::EffectsMeta::effect_buffers
 #[derive(Resource)]
 pub struct EffectsMeta {
     /// Map from an entity with a [`ParticleEffect`] component attached to it,
@@ -1668,6 +2137,8 @@ pub struct EffectsMeta {
 
     sim_params_uniforms: UniformBuffer<GpuSimParams>,
     spawner_buffer: AlignedBufferVec<GpuSpawnerParams>,
+    /// Particle states injected from CPU this frame, for all effects combined.
+    injected_state_buffer: BufferVec<GpuInjectedState>,
     dispatch_indirect_buffer: BufferTable<GpuDispatchIndirect>,
     /// Stores the GPU `RenderEffectMetadata` structures, which describe mutable
     /// data relating to the entire effect.
@@ -1695,8 +2166,58 @@ pub struct EffectsMeta {
     gpu_limits: GpuLimits,
 }
 
+/// GPU buffers storing the particles of a single effect instance.
+///
+/// This is returned by [`EffectsMeta::effect_buffers()`] to allow running
+/// custom GPU passes over the particles of an effect, for example a custom
+/// compute shader implementing flocking. The buffers are shared with other
+/// effect instances, so only the particles in the range of the instance
+/// should be accessed.
+///
+/// # Layout
+///
+/// The particle buffer is a storage buffer containing an array of `Particle`
+/// structs, whose fields are defined by the [`ParticleLayout`] of the effect.
+/// The WGSL code declaring the fields of that struct can be obtained with
+/// [`ParticleLayout::generate_code()`]. The particles of the effect instance
+/// are stored at indices `[slices[0]:slices[N])` of that array, with the
+/// particles of the particle group `i` at indices `[slices[i]:slices[i+1])`.
+///
+/// The indirect buffer is a storage buffer of `u32` values, grouped by 3 for
+/// each particle index: the particle indices of alive particles, for the "ping"
+/// and "pong" frames respectively, and the indices of dead particles. Most
+/// custom passes only need the particle buffer, and can check the age and
+/// lifetime of each particle to determine if it's alive.
+///
+/// # Synchronization
+///
+/// The particle buffer is written by the init and update compute passes of
+/// the Hanabi simulation sub-graph, and read by the render pass of each view.
+/// A custom pass accessing the particles should run in a render graph node
+/// scheduled after the [`HanabiDriverNode`] and before the camera driver node,
+/// so that it observes the particles simulated this frame, and its results are
+/// visible to the rendering of the same frame. Writing to the buffers in any
+/// other place races with the Hanabi passes. Custom passes should not write
+/// the indirect buffer, nor change which particles are alive, otherwise the
+/// internal state of the effect becomes inconsistent.
+///
+/// [`HanabiDriverNode`]: crate::main_graph::node::HanabiDriverNode
+#[derive(Debug, Clone)]
+pub struct EffectGpuBuffers {
+    /// GPU buffer storing the particles.
+    pub particle_buffer: Buffer,
+    /// GPU buffer storing the indirection indices of the particles.
+    pub indirect_buffer: Buffer,
+    /// Boundaries of the slices of the particle groups in the particle buffer,
+    /// in number of particles. This contains one more element than the number
+    /// of groups.
+    pub slices: Vec<u32>,
+    /// Layout of a single particle in the particle buffer.
+    pub particle_layout: ParticleLayout,
+}
+
 impl EffectsMeta {
-    pub fn new(device: RenderDevice) -> Self {
+    pub(crate) fn new(device: RenderDevice) -> Self {
         let mut vertices = BufferVec::new(BufferUsages::VERTEX);
         for v in QUAD_VERTEX_POSITIONS {
             let uv = v.truncate() + 0.5;
@@ -1731,6 +2252,11 @@ impl EffectsMeta {
                 NonZeroU64::new(item_align),
                 Some("hanabi:buffer:spawner".to_string()),
             ),
+            injected_state_buffer: {
+                let mut buffer = BufferVec::new(BufferUsages::STORAGE);
+                buffer.set_label(Some("hanabi:buffer:injected_states"));
+                buffer
+            },
             dispatch_indirect_buffer: BufferTable::new(
                 BufferUsages::STORAGE | BufferUsages::INDIRECT,
                 // NOTE: Technically we're using an offset in dispatch_workgroups_indirect(), but
@@ -1761,9 +2287,32 @@ impl EffectsMeta {
         }
     }
 
+    /// Get the GPU buffers storing the particles of an effect instance.
+    ///
+    /// The `entity` is the entity the [`ParticleEffect`] component is
+    /// attached to. This returns `None` if the effect instance was not
+    /// allocated yet, which happens during the first frame the effect is
+    /// extracted to the render world. See [`EffectGpuBuffers`] for the layout
+    /// of the buffers and the synchronization requirements.
+    ///
+    /// This resource is only available in the render world.
+    ///
+    /// [`ParticleEffect`]: crate::ParticleEffect
+    pub fn effect_buffers(&self, entity: Entity) -> Option<EffectGpuBuffers> {
+        let id = *self.entity_map.get(&entity)?;
+        let buffer = self.effect_cache.get_buffer(id)?;
+        let slices = self.effect_cache.get_slices(id);
+        Some(EffectGpuBuffers {
+            particle_buffer: buffer.particle_buffer().clone(),
+            indirect_buffer: buffer.indirect_buffer().clone(),
+            slices: slices.slices,
+            particle_layout: slices.particle_layout,
+        })
+    }
+
     /// Allocate internal resources for newly spawned effects, and deallocate
     /// them for just-removed ones.
-    pub fn add_remove_effects(
+    pub(crate) fn add_remove_effects(
         &mut self,
         mut added_effects: Vec<AddedEffect>,
         removed_effect_entities: Vec<Entity>,
@@ -1831,9 +2380,18 @@ impl EffectsMeta {
                 &mut self.render_group_dispatch_buffer,
                 added_effect.capacities.iter().map(|&capacity| {
                     let indirect_dispatch = GpuRenderGroupIndirect {
-                        vertex_count: 6, // TODO - Flexible vertex count and mesh particles
+                        // TODO - Flexible vertex count and mesh particles
+                        vertex_count: if added_effect
+                            .layout_flags
+                            .contains(LayoutFlags::POINT_PRIMITIVE)
+                        {
+                            1
+                        } else {
+                            6
+                        },
                         dead_count: capacity,
                         base_instance: current_base_instance,
+                        first_spawn_index: u32::MAX,
                         ..default()
                     };
                     current_base_instance += capacity;
@@ -1927,6 +2485,20 @@ bitflags! {
         const USE_ALPHA_MASK = (1 << 3);
         /// The effect is rendered with flipbook texture animation based on the [`Attribute::SPRITE_INDEX`] of each particle.
         const FLIPBOOK = (1 << 4);
+        /// The effect dissolves particles with an erosion texture.
+        const DISSOLVE_TEXTURE = (1 << 5);
+        /// The effect refracts the scene color texture behind particles.
+        const REFRACTION_TEXTURE = (1 << 6);
+        /// The effect renders each particle as a single point instead of a quad.
+        const POINT_PRIMITIVE = (1 << 7);
+        /// The effect computes a normal for each particle fragment.
+        const PARTICLE_NORMAL = (1 << 8);
+        /// The effect is rendered with a minimum blend operation.
+        const BLEND_MIN = (1 << 9);
+        /// The effect is rendered with a maximum blend operation.
+        const BLEND_MAX = (1 << 10);
+        /// The fragment shader reads the index of the particle.
+        const PARTICLE_INDEX = (1 << 11);
     }
 }
 
@@ -1952,6 +2524,7 @@ pub(crate) fn prepare_effects(
     mut effects_meta: ResMut<EffectsMeta>,
     mut extracted_effects: ResMut<ExtractedEffects>,
     mut effect_bind_groups: ResMut<EffectBindGroups>,
+    mut random: ResMut<Random>,
 ) {
     trace!("prepare_effects");
 
@@ -2019,7 +2592,17 @@ pub(crate) fn prepare_effects(
                 effect_shader: extracted_effect.effect_shader.clone(),
                 layout_flags: extracted_effect.layout_flags,
                 image_handle: extracted_effect.image_handle,
+                dissolve_image_handle: extracted_effect.dissolve_image_handle,
+                refraction_image_handle: extracted_effect.refraction_image_handle,
+                heightfield_image_handle: extracted_effect.heightfield_image_handle,
+                blend_state: extracted_effect.blend_state,
                 spawn_count: extracted_effect.spawn_count,
+                group_spawn_counts: extracted_effect.group_spawn_counts,
+                injected_states: extracted_effect.injected_states,
+                delta_time: extracted_effect.delta_time,
+                is_warming_up: extracted_effect.is_warming_up,
+                compact: extracted_effect.compact,
+                group_render_orders: extracted_effect.group_render_orders,
                 transform: extracted_effect.transform.into(),
                 inverse_transform: extracted_effect.inverse_transform.into(),
                 property_buffer,
@@ -2042,9 +2625,10 @@ pub(crate) fn prepare_effects(
     // Loop on all extracted effects in order and try to batch them together to
     // reduce draw calls
     effects_meta.spawner_buffer.clear();
+    effects_meta.injected_state_buffer.clear();
     effects_meta.particle_group_buffer.clear();
     let mut total_group_count = 0;
-    for (effect_index, input) in effect_entity_list.into_iter().enumerate() {
+    for (effect_index, mut input) in effect_entity_list.into_iter().enumerate() {
         // Specialize the init pipeline based on the effect. Note that this is shared by
         // all effect groups of a same effect.
         trace!(
@@ -2089,10 +2673,37 @@ pub(crate) fn prepare_effects(
                         shader: update_source.clone(),
                         particle_layout: input.effect_slices.particle_layout.clone(),
                         property_layout: input.property_layout.clone(),
+                        compact: false,
+                        has_heightfield_texture: input.heightfield_image_handle.is_some(),
                     },
                 )
             })
             .collect();
+
+        // Specialize the compaction pipelines, only on frames where the effect is
+        // compacted.
+        let compact_pipeline_ids: Vec<_> = if input.compact {
+            input
+                .effect_shader
+                .update
+                .iter()
+                .map(|update_source| {
+                    specialized_update_pipelines.specialize(
+                        &pipeline_cache,
+                        &update_pipeline,
+                        ParticleUpdatePipelineKey {
+                            shader: update_source.clone(),
+                            particle_layout: input.effect_slices.particle_layout.clone(),
+                            property_layout: input.property_layout.clone(),
+                            compact: true,
+                            has_heightfield_texture: input.heightfield_image_handle.is_some(),
+                        },
+                    )
+                })
+                .collect()
+        } else {
+            vec![]
+        };
         trace!(
             "Update pipeline(s) specialized: ids={:?}",
             update_pipeline_ids
@@ -2127,21 +2738,59 @@ pub(crate) fn prepare_effects(
         // will be pushed in order into the array.
         let spawner_base = effects_meta.spawner_buffer.len() as u32;
 
+        // Append the particles injected this frame, if any. Those are spawned in
+        // addition to the ones emitted by the spawner.
+        let inject_offset = effects_meta.injected_state_buffer.len() as u32;
+        let inject_count = input.injected_states.len() as u32;
+        for state in input.injected_states.drain(..) {
+            effects_meta.injected_state_buffer.push(state);
+        }
+        input.spawn_count += inject_count;
+
         let spawner_params = GpuSpawnerParams {
             transform: input.transform,
             inverse_transform: input.inverse_transform,
             spawn: input.spawn_count as i32,
-            seed: random::<u32>(), /* FIXME - Probably bad to re-seed each time there's a
-                                    * change */
+            // Drawn from the render world RNG, so that seeding it makes the GPU
+            // simulation deterministic.
+            seed: random.0.gen::<u32>(),
             count: 0,
             // FIXME: the effect_index is global inside the global spawner buffer,
             // but the group_index is the index of the particle buffer, which can
             // in theory (with batching) contain > 1 effect per buffer.
             effect_index: input.effect_slices.buffer_index,
+            delta_time: input.delta_time,
+            inject_offset,
+            inject_count,
+            ..default()
         };
         trace!("spawner_params = {:?}", spawner_params);
         effects_meta.spawner_buffer.push(spawner_params);
 
+        // Add one spawner entry for each other group some spawner emits into. Those
+        // are only used by the init pass; the update pass always uses the entry of
+        // the effect at spawner_base.
+        let group_spawns: Vec<_> = input
+            .group_spawn_counts
+            .iter()
+            .enumerate()
+            .skip(1)
+            .filter(|(_, spawn_count)| **spawn_count > 0)
+            .map(|(group_index, spawn_count)| {
+                let spawner_index = effects_meta.spawner_buffer.push(GpuSpawnerParams {
+                    spawn: *spawn_count as i32,
+                    inject_count: 0,
+                    spawn_group: group_index as u32,
+                    ..spawner_params
+                }) as u32;
+                GroupSpawn {
+                    group_index: group_index as u32,
+                    spawner_index,
+                    spawn_count: *spawn_count,
+                }
+            })
+            .collect();
+
         // Create the particle group buffer entries.
         let mut first_particle_group_buffer_index = None;
         let mut local_group_count = 0;
@@ -2183,11 +2832,12 @@ pub(crate) fn prepare_effects(
 
         #[cfg(feature = "2d")]
         let z_sort_key_2d = input.z_sort_key_2d;
+        let group_render_orders = std::mem::take(&mut input.group_render_orders);
 
         // Spawn one shared EffectBatches for all groups of this effect. This contains
         // most of the data needed to drive rendering, except the per-group data.
         // However this doesn't drive rendering; this is just storage.
-        let batches = EffectBatches::from_input(
+        let mut batches = EffectBatches::from_input(
             input,
             spawner_base,
             effect_cache_id,
@@ -2196,6 +2846,8 @@ pub(crate) fn prepare_effects(
             dispatch_buffer_indices,
             first_particle_group_buffer_index.unwrap_or_default(),
         );
+        batches.compact_pipeline_ids = compact_pipeline_ids;
+        batches.group_spawns = group_spawns;
         let batches_entity = commands.spawn(batches).id();
 
         // Spawn one EffectDrawBatch per group, to actually drive rendering. Each group
@@ -2205,6 +2857,10 @@ pub(crate) fn prepare_effects(
             commands.spawn(EffectDrawBatch {
                 batches_entity,
                 group_index,
+                render_order: group_render_orders
+                    .get(group_index as usize)
+                    .copied()
+                    .unwrap_or(0),
                 #[cfg(feature = "2d")]
                 z_sort_key_2d,
             });
@@ -2216,6 +2872,17 @@ pub(crate) fn prepare_effects(
         .spawner_buffer
         .write_buffer(&render_device, &render_queue);
 
+    // Write the injected particle states for this frame. The buffer is always
+    // bound, so make sure it's never empty.
+    if effects_meta.injected_state_buffer.is_empty() {
+        effects_meta
+            .injected_state_buffer
+            .push(GpuInjectedState::default());
+    }
+    effects_meta
+        .injected_state_buffer
+        .write_buffer(&render_device, &render_queue);
+
     // Write the entire particle group buffer for this frame
     effects_meta
         .particle_group_buffer
@@ -2283,6 +2950,8 @@ pub(crate) struct BufferBindGroups {
     /// #ifdef RENDER_NEEDS_SPAWNER
     /// @binding(3) var<storage, read> spawner : Spawner;
     /// #endif
+    /// // only if the effect has properties
+    /// @binding(4) var<storage, read> properties : Properties;
     /// ```
     render: BindGroup,
 }
@@ -2292,15 +2961,16 @@ pub struct EffectBindGroups {
     /// Map from buffer index to the bind groups shared among all effects that
     /// use that buffer.
     particle_buffers: HashMap<u32, BufferBindGroups>,
-    /// Map of bind groups for image assets used as particle textures.
-    images: HashMap<AssetId<Image>, BindGroup>,
+    /// Map of material bind groups for the textures sampled by the render
+    /// pass.
+    materials: HashMap<MaterialKey, BindGroup>,
     /// Map from effect index to its init particle buffer bind group (group 1).
     init_particle_buffer_bind_groups: HashMap<EffectCacheId, BindGroup>,
     /// Map from effect index to its update particle buffer bind group (group
     /// 1).
     update_particle_buffer_bind_groups: HashMap<EffectCacheId, BindGroup>,
     /// Map from effect index to its update render indirect bind group (group
-    /// 3).
+    /// 3), which also contains the heightmap texture of the effect, if any.
     update_render_indirect_bind_groups: HashMap<EffectCacheId, BindGroup>,
 }
 
@@ -2328,6 +2998,60 @@ pub struct QueueEffectsReadOnlyParams<'w, 's> {
     marker: PhantomData<&'s usize>,
 }
 
+/// Ensure a material bind group exists for the given textures, creating it if
+/// needed.
+///
+/// Returns `false` if any of the images is not yet available as a GPU
+/// resource, in which case no bind group can be created yet.
+fn prepare_material_bind_group(
+    materials: &mut HashMap<MaterialKey, BindGroup>,
+    material: &MaterialKey,
+    gpu_images: &RenderAssets<Image>,
+    render_device: &RenderDevice,
+    material_layouts: &HashMap<LayoutFlags, BindGroupLayout>,
+) -> bool {
+    if let Some(bind_group) = materials.get(material) {
+        trace!(
+            "Material {:?} already has bind group {:?}.",
+            material,
+            bind_group
+        );
+        return true;
+    }
+
+    // If the textures don't have a bind group yet from another instance of the
+    // same effect, then try to create one now
+    trace!(
+        "Material {:?} has missing GPU bind group, creating...",
+        material
+    );
+    let mut entries = Vec::with_capacity(MATERIAL_TEXTURES.len() * 2);
+    for (index, id) in material.0.iter().enumerate() {
+        let Some(id) = id else {
+            continue;
+        };
+        let Some(gpu_image) = gpu_images.get(*id) else {
+            return false;
+        };
+        let binding = index as u32 * 2;
+        entries.push(BindGroupEntry {
+            binding,
+            resource: BindingResource::TextureView(&gpu_image.texture_view),
+        });
+        entries.push(BindGroupEntry {
+            binding: binding + 1,
+            resource: BindingResource::Sampler(&gpu_image.sampler),
+        });
+    }
+    let bind_group = render_device.create_bind_group(
+        "hanabi:material_bind_group",
+        &material_layouts[&material.layout_flags()],
+        &entries,
+    );
+    materials.insert(*material, bind_group);
+    true
+}
+
 fn emit_draw<T, F>(
     views: &mut Query<(&mut RenderPhase<T>, &VisibleEntities, &ExtractedView)>,
     effect_batches: &Query<(Entity, &mut EffectBatches)>,
@@ -2346,6 +3070,13 @@ fn emit_draw<T, F>(
     T: PhaseItem,
     F: Fn(CachedRenderPipelineId, Entity, &EffectDrawBatch, u32) -> T,
 {
+    // Sort the draw batches by render order, then by effect and group index. Phase
+    // items with the same sort key retain their insertion order when the render
+    // phase is sorted, so this makes the draw order of groups deterministic and
+    // controlled by their render order.
+    let mut draw_batches: Vec<_> = effect_draw_batches.iter().collect();
+    draw_batches.sort_by_key(|(_, draw_batch)| draw_batch.draw_order_key());
+
     for (mut render_phase, visible_entities, view) in views.iter_mut() {
         trace!("Process new view (use_alpha_mask={})", use_alpha_mask);
 
@@ -2358,7 +3089,7 @@ fn emit_draw<T, F>(
         // For each view, loop over all the effect batches to determine if the effect
         // needs to be rendered for that view, and enqueue a view-dependent
         // batch if so.
-        for (draw_entity, draw_batch) in effect_draw_batches.iter() {
+        for &(draw_entity, draw_batch) in draw_batches.iter() {
             trace!(
                 "Process draw batch: draw_entity={:?} group_index={} batches_entity={:?}",
                 draw_entity,
@@ -2384,6 +3115,11 @@ fn emit_draw<T, F>(
                 continue;
             }
 
+            // Effects warming up are simulated but not rendered yet
+            if batches.is_warming_up {
+                continue;
+            }
+
             // Check if batch contains any entity visible in the current view. Otherwise we
             // can skip the entire batch. Note: This is O(n^2) but (unlike
             // the Sprite renderer this is inspired from) we don't expect more than
@@ -2400,61 +3136,39 @@ fn emit_draw<T, F>(
             // FIXME - We draw the entire batch, but part of it may not be visible in this
             // view! We should re-batch for the current view specifically!
 
-            // Ensure the particle texture is available as a GPU resource and create a bind
-            // group for it
-            let has_image = batches.layout_flags.contains(LayoutFlags::PARTICLE_TEXTURE);
-            if has_image {
-                if effect_bind_groups
-                    .images
-                    .get(&batches.image_handle.id())
-                    .is_none()
-                {
-                    trace!(
-                        "Batch buffer #{} has missing GPU image bind group, creating...",
-                        batches.buffer_index,
-                    );
-                    // If texture doesn't have a bind group yet from another instance of the
-                    // same effect, then try to create one now
-                    if let Some(gpu_image) = gpu_images.get(&batches.image_handle) {
-                        let bind_group = render_device.create_bind_group(
-                            "hanabi:material_bind_group",
-                            &read_params.render_pipeline.material_layout,
-                            &[
-                                BindGroupEntry {
-                                    binding: 0,
-                                    resource: BindingResource::TextureView(&gpu_image.texture_view),
-                                },
-                                BindGroupEntry {
-                                    binding: 1,
-                                    resource: BindingResource::Sampler(&gpu_image.sampler),
-                                },
-                            ],
-                        );
-                        effect_bind_groups
-                            .images
-                            .insert(batches.image_handle.id(), bind_group);
-                    } else {
-                        // Texture is not ready; skip for now...
-                        trace!("GPU image not yet available; skipping batch for now.");
-                        continue;
-                    }
-                } else {
-                    trace!(
-                        "Image {:?} already has bind group {:?}.",
-                        batches.image_handle,
-                        effect_bind_groups
-                            .images
-                            .get(&batches.image_handle.id())
-                            .unwrap()
-                    );
-                }
+            // Ensure the textures (particle texture, erosion texture of the dissolve
+            // effect, and scene color texture of the refraction effect) are available
+            // as GPU resources and create a material bind group for them
+            let material = MaterialKey::from_batches(batches);
+            if !material.is_empty()
+                && !prepare_material_bind_group(
+                    &mut effect_bind_groups.materials,
+                    &material,
+                    gpu_images,
+                    &render_device,
+                    &read_params.render_pipeline.material_layouts,
+                )
+            {
+                // Texture is not ready; skip for now...
+                trace!("GPU image not yet available; skipping batch for now.");
+                continue;
             }
+            let has_image = batches.layout_flags.contains(LayoutFlags::PARTICLE_TEXTURE);
+            let has_dissolve_texture = batches.layout_flags.contains(LayoutFlags::DISSOLVE_TEXTURE);
+            let has_refraction_texture = batches
+                .layout_flags
+                .contains(LayoutFlags::REFRACTION_TEXTURE);
 
             let local_space_simulation = batches
                 .layout_flags
                 .contains(LayoutFlags::LOCAL_SPACE_SIMULATION);
             let use_alpha_mask = batches.layout_flags.contains(LayoutFlags::USE_ALPHA_MASK);
             let flipbook = batches.layout_flags.contains(LayoutFlags::FLIPBOOK);
+            let point_primitive = batches.layout_flags.contains(LayoutFlags::POINT_PRIMITIVE);
+            let particle_normal = batches.layout_flags.contains(LayoutFlags::PARTICLE_NORMAL);
+            let particle_index = batches.layout_flags.contains(LayoutFlags::PARTICLE_INDEX);
+            let blend_min = batches.layout_flags.contains(LayoutFlags::BLEND_MIN);
+            let blend_max = batches.layout_flags.contains(LayoutFlags::BLEND_MAX);
 
             // Specialize the render pipeline based on the effect batch
             trace!(
@@ -2477,10 +3191,19 @@ fn emit_draw<T, F>(
                 ParticleRenderPipelineKey {
                     shader: render_shader_source.clone(),
                     particle_layout: batches.particle_layout.clone(),
+                    property_layout: batches.property_layout.clone(),
                     has_image,
+                    has_dissolve_texture,
+                    has_refraction_texture,
                     local_space_simulation,
                     use_alpha_mask,
                     flipbook,
+                    point_primitive,
+                    particle_normal,
+                    particle_index,
+                    blend_min,
+                    blend_max,
+                    blend_state: batches.blend_state,
                     #[cfg(all(feature = "2d", feature = "3d"))]
                     pipeline_mode,
                     msaa_samples,
@@ -2546,19 +3269,12 @@ pub(crate) fn queue_effects(
 
     // If an image has changed, the GpuImage has (probably) changed
     for event in &events.images {
-        match event {
-            AssetEvent::Added { .. } => None,
-            AssetEvent::LoadedWithDependencies { .. } => None,
-            AssetEvent::Unused { .. } => None,
-            AssetEvent::Modified { id } => {
-                trace!("Destroy bind group of modified image asset {:?}", id);
-                effect_bind_groups.images.remove(id)
-            }
-            AssetEvent::Removed { id } => {
-                trace!("Destroy bind group of removed image asset {:?}", id);
-                effect_bind_groups.images.remove(id)
-            }
-        };
+        if let AssetEvent::Modified { id } | AssetEvent::Removed { id } = event {
+            trace!("Destroy material bind groups of changed image asset {:?}", id);
+            effect_bind_groups
+                .materials
+                .retain(|material, _| !material.0.contains(&Some(*id)));
+        }
     }
 
     if effects_meta.spawner_buffer.buffer().is_none() || effects_meta.spawner_buffer.is_empty() {
@@ -2588,20 +3304,33 @@ pub(crate) fn queue_effects(
     assert!(
         effects_meta.spawner_buffer.aligned_size() >= GpuSpawnerParams::min_size().get() as usize
     );
-    effects_meta.spawner_bind_group = Some(render_device.create_bind_group(
-        "hanabi:bind_group_spawner_buffer",
-        &read_params.update_pipeline.spawner_buffer_layout, // FIXME - Shared with init,is that OK?
-        &[BindGroupEntry {
-            binding: 0,
-            resource: BindingResource::Buffer(BufferBinding {
-                buffer: effects_meta.spawner_buffer.buffer().unwrap(),
-                offset: 0,
-                size: Some(
-                    NonZeroU64::new(effects_meta.spawner_buffer.aligned_size() as u64).unwrap(),
-                ),
-            }),
-        }],
-    ));
+    effects_meta.spawner_bind_group = Some(
+        render_device.create_bind_group(
+            "hanabi:bind_group_spawner_buffer",
+            &read_params.update_pipeline.spawner_buffer_layout, // FIXME - Shared with init,is that OK?
+            &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::Buffer(BufferBinding {
+                        buffer: effects_meta.spawner_buffer.buffer().unwrap(),
+                        offset: 0,
+                        size: Some(
+                            NonZeroU64::new(effects_meta.spawner_buffer.aligned_size() as u64)
+                                .unwrap(),
+                        ),
+                    }),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: effects_meta
+                        .injected_state_buffer
+                        .buffer()
+                        .unwrap()
+                        .as_entire_binding(),
+                },
+            ],
+        ),
+    );
 
     // Create the bind group for the indirect dispatch of all effects
     effects_meta.dr_indirect_bind_group = Some(
@@ -2797,6 +3526,12 @@ pub(crate) fn queue_effects(
                         }),
                     });
                 }
+                if let Some(property_binding) = buffer.properties_max_binding() {
+                    entries.push(BindGroupEntry {
+                        binding: 4,
+                        resource: property_binding,
+                    });
+                }
                 trace!("Creating render bind group with {} entries (layout flags: {:?})", entries.len(), buffer.layout_flags());
                 let render = render_device.create_bind_group(
                     &format!("hanabi:bind_group_render_vfx{buffer_index}_particles")[..],
@@ -2924,35 +3659,64 @@ pub(crate) fn queue_effects(
             ..
         } = effect_batches.dispatch_buffer_indices;
 
-        let particles_buffer_layout_update_render_indirect = render_device.create_bind_group(
-            "hanabi:bind_group_update_render_group_dispatch",
-            &read_params.update_pipeline.render_indirect_layout,
-            &[
-                BindGroupEntry {
-                    binding: 0,
-                    resource: BindingResource::Buffer(BufferBinding {
-                        buffer: effects_meta.render_effect_dispatch_buffer.buffer().unwrap(),
-                        offset: effects_meta
-                            .gpu_limits
-                            .render_effect_indirect_offset(render_effect_dispatch_buffer_index.0),
-                        size: Some(GpuRenderEffectMetadata::min_size()),
-                    }),
-                },
-                BindGroupEntry {
-                    binding: 1,
-                    resource: BindingResource::Buffer(BufferBinding {
-                        buffer: effects_meta.render_group_dispatch_buffer.buffer().unwrap(),
-                        offset: effects_meta.gpu_limits.render_group_indirect_offset(
-                            first_render_group_dispatch_buffer_index.0,
-                        ),
-                        size: NonZeroU64::new(
-                            u64::from(GpuRenderGroupIndirect::min_size())
-                                * effect_batches.group_batches.len() as u64,
-                        ),
-                    }),
-                },
-            ],
-        );
+        let mut render_indirect_bindings = vec![
+            BindGroupEntry {
+                binding: 0,
+                resource: BindingResource::Buffer(BufferBinding {
+                    buffer: effects_meta.render_effect_dispatch_buffer.buffer().unwrap(),
+                    offset: effects_meta
+                        .gpu_limits
+                        .render_effect_indirect_offset(render_effect_dispatch_buffer_index.0),
+                    size: Some(GpuRenderEffectMetadata::min_size()),
+                }),
+            },
+            BindGroupEntry {
+                binding: 1,
+                resource: BindingResource::Buffer(BufferBinding {
+                    buffer: effects_meta.render_group_dispatch_buffer.buffer().unwrap(),
+                    offset: effects_meta
+                        .gpu_limits
+                        .render_group_indirect_offset(first_render_group_dispatch_buffer_index.0),
+                    size: NonZeroU64::new(
+                        u64::from(GpuRenderGroupIndirect::min_size())
+                            * effect_batches.group_batches.len() as u64,
+                    ),
+                }),
+            },
+        ];
+
+        // The heightmap texture sampled by the update pass, if any, is bound after the
+        // render indirect buffers. If it's not yet available as a GPU resource, no
+        // bind group can be created, and the update is skipped.
+        let particles_buffer_layout_update_render_indirect =
+            match &effect_batches.heightfield_image_handle {
+                Some(heightfield_image_handle) => {
+                    gpu_images.get(heightfield_image_handle).map(|gpu_image| {
+                        render_indirect_bindings.extend([
+                            BindGroupEntry {
+                                binding: 2,
+                                resource: BindingResource::TextureView(&gpu_image.texture_view),
+                            },
+                            BindGroupEntry {
+                                binding: 3,
+                                resource: BindingResource::Sampler(&gpu_image.sampler),
+                            },
+                        ]);
+                        render_device.create_bind_group(
+                            "hanabi:bind_group_update_render_group_dispatch_heightfield",
+                            &read_params
+                                .update_pipeline
+                                .render_indirect_heightfield_layout,
+                            &render_indirect_bindings,
+                        )
+                    })
+                }
+                None => Some(render_device.create_bind_group(
+                    "hanabi:bind_group_update_render_group_dispatch",
+                    &read_params.update_pipeline.render_indirect_layout,
+                    &render_indirect_bindings,
+                )),
+            };
 
         effect_bind_groups
             .init_particle_buffer_bind_groups
@@ -2960,12 +3724,20 @@ pub(crate) fn queue_effects(
         effect_bind_groups
             .update_particle_buffer_bind_groups
             .insert(effect_cache_id, update);
-        effect_bind_groups
-            .update_render_indirect_bind_groups
-            .insert(
-                effect_cache_id,
-                particles_buffer_layout_update_render_indirect,
+        if let Some(bind_group) = particles_buffer_layout_update_render_indirect {
+            effect_bind_groups
+                .update_render_indirect_bind_groups
+                .insert(effect_cache_id, bind_group);
+        } else {
+            trace!(
+                "Heightmap texture {:?} of entity {:?} not ready yet.",
+                effect_batches.heightfield_image_handle,
+                entity
             );
+            effect_bind_groups
+                .update_render_indirect_bind_groups
+                .remove(&effect_cache_id);
+        }
     }
 
     // Loop over all 2D cameras/views that need to render effects
@@ -3206,24 +3978,18 @@ fn draw<'w>(
         dyn_uniform_indices,
     );
 
-    // Particle texture
-    if effect_batches
-        .layout_flags
-        .contains(LayoutFlags::PARTICLE_TEXTURE)
-    {
-        if let Some(bind_group) = effect_bind_groups
-            .images
-            .get(&effect_batches.image_handle.id())
-        {
-            pass.set_bind_group(2, bind_group, &[]);
-        } else {
+    // Textures, all in a single material bind group following the particle buffers
+    let material = MaterialKey::from_batches(effect_batches);
+    if !material.is_empty() {
+        let Some(bind_group) = effect_bind_groups.materials.get(&material) else {
             // Texture not ready; skip this drawing for now
             trace!(
-                "Particle texture bind group not available for batch buf={}. Skipping draw call.",
+                "Material bind group not available for batch buf={}. Skipping draw call.",
                 effect_batches.buffer_index,
             );
-            return; // continue;
-        }
+            return;
+        };
+        pass.set_bind_group(2, bind_group, &[]);
     }
 
     let render_indirect_buffer = effects_meta.render_group_dispatch_buffer.buffer().unwrap();
@@ -3438,14 +4204,10 @@ impl Node for VfxSimulateNode {
                     };
 
                     // Do not dispatch any init work if there's nothing to spawn this frame
-                    let spawn_count = batches.spawn_count;
-                    if spawn_count == 0 {
+                    if batches.spawn_count == 0 && batches.group_spawns.is_empty() {
                         continue;
                     }
 
-                    const WORKGROUP_SIZE: u32 = 64;
-                    let workgroup_count = (spawn_count + WORKGROUP_SIZE - 1) / WORKGROUP_SIZE;
-
                     let effect_cache_id = batches.effect_cache_id;
 
                     // for (effect_entity, effect_slice) in effects_meta.entity_map.iter() {
@@ -3469,62 +4231,80 @@ impl Node for VfxSimulateNode {
                         continue;
                     };
 
-                    let spawner_base = batches.spawner_base;
-
                     let spawner_buffer_aligned = effects_meta.spawner_buffer.aligned_size();
                     assert!(spawner_buffer_aligned >= GpuSpawnerParams::min_size().get() as usize);
-                    let spawner_offset = spawner_base * spawner_buffer_aligned as u32;
 
                     let render_effect_indirect_offset = effects_meta
                         .gpu_limits
                         .render_effect_indirect_offset(render_effect_dispatch_buffer_index.0);
 
-                    let first_render_group_indirect_offset = effects_meta
-                        .gpu_limits
-                        .render_group_indirect_offset(first_render_group_dispatch_buffer_index.0);
+                    // Spawn into group #0 with the spawner entry of the effect, then into
+                    // any other group targeted by some spawner, each with its own entry.
+                    let group_spawns = iter::once(GroupSpawn {
+                        group_index: 0,
+                        spawner_index: batches.spawner_base,
+                        spawn_count: batches.spawn_count,
+                    })
+                    .chain(batches.group_spawns.iter().copied())
+                    .filter(|group_spawn| group_spawn.spawn_count > 0);
+                    for group_spawn in group_spawns {
+                        const WORKGROUP_SIZE: u32 = 64;
+                        let workgroup_count =
+                            (group_spawn.spawn_count + WORKGROUP_SIZE - 1) / WORKGROUP_SIZE;
+
+                        let spawner_offset =
+                            group_spawn.spawner_index * spawner_buffer_aligned as u32;
+
+                        let render_group_indirect_offset =
+                            effects_meta.gpu_limits.render_group_indirect_offset(
+                                first_render_group_dispatch_buffer_index.0
+                                    + group_spawn.group_index,
+                            );
 
-                    trace!(
-                        "record commands for init pipeline of effect {:?} \
-                            (spawn {} = {} workgroups) spawner_base={} \
-                            spawner_offset={} \
-                            render_effect_indirect_offset={} \
-                            first_render_group_indirect_offset={}...",
-                        batches.handle,
-                        spawn_count,
-                        workgroup_count,
-                        spawner_base,
-                        spawner_offset,
-                        render_effect_indirect_offset,
-                        first_render_group_indirect_offset,
-                    );
+                        trace!(
+                            "record commands for init pipeline of effect {:?} group #{} \
+                                (spawn {} = {} workgroups) spawner_index={} \
+                                spawner_offset={} \
+                                render_effect_indirect_offset={} \
+                                render_group_indirect_offset={}...",
+                            batches.handle,
+                            group_spawn.group_index,
+                            group_spawn.spawn_count,
+                            workgroup_count,
+                            group_spawn.spawner_index,
+                            spawner_offset,
+                            render_effect_indirect_offset,
+                            render_group_indirect_offset,
+                        );
 
-                    // Setup compute pass
-                    // compute_pass.set_pipeline(&effect_group.init_pipeline);
-                    compute_pass.set_pipeline(init_pipeline);
-                    compute_pass.set_bind_group(
-                        0,
-                        effects_meta.sim_params_bind_group.as_ref().unwrap(),
-                        &[],
-                    );
-                    compute_pass.set_bind_group(1, particles_init_bind_group, &[]);
-                    compute_pass.set_bind_group(
-                        2,
-                        effects_meta.spawner_bind_group.as_ref().unwrap(),
-                        &[spawner_offset],
-                    );
-                    compute_pass.set_bind_group(
-                        3,
-                        effects_meta
-                            .init_render_indirect_bind_group
-                            .as_ref()
-                            .unwrap(),
-                        &[
-                            render_effect_indirect_offset as u32,
-                            first_render_group_indirect_offset as u32,
-                        ],
-                    );
-                    compute_pass.dispatch_workgroups(workgroup_count, 1, 1);
-                    trace!("init compute dispatched");
+                        // Setup compute pass
+                        // compute_pass.set_pipeline(&effect_group.init_pipeline);
+                        compute_pass.set_pipeline(init_pipeline);
+                        compute_pass.set_bind_group(
+                            0,
+                            effects_meta.sim_params_bind_group.as_ref().unwrap(),
+                            &[],
+                        );
+                        compute_pass.set_bind_group(1, particles_init_bind_group, &[]);
+                        compute_pass.set_bind_group(
+                            2,
+                            effects_meta.spawner_bind_group.as_ref().unwrap(),
+                            &[spawner_offset],
+                        );
+                        compute_pass.set_bind_group(
+                            3,
+                            effects_meta
+                                .init_render_indirect_bind_group
+                                .as_ref()
+                                .unwrap(),
+                            &[
+                                render_effect_indirect_offset as u32,
+                                render_group_indirect_offset as u32,
+                            ],
+                        );
+                        compute_pass.dispatch_workgroups(workgroup_count, 1, 1);
+                        trace!("init compute dispatched");
+                    }
                 }
             }
         }
@@ -3612,6 +4392,15 @@ impl Node for VfxSimulateNode {
                     .update_render_indirect_bind_groups
                     .get(&effect_cache_id)
                 else {
+                    // The bind group also contains the heightmap texture, if any, so can't be
+                    // created until that texture is available.
+                    if batches.heightfield_image_handle.is_some() {
+                        trace!(
+                            "Heightmap texture of effect {:?} not ready; update skipped.",
+                            entity
+                        );
+                        continue;
+                    }
                     error!(
                         "Failed to find update render indirect bind group for effect cache ID: \
                         {:?}, IDs present: {:?}",
@@ -3692,6 +4481,42 @@ impl Node for VfxSimulateNode {
 
                     trace!("update compute dispatched");
                 }
+
+                // Compact the particles of each group, once all groups are updated since the
+                // update of a group can clone particles into another group.
+                for (group_index, compact_pipeline_id) in
+                    batches.compact_pipeline_ids.iter().enumerate()
+                {
+                    let Some(compact_pipeline) =
+                        pipeline_cache.get_compute_pipeline(*compact_pipeline_id)
+                    else {
+                        trace!(
+                            "Compaction pipeline for effect {:?}, group {} not ready; skipped.",
+                            entity,
+                            group_index
+                        );
+                        continue;
+                    };
+
+                    compute_pass.set_pipeline(compact_pipeline);
+                    compute_pass.set_bind_group(
+                        0,
+                        effects_meta.sim_params_bind_group.as_ref().unwrap(),
+                        &[],
+                    );
+                    compute_pass.set_bind_group(1, particles_update_bind_group, &[]);
+                    compute_pass.set_bind_group(
+                        2,
+                        effects_meta.spawner_bind_group.as_ref().unwrap(),
+                        &[spawner_base * spawner_buffer_aligned as u32],
+                    );
+                    compute_pass.set_bind_group(3, update_render_indirect_bind_group, &[]);
+
+                    // The compaction is serial, with a single thread per group.
+                    compute_pass.dispatch_workgroups(1, 1, 1);
+
+                    trace!("compaction compute dispatched");
+                }
             }
         }
 
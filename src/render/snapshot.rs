@@ -0,0 +1,482 @@
+use std::sync::mpsc::{channel, Receiver, TryRecvError};
+
+use bevy::{
+    ecs::entity::Entity,
+    log::{trace, warn},
+    render::{
+        render_resource::{
+            Buffer, BufferAsyncError, BufferDescriptor, BufferUsages, CommandEncoderDescriptor,
+            MapMode,
+        },
+        renderer::{RenderDevice, RenderQueue},
+    },
+};
+use bytemuck::{cast_slice, offset_of};
+
+use super::{EffectsMeta, GpuRenderEffectMetadata, GpuRenderGroupIndirect};
+use crate::ParticleLayout;
+
+/// Snapshot of the entire GPU particle state of a single effect instance.
+///
+/// A snapshot contains the raw content of the particle buffer for all the
+/// particle groups of the effect, as well as the indirection indices and
+/// the per-group particle counters needed to restore the exact same set of
+/// alive particles. The particle data is stored as raw bytes, and can be
+/// interpreted with the [`ParticleLayout`] of the effect. Each particle
+/// occupies [`ParticleLayout::min_binding_size()`] bytes, and the attributes
+/// inside a particle are laid out as in the WGSL struct returned by
+/// [`ParticleLayout::generate_code()`].
+///
+/// Snapshots are obtained with [`EffectsMeta::snapshot()`], and restored with
+/// [`EffectsMeta::restore()`]. See those methods for details.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EffectSnapshot {
+    /// Layout of a single particle in [`particles`].
+    ///
+    /// [`particles`]: EffectSnapshot::particles
+    pub particle_layout: ParticleLayout,
+    /// Boundaries of the slices of the particle groups in the particle buffer,
+    /// in number of particles. This contains one more element than the number
+    /// of groups. The first element is the index of the first particle of the
+    /// effect in its particle buffer.
+    pub slices: Vec<u32>,
+    /// Raw particle data for all particle slots of the effect, alive or dead.
+    pub particles: Vec<u8>,
+    /// Indirection indices for all particle slots of the effect. This
+    /// contains 3 indices per slot: the ping-pong alive particle indices, and
+    /// the dead particle index. Indices are absolute indices into the particle
+    /// buffer of the effect.
+    pub indices: Vec<u32>,
+    /// Index (0 or 1) of the ping-pong buffer currently storing the indices of
+    /// the alive particles.
+    pub ping: u32,
+    /// Number of alive particles in each group.
+    pub alive_counts: Vec<u32>,
+    /// Number of dead particles in each group.
+    pub dead_counts: Vec<u32>,
+}
+
+impl EffectSnapshot {
+    /// Number of particle groups in the snapshot.
+    pub fn group_count(&self) -> u32 {
+        self.slices.len().saturating_sub(1) as u32
+    }
+
+    /// Total number of particle slots of the effect, for all groups.
+    pub fn capacity(&self) -> u32 {
+        match (self.slices.first(), self.slices.last()) {
+            (Some(first), Some(last)) => last - first,
+            _ => 0,
+        }
+    }
+
+    /// Get the raw data of the alive particles of a group.
+    ///
+    /// This returns an iterator over the bytes of each alive particle of the
+    /// given group, in the order the particles are stored in the alive list.
+    /// The iterator is empty if the group index is out of bounds.
+    pub fn alive_particles(&self, group_index: u32) -> impl Iterator<Item = &[u8]> + '_ {
+        let stride = self.particle_layout.min_binding_size().get() as usize;
+        let base = self.slices.first().copied().unwrap_or(0);
+        let (start, count) = if (group_index as usize) < self.alive_counts.len() {
+            (
+                self.slices[group_index as usize] - base,
+                self.alive_counts[group_index as usize],
+            )
+        } else {
+            (0, 0)
+        };
+        (start..start + count).map(move |slot| {
+            let index = self.indices[slot as usize * 3 + self.ping as usize] - base;
+            let offset = index as usize * stride;
+            &self.particles[offset..offset + stride]
+        })
+    }
+}
+
+/// Pending asynchronous read back of an [`EffectSnapshot`].
+///
+/// This is returned by [`EffectsMeta::snapshot()`]. The GPU copies the
+/// particle data into a staging buffer, which is then mapped for reading on
+/// CPU once the GPU is done. Call [`try_take()`] once per frame until it
+/// returns a value; the data is typically available one or two frames after
+/// the request was made.
+///
+/// [`try_take()`]: EffectSnapshotRequest::try_take
+#[derive(Debug)]
+pub struct EffectSnapshotRequest {
+    staging_buffer: Buffer,
+    receiver: Receiver<Result<(), BufferAsyncError>>,
+    particle_layout: ParticleLayout,
+    slices: Vec<u32>,
+    particles_size: u64,
+    indices_size: u64,
+    effect_metadata_size: u64,
+    group_indirect_size: u64,
+}
+
+impl EffectSnapshotRequest {
+    /// Try to take the snapshot once the read back completed.
+    ///
+    /// Returns `None` if the data is not available yet. Once this returned
+    /// `Some`, any further call returns `None`. If the GPU failed to map the
+    /// staging buffer, the request is abandoned and this always returns
+    /// `None`.
+    ///
+    /// This never blocks. The GPU device is polled by Bevy each frame, so
+    /// simply calling this method each frame is enough to eventually obtain
+    /// the snapshot.
+    pub fn try_take(&mut self) -> Option<EffectSnapshot> {
+        match self.receiver.try_recv() {
+            Ok(Ok(())) => {}
+            Ok(Err(err)) => {
+                warn!("Failed to read back effect snapshot: {:?}", err);
+                return None;
+            }
+            Err(TryRecvError::Empty) | Err(TryRecvError::Disconnected) => return None,
+        }
+
+        let group_count = self.slices.len() - 1;
+        let snapshot = {
+            let view = self.staging_buffer.slice(..).get_mapped_range();
+            let particles_end = self.particles_size as usize;
+            let indices_end = particles_end + self.indices_size as usize;
+            let metadata_end = indices_end + self.effect_metadata_size as usize;
+
+            let particles = view[..particles_end].to_vec();
+            let indices = cast_slice::<u8, u32>(&view[particles_end..indices_end]).to_vec();
+
+            let ping_offset = indices_end + offset_of!(GpuRenderEffectMetadata, ping);
+            let ping = read_u32(&view, ping_offset);
+
+            let mut alive_counts = Vec::with_capacity(group_count);
+            let mut dead_counts = Vec::with_capacity(group_count);
+            for group_index in 0..group_count {
+                let row = metadata_end + group_index * self.group_indirect_size as usize;
+                alive_counts.push(read_u32(
+                    &view,
+                    row + offset_of!(GpuRenderGroupIndirect, alive_count),
+                ));
+                dead_counts.push(read_u32(
+                    &view,
+                    row + offset_of!(GpuRenderGroupIndirect, dead_count),
+                ));
+            }
+
+            EffectSnapshot {
+                particle_layout: self.particle_layout.clone(),
+                slices: std::mem::take(&mut self.slices),
+                particles,
+                indices,
+                ping,
+                alive_counts,
+                dead_counts,
+            }
+        };
+        self.staging_buffer.unmap();
+        Some(snapshot)
+    }
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_ne_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+impl EffectsMeta {
+    /// Request a snapshot of the entire GPU particle state of an effect
+    /// instance.
+    ///
+    /// The `entity` is the entity the [`ParticleEffect`] component is
+    /// attached to. This returns `None` if the effect instance was not
+    /// allocated yet.
+    ///
+    /// # Performance
+    ///
+    /// This is a heavyweight operation. It allocates a staging buffer as large
+    /// as the entire particle storage of the effect, submits a GPU copy of that
+    /// storage, and maps the staging buffer for reading on CPU. The copy
+    /// stalls any GPU work writing to the same buffers. Avoid taking snapshots
+    /// every frame; this is intended for occasional save/load and replay.
+    ///
+    /// # Asynchronous read back
+    ///
+    /// The read back is asynchronous. The snapshot captures the state of the
+    /// particles at the time the copy is executed on GPU, which is before any
+    /// simulation work of the current frame, since this is expected to be
+    /// called from a render world system before the render graph runs. The
+    /// data becomes available on CPU later; use
+    /// [`EffectSnapshotRequest::try_take()`] to poll it.
+    ///
+    /// This resource is only available in the render world.
+    ///
+    /// [`ParticleEffect`]: crate::ParticleEffect
+    pub fn snapshot(
+        &self,
+        entity: Entity,
+        render_device: &RenderDevice,
+        render_queue: &RenderQueue,
+    ) -> Option<EffectSnapshotRequest> {
+        let id = *self.entity_map.get(&entity)?;
+        let buffer = self.effect_cache.get_buffer(id)?;
+        let slices = self.effect_cache.get_slices(id);
+        let dispatch_buffer_indices = self.effect_cache.get_dispatch_buffer_indices(id);
+        let effect_metadata_buffer = self.render_effect_dispatch_buffer.buffer()?;
+        let group_indirect_buffer = self.render_group_dispatch_buffer.buffer()?;
+
+        let first = *slices.slices.first()? as u64;
+        let last = *slices.slices.last()? as u64;
+        let group_count = slices.slices.len() as u64 - 1;
+        let stride = slices.particle_layout.min_binding_size().get();
+
+        let particles_size = (last - first) * stride;
+        let indices_size = (last - first) * 12;
+        let effect_metadata_size = self.gpu_limits.render_effect_indirect_offset(1);
+        let group_indirect_size = self.gpu_limits.render_group_indirect_offset(1);
+        let total_size = particles_size
+            + indices_size
+            + effect_metadata_size
+            + group_indirect_size * group_count;
+
+        trace!(
+            "Snapshot effect {:?}: {} particles of {}B in {} groups, {}B total",
+            entity,
+            last - first,
+            stride,
+            group_count,
+            total_size
+        );
+
+        let staging_buffer = render_device.create_buffer(&BufferDescriptor {
+            label: Some("hanabi:buffer:effect_snapshot"),
+            size: total_size,
+            usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = render_device.create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("hanabi:effect_snapshot"),
+        });
+        let mut dst_offset = 0;
+        encoder.copy_buffer_to_buffer(
+            buffer.particle_buffer(),
+            first * stride,
+            &staging_buffer,
+            dst_offset,
+            particles_size,
+        );
+        dst_offset += particles_size;
+        encoder.copy_buffer_to_buffer(
+            buffer.indirect_buffer(),
+            first * 12,
+            &staging_buffer,
+            dst_offset,
+            indices_size,
+        );
+        dst_offset += indices_size;
+        encoder.copy_buffer_to_buffer(
+            effect_metadata_buffer,
+            self.gpu_limits.render_effect_indirect_offset(
+                dispatch_buffer_indices
+                    .render_effect_metadata_buffer_index
+                    .0,
+            ),
+            &staging_buffer,
+            dst_offset,
+            effect_metadata_size,
+        );
+        dst_offset += effect_metadata_size;
+        encoder.copy_buffer_to_buffer(
+            group_indirect_buffer,
+            self.gpu_limits.render_group_indirect_offset(
+                dispatch_buffer_indices
+                    .first_render_group_dispatch_buffer_index
+                    .0,
+            ),
+            &staging_buffer,
+            dst_offset,
+            group_indirect_size * group_count,
+        );
+        render_queue.submit([encoder.finish()]);
+
+        let (sender, receiver) = channel();
+        staging_buffer
+            .slice(..)
+            .map_async(MapMode::Read, move |result| {
+                // The request may have been dropped already, ignore errors.
+                let _ = sender.send(result);
+            });
+
+        Some(EffectSnapshotRequest {
+            staging_buffer,
+            receiver,
+            particle_layout: slices.particle_layout,
+            slices: slices.slices,
+            particles_size,
+            indices_size,
+            effect_metadata_size,
+            group_indirect_size,
+        })
+    }
+
+    /// Restore the entire GPU particle state of an effect instance from a
+    /// snapshot.
+    ///
+    /// The `entity` is the entity the [`ParticleEffect`] component is
+    /// attached to. The snapshot must have been taken from an effect instance
+    /// with the same [`ParticleLayout`] and the same slices, which generally
+    /// means the same [`EffectAsset`] instantiated in the same order. This
+    /// returns `false` without modifying anything if the effect instance was
+    /// not allocated yet, or if the snapshot is not compatible with it.
+    ///
+    /// The upload is queued on the render queue and executes before any GPU
+    /// work submitted afterwards, so calling this from a render world system
+    /// before the render graph runs restores the particles in time for the
+    /// simulation of the current frame. The upload costs as much bandwidth as
+    /// the size of the snapshot.
+    ///
+    /// Restoring the particles doesn't restore the CPU state of the
+    /// [`EffectSpawner`]; use [`EffectSpawner::state()`] and
+    /// [`EffectSpawner::set_state()`] for that.
+    ///
+    /// This resource is only available in the render world.
+    ///
+    /// [`ParticleEffect`]: crate::ParticleEffect
+    /// [`EffectAsset`]: crate::EffectAsset
+    /// [`EffectSpawner`]: crate::EffectSpawner
+    /// [`EffectSpawner::state()`]: crate::EffectSpawner::state
+    /// [`EffectSpawner::set_state()`]: crate::EffectSpawner::set_state
+    pub fn restore(
+        &self,
+        entity: Entity,
+        snapshot: &EffectSnapshot,
+        render_queue: &RenderQueue,
+    ) -> bool {
+        let Some(id) = self.entity_map.get(&entity).copied() else {
+            return false;
+        };
+        let Some(buffer) = self.effect_cache.get_buffer(id) else {
+            return false;
+        };
+        let (Some(effect_metadata_buffer), Some(group_indirect_buffer)) = (
+            self.render_effect_dispatch_buffer.buffer(),
+            self.render_group_dispatch_buffer.buffer(),
+        ) else {
+            return false;
+        };
+        let slices = self.effect_cache.get_slices(id);
+        let group_count = snapshot.group_count() as usize;
+        let stride = slices.particle_layout.min_binding_size().get();
+        if slices.particle_layout != snapshot.particle_layout
+            || slices.slices != snapshot.slices
+            || snapshot.particles.len() as u64 != snapshot.capacity() as u64 * stride
+            || snapshot.indices.len() != snapshot.capacity() as usize * 3
+            || snapshot.alive_counts.len() != group_count
+            || snapshot.dead_counts.len() != group_count
+            || snapshot.ping > 1
+        {
+            warn!(
+                "Cannot restore snapshot into effect {:?}: incompatible particle layout or slices.",
+                entity
+            );
+            return false;
+        }
+
+        let first = snapshot.slices[0] as u64;
+        render_queue.write_buffer(
+            buffer.particle_buffer(),
+            first * stride,
+            &snapshot.particles,
+        );
+        render_queue.write_buffer(
+            buffer.indirect_buffer(),
+            first * 12,
+            cast_slice(&snapshot.indices[..]),
+        );
+
+        let dispatch_buffer_indices = self.effect_cache.get_dispatch_buffer_indices(id);
+        // max_spawn and ping are contiguous. The init pass spawns into the first
+        // group, so max_spawn is the number of dead particles of that group.
+        let effect_metadata = [
+            snapshot.dead_counts.first().copied().unwrap_or(0),
+            snapshot.ping,
+        ];
+        render_queue.write_buffer(
+            effect_metadata_buffer,
+            self.gpu_limits.render_effect_indirect_offset(
+                dispatch_buffer_indices
+                    .render_effect_metadata_buffer_index
+                    .0,
+            ) + offset_of!(GpuRenderEffectMetadata, max_spawn) as u64,
+            cast_slice(&effect_metadata[..]),
+        );
+
+        let first_group = dispatch_buffer_indices
+            .first_render_group_dispatch_buffer_index
+            .0;
+        for group_index in 0..group_count {
+            let row = self
+                .gpu_limits
+                .render_group_indirect_offset(first_group + group_index as u32);
+            // alive_count, max_update, dead_count are contiguous
+            let counts = [
+                snapshot.alive_counts[group_index],
+                snapshot.alive_counts[group_index],
+                snapshot.dead_counts[group_index],
+            ];
+            render_queue.write_buffer(
+                group_indirect_buffer,
+                row + offset_of!(GpuRenderGroupIndirect, alive_count) as u64,
+                cast_slice(&counts[..]),
+            );
+        }
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Attribute;
+
+    #[test]
+    fn alive_particles() {
+        // Single attribute per particle; only the first byte is used as a marker
+        let particle_layout = ParticleLayout::new().append(Attribute::AGE).build();
+        let stride = particle_layout.min_binding_size().get() as usize;
+
+        // Effect at offset 4 in its buffer, with 2 groups of 3 and 2 particles
+        let slices = vec![4, 7, 9];
+        let mut particles = vec![0u8; 5 * stride];
+        for (i, p) in particles.chunks_mut(stride).enumerate() {
+            p[0] = i as u8;
+        }
+        #[rustfmt::skip]
+        let indices = vec![
+            // group #0
+            0, 6, 0,
+            0, 4, 0,
+            0, 0, 0,
+            // group #1
+            0, 8, 0,
+            0, 0, 0,
+        ];
+        let snapshot = EffectSnapshot {
+            particle_layout,
+            slices,
+            particles,
+            indices,
+            ping: 1,
+            alive_counts: vec![2, 1],
+            dead_counts: vec![1, 1],
+        };
+        assert_eq!(snapshot.group_count(), 2);
+        assert_eq!(snapshot.capacity(), 5);
+
+        let group0: Vec<u8> = snapshot.alive_particles(0).map(|p| p[0]).collect();
+        assert_eq!(group0, vec![2, 0]);
+        let group1: Vec<u8> = snapshot.alive_particles(1).map(|p| p[0]).collect();
+        assert_eq!(group1, vec![4]);
+        assert_eq!(snapshot.alive_particles(2).count(), 0);
+    }
+}
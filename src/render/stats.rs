@@ -0,0 +1,352 @@
+use std::sync::{Arc, Mutex};
+
+use bevy::{
+    ecs::{
+        component::Component,
+        entity::Entity,
+        query::With,
+        reflect::ReflectComponent,
+        system::{Query, Res, ResMut, Resource},
+    },
+    log::{trace, warn},
+    reflect::Reflect,
+    render::{
+        render_resource::{
+            Buffer, BufferAsyncError, BufferDescriptor, BufferUsages, CommandEncoderDescriptor,
+            MapMode,
+        },
+        renderer::{RenderDevice, RenderQueue},
+    },
+    utils::{HashMap, HashSet},
+};
+use bytemuck::offset_of;
+
+use super::{EffectsMeta, GpuRenderGroupIndirect};
+use crate::{EffectSpawner, ParticleEffect};
+
+/// Particle statistics of an effect instance.
+///
+/// Reading back the particle counts from GPU has a cost each frame, so this is
+/// opt-in: insert this component on the entities with a [`ParticleEffect`]
+/// whose statistics you need. No read back takes place if no effect has this
+/// component.
+///
+/// The component is updated each frame during the [`PostUpdate`] schedule, as
+/// part of the [`EffectSystems::UpdateEffectStats`] set. Fields are only
+/// written when their value actually changes, so systems can use Bevy's change
+/// detection (`Changed<EffectStats>`) to react to changes, for example to
+/// trigger a sound once all the particles of a burst died.
+///
+/// # Example
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_hanabi::*;
+/// fn spawn_effect(mut commands: Commands, effect: Res<MyEffect>) {
+///     commands.spawn((
+///         ParticleEffectBundle::new(effect.0.clone()),
+///         EffectStats::default(),
+///     ));
+/// }
+/// # #[derive(Resource)]
+/// # struct MyEffect(Handle<EffectAsset>);
+/// ```
+///
+/// # Frame latency
+///
+/// The number of alive particles is read back from GPU, where the particles
+/// are simulated. The read back is asynchronous to avoid stalling the GPU, so
+/// [`alive`] lags behind the actual simulation, typically by two to three
+/// frames depending on the GPU and whether pipelined rendering is enabled.
+/// Conversely, [`spawned_this_frame`] is known on CPU and is always up to date.
+///
+/// [`PostUpdate`]: bevy::app::PostUpdate
+/// [`EffectSystems::UpdateEffectStats`]: crate::EffectSystems::UpdateEffectStats
+/// [`alive`]: EffectStats::alive
+/// [`spawned_this_frame`]: EffectStats::spawned_this_frame
+#[derive(Debug, Default, Clone, PartialEq, Eq, Component, Reflect)]
+#[reflect(Component)]
+pub struct EffectStats {
+    /// Number of alive particles in each group of the effect, as of the last
+    /// completed GPU read back.
+    ///
+    /// This is empty until the first read back completes.
+    pub alive: Vec<u32>,
+    /// Number of particles the spawners of the effect emitted this frame, for
+    /// all groups combined.
+    pub spawned_this_frame: u32,
+}
+
+impl EffectStats {
+    /// Total number of alive particles, for all groups of the effect.
+    pub fn total_alive(&self) -> u32 {
+        self.alive.iter().sum()
+    }
+}
+
+/// State of the [`EffectStats`] shared between the main and render worlds.
+#[derive(Default)]
+struct EffectStatsShared {
+    /// Effect instances with an [`EffectStats`] component, which are read back.
+    requested: HashSet<Entity>,
+    /// Per-group alive counts read back from GPU, waiting to be consumed by the
+    /// main world.
+    readbacks: Vec<(Entity, Vec<u32>)>,
+}
+
+/// Channel between the main and render worlds for the [`EffectStats`].
+///
+/// This resource is shared between the main and render worlds.
+#[derive(Default, Clone, Resource)]
+pub(crate) struct EffectStatsChannel(Arc<Mutex<EffectStatsShared>>);
+
+/// Range of rows of the group indirect buffer belonging to an effect.
+struct EffectRows {
+    entity: Entity,
+    first_row: u32,
+    group_count: u32,
+}
+
+/// In-flight read back of the group indirect buffer.
+struct PendingReadback {
+    staging_buffer: Buffer,
+    status: Arc<Mutex<Option<Result<(), BufferAsyncError>>>>,
+    row_size: u64,
+    effects: Vec<EffectRows>,
+}
+
+/// Render world state of the read back of the [`EffectStats`].
+#[derive(Default, Resource)]
+pub(crate) struct EffectStatsReadback {
+    channel: EffectStatsChannel,
+    pending: Vec<PendingReadback>,
+    /// Unmapped staging buffers available for reuse.
+    free_buffers: Vec<Buffer>,
+}
+
+impl EffectStatsReadback {
+    /// Maximum number of read backs in flight. If the GPU is too slow to map
+    /// the staging buffers, new read backs are skipped until one completes.
+    const MAX_PENDING: usize = 3;
+
+    pub fn new(channel: EffectStatsChannel) -> Self {
+        Self {
+            channel,
+            ..Default::default()
+        }
+    }
+}
+
+/// Collect the completed read backs of the alive particle counts, and queue a
+/// new one for all allocated effects with an [`EffectStats`] component.
+///
+/// This system runs in the render world during [`RenderSet::Cleanup`], after
+/// the render graph submitted the simulation work of the frame, so the copy
+/// captures the particle counts at the end of the frame.
+///
+/// [`RenderSet::Cleanup`]: bevy::render::RenderSet::Cleanup
+pub(crate) fn readback_effect_stats(
+    effects_meta: Res<EffectsMeta>,
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+    mut readback: ResMut<EffectStatsReadback>,
+) {
+    let readback = &mut *readback;
+
+    // Collect completed read backs, in submission order
+    while let Some(pending) = readback.pending.first() {
+        let Some(status) = pending.status.lock().unwrap().take() else {
+            break;
+        };
+        let pending = readback.pending.remove(0);
+        if let Err(err) = status {
+            warn!("Failed to read back effect stats: {:?}", err);
+            continue;
+        }
+        {
+            let view = pending.staging_buffer.slice(..).get_mapped_range();
+            let mut shared = readback.channel.0.lock().unwrap();
+            for rows in &pending.effects {
+                let alive = (rows.first_row..rows.first_row + rows.group_count)
+                    .map(|row| {
+                        let offset = row as usize * pending.row_size as usize
+                            + offset_of!(GpuRenderGroupIndirect, alive_count);
+                        u32::from_ne_bytes(view[offset..offset + 4].try_into().unwrap())
+                    })
+                    .collect();
+                shared.readbacks.push((rows.entity, alive));
+            }
+        }
+        pending.staging_buffer.unmap();
+        readback.free_buffers.push(pending.staging_buffer);
+    }
+
+    if readback.pending.len() >= EffectStatsReadback::MAX_PENDING {
+        trace!("Skipping effect stats read back; too many pending read backs.");
+        return;
+    }
+    let Some(group_indirect_buffer) = effects_meta.render_group_dispatch_buffer.buffer() else {
+        return;
+    };
+
+    let effects: Vec<_> = {
+        let shared = readback.channel.0.lock().unwrap();
+        effects_meta
+            .entity_map
+            .iter()
+            .filter(|(entity, _)| shared.requested.contains(*entity))
+            .map(|(entity, id)| {
+                let slices = effects_meta.effect_cache.get_slices(*id);
+                let dispatch_buffer_indices =
+                    effects_meta.effect_cache.get_dispatch_buffer_indices(*id);
+                EffectRows {
+                    entity: *entity,
+                    first_row: dispatch_buffer_indices
+                        .first_render_group_dispatch_buffer_index
+                        .0,
+                    group_count: slices.slices.len().saturating_sub(1) as u32,
+                }
+            })
+            .collect()
+    };
+    if effects.is_empty() {
+        return;
+    }
+
+    // Copy the entire buffer; it only contains a few counters per group, and is
+    // therefore much smaller than any particle data.
+    let size = group_indirect_buffer.size();
+    let staging_buffer = match readback
+        .free_buffers
+        .iter()
+        .position(|buffer| buffer.size() == size)
+    {
+        Some(index) => readback.free_buffers.swap_remove(index),
+        None => {
+            // Drop any staging buffer left from before the buffer was reallocated.
+            readback.free_buffers.retain(|buffer| buffer.size() == size);
+            render_device.create_buffer(&BufferDescriptor {
+                label: Some("hanabi:buffer:effect_stats_readback"),
+                size,
+                usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+                mapped_at_creation: false,
+            })
+        }
+    };
+
+    let mut encoder = render_device.create_command_encoder(&CommandEncoderDescriptor {
+        label: Some("hanabi:effect_stats_readback"),
+    });
+    encoder.copy_buffer_to_buffer(group_indirect_buffer, 0, &staging_buffer, 0, size);
+    render_queue.submit([encoder.finish()]);
+
+    let status = Arc::new(Mutex::new(None));
+    let callback_status = status.clone();
+    staging_buffer
+        .slice(..)
+        .map_async(MapMode::Read, move |result| {
+            *callback_status.lock().unwrap() = Some(result);
+        });
+
+    readback.pending.push(PendingReadback {
+        staging_buffer,
+        status,
+        row_size: effects_meta.gpu_limits.render_group_indirect_offset(1),
+        effects,
+    });
+}
+
+/// Update the [`EffectStats`] of all effect instances having one.
+///
+/// This updates the alive counts from the latest completed GPU read back, if
+/// any, and records which effect instances need a read back.
+///
+/// This system runs during the [`PostUpdate`] schedule, as part of the
+/// [`EffectSystems::UpdateEffectStats`] set.
+///
+/// [`PostUpdate`]: bevy::app::PostUpdate
+/// [`EffectSystems::UpdateEffectStats`]: crate::EffectSystems::UpdateEffectStats
+pub(crate) fn update_effect_stats(
+    channel: Res<EffectStatsChannel>,
+    mut query: Query<(Entity, Option<&EffectSpawner>, &mut EffectStats), With<ParticleEffect>>,
+) {
+    let mut shared = channel.0.lock().unwrap();
+
+    // Only keep the latest read back of each effect
+    let mut readbacks: HashMap<Entity, Vec<u32>> = shared.readbacks.drain(..).collect();
+
+    shared.requested.clear();
+    for (entity, spawner, mut stats) in query.iter_mut() {
+        shared.requested.insert(entity);
+
+        // Only write on change, to keep change detection meaningful
+        let spawned_this_frame = spawner.map(|s| s.spawn_count()).unwrap_or(0);
+        if stats.spawned_this_frame != spawned_this_frame {
+            stats.spawned_this_frame = spawned_this_frame;
+        }
+        if let Some(alive) = readbacks.remove(&entity) {
+            if stats.alive != alive {
+                stats.alive = alive;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn update_stats() {
+        let mut app = App::new();
+        let channel = EffectStatsChannel::default();
+        app.insert_resource(channel.clone())
+            .add_systems(Update, update_effect_stats);
+
+        let entity = app
+            .world
+            .spawn((ParticleEffect::default(), EffectStats::default()))
+            .id();
+        let other = app.world.spawn(ParticleEffect::default()).id();
+        let push_readback = |alive: Vec<u32>| {
+            channel.0.lock().unwrap().readbacks.push((entity, alive));
+        };
+
+        // Only effects with the component are read back
+        app.update();
+        let stats = app.world.get::<EffectStats>(entity).unwrap();
+        assert!(stats.alive.is_empty());
+        assert_eq!(stats.spawned_this_frame, 0);
+        assert!(app.world.get::<EffectStats>(other).is_none());
+        let requested = channel.0.lock().unwrap().requested.clone();
+        assert_eq!(requested, HashSet::from_iter([entity]));
+
+        // Read back completed; the latest one wins
+        push_readback(vec![3, 1]);
+        push_readback(vec![5, 2]);
+        app.update();
+        let stats = app.world.get::<EffectStats>(entity).unwrap();
+        assert_eq!(stats.alive, vec![5, 2]);
+        assert_eq!(stats.total_alive(), 7);
+        assert!(channel.0.lock().unwrap().readbacks.is_empty());
+
+        // Same value doesn't trigger change detection
+        let tick = app
+            .world
+            .entity(entity)
+            .get_ref::<EffectStats>()
+            .unwrap()
+            .last_changed();
+        push_readback(vec![5, 2]);
+        app.update();
+        let stats = app.world.entity(entity).get_ref::<EffectStats>().unwrap();
+        assert_eq!(stats.last_changed(), tick);
+
+        // Removing the component stops the read back
+        app.world.entity_mut(entity).remove::<EffectStats>();
+        app.update();
+        assert!(channel.0.lock().unwrap().requested.is_empty());
+    }
+}
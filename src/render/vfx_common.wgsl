@@ -26,6 +26,8 @@ struct SimParams {
     /// the position of each effect's data into the buffer of a batch.
     dispatch_stride: u32,
 //#endif
+    /// World position of the main camera.
+    camera_position: vec3<f32>,
 }
 
 struct Spawner {
@@ -35,6 +37,16 @@ struct Spawner {
     seed: u32,
     count: atomic<i32>,
     effect_index: u32,
+    // Simulation delta time for this effect, overriding SimParams::delta_time.
+    delta_time: f32,
+    // Offset of the first state injected from CPU this frame, in the array of
+    // injected states shared by all effects.
+    inject_offset: u32,
+    // Number of particles injected from CPU this frame. Those are part of the
+    // spawn count, and use the first spawn slots.
+    inject_count: u32,
+    // Index of the group the init pass spawns particles into.
+    spawn_group: u32,
 #ifdef SPAWNER_PADDING
     {{SPAWNER_PADDING}}
 #endif
@@ -97,6 +109,9 @@ const RGI_OFFSET_BASE_INSTANCE: u32 = 3u;
 const RGI_OFFSET_ALIVE_COUNT: u32 = 4u;
 const RGI_OFFSET_MAX_UPDATE: u32 = 5u;
 const RGI_OFFSET_DEAD_COUNT: u32 = 6u;
+const RGI_OFFSET_RECYCLE_COUNT: u32 = 7u;
+const RGI_OFFSET_FIRST_SPAWN_INDEX: u32 = 8u;
+const RGI_OFFSET_SPAWN_OFFSET: u32 = 9u;
 
 struct RenderEffectMetadata {
     /// Maxmimum number of init threads to run on next frame. This is cached from
@@ -136,7 +151,18 @@ struct RenderGroupIndirect {
     /// Number of dead particles, decremented during the init pass as new particles
     /// are spawned, and incremented during the update pass as existing particles die.
     dead_count: atomic<u32>,
-    pad: u32,
+    /// Number of alive particles overwritten during the update pass because the
+    /// group was full, when recycling alive particles. Reset each frame by the
+    /// indirect dispatch pass.
+    recycle_count: atomic<u32>,
+    /// Index in the alive list of the first particle spawned by the init pass, or
+    /// added by a clone or transfer modifier, this frame, or 0xFFFFFFFF if none.
+    /// Reset each frame by the indirect dispatch pass.
+    first_spawn_index: atomic<u32>,
+    /// Number of particles in the alive list before the ones spawned this frame.
+    /// This is cached from `first_spawn_index` during the indirect dispatch, and
+    /// used by the update pass to draw the newest particles first.
+    spawn_offset: u32,
 }
 
 var<private> seed : u32 = 0u;
@@ -204,6 +230,18 @@ fn rand_uniform(a: f32, b: f32) -> f32 {
     return a + frand() * (b - a);
 }
 
+// Divide a by b, or return 0 if b is 0, instead of an infinite or NaN value.
+fn safe_div(a: f32, b: f32) -> f32 {
+    return select(a / b, 0., b == 0.);
+}
+
+// Normalize a vector, or return the zero vector if it's too short to be
+// normalized without producing an infinite or NaN value.
+fn safe_normalize(v: vec3<f32>) -> vec3<f32> {
+    let len_sq = dot(v, v);
+    return select(vec3<f32>(0.), v * inverseSqrt(len_sq), len_sq > 1e-12);
+}
+
 fn proj(u: vec3<f32>, v: vec3<f32>) -> vec3<f32> {
-    return dot(v, u) / dot(u,u) * u;
+    return safe_div(dot(v, u), dot(u, u)) * u;
 }
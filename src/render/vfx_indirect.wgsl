@@ -1,7 +1,8 @@
 #import bevy_hanabi::vfx_common::{
     ParticleGroup, SimParams, Spawner,
     DI_OFFSET_X, DI_OFFSET_PONG,
-    RGI_OFFSET_ALIVE_COUNT, RGI_OFFSET_MAX_UPDATE, RGI_OFFSET_DEAD_COUNT,
+    RGI_OFFSET_ALIVE_COUNT, RGI_OFFSET_MAX_UPDATE, RGI_OFFSET_DEAD_COUNT, RGI_OFFSET_RECYCLE_COUNT,
+    RGI_OFFSET_FIRST_SPAWN_INDEX, RGI_OFFSET_SPAWN_OFFSET,
     REM_OFFSET_MAX_SPAWN, RGI_OFFSET_INSTANCE_COUNT, REM_OFFSET_PING
 }
 
@@ -40,6 +41,10 @@ fn main(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
     // with the particles actually alive at the end of their update (after aged).
     render_group_indirect_buffer[rgi_base + RGI_OFFSET_INSTANCE_COUNT] = 0u;
 
+    // Clear the number of alive particles recycled, which is incremented by the
+    // update pass when a full group overwrites some of its particles.
+    render_group_indirect_buffer[rgi_base + RGI_OFFSET_RECYCLE_COUNT] = 0u;
+
     // Calculate the number of thread groups to dispatch for the update
     // pass, which is the number of alive particles rounded up to 64
     // (workgroup_size).
@@ -51,6 +56,12 @@ fn main(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
     // atomically modifying alive_count itself for next frame.
     render_group_indirect_buffer[rgi_base + RGI_OFFSET_MAX_UPDATE] = alive_count;
 
+    // Cache the number of particles alive before the init pass spawned new ones this
+    // frame, and reset the index of the first spawned particle for next frame.
+    let first_spawn_index = render_group_indirect_buffer[rgi_base + RGI_OFFSET_FIRST_SPAWN_INDEX];
+    render_group_indirect_buffer[rgi_base + RGI_OFFSET_SPAWN_OFFSET] = min(first_spawn_index, alive_count);
+    render_group_indirect_buffer[rgi_base + RGI_OFFSET_FIRST_SPAWN_INDEX] = 0xFFFFFFFFu;
+
     if (is_first_group) {
         let rem_base = sim_params.render_effect_stride * effect_index / 4u;
 
@@ -1,7 +1,7 @@
 #import bevy_hanabi::vfx_common::{
     IndirectBuffer, ParticleGroup, RenderEffectMetadata, RenderGroupIndirect, SimParams, Spawner,
     seed, tau, pcg_hash, to_float01, frand, frand2, frand3, frand4,
-    rand_uniform, proj
+    rand_uniform, proj, safe_div, safe_normalize
 }
 
 struct Particle {
@@ -12,39 +12,80 @@ struct ParticleBuffer {
     particles: array<Particle>,
 }
 
+// Initial state of a particle injected from CPU.
+struct InjectedState {
+    position: vec3<f32>,
+    // Bit 0: velocity is valid; bit 1: color is valid.
+    flags: u32,
+    velocity: vec3<f32>,
+    color: vec4<f32>,
+}
+
 {{PROPERTIES}}
 
-@group(0) @binding(0) var<uniform> sim_params : SimParams;
+@group(0) @binding(0) var<uniform> sim_params_uniform : SimParams;
 @group(1) @binding(0) var<storage, read_write> particle_buffer : ParticleBuffer;
 @group(1) @binding(1) var<storage, read_write> indirect_buffer : IndirectBuffer;
 @group(1) @binding(2) var<storage, read> particle_groups : array<ParticleGroup>;
 {{PROPERTIES_BINDING}}
 @group(2) @binding(0) var<storage, read_write> spawner : Spawner; // NOTE - same group as update
+@group(2) @binding(1) var<storage, read> injected_states : array<InjectedState>;
 @group(3) @binding(0) var<storage, read_write> render_effect_indirect : RenderEffectMetadata;
 @group(3) @binding(1) var<storage, read_write> render_group_indirect : RenderGroupIndirect;
 
+// Per-effect copy of the simulation parameters, with the delta time overridden
+// by the one of the spawner (which differs e.g. during warmup).
+var<private> sim_params : SimParams;
+
 {{INIT_EXTRA}}
 
 @compute @workgroup_size(64)
 fn main(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
     var index = global_invocation_id.x;
 
+    sim_params = sim_params_uniform;
+    sim_params.delta_time = spawner.delta_time;
+
     // Cap to max number of dead particles, copied from dead_count at the end of the
-    // previous iteration, and constant during this pass (unlike dead_count).
-    if (index >= render_effect_indirect.max_spawn) {
+    // previous iteration, and constant during this pass (unlike dead_count). This is
+    // only available for group #0; other groups check dead_count below.
+    let spawn_group = spawner.spawn_group;
+    if (spawn_group == 0u && index >= render_effect_indirect.max_spawn) {
         return;
     }
 
     // Cap to the actual number of spawning requested by CPU, since compute shaders run
     // in workgroup_size(64) so more threads than needed are launched (rounded up to 64).
-    let spawn_count : u32 = u32(spawner.spawn);
+    // A negative count spawns nothing, instead of wrapping around and underflowing the
+    // dead particle count.
+    let spawn_count : u32 = u32(max(spawner.spawn, 0));
     if (index >= spawn_count) {
         return;
     }
 
-    // Recycle a dead particle from the first group
-    let base_index = particle_groups[0].effect_particle_offset;
-    let dead_index = atomicSub(&render_group_indirect.dead_count, 1u) - 1u;
+    // Recycle a dead particle from the target group
+    var base_index = particle_groups[0].effect_particle_offset;
+    var dead_index = 0u;
+    if (spawn_group == 0u) {
+        dead_index = atomicSub(&render_group_indirect.dead_count, 1u) - 1u;
+    } else {
+        // Reserve a dead particle in the target group, if there's room left. Never
+        // decrement the dead count below zero, so that other threads don't observe
+        // a wrapped-around count.
+        var dead_count = atomicLoad(&render_group_indirect.dead_count);
+        loop {
+            if (dead_count == 0u) {
+                return;
+            }
+            let res = atomicCompareExchangeWeak(&render_group_indirect.dead_count, dead_count, dead_count - 1u);
+            if (res.exchanged) {
+                break;
+            }
+            dead_count = res.old_value;
+        }
+        dead_index = dead_count - 1u;
+        base_index = particle_groups[spawn_group].effect_particle_offset + particle_groups[spawn_group].indirect_index;
+    }
     index = indirect_buffer.indices[3u * (base_index + dead_index) + 2u];
 
     // Update PRNG seed
@@ -75,6 +116,7 @@ fn main(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
     // Add to alive list
     let indirect_index = atomicAdd(&render_group_indirect.instance_count, 1u);
     indirect_buffer.indices[3u * (base_index + indirect_index) + ping] = index;
+    atomicMin(&render_group_indirect.first_spawn_index, indirect_index);
 
     // Write back spawned particle
     particle_buffer.particles[index] = particle;
@@ -2,7 +2,7 @@
 #import bevy_hanabi::vfx_common::{
     DispatchIndirect, IndirectBuffer, SimParams, Spawner,
     seed, tau, pcg_hash, to_float01, frand, frand2, frand3, frand4,
-    rand_uniform, proj
+    rand_uniform, proj, safe_div, safe_normalize
 }
 
 struct Particle {
@@ -13,12 +13,26 @@ struct ParticleBuffer {
     particles: array<Particle>,
 }
 
+{{PROPERTIES}}
+
 struct VertexOutput {
     @builtin(position) position: vec4<f32>,
     @location(0) color: vec4<f32>,
-#ifdef PARTICLE_TEXTURE
+#ifdef PARTICLE_UV
     @location(1) uv: vec2<f32>,
 #endif
+#ifdef DISSOLVE_TEXTURE
+    @location(2) dissolve: f32,
+#endif
+#ifdef PARTICLE_NORMAL
+    @location(3) normal: vec3<f32>,
+    @location(4) tangent: vec3<f32>,
+    @location(5) bitangent: vec3<f32>,
+    @location(6) normal_quad_position: vec2<f32>,
+#endif
+#ifdef PARTICLE_INDEX
+    @location(7) @interpolate(flat) particle_index: u32,
+#endif
 }
 
 @group(0) @binding(0) var<uniform> view: View;
@@ -29,10 +43,20 @@ struct VertexOutput {
 #ifdef RENDER_NEEDS_SPAWNER
 @group(1) @binding(3) var<storage, read> spawner : Spawner; // NOTE - same group as update
 #endif
+{{PROPERTIES_BINDING}}
+// All textures are bound in the material bind group, with fixed bindings
 #ifdef PARTICLE_TEXTURE
 @group(2) @binding(0) var particle_texture: texture_2d<f32>;
 @group(2) @binding(1) var particle_sampler: sampler;
 #endif
+#ifdef DISSOLVE_TEXTURE
+@group(2) @binding(2) var dissolve_texture: texture_2d<f32>;
+@group(2) @binding(3) var dissolve_sampler: sampler;
+#endif
+#ifdef REFRACTION_TEXTURE
+@group(2) @binding(4) var refraction_texture: texture_2d<f32>;
+@group(2) @binding(5) var refraction_sampler: sampler;
+#endif
 // #ifdef PARTICLE_GRADIENTS
 // @group(3) @binding(0) var gradient_texture: texture_2d<f32>;
 // @group(3) @binding(1) var gradient_sampler: sampler;
@@ -95,6 +119,18 @@ fn transform_position_simulation_to_world(sim_position: vec3<f32>) -> vec4<f32>
 #endif
 }
 
+/// Transform a simulation space direction into a world space direction.
+///
+/// The direction is not normalized.
+fn transform_direction_simulation_to_world(sim_direction: vec3<f32>) -> vec3<f32> {
+#ifdef LOCAL_SPACE_SIMULATION
+    let transform = unpack_compressed_transform(spawner.transform);
+    return (transform * vec4<f32>(sim_direction, 0.0)).xyz;
+#else
+    return sim_direction;
+#endif
+}
+
 /// Transform a simulation space position into a clip space position.
 ///
 /// The simulation space depends on the effect's SimulationSpace value, and is either
@@ -105,13 +141,16 @@ fn transform_position_simulation_to_clip(sim_position: vec3<f32>) -> vec4<f32> {
     return view.view_proj * transform_position_simulation_to_world(sim_position);
 }
 
+// Index of the particle group rendered by this shader.
+const group_index : u32 = {{GROUP_INDEX}}u;
+
 {{RENDER_EXTRA}}
 
 @vertex
 fn vertex(
     @builtin(instance_index) instance_index: u32,
     @location(0) vertex_position: vec3<f32>,
-#ifdef PARTICLE_TEXTURE
+#ifdef PARTICLE_UV
     @location(1) vertex_uv: vec2<f32>,
 #endif
     // @location(1) vertex_color: u32,
@@ -119,10 +158,17 @@ fn vertex(
 ) -> VertexOutput {
     let pong = dispatch_indirect.pong;
     let index = indirect_buffer.indices[3u * instance_index + pong];
+    let particle_index = index;
     var particle = particle_buffer.particles[index];
     var out: VertexOutput;
-#ifdef PARTICLE_TEXTURE
+#ifdef PARTICLE_UV
+#ifdef POINT_PRIMITIVE
+    // Points have no UV coordinates; sample the center of the image.
+    var uv = vec2<f32>(0.5);
+#else
     var uv = vertex_uv;
+#endif
+{{UV_CODE}}
 #ifdef FLIPBOOK
     let row_count = {{FLIPBOOK_ROW_COUNT}};
     let ij = vec2<f32>(f32(particle.sprite_index % row_count), f32(particle.sprite_index / row_count));
@@ -135,16 +181,39 @@ fn vertex(
 
 {{VERTEX_MODIFIERS}}
 
+#ifdef POINT_PRIMITIVE
+    // Points are rendered as a single pixel at the particle position, ignoring the
+    // particle size and orientation.
+    let sim_position = particle.position;
+#else
     // Expand particle mesh vertex based on particle position ("origin"), and local
     // orientation and size of the particle mesh (currently: only quad).
     let vpos = vertex_position * vec3<f32>(size.x, size.y, 1.0);
     let sim_position = particle.position
         + axis_x * vpos.x
         + axis_y * vpos.y;
+#endif
     out.position = transform_position_simulation_to_clip(sim_position);
 
     out.color = color;
 
+#ifdef PARTICLE_INDEX
+    out.particle_index = particle_index;
+#endif
+
+#ifdef PARTICLE_NORMAL
+    // World space normal of the particle, and tangent frame to perturb it per fragment
+    out.normal = safe_normalize(transform_direction_simulation_to_world(axis_z));
+    out.tangent = transform_direction_simulation_to_world(axis_x);
+    out.bitangent = transform_direction_simulation_to_world(axis_y);
+#ifdef POINT_PRIMITIVE
+    out.normal_quad_position = vec2<f32>(0.0);
+#else
+    // Position on the quad, in [-1:1]
+    out.normal_quad_position = vertex_position.xy * 2.0;
+#endif
+#endif
+
     return out;
 }
 
@@ -155,15 +224,46 @@ fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
     var alpha_cutoff: f32 = {{ALPHA_CUTOFF}};
 #endif
 
+#ifdef PARTICLE_INDEX
+    let particle_index = in.particle_index;
+#endif
+
+#ifdef PARTICLE_NORMAL
+    // World space normal of the particle at this fragment
+    {{NORMAL_CODE}}
+#endif
+
 {{FRAGMENT_MODIFIERS}}
 
     var color = in.color;
 
 #ifdef PARTICLE_TEXTURE
-    var texColor = textureSample(particle_texture, particle_sampler, in.uv);
+    var texColor = textureSample(particle_texture, particle_sampler, {{PARTICLE_TEXTURE_UV}});
     {{PARTICLE_TEXTURE_SAMPLE_MAPPING}}
 #endif
 
+#ifdef DISSOLVE_TEXTURE
+    {{DISSOLVE_CODE}}
+#endif
+
+#ifdef REFRACTION_TEXTURE
+    // Distortion direction, from the particle normal if any, else from the particle
+    // texture if any, or radially from the center of the particle quad otherwise.
+#ifdef PARTICLE_NORMAL
+    // Project the world space normal onto the view plane. Screen space Y points down.
+    let refraction_normal_view = (view.inverse_view * vec4<f32>(normal, 0.0)).xy;
+    let refraction_normal = vec2<f32>(refraction_normal_view.x, -refraction_normal_view.y);
+#else
+#ifdef PARTICLE_TEXTURE
+    let refraction_normal = texColor.rg * 2. - 1.;
+#else
+    let refraction_normal = in.uv * 2. - 1.;
+#endif
+#endif
+    let refraction_screen_uv = (in.position.xy - view.viewport.xy) / view.viewport.zw;
+    {{REFRACTION_CODE}}
+#endif
+
 #ifdef USE_ALPHA_MASK
     if color.a >= alpha_cutoff {
         color.a = 1.0;
@@ -172,5 +272,15 @@ fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
     }
 #endif
 
+#ifdef BLEND_MIN
+    // Transparent fragments don't lower the destination color
+    color = vec4<f32>(mix(vec3<f32>(1.0), color.rgb, color.a), color.a);
+#endif
+
+#ifdef BLEND_MAX
+    // Transparent fragments don't raise the destination color
+    color = vec4<f32>(color.rgb * color.a, color.a);
+#endif
+
     return color;
 }
\ No newline at end of file
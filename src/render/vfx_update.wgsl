@@ -1,7 +1,7 @@
 #import bevy_hanabi::vfx_common::{
     IndirectBuffer, ParticleGroup, RenderEffectMetadata, RenderGroupIndirect, SimParams, Spawner,
     seed, tau, pcg_hash, to_float01, frand, frand2, frand3, frand4,
-    rand_uniform, proj
+    rand_uniform, proj, safe_div, safe_normalize
 }
 
 struct Particle {
@@ -14,7 +14,7 @@ struct ParticleBuffer {
 
 {{PROPERTIES}}
 
-@group(0) @binding(0) var<uniform> sim_params : SimParams;
+@group(0) @binding(0) var<uniform> sim_params_uniform : SimParams;
 @group(1) @binding(0) var<storage, read_write> particle_buffer : ParticleBuffer;
 @group(1) @binding(1) var<storage, read_write> indirect_buffer : IndirectBuffer;
 @group(1) @binding(2) var<storage, read> particle_groups : array<ParticleGroup>;
@@ -22,6 +22,17 @@ struct ParticleBuffer {
 @group(2) @binding(0) var<storage, read_write> spawner : Spawner; // NOTE - same group as init
 @group(3) @binding(0) var<storage, read_write> render_effect_indirect : RenderEffectMetadata;
 @group(3) @binding(1) var<storage, read_write> render_group_indirect : array<RenderGroupIndirect>;
+#ifdef HEIGHTFIELD_TEXTURE
+@group(3) @binding(2) var heightfield_texture: texture_2d<f32>;
+@group(3) @binding(3) var heightfield_sampler: sampler;
+#endif
+
+// Per-effect copy of the simulation parameters, with the delta time overridden
+// by the one of the spawner (which differs e.g. during warmup).
+var<private> sim_params : SimParams;
+
+// Index of the particle group updated by this shader.
+const group_index : u32 = {{GROUP_INDEX}}u;
 
 {{UPDATE_EXTRA}}
 
@@ -29,6 +40,9 @@ struct ParticleBuffer {
 fn main(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
     let thread_index = global_invocation_id.x;
 
+    sim_params = sim_params_uniform;
+    sim_params.delta_time = spawner.delta_time;
+
     // Cap at maximum number of particles.
     // FIXME - This is probably useless given below cap
     let max_particles : u32 = particle_groups[{{GROUP_INDEX}}].capacity;
@@ -47,8 +61,10 @@ fn main(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
 
     let effect_particle_offset = particle_groups[{{GROUP_INDEX}}].effect_particle_offset;
     let base_index = effect_particle_offset + particle_groups[{{GROUP_INDEX}}].indirect_index;
-    let index = indirect_buffer.indices[3u * (base_index + thread_index) + pong];
+    {{DRAW_ORDER_CODE}}
+    let index = indirect_buffer.indices[3u * (base_index + read_index) + pong];
 
+    let particle_index = index;
     var particle: Particle = particle_buffer.particles[index];
 
     {{AGE_CODE}}
@@ -72,3 +88,62 @@ fn main(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
         indirect_buffer.indices[3u * (base_index + indirect_index) + ping] = index;
     }
 }
+
+/// Compact the particles of the group to the front of the group's slice of the
+/// particle buffer.
+///
+/// This runs on a single thread once all groups are updated, and moves each alive
+/// particle stored past the first `instance_count` slots of the group into a dead
+/// slot located before that limit. Both the alive indices (ping) used for rendering
+/// and the dead indices recycled by the init pass are rewritten accordingly.
+@compute @workgroup_size(1)
+fn compact(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
+    if (global_invocation_id.x != 0u) {
+        return;
+    }
+
+    // The update pass wrote the alive indices into ping
+    let ping = render_effect_indirect.ping;
+
+    let effect_particle_offset = particle_groups[{{GROUP_INDEX}}].effect_particle_offset;
+    let base_index = effect_particle_offset + particle_groups[{{GROUP_INDEX}}].indirect_index;
+    let alive_count = atomicLoad(&render_group_indirect[{{GROUP_INDEX}}].instance_count);
+    let dead_count = atomicLoad(&render_group_indirect[{{GROUP_INDEX}}].dead_count);
+
+    // Particles never change group, so the slots of the group form a contiguous
+    // range. Find its start.
+    var first_slot = 0xFFFFFFFFu;
+    for (var i = 0u; i < alive_count; i += 1u) {
+        first_slot = min(first_slot, indirect_buffer.indices[3u * (base_index + i) + ping]);
+    }
+    for (var i = 0u; i < dead_count; i += 1u) {
+        first_slot = min(first_slot, indirect_buffer.indices[3u * (base_index + i) + 2u]);
+    }
+    let end_slot = first_slot + alive_count;
+
+    // Pair each alive particle past the end with a dead slot before it. There are
+    // exactly as many of each.
+    var dead_index = 0u;
+    for (var i = 0u; i < alive_count; i += 1u) {
+        let src = indirect_buffer.indices[3u * (base_index + i) + ping];
+        if (src < end_slot) {
+            continue;
+        }
+        loop {
+            if (dead_index >= dead_count
+                || indirect_buffer.indices[3u * (base_index + dead_index) + 2u] < end_slot) {
+                break;
+            }
+            dead_index += 1u;
+        }
+        if (dead_index >= dead_count) {
+            // Should not happen; bail out, leaving the group in a valid state.
+            return;
+        }
+        let dst = indirect_buffer.indices[3u * (base_index + dead_index) + 2u];
+        particle_buffer.particles[dst] = particle_buffer.particles[src];
+        indirect_buffer.indices[3u * (base_index + i) + ping] = dst;
+        indirect_buffer.indices[3u * (base_index + dead_index) + 2u] = src;
+        dead_index += 1u;
+    }
+}
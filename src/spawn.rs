@@ -3,12 +3,12 @@ use std::hash::{Hash, Hasher};
 use bevy::{ecs::system::Resource, prelude::*, reflect::Reflect, utils::FloatOrd};
 use rand::{
     distributions::{uniform::SampleUniform, Distribution, Uniform},
-    SeedableRng,
+    Rng, SeedableRng,
 };
 use rand_pcg::Pcg32;
 use serde::{Deserialize, Serialize};
 
-use crate::{EffectAsset, EffectSimulation, ParticleEffect, SimulationCondition};
+use crate::{EffectAsset, EffectInstance, EffectSimulation, ParticleEffect, SimulationCondition};
 
 /// An RNG to be used in the CPU for the particle system engine
 pub(crate) fn new_rng() -> Pcg32 {
@@ -19,6 +19,13 @@ pub(crate) fn new_rng() -> Pcg32 {
 }
 
 /// An RNG resource
+///
+/// The [`HanabiPlugin`] inserts one instance in the main world, used by the
+/// spawners, and another one in the render world, used to seed the GPU
+/// simulation each frame. Replacing both with seeded instances makes the
+/// simulation deterministic, for example for tests.
+///
+/// [`HanabiPlugin`]: crate::HanabiPlugin
 #[derive(Resource)]
 pub struct Random(pub Pcg32);
 
@@ -61,7 +68,20 @@ impl FloatHash for Vec4 {
 /// A constant or random value evaluated on CPU.
 ///
 /// This enum represents a value which is either constant, or randomly sampled
-/// according to a given probability distribution.
+/// according to a given probability distribution. Use [`CpuValue::constant()`]
+/// and [`CpuValue::uniform()`] to create a value, or convert a constant value
+/// with `into()`.
+///
+/// In serialized form (for example RON), a constant value is written as
+/// `Single(x)` and a uniform random value as `Uniform((min, max))`.
+///
+/// # Example
+///
+/// ```
+/// # use bevy_hanabi::*;
+/// // Spawn between 10 and 20 particles (inclusive) every 2 seconds.
+/// let spawner = Spawner::burst(CpuValue::uniform(10., 20.), CpuValue::constant(2.));
+/// ```
 ///
 /// Not to be confused with [`graph::Value`]. This [`CpuValue`] is a legacy type
 /// that will be eventually replaced with a [`graph::Value`] once evaluation of
@@ -83,6 +103,35 @@ pub enum CpuValue<T: Copy + FromReflect> {
     Uniform((T, T)),
 }
 
+impl<T: Copy + FromReflect> CpuValue<T> {
+    /// Create a constant value.
+    ///
+    /// This is equivalent to [`CpuValue::Single`].
+    pub fn constant(value: T) -> Self {
+        Self::Single(value)
+    }
+
+    /// Create a random value distributed uniformly in the `[min:max]` range.
+    ///
+    /// Both bounds are inclusive, so both `min` and `max` can be sampled. The
+    /// minimum bound must be less than or equal to the maximum one, otherwise
+    /// [`sample()`] will panic. This is equivalent to [`CpuValue::Uniform`].
+    ///
+    /// [`sample()`]: crate::CpuValue::sample
+    pub fn uniform(min: T, max: T) -> Self {
+        Self::Uniform((min, max))
+    }
+
+    /// Check if the value is constant.
+    ///
+    /// Note that a [`CpuValue::Uniform`] value with equal bounds is not
+    /// considered constant by this method, even though it always samples to
+    /// the same value.
+    pub fn is_constant(&self) -> bool {
+        matches!(self, Self::Single(_))
+    }
+}
+
 impl<T: Copy + FromReflect + Default> Default for CpuValue<T> {
     fn default() -> Self {
         Self::Single(T::default())
@@ -93,8 +142,12 @@ impl<T: Copy + FromReflect + SampleUniform> CpuValue<T> {
     /// Sample the value.
     /// - For [`CpuValue::Single`], always return the same single value.
     /// - For [`CpuValue::Uniform`], use the given pseudo-random number
-    ///   generator to generate a random sample.
-    pub fn sample(&self, rng: &mut Pcg32) -> T {
+    ///   generator to generate a random sample, uniformly distributed between
+    ///   the two inclusive bounds.
+    ///
+    /// This can be used to preview on CPU the values sampled by the
+    /// [`Spawner`], with any random number generator.
+    pub fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> T {
         match self {
             Self::Single(x) => *x,
             Self::Uniform((a, b)) => Uniform::new_inclusive(*a, *b).sample(rng),
@@ -178,6 +231,25 @@ pub struct Spawner {
     /// spawner becomes active. If `false`, the spawner doesn't do anything
     /// until [`EffectSpawner::reset()`] is called.
     starts_immediately: bool,
+
+    /// Duration, in seconds, over which the spawn rate linearly ramps down to
+    /// zero at the end of the spawn time. Zero disables the ramp.
+    #[serde(default)]
+    fade_out: f32,
+
+    /// Duration, in seconds, over which the spawn rate linearly ramps up from
+    /// zero each time the spawner is activated. Zero disables the ramp.
+    #[serde(default)]
+    ramp_up: f32,
+
+    /// Duration, in seconds, after the spawner is deactivated during which it
+    /// can't emit again. Zero disables the cooldown.
+    #[serde(default)]
+    cooldown: f32,
+
+    /// Index of the group the spawner emits particles into. Defaults to `0`.
+    #[serde(default)]
+    group: u32,
 }
 
 impl Default for Spawner {
@@ -197,8 +269,9 @@ impl Spawner {
     /// The control parameters are:
     ///
     /// - `count` is the number of particles to spawn over `time` in a burst. It
-    ///   can generate negative or zero random values, in which case no particle
-    ///   is spawned during the current frame.
+    ///   is sampled once at the start of each period. It can generate negative
+    ///   or zero random values, in which case no particle is spawned during the
+    ///   current period.
     /// - `time` is how long to spawn particles for. If this is <= 0, then the
     ///   particles spawn all at once exactly at the same instant.
     /// - `period` is the amount of time between bursts of particles. If this is
@@ -246,6 +319,10 @@ impl Spawner {
             period,
             starts_active: true,
             starts_immediately: true,
+            fade_out: 0.,
+            ramp_up: 0.,
+            cooldown: 0.,
+            group: 0,
         }
     }
 
@@ -358,6 +435,248 @@ impl Spawner {
     pub fn starts_active(&self) -> bool {
         self.starts_active
     }
+
+    /// Set the duration over which the emission ramps down at the end of the
+    /// spawn time.
+    ///
+    /// Over the last `fade_out` seconds of each spawn time window, the number
+    /// of particles spawned is linearly scaled down from its nominal value to
+    /// zero, instead of abruptly stopping when the spawn time elapses. This
+    /// reduces the total number of particles emitted over the window. The
+    /// value is clamped to the spawn time; a value of zero (the default)
+    /// disables the ramp.
+    ///
+    /// This has no effect on spawners emitting all particles at once, like
+    /// [`once()`] and [`burst()`], since their spawn time is zero.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::Spawner;
+    /// // Spawn 100 particles over 5 seconds, ramping down over the last 2
+    /// // seconds, then wait until the 10 second period elapses.
+    /// let spawner = Spawner::new(100.0.into(), 5.0.into(), 10.0.into()).with_fade_out(2.);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `fade_out` is negative.
+    ///
+    /// [`once()`]: crate::Spawner::once
+    /// [`burst()`]: crate::Spawner::burst
+    pub fn with_fade_out(mut self, fade_out: f32) -> Self {
+        self.set_fade_out(fade_out);
+        self
+    }
+
+    /// Set the duration over which the emission ramps down at the end of the
+    /// spawn time.
+    ///
+    /// See [`with_fade_out()`] for details.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `fade_out` is negative.
+    ///
+    /// [`with_fade_out()`]: crate::Spawner::with_fade_out
+    pub fn set_fade_out(&mut self, fade_out: f32) {
+        assert!(
+            fade_out >= 0.,
+            "`fade_out` must not be negative (was {}).",
+            fade_out
+        );
+        self.fade_out = fade_out;
+    }
+
+    /// Get the duration over which the emission ramps down at the end of the
+    /// spawn time.
+    ///
+    /// See [`with_fade_out()`] for details.
+    ///
+    /// [`with_fade_out()`]: crate::Spawner::with_fade_out
+    pub fn fade_out(&self) -> f32 {
+        self.fade_out
+    }
+
+    /// Set the duration over which the emission ramps up when the spawner is
+    /// activated.
+    ///
+    /// Over the first `ramp_up` seconds after the [`EffectSpawner`] is
+    /// activated, including when it starts active, the number of particles
+    /// spawned is linearly scaled up from zero to its nominal value, instead of
+    /// abruptly starting at full rate. This gives a more natural start to
+    /// continuous emissions, like a flamethrower. A value of zero (the
+    /// default) disables the ramp.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::Spawner;
+    /// // Spawn 100 particles per second, reaching that rate 0.5 second after
+    /// // the spawner is activated.
+    /// let spawner = Spawner::rate(100.0.into()).with_ramp_up(0.5);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `ramp_up` is negative.
+    pub fn with_ramp_up(mut self, ramp_up: f32) -> Self {
+        self.set_ramp_up(ramp_up);
+        self
+    }
+
+    /// Set the duration over which the emission ramps up when the spawner is
+    /// activated.
+    ///
+    /// See [`with_ramp_up()`] for details.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `ramp_up` is negative.
+    ///
+    /// [`with_ramp_up()`]: crate::Spawner::with_ramp_up
+    pub fn set_ramp_up(&mut self, ramp_up: f32) {
+        assert!(
+            ramp_up >= 0.,
+            "`ramp_up` must not be negative (was {}).",
+            ramp_up
+        );
+        self.ramp_up = ramp_up;
+    }
+
+    /// Get the duration over which the emission ramps up when the spawner is
+    /// activated.
+    ///
+    /// See [`with_ramp_up()`] for details.
+    ///
+    /// [`with_ramp_up()`]: crate::Spawner::with_ramp_up
+    pub fn ramp_up(&self) -> f32 {
+        self.ramp_up
+    }
+
+    /// Set the duration after the spawner is deactivated during which it can't
+    /// emit again.
+    ///
+    /// Once an active [`EffectSpawner`] is deactivated, it doesn't spawn any
+    /// particle for `cooldown` seconds, even if activated again in the
+    /// meantime. This is useful for weapons and other repeated emissions which
+    /// shouldn't fire again instantaneously. Use [`EffectSpawner::trigger()`]
+    /// to only activate the spawner once its cooldown elapsed. Any
+    /// [`ramp_up()`] only starts once the cooldown elapsed. A value of zero
+    /// (the default) disables the cooldown.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::Spawner;
+    /// // Fire a burst of 30 particles, then wait at least 2 seconds before
+    /// // the next one can be fired.
+    /// let spawner = Spawner::once(30.0.into(), false).with_cooldown(2.);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `cooldown` is negative.
+    ///
+    /// [`ramp_up()`]: crate::Spawner::ramp_up
+    pub fn with_cooldown(mut self, cooldown: f32) -> Self {
+        self.set_cooldown(cooldown);
+        self
+    }
+
+    /// Set the duration after the spawner is deactivated during which it can't
+    /// emit again.
+    ///
+    /// See [`with_cooldown()`] for details.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `cooldown` is negative.
+    ///
+    /// [`with_cooldown()`]: crate::Spawner::with_cooldown
+    pub fn set_cooldown(&mut self, cooldown: f32) {
+        assert!(
+            cooldown >= 0.,
+            "`cooldown` must not be negative (was {}).",
+            cooldown
+        );
+        self.cooldown = cooldown;
+    }
+
+    /// Get the duration after the spawner is deactivated during which it can't
+    /// emit again.
+    ///
+    /// See [`with_cooldown()`] for details.
+    ///
+    /// [`with_cooldown()`]: crate::Spawner::with_cooldown
+    pub fn cooldown(&self) -> f32 {
+        self.cooldown
+    }
+
+    /// Set the index of the group the spawner emits particles into.
+    ///
+    /// By default spawners emit into group #0. Emitting directly into another
+    /// group allows several independent emissions to share a single effect,
+    /// without having to clone particles from group #0 with a
+    /// [`CloneModifier`]. The init modifiers of the effect apply to all
+    /// spawned particles, whichever group they're spawned into.
+    ///
+    /// Unlike group #0, other groups don't reserve their dead particles ahead
+    /// of the init pass, so particles in excess of the group capacity are
+    /// simply not spawned. A spawner emitting into a group which doesn't exist
+    /// doesn't spawn anything; see [`EffectAsset::validate()`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy_hanabi::Spawner;
+    /// // Spawn 5 particles per second into group #1
+    /// let spawner = Spawner::rate(5.0.into()).with_group(1);
+    /// assert_eq!(spawner.group(), 1);
+    /// ```
+    ///
+    /// [`CloneModifier`]: crate::modifier::CloneModifier
+    /// [`EffectAsset::validate()`]: crate::EffectAsset::validate
+    pub fn with_group(mut self, group: u32) -> Self {
+        self.group = group;
+        self
+    }
+
+    /// Get the index of the group the spawner emits particles into.
+    pub fn group(&self) -> u32 {
+        self.group
+    }
+}
+
+/// Integral over `[0:t]` of the spawn rate scale for a spawn window of duration
+/// `spawn_time` ramping down linearly to zero over its last `fade_out` seconds.
+///
+/// The scale is 1 before the ramp starts, so this returns `t` when the ramp is
+/// disabled.
+fn fade_out_integral(t: f32, spawn_time: f32, fade_out: f32) -> f32 {
+    let fade_out = fade_out.min(spawn_time);
+    let fade_start = spawn_time - fade_out;
+    if fade_out <= 0. || t <= fade_start {
+        t
+    } else {
+        let remain = (spawn_time - t).max(0.);
+        fade_start + fade_out.mul_add(fade_out, -remain * remain) / (2. * fade_out)
+    }
+}
+
+/// Integral over `[0:t]` of the spawn rate scale ramping up linearly from zero
+/// over the first `ramp_up` seconds.
+///
+/// The scale is 1 after the ramp ends, so this returns `t` when the ramp is
+/// disabled.
+fn ramp_up_integral(t: f32, ramp_up: f32) -> f32 {
+    if ramp_up <= 0. {
+        t
+    } else if t < ramp_up {
+        t * t / (2. * ramp_up)
+    } else {
+        t - ramp_up / 2.
+    }
 }
 
 /// Runtime component maintaining the state of the spawner for an effect.
@@ -367,7 +686,7 @@ impl Spawner {
 /// already present on the entity. The spawer configuration is derived from the
 /// [`ParticleEffect`] itself, or as fallback from the underlying
 /// [`EffectAsset`] associated with the particle effect instance.
-#[derive(Default, Clone, Copy, PartialEq, Component)]
+#[derive(Default, Clone, PartialEq, Component)]
 pub struct EffectSpawner {
     /// The spawner configuration extracted either from the [`EffectAsset`], or
     /// from any overriden value provided by the user on the [`ParticleEffect`].
@@ -379,6 +698,9 @@ pub struct EffectSpawner {
     /// Sampled value of `spawn_time` until `limit` is reached.
     curr_spawn_time: f32,
 
+    /// Sampled value of `num_particles` until `limit` is reached.
+    curr_num_particles: f32,
+
     /// Time limit until next spawn.
     limit: f32,
 
@@ -387,11 +709,70 @@ pub struct EffectSpawner {
     /// [`tick()`]: crate::EffectSpawner::tick
     spawn_count: u32,
 
+    /// Number of particles to spawn by the main spawner only, as calculated
+    /// by last [`tick()`] call. This differs from [`spawn_count`] only if the
+    /// effect has additional spawners.
+    ///
+    /// [`tick()`]: crate::EffectSpawner::tick
+    /// [`spawn_count`]: crate::EffectSpawner::spawn_count
+    main_spawn_count: u32,
+
     /// Fractional remainder of particle count to spawn.
     spawn_remainder: f32,
 
     /// Whether the system is active. Defaults to `true`.
     active: bool,
+
+    /// Time elapsed since the spawner was last activated and any cooldown
+    /// elapsed, in seconds, for the ramp up of the emission.
+    active_time: f32,
+
+    /// Remaining time until the spawner can emit again after it was
+    /// deactivated, in seconds.
+    cooldown_remaining: f32,
+
+    /// Time elapsed since the last frame any particle was spawned, in seconds.
+    idle_time: f32,
+
+    /// Remaining warmup time to pre-simulate, in seconds.
+    warmup: f32,
+
+    /// Simulation delta time of the last [`tick()`] call, including any
+    /// warmup step.
+    ///
+    /// [`tick()`]: crate::EffectSpawner::tick
+    delta_time: f32,
+
+    /// Scale applied to the number of particles spawned. Defaults to `1.0`.
+    spawn_scale: f32,
+
+    /// Scale of the emitter applied to the number of particles spawned, if
+    /// the effect enables [`EffectAsset::emitter_scaling`]. Defaults to `1.0`.
+    emitter_scale: f32,
+
+    /// Number of particles requested with [`spawn_now()`], to be spawned on
+    /// the next [`tick()`].
+    ///
+    /// [`spawn_now()`]: crate::EffectSpawner::spawn_now
+    /// [`tick()`]: crate::EffectSpawner::tick
+    pending_spawn_now: Option<u32>,
+
+    /// Number of particles requested with [`spawn_now()`] and spawned this
+    /// frame, overriding the spawners, if any.
+    ///
+    /// [`spawn_now()`]: crate::EffectSpawner::spawn_now
+    spawn_now_count: Option<u32>,
+
+    /// Capacity of group #0, which [`spawn_now()`] spawns into.
+    ///
+    /// [`spawn_now()`]: crate::EffectSpawner::spawn_now
+    capacity: u32,
+
+    /// Runtime state of the additional spawners of the effect, if any. See
+    /// [`EffectAsset::with_spawner()`].
+    ///
+    /// [`EffectAsset::with_spawner()`]: crate::EffectAsset::with_spawner
+    extra: Vec<EffectSpawner>,
 }
 
 impl EffectSpawner {
@@ -401,6 +782,19 @@ impl EffectSpawner {
     /// override. Otherwise it's cloned from the asset.
     pub fn new(asset: &EffectAsset, instance: &ParticleEffect) -> Self {
         let spawner = *instance.spawner.as_ref().unwrap_or(&asset.spawner);
+        let warmup = asset.warmup_duration();
+        let mut effect_spawner = Self::from_spawner(spawner, warmup);
+        effect_spawner.capacity = asset.capacities().first().copied().unwrap_or(0);
+        effect_spawner.extra = asset
+            .extra_spawners
+            .iter()
+            .map(|spawner| Self::from_spawner(*spawner, warmup))
+            .collect();
+        effect_spawner
+    }
+
+    /// Create a new spawner state for a single spawner configuration.
+    fn from_spawner(spawner: Spawner, warmup: f32) -> Self {
         Self {
             spawner,
             time: if spawner.is_once() && !spawner.starts_immediately {
@@ -409,26 +803,105 @@ impl EffectSpawner {
                 0.
             },
             curr_spawn_time: 0.,
+            curr_num_particles: 0.,
             limit: 0.,
             spawn_count: 0,
+            main_spawn_count: 0,
             spawn_remainder: 0.,
             active: spawner.starts_active(),
+            active_time: 0.,
+            cooldown_remaining: 0.,
+            idle_time: 0.,
+            warmup,
+            delta_time: 0.,
+            spawn_scale: 1.,
+            emitter_scale: 1.,
+            pending_spawn_now: None,
+            spawn_now_count: None,
+            capacity: u32::MAX,
+            extra: vec![],
         }
     }
 
+    /// Maximum warmup time consumed per frame, in seconds.
+    ///
+    /// The warmup of an effect (see [`EffectAsset::with_warmup()`]) is spread
+    /// over several frames to cap its cost. Each frame, at most this amount
+    /// of the remaining warmup time is added to the frame's delta time.
+    /// Larger steps would reduce the number of frames the effect is hidden,
+    /// at the expense of simulation accuracy.
+    ///
+    /// [`EffectAsset::with_warmup()`]: crate::EffectAsset::with_warmup
+    pub const MAX_WARMUP_STEP: f32 = 0.1;
+
     /// Set whether the spawner is active.
     ///
     /// Inactive spawners do not spawn any particle.
     pub fn with_active(mut self, active: bool) -> Self {
-        self.active = active;
+        self.set_active(active);
         self
     }
 
     /// Set whether the spawner is active.
     ///
     /// Inactive spawners do not spawn any particle.
+    ///
+    /// Activating an inactive spawner restarts the [`Spawner::ramp_up()`] of
+    /// its emission, while deactivating an active one starts its
+    /// [`Spawner::cooldown()`]. A spawner activated during its cooldown is
+    /// active, but doesn't spawn any particle until the cooldown elapsed.
     pub fn set_active(&mut self, active: bool) {
+        if active != self.active {
+            if active {
+                self.active_time = 0.;
+            } else {
+                self.cooldown_remaining = self.spawner.cooldown;
+            }
+        }
         self.active = active;
+        for extra in &mut self.extra {
+            extra.set_active(active);
+        }
+    }
+
+    /// Activate the spawner, unless it's cooling down.
+    ///
+    /// This is the equivalent of [`set_active(true)`], except that a spawner
+    /// deactivated less than [`Spawner::cooldown()`] seconds ago is left
+    /// inactive. This is convenient to implement a weapon firing on user
+    /// input, which can't fire again before some delay.
+    ///
+    /// Returns `true` if the spawner is active after the call.
+    ///
+    /// ```
+    /// # use bevy::prelude::*;
+    /// # use bevy_hanabi::*;
+    /// fn fire(input: Res<ButtonInput<MouseButton>>, mut query: Query<&mut EffectSpawner>) {
+    ///     for mut spawner in query.iter_mut() {
+    ///         if input.pressed(MouseButton::Left) {
+    ///             spawner.trigger();
+    ///         } else {
+    ///             spawner.set_active(false);
+    ///         }
+    ///     }
+    /// }
+    /// ```
+    ///
+    /// [`set_active(true)`]: crate::EffectSpawner::set_active
+    pub fn trigger(&mut self) -> bool {
+        if !self.active && self.cooldown_remaining <= 0. {
+            self.set_active(true);
+        }
+        self.active
+    }
+
+    /// Get the remaining time, in seconds, until the spawner can emit again
+    /// after it was deactivated.
+    ///
+    /// This is zero if the spawner is not cooling down. See
+    /// [`Spawner::cooldown()`] for details.
+    pub fn cooldown_remaining(&self) -> f32 {
+        self.cooldown_remaining
     }
 
     /// Get whether the spawner is active.
@@ -438,6 +911,60 @@ impl EffectSpawner {
         self.active
     }
 
+    /// Set the scale applied to the number of particles spawned.
+    ///
+    /// The number of particles the [`Spawner`] emits is multiplied by this
+    /// scale, which allows reducing the particle count of an instance without
+    /// modifying its spawner configuration, for example to implement some
+    /// level of detail. The scale is clamped to be positive. Defaults to
+    /// `1.0`.
+    ///
+    /// For effects with a [`max_screen_coverage`] budget, the scale is
+    /// overwritten each frame by [`apply_screen_coverage_budget()`].
+    ///
+    /// [`max_screen_coverage`]: crate::EffectAsset::max_screen_coverage
+    pub fn set_spawn_scale(&mut self, spawn_scale: f32) {
+        self.spawn_scale = spawn_scale.max(0.);
+        for extra in &mut self.extra {
+            extra.set_spawn_scale(spawn_scale);
+        }
+    }
+
+    /// Get the scale applied to the number of particles spawned.
+    ///
+    /// See [`set_spawn_scale()`] for details.
+    ///
+    /// [`set_spawn_scale()`]: crate::EffectSpawner::set_spawn_scale
+    pub fn spawn_scale(&self) -> f32 {
+        self.spawn_scale
+    }
+
+    /// Set the emitter scale applied to the number of particles spawned.
+    ///
+    /// This is updated each frame by [`tick_spawners()`] for effects with
+    /// [`EffectAsset::emitter_scaling`] enabled, from the volume scale of the
+    /// emitter's transform. Unlike [`spawn_scale()`], this is not meant to be
+    /// set manually. The scale is clamped to be positive.
+    ///
+    /// [`spawn_scale()`]: crate::EffectSpawner::spawn_scale
+    pub(crate) fn set_emitter_scale(&mut self, emitter_scale: f32) {
+        self.emitter_scale = emitter_scale.max(0.);
+        for extra in &mut self.extra {
+            extra.set_emitter_scale(emitter_scale);
+        }
+    }
+
+    /// Get the emitter scale applied to the number of particles spawned.
+    ///
+    /// This is always `1.0` unless the effect enables
+    /// [`EffectAsset::emitter_scaling`]. See
+    /// [`EffectAsset::with_emitter_scaling()`] for details.
+    ///
+    /// [`EffectAsset::with_emitter_scaling()`]: crate::EffectAsset::with_emitter_scaling
+    pub fn emitter_scale(&self) -> f32 {
+        self.emitter_scale
+    }
+
     /// Get the spawner configuration in use.
     ///
     /// The effective [`Spawner`] used is either the override specified in the
@@ -447,27 +974,101 @@ impl EffectSpawner {
         &self.spawner
     }
 
+    /// Get the configuration of all the spawners in use, starting with the
+    /// main one returned by [`spawner()`], followed by the additional spawners
+    /// of the [`EffectAsset`].
+    ///
+    /// [`spawner()`]: crate::EffectSpawner::spawner
+    pub fn spawners(&self) -> impl Iterator<Item = &Spawner> {
+        std::iter::once(&self.spawner).chain(self.extra.iter().map(|extra| &extra.spawner))
+    }
+
+    /// Spawn an exact number of particles on the next frame.
+    ///
+    /// On the next [`tick()`], the spawner emits exactly `count` particles
+    /// into group #0, overriding the number of particles all the spawners of
+    /// the effect would otherwise emit that frame. This allows precise,
+    /// event-driven emission, like spawning 50 sparks exactly when a bullet
+    /// hits, without configuring a [`Spawner::once()`] and calling
+    /// [`reset()`]. The spawners keep advancing their time as usual, so
+    /// resume their emission where they left off on the following frames.
+    ///
+    /// The particles are spawned even if the spawner is inactive (see
+    /// [`set_active()`]), so a spawner created inactive can be fully driven by
+    /// code. Calling this several times before the next tick accumulates the
+    /// requested counts. The count is clamped to the capacity of group #0,
+    /// and like any other spawned particle, the particles are only spawned if
+    /// enough dead particles are available in that group.
+    ///
+    /// ```
+    /// # use bevy::prelude::*;
+    /// # use bevy_hanabi::*;
+    /// # #[derive(Event)]
+    /// # struct BulletHit(Entity);
+    /// fn on_hit(mut events: EventReader<BulletHit>, mut query: Query<&mut EffectSpawner>) {
+    ///     for hit in events.read() {
+    ///         if let Ok(mut spawner) = query.get_mut(hit.0) {
+    ///             spawner.spawn_now(50);
+    ///         }
+    ///     }
+    /// }
+    /// ```
+    ///
+    /// [`tick()`]: crate::EffectSpawner::tick
+    /// [`reset()`]: crate::EffectSpawner::reset
+    /// [`set_active()`]: crate::EffectSpawner::set_active
+    pub fn spawn_now(&mut self, count: u32) {
+        self.pending_spawn_now = Some(
+            self.pending_spawn_now
+                .unwrap_or(0)
+                .saturating_add(count)
+                .min(self.capacity),
+        );
+    }
+
+    /// Get the number of particles requested with [`spawn_now()`] and not yet
+    /// spawned, if any.
+    ///
+    /// [`spawn_now()`]: crate::EffectSpawner::spawn_now
+    pub fn pending_spawn_now(&self) -> Option<u32> {
+        self.pending_spawn_now
+    }
+
     /// Reset the spawner state.
     ///
     /// This resets the internal spawner time to zero, and restarts any internal
-    /// particle counter.
+    /// particle counter and the idle time tracking the last spawn. Any
+    /// [`Spawner::with_ramp_up()`] ramp restarts from the beginning, and any
+    /// pending [`Spawner::with_cooldown()`] cooldown is cancelled.
     ///
     /// Use this, for example, to immediately spawn some particles in a spawner
     /// constructed with [`Spawner::once`].
     ///
     /// [`Spawner::once`]: crate::Spawner::once
+    /// [`Spawner::with_ramp_up()`]: crate::Spawner::with_ramp_up
+    /// [`Spawner::with_cooldown()`]: crate::Spawner::with_cooldown
     pub fn reset(&mut self) {
         self.time = 0.;
         self.limit = 0.;
         self.spawn_count = 0;
+        self.main_spawn_count = 0;
+        self.spawn_now_count = None;
         self.spawn_remainder = 0.;
+        self.idle_time = 0.;
+        self.active_time = 0.;
+        self.cooldown_remaining = 0.;
+        for extra in &mut self.extra {
+            extra.reset();
+        }
     }
 
     /// Tick the spawner to calculate the number of particles to spawn this
     /// frame.
     ///
     /// The frame delta time `dt` is added to the current spawner time, before
-    /// the spawner calculates the number of particles to spawn.
+    /// the spawner calculates the number of particles to spawn. If the effect
+    /// is still warming up, part of the remaining warmup time is added to `dt`
+    /// too (see [`MAX_WARMUP_STEP`]).
     ///
     /// This method is called automatically by [`tick_spawners()`] during the
     /// [`PostUpdate`], so you normally don't have to call it yourself
@@ -475,14 +1076,70 @@ impl EffectSpawner {
     ///
     /// # Returns
     ///
-    /// The integral number of particles to spawn this frame. Any fractional
-    /// remainder is saved for the next call.
-    pub fn tick(&mut self, mut dt: f32, rng: &mut Pcg32) -> u32 {
-        if !self.active {
+    /// The integral number of particles to spawn this frame, summed over all
+    /// spawners. Any fractional remainder is saved for the next call.
+    ///
+    /// [`MAX_WARMUP_STEP`]: crate::EffectSpawner::MAX_WARMUP_STEP
+    pub fn tick(&mut self, dt: f32, rng: &mut Pcg32) -> u32 {
+        let mut count = self.tick_single(dt, rng);
+        self.main_spawn_count = count;
+        if !self.extra.is_empty() {
+            for extra in &mut self.extra {
+                count += extra.tick(dt, rng);
+            }
+            self.spawn_count = count;
+            if count > 0 {
+                self.idle_time = 0.;
+            }
+        }
+
+        // Override the spawners with any count requested by spawn_now()
+        self.spawn_now_count = self.pending_spawn_now.take();
+        if let Some(count) = self.spawn_now_count {
+            self.spawn_count = count;
+            if count > 0 {
+                self.idle_time = 0.;
+            }
+            return count;
+        }
+
+        count
+    }
+
+    /// Tick the main spawner only, ignoring any additional spawner.
+    fn tick_single(&mut self, mut dt: f32, rng: &mut Pcg32) -> u32 {
+        let frame_dt = dt;
+
+        // Consume part of any remaining warmup by simulating a longer frame
+        let warmup_dt = self.warmup.min(Self::MAX_WARMUP_STEP);
+        self.warmup -= warmup_dt;
+        dt += warmup_dt;
+        self.delta_time = dt;
+
+        // The cooldown elapses whether the spawner was activated again or not
+        let cooldown_dt = self.cooldown_remaining.min(dt);
+        self.cooldown_remaining -= cooldown_dt;
+
+        if !self.active || self.cooldown_remaining > 0. {
             self.spawn_count = 0;
+            self.idle_time += frame_dt;
             return 0;
         }
 
+        // Emission resumes only once the cooldown elapsed
+        dt -= cooldown_dt;
+
+        // Scale the emission while ramping up after activation
+        let ramp_up = self.spawner.ramp_up;
+        let ramp_scale = if ramp_up > 0. && self.active_time < ramp_up && dt > 0. {
+            (ramp_up_integral(self.active_time + dt, ramp_up)
+                - ramp_up_integral(self.active_time, ramp_up))
+                / dt
+        } else {
+            1.
+        };
+        self.active_time += dt;
+
         // The limit can be reached multiple times, so use a loop
         loop {
             if self.limit == 0.0 {
@@ -494,15 +1151,25 @@ impl EffectSpawner {
             if self.time <= self.curr_spawn_time {
                 // If the spawn time is very small, close to zero, spawn all particles
                 // immediately in one burst over a single frame.
-                self.spawn_remainder += if self.curr_spawn_time < 1e-5f32.max(dt / 100.0) {
-                    self.spawner.num_particles.sample(rng)
-                } else {
-                    // Spawn an amount of particles equal to the fraction of time the current frame
-                    // spans compared to the total burst duration.
-                    self.spawner.num_particles.sample(rng)
-                        * (new_time.min(self.curr_spawn_time) - self.time)
-                        / self.curr_spawn_time
-                };
+                self.spawn_remainder += self.spawn_scale
+                    * self.emitter_scale
+                    * ramp_scale
+                    * if self.curr_spawn_time < 1e-5f32.max(dt / 100.0) {
+                        self.curr_num_particles
+                    } else {
+                        // Spawn an amount of particles equal to the fraction of time the current frame
+                        // spans compared to the total burst duration.
+                        // If a fade out is set, the fraction is weighted by the ramp down
+                        // over the end of the burst.
+                        let fade_out = self.spawner.fade_out;
+                        self.curr_num_particles
+                            * (fade_out_integral(
+                                new_time.min(self.curr_spawn_time),
+                                self.curr_spawn_time,
+                                fade_out,
+                            ) - fade_out_integral(self.time, self.curr_spawn_time, fade_out))
+                            / self.curr_spawn_time
+                    };
             }
 
             let old_time = self.time;
@@ -521,9 +1188,55 @@ impl EffectSpawner {
         self.spawn_remainder -= count;
         self.spawn_count = count as u32;
 
+        if self.spawn_count > 0 {
+            self.idle_time = 0.;
+        } else {
+            self.idle_time += frame_dt;
+        }
+
         self.spawn_count
     }
 
+    /// Check whether the spawner is done spawning particles.
+    ///
+    /// A spawner is done if it's inactive, or if it's a [`Spawner::once()`]
+    /// spawner which already emitted its burst and wasn't reset since. Note
+    /// that a done spawner can spawn again later, if it's re-activated or
+    /// reset. For effects with several spawners, all spawners must be done.
+    ///
+    /// [`Spawner::once()`]: crate::Spawner::once
+    pub fn is_done(&self) -> bool {
+        (!self.active
+            || (self.spawner.is_once() && self.limit > 0. && self.time > self.curr_spawn_time))
+            && self.extra.iter().all(EffectSpawner::is_done)
+    }
+
+    /// Check whether the effect is still pre-simulating its warmup.
+    ///
+    /// The effect is not rendered while warming up. See
+    /// [`EffectAsset::with_warmup()`].
+    ///
+    /// [`EffectAsset::with_warmup()`]: crate::EffectAsset::with_warmup
+    pub fn is_warming_up(&self) -> bool {
+        self.warmup > 0.
+    }
+
+    /// Get the simulation delta time of the last [`tick()`] call, in seconds.
+    ///
+    /// This is the frame delta time, plus any warmup time consumed this
+    /// frame.
+    ///
+    /// [`tick()`]: crate::EffectSpawner::tick
+    pub fn delta_time(&self) -> f32 {
+        self.delta_time
+    }
+
+    /// Get the time elapsed since the last frame any particle was spawned, in
+    /// seconds.
+    pub(crate) fn idle_time(&self) -> f32 {
+        self.idle_time
+    }
+
     /// Get the particle spawn count calculated by the last [`tick()`] call.
     ///
     /// This corresponds to the number of particles that will be (or have been,
@@ -536,15 +1249,274 @@ impl EffectSpawner {
         self.spawn_count
     }
 
-    /// Resamples the spawn time and period.
-    fn resample(&mut self, rng: &mut Pcg32) {
-        self.limit = self.spawner.period.sample(rng);
-        self.curr_spawn_time = self.spawner.spawn_time.sample(rng).clamp(0.0, self.limit);
-    }
-}
-
-/// Tick all the spawners of the visible [`ParticleEffect`] components.
-///
+    /// Get the particle spawn count calculated by the last [`tick()`] call for
+    /// a single group.
+    ///
+    /// This is the part of [`spawn_count()`] emitted by the spawners
+    /// targeting the group with the given index (see [`Spawner::with_group()`]).
+    ///
+    /// [`tick()`]: crate::EffectSpawner::tick
+    /// [`spawn_count()`]: crate::EffectSpawner::spawn_count
+    /// [`Spawner::with_group()`]: crate::Spawner::with_group
+    pub fn spawn_count_for_group(&self, group_index: u32) -> u32 {
+        if let Some(count) = self.spawn_now_count {
+            return if group_index == 0 { count } else { 0 };
+        }
+        let main_count = if self.spawner.group == group_index {
+            self.main_spawn_count
+        } else {
+            0
+        };
+        main_count
+            + self
+                .extra
+                .iter()
+                .map(|extra| extra.spawn_count_for_group(group_index))
+                .sum::<u32>()
+    }
+
+    /// Get a snapshot of the runtime state of the spawner.
+    ///
+    /// The state can be saved, for example as part of a save game, and later
+    /// restored with [`set_state()`] to continue an effect mid-emission. Only
+    /// the spawner timing is captured; the content of the particle buffer on
+    /// the GPU (the particles already alive) is not included, and neither is
+    /// the spawner configuration, which is restored from the [`EffectAsset`]
+    /// or [`ParticleEffect`]. For effects with several spawners, the state of
+    /// each additional spawner is captured in [`EffectSpawnerState::extra`].
+    ///
+    /// [`set_state()`]: crate::EffectSpawner::set_state
+    pub fn state(&self) -> EffectSpawnerState {
+        EffectSpawnerState {
+            time: self.time,
+            curr_spawn_time: self.curr_spawn_time,
+            curr_num_particles: self.curr_num_particles,
+            limit: self.limit,
+            spawn_remainder: self.spawn_remainder,
+            active: self.active,
+            warmup: self.warmup,
+            active_time: self.active_time,
+            cooldown_remaining: self.cooldown_remaining,
+            idle_time: self.idle_time,
+            extra: self.extra.iter().map(EffectSpawner::state).collect(),
+        }
+    }
+
+    /// Restore the runtime state of the spawner from a snapshot previously
+    /// obtained with [`state()`].
+    ///
+    /// The spawn count of the current frame is reset, so the next particles
+    /// are spawned on the next [`tick()`]. The additional spawners are
+    /// restored in order from [`EffectSpawnerState::extra`]; any additional
+    /// spawner without a saved state is left unchanged.
+    ///
+    /// [`state()`]: crate::EffectSpawner::state
+    /// [`tick()`]: crate::EffectSpawner::tick
+    pub fn set_state(&mut self, state: &EffectSpawnerState) {
+        self.time = state.time;
+        self.curr_spawn_time = state.curr_spawn_time;
+        self.curr_num_particles = state.curr_num_particles.max(0.);
+        self.limit = state.limit;
+        self.spawn_remainder = state.spawn_remainder.max(0.);
+        self.active = state.active;
+        self.warmup = state.warmup;
+        self.active_time = state.active_time;
+        self.cooldown_remaining = state.cooldown_remaining.max(0.);
+        self.spawn_count = 0;
+        self.main_spawn_count = 0;
+        self.spawn_now_count = None;
+        self.idle_time = state.idle_time.max(0.);
+        for (extra, state) in self.extra.iter_mut().zip(&state.extra) {
+            extra.set_state(state);
+        }
+    }
+
+    /// Resamples the spawn time, period, and particle count.
+    fn resample(&mut self, rng: &mut Pcg32) {
+        self.limit = self.spawner.period.sample(rng);
+        self.curr_spawn_time = self.spawner.spawn_time.sample(rng).clamp(0.0, self.limit);
+        // A negative count spawns nothing, and must not eat into the fractional
+        // remainder accumulated for the next periods.
+        self.curr_num_particles = self.spawner.num_particles.sample(rng).max(0.);
+    }
+}
+
+/// Serializable snapshot of the runtime state of an [`EffectSpawner`].
+///
+/// See [`EffectSpawner::state()`] for details.
+#[derive(Debug, Default, Clone, PartialEq, Reflect, Serialize, Deserialize)]
+pub struct EffectSpawnerState {
+    /// Accumulated time since the start of the current spawn period, in
+    /// seconds.
+    pub time: f32,
+    /// Sampled duration of the spawn burst of the current period, in seconds.
+    pub curr_spawn_time: f32,
+    /// Sampled number of particles to spawn during the current period.
+    #[serde(default)]
+    pub curr_num_particles: f32,
+    /// Sampled duration of the current period, in seconds, or `0` if not
+    /// sampled yet.
+    pub limit: f32,
+    /// Fractional remainder of particle count to spawn.
+    pub spawn_remainder: f32,
+    /// Whether the spawner is active.
+    pub active: bool,
+    /// Remaining warmup time to pre-simulate, in seconds.
+    pub warmup: f32,
+    /// Time elapsed since the spawner was activated, for its ramp up, in
+    /// seconds.
+    #[serde(default)]
+    pub active_time: f32,
+    /// Remaining cooldown time before the spawner can emit again, in seconds.
+    #[serde(default)]
+    pub cooldown_remaining: f32,
+    /// Time elapsed since the last frame any particle was spawned, in seconds.
+    ///
+    /// This is used to determine whether all the particles of a done effect
+    /// died. See [`CompiledParticleEffect::is_finished()`].
+    ///
+    /// [`CompiledParticleEffect::is_finished()`]: crate::CompiledParticleEffect::is_finished
+    #[serde(default)]
+    pub idle_time: f32,
+    /// State of each additional spawner, in the order of
+    /// [`EffectAsset::extra_spawners`].
+    ///
+    /// This field is not reflected, since reflecting a type containing itself
+    /// is not supported.
+    #[serde(default)]
+    #[reflect(ignore)]
+    pub extra: Vec<EffectSpawnerState>,
+}
+
+/// Initial state of a particle injected into an effect instance.
+///
+/// See [`ParticleInjector`] for details.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Reflect, Serialize, Deserialize)]
+pub struct ParticleInitState {
+    /// Initial position of the particle, relative to the effect emitter.
+    ///
+    /// This overwrites any [`Attribute::POSITION`] assigned by the init
+    /// modifiers. The position is expressed like the ones assigned by init
+    /// modifiers, and is therefore offset by the emitter position if the
+    /// effect is simulated in [`SimulationSpace::Global`].
+    ///
+    /// [`Attribute::POSITION`]: crate::Attribute::POSITION
+    /// [`SimulationSpace::Global`]: crate::SimulationSpace::Global
+    pub position: Vec3,
+    /// Initial velocity of the particle, if any.
+    ///
+    /// If `Some`, this overwrites any [`Attribute::VELOCITY`] assigned by the
+    /// init modifiers. Otherwise the velocity assigned by those modifiers is
+    /// retained.
+    ///
+    /// [`Attribute::VELOCITY`]: crate::Attribute::VELOCITY
+    pub velocity: Option<Vec3>,
+    /// Initial color of the particle, if any.
+    ///
+    /// If `Some`, this overwrites any [`Attribute::COLOR`] or
+    /// [`Attribute::HDR_COLOR`] assigned by the init modifiers. Otherwise the
+    /// color assigned by those modifiers is retained.
+    ///
+    /// [`Attribute::COLOR`]: crate::Attribute::COLOR
+    /// [`Attribute::HDR_COLOR`]: crate::Attribute::HDR_COLOR
+    pub color: Option<Vec4>,
+}
+
+impl ParticleInitState {
+    /// Create a new initial state with the given position, retaining the
+    /// velocity and color assigned by the init modifiers.
+    pub fn new(position: Vec3) -> Self {
+        Self {
+            position,
+            ..default()
+        }
+    }
+
+    /// Set the initial velocity of the particle.
+    pub fn with_velocity(mut self, velocity: Vec3) -> Self {
+        self.velocity = Some(velocity);
+        self
+    }
+
+    /// Set the initial color of the particle.
+    pub fn with_color(mut self, color: Vec4) -> Self {
+        self.color = Some(color);
+        self
+    }
+}
+
+/// Component to inject particles with explicit initial states into an
+/// effect instance.
+///
+/// While [`Spawner`] emits particles procedurally, the injector allows
+/// gameplay code to place particles exactly, for example on the vertices of
+/// a mesh, or to replay some recorded data. Add this component to the entity
+/// holding the [`ParticleEffect`], and call [`inject()`] to queue some
+/// particles.
+///
+/// Injection is a one-shot operation: the particles queued during a frame
+/// are spawned into group #0 on the next simulation update, in addition to
+/// the ones emitted by the spawner of the effect, and the queue is then
+/// cleared. The init modifiers of the effect still run for the injected
+/// particles; their position, and optionally their velocity and color, are
+/// then overwritten by the injected values.
+///
+/// Injected particles are subject to the same capacity constraints as any
+/// other particle. If there are not enough dead particles available in the
+/// effect to spawn all the particles requested this frame, the excess ones
+/// are silently dropped. Injected particles are spawned first, so take
+/// precedence over the ones emitted by the spawner.
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_hanabi::*;
+/// fn inject(mut query: Query<&mut ParticleInjector>) {
+///     for mut injector in &mut query {
+///         injector.inject([
+///             ParticleInitState::new(Vec3::X),
+///             ParticleInitState::new(Vec3::Y).with_velocity(Vec3::Y),
+///         ]);
+///     }
+/// }
+/// ```
+///
+/// [`inject()`]: crate::ParticleInjector::inject
+#[derive(Debug, Default, Clone, PartialEq, Component)]
+pub struct ParticleInjector {
+    /// Particles queued for the next simulation update.
+    pending: Vec<ParticleInitState>,
+    /// Particles injected during the current simulation update.
+    injected: Vec<ParticleInitState>,
+}
+
+impl ParticleInjector {
+    /// Queue some particles to be spawned on the next simulation update.
+    pub fn inject(&mut self, states: impl IntoIterator<Item = ParticleInitState>) {
+        self.pending.extend(states);
+    }
+
+    /// Get the particles queued for the next simulation update.
+    pub fn pending(&self) -> &[ParticleInitState] {
+        &self.pending
+    }
+
+    /// Get the particles injected during the current simulation update.
+    ///
+    /// These are the particles queued during the previous frame, and moved
+    /// to this list by [`tick_spawners()`].
+    pub fn injected(&self) -> &[ParticleInitState] {
+        &self.injected
+    }
+
+    /// Move the pending particles to the list of particles injected during
+    /// the current simulation update.
+    pub(crate) fn flush(&mut self) {
+        self.injected = std::mem::take(&mut self.pending);
+    }
+}
+
+/// Tick all the spawners of the visible [`ParticleEffect`] components.
+///
 /// This system runs in the [`PostUpdate`] stage, after the visibility system
 /// has updated the [`InheritedVisibility`] of each effect instance (see
 /// [`VisibilitySystems::VisibilityPropagate`]). Hidden instances are not
@@ -557,6 +1529,16 @@ impl EffectSpawner {
 /// per-view culling of invisible (not in view) effects is performed later on
 /// the render world.
 ///
+/// Instances disabled via their [`EffectInstance`] component are never
+/// updated, irrespective of their visibility.
+///
+/// This system also flushes the [`ParticleInjector`] of each effect instance,
+/// so that the particles queued since the last tick are injected this frame.
+///
+/// For effects with [`EffectAsset::emitter_scaling`] enabled, the number of
+/// particles spawned is scaled by the volume scale of the emitter's
+/// [`GlobalTransform`], composed with the [`ParticleEffect::local_transform`].
+///
 /// [`VisibilitySystems::VisibilityPropagate`]: bevy::render::view::VisibilitySystems::VisibilityPropagate
 /// [`EffectAsset::simulation_condition`]: crate::EffectAsset::simulation_condition
 pub fn tick_spawners(
@@ -567,15 +1549,32 @@ pub fn tick_spawners(
     mut query: Query<(
         Entity,
         &ParticleEffect,
+        Option<&EffectInstance>,
         Option<&InheritedVisibility>,
+        Option<&GlobalTransform>,
         Option<&mut EffectSpawner>,
+        Option<&mut ParticleInjector>,
     )>,
 ) {
     trace!("tick_spawners");
 
     let dt = time.delta_seconds();
 
-    for (entity, effect, maybe_inherited_visibility, maybe_spawner) in query.iter_mut() {
+    for (
+        entity,
+        effect,
+        maybe_instance,
+        maybe_inherited_visibility,
+        maybe_transform,
+        maybe_spawner,
+        maybe_injector,
+    ) in query.iter_mut()
+    {
+        // Disabled instances are frozen, including their spawner
+        if !maybe_instance.map_or(true, |instance| instance.is_enabled()) {
+            continue;
+        }
+
         // TODO - maybe cache simulation_condition so we don't need to unconditionally
         // query the asset?
         let Some(asset) = effects.get(&effect.handle) else {
@@ -590,16 +1589,142 @@ pub fn tick_spawners(
             continue;
         }
 
+        if let Some(mut injector) = maybe_injector {
+            if !injector.pending.is_empty() || !injector.injected.is_empty() {
+                injector.flush();
+            }
+        }
+
+        let emitter_scale = if asset.emitter_scaling {
+            maybe_transform
+                .map(|transform| emitter_volume_scale(transform, effect))
+                .unwrap_or(1.)
+        } else {
+            1.
+        };
+
         if let Some(mut spawner) = maybe_spawner {
+            if spawner.emitter_scale != emitter_scale {
+                spawner.set_emitter_scale(emitter_scale);
+            }
             spawner.tick(dt, &mut rng.0);
         } else {
             let mut spawner = EffectSpawner::new(asset, effect);
+            spawner.set_emitter_scale(emitter_scale);
             spawner.tick(dt, &mut rng.0);
             commands.entity(entity).insert(spawner);
         }
     }
 }
 
+/// Calculate the volume scale of an emitter, that is the product of the
+/// absolute scale components of its transform composed with the local
+/// transform of the effect instance.
+fn emitter_volume_scale(transform: &GlobalTransform, effect: &ParticleEffect) -> f32 {
+    let scale = transform
+        .mul_transform(effect.local_transform)
+        .compute_transform()
+        .scale
+        .abs();
+    scale.x * scale.y * scale.z
+}
+
+/// Estimate the number of particles alive at the same time for an effect
+/// instance, in steady state.
+fn estimated_alive_count(asset: &EffectAsset, spawner: &Spawner) -> f32 {
+    let capacity = asset.capacities().iter().sum::<u32>() as f32;
+    let num_particles = spawner.num_particles.range()[1];
+    if spawner.is_once() {
+        return num_particles.min(capacity);
+    }
+    let Some(lifetime) = asset.estimated_max_lifetime() else {
+        return capacity;
+    };
+    let period = spawner.period.range()[0];
+    if period <= 0. {
+        return capacity;
+    }
+    // Number of periods overlapping a particle lifetime
+    let periods = (lifetime / period).ceil().max(1.);
+    (num_particles * periods).min(capacity)
+}
+
+/// Apply the [`EffectAsset::max_screen_coverage`] fill-rate budget to all
+/// effect instances.
+///
+/// This system runs in the [`PostUpdate`] stage, just before
+/// [`tick_spawners()`]. For each effect instance whose asset defines a
+/// screen coverage budget, it estimates the fraction of the viewport covered
+/// by the particles of that instance, for each active camera, and updates
+/// the [`EffectSpawner::spawn_scale()`] so that the worst estimate fits
+/// inside the budget. See [`EffectAsset::with_max_screen_coverage()`] for
+/// details.
+///
+/// [`EffectAsset::max_screen_coverage`]: crate::EffectAsset::max_screen_coverage
+/// [`EffectAsset::with_max_screen_coverage()`]: crate::EffectAsset::with_max_screen_coverage
+pub fn apply_screen_coverage_budget(
+    effects: Res<Assets<EffectAsset>>,
+    cameras: Query<(
+        &Camera,
+        &GlobalTransform,
+        Option<&Projection>,
+        Option<&OrthographicProjection>,
+    )>,
+    mut query: Query<(&ParticleEffect, &GlobalTransform, &mut EffectSpawner)>,
+) {
+    trace!("apply_screen_coverage_budget");
+
+    for (effect, transform, mut spawner) in query.iter_mut() {
+        let Some(asset) = effects.get(&effect.handle) else {
+            continue;
+        };
+        let Some(max_screen_coverage) = asset.max_screen_coverage else {
+            continue;
+        };
+        let Some(size) = asset.estimated_max_size() else {
+            continue;
+        };
+        let capacity = asset.capacities().iter().sum::<u32>() as f32;
+        let count = spawner
+            .spawners()
+            .map(|spawner| estimated_alive_count(asset, spawner))
+            .sum::<f32>()
+            .min(capacity);
+        let origin = transform.translation();
+
+        let mut coverage = 0_f32;
+        for (camera, camera_transform, projection, ortho) in cameras.iter() {
+            if !camera.is_active {
+                continue;
+            }
+            let view_area = match (projection, ortho) {
+                (Some(Projection::Perspective(p)), _) => {
+                    let forward = camera_transform.forward();
+                    let distance = (origin - camera_transform.translation())
+                        .dot(forward)
+                        .max(p.near);
+                    let height = 2. * distance * (p.fov / 2.).tan();
+                    height * height * p.aspect_ratio
+                }
+                (Some(Projection::Orthographic(o)), _) | (None, Some(o)) => {
+                    o.area.width() * o.area.height()
+                }
+                _ => continue,
+            };
+            if view_area > 0. {
+                coverage = coverage.max(count * size * size / view_area);
+            }
+        }
+
+        let scale = if coverage > max_screen_coverage {
+            max_screen_coverage / coverage
+        } else {
+            1.
+        };
+        spawner.set_spawn_scale(scale);
+    }
+}
+
 #[cfg(test)]
 mod test {
     use std::time::Duration;
@@ -619,7 +1744,7 @@ mod test {
         tasks::{IoTaskPool, TaskPoolBuilder},
     };
 
-    use crate::Module;
+    use crate::{test_utils::assert_approx_eq, Attribute, Module, SetAttributeModifier};
 
     use super::*;
 
@@ -643,6 +1768,40 @@ mod test {
         assert_eq!(value.range(), [1.0, 3.0]);
     }
 
+    #[test]
+    fn test_constructors() {
+        let value = CpuValue::constant(3.5);
+        assert_eq!(value, CpuValue::Single(3.5));
+        assert!(value.is_constant());
+        assert_eq!(value, 3.5.into());
+
+        let value = CpuValue::uniform(10., 20.);
+        assert_eq!(value, CpuValue::Uniform((10., 20.)));
+        assert!(!value.is_constant());
+
+        let rng = &mut new_rng();
+        for _ in 0..100 {
+            let x = value.sample(rng);
+            assert!((10. ..=20.).contains(&x));
+        }
+        // Any RNG can be used, for example for a CPU-side preview
+        let x = value.sample(&mut rand::thread_rng());
+        assert!((10. ..=20.).contains(&x));
+    }
+
+    #[test]
+    fn test_serde() {
+        let value = CpuValue::constant(3.5);
+        let s = ron::to_string(&value).unwrap();
+        assert_eq!(s, "Single(3.5)");
+        assert_eq!(ron::from_str::<CpuValue<f32>>(&s).unwrap(), value);
+
+        let value = CpuValue::uniform(10., 20.);
+        let s = ron::to_string(&value).unwrap();
+        assert_eq!(s, "Uniform((10.0,20.0))");
+        assert_eq!(ron::from_str::<CpuValue<f32>>(&s).unwrap(), value);
+    }
+
     #[test]
     fn test_range_uniform_reverse() {
         let value = CpuValue::Uniform((3.0, 1.0));
@@ -692,7 +1851,10 @@ mod test {
         let spawner = Spawner::once(5.0.into(), true);
         let mut spawner = make_effect_spawner(spawner);
         spawner.tick(1.0, rng);
+        spawner.tick(1.0, rng);
+        assert_eq!(spawner.idle_time(), 1.);
         spawner.reset();
+        assert_eq!(spawner.idle_time(), 0.);
         let count = spawner.tick(1.0, rng);
         assert_eq!(count, 5);
     }
@@ -737,6 +1899,199 @@ mod test {
         assert_eq!(count, 2);
     }
 
+    #[test]
+    fn test_state() {
+        let rng = &mut new_rng();
+        // 3 particles over 3 seconds, pause 7 seconds (total 10 seconds period).
+        let spawner = Spawner::new(3.0.into(), 3.0.into(), 10.0.into());
+        let mut spawner = make_effect_spawner(spawner);
+        let count = spawner.tick(1.5, rng); // t = 1.5s
+        assert_eq!(count, 1);
+
+        let state = spawner.state();
+        assert_eq!(state.time, 1.5);
+        assert_eq!(state.limit, 10.);
+        assert_eq!(state.spawn_remainder, 0.5);
+        assert!(state.active);
+
+        // Round-trip through serialization, as a save game would
+        let s = ron::to_string(&state).unwrap();
+        let state: EffectSpawnerState = ron::from_str(&s).unwrap();
+
+        // A new spawner continues mid-emission like the original one
+        let mut restored = make_effect_spawner(Spawner::new(3.0.into(), 3.0.into(), 10.0.into()));
+        restored.set_state(&state);
+        assert_eq!(restored.state(), spawner.state());
+        assert_eq!(restored.spawn_count(), 0);
+        let count = restored.tick(1.5, rng); // t = 3s
+        assert_eq!(count, 2);
+        assert_eq!(spawner.tick(1.5, rng), 2);
+        assert_eq!(restored.state(), spawner.state());
+        // The idle time is restored too, and defaults to zero for older states
+        spawner.set_active(false);
+        spawner.tick(2., rng);
+        let state = spawner.state();
+        assert_eq!(state.idle_time, 2.);
+        restored.set_state(&state);
+        assert_eq!(restored.idle_time(), 2.);
+        let state: EffectSpawnerState = ron::from_str(
+            "(time:0.,curr_spawn_time:0.,limit:0.,spawn_remainder:0.,active:true,warmup:0.)",
+        )
+        .unwrap();
+        assert_eq!(state.idle_time, 0.);
+    }
+
+    #[test]
+    fn test_sample_count_per_period() {
+        let rng = &mut new_rng();
+        // 10 to 20 particles over 4 seconds, every 10 seconds
+        let spawner = Spawner::new(CpuValue::uniform(10., 20.), 4.0.into(), 10.0.into());
+        let mut spawner = make_effect_spawner(spawner);
+        let mut total = spawner.tick(1.0, rng);
+        let count = spawner.state().curr_num_particles;
+        assert!((10. ..=20.).contains(&count));
+        for _ in 0..8 {
+            total += spawner.tick(1.0, rng);
+            // The count is constant within a period
+            assert_eq!(spawner.state().curr_num_particles, count);
+        }
+        // The entire period spawned the sampled count, up to rounding
+        assert_eq!(total, count.floor() as u32);
+    }
+
+    #[test]
+    fn test_negative_count() {
+        let rng = &mut new_rng();
+
+        // Zero count
+        let mut spawner = make_effect_spawner(Spawner::rate(0.0.into()));
+        for _ in 0..10 {
+            assert_eq!(spawner.tick(1.0, rng), 0);
+            assert_eq!(spawner.spawn_count(), 0);
+            assert_eq!(spawner.state().spawn_remainder, 0.);
+        }
+
+        // Negative count, as can be produced by a uniform range straddling zero
+        let mut spawner = make_effect_spawner(Spawner::rate((-5.0).into()));
+        for _ in 0..10 {
+            assert_eq!(spawner.tick(1.0, rng), 0);
+            assert_eq!(spawner.state().curr_num_particles, 0.);
+            assert_eq!(spawner.state().spawn_remainder, 0.);
+        }
+
+        // A negative period doesn't cancel the fractional remainder accumulated
+        // by previous ones.
+        let spawner = Spawner::new(CpuValue::uniform(-10., 10.), 1.0.into(), 1.0.into());
+        let mut spawner = make_effect_spawner(spawner);
+        for _ in 0..100 {
+            let remainder = spawner.state().spawn_remainder;
+            let count = spawner.tick(1.0, rng);
+            assert!(count <= 10);
+            assert!(spawner.state().spawn_remainder >= 0.);
+            if count == 0 {
+                assert!(spawner.state().spawn_remainder >= remainder);
+            }
+        }
+
+        // A restored state can't make the spawner underflow
+        let mut spawner = make_effect_spawner(Spawner::rate(5.0.into()));
+        let mut state = spawner.state();
+        state.curr_num_particles = -3.;
+        state.spawn_remainder = -100.;
+        spawner.set_state(&state);
+        assert_eq!(spawner.state().curr_num_particles, 0.);
+        assert_eq!(spawner.state().spawn_remainder, 0.);
+        assert_eq!(spawner.tick(1.01, rng), 5);
+    }
+
+    #[test]
+    fn test_spawn_scale() {
+        let rng = &mut new_rng();
+        let mut spawner = make_effect_spawner(Spawner::rate(10.0.into()));
+        assert_eq!(spawner.spawn_scale(), 1.);
+        spawner.set_spawn_scale(0.25);
+        assert_eq!(spawner.spawn_scale(), 0.25);
+        let count = spawner.tick(2.0, rng); // 20 particles, scaled
+        assert_eq!(count, 5);
+        spawner.set_spawn_scale(-1.);
+        assert_eq!(spawner.spawn_scale(), 0.);
+        let count = spawner.tick(2.0, rng);
+        assert_eq!(count, 0);
+    }
+
+    #[test]
+    fn test_spawn_now() {
+        let rng = &mut new_rng();
+        let mut spawner = make_effect_spawner(Spawner::rate(10.0.into()));
+        assert_eq!(spawner.pending_spawn_now(), None);
+        spawner.spawn_now(30);
+        spawner.spawn_now(20);
+        assert_eq!(spawner.pending_spawn_now(), Some(50));
+
+        // Overrides the spawner for a single frame
+        let count = spawner.tick(1.0, rng);
+        assert_eq!(count, 50);
+        assert_eq!(spawner.spawn_count(), 50);
+        assert_eq!(spawner.spawn_count_for_group(0), 50);
+        assert_eq!(spawner.spawn_count_for_group(1), 0);
+        assert_eq!(spawner.pending_spawn_now(), None);
+        let count = spawner.tick(1.0, rng);
+        assert_eq!(count, 10);
+
+        // Spawns even if inactive
+        spawner.set_active(false);
+        spawner.spawn_now(5);
+        let count = spawner.tick(1.0, rng);
+        assert_eq!(count, 5);
+        let count = spawner.tick(1.0, rng);
+        assert_eq!(count, 0);
+
+        // Clamped to the group #0 capacity
+        spawner.spawn_now(1000);
+        assert_eq!(spawner.pending_spawn_now(), Some(256));
+        let count = spawner.tick(1.0, rng);
+        assert_eq!(count, 256);
+    }
+
+    #[test]
+    fn test_emitter_scale() {
+        let rng = &mut new_rng();
+        let mut spawner = make_effect_spawner(Spawner::rate(10.0.into()));
+        assert_eq!(spawner.emitter_scale(), 1.);
+        spawner.set_emitter_scale(2.);
+        spawner.set_spawn_scale(0.5);
+        let count = spawner.tick(2.0, rng); // 20 particles, scaled twice
+        assert_eq!(count, 20);
+        spawner.set_emitter_scale(3.);
+        let count = spawner.tick(2.0, rng);
+        assert_eq!(count, 30);
+
+        let effect = ParticleEffect::default()
+            .with_local_transform(Transform::from_scale(Vec3::new(1., 2., 1.)));
+        let transform = GlobalTransform::from(Transform::from_scale(Vec3::new(-2., 1., 3.)));
+        assert!((emitter_volume_scale(&transform, &effect) - 12.).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_estimated_alive_count() {
+        let mut module = Module::default();
+        let lifetime = module.lit(2.5);
+        let asset = EffectAsset::new(vec![256], Spawner::rate(10.0.into()), module)
+            .init(SetAttributeModifier::new(Attribute::LIFETIME, lifetime));
+
+        // 10 particles per second spread over each 1s period, alive for 2.5s, so
+        // overlapping at most 3 periods
+        assert_approx_eq!(estimated_alive_count(&asset, &asset.spawner), 30.);
+        // Bursts every second overlap 3 times, capped by the capacity
+        let burst = Spawner::burst(100.0.into(), 1.0.into());
+        assert_approx_eq!(estimated_alive_count(&asset, &burst), 256.);
+        let burst = Spawner::burst(50.0.into(), 1.0.into());
+        assert_approx_eq!(estimated_alive_count(&asset, &burst), 150.);
+        // Once spawners emit a single burst
+        let once = Spawner::once(30.0.into(), true);
+        assert_approx_eq!(estimated_alive_count(&asset, &once), 30.);
+    }
+
     #[test]
     fn test_rate_accumulate() {
         let rng = &mut new_rng();
@@ -760,6 +2115,319 @@ mod test {
         assert_eq!(count, 0);
     }
 
+    #[test]
+    fn test_warmup() {
+        let asset = EffectAsset::new(vec![256], Spawner::rate(10.0.into()), Module::default())
+            .with_warmup(0.25);
+        assert_eq!(asset.warmup, 0.25);
+        let mut spawner = EffectSpawner::new(&asset, &ParticleEffect::default());
+        assert!(spawner.is_warming_up());
+
+        // Warmup is consumed in steps of at most MAX_WARMUP_STEP per frame
+        let rng = &mut new_rng();
+        let count = spawner.tick(0.1, rng);
+        assert!(spawner.is_warming_up());
+        assert_approx_eq!(spawner.delta_time(), 0.1 + EffectSpawner::MAX_WARMUP_STEP);
+        assert_eq!(count, 2);
+        spawner.tick(0.1, rng);
+        assert!(spawner.is_warming_up());
+        spawner.tick(0.1, rng);
+        assert!(!spawner.is_warming_up());
+        assert_approx_eq!(spawner.delta_time(), 0.15);
+
+        // Once warmed up, the simulation runs at the frame delta time
+        spawner.tick(0.1, rng);
+        assert!(!spawner.is_warming_up());
+        assert_approx_eq!(spawner.delta_time(), 0.1);
+    }
+
+    #[test]
+    fn test_prewarm_full_lifetime() {
+        let mut module = Module::default();
+        let lifetime = module.lit(3.);
+        let asset = EffectAsset::new(vec![256], Spawner::rate(10.0.into()), module)
+            .with_warmup(1.)
+            .init(SetAttributeModifier::new(Attribute::LIFETIME, lifetime));
+        assert_eq!(asset.warmup_duration(), 1.);
+
+        // Prewarm for the full lifetime, which is longer than the warmup
+        let asset = asset.with_prewarm_full_lifetime(true);
+        assert_eq!(asset.warmup_duration(), 3.);
+        let mut spawner = EffectSpawner::new(&asset, &ParticleEffect::default());
+        assert!(spawner.is_warming_up());
+        let rng = &mut new_rng();
+        let mut frames = 0;
+        while spawner.is_warming_up() {
+            spawner.tick(0.016, rng);
+            frames += 1;
+        }
+        // 3 seconds in steps of MAX_WARMUP_STEP, give or take rounding errors
+        assert!((30..=31).contains(&frames));
+
+        // Unknown lifetime falls back to the warmup duration
+        let asset = EffectAsset::new(vec![256], Spawner::rate(10.0.into()), Module::default())
+            .with_warmup(0.5)
+            .with_prewarm_full_lifetime(true);
+        assert_eq!(asset.warmup_duration(), 0.5);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_warmup_panic_negative() {
+        let _ = EffectAsset::default().with_warmup(-1.);
+    }
+
+    #[test]
+    fn test_fade_out() {
+        let spawner = Spawner::new(40.0.into(), 4.0.into(), 10.0.into());
+        assert_eq!(spawner.fade_out(), 0.);
+        let spawner = spawner.with_fade_out(2.);
+        assert_eq!(spawner.fade_out(), 2.);
+
+        // Integral of the ramp: 1 over [0:2], then linear from 1 to 0 over [2:4]
+        assert_eq!(fade_out_integral(1., 4., 0.), 1.);
+        assert_eq!(fade_out_integral(1., 4., 2.), 1.);
+        assert_eq!(fade_out_integral(2., 4., 2.), 2.);
+        assert_eq!(fade_out_integral(4., 4., 2.), 3.);
+        assert_eq!(fade_out_integral(3., 4., 2.), 2.75);
+
+        let rng = &mut new_rng();
+        let mut spawner = make_effect_spawner(spawner);
+        // Full rate over the first 2 seconds: 10 particles/s
+        let count = spawner.tick(1., rng);
+        assert_eq!(count, 10);
+        let count = spawner.tick(1., rng);
+        assert_eq!(count, 10);
+        // Ramp down over the last 2 seconds
+        let count = spawner.tick(1., rng);
+        assert_eq!(count, 7);
+        let count = spawner.tick(1., rng);
+        assert_eq!(count, 3);
+        // Idle until the end of the period
+        let count = spawner.tick(1., rng);
+        assert_eq!(count, 0);
+    }
+
+    #[test]
+    fn test_ramp_up() {
+        let spawner = Spawner::rate(10.0.into());
+        assert_eq!(spawner.ramp_up(), 0.);
+        let spawner = spawner.with_ramp_up(2.);
+        assert_eq!(spawner.ramp_up(), 2.);
+
+        // Integral of the ramp: linear from 0 to 1 over [0:2], then 1
+        assert_eq!(ramp_up_integral(1., 0.), 1.);
+        assert_eq!(ramp_up_integral(1., 2.), 0.25);
+        assert_eq!(ramp_up_integral(2., 2.), 1.);
+        assert_eq!(ramp_up_integral(3., 2.), 2.);
+
+        let rng = &mut new_rng();
+        let mut spawner = make_effect_spawner(spawner);
+        // Ramp up over the first 2 seconds
+        let count = spawner.tick(1., rng);
+        assert_eq!(count, 2);
+        let count = spawner.tick(1., rng);
+        assert_eq!(count, 8);
+        // Full rate
+        let count = spawner.tick(1., rng);
+        assert_eq!(count, 10);
+
+        // Re-activating restarts the ramp, but keeping the spawner active doesn't
+        spawner.set_active(true);
+        let count = spawner.tick(1., rng);
+        assert_eq!(count, 10);
+        spawner.set_active(false);
+        assert_eq!(spawner.tick(1., rng), 0);
+        spawner.set_active(true);
+        let count = spawner.tick(1., rng);
+        assert!(count < 10);
+
+        // Resetting restarts the ramp too
+        spawner.tick(2., rng);
+        spawner.reset();
+        assert_eq!(spawner.tick(1., rng), 2);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_ramp_up_panic_negative() {
+        let _ = Spawner::rate(10.0.into()).with_ramp_up(-1.);
+    }
+
+    #[test]
+    fn test_cooldown() {
+        let rng = &mut new_rng();
+        let spawner = Spawner::rate(10.0.into()).with_cooldown(2.);
+        assert_eq!(spawner.cooldown(), 2.);
+        let mut spawner = make_effect_spawner(spawner);
+        assert_eq!(spawner.cooldown_remaining(), 0.);
+        assert_eq!(spawner.tick(1.01, rng), 10);
+
+        // Deactivating starts the cooldown, even if already re-activated
+        spawner.set_active(false);
+        assert_eq!(spawner.cooldown_remaining(), 2.);
+        assert!(!spawner.trigger());
+        spawner.set_active(true);
+        assert!(spawner.is_active());
+        assert_eq!(spawner.tick(1., rng), 0);
+        assert_eq!(spawner.cooldown_remaining(), 1.);
+
+        // Emission resumes for the part of the frame after the cooldown
+        let count = spawner.tick(1.5, rng);
+        assert_eq!(spawner.cooldown_remaining(), 0.);
+        assert_eq!(count, 5);
+
+        // Deactivating an inactive spawner doesn't restart the cooldown
+        spawner.set_active(false);
+        assert_eq!(spawner.tick(2., rng), 0);
+        assert_eq!(spawner.cooldown_remaining(), 0.);
+        spawner.set_active(false);
+        assert_eq!(spawner.cooldown_remaining(), 0.);
+        assert!(spawner.trigger());
+        assert!(spawner.is_active());
+        assert!(spawner.tick(1., rng) > 0);
+
+        // The cooldown is part of the serialized state
+        spawner.set_active(false);
+        let state = spawner.state();
+        assert_eq!(state.cooldown_remaining, 2.);
+        let s = ron::to_string(&state).unwrap();
+        let state: EffectSpawnerState = ron::from_str(&s).unwrap();
+        let mut restored = make_effect_spawner(*spawner.spawner());
+        restored.set_state(&state);
+        assert_eq!(restored.cooldown_remaining(), 2.);
+
+        // Resetting cancels the cooldown
+        restored.set_active(true);
+        restored.reset();
+        assert_eq!(restored.cooldown_remaining(), 0.);
+        assert_eq!(restored.tick(1.01, rng), 10);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_cooldown_panic_negative() {
+        let _ = Spawner::rate(10.0.into()).with_cooldown(-1.);
+    }
+
+    #[test]
+    fn test_ramp_up_cooldown_serde() {
+        let spawner = Spawner::rate(10.0.into())
+            .with_ramp_up(0.5)
+            .with_cooldown(2.);
+        let s = ron::to_string(&spawner).unwrap();
+        let spawner_serde: Spawner = ron::from_str(&s).unwrap();
+        assert_eq!(spawner, spawner_serde);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_fade_out_panic_negative() {
+        let _ = Spawner::rate(5.0.into()).with_fade_out(-1.);
+    }
+
+    #[test]
+    fn test_particle_injector() {
+        let mut injector = ParticleInjector::default();
+        assert!(injector.pending().is_empty());
+        assert!(injector.injected().is_empty());
+
+        let state = ParticleInitState::new(Vec3::X)
+            .with_velocity(Vec3::Y)
+            .with_color(Vec4::ONE);
+        assert_eq!(state.position, Vec3::X);
+        assert_eq!(state.velocity, Some(Vec3::Y));
+        assert_eq!(state.color, Some(Vec4::ONE));
+
+        injector.inject([state, ParticleInitState::new(Vec3::Z)]);
+        assert_eq!(injector.pending().len(), 2);
+        assert!(injector.injected().is_empty());
+
+        // Flushing moves the pending states to the current frame
+        injector.flush();
+        assert!(injector.pending().is_empty());
+        assert_eq!(injector.injected().len(), 2);
+        assert_eq!(injector.injected()[0], state);
+
+        // Injection is one-shot
+        injector.flush();
+        assert!(injector.injected().is_empty());
+    }
+
+    #[test]
+    fn test_multiple_spawners() {
+        let mut rng = new_rng();
+        let asset = EffectAsset::new(vec![256], Spawner::rate(10.0.into()), Module::default())
+            .with_spawner(Spawner::once(5.0.into(), true));
+        let mut spawner = EffectSpawner::new(&asset, &ParticleEffect::default());
+        assert_eq!(spawner.spawners().count(), 2);
+
+        // The burst of the second spawner adds to the rate of the first one
+        let count = spawner.tick(0.5, &mut rng);
+        assert_eq!(count, 10);
+        assert_eq!(spawner.spawn_count(), 10);
+        assert!(!spawner.is_done());
+
+        // The once spawner is done, but the rate one is not
+        let count = spawner.tick(0.5, &mut rng);
+        assert_eq!(count, 5);
+        assert!(!spawner.is_done());
+
+        // Spawners targeting other groups are accounted separately
+        assert_eq!(spawner.spawn_count_for_group(0), 5);
+        assert_eq!(spawner.spawn_count_for_group(1), 0);
+
+        // Deactivating applies to all spawners
+        spawner.set_active(false);
+        assert!(spawner.is_done());
+        assert_eq!(spawner.tick(0.5, &mut rng), 0);
+
+        // The instance override only replaces the main spawner
+        let instance = ParticleEffect {
+            spawner: Some(Spawner::rate(2.0.into())),
+            ..default()
+        };
+        let spawner = EffectSpawner::new(&asset, &instance);
+        let spawners: Vec<_> = spawner.spawners().copied().collect();
+        assert_eq!(
+            spawners,
+            vec![Spawner::rate(2.0.into()), Spawner::once(5.0.into(), true)]
+        );
+
+        // The state of the additional spawners is saved and restored
+        let mut spawner = EffectSpawner::new(&asset, &ParticleEffect::default());
+        spawner.tick(0.5, &mut rng);
+        let state = spawner.state();
+        assert_eq!(state.extra.len(), 1);
+        let mut restored = EffectSpawner::new(&asset, &ParticleEffect::default());
+        restored.set_state(&state);
+        assert_eq!(restored.state(), state);
+        // The burst of the second spawner already happened
+        assert_eq!(restored.tick(0.5, &mut rng), 5);
+    }
+
+    #[test]
+    fn test_spawn_group() {
+        let mut rng = new_rng();
+        let asset = EffectAsset::new(
+            vec![256, 256],
+            Spawner::rate(10.0.into()),
+            Module::default(),
+        )
+        .with_spawner(Spawner::once(5.0.into(), true).with_group(1));
+        let mut spawner = EffectSpawner::new(&asset, &ParticleEffect::default());
+        assert_eq!(
+            spawner.spawners().map(Spawner::group).collect::<Vec<_>>(),
+            vec![0, 1]
+        );
+
+        let count = spawner.tick(0.5, &mut rng);
+        assert_eq!(count, 10);
+        assert_eq!(spawner.spawn_count_for_group(0), 5);
+        assert_eq!(spawner.spawn_count_for_group(1), 5);
+        assert_eq!(spawner.spawn_count_for_group(2), 0);
+    }
+
     #[test]
     fn test_with_active() {
         let rng = &mut new_rng();
@@ -885,6 +2553,7 @@ mod test {
                                 spawner: test_case.instance_spawner,
                                 #[cfg(feature = "2d")]
                                 z_layer_2d: None,
+                                local_transform: Transform::IDENTITY,
                             },
                         ))
                         .id()
@@ -895,6 +2564,7 @@ mod test {
                             spawner: test_case.instance_spawner,
                             #[cfg(feature = "2d")]
                             z_layer_2d: None,
+                            local_transform: Transform::IDENTITY,
                         },))
                         .id()
                 };
@@ -1001,4 +2671,47 @@ mod test {
             }
         }
     }
+
+    #[test]
+    fn test_tick_spawners_disabled() {
+        let mut app = make_test_app();
+
+        let effect_entity = {
+            let world = &mut app.world;
+
+            let mut assets = world.resource_mut::<Assets<EffectAsset>>();
+            let asset = EffectAsset::new(
+                vec![64],
+                Spawner::once(32.0.into(), true),
+                Module::default(),
+            )
+            .with_simulation_condition(SimulationCondition::Always);
+            let handle = assets.add(asset);
+
+            world
+                .spawn((ParticleEffect::new(handle), EffectInstance::new(false)))
+                .id()
+        };
+
+        app.world
+            .resource_mut::<Time<EffectSimulation>>()
+            .advance_by(Duration::from_millis(16));
+        app.update();
+
+        // A disabled instance is skipped entirely, so no spawner is created
+        assert!(app.world.get::<EffectSpawner>(effect_entity).is_none());
+
+        // Re-enabling the instance resumes ticking on the next update
+        app.world
+            .get_mut::<EffectInstance>(effect_entity)
+            .unwrap()
+            .set_enabled(true);
+        app.world
+            .resource_mut::<Time<EffectSimulation>>()
+            .advance_by(Duration::from_millis(16));
+        app.update();
+
+        let effect_spawner = app.world.get::<EffectSpawner>(effect_entity).unwrap();
+        assert_eq!(effect_spawner.spawn_count, 32);
+    }
 }
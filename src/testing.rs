@@ -0,0 +1,622 @@
+//! Deterministic headless rendering of effects, for golden-image tests.
+//!
+//! This module is only available with the `testing` feature. It provides
+//! [`GoldenImageTest`], a harness rendering one or more effects to an
+//! offscreen image and comparing the result against a reference image stored
+//! on disk, to catch visual regressions in modifiers and shaders.
+//!
+//! The rendering is made deterministic by:
+//! - advancing the time by a fixed timestep each frame, via
+//!   [`TimeUpdateStrategy::ManualDuration`];
+//! - seeding the [`Random`] resources of both the main and render worlds,
+//!   which drive the spawners and the GPU simulation;
+//! - compiling the render pipelines synchronously, so the effects are drawn
+//!   starting from the first frame;
+//! - rendering without pipelined rendering, so the captured image is the one
+//!   of the last simulated frame.
+//!
+//! # Reference images
+//!
+//! Reference images are stored as PNG files. If the reference image doesn't
+//! exist, or if the `HANABI_UPDATE_GOLDEN` environment variable is set, the
+//! rendered image is written as the new reference and the check passes.
+//! Otherwise, on mismatch, the rendered image is written next to the
+//! reference with an `.actual.png` extension, to help investigating.
+//!
+//! # GPU vendor tolerance
+//!
+//! Even with a deterministic simulation, different GPUs and drivers don't
+//! produce bit-identical images. Transcendental functions (`sin`, `pow`, ...)
+//! have implementation-defined precision in WGSL, rasterization rules differ
+//! slightly at triangle edges, and blending may be done with different
+//! internal precision. Those small differences can compound over frames, as
+//! the random values and positions of the particles feed into the next
+//! frame. The [`ImageTolerance`] accounts for this, and should be tuned per
+//! test:
+//! - keep the frame count low, to limit the accumulation of differences;
+//! - prefer larger, opaque particles over many tiny ones, whose coverage is
+//!   very sensitive to rasterization rules;
+//! - generate the reference images on the same kind of machine as the one
+//!   running the tests (typically the CI), or increase the tolerance.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # use bevy::prelude::*;
+//! # use bevy_hanabi::{prelude::*, testing::GoldenImageTest};
+//! # fn make_asset() -> EffectAsset { unimplemented!() }
+//! GoldenImageTest::new(256, 256)
+//!     .with_frame_count(30)
+//!     .check("tests/golden/my_effect.png", |app| {
+//!         let asset = make_asset();
+//!         let handle = app.world.resource_mut::<Assets<EffectAsset>>().add(asset);
+//!         app.world.spawn(ParticleEffectBundle::new(handle));
+//!     })
+//!     .unwrap();
+//! ```
+
+use std::{
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use bevy::{
+    app::PluginsState,
+    asset::AssetPlugin,
+    core::{FrameCountPlugin, TaskPoolPlugin, TypeRegistrationPlugin},
+    core_pipeline::{tonemapping::Tonemapping, CorePipelinePlugin},
+    hierarchy::HierarchyPlugin,
+    prelude::*,
+    render::{
+        camera::RenderTarget,
+        render_asset::RenderAssets,
+        render_resource::{
+            BufferDescriptor, BufferUsages, CommandEncoderDescriptor, Extent3d, ImageCopyBuffer,
+            ImageDataLayout, Maintain, MapMode, TextureDescriptor, TextureDimension, TextureFormat,
+            TextureUsages,
+        },
+        renderer::{RenderDevice, RenderQueue},
+        texture::ImagePlugin,
+        Render, RenderApp, RenderPlugin, RenderSet,
+    },
+    time::{TimePlugin, TimeUpdateStrategy},
+    transform::TransformPlugin,
+    utils::thiserror::Error,
+    window::{ExitCondition, WindowPlugin},
+};
+use rand::SeedableRng;
+use rand_pcg::Pcg32;
+
+use crate::{HanabiPlugin, Random};
+
+/// Environment variable forcing the reference images to be rewritten.
+pub const UPDATE_GOLDEN_ENV: &str = "HANABI_UPDATE_GOLDEN";
+
+/// Required alignment of the rows of a texture copied into a buffer
+/// (`wgpu::COPY_BYTES_PER_ROW_ALIGNMENT`).
+const COPY_BYTES_PER_ROW_ALIGNMENT: u32 = 256;
+
+/// Error produced by a [`GoldenImageTest`].
+#[derive(Debug, Error)]
+pub enum GoldenImageError {
+    /// Failed to read or write an image file.
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    /// Failed to decode or encode an image file.
+    #[error("Image error: {0}")]
+    Image(#[from] image::ImageError),
+    /// The rendered image couldn't be read back from GPU.
+    #[error("Failed to capture the rendered image.")]
+    CaptureFailed,
+    /// The rendered image and the reference image have different sizes.
+    #[error("Image size {actual:?} doesn't match reference size {expected:?}.")]
+    SizeMismatch {
+        /// Size of the rendered image.
+        actual: UVec2,
+        /// Size of the reference image.
+        expected: UVec2,
+    },
+    /// The rendered image differs from the reference image by more than the
+    /// tolerance.
+    #[error("Image differs from reference {path:?}: {diff:?}.")]
+    Mismatch {
+        /// Path to the reference image.
+        path: PathBuf,
+        /// Differences between the rendered and reference images.
+        diff: ImageDiff,
+    },
+}
+
+/// Tolerance when comparing a rendered image against its reference.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ImageTolerance {
+    /// Maximum absolute difference of any color channel for two pixels to be
+    /// considered equal.
+    pub channel: u8,
+    /// Maximum fraction of pixels, in \[0:1\], allowed to differ by more than
+    /// [`channel`].
+    ///
+    /// [`channel`]: ImageTolerance::channel
+    pub pixel_ratio: f32,
+}
+
+impl Default for ImageTolerance {
+    fn default() -> Self {
+        Self {
+            channel: 2,
+            pixel_ratio: 0.001,
+        }
+    }
+}
+
+/// Differences between two images.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ImageDiff {
+    /// Number of pixels differing by more than the channel tolerance.
+    pub differing_pixels: usize,
+    /// Total number of pixels compared.
+    pub total_pixels: usize,
+    /// Largest absolute difference of any color channel.
+    pub max_channel_diff: u8,
+}
+
+impl ImageDiff {
+    /// Check whether the differences are within the given tolerance.
+    pub fn is_within(&self, tolerance: &ImageTolerance) -> bool {
+        self.differing_pixels as f32 <= self.total_pixels as f32 * tolerance.pixel_ratio
+    }
+}
+
+/// An RGBA8 image rendered by a [`GoldenImageTest`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenderedImage {
+    /// Size of the image, in pixels.
+    pub size: UVec2,
+    /// Pixel data, as tightly packed rows of RGBA8 sRGB pixels.
+    pub data: Vec<u8>,
+}
+
+impl RenderedImage {
+    /// Load an image from a PNG file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, GoldenImageError> {
+        let image = image::open(path)?.into_rgba8();
+        Ok(Self {
+            size: UVec2::new(image.width(), image.height()),
+            data: image.into_raw(),
+        })
+    }
+
+    /// Save the image to a PNG file.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), GoldenImageError> {
+        image::save_buffer(
+            path,
+            &self.data,
+            self.size.x,
+            self.size.y,
+            image::ColorType::Rgba8,
+        )?;
+        Ok(())
+    }
+
+    /// Compare this image against a reference image.
+    ///
+    /// Two pixels are considered different if any of their color channels
+    /// differ by more than `channel_tolerance`.
+    pub fn compare(
+        &self,
+        reference: &RenderedImage,
+        channel_tolerance: u8,
+    ) -> Result<ImageDiff, GoldenImageError> {
+        if self.size != reference.size {
+            return Err(GoldenImageError::SizeMismatch {
+                actual: self.size,
+                expected: reference.size,
+            });
+        }
+        let mut diff = ImageDiff {
+            total_pixels: (self.size.x * self.size.y) as usize,
+            ..default()
+        };
+        for (a, b) in self.data.chunks(4).zip(reference.data.chunks(4)) {
+            let pixel_diff = a
+                .iter()
+                .zip(b)
+                .map(|(a, b)| a.abs_diff(*b))
+                .max()
+                .unwrap_or(0);
+            diff.max_channel_diff = diff.max_channel_diff.max(pixel_diff);
+            if pixel_diff > channel_tolerance {
+                diff.differing_pixels += 1;
+            }
+        }
+        Ok(diff)
+    }
+}
+
+/// Harness rendering effects deterministically to an offscreen image.
+///
+/// See the [module documentation](crate::testing) for details.
+#[derive(Debug, Clone)]
+pub struct GoldenImageTest {
+    size: UVec2,
+    frame_count: u32,
+    timestep: Duration,
+    seed: u64,
+    tolerance: ImageTolerance,
+    camera_transform: Transform,
+    clear_color: Color,
+}
+
+impl GoldenImageTest {
+    /// Create a new test rendering an image of the given size.
+    ///
+    /// By default, the test renders 10 frames at 60 FPS, with a camera at
+    /// `(0, 0, 10)` looking at the origin, over a black background.
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            size: UVec2::new(width, height),
+            frame_count: 10,
+            timestep: Duration::from_secs_f64(1. / 60.),
+            seed: 0,
+            tolerance: ImageTolerance::default(),
+            camera_transform: Transform::from_xyz(0., 0., 10.).looking_at(Vec3::ZERO, Vec3::Y),
+            clear_color: Color::BLACK,
+        }
+    }
+
+    /// Set the number of frames simulated before capturing the image.
+    pub fn with_frame_count(mut self, frame_count: u32) -> Self {
+        assert!(frame_count > 0);
+        self.frame_count = frame_count;
+        self
+    }
+
+    /// Set the fixed timestep of each frame.
+    pub fn with_timestep(mut self, timestep: Duration) -> Self {
+        self.timestep = timestep;
+        self
+    }
+
+    /// Set the seed of the random number generators.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Set the tolerance when comparing against the reference image.
+    pub fn with_tolerance(mut self, tolerance: ImageTolerance) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Set the transform of the camera rendering the image.
+    pub fn with_camera_transform(mut self, transform: Transform) -> Self {
+        self.camera_transform = transform;
+        self
+    }
+
+    /// Set the background color of the image.
+    pub fn with_clear_color(mut self, color: Color) -> Self {
+        self.clear_color = color;
+        self
+    }
+
+    /// Render the image.
+    ///
+    /// The `setup` closure is invoked once the app is built, to add the
+    /// effect assets and spawn the effect instances to render. The camera is
+    /// spawned by the harness.
+    pub fn render(&self, setup: impl FnOnce(&mut App)) -> Result<RenderedImage, GoldenImageError> {
+        let mut app = App::new();
+        app.add_plugins((
+            TaskPoolPlugin::default(),
+            TypeRegistrationPlugin,
+            FrameCountPlugin,
+            TimePlugin,
+            TransformPlugin,
+            HierarchyPlugin,
+            WindowPlugin {
+                primary_window: None,
+                exit_condition: ExitCondition::DontExit,
+                close_when_requested: false,
+            },
+            AssetPlugin::default(),
+            RenderPlugin {
+                synchronous_pipeline_compilation: true,
+                ..default()
+            },
+            ImagePlugin::default(),
+            CorePipelinePlugin,
+            HanabiPlugin,
+        ))
+        .insert_resource(TimeUpdateStrategy::ManualDuration(self.timestep))
+        .insert_resource(ClearColor(self.clear_color))
+        .insert_resource(Random(Pcg32::seed_from_u64(self.seed)));
+
+        while app.plugins_state() == PluginsState::Adding {
+            bevy::tasks::tick_global_task_pools_on_main_thread();
+        }
+        app.finish();
+        app.cleanup();
+
+        // Create the render target and its camera
+        let size = Extent3d {
+            width: self.size.x,
+            height: self.size.y,
+            depth_or_array_layers: 1,
+        };
+        let mut image = Image {
+            texture_descriptor: TextureDescriptor {
+                label: Some("hanabi:golden_image"),
+                size,
+                dimension: TextureDimension::D2,
+                format: TextureFormat::Rgba8UnormSrgb,
+                mip_level_count: 1,
+                sample_count: 1,
+                usage: TextureUsages::TEXTURE_BINDING
+                    | TextureUsages::COPY_SRC
+                    | TextureUsages::RENDER_ATTACHMENT,
+                view_formats: &[],
+            },
+            ..default()
+        };
+        image.resize(size);
+        let target = app.world.resource_mut::<Assets<Image>>().add(image);
+        app.world.spawn(Camera3dBundle {
+            camera: Camera {
+                target: RenderTarget::Image(target.clone()),
+                ..default()
+            },
+            tonemapping: Tonemapping::None,
+            transform: self.camera_transform,
+            ..default()
+        });
+
+        let capture = GoldenCapture {
+            target,
+            requested: Arc::new(AtomicBool::new(false)),
+            data: Arc::new(Mutex::new(None)),
+        };
+        let render_app = app.sub_app_mut(RenderApp);
+        render_app
+            .insert_resource(Random(Pcg32::seed_from_u64(self.seed)))
+            .insert_resource(capture.clone())
+            .add_systems(Render, capture_golden_image.in_set(RenderSet::Cleanup));
+
+        setup(&mut app);
+
+        for frame in 0..self.frame_count {
+            if frame + 1 == self.frame_count {
+                capture.requested.store(true, Ordering::Release);
+            }
+            app.update();
+        }
+
+        let data = capture
+            .data
+            .lock()
+            .unwrap()
+            .take()
+            .ok_or(GoldenImageError::CaptureFailed)?;
+        Ok(RenderedImage {
+            size: self.size,
+            data,
+        })
+    }
+
+    /// Render the image and compare it against a reference image.
+    ///
+    /// If the reference image doesn't exist, or if the `HANABI_UPDATE_GOLDEN`
+    /// environment variable is set, the rendered image is saved as the new
+    /// reference instead. On mismatch, the rendered image is saved next to
+    /// the reference with an `.actual.png` extension.
+    pub fn check(
+        &self,
+        reference: impl AsRef<Path>,
+        setup: impl FnOnce(&mut App),
+    ) -> Result<(), GoldenImageError> {
+        let reference = reference.as_ref();
+        let rendered = self.render(setup)?;
+
+        if !reference.exists() || std::env::var_os(UPDATE_GOLDEN_ENV).is_some() {
+            if let Some(parent) = reference.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            warn!("Writing golden image {:?}", reference);
+            return rendered.save(reference);
+        }
+
+        let expected = RenderedImage::load(reference)?;
+        let diff = rendered.compare(&expected, self.tolerance.channel)?;
+        if diff.is_within(&self.tolerance) {
+            Ok(())
+        } else {
+            rendered.save(reference.with_extension("actual.png"))?;
+            Err(GoldenImageError::Mismatch {
+                path: reference.to_path_buf(),
+                diff,
+            })
+        }
+    }
+}
+
+/// Render world resource to read back the golden image.
+#[derive(Clone, Resource)]
+struct GoldenCapture {
+    /// Render target to read back.
+    target: Handle<Image>,
+    /// Read back the render target at the end of the next frame.
+    requested: Arc<AtomicBool>,
+    /// Tightly packed RGBA8 data read back.
+    data: Arc<Mutex<Option<Vec<u8>>>>,
+}
+
+/// Copy the render target into a buffer and read it back, blocking until the
+/// GPU completed the frame.
+fn capture_golden_image(
+    capture: Res<GoldenCapture>,
+    images: Res<RenderAssets<Image>>,
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+) {
+    if !capture.requested.swap(false, Ordering::AcqRel) {
+        return;
+    }
+    let Some(gpu_image) = images.get(&capture.target) else {
+        return;
+    };
+
+    let width = gpu_image.size.x as u32;
+    let height = gpu_image.size.y as u32;
+    let row_size = width * 4;
+    let padded_row_size =
+        crate::next_multiple_of(row_size as usize, COPY_BYTES_PER_ROW_ALIGNMENT as usize) as u32;
+    let buffer = render_device.create_buffer(&BufferDescriptor {
+        label: Some("hanabi:buffer:golden_capture"),
+        size: padded_row_size as u64 * height as u64,
+        usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+
+    let mut encoder = render_device.create_command_encoder(&CommandEncoderDescriptor {
+        label: Some("hanabi:golden_capture"),
+    });
+    encoder.copy_texture_to_buffer(
+        gpu_image.texture.as_image_copy(),
+        ImageCopyBuffer {
+            buffer: &buffer,
+            layout: ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(padded_row_size),
+                rows_per_image: None,
+            },
+        },
+        Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+    );
+    render_queue.submit([encoder.finish()]);
+
+    let slice = buffer.slice(..);
+    let status = Arc::new(Mutex::new(None));
+    let callback_status = status.clone();
+    slice.map_async(MapMode::Read, move |result| {
+        *callback_status.lock().unwrap() = Some(result);
+    });
+    render_device.poll(Maintain::Wait);
+    if !matches!(status.lock().unwrap().take(), Some(Ok(()))) {
+        warn!("Failed to read back golden image.");
+        return;
+    }
+
+    let data = {
+        let view = slice.get_mapped_range();
+        view.chunks(padded_row_size as usize)
+            .flat_map(|row| &row[..row_size as usize])
+            .copied()
+            .collect()
+    };
+    buffer.unmap();
+    *capture.data.lock().unwrap() = Some(data);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "gpu_tests")]
+    use crate::prelude::*;
+
+    fn solid(size: UVec2, color: [u8; 4]) -> RenderedImage {
+        RenderedImage {
+            size,
+            data: color.repeat((size.x * size.y) as usize),
+        }
+    }
+
+    #[test]
+    fn compare_images() {
+        let size = UVec2::new(10, 10);
+        let a = solid(size, [10, 20, 30, 255]);
+        let mut b = a.clone();
+        assert_eq!(
+            a.compare(&b, 0).unwrap(),
+            ImageDiff {
+                differing_pixels: 0,
+                total_pixels: 100,
+                max_channel_diff: 0,
+            }
+        );
+
+        // Small difference on one pixel, large on another
+        b.data[1] += 2;
+        b.data[4 * 7 + 2] -= 30;
+        let diff = a.compare(&b, 2).unwrap();
+        assert_eq!(diff.differing_pixels, 1);
+        assert_eq!(diff.max_channel_diff, 30);
+        assert!(diff.is_within(&ImageTolerance {
+            channel: 2,
+            pixel_ratio: 0.01,
+        }));
+        assert!(!diff.is_within(&ImageTolerance {
+            channel: 2,
+            pixel_ratio: 0.,
+        }));
+
+        let c = solid(UVec2::new(5, 20), [10, 20, 30, 255]);
+        assert!(matches!(
+            a.compare(&c, 0),
+            Err(GoldenImageError::SizeMismatch { .. })
+        ));
+    }
+
+    #[cfg(feature = "gpu_tests")]
+    #[test]
+    fn render_deterministic() {
+        let setup = |app: &mut App| {
+            let mut module = Module::default();
+            let center = module.lit(Vec3::ZERO);
+            let radius = module.lit(2.);
+            let lifetime = module.lit(10.);
+            let asset = EffectAsset::new(vec![256], Spawner::rate(100.0.into()), module)
+                .init(SetPositionSphereModifier {
+                    center,
+                    radius,
+                    dimension: ShapeDimension::Volume,
+                    thickness: None,
+                })
+                .init(SetAttributeModifier::new(Attribute::LIFETIME, lifetime))
+                .render(SetSizeModifier {
+                    size: Vec2::splat(0.5).into(),
+                });
+            let handle = app.world.resource_mut::<Assets<EffectAsset>>().add(asset);
+            app.world.spawn(ParticleEffectBundle::new(handle));
+        };
+
+        let test = GoldenImageTest::new(64, 64).with_seed(42);
+        let a = test.render(setup).unwrap();
+        let b = test.render(setup).unwrap();
+        assert!(a
+            .compare(&b, 0)
+            .unwrap()
+            .is_within(&ImageTolerance::default()));
+
+        // Some particles were drawn over the black background
+        assert!(a.data.chunks(4).any(|p| p[0] > 0));
+    }
+
+    #[test]
+    fn save_load() {
+        let image = solid(UVec2::new(4, 3), [255, 128, 0, 255]);
+        let path = std::env::temp_dir().join("hanabi_golden_save_load.png");
+        image.save(&path).unwrap();
+        let loaded = RenderedImage::load(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(image, loaded);
+    }
+}
@@ -11,6 +11,13 @@ use bevy::prelude::*;
 /// only the speed of the effects, use
 /// [`Time<EffectSimulation>`](EffectSimulation).
 ///
+/// This clock drives all effects at once, and is the intended way to implement
+/// a game-wide pause or slow-motion for particle effects. While paused, the
+/// delta time of all effects is zero: spawners don't emit new particles and
+/// particles don't age nor move. However the GPU buffers of all effects are
+/// retained, and the frozen particles keep being rendered, so effects resume
+/// exactly where they left off once unpaused.
+///
 /// # Example
 ///
 /// ```
@@ -249,5 +256,25 @@ mod tests {
         assert!(f32::abs(effect_simulation.delta_seconds() - 6.0 * real.delta_seconds()) < EPSILON);
         assert!(f32::abs(virt.effective_speed() - 2.0) < EPSILON);
         assert!(f32::abs(effect_simulation.effective_speed() - 6.0) < EPSILON);
+
+        // Pause the effects only; the virtual clock keeps running
+        app.world.resource_mut::<Time<EffectSimulation>>().pause();
+        sleep(Duration::from_millis(1));
+        app.update();
+        let virt = app.world.resource::<Time<Virtual>>();
+        let effect_simulation = app.world.resource::<Time<EffectSimulation>>();
+        assert!(virt.delta_seconds() > 0.0);
+        assert_eq!(effect_simulation.delta_seconds(), 0.0);
+        assert!(effect_simulation.is_paused());
+        assert!(effect_simulation.was_paused());
+
+        // Unpause; the relative speed is preserved
+        app.world.resource_mut::<Time<EffectSimulation>>().unpause();
+        sleep(Duration::from_millis(1));
+        app.update();
+        let real = app.world.resource::<Time<Real>>();
+        let effect_simulation = app.world.resource::<Time<EffectSimulation>>();
+        assert!(!effect_simulation.is_paused());
+        assert!(f32::abs(effect_simulation.delta_seconds() - 6.0 * real.delta_seconds()) < EPSILON);
     }
 }